@@ -83,6 +83,12 @@ pub struct Parameters {
     #[serde(default = "Parameters::default_commit_sync_batches_ahead")]
     pub commit_sync_batches_ahead: usize,
 
+    /// The maximum number of consecutive block proposals that can leave out the pending
+    /// transactions of a priority in favour of higher priorities. Past this, one batch of the
+    /// transactions of the priority is included first in the next proposal.
+    #[serde(default = "Parameters::default_max_starved_proposals")]
+    pub max_starved_proposals: u32,
+
     /// Anemo network settings.
     #[serde(default = "AnemoParameters::default")]
     pub anemo: AnemoParameters,
@@ -186,6 +192,10 @@ impl Parameters {
         // while keeping the total number of inflight fetches and unprocessed fetched commits limited.
         32
     }
+
+    pub(crate) fn default_max_starved_proposals() -> u32 {
+        10
+    }
 }
 
 impl Default for Parameters {
@@ -206,6 +216,7 @@ impl Default for Parameters {
             commit_sync_parallel_fetches: Parameters::default_commit_sync_parallel_fetches(),
             commit_sync_batch_size: Parameters::default_commit_sync_batch_size(),
             commit_sync_batches_ahead: Parameters::default_commit_sync_batches_ahead(),
+            max_starved_proposals: Parameters::default_max_starved_proposals(),
             anemo: AnemoParameters::default(),
             tonic: TonicParameters::default(),
         }
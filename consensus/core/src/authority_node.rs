@@ -204,6 +204,29 @@ where
             initialise_metrics(registry),
             Arc::new(Clock::new()),
         ));
+        let network_manager = N::new(context.clone(), network_keypair);
+
+        Self::start_with_network_manager(
+            context,
+            network_manager,
+            protocol_keypair,
+            transaction_verifier,
+            commit_consumer,
+            boot_counter,
+        )
+        .await
+    }
+
+    /// Starts the authority with an already created context and network manager, e.g. to share
+    /// a clock and a simulated network between authorities in tests.
+    pub(crate) async fn start_with_network_manager(
+        context: Arc<Context>,
+        mut network_manager: N,
+        protocol_keypair: ProtocolKeyPair,
+        transaction_verifier: Arc<dyn TransactionVerifier>,
+        commit_consumer: CommitConsumer,
+        boot_counter: u64,
+    ) -> Self {
         let start_time = Instant::now();
 
         let (tx_client, tx_receiver) = TransactionClient::new(context.clone());
@@ -211,7 +234,6 @@ where
 
         let (core_signals, signals_receivers) = CoreSignals::new(context.clone());
 
-        let mut network_manager = N::new(context.clone(), network_keypair);
         let network_client = network_manager.client();
 
         // REQUIRED: Broadcaster must be created before Core, to start listening on the
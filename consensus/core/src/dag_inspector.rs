@@ -0,0 +1,894 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::{self, Display, Write as _},
+    ops::RangeInclusive,
+    path::Path,
+    sync::Arc,
+};
+
+use consensus_config::{AuthorityIndex, Committee, Parameters, Stake};
+use itertools::Itertools as _;
+use mys_protocol_config::ProtocolConfig;
+use prometheus::Registry;
+use serde::Serialize;
+
+use crate::{
+    block::{BlockAPI as _, BlockDigest, BlockRef, BlockTimestampMs, Round, Slot, VerifiedBlock},
+    commit::{
+        CommitAPI as _, CommitIndex, CommitInfo, CommitRef, TrustedCommit, DEFAULT_WAVE_LENGTH,
+    },
+    context::{Clock, Context},
+    error::ConsensusResult,
+    leader_schedule::{LeaderSchedule, LeaderSwapTable},
+    leader_scoring::ReputationScores,
+    metrics::initialise_metrics,
+    storage::{rocksdb_store::RocksDBStore, Store},
+};
+
+/// Number of rounds past the inspected ones that are loaded as well, to find the votes,
+/// certificates, anchors and references that decided the fate of the inspected blocks.
+const LOOKAHEAD_ROUNDS: Round = 50;
+
+/// Number of commits read at once when looking for the commits of the inspected rounds.
+const COMMIT_SCAN_BATCH: CommitIndex = 1000;
+
+/// Read-only view of the blocks, commits and reputation scores persisted by an authority, to
+/// investigate the DAG offline. The commit rules are evaluated against every block found in the
+/// store, which can be more than the authority had when it decided a leader.
+pub struct DagInspector {
+    context: Arc<Context>,
+    store: Arc<dyn Store>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DagExportFormat {
+    /// Graphviz graph, with one rank per round and edges from blocks to their ancestors.
+    Dot,
+    Json,
+}
+
+/// How the commit rules decided a leader slot.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LeaderOutcome {
+    DirectCommit {
+        commit_index: CommitIndex,
+    },
+    IndirectCommit {
+        commit_index: CommitIndex,
+        anchor: Option<BlockRef>,
+    },
+    DirectSkip,
+    IndirectSkip {
+        anchor: Option<BlockRef>,
+    },
+    Undecided,
+}
+
+/// Explains the outcome of a leader slot, from the stake of the blocks that voted for the leader,
+/// blamed it, or certified it.
+#[derive(Clone, Debug)]
+pub struct LeaderReport {
+    pub round: Round,
+    pub leader: AuthorityIndex,
+    pub hostname: String,
+    /// Blocks proposed by the leader in its round, more than one if it equivocated.
+    pub blocks: Vec<BlockRef>,
+    /// Stake of the blocks of the next round that vote for the leader block.
+    pub vote_stake: Stake,
+    /// Stake of the blocks of the next round that do not reference any block of the leader.
+    pub blame_stake: Stake,
+    /// Stake of the blocks of the decision round that certify the leader block.
+    pub certificate_stake: Stake,
+    pub quorum_threshold: Stake,
+    pub outcome: LeaderOutcome,
+}
+
+/// Block propagation and leader statistics of an authority over the inspected rounds.
+#[derive(Clone, Debug)]
+pub struct AuthorityReport {
+    pub authority: AuthorityIndex,
+    pub hostname: String,
+    pub stake: Stake,
+    pub blocks: usize,
+    /// Inspected rounds without any block from the authority.
+    pub missing_rounds: usize,
+    /// Time between the timestamp of a block and that of the first block of another authority
+    /// referencing it.
+    pub median_propagation_delay_ms: Option<u64>,
+    pub p90_propagation_delay_ms: Option<u64>,
+    /// Average number of rounds until a block is first referenced by another authority.
+    pub average_propagation_rounds: Option<f64>,
+    pub leaders_committed: usize,
+    pub leaders_skipped: usize,
+    /// Score of the authority in the leader schedule in effect at the last inspected round.
+    pub reputation_score: Option<u64>,
+    /// Whether that leader schedule hands the leader slots of the authority to others.
+    pub swapped_out: bool,
+}
+
+/// Reputation scores persisted when the leader schedule changed.
+#[derive(Clone, Debug)]
+pub struct ReputationScoresReport {
+    /// Commits the scores were calculated from.
+    pub commit_range: RangeInclusive<CommitIndex>,
+    /// Score per authority, indexed by `AuthorityIndex`.
+    pub scores: Vec<u64>,
+}
+
+impl DagInspector {
+    /// Opens the consensus store at `path`. The store is locked by a running authority, and
+    /// `committee` and `protocol_config` must be those of the epoch the store belongs to.
+    pub fn open(path: &Path, committee: Committee, protocol_config: ProtocolConfig) -> Self {
+        let context = Context::new(
+            AuthorityIndex::ZERO,
+            committee,
+            Parameters {
+                db_path: path.to_path_buf(),
+                ..Default::default()
+            },
+            protocol_config,
+            initialise_metrics(Registry::new()),
+            Arc::new(Clock::new()),
+        );
+        let store = RocksDBStore::new(
+            path.to_str()
+                .expect("Consensus store path should be valid UTF-8"),
+        );
+        Self::new(Arc::new(context), Arc::new(store))
+    }
+
+    pub(crate) fn new(context: Arc<Context>, store: Arc<dyn Store>) -> Self {
+        Self { context, store }
+    }
+
+    /// Returns the leader block of the last commit, if any.
+    pub fn last_committed_leader(&self) -> Option<VerifiedBlock> {
+        let commit = self
+            .store
+            .read_last_commit()
+            .unwrap_or_else(|e| panic!("Failed to read last commit: {e}"))?;
+        self.store
+            .read_blocks(&[commit.leader()])
+            .unwrap_or_else(|e| panic!("Failed to read leader block: {e}"))
+            .pop()
+            .flatten()
+    }
+
+    /// Exports the blocks of `rounds`, and the commits of the leaders of those rounds.
+    pub fn export(&self, rounds: RangeInclusive<Round>, format: DagExportFormat) -> String {
+        let snapshot = self.load(rounds);
+        match format {
+            DagExportFormat::Dot => snapshot
+                .to_dot()
+                .expect("Writing to a String should not fail"),
+            DagExportFormat::Json => snapshot.to_json(),
+        }
+    }
+
+    /// Explains how each leader slot of `rounds` was decided.
+    pub fn explain_leaders(&self, rounds: RangeInclusive<Round>) -> Vec<LeaderReport> {
+        self.load(rounds).leader_reports()
+    }
+
+    /// Reports block propagation and leader statistics per authority over `rounds`.
+    pub fn authority_reports(&self, rounds: RangeInclusive<Round>) -> Vec<AuthorityReport> {
+        self.load(rounds).authority_reports()
+    }
+
+    /// Returns every set of reputation scores persisted in the store, oldest first.
+    pub fn reputation_scores(&self) -> Vec<ReputationScoresReport> {
+        self.read_commit_info()
+            .unwrap_or_else(|e| panic!("Failed to read commit info: {e}"))
+            .into_iter()
+            .map(|(_, commit_info)| ReputationScoresReport {
+                commit_range: commit_info.reputation_scores.commit_range.start()
+                    ..=commit_info.reputation_scores.commit_range.end(),
+                scores: commit_info.reputation_scores.scores_per_authority,
+            })
+            .collect()
+    }
+
+    fn read_commit_info(&self) -> ConsensusResult<Vec<(CommitRef, CommitInfo)>> {
+        match self.store.read_last_commit()? {
+            Some(last_commit) => self
+                .store
+                .scan_commit_info((1..=last_commit.index()).into()),
+            None => Ok(vec![]),
+        }
+    }
+
+    fn load(&self, rounds: RangeInclusive<Round>) -> DagSnapshot {
+        self.load_snapshot(rounds)
+            .unwrap_or_else(|e| panic!("Failed to read consensus store: {e}"))
+    }
+
+    /// Loads the blocks of `rounds` and of the rounds following them, with the commits of their
+    /// leaders and the leader schedules in effect.
+    fn load_snapshot(&self, rounds: RangeInclusive<Round>) -> ConsensusResult<DagSnapshot> {
+        // Genesis blocks are not persisted.
+        let rounds = (*rounds.start()).max(1)..=*rounds.end();
+        let load_end = rounds.end().saturating_add(LOOKAHEAD_ROUNDS);
+
+        let mut blocks = BTreeMap::new();
+        for (authority, _) in self.context.committee.authorities() {
+            for block in
+                self.store
+                    .scan_blocks_by_author_in_range(authority, *rounds.start(), load_end)?
+            {
+                blocks.insert(block.reference(), block);
+            }
+        }
+
+        // Leaders are committed in increasing round order, so commits are scanned backwards
+        // from the last one until reaching leaders before the inspected rounds.
+        let last_commit = self.store.read_last_commit()?;
+        let last_committed_round = last_commit.as_ref().map_or(0, |c| c.leader().round);
+        let last_commit_index = last_commit.map_or(0, |c| c.index());
+        let mut commits = BTreeMap::new();
+        let mut high = last_commit_index;
+        while high > 0 {
+            let low = high.saturating_sub(COMMIT_SCAN_BATCH - 1).max(1);
+            let batch = self.store.scan_commits((low..=high).into())?;
+            let reached_start = batch
+                .first()
+                .map_or(true, |commit| commit.leader().round < *rounds.start());
+            for commit in batch {
+                let round = commit.leader().round;
+                if round >= *rounds.start() && round <= load_end {
+                    commits.insert(commit.index(), commit);
+                }
+            }
+            if reached_start {
+                break;
+            }
+            high = low - 1;
+        }
+        let committed = commits
+            .values()
+            .flat_map(|commit| {
+                commit
+                    .blocks()
+                    .iter()
+                    .map(|block_ref| (*block_ref, commit.index()))
+            })
+            .collect();
+
+        // A new leader schedule applies to the leaders after the last leader committed under
+        // the previous one.
+        let mut schedules = vec![(
+            0,
+            LeaderSchedule::new(self.context.clone(), LeaderSwapTable::default()),
+            ReputationScores::default(),
+        )];
+        for (commit_ref, commit_info) in self.read_commit_info()? {
+            let Some(commit) = self
+                .store
+                .scan_commits((commit_ref.index..=commit_ref.index).into())?
+                .pop()
+            else {
+                continue;
+            };
+            let swap_table = LeaderSwapTable::new(
+                self.context.clone(),
+                commit_ref.index,
+                commit_info.reputation_scores.clone(),
+            );
+            schedules.push((
+                commit.leader().round,
+                LeaderSchedule::new(self.context.clone(), swap_table),
+                commit_info.reputation_scores,
+            ));
+        }
+
+        Ok(DagSnapshot {
+            context: self.context.clone(),
+            rounds,
+            blocks,
+            commits,
+            committed,
+            last_committed_round,
+            schedules,
+        })
+    }
+}
+
+struct DagSnapshot {
+    context: Arc<Context>,
+    /// Inspected rounds. Blocks are loaded for the following rounds as well.
+    rounds: RangeInclusive<Round>,
+    blocks: BTreeMap<BlockRef, VerifiedBlock>,
+    /// Commits of the leaders of the loaded rounds, by index.
+    commits: BTreeMap<CommitIndex, TrustedCommit>,
+    /// Index of the commit of each committed block.
+    committed: HashMap<BlockRef, CommitIndex>,
+    /// Round of the leader of the last commit in the store.
+    last_committed_round: Round,
+    /// Leader schedules and the scores they were built from, each applying to the leaders after
+    /// a round.
+    schedules: Vec<(Round, LeaderSchedule, ReputationScores)>,
+}
+
+impl DagSnapshot {
+    fn schedule(&self, round: Round) -> &(Round, LeaderSchedule, ReputationScores) {
+        self.schedules
+            .iter()
+            .rev()
+            .find(|(after, _, _)| *after < round)
+            .unwrap_or(&self.schedules[0])
+    }
+
+    fn leaders(&self, round: Round) -> Vec<Slot> {
+        let number_of_leaders = self
+            .context
+            .protocol_config
+            .mysticeti_num_leaders_per_round()
+            .unwrap_or(1);
+        let (_, schedule, _) = self.schedule(round);
+        (0..number_of_leaders)
+            .map(|offset| Slot::new(round, schedule.elect_leader(round, offset as u32)))
+            .collect()
+    }
+
+    fn blocks_at_round(&self, round: Round) -> Vec<&VerifiedBlock> {
+        self.blocks
+            .range(
+                BlockRef::new(round, AuthorityIndex::ZERO, BlockDigest::MIN)
+                    ..BlockRef::new(round + 1, AuthorityIndex::ZERO, BlockDigest::MIN),
+            )
+            .map(|(_, block)| block)
+            .collect()
+    }
+
+    fn blocks_at_slot(&self, slot: Slot) -> Vec<&VerifiedBlock> {
+        self.blocks
+            .range(
+                BlockRef::new(slot.round, slot.authority, BlockDigest::MIN)
+                    ..=BlockRef::new(slot.round, slot.authority, BlockDigest::MAX),
+            )
+            .map(|(_, block)| block)
+            .collect()
+    }
+
+    /// Total stake of the authors of `blocks`, counting equivocating authors once.
+    fn stake<'a>(&self, blocks: impl Iterator<Item = &'a VerifiedBlock>) -> Stake {
+        let authors: BTreeSet<_> = blocks.map(|block| block.author()).collect();
+        authors
+            .into_iter()
+            .map(|author| self.context.committee.stake(author))
+            .sum()
+    }
+
+    /// Same rule as `BaseCommitter::find_supported_block`: the block at `slot` supported by
+    /// `from`, through the first of its ancestors that links to the slot.
+    fn supported_block(&self, slot: Slot, from: &VerifiedBlock) -> Option<BlockRef> {
+        if from.round() < slot.round {
+            return None;
+        }
+        for ancestor in from.ancestors() {
+            if Slot::from(*ancestor) == slot {
+                return Some(*ancestor);
+            }
+            if ancestor.round <= slot.round {
+                continue;
+            }
+            if let Some(support) = self
+                .blocks
+                .get(ancestor)
+                .and_then(|ancestor| self.supported_block(slot, ancestor))
+            {
+                return Some(support);
+            }
+        }
+        None
+    }
+
+    fn is_certificate(&self, block: &VerifiedBlock, leader: BlockRef) -> bool {
+        let slot = Slot::from(leader);
+        let votes = block
+            .ancestors()
+            .iter()
+            .filter_map(|ancestor| self.blocks.get(ancestor))
+            .filter(|ancestor| self.supported_block(slot, ancestor) == Some(leader));
+        self.context.committee.reached_quorum(self.stake(votes))
+    }
+
+    fn leader_reports(&self) -> Vec<LeaderReport> {
+        self.rounds
+            .clone()
+            .flat_map(|round| self.leaders(round))
+            .map(|slot| self.leader_report(slot))
+            .collect()
+    }
+
+    fn leader_report(&self, slot: Slot) -> LeaderReport {
+        let committee = &self.context.committee;
+        let leader_blocks = self.blocks_at_slot(slot);
+        let voting_blocks = self.blocks_at_round(slot.round + 1);
+        let decision_blocks = self.blocks_at_round(slot.round + DEFAULT_WAVE_LENGTH - 1);
+
+        let blame_stake = self.stake(voting_blocks.iter().copied().filter(|block| {
+            block
+                .ancestors()
+                .iter()
+                .all(|ancestor| ancestor.author != slot.authority)
+        }));
+        let support: Vec<(BlockRef, Stake, Stake)> = leader_blocks
+            .iter()
+            .map(|leader| {
+                let leader = leader.reference();
+                let vote_stake = self.stake(
+                    voting_blocks
+                        .iter()
+                        .copied()
+                        .filter(|block| self.supported_block(slot, block) == Some(leader)),
+                );
+                let certificate_stake = self.stake(
+                    decision_blocks
+                        .iter()
+                        .copied()
+                        .filter(|block| self.is_certificate(block, leader)),
+                );
+                (leader, vote_stake, certificate_stake)
+            })
+            .collect();
+
+        let commit = self
+            .commits
+            .values()
+            .find(|commit| Slot::from(commit.leader()) == slot);
+        // Reports the committed leader block, otherwise the best supported one.
+        let (vote_stake, certificate_stake) = support
+            .iter()
+            .find(|(leader, _, _)| commit.is_some_and(|commit| commit.leader() == *leader))
+            .or_else(|| {
+                support
+                    .iter()
+                    .max_by_key(|(_, votes, certs)| (*certs, *votes))
+            })
+            .map_or((0, 0), |(_, votes, certs)| (*votes, *certs));
+
+        // The anchor of an indirect decision is the first leader committed at or after the
+        // decision round of the target leader.
+        let anchor = || {
+            self.commits
+                .values()
+                .map(|commit| commit.leader())
+                .find(|leader| leader.round >= slot.round + DEFAULT_WAVE_LENGTH)
+        };
+        let outcome = match commit {
+            Some(commit) if committee.reached_quorum(certificate_stake) => {
+                LeaderOutcome::DirectCommit {
+                    commit_index: commit.index(),
+                }
+            }
+            Some(commit) => LeaderOutcome::IndirectCommit {
+                commit_index: commit.index(),
+                anchor: anchor(),
+            },
+            None if slot.round > self.last_committed_round => LeaderOutcome::Undecided,
+            None if committee.reached_quorum(blame_stake) => LeaderOutcome::DirectSkip,
+            None => LeaderOutcome::IndirectSkip { anchor: anchor() },
+        };
+
+        LeaderReport {
+            round: slot.round,
+            leader: slot.authority,
+            hostname: committee.authority(slot.authority).hostname.clone(),
+            blocks: leader_blocks
+                .iter()
+                .map(|block| block.reference())
+                .collect(),
+            vote_stake,
+            blame_stake,
+            certificate_stake,
+            quorum_threshold: committee.quorum_threshold(),
+            outcome,
+        }
+    }
+
+    fn authority_reports(&self) -> Vec<AuthorityReport> {
+        // When each block was first referenced by another authority, and at which round.
+        let mut first_references: HashMap<BlockRef, (BlockTimestampMs, Round)> = HashMap::new();
+        for block in self.blocks.values() {
+            for ancestor in block.ancestors() {
+                if ancestor.author == block.author() {
+                    continue;
+                }
+                let first = first_references
+                    .entry(*ancestor)
+                    .or_insert((block.timestamp_ms(), block.round()));
+                first.0 = first.0.min(block.timestamp_ms());
+                first.1 = first.1.min(block.round());
+            }
+        }
+
+        let leader_reports = self.leader_reports();
+        let (_, schedule, scores) = self.schedule(*self.rounds.end());
+        let swap_table = schedule.leader_swap_table.read();
+
+        self.context
+            .committee
+            .authorities()
+            .map(|(authority, info)| {
+                let blocks: Vec<_> = self
+                    .blocks
+                    .values()
+                    .filter(|block| {
+                        block.author() == authority && self.rounds.contains(&block.round())
+                    })
+                    .collect();
+                let rounds_with_blocks: BTreeSet<_> =
+                    blocks.iter().map(|block| block.round()).collect();
+                let mut delays = vec![];
+                let mut propagation_rounds = vec![];
+                for block in &blocks {
+                    if let Some((timestamp_ms, round)) = first_references.get(&block.reference()) {
+                        delays.push(timestamp_ms.saturating_sub(block.timestamp_ms()));
+                        propagation_rounds.push(round - block.round());
+                    }
+                }
+                delays.sort_unstable();
+                let leaders = leader_reports
+                    .iter()
+                    .filter(|report| report.leader == authority);
+
+                AuthorityReport {
+                    authority,
+                    hostname: info.hostname.clone(),
+                    stake: info.stake,
+                    blocks: blocks.len(),
+                    missing_rounds: self.rounds.clone().count() - rounds_with_blocks.len(),
+                    median_propagation_delay_ms: percentile(&delays, 50),
+                    p90_propagation_delay_ms: percentile(&delays, 90),
+                    average_propagation_rounds: (!propagation_rounds.is_empty()).then(|| {
+                        propagation_rounds.iter().sum::<Round>() as f64
+                            / propagation_rounds.len() as f64
+                    }),
+                    leaders_committed: leaders
+                        .clone()
+                        .filter(|report| {
+                            matches!(
+                                report.outcome,
+                                LeaderOutcome::DirectCommit { .. }
+                                    | LeaderOutcome::IndirectCommit { .. }
+                            )
+                        })
+                        .count(),
+                    leaders_skipped: leaders
+                        .filter(|report| {
+                            matches!(
+                                report.outcome,
+                                LeaderOutcome::DirectSkip | LeaderOutcome::IndirectSkip { .. }
+                            )
+                        })
+                        .count(),
+                    reputation_score: scores.scores_per_authority.get(authority.value()).copied(),
+                    swapped_out: swap_table.bad_nodes.contains_key(&authority),
+                }
+            })
+            .collect()
+    }
+
+    fn block_color(&self, block: &VerifiedBlock) -> &'static str {
+        let committed = self.committed.contains_key(&block.reference());
+        if self.leaders(block.round()).contains(&block.slot()) {
+            if committed {
+                "gold"
+            } else {
+                "salmon"
+            }
+        } else if committed {
+            "lightblue"
+        } else {
+            "white"
+        }
+    }
+
+    fn inspected_blocks(&self) -> impl Iterator<Item = &VerifiedBlock> {
+        self.blocks
+            .values()
+            .filter(|block| self.rounds.contains(&block.round()))
+    }
+
+    fn inspected_commits(&self) -> impl Iterator<Item = &TrustedCommit> {
+        self.commits
+            .values()
+            .filter(|commit| self.rounds.contains(&commit.leader().round))
+    }
+
+    /// Committed leaders are gold, other leaders salmon, and other committed blocks light blue.
+    fn to_dot(&self) -> Result<String, fmt::Error> {
+        let mut dot = String::new();
+        writeln!(dot, "digraph dag {{")?;
+        writeln!(dot, "  rankdir=BT;")?;
+        writeln!(dot, "  node [shape=box, style=filled, fontname=monospace];")?;
+        for (round, blocks) in &self.inspected_blocks().chunk_by(|block| block.round()) {
+            let blocks: Vec<_> = blocks.collect();
+            writeln!(dot, "  // Round {round}")?;
+            writeln!(
+                dot,
+                "  {{ rank=same; {} }}",
+                blocks
+                    .iter()
+                    .map(|block| format!("\"{}\";", block.reference()))
+                    .join(" ")
+            )?;
+            for block in blocks {
+                let hostname = &self.context.committee.authority(block.author()).hostname;
+                writeln!(
+                    dot,
+                    "  \"{}\" [label=\"{}\\n{}\\n{} txs\", fillcolor={}];",
+                    block.reference(),
+                    block.reference(),
+                    hostname,
+                    block.transactions().len(),
+                    self.block_color(block)
+                )?;
+                for ancestor in block.ancestors() {
+                    if ancestor.round >= *self.rounds.start() {
+                        writeln!(dot, "  \"{}\" -> \"{}\";", block.reference(), ancestor)?;
+                    }
+                }
+            }
+        }
+        writeln!(dot, "}}")?;
+        Ok(dot)
+    }
+
+    fn to_json(&self) -> String {
+        let dag = DagJson {
+            rounds: (*self.rounds.start(), *self.rounds.end()),
+            blocks: self
+                .inspected_blocks()
+                .map(|block| BlockJson {
+                    reference: block.reference().to_string(),
+                    round: block.round(),
+                    author: block.author().value(),
+                    hostname: self
+                        .context
+                        .committee
+                        .authority(block.author())
+                        .hostname
+                        .clone(),
+                    digest: block.digest().to_string(),
+                    timestamp_ms: block.timestamp_ms(),
+                    transactions: block.transactions().len(),
+                    ancestors: block.ancestors().iter().map(ToString::to_string).collect(),
+                    leader: self.leaders(block.round()).contains(&block.slot()),
+                    commit_index: self.committed.get(&block.reference()).copied(),
+                })
+                .collect(),
+            commits: self
+                .inspected_commits()
+                .map(|commit| CommitJson {
+                    index: commit.index(),
+                    digest: commit.digest().to_string(),
+                    leader: commit.leader().to_string(),
+                    timestamp_ms: commit.timestamp_ms(),
+                    blocks: commit.blocks().iter().map(ToString::to_string).collect(),
+                })
+                .collect(),
+        };
+        serde_json::to_string_pretty(&dag).expect("Serializing the DAG should not fail")
+    }
+}
+
+#[derive(Serialize)]
+struct DagJson {
+    rounds: (Round, Round),
+    blocks: Vec<BlockJson>,
+    commits: Vec<CommitJson>,
+}
+
+#[derive(Serialize)]
+struct BlockJson {
+    reference: String,
+    round: Round,
+    author: usize,
+    hostname: String,
+    digest: String,
+    timestamp_ms: BlockTimestampMs,
+    transactions: usize,
+    ancestors: Vec<String>,
+    leader: bool,
+    commit_index: Option<CommitIndex>,
+}
+
+#[derive(Serialize)]
+struct CommitJson {
+    index: CommitIndex,
+    digest: String,
+    leader: String,
+    timestamp_ms: BlockTimestampMs,
+    blocks: Vec<String>,
+}
+
+fn percentile(sorted: &[u64], percentile: usize) -> Option<u64> {
+    (!sorted.is_empty()).then(|| sorted[(sorted.len() - 1) * percentile / 100])
+}
+
+impl Display for LeaderReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let decision_round = self.round + DEFAULT_WAVE_LENGTH - 1;
+        let quorum = self.quorum_threshold;
+        writeln!(
+            f,
+            "Leader {} ({}) of round {}:",
+            self.leader, self.hostname, self.round
+        )?;
+        match &self.outcome {
+            LeaderOutcome::DirectCommit { commit_index } => writeln!(
+                f,
+                "  committed directly by commit {commit_index}: certificates at round \
+                 {decision_round} reach the quorum"
+            )?,
+            LeaderOutcome::IndirectCommit {
+                commit_index,
+                anchor,
+            } => {
+                write!(
+                    f,
+                    "  committed indirectly by commit {commit_index}: certificates at round \
+                     {decision_round} do not reach the quorum, "
+                )?;
+                match anchor {
+                    Some(anchor) => writeln!(f, "but anchor {anchor} links to a certificate")?,
+                    None => writeln!(f, "but a later anchor links to a certificate")?,
+                }
+            }
+            LeaderOutcome::DirectSkip => writeln!(
+                f,
+                "  skipped directly: blocks at round {} not referencing the leader reach the \
+                 quorum",
+                self.round + 1
+            )?,
+            LeaderOutcome::IndirectSkip { anchor } => {
+                write!(f, "  skipped indirectly: ")?;
+                match anchor {
+                    Some(anchor) => write!(f, "anchor {anchor} does not link to a certificate")?,
+                    None => write!(f, "a later anchor does not link to a certificate")?,
+                }
+                writeln!(f, " of the leader at round {decision_round}")?;
+            }
+            LeaderOutcome::Undecided => writeln!(
+                f,
+                "  undecided: no leader of round {} or later is committed yet",
+                self.round
+            )?,
+        }
+        if self.blocks.is_empty() {
+            writeln!(f, "  no block from the leader at round {}", self.round)?;
+        } else {
+            writeln!(f, "  blocks: {}", self.blocks.iter().join(", "))?;
+        }
+        write!(
+            f,
+            "  stake (quorum {quorum}): votes {}, blames {}, certificates {}",
+            self.vote_stake, self.blame_stake, self.certificate_stake
+        )
+    }
+}
+
+impl Display for AuthorityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let optional = |value: Option<u64>| value.map_or("-".to_string(), |v| v.to_string());
+        write!(
+            f,
+            "{} {}: stake {}, {} blocks, {} missing rounds, propagation delay p50 {} ms p90 {} \
+             ms, {} rounds on average, leaders committed {} skipped {}, reputation score {}",
+            self.authority,
+            self.hostname,
+            self.stake,
+            self.blocks,
+            self.missing_rounds,
+            optional(self.median_propagation_delay_ms),
+            optional(self.p90_propagation_delay_ms),
+            self.average_propagation_rounds
+                .map_or("-".to_string(), |rounds| format!("{rounds:.2}")),
+            self.leaders_committed,
+            self.leaders_skipped,
+            optional(self.reputation_score),
+        )?;
+        if self.swapped_out {
+            write!(f, " (swapped out of leader slots)")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        storage::{mem_store::MemStore, WriteBatch},
+        test_dag_builder::DagBuilder,
+    };
+
+    /// Persists the blocks of `dag_builder` and the commits of its leaders up to `last_leader`.
+    fn inspector(mut dag_builder: DagBuilder, last_leader: Round) -> DagInspector {
+        let store = Arc::new(MemStore::new());
+        let commits = dag_builder
+            .get_sub_dag_and_commits(1..=last_leader)
+            .into_iter()
+            .map(|(_, commit)| commit)
+            .collect();
+        store
+            .write(WriteBatch::new(dag_builder.all_blocks(), commits, vec![]))
+            .unwrap();
+        DagInspector::new(dag_builder.context.clone(), store)
+    }
+
+    #[tokio::test]
+    async fn test_explain_leaders() {
+        telemetry_subscribers::init_for_testing();
+        let context = Arc::new(Context::new_for_test(4).0);
+        let mut dag_builder = DagBuilder::new(context);
+        dag_builder.layers(1..=3).build();
+        // The leader of round 4 does not propose a block, so it is blamed by every block of
+        // round 5.
+        dag_builder.layers(4..=4).no_leader_block(vec![]).build();
+        dag_builder.layers(5..=8).build();
+        let inspector = inspector(dag_builder, 6);
+
+        let reports = inspector.explain_leaders(3..=8);
+        assert_eq!(reports.len(), 6);
+        assert!(matches!(
+            reports[0].outcome,
+            LeaderOutcome::DirectCommit { .. }
+        ));
+        assert_eq!(reports[0].certificate_stake, 3);
+
+        assert_eq!(reports[1].round, 4);
+        assert_eq!(reports[1].outcome, LeaderOutcome::DirectSkip);
+        assert!(reports[1].blocks.is_empty());
+        assert_eq!(reports[1].vote_stake, 0);
+        assert_eq!(reports[1].blame_stake, 4);
+        let explanation = reports[1].to_string();
+        assert!(explanation.contains("skipped directly"), "{explanation}");
+        assert!(
+            explanation.contains("no block from the leader"),
+            "{explanation}"
+        );
+
+        assert!(matches!(
+            reports[2].outcome,
+            LeaderOutcome::DirectCommit { .. }
+        ));
+        // Leaders after the last commit have not been decided yet.
+        assert_eq!(reports[4].outcome, LeaderOutcome::Undecided);
+        assert_eq!(reports[5].outcome, LeaderOutcome::Undecided);
+    }
+
+    #[tokio::test]
+    async fn test_export_and_authority_reports() {
+        telemetry_subscribers::init_for_testing();
+        let context = Arc::new(Context::new_for_test(4).0);
+        let mut dag_builder = DagBuilder::new(context);
+        dag_builder.layers(1..=10).build();
+        let inspector = inspector(dag_builder, 8);
+
+        let dot = inspector.export(2..=3, DagExportFormat::Dot);
+        assert!(dot.starts_with("digraph dag {"));
+        assert_eq!(dot.matches("rank=same").count(), 2);
+        assert_eq!(dot.matches("fillcolor=gold").count(), 2);
+
+        let json: serde_json::Value =
+            serde_json::from_str(&inspector.export(2..=3, DagExportFormat::Json)).unwrap();
+        assert_eq!(json["blocks"].as_array().unwrap().len(), 8);
+        assert_eq!(json["commits"].as_array().unwrap().len(), 2);
+
+        let reports = inspector.authority_reports(1..=8);
+        assert_eq!(reports.len(), 4);
+        for report in reports {
+            assert_eq!(report.blocks, 8);
+            assert_eq!(report.missing_rounds, 0);
+            assert_eq!(report.average_propagation_rounds, Some(1.0));
+            assert_eq!(report.leaders_committed, 2);
+            assert_eq!(report.leaders_skipped, 0);
+            assert!(!report.swapped_out);
+        }
+    }
+}
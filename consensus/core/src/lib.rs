@@ -17,6 +17,7 @@ mod commit_vote_monitor;
 mod context;
 mod core;
 mod core_thread;
+mod dag_inspector;
 mod dag_state;
 mod error;
 mod leader_schedule;
@@ -44,6 +45,9 @@ mod universal_committer;
 #[cfg(test)]
 #[path = "tests/randomized_tests.rs"]
 mod randomized_tests;
+#[cfg(test)]
+#[path = "tests/simulation_tests.rs"]
+mod simulation_tests;
 
 mod round_prober;
 #[cfg(test)]
@@ -52,6 +56,8 @@ mod test_dag;
 mod test_dag_builder;
 #[cfg(test)]
 mod test_dag_parser;
+#[cfg(test)]
+mod test_simulation;
 
 /// Exported consensus API.
 pub use authority_node::ConsensusAuthority;
@@ -60,6 +66,11 @@ pub use block::{BlockAPI, BlockRef, Round, TransactionIndex};
 pub use block::{TestBlock, Transaction, VerifiedBlock};
 pub use commit::{CommitDigest, CommitIndex, CommitRef, CommittedSubDag};
 pub use commit_consumer::{CommitConsumer, CommitConsumerMonitor};
+/// Exported API for offline inspection of the consensus store.
+pub use dag_inspector::{
+    AuthorityReport, DagExportFormat, DagInspector, LeaderOutcome, LeaderReport,
+    ReputationScoresReport,
+};
 pub use network::{
     connection_monitor::{AnemoConnectionMonitor, ConnectionMonitorHandle, ConnectionStatus},
     metrics::{MetricsMakeCallbackHandler, NetworkRouteMetrics, QuinnConnectionMetrics},
@@ -68,5 +79,6 @@ pub use network::{
 #[cfg(msim)]
 pub use transaction::NoopTransactionVerifier;
 pub use transaction::{
-    BlockStatus, ClientError, TransactionClient, TransactionVerifier, ValidationError,
+    BlockStatus, ClientError, TransactionClient, TransactionPriority, TransactionVerifier,
+    ValidationError,
 };
@@ -101,6 +101,8 @@ pub(crate) struct NodeMetrics {
     pub(crate) proposed_blocks: IntCounterVec,
     pub(crate) proposed_block_size: Histogram,
     pub(crate) proposed_block_transactions: Histogram,
+    pub(crate) proposed_transactions_by_priority: IntCounterVec,
+    pub(crate) starved_transaction_priority_proposals: IntCounterVec,
     pub(crate) proposed_block_ancestors: Histogram,
     pub(crate) proposed_block_ancestors_depth: HistogramVec,
     pub(crate) highest_verified_authority_round: IntGaugeVec,
@@ -224,6 +226,18 @@ impl NodeMetrics {
                 NUM_BUCKETS.to_vec(),
                 registry
             ).unwrap(),
+            proposed_transactions_by_priority: register_int_counter_vec_with_registry!(
+                "proposed_transactions_by_priority",
+                "Total number of transactions included in proposed blocks, by the priority they were submitted with.",
+                &["priority"],
+                registry,
+            ).unwrap(),
+            starved_transaction_priority_proposals: register_int_counter_vec_with_registry!(
+                "starved_transaction_priority_proposals",
+                "Number of block proposals that included the transactions of a priority ahead of higher priorities, as they had been left out for too many proposals.",
+                &["priority"],
+                registry,
+            ).unwrap(),
             proposed_block_ancestors: register_histogram_with_registry!(
                 "proposed_block_ancestors",
                 "Number of ancestors in proposed blocks",
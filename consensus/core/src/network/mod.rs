@@ -50,6 +50,8 @@ mod metrics_layer;
 #[cfg(all(test, not(msim)))]
 mod network_tests;
 #[cfg(test)]
+pub(crate) mod simulated_network;
+#[cfg(test)]
 pub(crate) mod test_network;
 #[cfg(not(msim))]
 pub(crate) mod tonic_network;
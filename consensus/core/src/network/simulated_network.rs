@@ -0,0 +1,414 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! In-process network for simulation tests. Requests are delivered by calling the
+//! `NetworkService` of the target authority directly after the latency of the link, unless the
+//! request is lost, the authorities are partitioned, or the target is not running. Losses are
+//! drawn from a seeded RNG, and latencies are waited on the tokio clock, so simulations can run
+//! with paused time.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    future::Future,
+    sync::Arc,
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use consensus_config::{AuthorityIndex, NetworkKeyPair};
+use parking_lot::Mutex;
+use rand::{rngs::StdRng, Rng as _, SeedableRng as _};
+
+use super::{BlockStream, ExtendedSerializedBlock, NetworkClient, NetworkManager, NetworkService};
+use crate::{
+    block::{BlockAPI as _, BlockRef, SignedBlock, VerifiedBlock},
+    commit::CommitRange,
+    context::Context,
+    error::{ConsensusError, ConsensusResult},
+    Round,
+};
+
+/// Latency and loss of the messages sent from an authority to another.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct LinkConfig {
+    pub(crate) latency: Duration,
+    /// Probability for a request on the link to be lost, between 0 and 1.
+    pub(crate) loss: f64,
+}
+
+impl Default for LinkConfig {
+    fn default() -> Self {
+        Self {
+            latency: Duration::from_millis(50),
+            loss: 0.0,
+        }
+    }
+}
+
+/// Identifies an authority instance connected to the network. Equivocating authorities are run
+/// as several instances sharing the same authority index.
+pub(crate) type EndpointId = usize;
+
+pub(crate) struct SimulatedNetwork {
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    rng: StdRng,
+    default_link: LinkConfig,
+    links: BTreeMap<(AuthorityIndex, AuthorityIndex), LinkConfig>,
+    /// Partition of each authority, if the network is partitioned. Authorities of different
+    /// partitions cannot reach each other.
+    partitions: Option<BTreeMap<AuthorityIndex, usize>>,
+    /// Blocks that are not broadcast to some peers, as (author, round, peer).
+    withheld_blocks: BTreeSet<(AuthorityIndex, Round, AuthorityIndex)>,
+    endpoints: Vec<Endpoint>,
+}
+
+struct Endpoint {
+    authority: AuthorityIndex,
+    /// Authorities the endpoint communicates with, when restricted.
+    peers: Option<BTreeSet<AuthorityIndex>>,
+    /// Service of the endpoint while its authority is running.
+    service: Option<Arc<dyn NetworkService>>,
+}
+
+impl SimulatedNetwork {
+    pub(crate) fn new(seed: u64, default_link: LinkConfig) -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                rng: StdRng::seed_from_u64(seed),
+                default_link,
+                links: BTreeMap::new(),
+                partitions: None,
+                withheld_blocks: BTreeSet::new(),
+                endpoints: vec![],
+            }),
+        }
+    }
+
+    /// Adds an endpoint for `authority`. When `peers` is set, the endpoint only communicates with
+    /// those authorities, which allows running an equivocating authority as several endpoints
+    /// talking to different peers.
+    pub(crate) fn add_endpoint(
+        &self,
+        authority: AuthorityIndex,
+        peers: Option<BTreeSet<AuthorityIndex>>,
+    ) -> EndpointId {
+        let mut inner = self.inner.lock();
+        inner.endpoints.push(Endpoint {
+            authority,
+            peers,
+            service: None,
+        });
+        inner.endpoints.len() - 1
+    }
+
+    /// Returns the network manager of an endpoint, to start its authority with.
+    pub(crate) fn manager(self: &Arc<Self>, endpoint: EndpointId) -> SimulatedManager {
+        SimulatedManager {
+            network: self.clone(),
+            endpoint,
+            client: Arc::new(SimulatedClient {
+                network: self.clone(),
+                endpoint,
+            }),
+        }
+    }
+
+    pub(crate) fn set_link(&self, from: AuthorityIndex, to: AuthorityIndex, link: LinkConfig) {
+        self.inner.lock().links.insert((from, to), link);
+    }
+
+    /// Splits the authorities into partitions. Authorities missing from `partitions` are
+    /// isolated.
+    pub(crate) fn partition(&self, partitions: &[Vec<AuthorityIndex>]) {
+        let partitions = partitions
+            .iter()
+            .enumerate()
+            .flat_map(|(i, authorities)| authorities.iter().map(move |a| (*a, i)))
+            .collect();
+        self.inner.lock().partitions = Some(partitions);
+    }
+
+    pub(crate) fn heal(&self) {
+        self.inner.lock().partitions = None;
+    }
+
+    pub(crate) fn withhold_blocks(
+        &self,
+        withheld_blocks: impl IntoIterator<Item = (AuthorityIndex, Round, AuthorityIndex)>,
+    ) {
+        self.inner.lock().withheld_blocks.extend(withheld_blocks);
+    }
+
+    fn install(&self, endpoint: EndpointId, service: Arc<dyn NetworkService>) {
+        self.inner.lock().endpoints[endpoint].service = Some(service);
+    }
+
+    fn uninstall(&self, endpoint: EndpointId) {
+        self.inner.lock().endpoints[endpoint].service = None;
+    }
+
+    /// Sends a request from `from` to authority `to`, and waits for its response. The request is
+    /// handled by `handler` with the service of the target and the authority of the sender.
+    async fn request<T, F, Fut>(
+        &self,
+        from: EndpointId,
+        to: AuthorityIndex,
+        timeout: Duration,
+        handler: F,
+    ) -> ConsensusResult<T>
+    where
+        F: FnOnce(Arc<dyn NetworkService>, AuthorityIndex) -> Fut,
+        Fut: Future<Output = ConsensusResult<T>>,
+    {
+        let exchange = async {
+            let (sender, service, latency) = self.inner.lock().route(from, to, true)?;
+            tokio::time::sleep(latency).await;
+            let response = handler(service, sender).await?;
+            // The response is lost if the authorities got partitioned in the meantime.
+            let (_, _, latency) = self.inner.lock().route(from, to, false)?;
+            tokio::time::sleep(latency).await;
+            Ok(response)
+        };
+        tokio::time::timeout(timeout, exchange).await.map_err(|_| {
+            ConsensusError::NetworkRequestTimeout(format!("request to {to} timed out"))
+        })?
+    }
+
+    fn is_withheld(&self, block: &VerifiedBlock, peer: AuthorityIndex) -> bool {
+        self.inner
+            .lock()
+            .withheld_blocks
+            .contains(&(block.author(), block.round(), peer))
+    }
+
+    /// Returns the authority of an endpoint, if the endpoint is one of several instances of an
+    /// equivocating authority. Such endpoints must not receive the blocks of the other instances,
+    /// which they would consider as their own.
+    fn equivocator(&self, endpoint: EndpointId) -> Option<AuthorityIndex> {
+        let inner = self.inner.lock();
+        let authority = inner.endpoints[endpoint].authority;
+        (inner
+            .endpoints
+            .iter()
+            .filter(|e| e.authority == authority)
+            .count()
+            > 1)
+        .then_some(authority)
+    }
+
+    fn filter_blocks(&self, endpoint: EndpointId, blocks: Vec<Bytes>) -> Vec<Bytes> {
+        let Some(authority) = self.equivocator(endpoint) else {
+            return blocks;
+        };
+        blocks
+            .into_iter()
+            .filter(|serialized| {
+                bcs::from_bytes::<SignedBlock>(serialized)
+                    .map_or(true, |block| block.author() != authority)
+            })
+            .collect()
+    }
+}
+
+impl Inner {
+    /// Returns the sender authority, the service of the endpoint of `to` reachable from `from`,
+    /// and the latency of the link in the direction of the message.
+    fn route(
+        &mut self,
+        from: EndpointId,
+        to: AuthorityIndex,
+        request: bool,
+    ) -> ConsensusResult<(AuthorityIndex, Arc<dyn NetworkService>, Duration)> {
+        let sender = &self.endpoints[from];
+        let sender_authority = sender.authority;
+        let unreachable = || {
+            ConsensusError::NetworkRequest(format!("{to} is unreachable from {sender_authority}"))
+        };
+        if sender.service.is_none()
+            || sender
+                .peers
+                .as_ref()
+                .is_some_and(|peers| !peers.contains(&to))
+        {
+            return Err(unreachable());
+        }
+        if let Some(partitions) = &self.partitions {
+            if partitions.get(&sender_authority) != partitions.get(&to)
+                || !partitions.contains_key(&to)
+            {
+                return Err(unreachable());
+            }
+        }
+        let service = self
+            .endpoints
+            .iter()
+            .filter(|e| e.authority == to)
+            .filter(|e| {
+                e.peers
+                    .as_ref()
+                    .map_or(true, |peers| peers.contains(&sender_authority))
+            })
+            .find_map(|e| e.service.clone())
+            .ok_or_else(unreachable)?;
+
+        let (link_from, link_to) = if request {
+            (sender_authority, to)
+        } else {
+            (to, sender_authority)
+        };
+        let link = self
+            .links
+            .get(&(link_from, link_to))
+            .copied()
+            .unwrap_or(self.default_link);
+        if request && link.loss > 0.0 && self.rng.gen_bool(link.loss.min(1.0)) {
+            return Err(ConsensusError::NetworkRequest(format!(
+                "request from {sender_authority} to {to} was lost"
+            )));
+        }
+        Ok((sender_authority, service, link.latency))
+    }
+}
+
+pub(crate) struct SimulatedClient {
+    network: Arc<SimulatedNetwork>,
+    endpoint: EndpointId,
+}
+
+#[async_trait]
+impl NetworkClient for SimulatedClient {
+    const SUPPORT_STREAMING: bool = false;
+
+    async fn send_block(
+        &self,
+        peer: AuthorityIndex,
+        block: &VerifiedBlock,
+        timeout: Duration,
+    ) -> ConsensusResult<()> {
+        if self.network.is_withheld(block, peer) {
+            return Ok(());
+        }
+        let block = ExtendedSerializedBlock {
+            block: block.serialized().clone(),
+            excluded_ancestors: vec![],
+        };
+        self.network
+            .request(self.endpoint, peer, timeout, |service, sender| async move {
+                service.handle_send_block(sender, block).await
+            })
+            .await
+    }
+
+    async fn subscribe_blocks(
+        &self,
+        _peer: AuthorityIndex,
+        _last_received: Round,
+        _timeout: Duration,
+    ) -> ConsensusResult<BlockStream> {
+        unimplemented!("Unimplemented")
+    }
+
+    async fn fetch_blocks(
+        &self,
+        peer: AuthorityIndex,
+        block_refs: Vec<BlockRef>,
+        highest_accepted_rounds: Vec<Round>,
+        timeout: Duration,
+    ) -> ConsensusResult<Vec<Bytes>> {
+        let blocks = self
+            .network
+            .request(self.endpoint, peer, timeout, |service, sender| async move {
+                service
+                    .handle_fetch_blocks(sender, block_refs, highest_accepted_rounds)
+                    .await
+            })
+            .await?;
+        Ok(self.network.filter_blocks(self.endpoint, blocks))
+    }
+
+    async fn fetch_commits(
+        &self,
+        peer: AuthorityIndex,
+        commit_range: CommitRange,
+        timeout: Duration,
+    ) -> ConsensusResult<(Vec<Bytes>, Vec<Bytes>)> {
+        let (commits, certifier_blocks) = self
+            .network
+            .request(self.endpoint, peer, timeout, |service, sender| async move {
+                service.handle_fetch_commits(sender, commit_range).await
+            })
+            .await?;
+        let commits = commits
+            .into_iter()
+            .map(|c| c.serialized().clone())
+            .collect();
+        let certifier_blocks = certifier_blocks
+            .into_iter()
+            .map(|b| b.serialized().clone())
+            .collect();
+        Ok((
+            commits,
+            self.network.filter_blocks(self.endpoint, certifier_blocks),
+        ))
+    }
+
+    async fn fetch_latest_blocks(
+        &self,
+        peer: AuthorityIndex,
+        authorities: Vec<AuthorityIndex>,
+        timeout: Duration,
+    ) -> ConsensusResult<Vec<Bytes>> {
+        let blocks = self
+            .network
+            .request(self.endpoint, peer, timeout, |service, sender| async move {
+                service
+                    .handle_fetch_latest_blocks(sender, authorities)
+                    .await
+            })
+            .await?;
+        Ok(self.network.filter_blocks(self.endpoint, blocks))
+    }
+
+    async fn get_latest_rounds(
+        &self,
+        peer: AuthorityIndex,
+        timeout: Duration,
+    ) -> ConsensusResult<(Vec<Round>, Vec<Round>)> {
+        self.network
+            .request(self.endpoint, peer, timeout, |service, sender| async move {
+                service.handle_get_latest_rounds(sender).await
+            })
+            .await
+    }
+}
+
+/// Connects an authority to a `SimulatedNetwork`, through the endpoint it was created for.
+pub(crate) struct SimulatedManager {
+    network: Arc<SimulatedNetwork>,
+    endpoint: EndpointId,
+    client: Arc<SimulatedClient>,
+}
+
+impl<S: NetworkService> NetworkManager<S> for SimulatedManager {
+    type Client = SimulatedClient;
+
+    fn new(_context: Arc<Context>, _network_keypair: NetworkKeyPair) -> Self {
+        unimplemented!("Simulated network managers are created with SimulatedNetwork::manager()")
+    }
+
+    fn client(&self) -> Arc<Self::Client> {
+        self.client.clone()
+    }
+
+    async fn install_service(&mut self, service: Arc<S>) {
+        self.network.install(self.endpoint, service);
+    }
+
+    async fn stop(&mut self) {
+        self.network.uninstall(self.endpoint);
+    }
+}
@@ -108,12 +108,21 @@ impl Store for MemStore {
         &self,
         author: AuthorityIndex,
         start_round: Round,
+    ) -> ConsensusResult<Vec<VerifiedBlock>> {
+        self.scan_blocks_by_author_in_range(author, start_round, Round::MAX)
+    }
+
+    fn scan_blocks_by_author_in_range(
+        &self,
+        author: AuthorityIndex,
+        start_round: Round,
+        end_round: Round,
     ) -> ConsensusResult<Vec<VerifiedBlock>> {
         let inner = self.inner.read();
         let mut refs = vec![];
         for &(author, round, digest) in inner.digests_by_authorities.range((
             Included((author, start_round, BlockDigest::MIN)),
-            Included((author, Round::MAX, BlockDigest::MAX)),
+            Included((author, end_round, BlockDigest::MAX)),
         )) {
             refs.push(BlockRef::new(round, author, digest));
         }
@@ -213,4 +222,19 @@ impl Store for MemStore {
             .last_key_value()
             .map(|(k, v)| (CommitRef::new(k.0, k.1), v.clone())))
     }
+
+    fn scan_commit_info(
+        &self,
+        range: CommitRange,
+    ) -> ConsensusResult<Vec<(CommitRef, CommitInfo)>> {
+        let inner = self.inner.read();
+        Ok(inner
+            .commit_info
+            .range((
+                Included((range.start(), CommitDigest::MIN)),
+                Included((range.end(), CommitDigest::MAX)),
+            ))
+            .map(|(k, v)| (CommitRef::new(k.0, k.1), v.clone()))
+            .collect())
+    }
 }
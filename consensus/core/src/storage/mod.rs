@@ -38,6 +38,14 @@ pub(crate) trait Store: Send + Sync {
         start_round: Round,
     ) -> ConsensusResult<Vec<VerifiedBlock>>;
 
+    /// Reads blocks for an authority, from start_round until end_round (inclusive).
+    fn scan_blocks_by_author_in_range(
+        &self,
+        authority: AuthorityIndex,
+        start_round: Round,
+        end_round: Round,
+    ) -> ConsensusResult<Vec<VerifiedBlock>>;
+
     // The method returns the last `num_of_rounds` rounds blocks by author in round ascending order.
     // When a `before_round` is defined then the blocks of round `<=before_round` are returned. If not
     // then the max value for round will be used as cut off.
@@ -59,6 +67,10 @@ pub(crate) trait Store: Send + Sync {
 
     /// Reads the last commit info, written atomically with the last commit.
     fn read_last_commit_info(&self) -> ConsensusResult<Option<(CommitRef, CommitInfo)>>;
+
+    /// Reads all commit infos written for commits from start (inclusive) until end (inclusive).
+    fn scan_commit_info(&self, range: CommitRange)
+        -> ConsensusResult<Vec<(CommitRef, CommitInfo)>>;
 }
 
 /// Represents data to be written to the store together atomically.
@@ -193,11 +193,20 @@ impl Store for RocksDBStore {
         &self,
         author: AuthorityIndex,
         start_round: Round,
+    ) -> ConsensusResult<Vec<VerifiedBlock>> {
+        self.scan_blocks_by_author_in_range(author, start_round, Round::MAX)
+    }
+
+    fn scan_blocks_by_author_in_range(
+        &self,
+        author: AuthorityIndex,
+        start_round: Round,
+        end_round: Round,
     ) -> ConsensusResult<Vec<VerifiedBlock>> {
         let mut refs = vec![];
         for kv in self.digests_by_authorities.safe_range_iter((
             Included((author, start_round, BlockDigest::MIN)),
-            Included((author, Round::MAX, BlockDigest::MAX)),
+            Included((author, end_round, BlockDigest::MAX)),
         )) {
             let ((author, round, digest), _) = kv?;
             refs.push(BlockRef::new(round, author, digest));
@@ -295,4 +304,19 @@ impl Store for RocksDBStore {
         let (key, commit_info) = result.map_err(ConsensusError::RocksDBFailure)?;
         Ok(Some((CommitRef::new(key.0, key.1), commit_info)))
     }
+
+    fn scan_commit_info(
+        &self,
+        range: CommitRange,
+    ) -> ConsensusResult<Vec<(CommitRef, CommitInfo)>> {
+        let mut commit_infos = vec![];
+        for result in self.commit_info.safe_range_iter((
+            Included((range.start(), CommitDigest::MIN)),
+            Included((range.end(), CommitDigest::MAX)),
+        )) {
+            let (key, commit_info) = result.map_err(ConsensusError::RocksDBFailure)?;
+            commit_infos.push((CommitRef::new(key.0, key.1), commit_info));
+        }
+        Ok(commit_infos)
+    }
 }
@@ -8,7 +8,8 @@ use tempfile::TempDir;
 use super::{mem_store::MemStore, rocksdb_store::RocksDBStore, Store, WriteBatch};
 use crate::{
     block::{BlockAPI, BlockDigest, BlockRef, Slot, TestBlock, VerifiedBlock},
-    commit::{CommitDigest, TrustedCommit},
+    commit::{CommitAPI as _, CommitDigest, CommitInfo, TrustedCommit},
+    leader_scoring::ReputationScores,
 };
 
 /// Test fixture for store tests. Wraps around various store implementations.
@@ -183,6 +184,20 @@ async fn scan_blocks(
         );
     }
 
+    {
+        let scanned_blocks = store
+            .scan_blocks_by_author_in_range(AuthorityIndex::new_for_test(1), 11, 13)
+            .expect("Scan blocks should not fail");
+        assert_eq!(
+            scanned_blocks,
+            vec![
+                written_blocks[3].clone(),
+                written_blocks[5].clone(),
+                written_blocks[7].clone(),
+            ]
+        );
+    }
+
     {
         let scanned_blocks = store
             .scan_last_blocks_by_author(AuthorityIndex::new_for_test(1), 2, None)
@@ -297,3 +312,74 @@ async fn read_and_scan_commits(
         assert_eq!(scanned_commits, written_commits,);
     }
 }
+
+#[rstest]
+#[tokio::test]
+async fn read_and_scan_commit_info(
+    #[values(new_rocksdb_teststore(), new_mem_teststore())] test_store: TestStore,
+) {
+    let store = test_store.store();
+
+    let commits: Vec<_> = (1..=4)
+        .map(|index| {
+            TrustedCommit::new_for_test(
+                index,
+                CommitDigest::MIN,
+                index as u64,
+                BlockRef::new(
+                    index,
+                    AuthorityIndex::new_for_test(0),
+                    BlockDigest::default(),
+                ),
+                vec![],
+            )
+        })
+        .collect();
+    let commit_info: Vec<_> = [2, 4]
+        .into_iter()
+        .map(|index: usize| {
+            let commit: &TrustedCommit = &commits[index - 1];
+            (
+                commit.reference(),
+                CommitInfo {
+                    committed_rounds: vec![commit.round(); 4],
+                    reputation_scores: ReputationScores::new(
+                        (1..=commit.index()).into(),
+                        vec![index as u64; 4],
+                    ),
+                },
+            )
+        })
+        .collect();
+    store
+        .write(
+            WriteBatch::default()
+                .commits(commits)
+                .commit_info(commit_info.clone()),
+        )
+        .unwrap();
+
+    let (last_ref, last_info) = store.read_last_commit_info().unwrap().unwrap();
+    assert_eq!(last_ref, commit_info[1].0);
+    assert_eq!(
+        last_info.reputation_scores,
+        commit_info[1].1.reputation_scores
+    );
+
+    let scanned = store.scan_commit_info((1..=3).into()).unwrap();
+    assert_eq!(scanned.len(), 1);
+    assert_eq!(scanned[0].0, commit_info[0].0);
+    assert_eq!(
+        scanned[0].1.reputation_scores,
+        commit_info[0].1.reputation_scores
+    );
+
+    let scanned = store.scan_commit_info((1..=4).into()).unwrap();
+    assert_eq!(
+        scanned
+            .iter()
+            .map(|(commit_ref, _)| *commit_ref)
+            .collect::<Vec<_>>(),
+        vec![commit_info[0].0, commit_info[1].0]
+    );
+}
@@ -0,0 +1,430 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Harness running a committee of `AuthorityNode`s in process, connected by a
+//! `SimulatedNetwork` and sharing a clock. Tests run it on a current thread runtime with paused
+//! time, script faults between waits, and every commit output by an authority is checked against
+//! the commits output by the others.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+    time::Duration,
+};
+
+use consensus_config::{
+    local_committee_and_keys, AuthorityIndex, Committee, NetworkKeyPair, Parameters,
+    ProtocolKeyPair,
+};
+use mys_protocol_config::ProtocolConfig;
+use parking_lot::Mutex;
+use prometheus::Registry;
+use tempfile::TempDir;
+use tokio::task::JoinHandle;
+use typed_store::DBMetrics;
+
+use crate::{
+    authority_node::AuthorityNode,
+    block::{BlockAPI as _, BlockRef, Round, VerifiedBlock},
+    commit::{CommitIndex, CommitRef, CommittedSubDag},
+    context::{Clock, Context},
+    metrics::initialise_metrics,
+    network::simulated_network::{EndpointId, LinkConfig, SimulatedManager, SimulatedNetwork},
+    test_dag_parser::parse_dag,
+    transaction::NoopTransactionVerifier,
+    CommitConsumer,
+};
+
+pub(crate) struct SimulationBuilder {
+    num_authorities: usize,
+    seed: u64,
+    default_link: LinkConfig,
+    protocol_config: ProtocolConfig,
+    equivocators: BTreeSet<AuthorityIndex>,
+    scenario: Option<String>,
+}
+
+impl SimulationBuilder {
+    pub(crate) fn new(num_authorities: usize) -> Self {
+        Self {
+            num_authorities,
+            seed: 0,
+            default_link: LinkConfig::default(),
+            protocol_config: ProtocolConfig::get_for_max_version_UNSAFE(),
+            equivocators: BTreeSet::new(),
+            scenario: None,
+        }
+    }
+
+    /// Seeds the RNG deciding which requests are lost.
+    pub(crate) fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Sets the latency and loss of links without a specific config.
+    pub(crate) fn default_link(mut self, link: LinkConfig) -> Self {
+        self.default_link = link;
+        self
+    }
+
+    pub(crate) fn protocol_config(mut self, protocol_config: ProtocolConfig) -> Self {
+        self.protocol_config = protocol_config;
+        self
+    }
+
+    /// Runs `authority` as two instances with the same keys, each communicating with half of the
+    /// other authorities, so that they propose different blocks for the same rounds.
+    pub(crate) fn equivocating(mut self, authority: AuthorityIndex) -> Self {
+        self.equivocators.insert(authority);
+        self
+    }
+
+    /// Shapes the rounds of the DAG with a scenario in the `parse_dag` syntax. Blocks of the
+    /// scenario that are not referenced by a block of the next round are not broadcast to the
+    /// author of that block, and authorities without a block in a round do not broadcast their
+    /// block of that round. Withheld blocks can still be fetched once referenced.
+    pub(crate) fn scenario(mut self, dag: &str) -> Self {
+        self.scenario = Some(dag.to_string());
+        self
+    }
+
+    pub(crate) async fn start(self) -> Simulation {
+        let db_registry = Registry::new();
+        DBMetrics::init(&db_registry);
+
+        let (committee, keypairs) = local_committee_and_keys(0, vec![1; self.num_authorities]);
+        let network = Arc::new(SimulatedNetwork::new(self.seed, self.default_link));
+        if let Some(dag) = &self.scenario {
+            network.withhold_blocks(withheld_blocks(dag, self.num_authorities));
+        }
+
+        let mut nodes = vec![];
+        for (authority, _) in committee.authorities() {
+            if !self.equivocators.contains(&authority) {
+                let endpoint = network.add_endpoint(authority, None);
+                nodes.push(SimulatedNode::new(authority, endpoint, true));
+                continue;
+            }
+            let others: Vec<_> = committee
+                .authorities()
+                .map(|(peer, _)| peer)
+                .filter(|peer| *peer != authority)
+                .collect();
+            let (first_half, second_half) = others.split_at(others.len() / 2);
+            for peers in [first_half, second_half] {
+                let endpoint =
+                    network.add_endpoint(authority, Some(peers.iter().copied().collect()));
+                nodes.push(SimulatedNode::new(authority, endpoint, false));
+            }
+        }
+
+        let mut simulation = Simulation {
+            committee,
+            keypairs,
+            protocol_config: self.protocol_config,
+            clock: Arc::new(Clock::new()),
+            network,
+            nodes,
+            checker: Arc::new(Mutex::new(SafetyChecker::default())),
+        };
+        for node in 0..simulation.nodes.len() {
+            simulation.start_node(node).await;
+        }
+        simulation
+    }
+}
+
+pub(crate) struct Simulation {
+    committee: Committee,
+    keypairs: Vec<(NetworkKeyPair, ProtocolKeyPair)>,
+    protocol_config: ProtocolConfig,
+    clock: Arc<Clock>,
+    network: Arc<SimulatedNetwork>,
+    nodes: Vec<SimulatedNode>,
+    checker: Arc<Mutex<SafetyChecker>>,
+}
+
+struct SimulatedNode {
+    authority: AuthorityIndex,
+    endpoint: EndpointId,
+    /// Commits are only checked for honest authorities.
+    honest: bool,
+    dir: TempDir,
+    boot_counter: u64,
+    running: Option<(AuthorityNode<SimulatedManager>, JoinHandle<()>)>,
+}
+
+impl SimulatedNode {
+    fn new(authority: AuthorityIndex, endpoint: EndpointId, honest: bool) -> Self {
+        Self {
+            authority,
+            endpoint,
+            honest,
+            dir: TempDir::new().unwrap(),
+            boot_counter: 0,
+            running: None,
+        }
+    }
+}
+
+impl Simulation {
+    pub(crate) fn set_link(&self, from: AuthorityIndex, to: AuthorityIndex, link: LinkConfig) {
+        self.network.set_link(from, to, link);
+    }
+
+    /// Splits the authorities into partitions that cannot reach each other. Authorities missing
+    /// from `partitions` are isolated.
+    pub(crate) fn partition(&self, partitions: &[Vec<AuthorityIndex>]) {
+        self.network.partition(partitions);
+    }
+
+    pub(crate) fn heal(&self) {
+        self.network.heal();
+    }
+
+    /// Stops the instances of `authority`. Their stores are kept for restarts.
+    pub(crate) async fn crash(&mut self, authority: AuthorityIndex) {
+        for node in self.nodes.iter_mut().filter(|n| n.authority == authority) {
+            if let Some((authority_node, output)) = node.running.take() {
+                authority_node.stop().await;
+                output.abort();
+            }
+        }
+    }
+
+    /// Restarts the instances of `authority` from their stores.
+    pub(crate) async fn restart(&mut self, authority: AuthorityIndex) {
+        for node in 0..self.nodes.len() {
+            if self.nodes[node].authority == authority && self.nodes[node].running.is_none() {
+                self.start_node(node).await;
+            }
+        }
+    }
+
+    /// Lets the authorities run for `duration` of simulated time.
+    pub(crate) async fn run_for(&self, duration: Duration) {
+        tokio::time::sleep(duration).await;
+        self.assert_safety();
+    }
+
+    /// Waits until every running honest authority has output commits up to `index`.
+    pub(crate) async fn wait_for_commits(&self, index: CommitIndex, timeout: Duration) {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            self.assert_safety();
+            let lagging: Vec<_> = self
+                .nodes
+                .iter()
+                .enumerate()
+                .filter(|(_, node)| node.honest && node.running.is_some())
+                .map(|(i, node)| (node.authority, self.checker.lock().last_commit_index(i)))
+                .filter(|(_, last_commit_index)| *last_commit_index < index)
+                .collect();
+            if lagging.is_empty() {
+                return;
+            }
+            assert!(
+                tokio::time::Instant::now() < deadline,
+                "Timed out waiting for commit {index}, last commit of lagging authorities: \
+                 {lagging:?}"
+            );
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    /// Returns the highest commit output by any honest authority.
+    pub(crate) fn highest_commit_index(&self) -> CommitIndex {
+        self.checker.lock().commits.len() as CommitIndex
+    }
+
+    /// Panics if an invariant was violated by a commit output so far.
+    pub(crate) fn assert_safety(&self) {
+        let checker = self.checker.lock();
+        assert!(
+            checker.violations.is_empty(),
+            "Safety violations: {:#?}",
+            checker.violations
+        );
+    }
+
+    pub(crate) async fn stop(mut self) {
+        let authorities: Vec<_> = self.committee.authorities().map(|(a, _)| a).collect();
+        for authority in authorities {
+            self.crash(authority).await;
+        }
+        self.assert_safety();
+    }
+
+    async fn start_node(&mut self, node: usize) {
+        let last_commit_index = self.checker.lock().last_commit_index(node);
+        let (commit_consumer, mut commit_receiver, _transaction_receiver) =
+            CommitConsumer::new(last_commit_index);
+        let monitor = commit_consumer.monitor();
+
+        let SimulatedNode {
+            authority,
+            endpoint,
+            honest,
+            dir,
+            boot_counter,
+            ..
+        } = &mut self.nodes[node];
+        let context = Arc::new(Context::new(
+            *authority,
+            self.committee.clone(),
+            Parameters {
+                db_path: dir.path().to_path_buf(),
+                // Instances of an equivocating authority would sync each other's blocks.
+                sync_last_known_own_block_timeout: Duration::ZERO,
+                ..Default::default()
+            },
+            self.protocol_config.clone(),
+            initialise_metrics(Registry::new()),
+            self.clock.clone(),
+        ));
+        let authority_node = AuthorityNode::start_with_network_manager(
+            context,
+            self.network.manager(*endpoint),
+            self.keypairs[*authority].1.clone(),
+            Arc::new(NoopTransactionVerifier {}),
+            commit_consumer,
+            *boot_counter,
+        )
+        .await;
+        *boot_counter += 1;
+
+        let checker = self.checker.clone();
+        let honest = *honest;
+        let output = tokio::spawn(async move {
+            while let Some(sub_dag) = commit_receiver.recv().await {
+                checker.lock().observe(node, honest, &sub_dag);
+                monitor.set_highest_handled_commit(sub_dag.commit_ref.index);
+            }
+        });
+        self.nodes[node].running = Some((authority_node, output));
+    }
+}
+
+/// Checks the commits output by each authority, as they are output.
+#[derive(Default)]
+struct SafetyChecker {
+    /// Commit sequence output by honest authorities.
+    commits: Vec<CommitRecord>,
+    last_commit_indices: BTreeMap<usize, CommitIndex>,
+    committed_blocks: BTreeMap<usize, BTreeSet<BlockRef>>,
+    violations: Vec<String>,
+}
+
+#[derive(Debug, PartialEq)]
+struct CommitRecord {
+    commit_ref: CommitRef,
+    leader: BlockRef,
+    blocks: Vec<BlockRef>,
+}
+
+impl SafetyChecker {
+    fn last_commit_index(&self, node: usize) -> CommitIndex {
+        self.last_commit_indices.get(&node).copied().unwrap_or(0)
+    }
+
+    fn observe(&mut self, node: usize, honest: bool, sub_dag: &CommittedSubDag) {
+        let index = sub_dag.commit_ref.index;
+        let last_commit_index = self.last_commit_indices.insert(node, index).unwrap_or(0);
+        if !honest {
+            return;
+        }
+
+        if index != last_commit_index + 1 {
+            self.violations.push(format!(
+                "Node {node} output commit {index} after commit {last_commit_index}"
+            ));
+        }
+
+        let record = CommitRecord {
+            commit_ref: sub_dag.commit_ref,
+            leader: sub_dag.leader,
+            blocks: sub_dag
+                .blocks
+                .iter()
+                .map(|block| block.reference())
+                .collect(),
+        };
+        let mut sorted_blocks = record.blocks.clone();
+        sorted_blocks.sort_by_key(|block| (block.round, block.author));
+        if sorted_blocks != record.blocks {
+            self.violations.push(format!(
+                "Node {node} output the blocks of commit {index} out of order: {:?}",
+                record.blocks
+            ));
+        }
+        let committed_blocks = self.committed_blocks.entry(node).or_default();
+        for block in &record.blocks {
+            if !committed_blocks.insert(*block) {
+                self.violations.push(format!(
+                    "Node {node} committed block {block} again in commit {index}"
+                ));
+            }
+        }
+
+        // Every honest authority must output the same commits, with the same blocks in the same
+        // order.
+        match self.commits.get(index as usize - 1) {
+            Some(expected) if *expected != record => self.violations.push(format!(
+                "Node {node} output {record:?} as commit {index}, other nodes output {expected:?}"
+            )),
+            Some(_) => {}
+            None if index as usize == self.commits.len() + 1 => self.commits.push(record),
+            None => {}
+        }
+    }
+}
+
+/// Returns the blocks withheld from broadcast by a scenario, as (author, round, peer).
+pub(crate) fn withheld_blocks(
+    dag: &str,
+    num_authorities: usize,
+) -> BTreeSet<(AuthorityIndex, Round, AuthorityIndex)> {
+    let (_, dag_builder) = parse_dag(dag).expect("Invalid scenario");
+    let committee = &dag_builder.context.committee;
+    assert_eq!(
+        committee.size(),
+        num_authorities,
+        "Scenario is for a different number of authorities"
+    );
+
+    let mut blocks_by_round: BTreeMap<Round, Vec<&VerifiedBlock>> = BTreeMap::new();
+    for block in dag_builder.blocks.values() {
+        blocks_by_round
+            .entry(block.round())
+            .or_default()
+            .push(block);
+    }
+
+    let mut withheld = BTreeSet::new();
+    for (round, blocks) in &blocks_by_round {
+        for (author, _) in committee.authorities() {
+            if blocks.iter().all(|block| block.author() != author) {
+                withheld.extend(
+                    committee
+                        .authorities()
+                        .map(|(peer, _)| peer)
+                        .filter(|peer| *peer != author)
+                        .map(|peer| (author, *round, peer)),
+                );
+            }
+        }
+        let Some(next_blocks) = blocks_by_round.get(&(round + 1)) else {
+            continue;
+        };
+        for block in blocks {
+            for next in next_blocks {
+                if next.author() != block.author() && !next.ancestors().contains(&block.reference())
+                {
+                    withheld.insert((block.author(), *round, next.author()));
+                }
+            }
+        }
+    }
+    withheld
+}
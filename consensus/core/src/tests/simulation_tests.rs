@@ -0,0 +1,142 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{collections::BTreeSet, time::Duration};
+
+use consensus_config::AuthorityIndex;
+
+use crate::{
+    network::simulated_network::LinkConfig,
+    test_simulation::{withheld_blocks, SimulationBuilder},
+};
+
+const TIMEOUT: Duration = Duration::from_secs(300);
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn test_simulation_with_latency_and_loss() {
+    let simulation = SimulationBuilder::new(4)
+        .seed(7)
+        .default_link(LinkConfig {
+            latency: Duration::from_millis(150),
+            loss: 0.1,
+        })
+        .start()
+        .await;
+
+    simulation.wait_for_commits(30, TIMEOUT).await;
+    simulation.stop().await;
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn test_simulation_with_partition() {
+    let simulation = SimulationBuilder::new(4).start().await;
+    simulation.wait_for_commits(5, TIMEOUT).await;
+
+    // Neither side has a quorum, so no new leader can be committed.
+    simulation.partition(&[
+        vec![
+            AuthorityIndex::new_for_test(0),
+            AuthorityIndex::new_for_test(1),
+        ],
+        vec![
+            AuthorityIndex::new_for_test(2),
+            AuthorityIndex::new_for_test(3),
+        ],
+    ]);
+    simulation.run_for(Duration::from_secs(5)).await;
+    let partitioned_commit_index = simulation.highest_commit_index();
+    simulation.run_for(Duration::from_secs(30)).await;
+    assert_eq!(simulation.highest_commit_index(), partitioned_commit_index);
+
+    simulation.heal();
+    simulation
+        .wait_for_commits(partitioned_commit_index + 10, TIMEOUT)
+        .await;
+    simulation.stop().await;
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn test_simulation_with_crash_and_restart() {
+    let mut simulation = SimulationBuilder::new(4).start().await;
+    simulation.wait_for_commits(5, TIMEOUT).await;
+
+    // The remaining authorities still form a quorum.
+    let crashed = AuthorityIndex::new_for_test(3);
+    simulation.crash(crashed).await;
+    simulation.wait_for_commits(20, TIMEOUT).await;
+
+    // The restarted authority recovers from its store and catches up with the others.
+    simulation.restart(crashed).await;
+    let highest_commit_index = simulation.highest_commit_index();
+    simulation
+        .wait_for_commits(highest_commit_index + 10, TIMEOUT)
+        .await;
+    simulation.stop().await;
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn test_simulation_with_equivocating_authority() {
+    let simulation = SimulationBuilder::new(4)
+        .equivocating(AuthorityIndex::new_for_test(3))
+        .start()
+        .await;
+
+    simulation.wait_for_commits(20, TIMEOUT).await;
+    simulation.stop().await;
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn test_simulation_with_scenario() {
+    let simulation = SimulationBuilder::new(4)
+        .scenario(
+            "DAG {
+                Round 0 : { 4 },
+                Round 1 : { * },
+                Round 2 : { * },
+                Round 3 : {
+                    A -> [-D2],
+                    B -> [*],
+                    C -> [*],
+                    D -> [*],
+                },
+                Round 4 : {
+                    A -> [*],
+                    B -> [*],
+                    C -> [*],
+                },
+            }",
+        )
+        .start()
+        .await;
+
+    simulation.wait_for_commits(10, TIMEOUT).await;
+    simulation.stop().await;
+}
+
+#[tokio::test]
+async fn test_withheld_blocks() {
+    let dag = "DAG {
+        Round 0 : { 4 },
+        Round 1 : { * },
+        Round 2 : {
+            A -> [-D1],
+            B -> [*],
+            C -> [*],
+            D -> [*],
+        },
+        Round 3 : {
+            A -> [*],
+            B -> [*],
+            C -> [*],
+        },
+    }";
+
+    let a = AuthorityIndex::new_for_test(0);
+    let b = AuthorityIndex::new_for_test(1);
+    let c = AuthorityIndex::new_for_test(2);
+    let d = AuthorityIndex::new_for_test(3);
+    assert_eq!(
+        withheld_blocks(dag, 4),
+        BTreeSet::from([(d, 1, a), (d, 3, a), (d, 3, b), (d, 3, c)])
+    );
+}
@@ -16,9 +16,14 @@ use crate::{
     Round,
 };
 
-/// The maximum number of transactions pending to the queue to be pulled for block proposal
+/// The maximum number of normal priority transactions pending to the queue to be pulled for block
+/// proposal.
 const MAX_PENDING_TRANSACTIONS: usize = 2_000;
 
+/// The maximum number of high or low priority transactions pending to the queue. These lanes carry
+/// far less traffic than the normal one, so they are sized separately to not take away from it.
+const MAX_PENDING_PRIORITY_TRANSACTIONS: usize = 500;
+
 /// The guard acts as an acknowledgment mechanism for the inclusion of the transactions to a block.
 /// When its last transaction is included to a block then `included_in_block_ack` will be signalled.
 /// If the guard is dropped without getting acknowledged that means the transactions have not been
@@ -31,15 +36,92 @@ pub(crate) struct TransactionsGuard {
     included_in_block_ack: oneshot::Sender<(BlockRef, oneshot::Receiver<BlockStatus>)>,
 }
 
+/// The priority of submitted transactions. Block proposals include transactions of higher priority
+/// first, and transactions of the same priority in the order they were submitted.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub enum TransactionPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+impl TransactionPriority {
+    /// All the priorities, from the highest to the lowest.
+    const ALL: [TransactionPriority; 3] = [
+        TransactionPriority::High,
+        TransactionPriority::Normal,
+        TransactionPriority::Low,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransactionPriority::Low => "low",
+            TransactionPriority::Normal => "normal",
+            TransactionPriority::High => "high",
+        }
+    }
+
+    // Index of the lane of the priority in `ALL`.
+    fn lane(&self) -> usize {
+        match self {
+            TransactionPriority::High => 0,
+            TransactionPriority::Normal => 1,
+            TransactionPriority::Low => 2,
+        }
+    }
+
+    // Capacity of the lane of the priority.
+    fn max_pending_transactions(&self) -> usize {
+        match self {
+            TransactionPriority::Normal => MAX_PENDING_TRANSACTIONS,
+            TransactionPriority::High | TransactionPriority::Low => {
+                MAX_PENDING_PRIORITY_TRANSACTIONS
+            }
+        }
+    }
+}
+
+/// The receiving side of the channels shared with the TransactionClient, one per priority.
+pub(crate) struct TransactionReceiver {
+    lanes: Vec<Receiver<TransactionsGuard>>,
+}
+
+/// The transactions of one priority waiting to be included in block proposals.
+struct TransactionLane {
+    priority: TransactionPriority,
+    tx_receiver: Receiver<TransactionsGuard>,
+    // Transactions pulled from the channel but not included yet to a block.
+    pending_transactions: Option<TransactionsGuard>,
+    // The number of consecutive block proposals that left out the pending transactions of the lane.
+    starved_proposals: u32,
+}
+
+impl TransactionLane {
+    fn next(&mut self) -> Option<TransactionsGuard> {
+        self.pending_transactions
+            .take()
+            .or_else(|| self.tx_receiver.try_recv().ok())
+    }
+
+    fn has_pending(&mut self) -> bool {
+        if self.pending_transactions.is_none() {
+            self.pending_transactions = self.tx_receiver.try_recv().ok();
+        }
+        self.pending_transactions.is_some()
+    }
+}
+
 /// The TransactionConsumer is responsible for fetching the next transactions to be included for the block proposals.
-/// The transactions are submitted to a channel which is shared between the TransactionConsumer and the TransactionClient
-/// and are pulled every time the `next` method is called.
+/// The transactions are submitted to channels, one per priority, which are shared between the TransactionConsumer and the
+/// TransactionClient and are pulled every time the `next` method is called.
 pub(crate) struct TransactionConsumer {
     context: Arc<Context>,
-    tx_receiver: Receiver<TransactionsGuard>,
+    // The lanes of the transactions, from the highest priority to the lowest.
+    lanes: Vec<TransactionLane>,
     max_transactions_in_block_bytes: u64,
     max_num_transactions_in_block: u64,
-    pending_transactions: Option<TransactionsGuard>,
+    max_starved_proposals: u32,
     block_status_subscribers: Arc<Mutex<BTreeMap<BlockRef, Vec<oneshot::Sender<BlockStatus>>>>>,
 }
 
@@ -65,21 +147,34 @@ pub enum LimitReached {
 }
 
 impl TransactionConsumer {
-    pub(crate) fn new(tx_receiver: Receiver<TransactionsGuard>, context: Arc<Context>) -> Self {
+    pub(crate) fn new(tx_receiver: TransactionReceiver, context: Arc<Context>) -> Self {
+        let lanes = TransactionPriority::ALL
+            .into_iter()
+            .zip(tx_receiver.lanes)
+            .map(|(priority, tx_receiver)| TransactionLane {
+                priority,
+                tx_receiver,
+                pending_transactions: None,
+                starved_proposals: 0,
+            })
+            .collect();
         Self {
-            tx_receiver,
+            lanes,
             max_transactions_in_block_bytes: context
                 .protocol_config
                 .max_transactions_in_block_bytes(),
             max_num_transactions_in_block: context.protocol_config.max_num_transactions_in_block(),
+            max_starved_proposals: context.parameters.max_starved_proposals,
             context,
-            pending_transactions: None,
             block_status_subscribers: Arc::new(Mutex::new(BTreeMap::new())),
         }
     }
 
     // Attempts to fetch the next transactions that have been submitted for sequence. Respects the `max_transactions_in_block_bytes`
     // and `max_num_transactions_in_block` parameters specified via protocol config.
+    // Transactions are pulled from the lanes of higher priority first, except for lanes which have been starved for
+    // `max_starved_proposals` proposals, which get one batch of transactions in before all the others. Capping this to
+    // one batch lets the lane make progress without crowding out the higher priorities.
     // This returns one or more transactions to be included in the block and a callback to acknowledge the inclusion of those transactions.
     // Also returns a `LimitReached` enum to indicate which limit type has been reached.
     pub(crate) fn next(&mut self) -> (Vec<Transaction>, Box<dyn FnOnce(BlockRef)>, LimitReached) {
@@ -87,45 +182,79 @@ impl TransactionConsumer {
         let mut acks = Vec::new();
         let mut total_bytes = 0;
         let mut limit_reached = LimitReached::AllTransactionsIncluded;
-
-        // Handle one batch of incoming transactions from TransactionGuard.
-        // The method will return `None` if all the transactions can be included in the block. Otherwise none of the transactions will be
-        // included in the block and the method will return the TransactionGuard.
-        let mut handle_txs = |t: TransactionsGuard| -> Option<TransactionsGuard> {
-            let transactions_bytes =
-                t.transactions.iter().map(|t| t.data().len()).sum::<usize>() as u64;
-            let transactions_num = t.transactions.len() as u64;
-
-            if total_bytes + transactions_bytes > self.max_transactions_in_block_bytes {
-                limit_reached = LimitReached::MaxBytes;
-                return Some(t);
-            }
-            if transactions.len() as u64 + transactions_num > self.max_num_transactions_in_block {
-                limit_reached = LimitReached::MaxNumOfTransactions;
-                return Some(t);
+        let mut included_per_lane = vec![0; self.lanes.len()];
+
+        // One batch of each starved lane goes first, then all the lanes by priority. The flag marks the passes over
+        // starved lanes.
+        let lanes_order: Vec<(usize, bool)> = (0..self.lanes.len())
+            .filter(|lane| self.lanes[*lane].starved_proposals >= self.max_starved_proposals)
+            .map(|lane| (lane, true))
+            .chain((0..self.lanes.len()).map(|lane| (lane, false)))
+            .collect();
+
+        'lanes: for (lane_index, starved) in lanes_order {
+            let lane = &mut self.lanes[lane_index];
+            if starved {
+                self.context
+                    .metrics
+                    .node_metrics
+                    .starved_transaction_priority_proposals
+                    .with_label_values(&[lane.priority.as_str()])
+                    .inc();
             }
 
-            total_bytes += transactions_bytes;
-
-            // The transactions can be consumed, register its ack.
-            acks.push(t.included_in_block_ack);
-            transactions.extend(t.transactions);
-            None
-        };
+            // Handle one batch of incoming transactions from TransactionGuard at a time. If a batch can not be included in
+            // the block, the pull stops and the batch is kept as pending for the next block.
+            while let Some(t) = lane.next() {
+                let transactions_bytes =
+                    t.transactions.iter().map(|t| t.data().len()).sum::<usize>() as u64;
+                let transactions_num = t.transactions.len() as u64;
+
+                if total_bytes + transactions_bytes > self.max_transactions_in_block_bytes {
+                    limit_reached = LimitReached::MaxBytes;
+                } else if transactions.len() as u64 + transactions_num
+                    > self.max_num_transactions_in_block
+                {
+                    limit_reached = LimitReached::MaxNumOfTransactions;
+                } else {
+                    total_bytes += transactions_bytes;
+                    included_per_lane[lane_index] += transactions_num;
+
+                    // The transactions can be consumed, register its ack.
+                    acks.push(t.included_in_block_ack);
+                    transactions.extend(t.transactions);
+                    if starved {
+                        continue 'lanes;
+                    }
+                    continue;
+                }
 
-        if let Some(t) = self.pending_transactions.take() {
-            if let Some(pending_transactions) = handle_txs(t) {
-                debug_fatal!("Previously pending transaction(s) should fit into an empty block! Dropping: {:?}", pending_transactions.transactions);
+                if transactions.is_empty() {
+                    debug_fatal!(
+                        "Pending transaction(s) should fit into an empty block! Dropping: {:?}",
+                        t.transactions
+                    );
+                    continue;
+                }
+                lane.pending_transactions = Some(t);
+                break 'lanes;
             }
         }
 
-        // Until we have reached the limit for the pull.
-        // We may have already reached limit in the first iteration above, in which case we stop immediately.
-        while self.pending_transactions.is_none() {
-            if let Ok(t) = self.tx_receiver.try_recv() {
-                self.pending_transactions = handle_txs(t);
+        for (lane, included) in self.lanes.iter_mut().zip(included_per_lane) {
+            self.context
+                .metrics
+                .node_metrics
+                .proposed_transactions_by_priority
+                .with_label_values(&[lane.priority.as_str()])
+                .inc_by(included);
+            if included == 0
+                && limit_reached != LimitReached::AllTransactionsIncluded
+                && lane.has_pending()
+            {
+                lane.starved_proposals += 1;
             } else {
-                break;
+                lane.starved_proposals = 0;
             }
         }
 
@@ -204,20 +333,14 @@ impl TransactionConsumer {
 
     #[cfg(test)]
     fn is_empty(&mut self) -> bool {
-        if self.pending_transactions.is_some() {
-            return false;
-        }
-        if let Ok(t) = self.tx_receiver.try_recv() {
-            self.pending_transactions = Some(t);
-            return false;
-        }
-        true
+        self.lanes.iter_mut().all(|lane| !lane.has_pending())
     }
 }
 
 #[derive(Clone)]
 pub struct TransactionClient {
-    sender: Sender<TransactionsGuard>,
+    // The senders of the transactions, from the highest priority to the lowest.
+    senders: Vec<Sender<TransactionsGuard>>,
     max_transaction_size: u64,
     max_transactions_in_block_bytes: u64,
     max_transactions_in_block_count: u64,
@@ -239,12 +362,20 @@ pub enum ClientError {
 }
 
 impl TransactionClient {
-    pub(crate) fn new(context: Arc<Context>) -> (Self, Receiver<TransactionsGuard>) {
-        let (sender, receiver) = channel("consensus_input", MAX_PENDING_TRANSACTIONS);
+    pub(crate) fn new(context: Arc<Context>) -> (Self, TransactionReceiver) {
+        let (senders, lanes) = TransactionPriority::ALL
+            .iter()
+            .map(|priority| {
+                channel(
+                    &format!("consensus_input_{}", priority.as_str()),
+                    priority.max_pending_transactions(),
+                )
+            })
+            .unzip();
 
         (
             Self {
-                sender,
+                senders,
                 max_transaction_size: context.protocol_config.max_transaction_size_bytes(),
                 max_transactions_in_block_bytes: context
                     .protocol_config
@@ -253,18 +384,31 @@ impl TransactionClient {
                     .protocol_config
                     .max_num_transactions_in_block(),
             },
-            receiver,
+            TransactionReceiver { lanes },
         )
     }
 
-    /// Submits a list of transactions to be sequenced. The method returns when all the transactions have been successfully included
-    /// to next proposed blocks.
+    /// Submits a list of transactions to be sequenced with the default priority. The method returns when all the transactions
+    /// have been successfully included to next proposed blocks.
     pub async fn submit(
         &self,
         transactions: Vec<Vec<u8>>,
+    ) -> Result<(BlockRef, oneshot::Receiver<BlockStatus>), ClientError> {
+        self.submit_with_priority(transactions, TransactionPriority::default())
+            .await
+    }
+
+    /// Submits a list of transactions to be sequenced with the given priority. The method returns when all the transactions
+    /// have been successfully included to next proposed blocks.
+    pub async fn submit_with_priority(
+        &self,
+        transactions: Vec<Vec<u8>>,
+        priority: TransactionPriority,
     ) -> Result<(BlockRef, oneshot::Receiver<BlockStatus>), ClientError> {
         // TODO: Support returning the block refs for transactions that span multiple blocks
-        let included_in_block = self.submit_no_wait(transactions).await?;
+        let included_in_block = self
+            .submit_no_wait_with_priority(transactions, priority)
+            .await?;
         included_in_block
             .await
             .tap_err(|e| warn!("Transaction acknowledge failed with {:?}", e))
@@ -280,9 +424,10 @@ impl TransactionClient {
     /// If multiple transactions are submitted, the method will attempt to bundle them together in a single block. If the total size of
     /// the transactions exceeds `max_transactions_in_block_bytes`, no transaction will be submitted and an error will be returned instead.
     /// Similar if transactions exceed `max_transactions_in_block_count` an error will be returned.
-    pub(crate) async fn submit_no_wait(
+    pub(crate) async fn submit_no_wait_with_priority(
         &self,
         transactions: Vec<Vec<u8>>,
+        priority: TransactionPriority,
     ) -> Result<oneshot::Receiver<(BlockRef, oneshot::Receiver<BlockStatus>)>, ClientError> {
         let (included_in_block_ack_send, included_in_block_ack_receive) = oneshot::channel();
 
@@ -316,13 +461,22 @@ impl TransactionClient {
             transactions: transactions.into_iter().map(Transaction::new).collect(),
             included_in_block_ack: included_in_block_ack_send,
         };
-        self.sender
+        self.senders[priority.lane()]
             .send(t)
             .await
             .tap_err(|e| error!("Submit transactions failed with {:?}", e))
             .map_err(|e| ClientError::ConsensusShuttingDown(e.to_string()))?;
         Ok(included_in_block_ack_receive)
     }
+
+    #[cfg(test)]
+    pub(crate) async fn submit_no_wait(
+        &self,
+        transactions: Vec<Vec<u8>>,
+    ) -> Result<oneshot::Receiver<(BlockRef, oneshot::Receiver<BlockStatus>)>, ClientError> {
+        self.submit_no_wait_with_priority(transactions, TransactionPriority::default())
+            .await
+    }
 }
 
 /// `TransactionVerifier` implementation is supplied by Mys to validate transactions in a block,
@@ -372,7 +526,7 @@ impl TransactionVerifier for NoopTransactionVerifier {
 mod tests {
     use std::{sync::Arc, time::Duration};
 
-    use consensus_config::AuthorityIndex;
+    use consensus_config::{AuthorityIndex, Parameters};
     use futures::{stream::FuturesUnordered, StreamExt};
     use mys_protocol_config::ProtocolConfig;
     use tokio::time::timeout;
@@ -382,7 +536,9 @@ mod tests {
         block::{BlockDigest, BlockRef},
         block_verifier::SignedBlockVerifier,
         context::Context,
-        transaction::{BlockStatus, LimitReached, TransactionClient, TransactionConsumer},
+        transaction::{
+            BlockStatus, LimitReached, TransactionClient, TransactionConsumer, TransactionPriority,
+        },
     };
 
     #[tokio::test(flavor = "current_thread", start_paused = true)]
@@ -619,6 +775,130 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn submit_with_priorities_and_consume() {
+        let context = Arc::new(Context::new_for_test(4).0);
+        let (client, tx_receiver) = TransactionClient::new(context.clone());
+        let mut consumer = TransactionConsumer::new(tx_receiver, context.clone());
+
+        // submit transactions from the lowest priority to the highest
+        for priority in [
+            TransactionPriority::Low,
+            TransactionPriority::Normal,
+            TransactionPriority::High,
+        ] {
+            for i in 0..3 {
+                let transaction = bcs::to_bytes(&format!("{} transaction {i}", priority.as_str()))
+                    .expect("Serialization should not fail.");
+                let _w = client
+                    .submit_no_wait_with_priority(vec![transaction], priority)
+                    .await
+                    .expect("Shouldn't submit successfully transaction");
+            }
+        }
+
+        // the transactions are included from the highest priority to the lowest
+        let (transactions, _ack_transactions, limit_reached) = consumer.next();
+        assert_eq!(limit_reached, LimitReached::AllTransactionsIncluded);
+        let transactions: Vec<String> = transactions
+            .iter()
+            .map(|t| bcs::from_bytes(t.data()).unwrap())
+            .collect();
+        let expected: Vec<String> = ["high", "normal", "low"]
+            .into_iter()
+            .flat_map(|priority| (0..3).map(move |i| format!("{priority} transaction {i}")))
+            .collect();
+        assert_eq!(transactions, expected);
+
+        assert_eq!(
+            context
+                .metrics
+                .node_metrics
+                .proposed_transactions_by_priority
+                .with_label_values(&["high"])
+                .get(),
+            3
+        );
+        assert!(consumer.is_empty());
+    }
+
+    #[tokio::test]
+    async fn submit_with_priorities_and_consume_starved() {
+        let _guard = ProtocolConfig::apply_overrides_for_testing(|_, mut config| {
+            config.set_consensus_max_transaction_size_bytes_for_testing(100);
+            config.set_consensus_max_transactions_in_block_bytes_for_testing(100);
+            config
+        });
+
+        let (context, _keys) = Context::new_for_test(4);
+        let context = Arc::new(context.with_parameters(Parameters {
+            max_starved_proposals: 3,
+            ..Default::default()
+        }));
+        let (client, tx_receiver) = TransactionClient::new(context.clone());
+        let mut consumer = TransactionConsumer::new(tx_receiver, context.clone());
+
+        // Two batches of low priority transactions.
+        for index in 0..2 {
+            let low_transaction = bcs::to_bytes(&format!("transaction l{}", index))
+                .expect("Serialization should not fail.");
+            let _w = client
+                .submit_no_wait_with_priority(vec![low_transaction], TransactionPriority::Low)
+                .await
+                .expect("Shouldn't submit successfully transaction");
+        }
+
+        // Keep filling the blocks with high priority transactions. 7 of them fit in a block.
+        let max_starved_proposals = context.parameters.max_starved_proposals;
+        let mut index = 0;
+        for proposal in 0..=max_starved_proposals {
+            for _ in 0..7 {
+                let transaction = bcs::to_bytes(&format!("transaction {}", index % 10))
+                    .expect("Serialization should not fail.");
+                let _w = client
+                    .submit_no_wait_with_priority(vec![transaction], TransactionPriority::High)
+                    .await
+                    .expect("Shouldn't submit successfully transaction");
+                index += 1;
+            }
+
+            let (transactions, _ack_transactions, limit_reached) = consumer.next();
+            assert_eq!(limit_reached, LimitReached::MaxBytes);
+            assert_eq!(transactions.len(), 7);
+            let included: Vec<String> = transactions
+                .iter()
+                .map(|t| bcs::from_bytes(t.data()).unwrap())
+                .collect();
+            if proposal < max_starved_proposals {
+                // the low priority transactions are left out
+                assert!(!included.iter().any(|t| t.starts_with("transaction l")));
+            } else {
+                // the low priority transactions have been starved, so one batch of them is included first
+                assert_eq!(included[0], "transaction l0");
+                assert!(!included[1..].iter().any(|t| t.starts_with("transaction l")));
+            }
+        }
+
+        assert_eq!(
+            context
+                .metrics
+                .node_metrics
+                .starved_transaction_priority_proposals
+                .with_label_values(&["low"])
+                .get(),
+            1
+        );
+        assert_eq!(
+            context
+                .metrics
+                .node_metrics
+                .proposed_transactions_by_priority
+                .with_label_values(&["low"])
+                .get(),
+            1
+        );
+    }
+
     #[tokio::test]
     async fn submit_large_batch_and_ack() {
         let _guard = ProtocolConfig::apply_overrides_for_testing(|_, mut config| {
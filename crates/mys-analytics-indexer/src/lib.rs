@@ -57,6 +57,8 @@ mod package_store;
 pub mod tables;
 mod writers;
 
+pub use writers::parquet_writer::ParquetFileWriter;
+
 const EPOCH_DIR_PREFIX: &str = "epoch_";
 const CHECKPOINT_DIR_PREFIX: &str = "checkpoints";
 const OBJECT_DIR_PREFIX: &str = "objects";
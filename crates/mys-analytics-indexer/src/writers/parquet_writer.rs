@@ -7,6 +7,7 @@ use anyhow::{anyhow, Result};
 use arrow_array::{ArrayRef, BooleanArray, Int64Array, RecordBatch, StringArray, UInt64Array};
 use serde::Serialize;
 use std::fs::File;
+use std::marker::PhantomData;
 use std::fs::{create_dir_all, remove_file};
 use std::ops::Range;
 use std::path::{Path, PathBuf};
@@ -81,6 +82,26 @@ macro_rules! convert_to_arrow_array {
     };
 }
 
+fn writer_properties() -> WriterProperties {
+    WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build()
+}
+
+/// Converts column-major values into a record batch following the schema of `S`. Every column
+/// must be non-empty.
+fn to_record_batch<S: ParquetSchema>(data: Vec<Vec<ParquetValue>>) -> Result<RecordBatch> {
+    let mut batch_data = vec![];
+    for column in data {
+        convert_to_arrow_array!(column, batch_data,
+            ParquetValue::U64 => UInt64Array, ParquetValue::Str => StringArray, ParquetValue::OptionU64 => UInt64Array, ParquetValue::OptionStr => StringArray, ParquetValue::Bool => BooleanArray, ParquetValue::I64 => Int64Array
+        );
+    }
+    Ok(RecordBatch::try_from_iter(
+        S::schema().iter().zip(batch_data.into_iter()),
+    )?)
+}
+
 impl<S: Serialize + ParquetSchema> AnalyticsWriter<S> for ParquetWriter {
     fn file_format(&self) -> Result<FileFormat> {
         Ok(FileFormat::PARQUET)
@@ -103,19 +124,9 @@ impl<S: Serialize + ParquetSchema> AnalyticsWriter<S> for ParquetWriter {
             return Ok(false);
         }
         self.checkpoint_range.end = end_checkpoint_seq_num;
-        let mut batch_data = vec![];
-        for column in std::mem::take(&mut self.data) {
-            convert_to_arrow_array!(column, batch_data,
-                ParquetValue::U64 => UInt64Array, ParquetValue::Str => StringArray, ParquetValue::OptionU64 => UInt64Array, ParquetValue::OptionStr => StringArray, ParquetValue::Bool => BooleanArray, ParquetValue::I64 => Int64Array
-            );
-        }
-        let batch = RecordBatch::try_from_iter(S::schema().iter().zip(batch_data.into_iter()))?;
-
-        let properties = WriterProperties::builder()
-            .set_compression(Compression::SNAPPY)
-            .build();
-
-        let mut writer = ArrowWriter::try_new(self.file()?, batch.schema(), Some(properties))?;
+        let batch = to_record_batch::<S>(std::mem::take(&mut self.data))?;
+        let mut writer =
+            ArrowWriter::try_new(self.file()?, batch.schema(), Some(writer_properties()))?;
         writer.write(&batch)?;
         writer.close()?;
         Ok(true)
@@ -136,3 +147,57 @@ impl<S: Serialize + ParquetSchema> AnalyticsWriter<S> for ParquetWriter {
         Ok(None)
     }
 }
+
+/// Streams rows into a single parquet file at a given path, one row group per call to `write`.
+/// Unlike `ParquetWriter` this is not tied to the checkpoint based file layout of the analytics
+/// pipeline, so that tools can export arbitrary rows using the same schema conversion.
+pub struct ParquetFileWriter<S> {
+    path: PathBuf,
+    writer: Option<ArrowWriter<File>>,
+    num_rows: usize,
+    _row: PhantomData<S>,
+}
+
+impl<S: ParquetSchema> ParquetFileWriter<S> {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            writer: None,
+            num_rows: 0,
+            _row: PhantomData,
+        }
+    }
+
+    pub fn write(&mut self, rows: &[S]) -> Result<()> {
+        if rows.is_empty() {
+            return Ok(());
+        }
+        let data = (0..S::schema().len())
+            .map(|col_idx| rows.iter().map(|row| row.get_column(col_idx)).collect())
+            .collect();
+        let batch = to_record_batch::<S>(data)?;
+        let writer = match &mut self.writer {
+            Some(writer) => writer,
+            // The arrow schema is only known once the first batch is converted
+            None => self.writer.insert(ArrowWriter::try_new(
+                File::create(&self.path)?,
+                batch.schema(),
+                Some(writer_properties()),
+            )?),
+        };
+        writer.write(&batch)?;
+        // Flush to keep memory bounded by the size of a single batch
+        writer.flush()?;
+        self.num_rows += rows.len();
+        Ok(())
+    }
+
+    /// Finishes the file and returns the number of rows written. No file is created if no rows
+    /// were written.
+    pub fn close(self) -> Result<usize> {
+        if let Some(writer) = self.writer {
+            writer.close()?;
+        }
+        Ok(self.num_rows)
+    }
+}
@@ -11,13 +11,17 @@ mod tests;
 use crate::reader::{ArchiveReader, ArchiveReaderMetrics};
 use anyhow::{anyhow, Result};
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
-use bytes::Bytes;
+use bytes::buf::Reader;
+use bytes::{Buf, Bytes};
 use fastcrypto::hash::{HashFunction, Sha3_256};
+use futures::{StreamExt, TryStreamExt};
 use indicatif::{ProgressBar, ProgressStyle};
 use num_enum::IntoPrimitive;
 use num_enum::TryFromPrimitive;
 use object_store::path::Path;
+use object_store::DynObjectStore;
 use prometheus::Registry;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io::{BufWriter, Cursor, Read, Seek, SeekFrom, Write};
@@ -32,9 +36,15 @@ use mys_config::object_storage_config::ObjectStoreConfig;
 use mys_storage::blob::{Blob, BlobEncoding};
 use mys_storage::object_store::util::{get, put};
 use mys_storage::object_store::{ObjectStoreGetExt, ObjectStorePutExt};
-use mys_storage::{compute_sha3_checksum, compute_sha3_checksum_for_bytes, SHA3_BYTES};
+use mys_storage::{
+    compute_sha3_checksum, compute_sha3_checksum_for_bytes, make_iterator, FileCompression,
+    StorageFormat, SHA3_BYTES,
+};
 use mys_types::base_types::ExecutionData;
-use mys_types::messages_checkpoint::{FullCheckpointContents, VerifiedCheckpointContents};
+use mys_types::messages_checkpoint::{
+    CertifiedCheckpointSummary, CheckpointSequenceNumber, FullCheckpointContents,
+    VerifiedCheckpointContents,
+};
 use mys_types::storage::{SingleCheckpointSharedInMemoryStore, WriteStore};
 use tracing::{error, info};
 
@@ -45,6 +55,14 @@ use tracing::{error, info};
 /// <checkpoint_seq_num>.<suffix> where `checkpoint_seq_num` is the first checkpoint present in that
 /// file. MANIFEST is the index and source of truth for all files present in the archive.
 ///
+/// Starting with archive version 2 every blob is written as its own zstd frame and each pair of
+/// checkpoint and summary files comes with a <checkpoint_seq_num>.idx file holding the byte range
+/// of every checkpoint in both files. A single checkpoint can then be fetched with two range
+/// requests instead of downloading and decompressing whole files. Concatenated zstd frames decode
+/// as one stream, so readers unaware of the index read version 2 files just like before. Files of
+/// version 2 archives are named <checkpoint_seq_num>.v2.<suffix>, so that an archive converted to
+/// version 2 never overwrites the files its previous manifest points to.
+///
 /// State Archival Directory Layout
 ///  - archive/
 ///     - MANIFEST
@@ -62,6 +80,15 @@ use tracing::{error, info};
 ///        - 101000.chk
 ///        - ...
 ///
+/// Version 2 Directory Layout
+///  - archive/
+///     - MANIFEST
+///     - epoch_0/
+///        - 0.v2.chk
+///        - 0.v2.sum
+///        - 0.idx
+///        - ...
+///
 /// Blob File Disk Format
 ///┌──────────────────────────────┐
 ///│       magic <4 byte>         │
@@ -83,6 +110,24 @@ use tracing::{error, info};
 ///│ len <uvarint> │ encoding <1 byte> │ data <bytes> │
 ///└───────────────┴───────────────────┴──────────────┘
 ///
+/// Index File Disk Format
+///┌──────────────────────────────┐
+///│       magic <4 byte>         │
+///├──────────────────────────────┤
+///│    file compression <1 byte> │
+///├──────────────────────────────┤
+///│  start checkpoint <8 byte>   │
+///├──────────────────────────────┤
+///│ ┌──────────────────────────┐ │
+///│ │  content range <16 byte> │ │
+///│ │  summary range <16 byte> │ │
+///│ ├──────────────────────────┤ │
+///│ │          ...             │ │
+///│ └──────────────────────────┘ │
+///├──────────────────────────────┤
+///│      sha3 <32 bytes>         │
+///└──────────────────────────────┘
+///
 /// MANIFEST File Disk Format
 ///┌──────────────────────────────┐
 ///│        magic<4 byte>         │
@@ -94,11 +139,22 @@ use tracing::{error, info};
 pub const CHECKPOINT_FILE_MAGIC: u32 = 0x0000DEAD;
 pub const SUMMARY_FILE_MAGIC: u32 = 0x0000CAFE;
 const MANIFEST_FILE_MAGIC: u32 = 0x00C0FFEE;
+const INDEX_FILE_MAGIC: u32 = 0x0000BEEF;
 const MAGIC_BYTES: usize = 4;
+// magic, storage format and file compression
+const FILE_HEADER_BYTES: usize = MAGIC_BYTES + 2;
+// start and end offsets of the content and summary blobs
+const INDEX_ENTRY_BYTES: usize = 32;
 const CHECKPOINT_FILE_SUFFIX: &str = "chk";
 const SUMMARY_FILE_SUFFIX: &str = "sum";
+const INDEX_FILE_SUFFIX: &str = "idx";
+const INDEXED_FILE_INFIX: &str = "v2";
 const EPOCH_DIR_PREFIX: &str = "epoch_";
 const MANIFEST_FILENAME: &str = "MANIFEST";
+/// Blob files are compressed as a whole
+pub const ARCHIVE_VERSION_V1: u8 = 1;
+/// Blobs are compressed one frame each and every file pair has an offset index
+pub const ARCHIVE_VERSION_V2: u8 = 2;
 
 #[derive(
     Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize, TryFromPrimitive, IntoPrimitive,
@@ -118,6 +174,7 @@ pub struct FileMetadata {
 }
 
 impl FileMetadata {
+    /// Path of the file in an archive of version 1, see `Manifest::file_path`
     pub fn file_path(&self) -> Path {
         let dir_path = Path::from(format!("{}{}", EPOCH_DIR_PREFIX, self.epoch_num));
         match self.file_type {
@@ -131,6 +188,27 @@ impl FileMetadata {
             )),
         }
     }
+
+    /// Path of the file in an indexed archive
+    pub fn indexed_file_path(&self) -> Path {
+        let dir_path = Path::from(format!("{}{}", EPOCH_DIR_PREFIX, self.epoch_num));
+        let suffix = match self.file_type {
+            FileType::CheckpointContent => CHECKPOINT_FILE_SUFFIX,
+            FileType::CheckpointSummary => SUMMARY_FILE_SUFFIX,
+        };
+        dir_path.child(&*format!(
+            "{}.{INDEXED_FILE_INFIX}.{suffix}",
+            self.checkpoint_seq_range.start
+        ))
+    }
+
+    /// Path of the offset index shared by the checkpoint and summary files of this range
+    pub fn index_file_path(&self) -> Path {
+        Path::from(format!("{}{}", EPOCH_DIR_PREFIX, self.epoch_num)).child(&*format!(
+            "{}.{INDEX_FILE_SUFFIX}",
+            self.checkpoint_seq_range.start
+        ))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
@@ -149,12 +227,34 @@ pub enum Manifest {
 impl Manifest {
     pub fn new(epoch: u64, next_checkpoint_seq_num: u64) -> Self {
         Manifest::V1(ManifestV1 {
-            archive_version: 1,
+            archive_version: ARCHIVE_VERSION_V2,
             next_checkpoint_seq_num,
             file_metadata: vec![],
             epoch,
         })
     }
+    pub fn archive_version(&self) -> u8 {
+        match self {
+            Manifest::V1(manifest) => manifest.archive_version,
+        }
+    }
+    /// Whether every file pair in the archive has an offset index
+    pub fn is_indexed(&self) -> bool {
+        self.archive_version() >= ARCHIVE_VERSION_V2
+    }
+    /// Path of the given file of this archive, which depends on the archive version
+    pub fn file_path(&self, file_metadata: &FileMetadata) -> Path {
+        if self.is_indexed() {
+            file_metadata.indexed_file_path()
+        } else {
+            file_metadata.file_path()
+        }
+    }
+    pub fn set_archive_version(&mut self, archive_version: u8) {
+        match self {
+            Manifest::V1(manifest) => manifest.archive_version = archive_version,
+        }
+    }
     pub fn files(&self) -> Vec<FileMetadata> {
         match self {
             Manifest::V1(manifest) => manifest.file_metadata.clone(),
@@ -209,6 +309,220 @@ impl Manifest {
             }
         }
     }
+    /// Replace the metadata of the file with the same type and checkpoint range
+    pub fn replace_file_metadata(&mut self, file_metadata: FileMetadata) -> Result<()> {
+        match self {
+            Manifest::V1(manifest) => {
+                let existing = manifest
+                    .file_metadata
+                    .iter_mut()
+                    .find(|f| {
+                        f.file_type == file_metadata.file_type
+                            && f.checkpoint_seq_range == file_metadata.checkpoint_seq_range
+                    })
+                    .ok_or(anyhow!(
+                        "No file in manifest for: {:?}",
+                        file_metadata.file_path()
+                    ))?;
+                *existing = file_metadata;
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CheckpointIndexEntry {
+    pub content: Range<u64>,
+    pub summary: Range<u64>,
+}
+
+/// Byte ranges of the blob frames of every checkpoint in a pair of checkpoint and summary files
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CheckpointIndex {
+    pub file_compression: FileCompression,
+    pub start_checkpoint: CheckpointSequenceNumber,
+    pub entries: Vec<CheckpointIndexEntry>,
+}
+
+impl CheckpointIndex {
+    pub fn new(
+        file_compression: FileCompression,
+        start_checkpoint: CheckpointSequenceNumber,
+    ) -> Self {
+        CheckpointIndex {
+            file_compression,
+            start_checkpoint,
+            entries: vec![],
+        }
+    }
+    pub fn checkpoint_range(&self) -> Range<CheckpointSequenceNumber> {
+        self.start_checkpoint..self.start_checkpoint + self.entries.len() as u64
+    }
+    pub fn entry(&self, checkpoint: CheckpointSequenceNumber) -> Option<&CheckpointIndexEntry> {
+        checkpoint
+            .checked_sub(self.start_checkpoint)
+            .and_then(|offset| self.entries.get(offset as usize))
+    }
+    pub fn to_bytes(&self) -> Result<Bytes> {
+        let mut buf = BufWriter::new(vec![]);
+        buf.write_u32::<BigEndian>(INDEX_FILE_MAGIC)?;
+        buf.write_u8(self.file_compression.into())?;
+        buf.write_u64::<BigEndian>(self.start_checkpoint)?;
+        for entry in self.entries.iter() {
+            buf.write_u64::<BigEndian>(entry.content.start)?;
+            buf.write_u64::<BigEndian>(entry.content.end)?;
+            buf.write_u64::<BigEndian>(entry.summary.start)?;
+            buf.write_u64::<BigEndian>(entry.summary.end)?;
+        }
+        buf.flush()?;
+        let mut hasher = Sha3_256::default();
+        hasher.update(buf.get_ref());
+        let computed_digest = hasher.finalize().digest;
+        buf.write_all(&computed_digest)?;
+        Ok(Bytes::from(buf.into_inner()?))
+    }
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let header_bytes = MAGIC_BYTES + 1 + 8;
+        if bytes.len() < header_bytes + SHA3_BYTES
+            || (bytes.len() - header_bytes - SHA3_BYTES) % INDEX_ENTRY_BYTES != 0
+        {
+            return Err(anyhow!("Unexpected index file size: {}", bytes.len()));
+        }
+        let (content, sha3_digest) = bytes.split_at(bytes.len() - SHA3_BYTES);
+        let mut hasher = Sha3_256::default();
+        hasher.update(content);
+        let computed_digest = hasher.finalize().digest;
+        if computed_digest != sha3_digest {
+            return Err(anyhow!(
+                "Index corrupted, computed checksum: {:?}, stored checksum: {:?}",
+                computed_digest,
+                sha3_digest
+            ));
+        }
+        let mut reader = Cursor::new(content);
+        let magic = reader.read_u32::<BigEndian>()?;
+        if magic != INDEX_FILE_MAGIC {
+            return Err(anyhow!("Unexpected magic byte in index: {}", magic));
+        }
+        let file_compression = FileCompression::try_from(reader.read_u8()?)?;
+        let start_checkpoint = reader.read_u64::<BigEndian>()?;
+        let num_entries = (content.len() - header_bytes) / INDEX_ENTRY_BYTES;
+        let mut entries = Vec::with_capacity(num_entries);
+        for _ in 0..num_entries {
+            let content = reader.read_u64::<BigEndian>()?..reader.read_u64::<BigEndian>()?;
+            let summary = reader.read_u64::<BigEndian>()?..reader.read_u64::<BigEndian>()?;
+            entries.push(CheckpointIndexEntry { content, summary });
+        }
+        Ok(CheckpointIndex {
+            file_compression,
+            start_checkpoint,
+            entries,
+        })
+    }
+}
+
+/// Checkpoint, summary and index files of archive version 2 encoded in memory
+pub struct IndexedFiles {
+    pub checkpoint_range: Range<CheckpointSequenceNumber>,
+    pub contents: Bytes,
+    pub summaries: Bytes,
+    pub index: Bytes,
+}
+
+/// Builds the files of a checkpoint range in the format of archive version 2 in memory
+pub struct IndexedFilesBuilder {
+    contents: Vec<u8>,
+    summaries: Vec<u8>,
+    index: CheckpointIndex,
+}
+
+impl IndexedFilesBuilder {
+    pub fn new(
+        start_checkpoint: CheckpointSequenceNumber,
+        file_compression: FileCompression,
+    ) -> Result<Self> {
+        let mut contents = vec![];
+        write_file_header(&mut contents, CHECKPOINT_FILE_MAGIC, file_compression)?;
+        let mut summaries = vec![];
+        write_file_header(&mut summaries, SUMMARY_FILE_MAGIC, file_compression)?;
+        Ok(IndexedFilesBuilder {
+            contents,
+            summaries,
+            index: CheckpointIndex::new(file_compression, start_checkpoint),
+        })
+    }
+    pub fn append(
+        &mut self,
+        contents: &FullCheckpointContents,
+        summary: &CertifiedCheckpointSummary,
+    ) -> Result<()> {
+        if summary.sequence_number != self.index.checkpoint_range().end {
+            return Err(anyhow!(
+                "Expected checkpoint: {}, got: {}",
+                self.index.checkpoint_range().end,
+                summary.sequence_number
+            ));
+        }
+        let file_compression = self.index.file_compression;
+        let content_start = self.contents.len() as u64;
+        let contents_blob = Blob::encode(contents, BlobEncoding::Bcs)?;
+        let content_len = write_blob_frame(&mut self.contents, &contents_blob, file_compression)?;
+        let summary_start = self.summaries.len() as u64;
+        let summary_blob = Blob::encode(summary, BlobEncoding::Bcs)?;
+        let summary_len = write_blob_frame(&mut self.summaries, &summary_blob, file_compression)?;
+        self.index.entries.push(CheckpointIndexEntry {
+            content: content_start..content_start + content_len,
+            summary: summary_start..summary_start + summary_len,
+        });
+        Ok(())
+    }
+    pub fn finish(self) -> Result<IndexedFiles> {
+        Ok(IndexedFiles {
+            checkpoint_range: self.index.checkpoint_range(),
+            index: self.index.to_bytes()?,
+            contents: Bytes::from(self.contents),
+            summaries: Bytes::from(self.summaries),
+        })
+    }
+}
+
+/// Write the magic, storage format and file compression header of a blob file
+pub fn write_file_header<W: Write>(
+    writer: &mut W,
+    magic: u32,
+    file_compression: FileCompression,
+) -> Result<()> {
+    writer.write_u32::<BigEndian>(magic)?;
+    writer.write_u8(StorageFormat::Blob.into())?;
+    writer.write_u8(file_compression.into())?;
+    Ok(())
+}
+
+/// Write the blob as a frame which can be decompressed on its own and return its size in bytes
+pub fn write_blob_frame<W: Write>(
+    writer: &mut W,
+    blob: &Blob,
+    file_compression: FileCompression,
+) -> Result<u64> {
+    let mut frame = vec![];
+    blob.write(&mut frame)?;
+    if file_compression == FileCompression::Zstd {
+        let mut compressed = vec![];
+        FileCompression::zstd_compress(&mut Cursor::new(frame), &mut compressed)?;
+        frame = compressed;
+    }
+    writer.write_all(&frame)?;
+    Ok(frame.len() as u64)
+}
+
+/// Decode a single blob frame fetched with the byte range from a checkpoint index
+pub fn read_blob_frame<T: DeserializeOwned>(
+    bytes: Bytes,
+    file_compression: FileCompression,
+) -> Result<T> {
+    let mut reader = file_compression.bytes_decompress(bytes)?;
+    Blob::read(&mut reader)?.decode()
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
@@ -238,12 +552,23 @@ impl CheckpointUpdates {
             manifest: manifest.clone(),
         }
     }
+    /// Remote path of the checkpoint file, the local file is staged under `FileMetadata::file_path`
     pub fn content_file_path(&self) -> Path {
-        self.checkpoint_file_metadata.file_path()
+        self.manifest.file_path(&self.checkpoint_file_metadata)
     }
+    /// Remote path of the summary file, the local file is staged under `FileMetadata::file_path`
     pub fn summary_file_path(&self) -> Path {
+        self.manifest.file_path(&self.summary_file_metadata)
+    }
+    pub fn staged_content_file_path(&self) -> Path {
+        self.checkpoint_file_metadata.file_path()
+    }
+    pub fn staged_summary_file_path(&self) -> Path {
         self.summary_file_metadata.file_path()
     }
+    pub fn index_file_path(&self) -> Path {
+        self.checkpoint_file_metadata.index_file_path()
+    }
     pub fn manifest_file_path(&self) -> Path {
         Path::from(MANIFEST_FILENAME)
     }
@@ -429,11 +754,11 @@ pub async fn verify_archive_with_checksums(
         manifest.next_checkpoint_seq_num()
     );
 
-    let file_metadata = archive_reader.verify_manifest(manifest).await?;
+    let file_metadata = archive_reader.verify_manifest(manifest.clone()).await?;
     // Account for both summary and content files
     let num_files = file_metadata.len() * 2;
     archive_reader
-        .verify_file_consistency(file_metadata)
+        .verify_file_consistency(&manifest, file_metadata)
         .await?;
     info!("All {} files are valid", num_files);
     Ok(())
@@ -529,3 +854,130 @@ where
     info!("Highest verified checkpoint: {}", end);
     Ok(())
 }
+
+/// Rewrite an archive in the random access format of archive version 2. Converted file pairs are
+/// written next to the original ones under the names of version 2 files, and the manifest switches
+/// to them with a single update once all pairs are converted. Readers keep reading the original
+/// files, whose checksums stay valid, until they pick up the new manifest, after which the original
+/// files can be deleted. Archive writers must be stopped while converting as they would overwrite
+/// the manifest.
+pub async fn convert_archive_to_indexed(
+    remote_store_config: ObjectStoreConfig,
+    concurrency: usize,
+) -> Result<()> {
+    let metrics = ArchiveReaderMetrics::new(&Registry::default());
+    let config = ArchiveReaderConfig {
+        remote_store_config: remote_store_config.clone(),
+        download_concurrency: NonZeroUsize::new(concurrency).unwrap(),
+        use_for_pruning_watermark: false,
+    };
+    let archive_reader = ArchiveReader::new(config, &metrics)?;
+    archive_reader.sync_manifest_once().await?;
+    let mut manifest = archive_reader.get_manifest().await?;
+    if manifest.is_indexed() {
+        info!(
+            "Archive is already at version: {}",
+            manifest.archive_version()
+        );
+        return Ok(());
+    }
+    let remote_object_store = remote_store_config.make()?;
+    let files = archive_reader.verify_manifest(manifest.clone()).await?;
+    let num_files = files.len();
+    let mut converted = futures::stream::iter(files.into_iter())
+        .map(|(summary_metadata, content_metadata)| {
+            convert_file_pair(
+                remote_object_store.clone(),
+                summary_metadata,
+                content_metadata,
+            )
+        })
+        .buffer_unordered(concurrency);
+    while let Some((summary_metadata, content_metadata)) = converted.try_next().await? {
+        info!(
+            "Converted files for checkpoints: {:?}",
+            summary_metadata.checkpoint_seq_range
+        );
+        manifest.replace_file_metadata(summary_metadata)?;
+        manifest.replace_file_metadata(content_metadata)?;
+    }
+    manifest.set_archive_version(ARCHIVE_VERSION_V2);
+    write_manifest(manifest, remote_object_store).await?;
+    info!(
+        "Converted {} file pairs to archive version {}",
+        num_files, ARCHIVE_VERSION_V2
+    );
+    Ok(())
+}
+
+async fn convert_file_pair(
+    remote_object_store: Arc<DynObjectStore>,
+    summary_metadata: FileMetadata,
+    content_metadata: FileMetadata,
+) -> Result<(FileMetadata, FileMetadata)> {
+    let summary_data = get(&remote_object_store, &summary_metadata.file_path()).await?;
+    let content_data = get(&remote_object_store, &content_metadata.file_path()).await?;
+    let file_compression = content_data
+        .get(FILE_HEADER_BYTES - 1)
+        .copied()
+        .ok_or(anyhow!(
+            "Truncated file: {:?}",
+            content_metadata.file_path()
+        ))?;
+    let file_compression = FileCompression::try_from(file_compression)?;
+    let summaries = make_iterator::<CertifiedCheckpointSummary, Reader<Bytes>>(
+        SUMMARY_FILE_MAGIC,
+        summary_data.reader(),
+    )?;
+    let contents = make_iterator::<FullCheckpointContents, Reader<Bytes>>(
+        CHECKPOINT_FILE_MAGIC,
+        content_data.reader(),
+    )?;
+    let mut builder = IndexedFilesBuilder::new(
+        summary_metadata.checkpoint_seq_range.start,
+        file_compression,
+    )?;
+    for (summary, contents) in summaries.zip(contents) {
+        builder.append(&contents, &summary)?;
+    }
+    let files = builder.finish()?;
+    if files.checkpoint_range != summary_metadata.checkpoint_seq_range {
+        return Err(anyhow!(
+            "Files for checkpoints {:?} only contain checkpoints {:?}",
+            summary_metadata.checkpoint_seq_range,
+            files.checkpoint_range
+        ));
+    }
+    let new_content_metadata = create_file_metadata_from_bytes(
+        files.contents.clone(),
+        FileType::CheckpointContent,
+        content_metadata.epoch_num,
+        content_metadata.checkpoint_seq_range.clone(),
+    )?;
+    let new_summary_metadata = create_file_metadata_from_bytes(
+        files.summaries.clone(),
+        FileType::CheckpointSummary,
+        summary_metadata.epoch_num,
+        summary_metadata.checkpoint_seq_range.clone(),
+    )?;
+    // The index is written last so that its presence implies the files it points into exist
+    put(
+        &remote_object_store,
+        &new_content_metadata.indexed_file_path(),
+        files.contents,
+    )
+    .await?;
+    put(
+        &remote_object_store,
+        &new_summary_metadata.indexed_file_path(),
+        files.summaries,
+    )
+    .await?;
+    put(
+        &remote_object_store,
+        &summary_metadata.index_file_path(),
+        files.index,
+    )
+    .await?;
+    Ok((new_summary_metadata, new_content_metadata))
+}
@@ -2,23 +2,27 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    read_manifest, FileMetadata, FileType, Manifest, CHECKPOINT_FILE_MAGIC, SUMMARY_FILE_MAGIC,
+    read_blob_frame, read_manifest, CheckpointIndex, FileMetadata, FileType, Manifest,
+    CHECKPOINT_FILE_MAGIC, SUMMARY_FILE_MAGIC,
 };
 use anyhow::{anyhow, Context, Result};
 use bytes::buf::Reader;
 use bytes::{Buf, Bytes};
 use futures::{StreamExt, TryStreamExt};
+use lru::LruCache;
+use object_store::path::Path;
 use prometheus::{register_int_counter_vec_with_registry, IntCounterVec, Registry};
 use rand::seq::SliceRandom;
 use std::borrow::Borrow;
 use std::future;
+use std::num::NonZeroUsize;
 use std::ops::Range;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use mys_config::node::ArchiveReaderConfig;
 use mys_storage::object_store::http::HttpDownloaderBuilder;
-use mys_storage::object_store::util::get;
+use mys_storage::object_store::util::{get, get_range};
 use mys_storage::object_store::ObjectStoreGetExt;
 use mys_storage::{compute_sha3_checksum_for_bytes, make_iterator, verify_checkpoint};
 use mys_types::messages_checkpoint::{
@@ -30,6 +34,9 @@ use tokio::sync::oneshot::Sender;
 use tokio::sync::{oneshot, Mutex};
 use tracing::info;
 
+// Number of checkpoint file indexes kept in memory for point reads
+const INDEX_CACHE_SIZE: usize = 64;
+
 #[derive(Debug)]
 pub struct ArchiveReaderMetrics {
     pub archive_txns_read: IntCounterVec,
@@ -138,6 +145,7 @@ pub struct ArchiveReader {
     use_for_pruning_watermark: bool,
     remote_object_store: Arc<dyn ObjectStoreGetExt>,
     archive_reader_metrics: Arc<ArchiveReaderMetrics>,
+    index_cache: Arc<Mutex<LruCache<CheckpointSequenceNumber, Arc<CheckpointIndex>>>>,
 }
 
 impl ArchiveReader {
@@ -164,6 +172,9 @@ impl ArchiveReader {
             use_for_pruning_watermark: config.use_for_pruning_watermark,
             concurrency: config.download_concurrency.get(),
             archive_reader_metrics: metrics.clone(),
+            index_cache: Arc::new(Mutex::new(LruCache::new(
+                NonZeroUsize::new(INDEX_CACHE_SIZE).unwrap(),
+            ))),
         })
     }
 
@@ -217,6 +228,7 @@ impl ArchiveReader {
     /// the one in manifest
     pub async fn verify_file_consistency(
         &self,
+        manifest: &Manifest,
         files: Vec<(FileMetadata, FileMetadata)>,
     ) -> Result<()> {
         let remote_object_store = self.remote_object_store.clone();
@@ -224,11 +236,11 @@ impl ArchiveReader {
             .enumerate()
             .map(|(_, (summary_metadata, content_metadata))| {
                 let remote_object_store = remote_object_store.clone();
+                let summary_path = manifest.file_path(summary_metadata);
+                let content_path = manifest.file_path(content_metadata);
                 async move {
-                    let summary_data =
-                        get(&remote_object_store, &summary_metadata.file_path()).await?;
-                    let content_data =
-                        get(&remote_object_store, &content_metadata.file_path()).await?;
+                    let summary_data = get(&remote_object_store, &summary_path).await?;
+                    let content_data = get(&remote_object_store, &content_path).await?;
                     Ok::<((Bytes, &FileMetadata), (Bytes, &FileMetadata)), anyhow::Error>((
                         (summary_data, summary_metadata),
                         (content_data, content_metadata),
@@ -247,13 +259,13 @@ impl ArchiveReader {
                             .then_some(())
                             .ok_or(anyhow!(
                                 "Summary checksum doesn't match for file: {:?}",
-                                summary_metadata.file_path()
+                                manifest.file_path(summary_metadata)
                             ))?;
                         (content_checksum == content_metadata.sha3_digest)
                             .then_some(())
                             .ok_or(anyhow!(
                                 "Content checksum doesn't match for file: {:?}",
-                                content_metadata.file_path()
+                                manifest.file_path(content_metadata)
                             ))?;
                         Ok::<(), anyhow::Error>(())
                     });
@@ -281,11 +293,10 @@ impl ArchiveReader {
         let stream = futures::stream::iter(summary_files.iter())
             .enumerate()
             .filter(|(index, _s)| future::ready(*index >= start_index && *index < end_index))
-            .map(|(_, summary_metadata)| {
+            .map(|(_, summary_path)| {
                 let remote_object_store = remote_object_store.clone();
                 async move {
-                    let summary_data =
-                        get(&remote_object_store, &summary_metadata.file_path()).await?;
+                    let summary_data = get(&remote_object_store, summary_path).await?;
                     Ok::<Bytes, anyhow::Error>(summary_data)
                 }
             })
@@ -329,11 +340,10 @@ impl ArchiveReader {
         let summary_files = self.get_summary_files_for_list(skiplist.clone()).await?;
         let remote_object_store = self.remote_object_store.clone();
         let stream = futures::stream::iter(summary_files.iter())
-            .map(|summary_metadata| {
+            .map(|summary_path| {
                 let remote_object_store = remote_object_store.clone();
                 async move {
-                    let summary_data =
-                        get(&remote_object_store, &summary_metadata.file_path()).await?;
+                    let summary_data = get(&remote_object_store, summary_path).await?;
                     Ok::<Bytes, anyhow::Error>(summary_data)
                 }
             })
@@ -368,11 +378,10 @@ impl ArchiveReader {
         let summary_files = self.get_summary_files_for_list(cp_list.clone()).await?;
         let remote_object_store = self.remote_object_store.clone();
         let stream = futures::stream::iter(summary_files.iter())
-            .map(|summary_metadata| {
+            .map(|summary_path| {
                 let remote_object_store = remote_object_store.clone();
                 async move {
-                    let summary_data =
-                        get(&remote_object_store, &summary_metadata.file_path()).await?;
+                    let summary_data = get(&remote_object_store, summary_path).await?;
                     Ok::<Bytes, anyhow::Error>(summary_data)
                 }
             })
@@ -428,7 +437,8 @@ impl ArchiveReader {
             ));
         }
 
-        let files: Vec<(FileMetadata, FileMetadata)> = self.verify_manifest(manifest).await?;
+        let files: Vec<(FileMetadata, FileMetadata)> =
+            self.verify_manifest(manifest.clone()).await?;
 
         let start_index = match files.binary_search_by_key(&checkpoint_range.start, |(s, _c)| {
             s.checkpoint_seq_range.start
@@ -450,11 +460,11 @@ impl ArchiveReader {
             .filter(|(index, (_s, _c))| future::ready(*index >= start_index && *index < end_index))
             .map(|(_, (summary_metadata, content_metadata))| {
                 let remote_object_store = remote_object_store.clone();
+                let summary_path = manifest.file_path(summary_metadata);
+                let content_path = manifest.file_path(content_metadata);
                 async move {
-                    let summary_data =
-                        get(&remote_object_store, &summary_metadata.file_path()).await?;
-                    let content_data =
-                        get(&remote_object_store, &content_metadata.file_path()).await?;
+                    let summary_data = get(&remote_object_store, &summary_path).await?;
+                    let content_data = get(&remote_object_store, &content_path).await?;
                     Ok::<(Bytes, Bytes), anyhow::Error>((summary_data, content_data))
                 }
             })
@@ -515,6 +525,43 @@ impl ArchiveReader {
             .await
     }
 
+    /// Read a single checkpoint summary and its contents from the archive. Indexed archives are
+    /// read with range requests for just the two blobs of the checkpoint, older archives fall back
+    /// to downloading the whole files containing it. Contents are verified against the summary but
+    /// the summary itself is not verified.
+    pub async fn get_checkpoint(
+        &self,
+        checkpoint: CheckpointSequenceNumber,
+    ) -> Result<(CertifiedCheckpointSummary, CheckpointContents)> {
+        let manifest = self.manifest.lock().await.clone();
+        let (summary_metadata, content_metadata) = self
+            .get_files_for_checkpoint(manifest.clone(), checkpoint)
+            .await?;
+        let summary_path = manifest.file_path(&summary_metadata);
+        let content_path = manifest.file_path(&content_metadata);
+        let (summary, contents) = if manifest.is_indexed() {
+            self.get_checkpoint_with_index(
+                checkpoint,
+                &summary_metadata,
+                summary_path,
+                content_path,
+            )
+            .await?
+        } else {
+            self.get_checkpoint_from_files(checkpoint, summary_path, content_path)
+                .await?
+        };
+        if summary.sequence_number != checkpoint {
+            return Err(anyhow!(
+                "Expected checkpoint: {}, found: {}",
+                checkpoint,
+                summary.sequence_number
+            ));
+        }
+        contents.verify_digests(summary.content_digest)?;
+        Ok((summary, contents))
+    }
+
     /// Return latest available checkpoint in archive
     pub async fn latest_available_checkpoint(&self) -> Result<CheckpointSequenceNumber> {
         let manifest = self.manifest.lock().await.clone();
@@ -608,10 +655,12 @@ impl ArchiveReader {
             .map_err(|e| anyhow!("Failed to get verified checkpoint: {:?}", e))
     }
 
+    /// Return the paths of all summary files and the range of indices of the ones holding the
+    /// given checkpoints
     async fn get_summary_files_for_range(
         &self,
         checkpoint_range: Range<CheckpointSequenceNumber>,
-    ) -> Result<(Vec<FileMetadata>, usize, usize)> {
+    ) -> Result<(Vec<Path>, usize, usize)> {
         let manifest = self.manifest.lock().await.clone();
 
         let latest_available_checkpoint = manifest
@@ -627,7 +676,7 @@ impl ArchiveReader {
         }
 
         let summary_files: Vec<FileMetadata> = self
-            .verify_manifest(manifest)
+            .verify_manifest(manifest.clone())
             .await?
             .iter()
             .map(|(s, _)| s.clone())
@@ -647,13 +696,18 @@ impl ArchiveReader {
             Err(index) => index,
         };
 
-        Ok((summary_files, start_index, end_index))
+        let summary_paths = summary_files
+            .iter()
+            .map(|s| manifest.file_path(s))
+            .collect();
+        Ok((summary_paths, start_index, end_index))
     }
 
+    /// Return the paths of the summary files holding the given checkpoints
     async fn get_summary_files_for_list(
         &self,
         checkpoints: Vec<CheckpointSequenceNumber>,
-    ) -> Result<Vec<FileMetadata>> {
+    ) -> Result<Vec<Path>> {
         assert!(!checkpoints.is_empty());
         let manifest = self.manifest.lock().await.clone();
         let latest_available_checkpoint = manifest
@@ -671,7 +725,7 @@ impl ArchiveReader {
         }
 
         let summary_files: Vec<FileMetadata> = self
-            .verify_manifest(manifest)
+            .verify_manifest(manifest.clone())
             .await?
             .iter()
             .map(|(s, _)| s.clone())
@@ -690,12 +744,119 @@ impl ArchiveReader {
                     }
                 })
                 .expect("Archive does not contain checkpoint {checkpoint}");
-            summaries_filtered.push(summary_files[index].clone());
+            summaries_filtered.push(manifest.file_path(&summary_files[index]));
         }
 
         Ok(summaries_filtered)
     }
 
+    async fn get_files_for_checkpoint(
+        &self,
+        manifest: Manifest,
+        checkpoint: CheckpointSequenceNumber,
+    ) -> Result<(FileMetadata, FileMetadata)> {
+        if checkpoint >= manifest.next_checkpoint_seq_num() {
+            return Err(anyhow!(
+                "Checkpoint {} is not yet available in archive",
+                checkpoint
+            ));
+        }
+        let files = self.verify_manifest(manifest).await?;
+        let index = files
+            .binary_search_by(|(s, _c)| {
+                if checkpoint < s.checkpoint_seq_range.start {
+                    std::cmp::Ordering::Greater
+                } else if checkpoint >= s.checkpoint_seq_range.end {
+                    std::cmp::Ordering::Less
+                } else {
+                    std::cmp::Ordering::Equal
+                }
+            })
+            .map_err(|_| anyhow!("Archive does not contain checkpoint {}", checkpoint))?;
+        Ok(files[index].clone())
+    }
+
+    async fn get_checkpoint_with_index(
+        &self,
+        checkpoint: CheckpointSequenceNumber,
+        summary_metadata: &FileMetadata,
+        summary_path: Path,
+        content_path: Path,
+    ) -> Result<(CertifiedCheckpointSummary, CheckpointContents)> {
+        let index = self.get_checkpoint_index(summary_metadata).await?;
+        let entry = index
+            .entry(checkpoint)
+            .context(format!("Missing checkpoint {} in index", checkpoint))?;
+        let (summary_data, content_data) = futures::try_join!(
+            get_range(
+                &self.remote_object_store,
+                &summary_path,
+                entry.summary.start as usize..entry.summary.end as usize,
+            ),
+            get_range(
+                &self.remote_object_store,
+                &content_path,
+                entry.content.start as usize..entry.content.end as usize,
+            ),
+        )?;
+        let summary = read_blob_frame(summary_data, index.file_compression)?;
+        let contents = read_blob_frame(content_data, index.file_compression)?;
+        Ok((summary, contents))
+    }
+
+    async fn get_checkpoint_from_files(
+        &self,
+        checkpoint: CheckpointSequenceNumber,
+        summary_path: Path,
+        content_path: Path,
+    ) -> Result<(CertifiedCheckpointSummary, CheckpointContents)> {
+        let summary_data = get(&self.remote_object_store, &summary_path).await?;
+        let content_data = get(&self.remote_object_store, &content_path).await?;
+        let summary_iter = make_iterator::<CertifiedCheckpointSummary, Reader<Bytes>>(
+            SUMMARY_FILE_MAGIC,
+            summary_data.reader(),
+        )?;
+        let content_iter = make_iterator::<CheckpointContents, Reader<Bytes>>(
+            CHECKPOINT_FILE_MAGIC,
+            content_data.reader(),
+        )?;
+        summary_iter
+            .zip(content_iter)
+            .find(|(s, _c)| s.sequence_number == checkpoint)
+            .context(format!(
+                "Missing checkpoint {} in archive files",
+                checkpoint
+            ))
+    }
+
+    /// Return the index of the files starting with the given summary file, downloading it if it
+    /// isn't cached yet
+    async fn get_checkpoint_index(
+        &self,
+        summary_metadata: &FileMetadata,
+    ) -> Result<Arc<CheckpointIndex>> {
+        let start = summary_metadata.checkpoint_seq_range.start;
+        if let Some(index) = self.index_cache.lock().await.get(&start) {
+            return Ok(index.clone());
+        }
+        let bytes = get(
+            &self.remote_object_store,
+            &summary_metadata.index_file_path(),
+        )
+        .await?;
+        let index = CheckpointIndex::from_bytes(&bytes)?;
+        if index.checkpoint_range() != summary_metadata.checkpoint_seq_range {
+            return Err(anyhow!(
+                "Index for checkpoints {:?} covers checkpoints {:?}",
+                summary_metadata.checkpoint_seq_range,
+                index.checkpoint_range()
+            ));
+        }
+        let index = Arc::new(index);
+        self.index_cache.lock().await.put(start, index.clone());
+        Ok(index)
+    }
+
     fn spawn_manifest_sync_task<S: ObjectStoreGetExt + Clone>(
         remote_store: S,
         manifest: Arc<Mutex<Manifest>>,
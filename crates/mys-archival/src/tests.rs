@@ -3,14 +3,20 @@
 
 use crate::reader::{ArchiveReader, ArchiveReaderMetrics};
 use crate::writer::ArchiveWriter;
-use crate::{read_manifest, verify_archive_with_local_store, write_manifest, Manifest};
+use crate::{
+    convert_archive_to_indexed, create_file_metadata_from_bytes, read_manifest,
+    verify_archive_with_checksums, verify_archive_with_local_store, write_file_header,
+    write_manifest, FileType, Manifest, ManifestV1, ARCHIVE_VERSION_V1, CHECKPOINT_FILE_MAGIC,
+    SUMMARY_FILE_MAGIC,
+};
 use anyhow::{anyhow, Context, Result};
+use bytes::Bytes;
 use more_asserts as ma;
 use object_store::DynObjectStore;
 use prometheus::Registry;
 use std::fs;
 use std::fs::File;
-use std::io::Write;
+use std::io::{Cursor, Write};
 use std::num::NonZeroUsize;
 use std::path::PathBuf;
 use std::sync::atomic::AtomicU64;
@@ -18,11 +24,13 @@ use std::sync::Arc;
 use std::time::Duration;
 use mys_config::node::ArchiveReaderConfig;
 use mys_config::object_storage_config::{ObjectStoreConfig, ObjectStoreType};
-use mys_storage::object_store::util::path_to_filesystem;
-use mys_storage::{FileCompression, StorageFormat};
+use mys_storage::blob::{Blob, BlobEncoding};
+use mys_storage::object_store::util::{path_to_filesystem, put};
+use mys_storage::{compress, FileCompression, StorageFormat};
 use mys_swarm_config::test_utils::{empty_contents, CommitteeFixture};
 use mys_types::messages_checkpoint::{VerifiedCheckpoint, VerifiedCheckpointContents};
 use mys_types::storage::{ReadStore, SharedInMemoryStore, SingleCheckpointSharedInMemoryStore};
+use serde::Serialize;
 use tempfile::tempdir;
 
 struct TestState {
@@ -123,8 +131,10 @@ async fn insert_checkpoints_and_verify_manifest(
         if test_state.remote_path.join("MANIFEST").exists() {
             if let Ok(manifest) = read_manifest(test_state.remote_store.clone()).await {
                 for file in manifest.files().into_iter() {
-                    let file_path =
-                        path_to_filesystem(test_state.remote_path.clone(), &file.file_path())?;
+                    let file_path = path_to_filesystem(
+                        test_state.remote_path.clone(),
+                        &manifest.file_path(&file),
+                    )?;
                     assert!(file_path.exists());
                 }
 
@@ -372,3 +382,134 @@ async fn test_verify_archive_with_oneshot_store_bad_data() -> Result<(), anyhow:
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_archive_get_checkpoint() -> Result<(), anyhow::Error> {
+    let test_store = SharedInMemoryStore::default();
+    let test_state = setup_test_state(temp_dir()).await?;
+    let kill = test_state.archive_writer.start(test_store.clone()).await?;
+    insert_checkpoints_and_verify_manifest(&test_state, test_store.clone(), None).await?;
+    kill.send(())?;
+
+    test_state.archive_reader.sync_manifest_once().await?;
+    let manifest = test_state.archive_reader.get_manifest().await?;
+    assert!(manifest.is_indexed());
+    for file in manifest.files().into_iter() {
+        let index_path =
+            path_to_filesystem(test_state.remote_path.clone(), &file.index_file_path())?;
+        assert!(index_path.exists());
+    }
+    let latest_archived_checkpoint_seq_num = test_state
+        .archive_reader
+        .latest_available_checkpoint()
+        .await?;
+    for seq in 0..=latest_archived_checkpoint_seq_num {
+        let (summary, _contents) = test_state.archive_reader.get_checkpoint(seq).await?;
+        let expected = test_store
+            .get_checkpoint_by_sequence_number(seq)
+            .context("Missing checkpoint")?;
+        assert_eq!(summary.digest(), expected.digest());
+    }
+    assert!(test_state
+        .archive_reader
+        .get_checkpoint(latest_archived_checkpoint_seq_num + 1)
+        .await
+        .is_err());
+    Ok(())
+}
+
+fn encode_v1_file<T: Serialize>(magic: u32, values: &[T]) -> Result<Bytes> {
+    let mut buffer = vec![];
+    write_file_header(&mut buffer, magic, FileCompression::Zstd)?;
+    for value in values {
+        Blob::encode(value, BlobEncoding::Bcs)?.write(&mut buffer)?;
+    }
+    let mut compressed = vec![];
+    compress(&mut Cursor::new(buffer), &mut compressed)?;
+    Ok(Bytes::from(compressed))
+}
+
+#[tokio::test]
+async fn test_convert_archive_to_indexed() -> Result<(), anyhow::Error> {
+    let test_state = setup_test_state(temp_dir()).await?;
+    let (checkpoints, contents, _, _) = test_state.committee.make_empty_checkpoints(20, None);
+    let summaries: Vec<_> = checkpoints.iter().map(|c| c.clone().into_inner()).collect();
+    let contents: Vec<_> = contents.into_iter().map(|c| c.into_inner()).collect();
+
+    // Write an archive in the original format with two pairs of whole-file compressed files
+    let mut file_metadata = vec![];
+    for range in [0..10, 10..20] {
+        let content_bytes = encode_v1_file(CHECKPOINT_FILE_MAGIC, &contents[range.clone()])?;
+        let summary_bytes = encode_v1_file(SUMMARY_FILE_MAGIC, &summaries[range.clone()])?;
+        let checkpoint_range = range.start as u64..range.end as u64;
+        let content_metadata = create_file_metadata_from_bytes(
+            content_bytes.clone(),
+            FileType::CheckpointContent,
+            0,
+            checkpoint_range.clone(),
+        )?;
+        let summary_metadata = create_file_metadata_from_bytes(
+            summary_bytes.clone(),
+            FileType::CheckpointSummary,
+            0,
+            checkpoint_range,
+        )?;
+        put(
+            &test_state.remote_store,
+            &content_metadata.file_path(),
+            content_bytes,
+        )
+        .await?;
+        put(
+            &test_state.remote_store,
+            &summary_metadata.file_path(),
+            summary_bytes,
+        )
+        .await?;
+        file_metadata.extend([content_metadata, summary_metadata]);
+    }
+    let manifest = Manifest::V1(ManifestV1 {
+        archive_version: ARCHIVE_VERSION_V1,
+        next_checkpoint_seq_num: 20,
+        file_metadata,
+        epoch: 0,
+    });
+    write_manifest(manifest.clone(), test_state.remote_store.clone()).await?;
+
+    // Point reads fall back to downloading whole files
+    test_state.archive_reader.sync_manifest_once().await?;
+    let (summary, _contents) = test_state.archive_reader.get_checkpoint(15).await?;
+    assert_eq!(summary.digest(), checkpoints[15].digest());
+
+    convert_archive_to_indexed(test_state.remote_store_config.clone(), 2).await?;
+    let converted_manifest = read_manifest(test_state.remote_store.clone()).await?;
+    assert!(converted_manifest.is_indexed());
+    verify_archive_with_checksums(test_state.remote_store_config.clone(), 1).await?;
+
+    // Converted files don't replace the original ones, which readers of the previous manifest
+    // still verify and read
+    for (old, new) in manifest
+        .files()
+        .iter()
+        .zip(converted_manifest.files().iter())
+    {
+        assert_ne!(manifest.file_path(old), converted_manifest.file_path(new));
+    }
+    let files = test_state
+        .archive_reader
+        .verify_manifest(manifest.clone())
+        .await?;
+    test_state
+        .archive_reader
+        .verify_file_consistency(&manifest, files)
+        .await?;
+    let (summary, _contents) = test_state.archive_reader.get_checkpoint(15).await?;
+    assert_eq!(summary.digest(), checkpoints[15].digest());
+
+    test_state.archive_reader.sync_manifest_once().await?;
+    for (seq, checkpoint) in checkpoints.iter().enumerate() {
+        let (summary, _contents) = test_state.archive_reader.get_checkpoint(seq as u64).await?;
+        assert_eq!(summary.digest(), checkpoint.digest());
+    }
+    Ok(())
+}
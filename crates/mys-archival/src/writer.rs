@@ -3,9 +3,10 @@
 #![allow(dead_code)]
 
 use crate::{
-    create_file_metadata, read_manifest, write_manifest, CheckpointUpdates, FileMetadata, FileType,
-    Manifest, CHECKPOINT_FILE_MAGIC, CHECKPOINT_FILE_SUFFIX, EPOCH_DIR_PREFIX, MAGIC_BYTES,
-    SUMMARY_FILE_MAGIC, SUMMARY_FILE_SUFFIX,
+    create_file_metadata, read_manifest, write_blob_frame, write_manifest, CheckpointIndex,
+    CheckpointIndexEntry, CheckpointUpdates, FileMetadata, FileType, Manifest,
+    CHECKPOINT_FILE_MAGIC, CHECKPOINT_FILE_SUFFIX, EPOCH_DIR_PREFIX, FILE_HEADER_BYTES,
+    INDEX_FILE_SUFFIX, MAGIC_BYTES, SUMMARY_FILE_MAGIC, SUMMARY_FILE_SUFFIX,
 };
 use anyhow::Context;
 use anyhow::Result;
@@ -23,7 +24,7 @@ use std::time::Duration;
 use mys_config::object_storage_config::ObjectStoreConfig;
 use mys_storage::blob::{Blob, BlobEncoding};
 use mys_storage::object_store::util::{copy_file, path_to_filesystem};
-use mys_storage::{compress, FileCompression, StorageFormat};
+use mys_storage::{FileCompression, StorageFormat};
 use mys_types::messages_checkpoint::{
     CertifiedCheckpointSummary as Checkpoint, CheckpointSequenceNumber,
     FullCheckpointContents as CheckpointContents,
@@ -53,6 +54,7 @@ impl ArchiveMetrics {
 }
 
 /// CheckpointWriter writes checkpoints and summaries. It creates multiple *.chk and *.sum files
+/// along with an *.idx file holding the offset of every checkpoint in them
 struct CheckpointWriter {
     root_dir_path: PathBuf,
     epoch_num: u64,
@@ -61,6 +63,9 @@ struct CheckpointWriter {
     summary_wbuf: BufWriter<File>,
     sender: Sender<CheckpointUpdates>,
     checkpoint_buf_offset: usize,
+    checkpoint_file_offset: u64,
+    summary_file_offset: u64,
+    index: CheckpointIndex,
     file_compression: FileCompression,
     storage_format: StorageFormat,
     manifest: Manifest,
@@ -109,6 +114,9 @@ impl CheckpointWriter {
             wbuf: BufWriter::new(checkpoint_file),
             summary_wbuf: BufWriter::new(summary_file),
             checkpoint_buf_offset: 0,
+            checkpoint_file_offset: FILE_HEADER_BYTES as u64,
+            summary_file_offset: FILE_HEADER_BYTES as u64,
+            index: CheckpointIndex::new(file_compression, checkpoint_sequence_num),
             sender,
             file_compression,
             storage_format,
@@ -171,10 +179,19 @@ impl CheckpointWriter {
             self.reset()?;
         }
 
-        self.checkpoint_buf_offset += contents_blob.write(&mut self.wbuf)?;
+        self.checkpoint_buf_offset += blob_size;
+        let content_len = write_blob_frame(&mut self.wbuf, &contents_blob, self.file_compression)?;
 
         let summary_blob = Blob::encode(&checkpoint_summary, BlobEncoding::Bcs)?;
-        summary_blob.write(&mut self.summary_wbuf)?;
+        let summary_len =
+            write_blob_frame(&mut self.summary_wbuf, &summary_blob, self.file_compression)?;
+
+        self.index.entries.push(CheckpointIndexEntry {
+            content: self.checkpoint_file_offset..self.checkpoint_file_offset + content_len,
+            summary: self.summary_file_offset..self.summary_file_offset + summary_len,
+        });
+        self.checkpoint_file_offset += content_len;
+        self.summary_file_offset += summary_len;
 
         self.checkpoint_range.end = self
             .checkpoint_range
@@ -192,7 +209,6 @@ impl CheckpointWriter {
             "{}.{CHECKPOINT_FILE_SUFFIX}",
             self.checkpoint_range.start
         ));
-        self.compress(&file_path)?;
         let file_metadata = create_file_metadata(
             &file_path,
             FileType::CheckpointContent,
@@ -210,7 +226,6 @@ impl CheckpointWriter {
             "{}.{SUMMARY_FILE_SUFFIX}",
             self.checkpoint_range.start
         ));
-        self.compress(&file_path)?;
         let file_metadata = create_file_metadata(
             &file_path,
             FileType::CheckpointSummary,
@@ -219,10 +234,22 @@ impl CheckpointWriter {
         )?;
         Ok(file_metadata)
     }
+    fn finalize_index(&mut self) -> Result<()> {
+        assert_eq!(self.index.checkpoint_range(), self.checkpoint_range);
+        let file_path = self.epoch_dir().join(format!(
+            "{}.{INDEX_FILE_SUFFIX}",
+            self.checkpoint_range.start
+        ));
+        let mut f = File::create(file_path)?;
+        f.write_all(&self.index.to_bytes()?)?;
+        f.sync_data()?;
+        Ok(())
+    }
     fn cut(&mut self) -> Result<()> {
         if !self.checkpoint_range.is_empty() {
             let checkpoint_file_metadata = self.finalize()?;
             let summary_file_metadata = self.finalize_summary()?;
+            self.finalize_index()?;
             let checkpoint_updates = CheckpointUpdates::new(
                 self.epoch_num,
                 self.checkpoint_range.end,
@@ -235,17 +262,6 @@ impl CheckpointWriter {
         }
         Ok(())
     }
-    fn compress(&self, source: &Path) -> Result<()> {
-        if self.file_compression == FileCompression::None {
-            return Ok(());
-        }
-        let mut input = File::open(source)?;
-        let tmp_file_name = source.with_extension("tmp");
-        let mut output = File::create(&tmp_file_name)?;
-        compress(&mut input, &mut output)?;
-        fs::rename(tmp_file_name, source)?;
-        Ok(())
-    }
     fn next_file(
         dir_path: &Path,
         checkpoint_sequence_num: u64,
@@ -276,6 +292,9 @@ impl CheckpointWriter {
             self.file_compression,
         )?;
         self.checkpoint_buf_offset = MAGIC_BYTES;
+        self.checkpoint_file_offset = FILE_HEADER_BYTES as u64;
+        self.summary_file_offset = FILE_HEADER_BYTES as u64;
+        self.index = CheckpointIndex::new(self.file_compression, self.checkpoint_range.start);
         self.wbuf = BufWriter::new(f);
         let f = Self::next_file(
             &self.epoch_dir(),
@@ -445,26 +464,37 @@ impl ArchiveWriter {
                     if let Some(checkpoint_updates) = updates {
                         info!("Received checkpoint update: {:?}", checkpoint_updates);
                         let latest_checkpoint_seq_num = checkpoint_updates.manifest.next_checkpoint_seq_num();
-                        let summary_file_path = checkpoint_updates.summary_file_path();
                         Self::sync_file_to_remote(
                             local_staging_root_dir.clone(),
-                            summary_file_path,
+                            checkpoint_updates.staged_summary_file_path(),
+                            checkpoint_updates.summary_file_path(),
                             local_object_store.clone(),
                             remote_object_store.clone()
                         )
                         .await
                         .expect("Syncing checkpoint summary should not fail");
 
-                        let content_file_path = checkpoint_updates.content_file_path();
                         Self::sync_file_to_remote(
                             local_staging_root_dir.clone(),
-                            content_file_path,
+                            checkpoint_updates.staged_content_file_path(),
+                            checkpoint_updates.content_file_path(),
                             local_object_store.clone(),
                             remote_object_store.clone()
                         )
                         .await
                         .expect("Syncing checkpoint content should not fail");
 
+                        let index_file_path = checkpoint_updates.index_file_path();
+                        Self::sync_file_to_remote(
+                            local_staging_root_dir.clone(),
+                            index_file_path.clone(),
+                            index_file_path,
+                            local_object_store.clone(),
+                            remote_object_store.clone()
+                        )
+                        .await
+                        .expect("Syncing checkpoint index should not fail");
+
                         write_manifest(
                             checkpoint_updates.manifest,
                             remote_object_store.clone()
@@ -484,13 +514,14 @@ impl ArchiveWriter {
 
     async fn sync_file_to_remote(
         dir: PathBuf,
+        staged_path: object_store::path::Path,
         path: object_store::path::Path,
         from: Arc<DynObjectStore>,
         to: Arc<DynObjectStore>,
     ) -> Result<()> {
         debug!("Syncing archive file to remote: {:?}", path);
-        copy_file(&path, &path, &from, &to).await?;
-        fs::remove_file(path_to_filesystem(dir, &path)?)?;
+        copy_file(&staged_path, &path, &from, &to).await?;
+        fs::remove_file(path_to_filesystem(dir, &staged_path)?)?;
         Ok(())
     }
 }
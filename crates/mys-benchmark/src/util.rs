@@ -6,10 +6,10 @@ use crate::ValidatorProxy;
 use anyhow::Result;
 use std::path::PathBuf;
 use std::sync::Arc;
-use mys_keys::keystore::{AccountKeystore, FileBasedKeystore};
+use mys_keys::keystore::Keystore;
 use mys_test_transaction_builder::TestTransactionBuilder;
 use mys_types::base_types::ObjectRef;
-use mys_types::crypto::{AccountKeyPair, KeypairTraits};
+use mys_types::crypto::AccountKeyPair;
 use mys_types::object::Owner;
 use mys_types::transaction::{Transaction, TransactionData, TEST_ONLY_GAS_UNIT_FOR_TRANSFER};
 use mys_types::utils::to_sender_signed_transaction;
@@ -24,10 +24,13 @@ pub fn get_ed25519_keypair_from_keystore(
     keystore_path: PathBuf,
     requested_address: &MysAddress,
 ) -> Result<AccountKeyPair> {
-    let keystore = FileBasedKeystore::new(&keystore_path)?;
-    match keystore.get_key(requested_address) {
-        Ok(MysKeyPair::Ed25519(kp)) => Ok(kp.copy()),
-        other => Err(anyhow::anyhow!("Invalid key type: {:?}", other)),
+    let keystore = Keystore::from_path(&keystore_path)?;
+    match keystore.export_key(requested_address)? {
+        MysKeyPair::Ed25519(kp) => Ok(kp),
+        other => Err(anyhow::anyhow!(
+            "Invalid key type: {:?}",
+            other.public().scheme()
+        )),
     }
 }
 
@@ -1005,6 +1005,10 @@ pub struct StateSnapshotConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub object_store_config: Option<ObjectStoreConfig>,
     pub concurrency: usize,
+    /// Maximum number of delta snapshots written on top of a full snapshot before a new full
+    /// snapshot is written. Zero (the default) only writes full snapshots.
+    #[serde(default)]
+    pub max_delta_chain_length: usize,
 }
 
 #[derive(Default, Debug, Clone, Deserialize, Serialize)]
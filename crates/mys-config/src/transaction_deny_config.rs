@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use std::collections::HashSet;
+use std::path::PathBuf;
 
 use once_cell::sync::OnceCell;
 use serde::{Deserialize, Serialize};
@@ -71,6 +72,16 @@ pub struct TransactionDenyConfig {
     /// A list of disabled OAuth providers for zkLogin
     #[serde(default)]
     zklogin_disabled_providers: HashSet<String>,
+
+    /// Path to a file containing a `DynamicTransactionDenyConfig`. When set, the file is
+    /// watched and its content is layered on top of this config whenever it changes, without
+    /// restarting the node. Changes made through the admin endpoint are written back to it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dynamic_deny_list_path: Option<PathBuf>,
+
+    /// Path to an append-only file recording every change to the dynamic deny lists.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    deny_audit_log_path: Option<PathBuf>,
     // TODO: We could consider add a deny list for types that we want to disable public transfer.
     // TODO: We could also consider disable more types of commands, such as transfer, split and etc.
 }
@@ -118,6 +129,242 @@ impl TransactionDenyConfig {
     pub fn zklogin_disabled_providers(&self) -> &HashSet<String> {
         &self.zklogin_disabled_providers
     }
+
+    pub fn dynamic_deny_list_path(&self) -> Option<&PathBuf> {
+        self.dynamic_deny_list_path.as_ref()
+    }
+
+    pub fn deny_audit_log_path(&self) -> Option<&PathBuf> {
+        self.deny_audit_log_path.as_ref()
+    }
+
+    /// Returns a copy of this config with all entries of `overrides` that have not expired
+    /// at `now_ms` added to it. Overrides can only tighten the config, never relax it.
+    pub fn with_overrides(
+        &self,
+        overrides: &DynamicTransactionDenyConfig,
+        now_ms: u64,
+    ) -> TransactionDenyConfig {
+        fn active<T: Copy>(
+            entries: &[DenyListEntry<T>],
+            now_ms: u64,
+        ) -> impl Iterator<Item = T> + '_ {
+            entries
+                .iter()
+                .filter(move |entry| !entry.is_expired(now_ms))
+                .map(|entry| entry.id)
+        }
+        let switch = |switch: &Option<DenySwitch>| {
+            switch
+                .as_ref()
+                .is_some_and(|switch| !switch.is_expired(now_ms))
+        };
+
+        let mut config = TransactionDenyConfig {
+            object_deny_set: OnceCell::new(),
+            package_deny_set: OnceCell::new(),
+            address_deny_set: OnceCell::new(),
+            ..self.clone()
+        };
+        config
+            .object_deny_list
+            .extend(active(&overrides.object_deny_list, now_ms));
+        config
+            .package_deny_list
+            .extend(active(&overrides.package_deny_list, now_ms));
+        config
+            .address_deny_list
+            .extend(active(&overrides.address_deny_list, now_ms));
+        config.package_publish_disabled |= switch(&overrides.package_publish_disabled);
+        config.package_upgrade_disabled |= switch(&overrides.package_upgrade_disabled);
+        config.shared_object_disabled |= switch(&overrides.shared_object_disabled);
+        config.user_transaction_disabled |= switch(&overrides.user_transaction_disabled);
+        config.receiving_objects_disabled |= switch(&overrides.receiving_objects_disabled);
+        config.zklogin_sig_disabled |= switch(&overrides.zklogin_sig_disabled);
+        config
+    }
+}
+
+/// An entry of a dynamic deny list.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct DenyListEntry<T> {
+    pub id: T,
+
+    /// Unix timestamp in milliseconds after which the entry no longer applies.
+    /// Entries without an expiry stay in force until they are removed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at_ms: Option<u64>,
+
+    /// Free-form note explaining why the entry was added, e.g. an incident reference.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl<T> DenyListEntry<T> {
+    pub fn is_expired(&self, now_ms: u64) -> bool {
+        self.expires_at_ms.is_some_and(|expiry| expiry <= now_ms)
+    }
+}
+
+/// A feature switch of the dynamic deny config. The feature is disabled while the switch is
+/// present and not expired.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct DenySwitch {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at_ms: Option<u64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl DenySwitch {
+    pub fn is_expired(&self, now_ms: u64) -> bool {
+        self.expires_at_ms.is_some_and(|expiry| expiry <= now_ms)
+    }
+}
+
+/// Deny rules that can be changed at runtime, either through the admin endpoint or by editing
+/// the file at `TransactionDenyConfig::dynamic_deny_list_path`. They are applied in addition
+/// to the static `TransactionDenyConfig`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct DynamicTransactionDenyConfig {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub object_deny_list: Vec<DenyListEntry<ObjectID>>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub package_deny_list: Vec<DenyListEntry<ObjectID>>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub address_deny_list: Vec<DenyListEntry<MysAddress>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package_publish_disabled: Option<DenySwitch>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package_upgrade_disabled: Option<DenySwitch>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shared_object_disabled: Option<DenySwitch>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_transaction_disabled: Option<DenySwitch>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub receiving_objects_disabled: Option<DenySwitch>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zklogin_sig_disabled: Option<DenySwitch>,
+}
+
+impl DynamicTransactionDenyConfig {
+    /// Returns the switch with the given kebab-case name, e.g. `package-publish-disabled`.
+    pub fn switch_mut(&mut self, name: &str) -> Option<&mut Option<DenySwitch>> {
+        Some(match name {
+            "package-publish-disabled" => &mut self.package_publish_disabled,
+            "package-upgrade-disabled" => &mut self.package_upgrade_disabled,
+            "shared-object-disabled" => &mut self.shared_object_disabled,
+            "user-transaction-disabled" => &mut self.user_transaction_disabled,
+            "receiving-objects-disabled" => &mut self.receiving_objects_disabled,
+            "zklogin-sig-disabled" => &mut self.zklogin_sig_disabled,
+            _ => return None,
+        })
+    }
+
+    pub fn is_switch(name: &str) -> bool {
+        Self::default().switch_mut(name).is_some()
+    }
+
+    /// Number of list entries and switches that have not expired at `now_ms`.
+    pub fn active_entry_count(&self, now_ms: u64) -> usize {
+        let entries = self
+            .object_deny_list
+            .iter()
+            .chain(&self.package_deny_list)
+            .filter(|entry| !entry.is_expired(now_ms))
+            .count()
+            + self
+                .address_deny_list
+                .iter()
+                .filter(|entry| !entry.is_expired(now_ms))
+                .count();
+        let switches = self
+            .switches()
+            .into_iter()
+            .flatten()
+            .filter(|switch| !switch.is_expired(now_ms))
+            .count();
+        entries + switches
+    }
+
+    fn switches_mut(&mut self) -> [&mut Option<DenySwitch>; 6] {
+        [
+            &mut self.package_publish_disabled,
+            &mut self.package_upgrade_disabled,
+            &mut self.shared_object_disabled,
+            &mut self.user_transaction_disabled,
+            &mut self.receiving_objects_disabled,
+            &mut self.zklogin_sig_disabled,
+        ]
+    }
+
+    fn switches(&self) -> [&Option<DenySwitch>; 6] {
+        [
+            &self.package_publish_disabled,
+            &self.package_upgrade_disabled,
+            &self.shared_object_disabled,
+            &self.user_transaction_disabled,
+            &self.receiving_objects_disabled,
+            &self.zklogin_sig_disabled,
+        ]
+    }
+
+    /// The earliest expiry after `now_ms` of any entry, i.e. the next time the effective
+    /// deny config changes on its own.
+    pub fn next_expiry_ms(&self, now_ms: u64) -> Option<u64> {
+        let entries = self
+            .object_deny_list
+            .iter()
+            .chain(&self.package_deny_list)
+            .map(|entry| entry.expires_at_ms)
+            .chain(
+                self.address_deny_list
+                    .iter()
+                    .map(|entry| entry.expires_at_ms),
+            );
+        let switches = self
+            .switches()
+            .into_iter()
+            .flatten()
+            .map(|switch| switch.expires_at_ms);
+        entries
+            .chain(switches)
+            .flatten()
+            .filter(|expiry| *expiry > now_ms)
+            .min()
+    }
+
+    /// Drops all entries that have expired at `now_ms`. Returns whether anything was removed.
+    pub fn remove_expired(&mut self, now_ms: u64) -> bool {
+        let before = self.clone();
+        self.object_deny_list
+            .retain(|entry| !entry.is_expired(now_ms));
+        self.package_deny_list
+            .retain(|entry| !entry.is_expired(now_ms));
+        self.address_deny_list
+            .retain(|entry| !entry.is_expired(now_ms));
+        for switch in self.switches_mut() {
+            if switch
+                .as_ref()
+                .is_some_and(|switch| switch.is_expired(now_ms))
+            {
+                *switch = None;
+            }
+        }
+        before != *self
+    }
 }
 
 #[derive(Default)]
@@ -183,4 +430,14 @@ impl TransactionDenyConfigBuilder {
         self.config.zklogin_disabled_providers.insert(provider);
         self
     }
+
+    pub fn dynamic_deny_list_path(mut self, path: PathBuf) -> Self {
+        self.config.dynamic_deny_list_path = Some(path);
+        self
+    }
+
+    pub fn deny_audit_log_path(mut self, path: PathBuf) -> Self {
+        self.config.deny_audit_log_path = Some(path);
+        self
+    }
 }
@@ -163,6 +163,7 @@ use crate::state_accumulator::{AccumulatorStore, StateAccumulator, WrappedObject
 use crate::subscription_handler::SubscriptionHandler;
 use crate::transaction_input_loader::TransactionInputLoader;
 use crate::transaction_manager::TransactionManager;
+use crate::transaction_signing_filter::{TransactionDenyManager, TransactionDenyMetrics};
 
 #[cfg(msim)]
 pub use crate::checkpoints::checkpoint_executor::{
@@ -834,6 +835,9 @@ pub struct AuthorityState {
     chain_identifier: ChainIdentifier,
 
     pub(crate) congestion_tracker: Arc<CongestionTracker>,
+
+    /// The transaction deny config used for signing, which can be changed at runtime.
+    transaction_deny: Arc<TransactionDenyManager>,
 }
 
 /// The authority state encapsulates all state, drives execution, and ensures safety.
@@ -863,6 +867,10 @@ impl AuthorityState {
         &self.config.authority_overload_config
     }
 
+    pub fn transaction_deny_manager(&self) -> &Arc<TransactionDenyManager> {
+        &self.transaction_deny
+    }
+
     pub fn get_epoch_state_commitments(
         &self,
         epoch: EpochId,
@@ -884,12 +892,11 @@ impl AuthorityState {
         // Note: the deny checks may do redundant package loads but:
         // - they only load packages when there is an active package deny map
         // - the loads are cached anyway
-        mys_transaction_checks::deny::check_transaction_for_signing(
+        self.transaction_deny.check_transaction_for_signing(
             tx_data,
             transaction.tx_signatures(),
             &input_object_kinds,
             &receiving_objects_refs,
-            &self.config.transaction_deny_config,
             self.get_backing_package_store().as_ref(),
         )?;
 
@@ -1793,12 +1800,11 @@ impl AuthorityState {
         let input_object_kinds = transaction.input_objects()?;
         let receiving_object_refs = transaction.receiving_objects();
 
-        mys_transaction_checks::deny::check_transaction_for_signing(
+        self.transaction_deny.check_transaction_for_signing(
             &transaction,
             &[],
             &input_object_kinds,
             &receiving_object_refs,
-            &self.config.transaction_deny_config,
             self.get_backing_package_store().as_ref(),
         )?;
 
@@ -1984,12 +1990,11 @@ impl AuthorityState {
         let input_object_kinds = transaction.input_objects()?;
         let receiving_object_refs = transaction.receiving_objects();
 
-        mys_transaction_checks::deny::check_transaction_for_signing(
+        self.transaction_deny.check_transaction_for_signing(
             &transaction,
             &[],
             &input_object_kinds,
             &receiving_object_refs,
-            &self.config.transaction_deny_config,
             self.get_backing_package_store().as_ref(),
         )?;
 
@@ -2151,12 +2156,11 @@ impl AuthorityState {
         let input_object_kinds = transaction.input_objects()?;
         let receiving_object_refs = transaction.receiving_objects();
 
-        mys_transaction_checks::deny::check_transaction_for_signing(
+        self.transaction_deny.check_transaction_for_signing(
             &transaction,
             &[],
             &input_object_kinds,
             &receiving_object_refs,
-            &self.config.transaction_deny_config,
             self.get_backing_package_store().as_ref(),
         )?;
 
@@ -2921,6 +2925,10 @@ impl AuthorityState {
         );
         let input_loader =
             TransactionInputLoader::new(execution_cache_trait_pointers.object_cache_reader.clone());
+        let transaction_deny = Arc::new(TransactionDenyManager::new(
+            config.transaction_deny_config.clone(),
+            TransactionDenyMetrics::new(prometheus_registry),
+        ));
         let epoch = epoch_store.epoch();
         let state = Arc::new(AuthorityState {
             name,
@@ -2945,8 +2953,13 @@ impl AuthorityState {
             validator_tx_finalizer,
             chain_identifier,
             congestion_tracker: Arc::new(CongestionTracker::new()),
+            transaction_deny,
         });
 
+        if let Err(e) = state.transaction_deny.start_file_watcher() {
+            error!("Failed to watch dynamic transaction deny list: {e:?}");
+        }
+
         // Start a task to execute ready certificates.
         let authority_state = Arc::downgrade(&state);
         spawn_monitored_task!(execution_process(
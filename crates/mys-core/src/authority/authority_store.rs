@@ -582,23 +582,8 @@ impl AuthorityStore {
         object_id: &ObjectID,
         version: VersionNumber,
     ) -> Result<Option<ObjectRef>, MysError> {
-        let Some(prior_version) = version.one_before() else {
-            return Ok(None);
-        };
-        let mut iterator = self
-            .perpetual_tables
-            .objects
-            .unbounded_iter()
-            .skip_prior_to(&ObjectKey(*object_id, prior_version))?;
-
-        if let Some((object_key, value)) = iterator.next() {
-            if object_key.0 == *object_id {
-                return Ok(Some(
-                    self.perpetual_tables.object_reference(&object_key, value)?,
-                ));
-            }
-        }
-        Ok(None)
+        self.perpetual_tables
+            .get_object_ref_prior_to_key(object_id, version)
     }
 
     pub fn multi_get_objects_by_key(
@@ -755,11 +740,32 @@ impl AuthorityStore {
         live_objects: impl Iterator<Item = LiveObject>,
         indirect_objects_threshold: usize,
         expected_sha3_digest: &[u8; 32],
+    ) -> MysResult<()> {
+        Self::bulk_insert_filtered_live_objects(
+            perpetual_db,
+            live_objects,
+            |_| true,
+            indirect_objects_threshold,
+            expected_sha3_digest,
+        )
+    }
+
+    /// Same as `bulk_insert_live_objects`, but only objects for which `filter` returns true are
+    /// inserted. The sha3 digest is still checked against every object in `live_objects`.
+    pub fn bulk_insert_filtered_live_objects(
+        perpetual_db: &AuthorityPerpetualTables,
+        live_objects: impl Iterator<Item = LiveObject>,
+        filter: impl Fn(&LiveObject) -> bool,
+        indirect_objects_threshold: usize,
+        expected_sha3_digest: &[u8; 32],
     ) -> MysResult<()> {
         let mut hasher = Sha3_256::default();
         let mut batch = perpetual_db.objects.batch();
         for object in live_objects {
             hasher.update(object.object_reference().2.inner());
+            if !filter(&object) {
+                continue;
+            }
             match object {
                 LiveObject::Normal(object) => {
                     let StoreObjectPair(store_object_wrapper, indirect_object) =
@@ -809,6 +815,96 @@ impl AuthorityStore {
         Ok(())
     }
 
+    /// Same as `bulk_insert_filtered_live_objects`, but for objects restored from a delta state
+    /// snapshot on top of an already restored live object set. Live object markers of previous
+    /// versions of the inserted objects are removed, and objects that do not match `filter`
+    /// are tombstoned so that a previously restored version of them stops being live.
+    pub fn bulk_insert_delta_live_objects(
+        perpetual_db: &AuthorityPerpetualTables,
+        live_objects: impl Iterator<Item = LiveObject>,
+        filter: impl Fn(&LiveObject) -> bool,
+        indirect_objects_threshold: usize,
+        expected_sha3_digest: &[u8; 32],
+    ) -> MysResult<()> {
+        let live_objects: Vec<_> = live_objects.collect();
+        let mut batch = perpetual_db.live_owned_object_markers.batch();
+        for object in &live_objects {
+            let (object_id, version, _) = object.object_reference();
+            Self::delete_live_object_markers_before(perpetual_db, &mut batch, object_id, version)?;
+            if !filter(object) {
+                batch.insert_batch(
+                    &perpetual_db.objects,
+                    std::iter::once::<(ObjectKey, StoreObjectWrapper)>((
+                        ObjectKey(object_id, version),
+                        StoreObject::Deleted.into(),
+                    )),
+                )?;
+            }
+        }
+        batch.write()?;
+        Self::bulk_insert_filtered_live_objects(
+            perpetual_db,
+            live_objects.into_iter(),
+            filter,
+            indirect_objects_threshold,
+            expected_sha3_digest,
+        )
+    }
+
+    /// Removes objects deleted since the base epoch of a delta state snapshot from the live
+    /// object set. `deleted_objects` are the tombstone references of the objects, i.e. the
+    /// version at which they were deleted or wrapped together with `OBJECT_DIGEST_DELETED` or
+    /// `OBJECT_DIGEST_WRAPPED`.
+    pub fn bulk_delete_live_objects(
+        perpetual_db: &AuthorityPerpetualTables,
+        deleted_objects: impl Iterator<Item = ObjectRef>,
+    ) -> MysResult<()> {
+        let mut batch = perpetual_db.objects.batch();
+        for (object_id, version, digest) in deleted_objects {
+            let tombstone = if digest == ObjectDigest::OBJECT_DIGEST_DELETED {
+                StoreObject::Deleted
+            } else if digest == ObjectDigest::OBJECT_DIGEST_WRAPPED {
+                StoreObject::Wrapped
+            } else {
+                return Err(MysError::from(format!(
+                    "Deleted object {} at version {} does not have a tombstone digest",
+                    object_id, version
+                )));
+            };
+            batch.insert_batch(
+                &perpetual_db.objects,
+                std::iter::once::<(ObjectKey, StoreObjectWrapper)>((
+                    ObjectKey(object_id, version),
+                    tombstone.into(),
+                )),
+            )?;
+            Self::delete_live_object_markers_before(perpetual_db, &mut batch, object_id, version)?;
+        }
+        batch.write()?;
+        Ok(())
+    }
+
+    /// Adds to `batch` the removal of every live object marker of `object_id` below `version`.
+    /// The markers are looked up explicitly since range deletions are not visible to reads until
+    /// the range is compacted.
+    fn delete_live_object_markers_before(
+        perpetual_db: &AuthorityPerpetualTables,
+        batch: &mut DBBatch,
+        object_id: ObjectID,
+        version: SequenceNumber,
+    ) -> MysResult<()> {
+        let stale_markers = perpetual_db
+            .live_owned_object_markers
+            .safe_range_iter(
+                (object_id, SequenceNumber::MIN, ObjectDigest::MIN)
+                    ..(object_id, version, ObjectDigest::MIN),
+            )
+            .map(|result| result.map(|(object_ref, _)| object_ref))
+            .collect::<Result<Vec<_>, _>>()?;
+        batch.delete_batch(&perpetual_db.live_owned_object_markers, stale_markers)?;
+        Ok(())
+    }
+
     pub fn set_epoch_start_configuration(
         &self,
         epoch_start_configuration: &EpochStartConfiguration,
@@ -314,7 +314,11 @@ impl AuthorityStorePruner {
         )?;
 
         if let Some(rpc_index) = rpc_index {
-            rpc_index.prune(&checkpoint_content_to_prune)?;
+            rpc_index.prune(
+                checkpoint_number,
+                &checkpoint_content_to_prune,
+                effects_to_prune,
+            )?;
         }
         perpetual_batch.write()?;
         checkpoints_batch.write()?;
@@ -25,6 +25,7 @@ use crate::authority::authority_store_types::{
     StoreMoveObjectWrapper, StoreObject, StoreObjectPair, StoreObjectValue, StoreObjectWrapper,
 };
 use crate::authority::epoch_start_configuration::EpochStartConfiguration;
+use crate::state_accumulator::AccumulatorStore;
 use typed_store::rocksdb::compaction_filter::Decision;
 use typed_store::DBMapUtils;
 
@@ -328,6 +329,27 @@ impl AuthorityPerpetualTables {
         Ok(None)
     }
 
+    pub fn get_object_ref_prior_to_key(
+        &self,
+        object_id: &ObjectID,
+        version: VersionNumber,
+    ) -> Result<Option<ObjectRef>, MysError> {
+        let Some(prior_version) = version.one_before() else {
+            return Ok(None);
+        };
+        let mut iterator = self
+            .objects
+            .unbounded_iter()
+            .skip_prior_to(&ObjectKey(*object_id, prior_version))?;
+
+        if let Some((object_key, value)) = iterator.next() {
+            if object_key.0 == *object_id {
+                return Ok(Some(self.object_reference(&object_key, value)?));
+            }
+        }
+        Ok(None)
+    }
+
     pub fn get_latest_object_or_tombstone(
         &self,
         object_id: ObjectID,
@@ -551,6 +573,32 @@ impl AuthorityPerpetualTables {
     }
 }
 
+impl AuthorityPerpetualTablesReadOnly {
+    // Same as `AuthorityPerpetualTables::object`, for tools which open the tables read-only.
+    // Returns `None` if object was deleted/wrapped
+    pub fn object(
+        &self,
+        object_key: &ObjectKey,
+        store_object: StoreObjectWrapper,
+    ) -> Result<Option<Object>, MysError> {
+        let StoreObject::Value(store_object) = store_object.migrate().into_inner() else {
+            return Ok(None);
+        };
+        let indirect_object = match store_object.data {
+            StoreData::IndirectObject(ref metadata) => self
+                .indirect_move_objects
+                .get(&metadata.digest)?
+                .map(|o| o.migrate().into_inner()),
+            _ => None,
+        };
+        Ok(Some(try_construct_object(
+            object_key,
+            store_object,
+            indirect_object,
+        )?))
+    }
+}
+
 impl ObjectStore for AuthorityPerpetualTables {
     /// Read an object and return it, or Ok(None) if the object was not found.
     fn get_object(&self, object_id: &ObjectID) -> Option<Object> {
@@ -563,6 +611,55 @@ impl ObjectStore for AuthorityPerpetualTables {
     }
 }
 
+/// Lets the accumulator be recomputed directly on top of the perpetual tables, e.g. by offline
+/// tools which open the database without a running authority.
+impl AccumulatorStore for AuthorityPerpetualTables {
+    fn get_object_ref_prior_to_key_deprecated(
+        &self,
+        object_id: &ObjectID,
+        version: VersionNumber,
+    ) -> MysResult<Option<ObjectRef>> {
+        self.get_object_ref_prior_to_key(object_id, version)
+    }
+
+    fn get_root_state_accumulator_for_epoch(
+        &self,
+        epoch: EpochId,
+    ) -> MysResult<Option<(CheckpointSequenceNumber, Accumulator)>> {
+        self.get_root_state_hash(epoch)
+    }
+
+    fn get_root_state_accumulator_for_highest_epoch(
+        &self,
+    ) -> MysResult<Option<(EpochId, (CheckpointSequenceNumber, Accumulator))>> {
+        Ok(self
+            .root_state_hash_by_epoch
+            .safe_iter()
+            .skip_to_last()
+            .next()
+            .transpose()?)
+    }
+
+    fn insert_state_accumulator_for_epoch(
+        &self,
+        epoch: EpochId,
+        last_checkpoint_of_epoch: &CheckpointSequenceNumber,
+        acc: &Accumulator,
+    ) -> MysResult {
+        self.insert_root_state_hash(epoch, *last_checkpoint_of_epoch, acc.clone())
+    }
+
+    fn iter_live_object_set(
+        &self,
+        include_wrapped_object: bool,
+    ) -> Box<dyn Iterator<Item = LiveObject> + '_> {
+        Box::new(AuthorityPerpetualTables::iter_live_object_set(
+            self,
+            include_wrapped_object,
+        ))
+    }
+}
+
 pub struct LiveSetIter<'a> {
     iter:
         <DBMap<ObjectKey, StoreObjectWrapper> as Map<'a, ObjectKey, StoreObjectWrapper>>::Iterator,
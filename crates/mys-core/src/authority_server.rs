@@ -21,6 +21,7 @@ use mys_network::{
     api::{Validator, ValidatorServer},
     tonic,
 };
+use mys_types::base_types::MysAddress;
 use mys_types::messages_consensus::{ConsensusTransaction, ConsensusTransactionKind};
 use mys_types::messages_grpc::{
     HandleCertificateRequestV3, HandleCertificateResponseV3, HandleTransactionResponseV2,
@@ -607,6 +608,8 @@ impl ValidatorService {
     // toward spam count. In general, this should be set to true for
     // requests that are read-only and thus do not consume gas, such
     // as when the transaction is already executed.
+    // Also returns whether the signatures of the certificates were verified,
+    // which is not the case when the transaction is already executed.
     async fn handle_certificates(
         &self,
         certificates: NonEmpty<CertifiedTransaction>,
@@ -616,7 +619,7 @@ impl ValidatorService {
         include_auxiliary_data: bool,
         epoch_store: &Arc<AuthorityPerEpochStore>,
         wait_for_effects: bool,
-    ) -> Result<(Option<Vec<HandleCertificateResponseV3>>, Weight), tonic::Status> {
+    ) -> Result<(Option<Vec<HandleCertificateResponseV3>>, Weight, bool), tonic::Status> {
         // Validate if cert can be executed
         // Fullnode does not serve handle_certificate call.
         fp_ensure!(
@@ -676,6 +679,7 @@ impl ValidatorService {
                         auxiliary_data: None,
                     }]),
                     Weight::one(),
+                    false,
                 ));
             };
         }
@@ -748,7 +752,7 @@ impl ValidatorService {
             None
         };
 
-        Ok((responses, weight))
+        Ok((responses, weight, true))
     }
 
     async fn handle_submit_to_consensus(
@@ -870,19 +874,39 @@ impl ValidatorService {
 
 type WrappedServiceResponse<T> = Result<(tonic::Response<T>, Weight), tonic::Status>;
 
+/// Response extension recording that the signatures of the request were verified, so
+/// that its sender and gas payer can be tallied by the traffic controller.
+#[derive(Clone, Copy)]
+struct SignaturesVerified;
+
+fn mark_signatures_verified<T>(
+    mut response: tonic::Response<T>,
+    signatures_verified: bool,
+) -> tonic::Response<T> {
+    if signatures_verified {
+        response.extensions_mut().insert(SignaturesVerified);
+    }
+    response
+}
+
 impl ValidatorService {
     async fn transaction_impl(
         &self,
         request: tonic::Request<Transaction>,
     ) -> WrappedServiceResponse<HandleTransactionResponse> {
-        self.handle_transaction(request).await
+        // A transaction is only handled successfully once its signatures are verified
+        self.handle_transaction(request)
+            .await
+            .map(|(response, spam_weight)| (mark_signatures_verified(response, true), spam_weight))
     }
 
     async fn transaction_v2_impl(
         &self,
         request: tonic::Request<HandleTransactionRequestV2>,
     ) -> WrappedServiceResponse<HandleTransactionResponseV2> {
-        self.handle_transaction_v2(request).await
+        self.handle_transaction_v2(request)
+            .await
+            .map(|(response, spam_weight)| (mark_signatures_verified(response, true), spam_weight))
     }
 
     async fn submit_certificate_impl(
@@ -905,11 +929,14 @@ impl ValidatorService {
         )
         .instrument(span)
         .await
-        .map(|(executed, spam_weight)| {
+        .map(|(executed, spam_weight, signatures_verified)| {
             (
-                tonic::Response::new(SubmitCertificateResponse {
-                    executed: executed.map(|mut x| x.remove(0)).map(Into::into),
-                }),
+                mark_signatures_verified(
+                    tonic::Response::new(SubmitCertificateResponse {
+                        executed: executed.map(|mut x| x.remove(0)).map(Into::into),
+                    }),
+                    signatures_verified,
+                ),
                 spam_weight,
             )
         })
@@ -935,14 +962,17 @@ impl ValidatorService {
         )
         .instrument(span)
         .await
-        .map(|(resp, spam_weight)| {
+        .map(|(resp, spam_weight, signatures_verified)| {
             (
-                tonic::Response::new(
-                    resp.expect(
-                        "handle_certificate should not return none with wait_for_effects=true",
-                    )
-                    .remove(0)
-                    .into(),
+                mark_signatures_verified(
+                    tonic::Response::new(
+                        resp.expect(
+                            "handle_certificate should not return none with wait_for_effects=true",
+                        )
+                        .remove(0)
+                        .into(),
+                    ),
+                    signatures_verified,
                 ),
                 spam_weight,
             )
@@ -971,13 +1001,16 @@ impl ValidatorService {
         )
         .instrument(span)
         .await
-        .map(|(resp, spam_weight)| {
+        .map(|(resp, spam_weight, signatures_verified)| {
             (
-                tonic::Response::new(
-                    resp.expect(
-                        "handle_certificate should not return none with wait_for_effects=true",
-                    )
-                    .remove(0),
+                mark_signatures_verified(
+                    tonic::Response::new(
+                        resp.expect(
+                            "handle_certificate should not return none with wait_for_effects=true",
+                        )
+                        .remove(0),
+                    ),
+                    signatures_verified,
                 ),
                 spam_weight,
             )
@@ -1147,11 +1180,14 @@ impl ValidatorService {
         )
         .instrument(span)
         .await
-        .map(|(resp, spam_weight)| {
+        .map(|(resp, spam_weight, signatures_verified)| {
             (
-                tonic::Response::new(HandleSoftBundleCertificatesResponseV3 {
-                    responses: resp.unwrap_or_default(),
-                }),
+                mark_signatures_verified(
+                    tonic::Response::new(HandleSoftBundleCertificatesResponseV3 {
+                        responses: resp.unwrap_or_default(),
+                    }),
+                    signatures_verified,
+                ),
                 spam_weight,
             )
         })
@@ -1301,9 +1337,16 @@ impl ValidatorService {
         }
     }
 
-    async fn handle_traffic_req(&self, client: Option<IpAddr>) -> Result<(), tonic::Status> {
+    async fn handle_traffic_req(
+        &self,
+        client: Option<IpAddr>,
+        senders: Option<(MysAddress, MysAddress)>,
+    ) -> Result<(), tonic::Status> {
         if let Some(traffic_controller) = &self.traffic_controller {
-            if !traffic_controller.check(&client, &None).await {
+            let (sender, gas_payer) = senders.unzip();
+            if !traffic_controller.check(&client, &None).await
+                || !traffic_controller.check_sender(&sender, &gas_payer).await
+            {
                 // Entity in blocklist
                 Err(tonic::Status::from_error(MysError::TooManyRequests.into()))
             } else {
@@ -1317,6 +1360,7 @@ impl ValidatorService {
     fn handle_traffic_resp<T>(
         &self,
         client: Option<IpAddr>,
+        senders: Option<(MysAddress, MysAddress)>,
         wrapped_response: WrappedServiceResponse<T>,
     ) -> Result<tonic::Response<T>, tonic::Status> {
         let (error, spam_weight, unwrapped_response) = match wrapped_response {
@@ -1327,11 +1371,20 @@ impl ValidatorService {
                 Err(status.clone()),
             ),
         };
+        // Senders are taken from the request before its signatures are checked, so
+        // only tally them once they are known to be authentic. Otherwise anyone could
+        // get an arbitrary address blocked by sending requests on its behalf.
+        let signatures_verified = unwrapped_response
+            .as_ref()
+            .is_ok_and(|response| response.extensions().get::<SignaturesVerified>().is_some());
 
         if let Some(traffic_controller) = self.traffic_controller.clone() {
+            let (sender, gas_payer) = senders.filter(|_| signatures_verified).unzip();
             traffic_controller.tally(TrafficTally {
                 direct: client,
                 through_fullnode: None,
+                sender,
+                gas_payer,
                 error_info: error.map(|e| {
                     let error_type = String::from(e.clone().as_ref());
                     let error_weight = normalize(e);
@@ -1373,6 +1426,53 @@ fn normalize(err: MysError) -> Weight {
     }
 }
 
+/// Exposes the sender and gas payer of the transaction carried by a request, if
+/// any, to traffic control policies which are keyed on addresses rather than IPs.
+pub trait TrafficControlSenders {
+    fn traffic_control_senders(&self) -> Option<(MysAddress, MysAddress)> {
+        None
+    }
+}
+
+impl TrafficControlSenders for Transaction {
+    fn traffic_control_senders(&self) -> Option<(MysAddress, MysAddress)> {
+        Some((self.sender_address(), self.gas_owner()))
+    }
+}
+
+impl TrafficControlSenders for CertifiedTransaction {
+    fn traffic_control_senders(&self) -> Option<(MysAddress, MysAddress)> {
+        Some((self.sender_address(), self.gas_owner()))
+    }
+}
+
+impl TrafficControlSenders for HandleTransactionRequestV2 {
+    fn traffic_control_senders(&self) -> Option<(MysAddress, MysAddress)> {
+        self.transaction.traffic_control_senders()
+    }
+}
+
+impl TrafficControlSenders for HandleCertificateRequestV3 {
+    fn traffic_control_senders(&self) -> Option<(MysAddress, MysAddress)> {
+        self.certificate.traffic_control_senders()
+    }
+}
+
+impl TrafficControlSenders for HandleSoftBundleCertificatesRequestV3 {
+    fn traffic_control_senders(&self) -> Option<(MysAddress, MysAddress)> {
+        // All certificates in a soft bundle are tallied against the first one
+        self.certificates
+            .first()
+            .and_then(TrafficControlSenders::traffic_control_senders)
+    }
+}
+
+impl TrafficControlSenders for ObjectInfoRequest {}
+impl TrafficControlSenders for TransactionInfoRequest {}
+impl TrafficControlSenders for CheckpointRequest {}
+impl TrafficControlSenders for CheckpointRequestV2 {}
+impl TrafficControlSenders for SystemStateRequest {}
+
 /// Implements generic pre- and post-processing. Since this is on the critical
 /// path, any heavy lifting should be done in a separate non-blocking task
 /// unless it is necessary to override the return value.
@@ -1384,13 +1484,14 @@ macro_rules! handle_with_decoration {
         }
 
         let client = $self.get_client_ip_addr(&$request, $self.client_id_source.as_ref().unwrap());
+        let senders = $request.get_ref().traffic_control_senders();
 
-        // check if either IP is blocked, in which case return early
-        $self.handle_traffic_req(client.clone()).await?;
+        // check if either IP or the transaction's senders are blocked, in which case return early
+        $self.handle_traffic_req(client.clone(), senders).await?;
 
         // handle traffic tallying
         let wrapped_response = $self.$func_name($request).await;
-        $self.handle_traffic_resp(client, wrapped_response)
+        $self.handle_traffic_resp(client, senders, wrapped_response)
     }};
 }
 
@@ -130,7 +130,7 @@ impl CongestionTracker {
 }
 
 impl CongestionTracker {
-    fn process_per_checkpoint_events(
+    pub(crate) fn process_per_checkpoint_events(
         &self,
         now: CheckpointTimestamp,
         congestion_events: &[(u64, Vec<ObjectID>)],
@@ -49,6 +49,7 @@ use crate::{
         AuthorityMetrics, AuthorityState,
     },
     checkpoints::{CheckpointService, CheckpointServiceNotify},
+    congestion_tracker::CongestionTracker,
     consensus_throughput_calculator::ConsensusThroughputCalculator,
     consensus_types::consensus_output_api::{parse_block_transactions, ConsensusCommitAPI},
     execution_cache::ObjectCacheRead,
@@ -124,6 +125,10 @@ impl ConsensusHandlerInitializer {
         &self.state.metrics
     }
 
+    pub(crate) fn congestion_tracker(&self) -> &Arc<CongestionTracker> {
+        &self.state.congestion_tracker
+    }
+
     pub(crate) fn backpressure_subscriber(&self) -> BackpressureSubscriber {
         self.backpressure_manager.subscribe()
     }
@@ -194,6 +194,8 @@ impl ConsensusManagerTrait for MysticetiManager {
         self.authority.swap(Some(registered_authority.clone()));
 
         // Initialize the client to send transactions to this Mysticeti instance.
+        self.client
+            .set_congestion_tracker(consensus_handler_initializer.congestion_tracker().clone());
         self.client.set(client);
 
         // spin up the new mysticeti consensus handler to listen for committed sub dags
@@ -81,4 +81,4 @@ mod unit_test_utils;
 pub mod signature_verifier;
 
 pub mod runtime;
-mod transaction_signing_filter;
+pub mod transaction_signing_filter;
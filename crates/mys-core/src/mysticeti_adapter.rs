@@ -4,10 +4,11 @@
 use std::{sync::Arc, time::Duration};
 
 use arc_swap::{ArcSwapOption, Guard};
-use consensus_core::{ClientError, TransactionClient};
+use consensus_core::{ClientError, TransactionClient, TransactionPriority};
 use mys_types::{
     error::{MysError, MysResult},
     messages_consensus::{ConsensusTransaction, ConsensusTransactionKind},
+    transaction::TransactionDataAPI,
 };
 use tap::prelude::*;
 use tokio::time::{sleep, Instant};
@@ -15,6 +16,7 @@ use tracing::{error, info, warn};
 
 use crate::{
     authority::authority_per_epoch_store::AuthorityPerEpochStore,
+    congestion_tracker::CongestionTracker,
     consensus_adapter::{BlockStatusReceiver, ConsensusClient},
     consensus_handler::SequencedConsensusTransactionKey,
 };
@@ -26,12 +28,14 @@ use crate::{
 #[derive(Default, Clone)]
 pub struct LazyMysticetiClient {
     client: Arc<ArcSwapOption<TransactionClient>>,
+    congestion_tracker: Arc<ArcSwapOption<CongestionTracker>>,
 }
 
 impl LazyMysticetiClient {
     pub fn new() -> Self {
         Self {
             client: Arc::new(ArcSwapOption::empty()),
+            congestion_tracker: Arc::new(ArcSwapOption::empty()),
         }
     }
 
@@ -72,6 +76,44 @@ impl LazyMysticetiClient {
     pub fn clear(&self) {
         self.client.store(None);
     }
+
+    /// Sets the tracker of the congested shared objects, used to prioritize the submitted
+    /// transactions.
+    pub fn set_congestion_tracker(&self, congestion_tracker: Arc<CongestionTracker>) {
+        self.congestion_tracker.store(Some(congestion_tracker));
+    }
+
+    /// Returns the priority to submit transactions with. Messages from validators go before user
+    /// transactions, and user transactions with a gas price below the clearing price of the
+    /// congested shared objects they mutate go after the other user transactions. A bundle gets
+    /// the lowest priority of its transactions.
+    fn transaction_priority(&self, transactions: &[ConsensusTransaction]) -> TransactionPriority {
+        let congestion_tracker = self.congestion_tracker.load();
+        transactions
+            .iter()
+            .map(|transaction| {
+                let transaction_data = match &transaction.kind {
+                    ConsensusTransactionKind::CertifiedTransaction(certificate) => {
+                        certificate.data().transaction_data()
+                    }
+                    ConsensusTransactionKind::UserTransaction(user_transaction) => {
+                        user_transaction.data().transaction_data()
+                    }
+                    _ => return TransactionPriority::High,
+                };
+                let clearing_price = congestion_tracker
+                    .as_ref()
+                    .and_then(|tracker| tracker.get_suggested_gas_prices(transaction_data));
+                match clearing_price {
+                    Some(clearing_price) if transaction_data.gas_price() < clearing_price => {
+                        TransactionPriority::Low
+                    }
+                    _ => TransactionPriority::Normal,
+                }
+            })
+            .min()
+            .unwrap_or_default()
+    }
 }
 
 #[async_trait::async_trait]
@@ -85,6 +127,7 @@ impl ConsensusClient for LazyMysticetiClient {
         // The retrieved TransactionClient can be from the past epoch. Submit would fail after
         // Mysticeti shuts down, so there should be no correctness issue.
         let client = self.get().await;
+        let priority = self.transaction_priority(transactions);
         let transactions_bytes = transactions
             .iter()
             .map(|t| bcs::to_bytes(t).expect("Serializing consensus transaction cannot fail"))
@@ -92,7 +135,7 @@ impl ConsensusClient for LazyMysticetiClient {
         let (block_ref, status_waiter) = client
             .as_ref()
             .expect("Client should always be returned")
-            .submit(transactions_bytes)
+            .submit_with_priority(transactions_bytes, priority)
             .await
             .tap_err(|err| {
                 // Will be logged by caller as well.
@@ -132,3 +175,81 @@ impl ConsensusClient for LazyMysticetiClient {
         Ok(status_waiter)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use consensus_core::TransactionPriority;
+    use mys_test_transaction_builder::TestTransactionBuilder;
+    use mys_types::{
+        base_types::{random_object_ref, AuthorityName, ObjectID, SequenceNumber},
+        crypto::get_account_key_pair,
+        messages_consensus::ConsensusTransaction,
+    };
+
+    use super::LazyMysticetiClient;
+    use crate::congestion_tracker::CongestionTracker;
+
+    fn user_transaction(gas_price: u64, shared_object: ObjectID) -> ConsensusTransaction {
+        let (sender, keypair) = get_account_key_pair();
+        let transaction = TestTransactionBuilder::new(sender, random_object_ref(), gas_price)
+            .call_counter_increment(
+                ObjectID::random(),
+                shared_object,
+                SequenceNumber::from_u64(1),
+            )
+            .build_and_sign(&keypair);
+        ConsensusTransaction::new_user_transaction_message(&AuthorityName::ZERO, transaction)
+    }
+
+    #[test]
+    fn test_transaction_priority() {
+        let client = LazyMysticetiClient::new();
+        let congested = ObjectID::random();
+        let end_of_publish = ConsensusTransaction::new_end_of_publish(AuthorityName::ZERO);
+
+        // Without a congestion tracker, user transactions all get the normal priority.
+        assert_eq!(
+            client.transaction_priority(&[end_of_publish.clone()]),
+            TransactionPriority::High
+        );
+        assert_eq!(
+            client.transaction_priority(&[user_transaction(100, congested)]),
+            TransactionPriority::Normal
+        );
+
+        // The clearing price of the congested object is the highest cancelled gas price.
+        let tracker = CongestionTracker::new();
+        tracker.process_per_checkpoint_events(1000, &[(500, vec![congested])], &[]);
+        client.set_congestion_tracker(Arc::new(tracker));
+
+        let low = user_transaction(100, congested);
+        assert_eq!(
+            client.transaction_priority(&[low.clone()]),
+            TransactionPriority::Low
+        );
+        assert_eq!(
+            client.transaction_priority(&[user_transaction(500, congested)]),
+            TransactionPriority::Normal
+        );
+        assert_eq!(
+            client.transaction_priority(&[user_transaction(100, ObjectID::random())]),
+            TransactionPriority::Normal
+        );
+        assert_eq!(
+            client.transaction_priority(&[end_of_publish.clone()]),
+            TransactionPriority::High
+        );
+
+        // A bundle gets the lowest priority of its transactions.
+        assert_eq!(
+            client.transaction_priority(&[end_of_publish, low]),
+            TransactionPriority::Low
+        );
+        assert_eq!(
+            client.transaction_priority(&[]),
+            TransactionPriority::Normal
+        );
+    }
+}
@@ -12,6 +12,7 @@ use rayon::iter::ParallelIterator;
 use serde::Deserialize;
 use serde::Serialize;
 use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use std::collections::HashMap;
 use std::path::Path;
 use std::path::PathBuf;
@@ -24,9 +25,10 @@ use mys_types::base_types::SequenceNumber;
 use mys_types::base_types::MysAddress;
 use mys_types::digests::TransactionDigest;
 use mys_types::dynamic_field::visitor as DFV;
+use mys_types::effects::{TransactionEffects, TransactionEffectsAPI};
 use mys_types::full_checkpoint_content::CheckpointData;
 use mys_types::layout_resolver::LayoutResolver;
-use mys_types::messages_checkpoint::CheckpointContents;
+use mys_types::messages_checkpoint::{CheckpointContents, CheckpointSequenceNumber};
 use mys_types::object::Object;
 use mys_types::object::Owner;
 use mys_types::storage::error::Error as StorageError;
@@ -40,7 +42,7 @@ use typed_store::traits::{TableSummary, TypedStoreDebug};
 use typed_store::DBMapUtils;
 use typed_store::TypedStoreError;
 
-const CURRENT_DB_VERSION: u64 = 0;
+const CURRENT_DB_VERSION: u64 = 1;
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 struct MetadataInfo {
@@ -76,6 +78,66 @@ impl OwnerIndexInfo {
     }
 }
 
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct ObjectVersionKey {
+    pub object_id: ObjectID,
+    pub checkpoint: CheckpointSequenceNumber,
+}
+
+impl ObjectVersionKey {
+    fn new(object_id: ObjectID, checkpoint: CheckpointSequenceNumber) -> Self {
+        Self {
+            object_id,
+            checkpoint,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub struct ObjectVersionInfo {
+    /// Version of the object as of the end of the checkpoint, or `None` if it was deleted or
+    /// wrapped
+    pub version: Option<SequenceNumber>,
+    /// Address owning the object as of the end of the checkpoint, if it was address owned
+    pub owner: Option<MysAddress>,
+}
+
+impl ObjectVersionInfo {
+    fn new(object: &Object) -> Self {
+        Self {
+            version: Some(object.version()),
+            owner: match object.owner() {
+                Owner::AddressOwner(address) => Some(*address),
+                _ => None,
+            },
+        }
+    }
+
+    fn removed() -> Self {
+        Self {
+            version: None,
+            owner: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct OwnerHistoryKey {
+    pub owner: MysAddress,
+    pub object_id: ObjectID,
+    pub checkpoint: CheckpointSequenceNumber,
+}
+
+impl OwnerHistoryKey {
+    fn new(owner: MysAddress, object_id: ObjectID, checkpoint: CheckpointSequenceNumber) -> Self {
+        Self {
+            owner,
+            object_id,
+            checkpoint,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Serialize, Deserialize, Eq, PartialEq, Debug)]
 pub struct TransactionInfo {
     pub checkpoint: u64,
@@ -144,6 +206,24 @@ struct IndexStoreTables {
     /// Allows looking up information related to published Coins, like the ObjectID of its
     /// coorisponding CoinMetadata.
     coin: DBMap<CoinIndexKey, CoinIndexInfo>,
+
+    /// An index of object versions, keyed by the checkpoint in which the object was changed.
+    ///
+    /// Allows looking up the version of an object that was live as of a particular checkpoint.
+    /// Pruning collapses the history of an object down to its latest entry at or below the
+    /// highest pruned checkpoint.
+    object_versions: DBMap<ObjectVersionKey, ObjectVersionInfo>,
+
+    /// An index of object ownership over time, keyed by the checkpoint in which ownership changed.
+    ///
+    /// Allows listing the objects owned by a specific user account as of a particular checkpoint.
+    /// A `None` value marks the checkpoint in which the account stopped owning the object.
+    /// Pruned along with `object_versions`.
+    owner_history: DBMap<OwnerHistoryKey, Option<OwnerIndexInfo>>,
+
+    /// A singleton holding the lowest checkpoint which `object_versions` and `owner_history` can
+    /// answer queries for.
+    object_history_watermark: DBMap<(), CheckpointSequenceNumber>,
     // NOTE: Authors and Reviewers before adding any new tables ensure that they are either:
     // - bounded in size by the live object set
     // - are prune-able and have corresponding logic in the `prune` function
@@ -184,6 +264,12 @@ impl IndexStoreTables {
     ) -> Result<(), StorageError> {
         info!("Initializing RPC indexes");
 
+        // The object history indexes start out from the live object set, which reflects the state
+        // as of the highest executed checkpoint.
+        let history_checkpoint = checkpoint_store
+            .get_highest_executed_checkpoint_seq_number()?
+            .unwrap_or(0);
+
         // Iterate through available, executed checkpoints that have yet to be pruned
         // to initialize checkpoint and transaction based indexes.
         if let Some(highest_executed_checkpint) =
@@ -229,6 +315,7 @@ impl IndexStoreTables {
         let make_live_object_indexer = RpcParLiveObjectSetIndexer {
             tables: self,
             coin_index: &coin_index,
+            history_checkpoint,
             epoch_store,
             package_store,
         };
@@ -239,6 +326,8 @@ impl IndexStoreTables {
         )?;
 
         self.coin.multi_insert(coin_index.into_inner().unwrap())?;
+        self.object_history_watermark
+            .insert(&(), &history_checkpoint)?;
 
         self.meta.insert(
             &(),
@@ -255,7 +344,9 @@ impl IndexStoreTables {
     /// Prune data from this Index
     fn prune(
         &self,
+        pruned_checkpoint: CheckpointSequenceNumber,
         checkpoint_contents_to_prune: &[CheckpointContents],
+        effects_to_prune: &[TransactionEffects],
     ) -> Result<(), TypedStoreError> {
         let mut batch = self.transactions.batch();
 
@@ -265,6 +356,57 @@ impl IndexStoreTables {
 
         batch.delete_batch(&self.transactions, transactions_to_prune)?;
 
+        // Only the latest entry at or below the pruned checkpoint is needed to answer queries for
+        // checkpoints which are still available, so the history of every object touched by the
+        // pruned transactions is collapsed down to that entry.
+        let object_ids: BTreeSet<ObjectID> = effects_to_prune
+            .iter()
+            .flat_map(|effects| {
+                effects
+                    .modified_at_versions()
+                    .into_iter()
+                    .map(|(object_id, _)| object_id)
+                    .chain(
+                        effects
+                            .all_changed_objects()
+                            .into_iter()
+                            .map(|((object_id, _, _), _, _)| object_id),
+                    )
+            })
+            .collect();
+
+        for object_id in object_ids {
+            let versions = self
+                .object_versions
+                .safe_range_iter(
+                    ObjectVersionKey::new(object_id, 0)
+                        ..=ObjectVersionKey::new(object_id, pruned_checkpoint),
+                )
+                .collect::<Result<Vec<_>, _>>()?;
+            let owners: BTreeSet<MysAddress> =
+                versions.iter().filter_map(|(_, info)| info.owner).collect();
+            prune_history(&mut batch, &self.object_versions, versions, |info| {
+                info.version.is_some()
+            })?;
+
+            for owner in owners {
+                let ownership = self
+                    .owner_history
+                    .safe_range_iter(
+                        OwnerHistoryKey::new(owner, object_id, 0)
+                            ..=OwnerHistoryKey::new(owner, object_id, pruned_checkpoint),
+                    )
+                    .collect::<Result<Vec<_>, _>>()?;
+                prune_history(&mut batch, &self.owner_history, ownership, Option::is_some)?;
+            }
+        }
+
+        let watermark = self.object_history_watermark.get(&())?.unwrap_or(0);
+        batch.insert_batch(
+            &self.object_history_watermark,
+            [((), watermark.max(pruned_checkpoint))],
+        )?;
+
         batch.write()
     }
 
@@ -392,6 +534,49 @@ impl IndexStoreTables {
             batch.insert_batch(&self.coin, coin_index)?;
         }
 
+        // object history indexes
+        {
+            let sequence_number = checkpoint.checkpoint_summary.sequence_number;
+
+            let mut owned_at_start = HashMap::new();
+
+            for tx in &checkpoint.transactions {
+                for removed_object in tx.removed_objects_pre_version() {
+                    let key = ObjectVersionKey::new(removed_object.id(), sequence_number);
+                    batch.insert_batch(
+                        &self.object_versions,
+                        [(key, ObjectVersionInfo::removed())],
+                    )?;
+
+                    if let Owner::AddressOwner(address) = removed_object.owner() {
+                        let key =
+                            OwnerHistoryKey::new(*address, removed_object.id(), sequence_number);
+                        self.remove_owner_history(&mut batch, &mut owned_at_start, key)?;
+                    }
+                }
+
+                for (object, old_object) in tx.changed_objects() {
+                    let key = ObjectVersionKey::new(object.id(), sequence_number);
+                    let info = ObjectVersionInfo::new(object);
+                    batch.insert_batch(&self.object_versions, [(key, info)])?;
+
+                    if let Some(Owner::AddressOwner(address)) = old_object.map(Object::owner) {
+                        if info.owner != Some(*address) {
+                            let key = OwnerHistoryKey::new(*address, object.id(), sequence_number);
+                            self.remove_owner_history(&mut batch, &mut owned_at_start, key)?;
+                        }
+                    }
+
+                    if let Some(owner) = info.owner {
+                        owned_at_start.entry((owner, object.id())).or_insert(false);
+                        let owner_key = OwnerHistoryKey::new(owner, object.id(), sequence_number);
+                        let owner_info = OwnerIndexInfo::new(object);
+                        batch.insert_batch(&self.owner_history, [(owner_key, Some(owner_info))])?;
+                    }
+                }
+            }
+        }
+
         debug!(
             checkpoint = checkpoint.checkpoint_summary.sequence_number,
             "finished indexing checkpoint"
@@ -400,6 +585,28 @@ impl IndexStoreTables {
         Ok(batch)
     }
 
+    /// Records that an account stopped owning an object in the checkpoint being indexed.
+    ///
+    /// `owned_at_start` tracks whether the account already owned the object at the start of the
+    /// checkpoint, so that ownership which is gained and lost again within a single checkpoint
+    /// leaves no trace in the history.
+    fn remove_owner_history(
+        &self,
+        batch: &mut typed_store::rocks::DBBatch,
+        owned_at_start: &mut HashMap<(MysAddress, ObjectID), bool>,
+        key: OwnerHistoryKey,
+    ) -> Result<(), TypedStoreError> {
+        if *owned_at_start
+            .entry((key.owner, key.object_id))
+            .or_insert(true)
+        {
+            batch.insert_batch(&self.owner_history, [(key, None::<OwnerIndexInfo>)])?;
+        } else {
+            batch.delete_batch(&self.owner_history, [key])?;
+        }
+        Ok(())
+    }
+
     fn get_transaction_info(
         &self,
         digest: &TransactionDigest,
@@ -453,6 +660,77 @@ impl IndexStoreTables {
         };
         self.coin.get(&key)
     }
+
+    fn get_object_history_watermark(&self) -> Result<CheckpointSequenceNumber, TypedStoreError> {
+        self.object_history_watermark
+            .get(&())
+            .map(|watermark| watermark.unwrap_or(0))
+    }
+
+    fn get_object_version_at_checkpoint(
+        &self,
+        object_id: ObjectID,
+        checkpoint: CheckpointSequenceNumber,
+    ) -> Result<Option<SequenceNumber>, TypedStoreError> {
+        let latest = self
+            .object_versions
+            .unbounded_iter()
+            .skip_prior_to(&ObjectVersionKey::new(object_id, checkpoint))?
+            .reverse()
+            .next();
+
+        Ok(latest
+            .filter(|(key, _)| key.object_id == object_id)
+            .and_then(|(_, info)| info.version))
+    }
+
+    fn owner_iter_at_checkpoint(
+        &self,
+        owner: MysAddress,
+        checkpoint: CheckpointSequenceNumber,
+        cursor: Option<ObjectID>,
+    ) -> Result<impl Iterator<Item = (OwnerIndexKey, OwnerIndexInfo)> + '_, TypedStoreError> {
+        let lower_bound = OwnerHistoryKey::new(owner, cursor.unwrap_or(ObjectID::ZERO), 0);
+        let upper_bound = OwnerHistoryKey::new(owner, ObjectID::MAX, CheckpointSequenceNumber::MAX);
+        let mut iter = self
+            .owner_history
+            .range_iter(lower_bound..=upper_bound)
+            .peekable();
+
+        // The history of each object is ordered by checkpoint, so the last entry at or below
+        // `checkpoint` describes whether the account owned the object as of that checkpoint.
+        Ok(std::iter::from_fn(move || loop {
+            let (key, info) = iter.next()?;
+            let mut latest = (key.checkpoint <= checkpoint).then_some(info).flatten();
+
+            while let Some((next_key, next_info)) =
+                iter.next_if(|(next_key, _)| next_key.object_id == key.object_id)
+            {
+                if next_key.checkpoint <= checkpoint {
+                    latest = next_info;
+                }
+            }
+
+            if let Some(info) = latest {
+                return Some((OwnerIndexKey::new(owner, key.object_id), info));
+            }
+        }))
+    }
+}
+
+/// Deletes all but the latest entry of an object's history, and the latest entry as well if it
+/// records that the object is no longer live.
+fn prune_history<K: Serialize, V>(
+    batch: &mut typed_store::rocks::DBBatch,
+    table: &DBMap<K, V>,
+    mut history: Vec<(K, V)>,
+    is_live: impl Fn(&V) -> bool,
+) -> Result<(), TypedStoreError> {
+    if history.last().is_some_and(|(_, latest)| is_live(latest)) {
+        history.pop();
+    }
+    batch.delete_batch(table, history.into_iter().map(|(key, _)| key))?;
+    Ok(())
 }
 
 pub struct RpcIndexStore {
@@ -522,9 +800,15 @@ impl RpcIndexStore {
 
     pub fn prune(
         &self,
+        pruned_checkpoint: CheckpointSequenceNumber,
         checkpoint_contents_to_prune: &[CheckpointContents],
+        effects_to_prune: &[TransactionEffects],
     ) -> Result<(), TypedStoreError> {
-        self.tables.prune(checkpoint_contents_to_prune)
+        self.tables.prune(
+            pruned_checkpoint,
+            checkpoint_contents_to_prune,
+            effects_to_prune,
+        )
     }
 
     /// Index a checkpoint and stage the index updated in `pending_updates`.
@@ -597,6 +881,31 @@ impl RpcIndexStore {
     ) -> Result<Option<CoinIndexInfo>, TypedStoreError> {
         self.tables.get_coin_info(coin_type)
     }
+
+    pub fn get_object_history_watermark(
+        &self,
+    ) -> Result<CheckpointSequenceNumber, TypedStoreError> {
+        self.tables.get_object_history_watermark()
+    }
+
+    pub fn get_object_version_at_checkpoint(
+        &self,
+        object_id: ObjectID,
+        checkpoint: CheckpointSequenceNumber,
+    ) -> Result<Option<SequenceNumber>, TypedStoreError> {
+        self.tables
+            .get_object_version_at_checkpoint(object_id, checkpoint)
+    }
+
+    pub fn owner_iter_at_checkpoint(
+        &self,
+        owner: MysAddress,
+        checkpoint: CheckpointSequenceNumber,
+        cursor: Option<ObjectID>,
+    ) -> Result<impl Iterator<Item = (OwnerIndexKey, OwnerIndexInfo)> + '_, TypedStoreError> {
+        self.tables
+            .owner_iter_at_checkpoint(owner, checkpoint, cursor)
+    }
 }
 
 fn try_create_dynamic_field_info(
@@ -679,6 +988,7 @@ fn try_create_coin_index_info(object: &Object) -> Option<(CoinIndexKey, CoinInde
 struct RpcParLiveObjectSetIndexer<'a> {
     tables: &'a IndexStoreTables,
     coin_index: &'a Mutex<HashMap<CoinIndexKey, CoinIndexInfo>>,
+    history_checkpoint: CheckpointSequenceNumber,
     epoch_store: &'a AuthorityPerEpochStore,
     package_store: &'a Arc<dyn BackingPackageStore + Send + Sync>,
 }
@@ -687,6 +997,7 @@ struct RpcLiveObjectIndexer<'a> {
     tables: &'a IndexStoreTables,
     batch: typed_store::rocks::DBBatch,
     coin_index: &'a Mutex<HashMap<CoinIndexKey, CoinIndexInfo>>,
+    history_checkpoint: CheckpointSequenceNumber,
     resolver: Box<dyn LayoutResolver + 'a>,
 }
 
@@ -698,6 +1009,7 @@ impl<'a> ParMakeLiveObjectIndexer for RpcParLiveObjectSetIndexer<'a> {
             tables: self.tables,
             batch: self.tables.owner.batch(),
             coin_index: self.coin_index,
+            history_checkpoint: self.history_checkpoint,
             resolver: self
                 .epoch_store
                 .executor()
@@ -708,11 +1020,23 @@ impl<'a> ParMakeLiveObjectIndexer for RpcParLiveObjectSetIndexer<'a> {
 
 impl<'a> LiveObjectIndexer for RpcLiveObjectIndexer<'a> {
     fn index_object(&mut self, object: Object) -> Result<(), StorageError> {
+        // Object History Index
+        let version_key = ObjectVersionKey::new(object.id(), self.history_checkpoint);
+        self.batch.insert_batch(
+            &self.tables.object_versions,
+            [(version_key, ObjectVersionInfo::new(&object))],
+        )?;
+
         match object.owner {
             // Owner Index
             Owner::AddressOwner(owner) => {
                 let owner_key = OwnerIndexKey::new(owner, object.id());
                 let owner_info = OwnerIndexInfo::new(&object);
+                let history_key = OwnerHistoryKey::new(owner, object.id(), self.history_checkpoint);
+                self.batch.insert_batch(
+                    &self.tables.owner_history,
+                    [(history_key, Some(owner_info.clone()))],
+                )?;
                 self.batch
                     .insert_batch(&self.tables.owner, [(owner_key, owner_info)])?;
             }
@@ -764,3 +1088,223 @@ impl<'a> LiveObjectIndexer for RpcLiveObjectIndexer<'a> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use move_core_types::annotated_value::MoveDatatypeLayout;
+    use mys_types::error::MysError;
+    use mys_types::test_checkpoint_data_builder::TestCheckpointDataBuilder;
+
+    struct NoLayouts;
+
+    impl LayoutResolver for NoLayouts {
+        fn get_annotated_layout(
+            &mut self,
+            struct_tag: &StructTag,
+        ) -> Result<MoveDatatypeLayout, MysError> {
+            Err(MysError::FailObjectLayout {
+                st: struct_tag.to_string(),
+            })
+        }
+    }
+
+    fn index(tables: &IndexStoreTables, checkpoint: &CheckpointData) {
+        tables
+            .index_checkpoint(checkpoint, &mut NoLayouts)
+            .unwrap()
+            .write()
+            .unwrap();
+    }
+
+    fn object_id(object_idx: u64) -> ObjectID {
+        TestCheckpointDataBuilder::derive_object_id(object_idx)
+    }
+
+    fn version_at(
+        tables: &IndexStoreTables,
+        object_idx: u64,
+        checkpoint: CheckpointSequenceNumber,
+    ) -> Option<SequenceNumber> {
+        tables
+            .get_object_version_at_checkpoint(object_id(object_idx), checkpoint)
+            .unwrap()
+    }
+
+    /// Version of the object written last by the transactions of `checkpoint`.
+    fn output_version(checkpoint: &CheckpointData, object_idx: u64) -> SequenceNumber {
+        checkpoint
+            .transactions
+            .iter()
+            .flat_map(|tx| &tx.output_objects)
+            .filter(|object| object.id() == object_id(object_idx))
+            .last()
+            .unwrap()
+            .version()
+    }
+
+    /// The test objects, ignoring gas objects, owned by `owner_idx` as of `checkpoint`.
+    fn owned_objects(
+        tables: &IndexStoreTables,
+        owner_idx: u8,
+        checkpoint: CheckpointSequenceNumber,
+    ) -> Vec<ObjectID> {
+        let test_objects = [object_id(1), object_id(2), object_id(3)];
+        tables
+            .owner_iter_at_checkpoint(
+                TestCheckpointDataBuilder::derive_address(owner_idx),
+                checkpoint,
+                None,
+            )
+            .unwrap()
+            .map(|(key, _)| key.object_id)
+            .filter(|id| test_objects.contains(id))
+            .collect()
+    }
+
+    fn history_checkpoints(tables: &IndexStoreTables, object_idx: u64) -> Vec<u64> {
+        let id = object_id(object_idx);
+        tables
+            .object_versions
+            .safe_range_iter(ObjectVersionKey::new(id, 0)..=ObjectVersionKey::new(id, u64::MAX))
+            .map(|entry| entry.unwrap().0.checkpoint)
+            .collect()
+    }
+
+    /// Builds three checkpoints:
+    /// 1. Objects 1 and 3 are created, and object 1 is transferred twice.
+    /// 2. Object 1 is wrapped, object 2 is created and object 3 is transferred.
+    /// 3. Object 2 is deleted and object 3 is transferred again.
+    fn build_checkpoints() -> Vec<CheckpointData> {
+        let mut builder = TestCheckpointDataBuilder::new(1)
+            .start_transaction(0)
+            .create_owned_object(1)
+            .create_owned_object(3)
+            .finish_transaction()
+            .start_transaction(0)
+            .transfer_object(1, 1)
+            .finish_transaction()
+            .start_transaction(1)
+            .transfer_object(1, 2)
+            .finish_transaction();
+        let checkpoint_1 = builder.build_checkpoint();
+
+        let mut builder = builder
+            .start_transaction(2)
+            .wrap_object(1)
+            .finish_transaction()
+            .start_transaction(0)
+            .create_owned_object(2)
+            .transfer_object(3, 1)
+            .finish_transaction();
+        let checkpoint_2 = builder.build_checkpoint();
+
+        let mut builder = builder
+            .start_transaction(0)
+            .delete_object(2)
+            .finish_transaction()
+            .start_transaction(1)
+            .transfer_object(3, 2)
+            .finish_transaction();
+        let checkpoint_3 = builder.build_checkpoint();
+
+        vec![checkpoint_1, checkpoint_2, checkpoint_3]
+    }
+
+    #[tokio::test]
+    async fn test_object_history_index() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let tables = IndexStoreTables::open(tempdir.path());
+        let checkpoints = build_checkpoints();
+        for checkpoint in &checkpoints {
+            index(&tables, checkpoint);
+        }
+
+        // Several transfers within a single checkpoint only record the final version and owner.
+        assert_eq!(
+            version_at(&tables, 1, 1),
+            Some(output_version(&checkpoints[0], 1))
+        );
+        assert_eq!(owned_objects(&tables, 0, 1), vec![object_id(3)]);
+        assert!(owned_objects(&tables, 1, 1).is_empty());
+        assert_eq!(owned_objects(&tables, 2, 1), vec![object_id(1)]);
+
+        // Wrapped and deleted objects are reported as absent.
+        assert_eq!(version_at(&tables, 1, 2), None);
+        assert_eq!(version_at(&tables, 1, 3), None);
+        assert!(owned_objects(&tables, 2, 2).is_empty());
+        assert_eq!(version_at(&tables, 2, 1), None);
+        assert_eq!(
+            version_at(&tables, 2, 2),
+            Some(output_version(&checkpoints[1], 2))
+        );
+        assert_eq!(version_at(&tables, 2, 3), None);
+        assert_eq!(owned_objects(&tables, 0, 2), vec![object_id(2)]);
+        assert!(owned_objects(&tables, 0, 3).is_empty());
+
+        // Ownership of an object follows it from account to account.
+        assert_eq!(owned_objects(&tables, 1, 2), vec![object_id(3)]);
+        assert!(owned_objects(&tables, 1, 3).is_empty());
+        assert_eq!(owned_objects(&tables, 2, 3), vec![object_id(3)]);
+        assert_eq!(
+            version_at(&tables, 3, 3),
+            Some(output_version(&checkpoints[2], 3))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_object_history_pruning() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let tables = IndexStoreTables::open(tempdir.path());
+        let checkpoints = build_checkpoints();
+        for checkpoint in &checkpoints {
+            index(&tables, checkpoint);
+        }
+
+        let pruned = &checkpoints[..2];
+        let contents: Vec<_> = pruned
+            .iter()
+            .map(|checkpoint| checkpoint.checkpoint_contents.clone())
+            .collect();
+        let effects: Vec<_> = pruned
+            .iter()
+            .flat_map(|checkpoint| &checkpoint.transactions)
+            .map(|tx| tx.effects.clone())
+            .collect();
+        tables.prune(2, &contents, &effects).unwrap();
+
+        assert_eq!(tables.get_object_history_watermark().unwrap(), 2);
+
+        // The history of wrapped objects is dropped entirely, while live objects keep their
+        // latest entry as of the watermark.
+        assert!(history_checkpoints(&tables, 1).is_empty());
+        assert_eq!(history_checkpoints(&tables, 2), vec![2, 3]);
+        assert_eq!(history_checkpoints(&tables, 3), vec![2, 3]);
+
+        let owner_history = |owner_idx: u8, object_idx: u64| -> Vec<u64> {
+            let owner = TestCheckpointDataBuilder::derive_address(owner_idx);
+            let id = object_id(object_idx);
+            tables
+                .owner_history
+                .safe_range_iter(
+                    OwnerHistoryKey::new(owner, id, 0)..=OwnerHistoryKey::new(owner, id, u64::MAX),
+                )
+                .map(|entry| entry.unwrap().0.checkpoint)
+                .collect()
+        };
+        assert!(owner_history(0, 3).is_empty());
+        assert_eq!(owner_history(1, 3), vec![2, 3]);
+        assert!(owner_history(2, 1).is_empty());
+
+        // Queries at or above the watermark are answered just as before pruning.
+        assert_eq!(version_at(&tables, 1, 2), None);
+        assert_eq!(
+            version_at(&tables, 3, 2),
+            Some(output_version(&checkpoints[1], 3))
+        );
+        assert_eq!(owned_objects(&tables, 0, 2), vec![object_id(2)]);
+        assert_eq!(owned_objects(&tables, 1, 2), vec![object_id(3)]);
+        assert!(owned_objects(&tables, 2, 2).is_empty());
+        assert_eq!(owned_objects(&tables, 2, 3), vec![object_id(3)]);
+    }
+}
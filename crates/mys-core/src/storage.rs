@@ -6,6 +6,7 @@ use parking_lot::Mutex;
 use std::sync::Arc;
 use mys_types::base_types::ObjectID;
 use mys_types::base_types::MysAddress;
+use mys_types::base_types::SequenceNumber;
 use mys_types::base_types::TransactionDigest;
 use mys_types::committee::Committee;
 use mys_types::committee::EpochId;
@@ -528,4 +529,40 @@ impl RpcIndexes for RpcIndexStore {
             )
             .pipe(Ok)
     }
+
+    fn get_lowest_available_checkpoint_object_history(
+        &self,
+    ) -> mys_types::storage::error::Result<CheckpointSequenceNumber> {
+        Ok(self.get_object_history_watermark()?)
+    }
+
+    fn get_object_version_at_checkpoint(
+        &self,
+        object_id: ObjectID,
+        checkpoint: CheckpointSequenceNumber,
+    ) -> mys_types::storage::error::Result<Option<SequenceNumber>> {
+        Ok(self.get_object_version_at_checkpoint(object_id, checkpoint)?)
+    }
+
+    fn account_owned_objects_info_at_checkpoint_iter(
+        &self,
+        owner: MysAddress,
+        checkpoint: CheckpointSequenceNumber,
+        cursor: Option<ObjectID>,
+    ) -> Result<Box<dyn Iterator<Item = AccountOwnedObjectInfo> + '_>> {
+        let iter = self
+            .owner_iter_at_checkpoint(owner, checkpoint, cursor)?
+            .map(
+                |(OwnerIndexKey { owner, object_id }, OwnerIndexInfo { version, type_ })| {
+                    AccountOwnedObjectInfo {
+                        owner,
+                        object_id,
+                        version,
+                        type_,
+                    }
+                },
+            );
+
+        Ok(Box::new(iter) as _)
+    }
 }
@@ -10,6 +10,7 @@ use dashmap::DashMap;
 use fs::File;
 use prometheus::IntGauge;
 use std::fs;
+use std::hash::Hash;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::ops::Add;
 use std::sync::Arc;
@@ -23,6 +24,7 @@ use mysten_metrics::spawn_monitored_task;
 use rand::Rng;
 use std::fmt::Debug;
 use std::time::{Duration, Instant, SystemTime};
+use mys_types::base_types::MysAddress;
 use mys_types::traffic_control::{PolicyConfig, PolicyType, RemoteFirewallConfig, Weight};
 use tokio::sync::mpsc;
 use tokio::sync::mpsc::error::TrySendError;
@@ -31,12 +33,15 @@ use tracing::{debug, error, info, trace, warn};
 pub const METRICS_INTERVAL_SECS: u64 = 2;
 pub const DEFAULT_DRAIN_TIMEOUT_SECS: u64 = 300;
 
-type Blocklist = Arc<DashMap<IpAddr, SystemTime>>;
+type Blocklist<K = IpAddr> = Arc<DashMap<K, SystemTime>>;
 
 #[derive(Clone)]
 struct Blocklists {
     clients: Blocklist,
     proxied_clients: Blocklist,
+    /// Addresses blocked by sender-keyed policies. Checked against both
+    /// the sender and the gas payer of incoming transactions
+    senders: Blocklist<MysAddress>,
 }
 
 #[derive(Clone)]
@@ -73,6 +78,10 @@ impl Debug for TrafficController {
                 "proxy_ip_blocklist_len",
                 &self.metrics.proxy_ip_blocklist_len.get(),
             )
+            .field(
+                "sender_blocklist_len",
+                &self.metrics.sender_blocklist_len.get(),
+            )
             .finish()
     }
 }
@@ -126,6 +135,7 @@ impl TrafficController {
         let blocklists = Blocklists {
             clients: Arc::new(DashMap::new()),
             proxied_clients: Arc::new(DashMap::new()),
+            senders: Arc::new(DashMap::new()),
         };
         let tally_loop_blocklists = blocklists.clone();
         let clear_loop_blocklists = blocklists.clone();
@@ -207,35 +217,64 @@ impl TrafficController {
 
     /// Handle check with dry-run mode considered
     pub async fn check(&self, client: &Option<IpAddr>, proxied_client: &Option<IpAddr>) -> bool {
-        let check_with_dry_run_maybe = |allowed| -> bool {
-            match (allowed, self.dry_run_mode()) {
-                // check succeeded
-                (true, _) => true,
-                // check failed while in dry-run mode
-                (false, true) => {
-                    debug!("Dry run mode: Blocked request from client {:?}", client);
-                    self.metrics.num_dry_run_blocked_requests.inc();
-                    true
-                }
-                // check failed
-                (false, false) => false,
-            }
-        };
-
         match &self.acl {
             Acl::Allowlist(allowlist) => {
                 let allowed = client.is_none() || allowlist.contains(&client.unwrap());
-                check_with_dry_run_maybe(allowed)
+                self.check_with_dry_run_maybe(allowed, client)
             }
             Acl::Blocklists(blocklists) => {
                 let allowed = self
                     .check_blocklists(blocklists, client, proxied_client)
                     .await;
-                check_with_dry_run_maybe(allowed)
+                self.check_with_dry_run_maybe(allowed, client)
             }
         }
     }
 
+    /// Handle check of the sender and gas payer of a transaction against
+    /// the blocklist populated by sender-keyed policies, with dry-run mode
+    /// considered
+    pub async fn check_sender(
+        &self,
+        sender: &Option<MysAddress>,
+        gas_payer: &Option<MysAddress>,
+    ) -> bool {
+        match &self.acl {
+            // The allowlist is IP based, so there is nothing to check
+            Acl::Allowlist(_) => true,
+            Acl::Blocklists(blocklists) => {
+                let sender_check = self.check_and_clear_blocklist(
+                    sender,
+                    blocklists.senders.clone(),
+                    &self.metrics.sender_blocklist_len,
+                );
+                let gas_payer_check = self.check_and_clear_blocklist(
+                    gas_payer,
+                    blocklists.senders.clone(),
+                    &self.metrics.sender_blocklist_len,
+                );
+                let (sender_check, gas_payer_check) =
+                    futures::future::join(sender_check, gas_payer_check).await;
+                self.check_with_dry_run_maybe(sender_check && gas_payer_check, sender)
+            }
+        }
+    }
+
+    fn check_with_dry_run_maybe(&self, allowed: bool, client: &impl Debug) -> bool {
+        match (allowed, self.dry_run_mode()) {
+            // check succeeded
+            (true, _) => true,
+            // check failed while in dry-run mode
+            (false, true) => {
+                debug!("Dry run mode: Blocked request from client {:?}", client);
+                self.metrics.num_dry_run_blocked_requests.inc();
+                true
+            }
+            // check failed
+            (false, false) => false,
+        }
+    }
+
     /// Returns true if the connection is in blocklist, false otherwise
     async fn check_blocklists(
         &self,
@@ -262,10 +301,10 @@ impl TrafficController {
         self.dry_run_mode
     }
 
-    async fn check_and_clear_blocklist(
+    async fn check_and_clear_blocklist<K: Eq + Hash>(
         &self,
-        client: &Option<IpAddr>,
-        blocklist: Blocklist,
+        client: &Option<K>,
+        blocklist: Blocklist<K>,
         blocklist_len_gauge: &IntGauge,
     ) -> bool {
         let client = match client {
@@ -304,12 +343,16 @@ async fn run_clear_blocklists_loop(blocklists: Blocklists, metrics: Arc<TrafficC
         blocklists
             .proxied_clients
             .retain(|_, expiration| now < *expiration);
+        blocklists.senders.retain(|_, expiration| now < *expiration);
         metrics
             .connection_ip_blocklist_len
             .set(blocklists.clients.len() as i64);
         metrics
             .proxy_ip_blocklist_len
             .set(blocklists.proxied_clients.len() as i64);
+        metrics
+            .sender_blocklist_len
+            .set(blocklists.senders.len() as i64);
     }
 }
 
@@ -470,6 +513,8 @@ async fn handle_error_tally(
             let client = nodefw_client
                 .as_ref()
                 .expect("Expected NodeFWClient for blocklist delegation");
+            // The firewall only deals in IPs, so senders are always blocked locally
+            block_sender(resp.block_sender, policy_config, &blocklists, &metrics);
             return delegate_policy_response(
                 resp,
                 policy_config,
@@ -504,6 +549,8 @@ async fn handle_spam_tally(
             let client = nodefw_client
                 .as_ref()
                 .expect("Expected NodeFWClient for blocklist delegation");
+            // The firewall only deals in IPs, so senders are always blocked locally
+            block_sender(resp.block_sender, policy_config, &blocklists, &metrics);
             return delegate_policy_response(
                 resp,
                 policy_config,
@@ -527,12 +574,14 @@ async fn handle_policy_response(
     let PolicyResponse {
         block_client,
         block_proxied_client,
+        block_sender: sender,
     } = response;
     let PolicyConfig {
         connection_blocklist_ttl_sec,
         proxy_blocklist_ttl_sec,
         ..
     } = policy_config;
+    block_sender(sender, policy_config, &blocklists, &metrics);
     if let Some(client) = block_client {
         if blocklists
             .clients
@@ -565,6 +614,30 @@ async fn handle_policy_response(
     }
 }
 
+fn block_sender(
+    sender: Option<MysAddress>,
+    policy_config: &PolicyConfig,
+    blocklists: &Blocklists,
+    metrics: &TrafficControllerMetrics,
+) {
+    let Some(sender) = sender else {
+        return;
+    };
+    if blocklists
+        .senders
+        .insert(
+            sender,
+            SystemTime::now() + Duration::from_secs(policy_config.sender_blocklist_ttl_sec),
+        )
+        .is_none()
+    {
+        // Only increment the metric if the sender was not already blocked
+        debug!("Blocking sender: {:?}", sender);
+        metrics.requests_blocked_at_protocol.inc();
+        metrics.sender_blocklist_len.inc();
+    }
+}
+
 async fn delegate_policy_response(
     response: PolicyResponse,
     policy_config: &PolicyConfig,
@@ -575,6 +648,7 @@ async fn delegate_policy_response(
     let PolicyResponse {
         block_client,
         block_proxied_client,
+        ..
     } = response;
     let PolicyConfig {
         connection_blocklist_ttl_sec,
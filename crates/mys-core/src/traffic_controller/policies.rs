@@ -5,15 +5,23 @@
 use std::{collections::HashMap, net::IpAddr, sync::Arc};
 
 use count_min_sketch::CountMinSketch32;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use lru::LruCache;
 use mysten_metrics::spawn_monitored_task;
 use parking_lot::RwLock;
 use std::cmp::Reverse;
 use std::collections::{BinaryHeap, VecDeque};
 use std::fmt::Debug;
 use std::hash::Hash;
+use std::num::NonZeroUsize;
 use std::time::Duration;
 use std::time::{Instant, SystemTime};
-use mys_types::traffic_control::{FreqThresholdConfig, PolicyConfig, PolicyType, Weight};
+use mys_types::base_types::MysAddress;
+use mys_types::traffic_control::{
+    CompositeOperator, CompositePolicyConfig, FreqThresholdConfig, PolicyConfig, PolicyType,
+    SenderKey, SenderRateLimitConfig, TokenBucketConfig, Weight,
+};
 use tracing::{info, trace};
 
 const HIGHEST_RATES_CAPACITY: usize = 20;
@@ -226,6 +234,10 @@ impl TrafficSketch {
 pub struct TrafficTally {
     pub direct: Option<IpAddr>,
     pub through_fullnode: Option<IpAddr>,
+    /// Sender of the transaction carried by the request, if any
+    pub sender: Option<MysAddress>,
+    /// Gas payer of the transaction carried by the request, if any
+    pub gas_payer: Option<MysAddress>,
     pub error_info: Option<(Weight, String)>,
     pub spam_weight: Weight,
     pub timestamp: SystemTime,
@@ -241,6 +253,8 @@ impl TrafficTally {
         Self {
             direct,
             through_fullnode,
+            sender: None,
+            gas_payer: None,
             error_info,
             spam_weight,
             timestamp: SystemTime::now(),
@@ -252,6 +266,15 @@ impl TrafficTally {
 pub struct PolicyResponse {
     pub block_client: Option<IpAddr>,
     pub block_proxied_client: Option<IpAddr>,
+    pub block_sender: Option<MysAddress>,
+}
+
+impl PolicyResponse {
+    fn blocks_any(&self) -> bool {
+        self.block_client.is_some()
+            || self.block_proxied_client.is_some()
+            || self.block_sender.is_some()
+    }
 }
 
 pub trait Policy {
@@ -265,6 +288,9 @@ pub trait Policy {
 // not object safe, so we can't use a trait object instead
 pub enum TrafficControlPolicy {
     FreqThreshold(FreqThresholdPolicy),
+    TokenBucket(TokenBucketPolicy),
+    SenderRateLimit(SenderRateLimitPolicy),
+    Composite(CompositePolicy),
     NoOp(NoOpPolicy),
     // Test policies below this point
     TestNConnIP(TestNConnIPPolicy),
@@ -276,6 +302,9 @@ impl Policy for TrafficControlPolicy {
         match self {
             TrafficControlPolicy::NoOp(policy) => policy.handle_tally(tally),
             TrafficControlPolicy::FreqThreshold(policy) => policy.handle_tally(tally),
+            TrafficControlPolicy::TokenBucket(policy) => policy.handle_tally(tally),
+            TrafficControlPolicy::SenderRateLimit(policy) => policy.handle_tally(tally),
+            TrafficControlPolicy::Composite(policy) => policy.handle_tally(tally),
             TrafficControlPolicy::TestNConnIP(policy) => policy.handle_tally(tally),
             TrafficControlPolicy::TestPanicOnInvocation(policy) => policy.handle_tally(tally),
         }
@@ -285,6 +314,9 @@ impl Policy for TrafficControlPolicy {
         match self {
             TrafficControlPolicy::NoOp(policy) => policy.policy_config(),
             TrafficControlPolicy::FreqThreshold(policy) => policy.policy_config(),
+            TrafficControlPolicy::TokenBucket(policy) => policy.policy_config(),
+            TrafficControlPolicy::SenderRateLimit(policy) => policy.policy_config(),
+            TrafficControlPolicy::Composite(policy) => policy.policy_config(),
             TrafficControlPolicy::TestNConnIP(policy) => policy.policy_config(),
             TrafficControlPolicy::TestPanicOnInvocation(policy) => policy.policy_config(),
         }
@@ -298,19 +330,35 @@ impl TrafficControlPolicy {
     pub async fn from_error_config(policy_config: PolicyConfig) -> Self {
         Self::from_config(policy_config.clone().error_policy_type, policy_config).await
     }
-    pub async fn from_config(policy_type: PolicyType, policy_config: PolicyConfig) -> Self {
-        match policy_type {
-            PolicyType::NoOp => Self::NoOp(NoOpPolicy::new(policy_config)),
-            PolicyType::FreqThreshold(freq_threshold_config) => Self::FreqThreshold(
-                FreqThresholdPolicy::new(policy_config, freq_threshold_config),
-            ),
-            PolicyType::TestNConnIP(n) => {
-                Self::TestNConnIP(TestNConnIPPolicy::new(policy_config, n).await)
-            }
-            PolicyType::TestPanicOnInvocation => {
-                Self::TestPanicOnInvocation(TestPanicOnInvocationPolicy::new(policy_config))
+    // Returns a boxed future as composite policies are constructed recursively
+    pub fn from_config(
+        policy_type: PolicyType,
+        policy_config: PolicyConfig,
+    ) -> BoxFuture<'static, Self> {
+        async move {
+            match policy_type {
+                PolicyType::NoOp => Self::NoOp(NoOpPolicy::new(policy_config)),
+                PolicyType::FreqThreshold(freq_threshold_config) => Self::FreqThreshold(
+                    FreqThresholdPolicy::new(policy_config, freq_threshold_config),
+                ),
+                PolicyType::TokenBucket(token_bucket_config) => {
+                    Self::TokenBucket(TokenBucketPolicy::new(policy_config, token_bucket_config))
+                }
+                PolicyType::SenderRateLimit(sender_rate_limit_config) => Self::SenderRateLimit(
+                    SenderRateLimitPolicy::new(policy_config, sender_rate_limit_config),
+                ),
+                PolicyType::Composite(composite_config) => {
+                    Self::Composite(CompositePolicy::new(policy_config, composite_config).await)
+                }
+                PolicyType::TestNConnIP(n) => {
+                    Self::TestNConnIP(TestNConnIPPolicy::new(policy_config, n).await)
+                }
+                PolicyType::TestPanicOnInvocation => {
+                    Self::TestPanicOnInvocation(TestPanicOnInvocationPolicy::new(policy_config))
+                }
             }
         }
+        .boxed()
     }
 }
 
@@ -408,6 +456,201 @@ impl FreqThresholdPolicy {
         PolicyResponse {
             block_client,
             block_proxied_client,
+            block_sender: None,
+        }
+    }
+
+    fn policy_config(&self) -> &PolicyConfig {
+        &self.config
+    }
+}
+
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn refill(&mut self, now: Instant, capacity: f64, refill_rate: f64) {
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * refill_rate).min(capacity);
+        self.last_refill = now;
+    }
+}
+
+/// A set of token buckets, one per client. Buckets start out full and at
+/// most `max_tracked` of them are kept: once reached, tracking a new client
+/// evicts the least recently used bucket, which is the most likely to have
+/// refilled completely, and a full bucket is indistinguishable from one that
+/// was never used.
+struct TokenBuckets<K: Hash + Eq> {
+    buckets: LruCache<K, TokenBucket>,
+    capacity: f64,
+    refill_rate: f64,
+}
+
+impl<K: Hash + Eq + Copy> TokenBuckets<K> {
+    fn new(capacity: u64, refill_rate: f64, max_tracked: usize) -> Self {
+        Self {
+            buckets: LruCache::new(
+                NonZeroUsize::new(max_tracked.max(1)).expect("Capacity is not zero"),
+            ),
+            capacity: capacity as f64,
+            refill_rate,
+        }
+    }
+
+    /// Takes a token from the client's bucket, returning false if the
+    /// bucket was empty.
+    fn try_acquire(&mut self, key: K) -> bool {
+        let now = Instant::now();
+        let capacity = self.capacity;
+        let bucket = self.buckets.get_or_insert_mut(key, || TokenBucket {
+            tokens: capacity,
+            last_refill: now,
+        });
+        bucket.refill(now, self.capacity, self.refill_rate);
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+pub struct TokenBucketPolicy {
+    config: PolicyConfig,
+    clients: TokenBuckets<IpAddr>,
+    proxied_clients: TokenBuckets<IpAddr>,
+}
+
+impl TokenBucketPolicy {
+    pub fn new(
+        config: PolicyConfig,
+        TokenBucketConfig {
+            client_capacity,
+            client_refill_rate,
+            proxied_client_capacity,
+            proxied_client_refill_rate,
+            max_tracked_clients,
+        }: TokenBucketConfig,
+    ) -> Self {
+        Self {
+            config,
+            clients: TokenBuckets::new(client_capacity, client_refill_rate, max_tracked_clients),
+            proxied_clients: TokenBuckets::new(
+                proxied_client_capacity,
+                proxied_client_refill_rate,
+                max_tracked_clients,
+            ),
+        }
+    }
+
+    pub fn handle_tally(&mut self, tally: TrafficTally) -> PolicyResponse {
+        PolicyResponse {
+            block_client: tally
+                .direct
+                .filter(|client| !self.clients.try_acquire(*client)),
+            block_proxied_client: tally
+                .through_fullnode
+                .filter(|client| !self.proxied_clients.try_acquire(*client)),
+            block_sender: None,
+        }
+    }
+
+    fn policy_config(&self) -> &PolicyConfig {
+        &self.config
+    }
+}
+
+pub struct SenderRateLimitPolicy {
+    config: PolicyConfig,
+    key: SenderKey,
+    senders: TokenBuckets<MysAddress>,
+}
+
+impl SenderRateLimitPolicy {
+    pub fn new(
+        config: PolicyConfig,
+        SenderRateLimitConfig {
+            key,
+            capacity,
+            refill_rate,
+            max_tracked_senders,
+        }: SenderRateLimitConfig,
+    ) -> Self {
+        Self {
+            config,
+            key,
+            senders: TokenBuckets::new(capacity, refill_rate, max_tracked_senders),
+        }
+    }
+
+    pub fn handle_tally(&mut self, tally: TrafficTally) -> PolicyResponse {
+        let sender = match self.key {
+            SenderKey::Sender => tally.sender,
+            SenderKey::GasPayer => tally.gas_payer,
+        };
+        PolicyResponse {
+            block_sender: sender.filter(|sender| !self.senders.try_acquire(*sender)),
+            ..Default::default()
+        }
+    }
+
+    fn policy_config(&self) -> &PolicyConfig {
+        &self.config
+    }
+}
+
+pub struct CompositePolicy {
+    config: PolicyConfig,
+    operator: CompositeOperator,
+    policies: Vec<TrafficControlPolicy>,
+}
+
+impl CompositePolicy {
+    pub async fn new(
+        config: PolicyConfig,
+        CompositePolicyConfig { operator, policies }: CompositePolicyConfig,
+    ) -> Self {
+        let mut children = Vec::with_capacity(policies.len());
+        for policy_type in policies {
+            children.push(TrafficControlPolicy::from_config(policy_type, config.clone()).await);
+        }
+        Self {
+            config,
+            operator,
+            policies: children,
+        }
+    }
+
+    pub fn handle_tally(&mut self, tally: TrafficTally) -> PolicyResponse {
+        // Every policy sees every tally so that their state stays up to date,
+        // regardless of whether their decision ends up mattering
+        let responses: Vec<_> = self
+            .policies
+            .iter_mut()
+            .map(|policy| policy.handle_tally(tally.clone()))
+            .collect();
+        self.combine(responses)
+    }
+
+    /// Combines the decisions of the policies on a tally. Policies may be keyed
+    /// on different entities of the tally, e.g. one on its IP and another one on
+    /// its sender, so `And` does not require the policies to name the same key:
+    /// it blocks the entities named by the policies only if every policy decided
+    /// to block at least one entity of the tally.
+    fn combine(&self, responses: Vec<PolicyResponse>) -> PolicyResponse {
+        if self.operator == CompositeOperator::And
+            && !responses.iter().all(PolicyResponse::blocks_any)
+        {
+            return PolicyResponse::default();
+        }
+        PolicyResponse {
+            block_client: responses.iter().find_map(|r| r.block_client),
+            block_proxied_client: responses.iter().find_map(|r| r.block_proxied_client),
+            block_sender: responses.iter().find_map(|r| r.block_sender),
         }
     }
 
@@ -477,6 +720,7 @@ impl TestNConnIPPolicy {
                 None
             },
             block_proxied_client: None,
+            block_sender: None,
         }
     }
 
@@ -541,6 +785,8 @@ mod tests {
         let alice = TrafficTally {
             direct: Some(IpAddr::V4(Ipv4Addr::new(8, 7, 6, 5))),
             through_fullnode: Some(IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4))),
+            sender: None,
+            gas_payer: None,
             error_info: None,
             spam_weight: Weight::one(),
             timestamp: SystemTime::now(),
@@ -548,6 +794,8 @@ mod tests {
         let bob = TrafficTally {
             direct: Some(IpAddr::V4(Ipv4Addr::new(8, 7, 6, 5))),
             through_fullnode: Some(IpAddr::V4(Ipv4Addr::new(4, 3, 2, 1))),
+            sender: None,
+            gas_payer: None,
             error_info: None,
             spam_weight: Weight::one(),
             timestamp: SystemTime::now(),
@@ -555,6 +803,8 @@ mod tests {
         let charlie = TrafficTally {
             direct: Some(IpAddr::V4(Ipv4Addr::new(8, 7, 6, 5))),
             through_fullnode: Some(IpAddr::V4(Ipv4Addr::new(5, 6, 7, 8))),
+            sender: None,
+            gas_payer: None,
             error_info: None,
             spam_weight: Weight::one(),
             timestamp: SystemTime::now(),
@@ -674,6 +924,174 @@ mod tests {
         assert_eq!(proxied_rate, 1);
     }
 
+    #[sim_test]
+    async fn test_token_bucket_policy() {
+        // Allow bursts of 3 requests per proxied client, refilled at 1 per second
+        let mut policy = TokenBucketPolicy::new(
+            PolicyConfig::default(),
+            TokenBucketConfig {
+                proxied_client_capacity: 3,
+                proxied_client_refill_rate: 1.0,
+                ..Default::default()
+            },
+        );
+        let alice = TrafficTally::new(
+            Some(IpAddr::V4(Ipv4Addr::new(8, 7, 6, 5))),
+            Some(IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4))),
+            None,
+            Weight::one(),
+        );
+
+        for _ in 0..3 {
+            let response = policy.handle_tally(alice.clone());
+            assert_eq!(response.block_client, None);
+            assert_eq!(response.block_proxied_client, None);
+        }
+        let response = policy.handle_tally(alice.clone());
+        assert_eq!(response.block_client, None);
+        assert_eq!(response.block_proxied_client, alice.through_fullnode);
+
+        // a single token is refilled after a second
+        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+        let response = policy.handle_tally(alice.clone());
+        assert_eq!(response.block_proxied_client, None);
+        let response = policy.handle_tally(alice.clone());
+        assert_eq!(response.block_proxied_client, alice.through_fullnode);
+    }
+
+    #[sim_test]
+    async fn test_sender_rate_limit_policy() {
+        let mut policy = SenderRateLimitPolicy::new(
+            PolicyConfig::default(),
+            SenderRateLimitConfig {
+                capacity: 2,
+                refill_rate: 1.0,
+                ..Default::default()
+            },
+        );
+        // alice and bob share the same IP, but only alice is spamming
+        let ip = Some(IpAddr::V4(Ipv4Addr::new(8, 7, 6, 5)));
+        let alice = TrafficTally {
+            sender: Some(MysAddress::random_for_testing_only()),
+            ..TrafficTally::new(ip, None, None, Weight::one())
+        };
+        let bob = TrafficTally {
+            sender: Some(MysAddress::random_for_testing_only()),
+            ..TrafficTally::new(ip, None, None, Weight::one())
+        };
+
+        for _ in 0..2 {
+            assert_eq!(policy.handle_tally(alice.clone()).block_sender, None);
+        }
+        let response = policy.handle_tally(alice.clone());
+        assert_eq!(response.block_sender, alice.sender);
+        assert_eq!(response.block_client, None);
+
+        assert_eq!(policy.handle_tally(bob.clone()).block_sender, None);
+
+        // tallies without a transaction are never blocked
+        let anonymous = TrafficTally::new(ip, None, None, Weight::one());
+        for _ in 0..5 {
+            assert_eq!(policy.handle_tally(anonymous.clone()).block_sender, None);
+        }
+    }
+
+    #[sim_test]
+    async fn test_composite_policy() {
+        let token_bucket = PolicyType::TokenBucket(TokenBucketConfig {
+            client_capacity: 2,
+            client_refill_rate: 1.0,
+            ..Default::default()
+        });
+        let n_conn = PolicyType::TestNConnIP(4);
+        let client = IpAddr::V4(Ipv4Addr::new(8, 7, 6, 5));
+        let tally = TrafficTally::new(Some(client), None, None, Weight::one());
+
+        // OR blocks as soon as the token bucket is empty
+        let mut policy = CompositePolicy::new(
+            PolicyConfig::default(),
+            CompositePolicyConfig {
+                operator: CompositeOperator::Or,
+                policies: vec![token_bucket.clone(), n_conn.clone()],
+            },
+        )
+        .await;
+        let responses: Vec<_> = (0..4)
+            .map(|_| policy.handle_tally(tally.clone()).block_client)
+            .collect();
+        assert_eq!(responses, vec![None, None, Some(client), Some(client)]);
+
+        // AND only blocks once both policies would
+        let mut policy = CompositePolicy::new(
+            PolicyConfig::default(),
+            CompositePolicyConfig {
+                operator: CompositeOperator::And,
+                policies: vec![token_bucket, n_conn],
+            },
+        )
+        .await;
+        let responses: Vec<_> = (0..4)
+            .map(|_| policy.handle_tally(tally.clone()).block_client)
+            .collect();
+        assert_eq!(responses, vec![None, None, None, Some(client)]);
+    }
+
+    #[sim_test]
+    async fn test_composite_policy_and_with_different_keys() {
+        // The token bucket is keyed on IP and the sender rate limit on sender,
+        // so they never name the same key
+        let token_bucket = PolicyType::TokenBucket(TokenBucketConfig {
+            client_capacity: 2,
+            client_refill_rate: 0.001,
+            ..Default::default()
+        });
+        let sender_rate_limit = PolicyType::SenderRateLimit(SenderRateLimitConfig {
+            capacity: 3,
+            refill_rate: 0.001,
+            ..Default::default()
+        });
+        let mut policy = CompositePolicy::new(
+            PolicyConfig::default(),
+            CompositePolicyConfig {
+                operator: CompositeOperator::And,
+                policies: vec![token_bucket, sender_rate_limit],
+            },
+        )
+        .await;
+        let client = IpAddr::V4(Ipv4Addr::new(8, 7, 6, 5));
+        let tally = TrafficTally {
+            sender: Some(MysAddress::random_for_testing_only()),
+            ..TrafficTally::new(Some(client), None, None, Weight::one())
+        };
+
+        // the IP bucket is empty after 2 tallies, but the sender one only after 3
+        for _ in 0..3 {
+            let response = policy.handle_tally(tally.clone());
+            assert_eq!(response.block_client, None);
+            assert_eq!(response.block_sender, None);
+        }
+        let response = policy.handle_tally(tally.clone());
+        assert_eq!(response.block_client, Some(client));
+        assert_eq!(response.block_sender, tally.sender);
+    }
+
+    #[sim_test]
+    async fn test_token_buckets_max_tracked() {
+        let mut buckets = TokenBuckets::new(1, 0.001, 2);
+        let clients: Vec<_> = (0..3)
+            .map(|i| IpAddr::V4(Ipv4Addr::new(1, 2, 3, i)))
+            .collect();
+        assert!(buckets.try_acquire(clients[0]));
+        assert!(buckets.try_acquire(clients[1]));
+        assert!(!buckets.try_acquire(clients[1]));
+
+        // tracking a third client evicts the least recently used one
+        assert!(buckets.try_acquire(clients[2]));
+        assert_eq!(buckets.buckets.len(), 2);
+        assert!(!buckets.buckets.contains(&clients[0]));
+        assert!(!buckets.try_acquire(clients[1]));
+    }
+
     #[sim_test]
     async fn test_traffic_sketch_mem_estimate() {
         // Test for getting a rough estimate of memory usage for the traffic sketch
@@ -1,6 +1,483 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use arc_swap::ArcSwap;
+use mys_config::transaction_deny_config::{
+    DenyListEntry, DenySwitch, DynamicTransactionDenyConfig, TransactionDenyConfig,
+};
+use mys_transaction_checks::deny::check_transaction_for_signing_with_rule;
+use mys_types::base_types::{MysAddress, ObjectID, ObjectRef};
+use mys_types::error::MysResult;
+use mys_types::signature::GenericSignature;
+use mys_types::storage::BackingPackageStore;
+use mys_types::transaction::{InputObjectKind, TransactionData};
+use mysten_metrics::spawn_monitored_task;
+use notify::{RecursiveMode, Watcher};
+use parking_lot::Mutex;
+use prometheus::{
+    register_int_counter_vec_with_registry, register_int_gauge_with_registry, IntCounterVec,
+    IntGauge, Registry,
+};
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+/// Source of updates coming from the watched dynamic deny list file.
+const FILE_SOURCE: &str = "file";
+
 #[cfg(test)]
 #[path = "unit_tests/transaction_deny_tests.rs"]
 mod transaction_deny_tests;
+
+pub struct TransactionDenyMetrics {
+    /// Transactions rejected by the deny config, labelled by the rule that rejected them.
+    rejections: IntCounterVec,
+    /// Changes to the dynamic deny config, labelled by where they came from.
+    updates: IntCounterVec,
+    /// Number of dynamic deny list entries and switches currently in force.
+    active_entries: IntGauge,
+}
+
+impl TransactionDenyMetrics {
+    pub fn new(registry: &Registry) -> Self {
+        Self {
+            rejections: register_int_counter_vec_with_registry!(
+                "transaction_deny_rejections",
+                "Number of transactions rejected by the transaction deny config, by rule",
+                &["rule"],
+                registry,
+            )
+            .unwrap(),
+            updates: register_int_counter_vec_with_registry!(
+                "transaction_deny_updates",
+                "Number of changes applied to the dynamic transaction deny config, by source",
+                &["source"],
+                registry,
+            )
+            .unwrap(),
+            active_entries: register_int_gauge_with_registry!(
+                "transaction_deny_active_entries",
+                "Number of unexpired entries in the dynamic transaction deny config",
+                registry,
+            )
+            .unwrap(),
+        }
+    }
+
+    pub fn new_for_tests() -> Self {
+        Self::new(&Registry::new())
+    }
+}
+
+/// Which dynamic deny list an admin request refers to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DenyListKind {
+    Object,
+    Package,
+    Address,
+}
+
+impl FromStr for DenyListKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "object" => Ok(Self::Object),
+            "package" => Ok(Self::Package),
+            "address" => Ok(Self::Address),
+            _ => Err(anyhow!(
+                "unknown deny list {s:?}, expected one of: object, package, address"
+            )),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct AuditRecord<'a> {
+    timestamp_ms: u64,
+    source: &'a str,
+    action: &'a str,
+    /// The dynamic deny config in force after the change.
+    overrides: &'a DynamicTransactionDenyConfig,
+}
+
+/// Holds the transaction deny config used for signing, made of the static
+/// `TransactionDenyConfig` from the node config plus a `DynamicTransactionDenyConfig` that
+/// can be swapped at runtime through the admin endpoint or by editing a watched file.
+pub struct TransactionDenyManager {
+    base: TransactionDenyConfig,
+    overrides: Mutex<DynamicTransactionDenyConfig>,
+    /// `base` merged with the unexpired entries of `overrides`.
+    effective: ArcSwap<TransactionDenyConfig>,
+    /// When the next dynamic entry expires, at which point `effective` must be rebuilt.
+    next_expiry_ms: AtomicU64,
+    audit_log: Option<Mutex<File>>,
+    metrics: TransactionDenyMetrics,
+}
+
+impl TransactionDenyManager {
+    pub fn new(base: TransactionDenyConfig, metrics: TransactionDenyMetrics) -> Self {
+        let overrides = match base.dynamic_deny_list_path() {
+            Some(path) if path.exists() => read_overrides(path)
+                .unwrap_or_else(|e| {
+                    error!(
+                        "Failed to load dynamic transaction deny config from {}: {e:?}",
+                        path.display()
+                    );
+                    None
+                })
+                .unwrap_or_default(),
+            _ => DynamicTransactionDenyConfig::default(),
+        };
+        let audit_log = base.deny_audit_log_path().and_then(|path| {
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map(Mutex::new)
+                .map_err(|e| {
+                    error!(
+                        "Failed to open transaction deny audit log {}: {e:?}",
+                        path.display()
+                    )
+                })
+                .ok()
+        });
+        let manager = Self {
+            effective: ArcSwap::from_pointee(base.clone()),
+            base,
+            overrides: Mutex::new(overrides),
+            next_expiry_ms: AtomicU64::new(u64::MAX),
+            audit_log,
+            metrics,
+        };
+        manager.install(&manager.overrides.lock(), now_ms());
+        manager
+    }
+
+    /// Returns the deny config currently in force.
+    pub fn load(&self) -> Arc<TransactionDenyConfig> {
+        let now_ms = now_ms();
+        if now_ms >= self.next_expiry_ms.load(Ordering::Relaxed) {
+            self.expire(now_ms);
+        }
+        self.effective.load_full()
+    }
+
+    /// Returns the current dynamic deny config, including entries that have expired but
+    /// have not been cleaned up yet.
+    pub fn overrides(&self) -> DynamicTransactionDenyConfig {
+        self.overrides.lock().clone()
+    }
+
+    /// Checks `tx_data` against the deny config currently in force, counting rejections
+    /// per rule.
+    pub fn check_transaction_for_signing(
+        &self,
+        tx_data: &TransactionData,
+        tx_signatures: &[GenericSignature],
+        input_object_kinds: &[InputObjectKind],
+        receiving_objects: &[ObjectRef],
+        package_store: &dyn BackingPackageStore,
+    ) -> MysResult {
+        check_transaction_for_signing_with_rule(
+            tx_data,
+            tx_signatures,
+            input_object_kinds,
+            receiving_objects,
+            &self.load(),
+            package_store,
+        )
+        .map_err(|denied| {
+            if let Some(rule) = denied.rule {
+                self.metrics
+                    .rejections
+                    .with_label_values(&[rule.as_str()])
+                    .inc();
+            }
+            denied.error
+        })
+    }
+
+    /// Atomically replaces the whole dynamic deny config.
+    pub fn replace(
+        &self,
+        source: &str,
+        overrides: DynamicTransactionDenyConfig,
+    ) -> anyhow::Result<()> {
+        self.update(source, "replace", |current| *current = overrides)
+    }
+
+    /// Adds `id` to a dynamic deny list, or updates its expiry and reason if already present.
+    pub fn add_entry(
+        &self,
+        source: &str,
+        kind: DenyListKind,
+        id: &str,
+        ttl: Option<Duration>,
+        reason: Option<String>,
+    ) -> anyhow::Result<()> {
+        let expires_at_ms = ttl.map(|ttl| now_ms().saturating_add(ttl.as_millis() as u64));
+        let action = format!("add {kind:?} {id}");
+        match kind {
+            DenyListKind::Object | DenyListKind::Package => {
+                let id = ObjectID::from_str(id)?;
+                let entry = DenyListEntry {
+                    id,
+                    expires_at_ms,
+                    reason,
+                };
+                self.update(source, &action, |overrides| {
+                    let list = if kind == DenyListKind::Object {
+                        &mut overrides.object_deny_list
+                    } else {
+                        &mut overrides.package_deny_list
+                    };
+                    upsert(list, entry)
+                })
+            }
+            DenyListKind::Address => {
+                let id = MysAddress::from_str(id)?;
+                let entry = DenyListEntry {
+                    id,
+                    expires_at_ms,
+                    reason,
+                };
+                self.update(source, &action, |overrides| {
+                    upsert(&mut overrides.address_deny_list, entry)
+                })
+            }
+        }
+    }
+
+    /// Removes `id` from a dynamic deny list. Entries of the static config cannot be removed.
+    pub fn remove_entry(&self, source: &str, kind: DenyListKind, id: &str) -> anyhow::Result<()> {
+        let action = format!("remove {kind:?} {id}");
+        match kind {
+            DenyListKind::Object | DenyListKind::Package => {
+                let id = ObjectID::from_str(id)?;
+                self.update(source, &action, |overrides| {
+                    let list = if kind == DenyListKind::Object {
+                        &mut overrides.object_deny_list
+                    } else {
+                        &mut overrides.package_deny_list
+                    };
+                    list.retain(|entry| entry.id != id)
+                })
+            }
+            DenyListKind::Address => {
+                let id = MysAddress::from_str(id)?;
+                self.update(source, &action, |overrides| {
+                    overrides.address_deny_list.retain(|entry| entry.id != id)
+                })
+            }
+        }
+    }
+
+    /// Turns a dynamic feature switch, e.g. `package-publish-disabled`, on or off.
+    pub fn set_switch(
+        &self,
+        source: &str,
+        name: &str,
+        disabled: bool,
+        ttl: Option<Duration>,
+        reason: Option<String>,
+    ) -> anyhow::Result<()> {
+        if !DynamicTransactionDenyConfig::is_switch(name) {
+            return Err(anyhow!("unknown deny switch {name:?}"));
+        }
+        let switch = disabled.then(|| DenySwitch {
+            expires_at_ms: ttl.map(|ttl| now_ms().saturating_add(ttl.as_millis() as u64)),
+            reason,
+        });
+        let action = format!("set {name} = {disabled}");
+        self.update(source, &action, |overrides| {
+            *overrides.switch_mut(name).unwrap() = switch
+        })
+    }
+
+    /// Starts watching the dynamic deny list file, if one is configured, reloading it
+    /// whenever it changes.
+    pub fn start_file_watcher(self: &Arc<Self>) -> anyhow::Result<()> {
+        let Some(path) = self.base.dynamic_deny_list_path().cloned() else {
+            return Ok(());
+        };
+        // Watch the parent directory rather than the file itself, so that the file being
+        // replaced by a rename (as editors and `update` do) is picked up as well.
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let (sender, mut receiver) = mpsc::channel(1);
+        let file_name = path.file_name().map(|name| name.to_owned());
+        let mut watcher =
+            notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+                match res {
+                    Ok(event) => {
+                        if event
+                            .paths
+                            .iter()
+                            .any(|p| p.file_name().map(|name| name.to_owned()) == file_name)
+                        {
+                            // A full channel means a reload is already pending.
+                            let _ = sender.try_send(());
+                        }
+                    }
+                    Err(e) => warn!("Transaction deny list watch error: {e:?}"),
+                }
+            })?;
+        watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+        info!(
+            "Watching {} for transaction deny list changes",
+            path.display()
+        );
+
+        let manager: Weak<Self> = Arc::downgrade(self);
+        spawn_monitored_task!(async move {
+            // Keep the watcher alive for as long as the task runs.
+            let _watcher = watcher;
+            while receiver.recv().await.is_some() {
+                let Some(manager) = manager.upgrade() else {
+                    break;
+                };
+                if !path.exists() {
+                    continue;
+                }
+                let result = read_overrides(&path).and_then(|overrides| match overrides {
+                    Some(overrides) => manager.replace(FILE_SOURCE, overrides),
+                    None => Ok(()),
+                });
+                if let Err(e) = result {
+                    error!(
+                        "Failed to reload transaction deny list from {}: {e:?}",
+                        path.display()
+                    );
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Applies `f` to the dynamic deny config. If it changed, the result is persisted to the
+    /// dynamic deny list file, recorded in the audit log and swapped in.
+    fn update(
+        &self,
+        source: &str,
+        action: &str,
+        f: impl FnOnce(&mut DynamicTransactionDenyConfig),
+    ) -> anyhow::Result<()> {
+        let now_ms = now_ms();
+        let mut overrides = self.overrides.lock();
+        let mut updated = overrides.clone();
+        f(&mut updated);
+        updated.remove_expired(now_ms);
+        if updated == *overrides {
+            return Ok(());
+        }
+        if source != FILE_SOURCE {
+            if let Some(path) = self.base.dynamic_deny_list_path() {
+                write_overrides(path, &updated)?;
+            }
+        }
+        self.install(&updated, now_ms);
+        self.audit(now_ms, source, action, &updated);
+        self.metrics.updates.with_label_values(&[source]).inc();
+        info!(source, action, "Transaction deny config updated");
+        *overrides = updated;
+        Ok(())
+    }
+
+    /// Rebuilds the effective config once entries have expired.
+    fn expire(&self, now_ms: u64) {
+        let overrides = self.overrides.lock();
+        // Another thread may have handled the expiry while we waited for the lock.
+        if now_ms < self.next_expiry_ms.load(Ordering::Relaxed) {
+            return;
+        }
+        self.install(&overrides, now_ms);
+        let mut active = overrides.clone();
+        active.remove_expired(now_ms);
+        self.audit(now_ms, "expiry", "expire", &active);
+        info!("Expired entries removed from transaction deny config");
+    }
+
+    fn install(&self, overrides: &DynamicTransactionDenyConfig, now_ms: u64) {
+        self.effective
+            .store(Arc::new(self.base.with_overrides(overrides, now_ms)));
+        self.next_expiry_ms.store(
+            overrides.next_expiry_ms(now_ms).unwrap_or(u64::MAX),
+            Ordering::Relaxed,
+        );
+        self.metrics
+            .active_entries
+            .set(overrides.active_entry_count(now_ms) as i64);
+    }
+
+    fn audit(
+        &self,
+        now_ms: u64,
+        source: &str,
+        action: &str,
+        overrides: &DynamicTransactionDenyConfig,
+    ) {
+        let Some(audit_log) = &self.audit_log else {
+            return;
+        };
+        let record = AuditRecord {
+            timestamp_ms: now_ms,
+            source,
+            action,
+            overrides,
+        };
+        let result = serde_json::to_string(&record)
+            .map_err(anyhow::Error::from)
+            .and_then(|line| Ok(writeln!(audit_log.lock(), "{line}")?));
+        if let Err(e) = result {
+            error!("Failed to write transaction deny audit log: {e:?}");
+        }
+    }
+}
+
+fn upsert<T: PartialEq>(list: &mut Vec<DenyListEntry<T>>, entry: DenyListEntry<T>) {
+    match list.iter_mut().find(|existing| existing.id == entry.id) {
+        Some(existing) => *existing = entry,
+        None => list.push(entry),
+    }
+}
+
+/// Reads the dynamic deny list file. Returns `None` if the file is empty, which is also what
+/// a watcher may observe while the file is being rewritten in place.
+fn read_overrides(path: &Path) -> anyhow::Result<Option<DynamicTransactionDenyConfig>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    if content.trim().is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(serde_yaml::from_str(&content)?))
+}
+
+fn write_overrides(path: &Path, overrides: &DynamicTransactionDenyConfig) -> anyhow::Result<()> {
+    // Write to a temporary file first so the watcher never observes a partial file.
+    let tmp_path = path.with_extension("tmp");
+    std::fs::write(&tmp_path, serde_yaml::to_string(overrides)?)
+        .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+    std::fs::rename(&tmp_path, path)
+        .with_context(|| format!("Failed to replace {}", path.display()))?;
+    Ok(())
+}
+
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
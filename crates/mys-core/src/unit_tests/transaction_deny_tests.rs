@@ -7,17 +7,16 @@ use crate::authority::auth_unit_test_utils::{
 use crate::authority::test_authority_builder::TestAuthorityBuilder;
 use crate::authority::AuthorityState;
 use crate::test_utils::make_transfer_mys_transaction;
+use crate::transaction_signing_filter::DenyListKind;
 use fastcrypto::ed25519::Ed25519KeyPair;
 use fastcrypto::traits::KeyPair;
 use move_core_types::ident_str;
-use std::path::PathBuf;
-use std::sync::Arc;
 use mys_config::certificate_deny_config::CertificateDenyConfigBuilder;
 use mys_config::transaction_deny_config::{TransactionDenyConfig, TransactionDenyConfigBuilder};
 use mys_swarm_config::genesis_config::{AccountConfig, DEFAULT_GAS_AMOUNT};
 use mys_swarm_config::network_config::NetworkConfig;
 use mys_test_transaction_builder::TestTransactionBuilder;
-use mys_types::base_types::{ObjectID, ObjectRef, MysAddress};
+use mys_types::base_types::{MysAddress, ObjectID, ObjectRef};
 use mys_types::effects::TransactionEffectsAPI;
 use mys_types::error::{MysError, MysResult, UserInputError};
 use mys_types::execution_status::{ExecutionFailureStatus, ExecutionStatus};
@@ -30,6 +29,8 @@ use mys_types::utils::get_zklogin_user_address;
 use mys_types::utils::{
     make_zklogin_tx, to_sender_signed_transaction, to_sender_signed_transaction_with_multi_signers,
 };
+use std::path::PathBuf;
+use std::sync::Arc;
 
 const ACCOUNT_NUM: usize = 5;
 const GAS_OBJECT_COUNT: usize = 15;
@@ -484,3 +485,102 @@ async fn test_certificate_deny() {
         }
     ));
 }
+
+#[tokio::test]
+async fn test_dynamic_address_denied() {
+    let (network_config, state) = setup_test(TransactionDenyConfigBuilder::new().build()).await;
+    let accounts = get_accounts_and_coins(&network_config, &state);
+    let manager = state.transaction_deny_manager();
+
+    let address = accounts[0].0.to_string();
+    manager
+        .add_entry("test", DenyListKind::Address, &address, None, None)
+        .unwrap();
+    assert_denied(&transfer_with_account(&accounts[0], &accounts[0], &state).await);
+
+    manager
+        .remove_entry("test", DenyListKind::Address, &address)
+        .unwrap();
+    assert!(transfer_with_account(&accounts[0], &accounts[0], &state)
+        .await
+        .is_ok());
+}
+
+#[tokio::test]
+async fn test_dynamic_deny_switch_expires() {
+    let (network_config, state) = setup_test(TransactionDenyConfigBuilder::new().build()).await;
+    let accounts = get_accounts_and_coins(&network_config, &state);
+
+    state
+        .transaction_deny_manager()
+        .set_switch(
+            "test",
+            "user-transaction-disabled",
+            true,
+            Some(Duration::from_millis(500)),
+            None,
+        )
+        .unwrap();
+    assert_denied(&transfer_with_account(&accounts[0], &accounts[0], &state).await);
+
+    tokio::time::sleep(Duration::from_millis(600)).await;
+    assert!(transfer_with_account(&accounts[0], &accounts[0], &state)
+        .await
+        .is_ok());
+}
+
+#[tokio::test]
+async fn test_dynamic_deny_list_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let deny_list_path = dir.path().join("deny.yaml");
+    let audit_log_path = dir.path().join("deny-audit.log");
+    let (network_config, state) = setup_test(
+        TransactionDenyConfigBuilder::new()
+            .dynamic_deny_list_path(deny_list_path.clone())
+            .deny_audit_log_path(audit_log_path.clone())
+            .build(),
+    )
+    .await;
+    let accounts = get_accounts_and_coins(&network_config, &state);
+
+    // Changes made through the manager are persisted to the file and audited.
+    let package = ObjectID::random();
+    state
+        .transaction_deny_manager()
+        .add_entry(
+            "test",
+            DenyListKind::Package,
+            &package.to_string(),
+            None,
+            Some("incident".to_string()),
+        )
+        .unwrap();
+    let persisted: DynamicTransactionDenyConfig =
+        serde_yaml::from_str(&std::fs::read_to_string(&deny_list_path).unwrap()).unwrap();
+    assert_eq!(persisted.package_deny_list[0].id, package);
+    let audit_log = std::fs::read_to_string(&audit_log_path).unwrap();
+    assert_eq!(audit_log.lines().count(), 1);
+    assert!(audit_log.contains("incident"));
+
+    // Editing the file is picked up without restarting.
+    let mut overrides = persisted;
+    overrides.address_deny_list.push(DenyListEntry {
+        id: accounts[0].0,
+        expires_at_ms: None,
+        reason: None,
+    });
+    std::fs::write(&deny_list_path, serde_yaml::to_string(&overrides).unwrap()).unwrap();
+    let mut denied = false;
+    for _ in 0..100 {
+        if transfer_with_account(&accounts[0], &accounts[0], &state)
+            .await
+            .is_err()
+        {
+            denied = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(denied);
+    assert_eq!(state.transaction_deny_manager().overrides(), overrides);
+}
@@ -3,20 +3,15 @@
 
 use anyhow::Result;
 use async_trait::async_trait;
-use byteorder::BigEndian;
-use byteorder::ByteOrder;
-use bytes::Bytes;
 use object_store::path::Path;
 use object_store::ObjectStore;
 use serde::{Deserialize, Serialize};
-use std::io::Cursor;
 use mys_archival::{
     create_file_metadata_from_bytes, finalize_manifest, read_manifest_from_bytes, FileType,
-    Manifest, CHECKPOINT_FILE_MAGIC, SUMMARY_FILE_MAGIC,
+    IndexedFiles, IndexedFilesBuilder, Manifest,
 };
 use mys_data_ingestion_core::{create_remote_store_client, Reducer, Worker};
-use mys_storage::blob::{Blob, BlobEncoding};
-use mys_storage::{compress, FileCompression, StorageFormat};
+use mys_storage::FileCompression;
 use mys_types::base_types::{EpochId, ExecutionData};
 use mys_types::full_checkpoint_content::CheckpointData;
 use mys_types::messages_checkpoint::{CheckpointSequenceNumber, FullCheckpointContents};
@@ -52,44 +47,41 @@ impl ArchivalReducer {
             commit_duration_ms: config.commit_duration_seconds * 1000,
         })
     }
-    async fn upload(
-        &self,
-        epoch: EpochId,
-        start: CheckpointSequenceNumber,
-        end: CheckpointSequenceNumber,
-        summary_buffer: Vec<u8>,
-        buffer: Vec<u8>,
-    ) -> Result<()> {
-        let checkpoint_file_path = format!("epoch_{}/{}.chk", epoch, start);
-        let chk_bytes = self
-            .upload_file(
-                Path::from(checkpoint_file_path.clone()),
-                CHECKPOINT_FILE_MAGIC,
-                &buffer,
-            )
-            .await?;
-        let summary_file_path = format!("epoch_{}/{}.sum", epoch, start);
-        let sum_bytes = self
-            .upload_file(
-                Path::from(summary_file_path.clone()),
-                SUMMARY_FILE_MAGIC,
-                &summary_buffer,
-            )
-            .await?;
-        let mut manifest = Self::read_manifest(&self.remote_store).await?;
+    async fn upload(&self, epoch: EpochId, files: IndexedFiles) -> Result<()> {
         let checkpoint_file_metadata = create_file_metadata_from_bytes(
-            chk_bytes,
+            files.contents.clone(),
             FileType::CheckpointContent,
             epoch,
-            start..end,
+            files.checkpoint_range.clone(),
         )?;
         let summary_file_metadata = create_file_metadata_from_bytes(
-            sum_bytes,
+            files.summaries.clone(),
             FileType::CheckpointSummary,
             epoch,
-            start..end,
+            files.checkpoint_range.clone(),
         )?;
-        manifest.update(epoch, end, checkpoint_file_metadata, summary_file_metadata);
+        let mut manifest = Self::read_manifest(&self.remote_store).await?;
+        self.remote_store
+            .put(
+                &manifest.file_path(&checkpoint_file_metadata),
+                files.contents.into(),
+            )
+            .await?;
+        self.remote_store
+            .put(
+                &manifest.file_path(&summary_file_metadata),
+                files.summaries.into(),
+            )
+            .await?;
+        self.remote_store
+            .put(&summary_file_metadata.index_file_path(), files.index.into())
+            .await?;
+        manifest.update(
+            epoch,
+            files.checkpoint_range.end,
+            checkpoint_file_metadata,
+            summary_file_metadata,
+        );
 
         let bytes = finalize_manifest(manifest)?;
         self.remote_store
@@ -97,20 +89,6 @@ impl ArchivalReducer {
             .await?;
         Ok(())
     }
-    async fn upload_file(&self, location: Path, magic: u32, content: &[u8]) -> Result<Bytes> {
-        let mut buffer = vec![0; 4];
-        BigEndian::write_u32(&mut buffer, magic);
-        buffer.push(StorageFormat::Blob.into());
-        buffer.push(FileCompression::Zstd.into());
-        buffer.extend_from_slice(content);
-        let mut compressed_buffer = vec![];
-        let mut cursor = Cursor::new(buffer);
-        compress(&mut cursor, &mut compressed_buffer)?;
-        self.remote_store
-            .put(&location, Bytes::from(compressed_buffer.clone()).into())
-            .await?;
-        Ok(Bytes::from(compressed_buffer))
-    }
 
     pub async fn get_watermark(&self) -> Result<CheckpointSequenceNumber> {
         let manifest = Self::read_manifest(&self.remote_store).await?;
@@ -131,12 +109,10 @@ impl Reducer<CheckpointData> for ArchivalReducer {
         if batch.is_empty() {
             return Err(anyhow::anyhow!("commit batch can't be empty"));
         }
-        let mut summary_buffer = vec![];
-        let mut buffer = vec![];
         let first_checkpoint = &batch[0];
         let epoch = first_checkpoint.checkpoint_summary.epoch;
         let start_checkpoint = first_checkpoint.checkpoint_summary.sequence_number;
-        let mut last_checkpoint = start_checkpoint;
+        let mut files = IndexedFilesBuilder::new(start_checkpoint, FileCompression::Zstd)?;
         for checkpoint in batch {
             let full_checkpoint_contents = FullCheckpointContents::from_contents_and_execution_data(
                 checkpoint.checkpoint_contents.clone(),
@@ -145,20 +121,9 @@ impl Reducer<CheckpointData> for ArchivalReducer {
                     .iter()
                     .map(|t| ExecutionData::new(t.transaction.clone(), t.effects.clone())),
             );
-            let contents_blob = Blob::encode(&full_checkpoint_contents, BlobEncoding::Bcs)?;
-            let summary_blob = Blob::encode(&checkpoint.checkpoint_summary, BlobEncoding::Bcs)?;
-            contents_blob.write(&mut buffer)?;
-            summary_blob.write(&mut summary_buffer)?;
-            last_checkpoint += 1;
+            files.append(&full_checkpoint_contents, &checkpoint.checkpoint_summary)?;
         }
-        self.upload(
-            epoch,
-            start_checkpoint,
-            last_checkpoint,
-            summary_buffer,
-            buffer,
-        )
-        .await?;
+        self.upload(epoch, files.finish()?).await?;
         Ok(())
     }
 
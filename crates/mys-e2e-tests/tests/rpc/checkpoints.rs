@@ -426,6 +426,7 @@ async fn subscribe_checkpoint() {
         read_mask: Some(prost_types::FieldMask {
             paths: vec!["sequence_number".to_owned()],
         }),
+        ..Default::default()
     };
 
     let mut stream = client
@@ -459,3 +460,64 @@ async fn subscribe_checkpoint() {
 
     assert!(count >= 50);
 }
+
+#[sim_test]
+async fn subscribe_transactions_from_start_cursor() {
+    use mys_rpc_api::proto::node::v2alpha::subscription_service_client::SubscriptionServiceClient;
+    use mys_rpc_api::proto::node::v2alpha::{SubscribeTransactionsRequest, TransactionFilter};
+    use tokio_stream::StreamExt;
+
+    let test_cluster = TestClusterBuilder::new().build().await;
+
+    let sender = test_cluster
+        .wallet
+        .get_all_accounts_and_gas_objects()
+        .await
+        .unwrap()[0]
+        .0;
+    let transaction_digest = transfer_coin(&test_cluster.wallet).await;
+
+    let mut client = SubscriptionServiceClient::connect(test_cluster.rpc_url().to_owned())
+        .await
+        .unwrap();
+
+    // Catch up from genesis, only looking at transactions sent by `sender`
+    let request = SubscribeTransactionsRequest {
+        start_cursor: Some(0),
+        filters: vec![TransactionFilter {
+            sender: Some(mys_sdk_types::Address::from(sender).into()),
+            ..Default::default()
+        }],
+        read_mask: Some(prost_types::FieldMask {
+            paths: vec!["digest".to_owned()],
+        }),
+    };
+
+    let mut stream = client
+        .subscribe_transactions(request)
+        .await
+        .unwrap()
+        .into_inner();
+
+    let mut last = 0;
+    tokio::time::timeout(std::time::Duration::from_secs(60), async {
+        while let Some(item) = stream.next().await {
+            let item = item.unwrap();
+            let cursor = item.cursor.unwrap();
+            assert!(cursor >= last);
+            last = cursor;
+
+            let digest = mys_sdk_types::TransactionDigest::try_from(
+                item.transaction.unwrap().digest.as_ref().unwrap(),
+            )
+            .unwrap();
+            if digest == transaction_digest {
+                return;
+            }
+        }
+
+        panic!("subscription ended before transaction {transaction_digest} was seen");
+    })
+    .await
+    .expect("timed out waiting for the transaction in the subscription");
+}
@@ -0,0 +1,438 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Cache of JSON-RPC read responses that can no longer change. Only methods whose results are
+//! immutable once final are cached, and only once their result is final, so entries never need
+//! to be invalidated; they are only evicted to bound the size of the cache.
+
+use crate::config::CacheConfig;
+use crate::metrics::AppMetrics;
+use anyhow::{Context, Result};
+use bytes::Bytes;
+use lru::LruCache;
+use fastcrypto::encoding::{Encoding, Hex};
+use fastcrypto::hash::{HashFunction, Sha256};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::SystemTime;
+use tracing::{info, warn};
+
+/// Methods whose responses may be cached.
+const CACHEABLE_METHODS: &[&str] = &[
+    "mys_getTransactionBlock",
+    "mys_getCheckpoint",
+    "mys_tryGetPastObject",
+    "mys_getNormalizedMoveModulesByPackage",
+    "mys_getNormalizedMoveModule",
+    "mys_getNormalizedMoveStruct",
+    "mys_getNormalizedMoveFunction",
+];
+
+/// Options that make the response to a cacheable method depend on the current state of the
+/// chain: displays are rendered from the latest version of the `Display` object of a type.
+const LIVE_STATE_OPTIONS: &[&str] = &["showDisplay"];
+
+/// Identifies a request by its method and parameters, ignoring its id.
+pub struct CacheKey {
+    method: &'static str,
+    key: String,
+}
+
+impl CacheKey {
+    /// Returns the key of a single JSON-RPC request for a cacheable method, unless one of its
+    /// options reads live state.
+    pub fn from_request(request: &Value) -> Option<Self> {
+        let method = request.get("method")?.as_str()?;
+        let method = *CACHEABLE_METHODS.iter().find(|m| **m == method)?;
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+        if reads_live_state(&params) {
+            return None;
+        }
+        // Objects in `params` serialize with sorted keys, so equal parameters give equal keys.
+        Some(Self {
+            method,
+            key: format!("{method}:{params}"),
+        })
+    }
+
+    pub fn method(&self) -> &'static str {
+        self.method
+    }
+}
+
+/// Whether any of the options in `params`, positional or named, reads live state.
+fn reads_live_state(params: &Value) -> bool {
+    let args: Vec<&Value> = match params {
+        Value::Array(args) => args.iter().collect(),
+        Value::Object(args) => args.values().collect(),
+        _ => vec![],
+    };
+    args.into_iter().any(|arg| {
+        LIVE_STATE_OPTIONS
+            .iter()
+            .any(|option| arg.get(option).and_then(Value::as_bool) == Some(true))
+    })
+}
+
+/// Returns the serialized `result` of a JSON-RPC response, if it can be cached: the request
+/// succeeded and its result cannot change anymore. Requests with options reading live state
+/// have no `CacheKey`, so they never get here.
+pub fn cacheable_result(key: &CacheKey, response: &[u8]) -> Option<Bytes> {
+    let response: Value = serde_json::from_slice(response).ok()?;
+    if response.get("error").is_some() {
+        return None;
+    }
+    let result = response.get("result")?;
+    let is_final = match key.method {
+        // Executed transactions are final once they are included in a checkpoint.
+        "mys_getTransactionBlock" => result.get("checkpoint").is_some_and(|c| !c.is_null()),
+        // A version that does not exist yet may be created later.
+        "mys_tryGetPastObject" => {
+            result.get("status").and_then(Value::as_str) == Some("VersionFound")
+        }
+        // Checkpoints and packages are immutable.
+        _ => true,
+    };
+    is_final.then(|| Bytes::from(result.to_string()))
+}
+
+/// Wraps a cached result into a response to the request with id `id`.
+pub fn response_body(id: &Value, result: &[u8]) -> Bytes {
+    let mut body = format!(r#"{{"jsonrpc":"2.0","id":{id},"result":"#).into_bytes();
+    body.extend_from_slice(result);
+    body.push(b'}');
+    body.into()
+}
+
+pub struct ResponseCache {
+    /// Entries in least recently used order, and their total size.
+    memory: Mutex<(LruCache<String, Bytes>, u64)>,
+    capacity: usize,
+    max_memory_bytes: u64,
+    disk: Option<DiskCache>,
+    metrics: AppMetrics,
+}
+
+impl ResponseCache {
+    pub async fn new(config: &CacheConfig, metrics: AppMetrics) -> Result<Self> {
+        if config.capacity == 0 {
+            anyhow::bail!("Cache capacity must not be zero");
+        }
+        let disk = match &config.disk {
+            Some(disk) => Some(DiskCache::open(disk.path.clone(), disk.max_size_bytes).await?),
+            None => None,
+        };
+        if let Some(disk) = &disk {
+            metrics
+                .cache_disk_bytes
+                .set(disk.index.lock().unwrap().1 as i64);
+        }
+        Ok(Self {
+            memory: Mutex::new((LruCache::unbounded(), 0)),
+            capacity: config.capacity,
+            max_memory_bytes: config.max_memory_bytes,
+            disk,
+            metrics,
+        })
+    }
+
+    /// Looks up the cached result of a request, in memory first and then on disk.
+    pub async fn get(&self, key: &CacheKey) -> Option<Bytes> {
+        let requests = &self.metrics.cache_requests_total;
+        if let Some(result) = self.memory.lock().unwrap().0.get(&key.key).cloned() {
+            requests
+                .with_label_values(&[key.method, "memory_hit"])
+                .inc();
+            return Some(result);
+        }
+        if let Some(result) = self.get_from_disk(key).await {
+            requests.with_label_values(&[key.method, "disk_hit"]).inc();
+            self.insert_in_memory(key, result.clone());
+            return Some(result);
+        }
+        requests.with_label_values(&[key.method, "miss"]).inc();
+        None
+    }
+
+    pub async fn insert(&self, key: &CacheKey, result: Bytes) {
+        self.insert_in_memory(key, result.clone());
+        if let Some(disk) = &self.disk {
+            match disk.insert(&key.key, &result).await {
+                Ok(size) => self.metrics.cache_disk_bytes.set(size as i64),
+                Err(e) => warn!("Failed to write cache entry to disk: {e:#}"),
+            }
+        }
+    }
+
+    /// Inserts an entry in memory, evicting the least recently used entries if there are too
+    /// many of them or they grow too large. Entries larger than the whole memory tier are only
+    /// kept on disk.
+    fn insert_in_memory(&self, key: &CacheKey, result: Bytes) {
+        let size = entry_size(&key.key, &result);
+        if size > self.max_memory_bytes {
+            return;
+        }
+        let (entries, total) = &mut *self.memory.lock().unwrap();
+        if let Some(previous) = entries.put(key.key.clone(), result) {
+            *total -= entry_size(&key.key, &previous);
+        }
+        *total += size;
+        while entries.len() > self.capacity || *total > self.max_memory_bytes {
+            let Some((key, result)) = entries.pop_lru() else {
+                break;
+            };
+            *total -= entry_size(&key, &result);
+        }
+        self.metrics.cache_memory_entries.set(entries.len() as i64);
+        self.metrics.cache_memory_bytes.set(*total as i64);
+    }
+
+    async fn get_from_disk(&self, key: &CacheKey) -> Option<Bytes> {
+        let disk = self.disk.as_ref()?;
+        match disk.get(&key.key).await {
+            Ok(result) => result,
+            Err(e) => {
+                warn!("Failed to read cache entry from disk: {e:#}");
+                None
+            }
+        }
+    }
+}
+
+fn entry_size(key: &str, result: &[u8]) -> u64 {
+    (key.len() + result.len()) as u64
+}
+
+/// Cache entries stored in files named after the SHA-256 hash of their key, which is stable
+/// across restarts and builds. A file holds the key on its first line, to tell hash collisions
+/// apart, followed by the cached result.
+struct DiskCache {
+    dir: PathBuf,
+    max_size_bytes: u64,
+    /// Size of each file by name, in least recently used order, and their total size.
+    index: Mutex<(LruCache<String, u64>, u64)>,
+}
+
+impl DiskCache {
+    async fn open(dir: PathBuf, max_size_bytes: u64) -> Result<Self> {
+        tokio::fs::create_dir_all(&dir)
+            .await
+            .with_context(|| format!("Cannot create cache directory {}", dir.display()))?;
+
+        // Files written by a previous run are indexed from least to most recently modified.
+        // Temporary files of writes interrupted by the end of that run are removed.
+        let mut files = vec![];
+        let mut entries = tokio::fs::read_dir(&dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+            if name.ends_with(".tmp") {
+                if let Err(e) = tokio::fs::remove_file(entry.path()).await {
+                    warn!("Failed to remove temporary cache file {name}: {e}");
+                }
+                continue;
+            }
+            if !is_hash_name(&name) {
+                continue;
+            }
+            let hash = name;
+            let metadata = entry.metadata().await?;
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            files.push((modified, hash, metadata.len()));
+        }
+        files.sort();
+
+        let mut index = LruCache::unbounded();
+        let mut total = 0;
+        for (_, hash, size) in files {
+            index.put(hash, size);
+            total += size;
+        }
+        info!(
+            "Opened response cache in {} with {} entries",
+            dir.display(),
+            index.len()
+        );
+
+        let cache = Self {
+            dir,
+            max_size_bytes,
+            index: Mutex::new((index, total)),
+        };
+        cache.evict().await;
+        Ok(cache)
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Bytes>> {
+        let hash = hash_key(key);
+        if self.index.lock().unwrap().0.get(&hash).is_none() {
+            return Ok(None);
+        }
+        let contents = tokio::fs::read(self.path(&hash)).await?;
+        let Some(newline) = contents.iter().position(|b| *b == b'\n') else {
+            return Ok(None);
+        };
+        if &contents[..newline] != key.as_bytes() {
+            return Ok(None);
+        }
+        Ok(Some(Bytes::from(contents).slice(newline + 1..)))
+    }
+
+    /// Writes an entry, evicting the least recently used entries if the cache grows too large.
+    /// Returns the new size of the cache.
+    async fn insert(&self, key: &str, result: &[u8]) -> Result<u64> {
+        let hash = hash_key(key);
+        let mut contents = Vec::with_capacity(key.len() + 1 + result.len());
+        contents.extend_from_slice(key.as_bytes());
+        contents.push(b'\n');
+        contents.extend_from_slice(result);
+
+        let tmp = self.dir.join(format!("{hash}.tmp"));
+        tokio::fs::write(&tmp, &contents).await?;
+        tokio::fs::rename(&tmp, self.path(&hash)).await?;
+
+        {
+            let (index, total) = &mut *self.index.lock().unwrap();
+            if let Some(previous) = index.put(hash, contents.len() as u64) {
+                *total -= previous;
+            }
+            *total += contents.len() as u64;
+        }
+        Ok(self.evict().await)
+    }
+
+    async fn evict(&self) -> u64 {
+        let (evicted, total) = {
+            let (index, total) = &mut *self.index.lock().unwrap();
+            let mut evicted = vec![];
+            while *total > self.max_size_bytes {
+                let Some((hash, size)) = index.pop_lru() else {
+                    break;
+                };
+                *total -= size;
+                evicted.push(hash);
+            }
+            (evicted, *total)
+        };
+        for hash in evicted {
+            if let Err(e) = tokio::fs::remove_file(self.path(&hash)).await {
+                warn!("Failed to remove evicted cache entry: {e}");
+            }
+        }
+        total
+    }
+
+    fn path(&self, hash: &str) -> PathBuf {
+        self.dir.join(hash)
+    }
+}
+
+/// Hex encoded SHA-256 hash of a key, used as the name of its file.
+fn hash_key(key: &str) -> String {
+    Hex::encode(Sha256::digest(key.as_bytes()).digest)
+}
+
+fn is_hash_name(name: &str) -> bool {
+    name.len() == 2 * Sha256::OUTPUT_SIZE && name.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn key(request: Value) -> CacheKey {
+        CacheKey::from_request(&request).expect("Request should be cacheable")
+    }
+
+    #[test]
+    fn test_cache_key_from_request() {
+        let request = |id, params| json!({"jsonrpc": "2.0", "id": id, "method": "mys_getCheckpoint", "params": params});
+        // The id of the request is not part of the key, its parameters are.
+        assert_eq!(
+            key(request(1, json!(["10"]))).key,
+            key(request(2, json!(["10"]))).key
+        );
+        assert_ne!(
+            key(request(1, json!(["10"]))).key,
+            key(request(1, json!(["11"]))).key
+        );
+        assert_eq!(key(request(1, json!(["10"]))).method(), "mys_getCheckpoint");
+
+        assert!(
+            CacheKey::from_request(&json!({"method": "mys_getObject", "params": []})).is_none()
+        );
+        assert!(CacheKey::from_request(&json!({"params": []})).is_none());
+
+        // Displays are rendered from live state, positional or named options alike.
+        let past_object =
+            |options| json!({"method": "mys_tryGetPastObject", "params": ["0x5", 3, options]});
+        assert!(CacheKey::from_request(&past_object(json!({"showContent": true}))).is_some());
+        assert!(CacheKey::from_request(&past_object(json!({"showDisplay": false}))).is_some());
+        assert!(CacheKey::from_request(&past_object(json!({"showDisplay": true}))).is_none());
+        assert!(CacheKey::from_request(&json!({
+            "method": "mys_tryGetPastObject",
+            "params": {"id": "0x5", "version": 3, "options": {"showDisplay": true}},
+        }))
+        .is_none());
+    }
+
+    #[test]
+    fn test_cacheable_result() {
+        let response = |result: Value| json!({"jsonrpc": "2.0", "id": 1, "result": result});
+        let bytes = |value: Value| serde_json::to_vec(&value).unwrap();
+
+        let transaction = key(json!({"method": "mys_getTransactionBlock", "params": ["A"]}));
+        let checkpointed = json!({"digest": "A", "checkpoint": "5"});
+        assert_eq!(
+            cacheable_result(&transaction, &bytes(response(checkpointed.clone()))),
+            Some(Bytes::from(checkpointed.to_string()))
+        );
+        let pending = response(json!({"digest": "A", "checkpoint": null}));
+        assert_eq!(cacheable_result(&transaction, &bytes(pending)), None);
+        let error = json!({"jsonrpc": "2.0", "id": 1, "error": {"code": -32602}});
+        assert_eq!(cacheable_result(&transaction, &bytes(error)), None);
+        assert_eq!(cacheable_result(&transaction, b"not json"), None);
+
+        let object = key(json!({"method": "mys_tryGetPastObject", "params": ["0x5", 3]}));
+        let found = response(json!({"status": "VersionFound", "details": {}}));
+        assert!(cacheable_result(&object, &bytes(found)).is_some());
+        let not_found = response(json!({"status": "VersionNotFound", "details": []}));
+        assert_eq!(cacheable_result(&object, &bytes(not_found)), None);
+
+        let checkpoint = key(json!({"method": "mys_getCheckpoint", "params": ["5"]}));
+        assert!(cacheable_result(&checkpoint, &bytes(response(json!({"epoch": "0"})))).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_disk_cache_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().to_path_buf();
+        let cache = DiskCache::open(path.clone(), 1024).await.unwrap();
+        assert_eq!(cache.get("a").await.unwrap(), None);
+        cache.insert("a", b"first").await.unwrap();
+        assert_eq!(cache.get("a").await.unwrap(), Some(Bytes::from("first")));
+        let size = cache.insert("b", b"second").await.unwrap();
+        assert_eq!(size, ("a\nfirst".len() + "b\nsecond".len()) as u64);
+
+        // Entries survive a restart, and interrupted writes are cleaned up.
+        tokio::fs::write(path.join(format!("{}.tmp", hash_key("c"))), b"c\nthird")
+            .await
+            .unwrap();
+        drop(cache);
+        let cache = DiskCache::open(path.clone(), 1024).await.unwrap();
+        assert_eq!(cache.get("b").await.unwrap(), Some(Bytes::from("second")));
+        assert_eq!(cache.get("c").await.unwrap(), None);
+        assert!(!path.join(format!("{}.tmp", hash_key("c"))).exists());
+
+        // The least recently used entries are evicted beyond the maximum size.
+        let cache = DiskCache::open(path.clone(), 16).await.unwrap();
+        cache.get("a").await.unwrap();
+        cache.insert("d", b"fourth").await.unwrap();
+        assert_eq!(cache.get("b").await.unwrap(), None);
+        assert!(!cache.path(&hash_key("b")).exists());
+        assert_eq!(cache.get("d").await.unwrap(), Some(Bytes::from("fourth")));
+    }
+}
@@ -1,12 +1,13 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use ipnetwork::IpNetwork;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 use serde_with::DurationSeconds;
-use std::{net::SocketAddr, time::Duration};
+use std::{net::SocketAddr, path::PathBuf, time::Duration};
 use tracing::error;
 use url::Url;
 
@@ -16,8 +17,26 @@ use url::Url;
 pub struct ProxyConfig {
     pub listen_address: SocketAddr,
     pub metrics_address: SocketAddr,
-    pub execution_peer: PeerConfig,
-    pub read_peer: PeerConfig,
+    /// Either a single peer (`address: ...`), or a pool of peers to balance between.
+    pub execution_peer: PeerPoolConfig,
+    /// Either a single peer (`address: ...`), or a pool of peers to balance between.
+    pub read_peer: PeerPoolConfig,
+    /// Active health checks used to eject and readmit peers of both pools.
+    #[serde(default)]
+    pub health_check: HealthCheckConfig,
+    /// For how long after an execution request the reads of the same client are routed to the
+    /// execution peer that served it, so that they observe its effects. 0 disables sticky routing.
+    #[serde_as(as = "DurationSeconds")]
+    #[serde(default = "default_read_your_writes")]
+    pub read_your_writes_seconds: Duration,
+    /// Load balancers and proxies in front of the edge proxy. Clients are identified by the
+    /// rightmost `X-Forwarded-For` address not added by one of these, or by their connection
+    /// address when it does not come from one of these.
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpNetwork>,
+    /// Cache of responses to immutable reads. Disabled when not set.
+    #[serde(default)]
+    pub cache: Option<CacheConfig>,
     /// Maximum number of idle connections to keep in the connection pool.
     /// When set, this limits the number of connections that remain open but unused,
     /// helping to conserve system resources.
@@ -42,10 +61,152 @@ fn default_idle_timeout() -> Duration {
     Duration::from_secs(60)
 }
 
+fn default_read_your_writes() -> Duration {
+    Duration::from_secs(10)
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct PeerConfig {
     pub address: Url,
+    /// Relative share of traffic with the `weighted` balancing strategy.
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum PeerPoolConfig {
+    Single(PeerConfig),
+    Pool(PeerPool),
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PeerPool {
+    pub peers: Vec<PeerConfig>,
+    #[serde(default)]
+    pub balancing: BalancingStrategy,
+}
+
+impl PeerPoolConfig {
+    pub fn peers(&self) -> &[PeerConfig] {
+        match self {
+            PeerPoolConfig::Single(peer) => std::slice::from_ref(peer),
+            PeerPoolConfig::Pool(pool) => &pool.peers,
+        }
+    }
+
+    pub fn balancing(&self) -> BalancingStrategy {
+        match self {
+            PeerPoolConfig::Single(_) => BalancingStrategy::default(),
+            PeerPoolConfig::Pool(pool) => pool.balancing,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum BalancingStrategy {
+    /// Pick a healthy peer at random, in proportion to its weight.
+    #[default]
+    Weighted,
+    /// Pick the healthy peer with the lowest recent upstream latency.
+    LeastLatency,
+}
+
+#[serde_as]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct HealthCheckConfig {
+    #[serde_as(as = "DurationSeconds")]
+    #[serde(default = "default_health_check_interval")]
+    pub interval_seconds: Duration,
+    #[serde_as(as = "DurationSeconds")]
+    #[serde(default = "default_health_check_timeout")]
+    pub timeout_seconds: Duration,
+    /// A peer fails a check if its latest checkpoint is more than this many checkpoints behind
+    /// the highest checkpoint reported by any peer.
+    #[serde(default = "default_max_checkpoint_lag")]
+    pub max_checkpoint_lag: u64,
+    /// Consecutive failed checks or requests after which a peer is ejected.
+    #[serde(default = "default_unhealthy_threshold")]
+    pub unhealthy_threshold: u32,
+    /// Consecutive passed checks after which an ejected peer is readmitted.
+    #[serde(default = "default_healthy_threshold")]
+    pub healthy_threshold: u32,
+}
+
+impl Default for HealthCheckConfig {
+    fn default() -> Self {
+        Self {
+            interval_seconds: default_health_check_interval(),
+            timeout_seconds: default_health_check_timeout(),
+            max_checkpoint_lag: default_max_checkpoint_lag(),
+            unhealthy_threshold: default_unhealthy_threshold(),
+            healthy_threshold: default_healthy_threshold(),
+        }
+    }
+}
+
+fn default_health_check_interval() -> Duration {
+    Duration::from_secs(5)
+}
+
+fn default_health_check_timeout() -> Duration {
+    Duration::from_secs(2)
+}
+
+fn default_max_checkpoint_lag() -> u64 {
+    10
+}
+
+fn default_unhealthy_threshold() -> u32 {
+    3
+}
+
+fn default_healthy_threshold() -> u32 {
+    2
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CacheConfig {
+    /// Maximum number of responses kept in memory.
+    #[serde(default = "default_cache_capacity")]
+    pub capacity: usize,
+    /// Total size of the responses kept in memory above which the least recently used are
+    /// evicted.
+    #[serde(default = "default_memory_cache_size")]
+    pub max_memory_bytes: u64,
+    /// Second tier of the cache, on disk, for responses evicted from or not yet loaded in memory.
+    #[serde(default)]
+    pub disk: Option<DiskCacheConfig>,
+}
+
+fn default_cache_capacity() -> usize {
+    10_000
+}
+
+fn default_memory_cache_size() -> u64 {
+    256 * 1024 * 1024
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct DiskCacheConfig {
+    pub path: PathBuf,
+    /// Total size of the cached responses above which the least recently used are removed.
+    #[serde(default = "default_disk_cache_size")]
+    pub max_size_bytes: u64,
+}
+
+fn default_disk_cache_size() -> u64 {
+    1024 * 1024 * 1024
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
@@ -77,57 +238,61 @@ pub async fn load<P: AsRef<std::path::Path>>(path: P) -> Result<(ProxyConfig, Cl
         .build()
         .expect("Failed to build HTTP/2 client");
 
-    validate_peer_url(&client, &config.read_peer).await?;
-    validate_peer_url(&client, &config.execution_peer).await?;
+    for pool in [&config.read_peer, &config.execution_peer] {
+        if pool.peers().is_empty() {
+            bail!("Peer pools must contain at least one peer");
+        }
+        validate_peer_urls(&client, pool.peers()).await?;
+    }
 
     Ok((config, client))
 }
 
-/// Validate that the given PeerConfig URL has a valid host
-async fn validate_peer_url(client: &Client, peer: &PeerConfig) -> Result<()> {
-    let health_url = peer
-        .address
-        .join("/health")
-        .context("Failed to construct health check URL")?;
-
+/// Validate that the given PeerConfig URLs have valid hosts, waiting until at least one of the
+/// peers is reachable. Unreachable peers are ejected by the health checks later on.
+async fn validate_peer_urls(client: &Client, peers: &[PeerConfig]) -> Result<()> {
     const RETRY_DELAY: Duration = Duration::from_secs(1);
     const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
 
     let mut attempt = 1;
     loop {
-        match client
-            .get(health_url.clone())
-            .timeout(REQUEST_TIMEOUT)
-            .send()
-            .await
-        {
-            Ok(response) => {
-                if response.version() != reqwest::Version::HTTP_2 {
-                    tracing::warn!(
-                        "Peer {} does not support HTTP/2 (using {:?})",
-                        peer.address,
-                        response.version()
-                    );
-                }
+        let mut reachable = false;
+        for peer in peers {
+            let health_url = peer
+                .address
+                .join("/health")
+                .context("Failed to construct health check URL")?;
+            match client.get(health_url).timeout(REQUEST_TIMEOUT).send().await {
+                Ok(response) => {
+                    if response.version() != reqwest::Version::HTTP_2 {
+                        tracing::warn!(
+                            "Peer {} does not support HTTP/2 (using {:?})",
+                            peer.address,
+                            response.version()
+                        );
+                    }
 
-                if !response.status().is_success() {
-                    tracing::warn!(
-                        "Health check failed for peer {} with status {}",
-                        peer.address,
-                        response.status()
+                    if !response.status().is_success() {
+                        tracing::warn!(
+                            "Health check failed for peer {} with status {}",
+                            peer.address,
+                            response.status()
+                        );
+                    }
+                    reachable = true;
+                }
+                Err(e) => {
+                    error!(
+                        "Failed to connect to peer {} (attempt {}): {}",
+                        peer.address, attempt, e
                     );
                 }
-                return Ok(());
-            }
-            Err(e) => {
-                error!(
-                    "Failed to connect to peer {} (attempt {}): {}",
-                    peer.address, attempt, e
-                );
-                tokio::time::sleep(RETRY_DELAY).await;
-                attempt += 1;
-                continue;
             }
         }
+        if reachable {
+            return Ok(());
+        }
+        tokio::time::sleep(RETRY_DELAY).await;
+        attempt += 1;
     }
 }
@@ -1,17 +1,23 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::config::{LoggingConfig, PeerConfig};
+use crate::cache::{self, CacheKey, ResponseCache};
+use crate::config::LoggingConfig;
 use crate::metrics::AppMetrics;
+use crate::peers::{PeerPool, StickySessions};
 use axum::{
     body::Body,
-    extract::{Request, State},
+    extract::{ConnectInfo, Request, State},
     http::request::Parts,
-    http::StatusCode,
+    http::{header, StatusCode},
     response::Response,
 };
 use bytes::Bytes;
+use ipnetwork::IpNetwork;
 use rand::Rng;
+use mys_http::forwarded::client_ip;
+use std::net::SocketAddr;
+use std::sync::Arc;
 use std::time::Instant;
 use tracing::{debug, warn};
 
@@ -33,35 +39,47 @@ impl PeerRole {
 #[derive(Clone)]
 pub struct AppState {
     client: reqwest::Client,
-    read_peer: PeerConfig,
-    execution_peer: PeerConfig,
+    read_peers: Arc<PeerPool>,
+    execution_peers: Arc<PeerPool>,
+    sessions: Arc<StickySessions>,
+    cache: Option<Arc<ResponseCache>>,
     metrics: AppMetrics,
     logging_config: LoggingConfig,
+    trusted_proxies: Arc<Vec<IpNetwork>>,
 }
 
 impl AppState {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         client: reqwest::Client,
-        read_peer: PeerConfig,
-        execution_peer: PeerConfig,
+        read_peers: Arc<PeerPool>,
+        execution_peers: Arc<PeerPool>,
+        sessions: Arc<StickySessions>,
+        cache: Option<Arc<ResponseCache>>,
         metrics: AppMetrics,
         logging_config: LoggingConfig,
+        trusted_proxies: Vec<IpNetwork>,
     ) -> Self {
         Self {
             client,
-            read_peer,
-            execution_peer,
+            read_peers,
+            execution_peers,
+            sessions,
+            cache,
             metrics,
             logging_config,
+            trusted_proxies: Arc::new(trusted_proxies),
         }
     }
 }
 
 pub async fn proxy_handler(
     State(state): State<AppState>,
+    ConnectInfo(remote_address): ConnectInfo<SocketAddr>,
     request: Request<Body>,
 ) -> Result<Response, (StatusCode, String)> {
     let (parts, body) = request.into_parts();
+    let client = client_ip(&parts.headers, remote_address.ip(), &state.trusted_proxies).to_string();
     let body_bytes = match axum::body::to_bytes(body, 10 * 1024 * 1024).await {
         Ok(bytes) => bytes,
         Err(e) => {
@@ -80,22 +98,37 @@ pub async fn proxy_handler(
     {
         Some("mys_executeTransactionBlock") => {
             debug!("Using execution peer");
-            proxy_request(state, parts, body_bytes, PeerRole::Execution).await
+            proxy_request(state, parts, body_bytes, PeerRole::Execution, client, None).await
         }
         _ => {
             let json_body = match serde_json::from_slice::<serde_json::Value>(&body_bytes) {
                 Ok(json_body) => json_body,
                 Err(_) => {
                     debug!("Failed to parse request body as JSON");
-                    return proxy_request(state, parts, body_bytes, PeerRole::Read).await;
+                    return proxy_request(state, parts, body_bytes, PeerRole::Read, client, None)
+                        .await;
                 }
             };
             if let Some("mys_executeTransactionBlock") =
                 json_body.get("method").and_then(|m| m.as_str())
             {
-                proxy_request(state, parts, body_bytes, PeerRole::Execution).await
+                proxy_request(state, parts, body_bytes, PeerRole::Execution, client, None).await
             } else {
-                proxy_request(state, parts, body_bytes, PeerRole::Read).await
+                let cache_key = match &state.cache {
+                    Some(_) => CacheKey::from_request(&json_body),
+                    None => None,
+                };
+                if let (Some(cache), Some(key)) = (&state.cache, &cache_key) {
+                    if let Some(result) = cache.get(key).await {
+                        debug!("Serving {} from cache", key.method());
+                        let id = json_body.get("id").unwrap_or(&serde_json::Value::Null);
+                        return Ok(Response::builder()
+                            .header(header::CONTENT_TYPE, "application/json")
+                            .body(Body::from(cache::response_body(id, &result)))
+                            .unwrap());
+                    }
+                }
+                proxy_request(state, parts, body_bytes, PeerRole::Read, client, cache_key).await
             }
         }
     }
@@ -106,6 +139,8 @@ async fn proxy_request(
     parts: Parts,
     body_bytes: Bytes,
     peer_type: PeerRole,
+    client: String,
+    cache_key: Option<CacheKey>,
 ) -> Result<Response, (StatusCode, String)> {
     debug!(
         "Proxying request: method={:?}, uri={:?}, headers={:?}, body_len={}, peer_type={:?}",
@@ -152,62 +187,117 @@ async fn proxy_request(
         .with_label_values(&[peer_type_str])
         .observe(body_bytes.len() as f64);
 
-    let peer_config = match peer_type {
-        PeerRole::Read => &state.read_peer,
-        PeerRole::Execution => &state.execution_peer,
+    // Reads follow the client's latest execution request to its peer for a while, as long as
+    // that peer is healthy.
+    let sticky_peer = match peer_type {
+        PeerRole::Read => state
+            .sessions
+            .get(&client)
+            .filter(|index| state.execution_peers.is_healthy(*index)),
+        PeerRole::Execution => None,
     };
-
-    let mut target_url = peer_config.address.clone();
-    target_url.set_path(parts.uri.path());
-    if let Some(query) = parts.uri.query() {
-        target_url.set_query(Some(query));
-    }
+    let (mut pool, mut index) = match (&peer_type, sticky_peer) {
+        (PeerRole::Read, Some(index)) => (&state.execution_peers, index),
+        (PeerRole::Read, None) => (&state.read_peers, select_peer(&state.read_peers)),
+        (PeerRole::Execution, _) => (&state.execution_peers, select_peer(&state.execution_peers)),
+    };
+    let mut retried = false;
 
     // remove host header to avoid interfering with reqwest auto-host header
     let mut headers = parts.headers.clone();
     headers.remove("host");
-    let request_builder = state
-        .client
-        .request(parts.method.clone(), target_url)
-        .headers(headers)
-        .body(body_bytes.clone());
-    debug!("Request builder: {:?}", request_builder);
-
-    let upstream_start = Instant::now();
-    let response = match request_builder.send().await {
-        Ok(response) => {
-            let status = response.status().as_u16().to_string();
-            metrics
-                .upstream_response_latency
-                .with_label_values(&[peer_type_str, &status])
-                .observe(upstream_start.elapsed().as_secs_f64());
-            metrics
-                .requests_total
-                .with_label_values(&[peer_type_str, &status])
-                .inc();
-            debug!("Response: {:?}", response);
-            response
+
+    let response = loop {
+        let mut target_url = pool.peer(index).address.clone();
+        target_url.set_path(parts.uri.path());
+        if let Some(query) = parts.uri.query() {
+            target_url.set_query(Some(query));
         }
-        Err(e) => {
-            warn!("Failed to send request: {}", e);
-            metrics
-                .upstream_response_latency
-                .with_label_values(&[peer_type_str, "error"])
-                .observe(upstream_start.elapsed().as_secs_f64());
-            metrics
-                .requests_total
-                .with_label_values(&[peer_type_str, "error"])
-                .inc();
-            if e.is_timeout() {
+
+        let request_builder = state
+            .client
+            .request(parts.method.clone(), target_url)
+            .headers(headers.clone())
+            .body(body_bytes.clone());
+        debug!("Request builder: {:?}", request_builder);
+
+        let upstream_start = Instant::now();
+        // The peer failed to serve the request, either with an error response or with no response.
+        let failure = match request_builder.send().await {
+            Ok(response) => {
+                let status = response.status().as_u16().to_string();
+                metrics
+                    .upstream_response_latency
+                    .with_label_values(&[peer_type_str, &status])
+                    .observe(upstream_start.elapsed().as_secs_f64());
                 metrics
-                    .timeouts_total
-                    .with_label_values(&[peer_type_str])
+                    .requests_total
+                    .with_label_values(&[peer_type_str, &status])
                     .inc();
+                debug!("Response: {:?}", response);
+                // Overloaded or broken peers still answer, but count as failing the request.
+                if !is_peer_failure(response.status()) {
+                    pool.record_success(index, upstream_start.elapsed());
+                    break response;
+                }
+                warn!(
+                    "{} peer {} responded with {}",
+                    peer_type_str,
+                    pool.peer(index).address,
+                    response.status()
+                );
+                pool.record_failure(index, metrics);
+                Ok(response)
+            }
+            Err(e) => {
+                warn!("Failed to send request: {}", e);
+                metrics
+                    .upstream_response_latency
+                    .with_label_values(&[peer_type_str, "error"])
+                    .observe(upstream_start.elapsed().as_secs_f64());
+                metrics
+                    .requests_total
+                    .with_label_values(&[peer_type_str, "error"])
+                    .inc();
+                if e.is_timeout() {
+                    metrics
+                        .timeouts_total
+                        .with_label_values(&[peer_type_str])
+                        .inc();
+                }
+                pool.record_failure(index, metrics);
+                Err(e)
+            }
+        };
+
+        // Reads are safe to retry, once, on another read peer.
+        let retry = match peer_type {
+            PeerRole::Read if !retried => {
+                // A failed sticky read was sent to an execution peer, any read peer will do
+                let exclude = sticky_peer.is_none().then_some(index);
+                state.read_peers.select(exclude)
+            }
+            _ => None,
+        };
+        match (retry, failure) {
+            (Some(next), _) => {
+                retried = true;
+                pool = &state.read_peers;
+                index = next;
+            }
+            // The peer's error response is passed on to the client as is.
+            (None, Ok(response)) => break response,
+            (None, Err(e)) => {
+                return Err((StatusCode::BAD_GATEWAY, format!("Request failed: {}", e)));
             }
-            return Err((StatusCode::BAD_GATEWAY, format!("Request failed: {}", e)));
         }
     };
 
+    if matches!(peer_type, PeerRole::Execution) && response.status().is_success() {
+        state.sessions.insert(client, index);
+    }
+
+    let response_status = response.status();
     let response_headers = response.headers().clone();
     let response_bytes = match response.bytes().await {
         Ok(bytes) => bytes,
@@ -228,6 +318,14 @@ async fn proxy_request(
         .with_label_values(&[peer_type_str])
         .observe(response_bytes.len() as f64);
 
+    if let (Some(cache), Some(key)) = (&state.cache, &cache_key) {
+        if response_status.is_success() {
+            if let Some(result) = cache::cacheable_result(key, &response_bytes) {
+                cache.insert(key, result).await;
+            }
+        }
+    }
+
     let mut resp = Response::new(response_bytes.into());
     for (name, value) in response_headers {
         if let Some(name) = name {
@@ -237,3 +335,28 @@ async fn proxy_request(
 
     Ok(resp)
 }
+
+/// Whether a response means the peer failed to serve the request, rather than the request
+/// being invalid.
+fn is_peer_failure(status: reqwest::StatusCode) -> bool {
+    status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+}
+
+fn select_peer(pool: &PeerPool) -> usize {
+    pool.select(None)
+        .expect("peer pools are validated to not be empty")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_peer_failure() {
+        assert!(is_peer_failure(reqwest::StatusCode::INTERNAL_SERVER_ERROR));
+        assert!(is_peer_failure(reqwest::StatusCode::SERVICE_UNAVAILABLE));
+        assert!(is_peer_failure(reqwest::StatusCode::TOO_MANY_REQUESTS));
+        assert!(!is_peer_failure(reqwest::StatusCode::OK));
+        assert!(!is_peer_failure(reqwest::StatusCode::BAD_REQUEST));
+    }
+}
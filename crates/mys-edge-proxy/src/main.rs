@@ -5,9 +5,13 @@ use axum::{routing::any, Router};
 use clap::Parser;
 use mysten_metrics::start_prometheus_server;
 use reqwest::Client;
+use mys_edge_proxy::cache::ResponseCache;
 use mys_edge_proxy::config::{load, ProxyConfig};
 use mys_edge_proxy::handlers::{proxy_handler, AppState};
 use mys_edge_proxy::metrics::AppMetrics;
+use mys_edge_proxy::peers::{spawn_health_checks, PeerPool, StickySessions};
+use std::net::SocketAddr;
+use std::sync::Arc;
 use tracing::info;
 
 #[derive(Parser, Debug)]
@@ -42,12 +46,43 @@ async fn main() {
 
     let app_metrics = AppMetrics::new(&prometheus_registry);
 
+    let read_peers = Arc::new(PeerPool::new(
+        "read",
+        &config.read_peer,
+        &config.health_check,
+    ));
+    let execution_peers = Arc::new(PeerPool::new(
+        "execution",
+        &config.execution_peer,
+        &config.health_check,
+    ));
+    let sessions = Arc::new(StickySessions::new(config.read_your_writes_seconds));
+    spawn_health_checks(
+        client.clone(),
+        vec![read_peers.clone(), execution_peers.clone()],
+        sessions.clone(),
+        config.health_check.clone(),
+        app_metrics.clone(),
+    );
+
+    let cache = match &config.cache {
+        Some(cache_config) => Some(Arc::new(
+            ResponseCache::new(cache_config, app_metrics.clone())
+                .await
+                .expect("Failed to open response cache"),
+        )),
+        None => None,
+    };
+
     let app_state = AppState::new(
         client,
-        config.read_peer.clone(),
-        config.execution_peer.clone(),
+        read_peers,
+        execution_peers,
+        sessions,
+        cache,
         app_metrics,
         config.logging,
+        config.trusted_proxies,
     );
 
     let app = Router::new()
@@ -56,7 +91,7 @@ async fn main() {
 
     info!("Starting server on {}", config.listen_address);
     axum_server::Server::bind(config.listen_address)
-        .serve(app.into_make_service())
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
         .await
         .unwrap();
 }
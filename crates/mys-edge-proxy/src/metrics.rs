@@ -3,7 +3,9 @@
 
 use prometheus::{
     register_gauge_vec_with_registry, register_histogram_vec_with_registry,
-    register_int_counter_vec_with_registry, GaugeVec, HistogramVec, IntCounterVec, Registry,
+    register_int_counter_vec_with_registry, register_int_gauge_vec_with_registry,
+    register_int_gauge_with_registry, GaugeVec, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec,
+    Registry,
 };
 
 #[derive(Clone)]
@@ -16,6 +18,12 @@ pub struct AppMetrics {
     pub request_size_bytes: HistogramVec,
     pub timeouts_total: IntCounterVec,
     pub error_counts: IntCounterVec,
+    pub peer_checkpoint_lag: IntGaugeVec,
+    pub peer_ejections_total: IntCounterVec,
+    pub cache_requests_total: IntCounterVec,
+    pub cache_memory_entries: IntGauge,
+    pub cache_memory_bytes: IntGauge,
+    pub cache_disk_bytes: IntGauge,
 }
 
 impl AppMetrics {
@@ -24,7 +32,7 @@ impl AppMetrics {
             backend_up: register_gauge_vec_with_registry!(
                 "edge_proxy_backend_up",
                 "Indicates if the backend is up (1) or down (0)",
-                &["peer_type"],
+                &["peer_type", "peer"],
                 registry
             )
             .unwrap(),
@@ -77,6 +85,45 @@ impl AppMetrics {
                 registry
             )
             .unwrap(),
+            peer_checkpoint_lag: register_int_gauge_vec_with_registry!(
+                "edge_proxy_peer_checkpoint_lag",
+                "Number of checkpoints a peer is behind the most up to date peer",
+                &["peer_type", "peer"],
+                registry
+            )
+            .unwrap(),
+            peer_ejections_total: register_int_counter_vec_with_registry!(
+                "edge_proxy_peer_ejections_total",
+                "Total number of times a peer was ejected for failing health checks or requests",
+                &["peer_type", "peer"],
+                registry
+            )
+            .unwrap(),
+            cache_requests_total: register_int_counter_vec_with_registry!(
+                "edge_proxy_cache_requests_total",
+                "Total number of cacheable requests, by whether they were served from memory, from disk, or missed",
+                &["method", "result"],
+                registry
+            )
+            .unwrap(),
+            cache_memory_entries: register_int_gauge_with_registry!(
+                "edge_proxy_cache_memory_entries",
+                "Number of responses cached in memory",
+                registry
+            )
+            .unwrap(),
+            cache_memory_bytes: register_int_gauge_with_registry!(
+                "edge_proxy_cache_memory_bytes",
+                "Total size of the responses cached in memory",
+                registry
+            )
+            .unwrap(),
+            cache_disk_bytes: register_int_gauge_with_registry!(
+                "edge_proxy_cache_disk_bytes",
+                "Total size of the responses cached on disk",
+                registry
+            )
+            .unwrap(),
         }
     }
 }
@@ -0,0 +1,420 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::config::{BalancingStrategy, HealthCheckConfig, PeerPoolConfig};
+use crate::metrics::AppMetrics;
+use anyhow::{anyhow, Context, Result};
+use rand::Rng;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+use url::Url;
+
+/// Weight of the latest sample in the moving average of a peer's latency.
+const LATENCY_EWMA_ALPHA: f64 = 0.2;
+
+pub struct Peer {
+    pub address: Url,
+    weight: u32,
+    state: Mutex<PeerState>,
+}
+
+struct PeerState {
+    healthy: bool,
+    consecutive_failures: u32,
+    consecutive_successes: u32,
+    latency: Option<Duration>,
+}
+
+/// The peers serving one role, and how traffic is balanced between them.
+pub struct PeerPool {
+    role: &'static str,
+    peers: Vec<Peer>,
+    balancing: BalancingStrategy,
+    unhealthy_threshold: u32,
+    healthy_threshold: u32,
+}
+
+impl PeerPool {
+    pub fn new(role: &'static str, config: &PeerPoolConfig, health: &HealthCheckConfig) -> Self {
+        Self {
+            role,
+            peers: config
+                .peers()
+                .iter()
+                .map(|peer| Peer {
+                    address: peer.address.clone(),
+                    weight: peer.weight,
+                    // Peers start out healthy, the first health checks eject them if needed.
+                    state: Mutex::new(PeerState {
+                        healthy: true,
+                        consecutive_failures: 0,
+                        consecutive_successes: 0,
+                        latency: None,
+                    }),
+                })
+                .collect(),
+            balancing: config.balancing(),
+            unhealthy_threshold: health.unhealthy_threshold,
+            healthy_threshold: health.healthy_threshold,
+        }
+    }
+
+    pub fn peer(&self, index: usize) -> &Peer {
+        &self.peers[index]
+    }
+
+    pub fn is_healthy(&self, index: usize) -> bool {
+        self.peers[index].state.lock().unwrap().healthy
+    }
+
+    /// Picks the peer to send a request to, other than `exclude`. If every peer is ejected, all
+    /// of them are candidates again, so that the pool keeps trying rather than failing outright.
+    pub fn select(&self, exclude: Option<usize>) -> Option<usize> {
+        let candidates: Vec<usize> = (0..self.peers.len())
+            .filter(|index| Some(*index) != exclude)
+            .collect();
+        let healthy: Vec<usize> = candidates
+            .iter()
+            .copied()
+            .filter(|index| self.is_healthy(*index))
+            .collect();
+        let candidates = if healthy.is_empty() {
+            candidates
+        } else {
+            healthy
+        };
+
+        match self.balancing {
+            BalancingStrategy::Weighted => {
+                let total: u64 = candidates
+                    .iter()
+                    .map(|index| self.peers[*index].weight as u64)
+                    .sum();
+                if total == 0 {
+                    return candidates.first().copied();
+                }
+                let mut pick = rand::thread_rng().gen_range(0..total);
+                candidates.into_iter().find(|index| {
+                    let weight = self.peers[*index].weight as u64;
+                    if pick < weight {
+                        true
+                    } else {
+                        pick -= weight;
+                        false
+                    }
+                })
+            }
+            // Peers without a latency sample yet go first, so that they get measured.
+            BalancingStrategy::LeastLatency => candidates
+                .into_iter()
+                .min_by_key(|index| self.peers[*index].state.lock().unwrap().latency),
+        }
+    }
+
+    /// Records a request served by the peer at `index`.
+    pub fn record_success(&self, index: usize, latency: Duration) {
+        let mut state = self.peers[index].state.lock().unwrap();
+        state.consecutive_failures = 0;
+        state.latency = Some(match state.latency {
+            Some(average) => {
+                average.mul_f64(1.0 - LATENCY_EWMA_ALPHA) + latency.mul_f64(LATENCY_EWMA_ALPHA)
+            }
+            None => latency,
+        });
+    }
+
+    /// Records a request the peer at `index` failed to serve, ejecting it after too many of those
+    /// in a row. Ejected peers are only readmitted by health checks.
+    pub fn record_failure(&self, index: usize, metrics: &AppMetrics) {
+        let mut state = self.peers[index].state.lock().unwrap();
+        state.consecutive_successes = 0;
+        state.consecutive_failures += 1;
+        if state.healthy && state.consecutive_failures >= self.unhealthy_threshold {
+            state.healthy = false;
+            self.on_health_change(index, false, metrics);
+        }
+    }
+
+    /// Records the outcome of a health check of the peer at `index`.
+    fn record_check(&self, index: usize, passed: bool, metrics: &AppMetrics) {
+        if !passed {
+            return self.record_failure(index, metrics);
+        }
+        let mut state = self.peers[index].state.lock().unwrap();
+        state.consecutive_failures = 0;
+        state.consecutive_successes += 1;
+        if !state.healthy && state.consecutive_successes >= self.healthy_threshold {
+            state.healthy = true;
+            self.on_health_change(index, true, metrics);
+        }
+    }
+
+    fn on_health_change(&self, index: usize, healthy: bool, metrics: &AppMetrics) {
+        let address = self.peers[index].address.as_str();
+        if healthy {
+            info!("Readmitting {} peer {}", self.role, address);
+        } else {
+            warn!("Ejecting {} peer {}", self.role, address);
+            metrics
+                .peer_ejections_total
+                .with_label_values(&[self.role, address])
+                .inc();
+        }
+        metrics
+            .backend_up
+            .with_label_values(&[self.role, address])
+            .set(if healthy { 1.0 } else { 0.0 });
+    }
+}
+
+/// Remembers which execution peer served each client's latest execution request, so that the
+/// client's reads can follow it for a while.
+pub struct StickySessions {
+    ttl: Duration,
+    sessions: Mutex<HashMap<String, (usize, Instant)>>,
+}
+
+impl StickySessions {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn insert(&self, client: String, peer: usize) {
+        if self.ttl.is_zero() {
+            return;
+        }
+        self.sessions
+            .lock()
+            .unwrap()
+            .insert(client, (peer, Instant::now() + self.ttl));
+    }
+
+    pub fn get(&self, client: &str) -> Option<usize> {
+        let sessions = self.sessions.lock().unwrap();
+        sessions
+            .get(client)
+            .filter(|(_, expiry)| *expiry > Instant::now())
+            .map(|(peer, _)| *peer)
+    }
+
+    fn cleanup(&self) {
+        let now = Instant::now();
+        self.sessions
+            .lock()
+            .unwrap()
+            .retain(|_, (_, expiry)| *expiry > now);
+    }
+}
+
+/// Periodically checks every peer of `pools`. A peer passes a check if it reports its latest
+/// checkpoint in time, and that checkpoint is at most `max_checkpoint_lag` behind the highest
+/// checkpoint reported by any peer.
+pub fn spawn_health_checks(
+    client: reqwest::Client,
+    pools: Vec<Arc<PeerPool>>,
+    sessions: Arc<StickySessions>,
+    config: HealthCheckConfig,
+    metrics: AppMetrics,
+) {
+    for pool in &pools {
+        for peer in &pool.peers {
+            metrics
+                .backend_up
+                .with_label_values(&[pool.role, peer.address.as_str()])
+                .set(1.0);
+        }
+    }
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(config.interval_seconds);
+        loop {
+            interval.tick().await;
+            let checks = pools.iter().flat_map(|pool| {
+                pool.peers
+                    .iter()
+                    .map(|peer| latest_checkpoint(&client, &peer.address, config.timeout_seconds))
+            });
+            let mut results = futures::future::join_all(checks).await.into_iter();
+            let checkpoints: Vec<Vec<Option<u64>>> = pools
+                .iter()
+                .map(|pool| {
+                    pool.peers
+                        .iter()
+                        .map(|peer| match results.next().unwrap() {
+                            Ok(checkpoint) => Some(checkpoint),
+                            Err(e) => {
+                                warn!(
+                                    "Health check of {} peer {} failed: {e:#}",
+                                    pool.role, peer.address
+                                );
+                                None
+                            }
+                        })
+                        .collect()
+                })
+                .collect();
+
+            let highest = checkpoints.iter().flatten().flatten().max().copied();
+            for (pool, checkpoints) in pools.iter().zip(checkpoints) {
+                for (index, checkpoint) in checkpoints.into_iter().enumerate() {
+                    let address = pool.peers[index].address.as_str();
+                    let passed = match (checkpoint, highest) {
+                        (Some(checkpoint), Some(highest)) => {
+                            let lag = highest - checkpoint;
+                            metrics
+                                .peer_checkpoint_lag
+                                .with_label_values(&[pool.role, address])
+                                .set(lag as i64);
+                            if lag > config.max_checkpoint_lag {
+                                warn!("{} peer {} is {lag} checkpoints behind", pool.role, address);
+                            }
+                            lag <= config.max_checkpoint_lag
+                        }
+                        _ => false,
+                    };
+                    pool.record_check(index, passed, &metrics);
+                }
+            }
+
+            sessions.cleanup();
+        }
+    });
+}
+
+async fn latest_checkpoint(
+    client: &reqwest::Client,
+    address: &Url,
+    timeout: Duration,
+) -> Result<u64> {
+    let response: serde_json::Value = client
+        .post(address.clone())
+        .timeout(timeout)
+        .json(&serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "mys_getLatestCheckpointSequenceNumber",
+            "params": [],
+        }))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    // The sequence number is a BigInt, serialized as a string
+    response
+        .get("result")
+        .and_then(|result| result.as_str())
+        .ok_or_else(|| anyhow!("Unexpected response: {response}"))?
+        .parse()
+        .context("Invalid checkpoint sequence number")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{PeerConfig, PeerPool as PeerPoolSettings};
+    use prometheus::Registry;
+
+    fn test_pool(weights: &[u32], balancing: BalancingStrategy) -> PeerPool {
+        let config = PeerPoolConfig::Pool(PeerPoolSettings {
+            peers: weights
+                .iter()
+                .enumerate()
+                .map(|(index, weight)| PeerConfig {
+                    address: format!("http://peer-{index}:9000").parse().unwrap(),
+                    weight: *weight,
+                })
+                .collect(),
+            balancing,
+        });
+        let health = HealthCheckConfig {
+            unhealthy_threshold: 2,
+            healthy_threshold: 2,
+            ..Default::default()
+        };
+        PeerPool::new("read", &config, &health)
+    }
+
+    #[test]
+    fn test_select_weighted() {
+        let pool = test_pool(&[1, 0, 1], BalancingStrategy::Weighted);
+        for _ in 0..100 {
+            let index = pool.select(None).unwrap();
+            assert_ne!(index, 1, "peers without weight get no traffic");
+            assert_eq!(pool.select(Some(0)), Some(2));
+        }
+
+        // Only the excluded peer is left
+        let pool = test_pool(&[1], BalancingStrategy::Weighted);
+        assert_eq!(pool.select(Some(0)), None);
+    }
+
+    #[test]
+    fn test_select_least_latency() {
+        let pool = test_pool(&[1, 1, 1], BalancingStrategy::LeastLatency);
+        pool.record_success(0, Duration::from_millis(50));
+        pool.record_success(1, Duration::from_millis(10));
+        // Peers never measured go first
+        assert_eq!(pool.select(None), Some(2));
+        pool.record_success(2, Duration::from_millis(30));
+        assert_eq!(pool.select(None), Some(1));
+        assert_eq!(pool.select(Some(1)), Some(2));
+    }
+
+    #[test]
+    fn test_ejection_and_readmission() {
+        let metrics = AppMetrics::new(&Registry::new());
+        let pool = test_pool(&[1, 1], BalancingStrategy::Weighted);
+
+        // A success in between resets the count of consecutive failures
+        pool.record_failure(0, &metrics);
+        pool.record_success(0, Duration::from_millis(10));
+        pool.record_failure(0, &metrics);
+        assert!(pool.is_healthy(0));
+
+        pool.record_failure(0, &metrics);
+        assert!(!pool.is_healthy(0));
+        for _ in 0..100 {
+            assert_eq!(pool.select(None), Some(1));
+        }
+
+        // Successful requests do not readmit a peer, passed health checks do
+        pool.record_success(0, Duration::from_millis(10));
+        pool.record_check(0, true, &metrics);
+        assert!(!pool.is_healthy(0));
+        pool.record_check(0, true, &metrics);
+        assert!(pool.is_healthy(0));
+
+        // With every peer ejected, all of them are candidates again
+        for index in 0..2 {
+            pool.record_check(index, false, &metrics);
+            pool.record_check(index, false, &metrics);
+            assert!(!pool.is_healthy(index));
+        }
+        assert!(pool.select(None).is_some());
+        assert_eq!(pool.select(Some(0)), Some(1));
+    }
+
+    #[test]
+    fn test_sticky_sessions() {
+        let sessions = StickySessions::new(Duration::from_millis(50));
+        sessions.insert("client".to_string(), 1);
+        assert_eq!(sessions.get("client"), Some(1));
+        assert_eq!(sessions.get("other"), None);
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(sessions.get("client"), None);
+        sessions.cleanup();
+        assert!(sessions.sessions.lock().unwrap().is_empty());
+
+        // A zero TTL disables sticky routing
+        let sessions = StickySessions::new(Duration::ZERO);
+        sessions.insert("client".to_string(), 1);
+        assert_eq!(sessions.get("client"), None);
+    }
+}
@@ -51,6 +51,9 @@ pub enum FaucetError {
 
     #[error("Invalid user agent: {0}")]
     InvalidUserAgent(String),
+
+    #[error("Challenge failed: {0}")]
+    InvalidChallenge(String),
 }
 
 impl FaucetError {
@@ -2,15 +2,20 @@
 // SPDX-License-Identifier: Apache-2.0
 use crate::FaucetError;
 use async_trait::async_trait;
+use ipnetwork::IpNetwork;
 use serde::{Deserialize, Serialize};
 use mys_types::base_types::{ObjectID, MysAddress, TransactionDigest};
+use mys_types::gas_coin::GAS;
+use mys_types::{parse_mys_type_tag, TypeTag};
 use uuid::Uuid;
 
+mod rate_limiter;
 mod simple_faucet;
 mod write_ahead_log;
+pub use self::rate_limiter::*;
 pub use self::simple_faucet::SimpleFaucet;
 use clap::Parser;
-use std::{net::Ipv4Addr, path::PathBuf, sync::Arc};
+use std::{net::Ipv4Addr, path::PathBuf, str::FromStr, sync::Arc};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct FaucetReceipt {
@@ -80,6 +85,32 @@ pub trait Faucet {
 pub const DEFAULT_AMOUNT: u64 = 1_000_000_000;
 pub const DEFAULT_NUM_OF_COINS: usize = 1;
 
+/// A `Coin<T>` handed out together with MYS, given as `<COIN_TYPE>=<AMOUNT>` on the command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomCoinConfig {
+    pub coin_type: TypeTag,
+    pub amount: u64,
+}
+
+impl FromStr for CustomCoinConfig {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((coin_type, amount)) = s.rsplit_once('=') else {
+            anyhow::bail!("Expected <COIN_TYPE>=<AMOUNT>, got {s}");
+        };
+        let coin_type = parse_mys_type_tag(coin_type)?;
+        // MYS is already handed out from the gas coins, and must not be split off them again.
+        if GAS::is_gas_type(&coin_type) {
+            anyhow::bail!("{coin_type} is already sent by the faucet, expected another coin type");
+        }
+        Ok(Self {
+            coin_type,
+            amount: amount.parse()?,
+        })
+    }
+}
+
 #[derive(Parser, Clone)]
 #[clap(
     name = "Mys Faucet",
@@ -126,6 +157,11 @@ pub struct FaucetConfig {
     #[clap(long, action = clap::ArgAction::Set, default_value_t = false)]
     pub batch_enabled: bool,
 
+    /// Also send `AMOUNT` of `COIN_TYPE` to every recipient, split off from the `Coin<COIN_TYPE>`
+    /// objects owned by the faucet. Can be repeated to hand out several coin types.
+    #[clap(long = "custom-coin", value_name = "COIN_TYPE=AMOUNT")]
+    pub custom_coins: Vec<CustomCoinConfig>,
+
     /// Testnet faucet requires authentication via the Web UI at <https://faucet.mys.io>
     /// This flag is used to indicate that authentication mode is enabled.
     #[clap(long)]
@@ -151,6 +187,42 @@ pub struct FaucetConfig {
     /// used for authenticated mode.
     #[clap(long, default_value_t = 60)]
     pub rate_limiter_cleanup_interval_secs: u64,
+
+    /// Maximum number of requests per recipient address within a window of
+    /// `address-rate-limit-window-secs`. Counters are persisted in the write-ahead log database.
+    #[clap(long)]
+    pub address_rate_limit: Option<u64>,
+
+    #[clap(long, default_value_t = 3600 * 24)]
+    pub address_rate_limit_window_secs: u64,
+
+    /// Maximum number of requests per client IP within a window of `ip-rate-limit-window-secs`.
+    /// Counters are persisted in the write-ahead log database.
+    #[clap(long)]
+    pub ip_rate_limit: Option<u64>,
+
+    #[clap(long, default_value_t = 3600 * 24)]
+    pub ip_rate_limit_window_secs: u64,
+
+    /// Load balancers or proxies in front of the faucet. For requests relayed by one of these, the
+    /// client IP is the rightmost `X-Forwarded-For` address not added by one of these. Can be
+    /// repeated.
+    #[clap(long = "trusted-proxy", value_name = "IP_OR_CIDR")]
+    pub trusted_proxies: Vec<IpNetwork>,
+
+    /// Require requests to solve a proof-of-work challenge, fetched from `/v1/challenge`, with this
+    /// many leading zero bits.
+    #[clap(long)]
+    pub pow_difficulty: Option<u8>,
+
+    /// Maximum number of proof-of-work challenges a client IP can hold without solving them.
+    #[clap(long, default_value_t = 10)]
+    pub max_challenges_per_ip: usize,
+
+    /// Require requests to carry a token signed by this address, as an alternative to solving a
+    /// proof-of-work challenge.
+    #[clap(long)]
+    pub token_issuer: Option<MysAddress>,
 }
 
 impl Default for FaucetConfig {
@@ -169,11 +241,20 @@ impl Default for FaucetConfig {
             batch_request_size: 500,
             ttl_expiration: 300,
             batch_enabled: false,
+            custom_coins: vec![],
             authenticated: false,
             max_requests_per_ip: 3,
             replenish_quota_interval_ms: 10,
             reset_time_interval_secs: 3600 * 12,
             rate_limiter_cleanup_interval_secs: 60,
+            address_rate_limit: None,
+            address_rate_limit_window_secs: 3600 * 24,
+            ip_rate_limit: None,
+            ip_rate_limit_window_secs: 3600 * 24,
+            trusted_proxies: vec![],
+            pow_difficulty: None,
+            max_challenges_per_ip: 10,
+            token_issuer: None,
         }
     }
 }
@@ -0,0 +1,579 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Built-in abuse control for the faucet: limits on how many requests a recipient address or a
+//! client IP can make within a window, persisted in the write-ahead log database so that they
+//! survive restarts, and optional challenges a request has to pass before it is served.
+//!
+//! IPv6 clients are limited per /64 network, as a single host is usually handed a whole /64.
+
+use crate::server::secs_to_human_readable;
+use crate::{FaucetConfig, FaucetError};
+use dashmap::DashMap;
+use fastcrypto::encoding::{Base64, Encoding, Hex};
+use fastcrypto::hash::{Blake2b256, HashFunction};
+use http::HeaderMap;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use shared_crypto::intent::{Intent, IntentMessage, PersonalMessage};
+use std::net::{IpAddr, Ipv6Addr};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use mys_types::base_types::MysAddress;
+use mys_types::crypto::{MysSignature, Signature, ToFromBytes};
+use typed_store::rocks::DBMap;
+use typed_store::Map;
+
+pub use mys_http::forwarded::client_ip;
+
+pub const POW_CHALLENGE_HEADER: &str = "X-Faucet-Pow-Challenge";
+pub const POW_SOLUTION_HEADER: &str = "X-Faucet-Pow-Solution";
+pub const TOKEN_HEADER: &str = "X-Faucet-Token";
+pub const TOKEN_SIGNATURE_HEADER: &str = "X-Faucet-Token-Signature";
+
+/// How long a proof-of-work challenge can be solved for after it was issued
+const POW_CHALLENGE_TTL: Duration = Duration::from_secs(300);
+/// Bounds the memory used by challenges which are issued but never solved
+const MAX_OUTSTANDING_POW_CHALLENGES: usize = 100_000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RateLimitKey {
+    Address(MysAddress),
+    Ip(IpAddr),
+}
+
+/// Requests counted for a key in the window starting at `window_start_ms`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RateLimitEntry {
+    pub window_start_ms: u64,
+    pub requests: u64,
+}
+
+/// Maximum number of requests per recipient address and per client IP within fixed windows.
+pub struct RateLimiter {
+    table: DBMap<RateLimitKey, RateLimitEntry>,
+    address_limit: Option<(u64, Duration)>,
+    ip_limit: Option<(u64, Duration)>,
+    /// Serializes the read-modify-write of the counters
+    lock: parking_lot::Mutex<()>,
+}
+
+impl RateLimiter {
+    pub fn new(table: DBMap<RateLimitKey, RateLimitEntry>, config: &FaucetConfig) -> Self {
+        Self {
+            table,
+            address_limit: config.address_rate_limit.map(|limit| {
+                (
+                    limit,
+                    Duration::from_secs(config.address_rate_limit_window_secs),
+                )
+            }),
+            ip_limit: config
+                .ip_rate_limit
+                .map(|limit| (limit, Duration::from_secs(config.ip_rate_limit_window_secs))),
+            lock: parking_lot::Mutex::new(()),
+        }
+    }
+
+    /// Counts a request for `recipient` from `ip`, or fails without counting it if either of them
+    /// has used up its limit. A request is counted even if sending the coins fails later on.
+    pub fn check(&self, recipient: MysAddress, ip: Option<IpAddr>) -> Result<(), FaucetError> {
+        self.check_at(recipient, ip, now_ms())
+    }
+
+    fn check_at(
+        &self,
+        recipient: MysAddress,
+        ip: Option<IpAddr>,
+        now_ms: u64,
+    ) -> Result<(), FaucetError> {
+        let limits = [
+            self.address_limit
+                .map(|limit| (RateLimitKey::Address(recipient), limit)),
+            ip.zip(self.ip_limit)
+                .map(|(ip, limit)| (RateLimitKey::Ip(client_network(ip)), limit)),
+        ];
+
+        let _guard = self.lock.lock();
+        let mut updates = vec![];
+        for (key, (max_requests, window)) in limits.into_iter().flatten() {
+            let window_ms = window.as_millis() as u64;
+            let entry = match self.table.get(&key).map_err(FaucetError::internal)? {
+                Some(entry) if now_ms < entry.window_start_ms + window_ms => entry,
+                _ => RateLimitEntry {
+                    window_start_ms: now_ms,
+                    requests: 0,
+                },
+            };
+            if entry.requests >= max_requests {
+                let wait_secs = (entry.window_start_ms + window_ms - now_ms).div_ceil(1000);
+                let subject = match key {
+                    RateLimitKey::Address(_) => "this address",
+                    RateLimitKey::Ip(_) => "your IP address",
+                };
+                return Err(FaucetError::TooManyRequests(format!(
+                    "Coins for {subject} can be requested again in {}",
+                    secs_to_human_readable(wait_secs)
+                )));
+            }
+            updates.push((
+                key,
+                RateLimitEntry {
+                    window_start_ms: entry.window_start_ms,
+                    requests: entry.requests + 1,
+                },
+            ));
+        }
+        self.table
+            .multi_insert(updates)
+            .map_err(FaucetError::internal)
+    }
+
+    /// Removes the counters of windows which have ended.
+    pub fn cleanup(&self) -> Result<(), FaucetError> {
+        let now_ms = now_ms();
+        let _guard = self.lock.lock();
+        let mut expired = vec![];
+        for item in self.table.safe_iter() {
+            let (key, entry) = item.map_err(FaucetError::internal)?;
+            let window = match key {
+                RateLimitKey::Address(_) => self.address_limit.map(|(_, window)| window),
+                RateLimitKey::Ip(_) => self.ip_limit.map(|(_, window)| window),
+            };
+            if window.map_or(true, |window| {
+                now_ms >= entry.window_start_ms + window.as_millis() as u64
+            }) {
+                expired.push(key);
+            }
+        }
+        self.table
+            .multi_remove(expired)
+            .map_err(FaucetError::internal)
+    }
+}
+
+/// A proof-of-work challenge handed out to a client. The client has to find a `solution` such
+/// that the Blake2b256 hash of `challenge || recipient || solution` starts with `difficulty` zero
+/// bits.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PowChallenge {
+    /// Hex encoded challenge
+    pub challenge: String,
+    pub difficulty: u8,
+    pub expires_in_secs: u64,
+}
+
+/// Permission to request coins for `recipient`, signed by the token issuer configured with
+/// `--token-issuer` over its BCS bytes as a personal message.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FaucetToken {
+    pub recipient: MysAddress,
+    pub expires_at_ms: u64,
+}
+
+/// Verifies that a request passed one of the configured challenges: a solved proof-of-work
+/// challenge or a token signed by the token issuer.
+pub struct ChallengeVerifier {
+    pow_difficulty: Option<u8>,
+    token_issuer: Option<MysAddress>,
+    /// Proof-of-work challenges that were issued and not solved yet, with their expiry and the
+    /// client network they were issued to
+    pow_challenges: DashMap<[u8; 32], (Instant, IpAddr)>,
+    /// Number of outstanding challenges per client network, so that a single client cannot use
+    /// up `MAX_OUTSTANDING_POW_CHALLENGES`
+    challenges_per_ip: DashMap<IpAddr, usize>,
+    max_challenges_per_ip: usize,
+}
+
+impl ChallengeVerifier {
+    pub fn new(config: &FaucetConfig) -> Self {
+        Self {
+            pow_difficulty: config.pow_difficulty,
+            token_issuer: config.token_issuer,
+            pow_challenges: DashMap::new(),
+            challenges_per_ip: DashMap::new(),
+            max_challenges_per_ip: config.max_challenges_per_ip,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.pow_difficulty.is_some() || self.token_issuer.is_some()
+    }
+
+    /// Issues a proof-of-work challenge to the client at `ip`.
+    pub fn issue_pow_challenge(&self, ip: IpAddr) -> Result<PowChallenge, FaucetError> {
+        let Some(difficulty) = self.pow_difficulty else {
+            return Err(FaucetError::InvalidChallenge(
+                "Proof-of-work challenges are not enabled".to_string(),
+            ));
+        };
+        if self.pow_challenges.len() >= MAX_OUTSTANDING_POW_CHALLENGES {
+            return Err(FaucetError::TooManyRequests(
+                "Too many outstanding challenges, please try again later".to_string(),
+            ));
+        }
+        let ip = client_network(ip);
+        {
+            let mut outstanding = self.challenges_per_ip.entry(ip).or_insert(0);
+            if *outstanding >= self.max_challenges_per_ip {
+                return Err(FaucetError::TooManyRequests(
+                    "Too many unsolved challenges from your IP address, solve those first or \
+                     wait for them to expire"
+                        .to_string(),
+                ));
+            }
+            *outstanding += 1;
+        }
+        let mut challenge = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut challenge);
+        self.pow_challenges
+            .insert(challenge, (Instant::now() + POW_CHALLENGE_TTL, ip));
+        Ok(PowChallenge {
+            challenge: Hex::encode(challenge),
+            difficulty,
+            expires_in_secs: POW_CHALLENGE_TTL.as_secs(),
+        })
+    }
+
+    /// Checks the challenge headers of a request for `recipient`. A proof-of-work challenge can
+    /// only be used once.
+    pub fn verify(&self, recipient: MysAddress, headers: &HeaderMap) -> Result<(), FaucetError> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+
+        if let (Some(issuer), Some(token), Some(signature)) = (
+            self.token_issuer,
+            header(TOKEN_HEADER),
+            header(TOKEN_SIGNATURE_HEADER),
+        ) {
+            return verify_token(issuer, recipient, token, signature, now_ms());
+        }
+        if let (Some(difficulty), Some(challenge), Some(solution)) = (
+            self.pow_difficulty,
+            header(POW_CHALLENGE_HEADER),
+            header(POW_SOLUTION_HEADER),
+        ) {
+            return self.verify_pow(difficulty, recipient, challenge, solution);
+        }
+        Err(FaucetError::InvalidChallenge(format!(
+            "Requests must carry either the {POW_CHALLENGE_HEADER} and {POW_SOLUTION_HEADER} headers \
+             with a solved challenge from /v1/challenge, or the {TOKEN_HEADER} and \
+             {TOKEN_SIGNATURE_HEADER} headers"
+        )))
+    }
+
+    fn verify_pow(
+        &self,
+        difficulty: u8,
+        recipient: MysAddress,
+        challenge: &str,
+        solution: &str,
+    ) -> Result<(), FaucetError> {
+        let challenge: [u8; 32] = Hex::decode(challenge)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| FaucetError::InvalidChallenge("Malformed challenge".to_string()))?;
+        let solution = solution
+            .parse::<u64>()
+            .map_err(|_| FaucetError::InvalidChallenge("Malformed solution".to_string()))?;
+        if leading_zero_bits(&pow_digest(&challenge, recipient, solution)) < difficulty as u32 {
+            return Err(FaucetError::InvalidChallenge(
+                "Solution does not meet the difficulty".to_string(),
+            ));
+        }
+        // Remove only once the solution is verified, so that a wrong guess does not burn the
+        // challenge
+        let removed = self.pow_challenges.remove(&challenge);
+        if let Some((_, (_, ip))) = removed {
+            self.release_challenge(ip);
+        }
+        match removed {
+            Some((_, (expiry, _))) if expiry > Instant::now() => Ok(()),
+            _ => Err(FaucetError::InvalidChallenge(
+                "Unknown or expired challenge".to_string(),
+            )),
+        }
+    }
+
+    fn release_challenge(&self, ip: IpAddr) {
+        self.challenges_per_ip.remove_if_mut(&ip, |_, outstanding| {
+            *outstanding -= 1;
+            *outstanding == 0
+        });
+    }
+
+    /// Forgets challenges which have expired without being solved.
+    pub fn cleanup(&self) {
+        let now = Instant::now();
+        self.pow_challenges.retain(|_, (expiry, ip)| {
+            let keep = *expiry > now;
+            if !keep {
+                self.release_challenge(*ip);
+            }
+            keep
+        });
+    }
+}
+
+/// The address requests from `ip` are limited by: IPv4 addresses as is, IPv6 addresses by their
+/// /64 network.
+fn client_network(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V4(_) => ip,
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => IpAddr::V4(v4),
+            None => {
+                let prefix = u128::from(v6) & !(u128::MAX >> 64);
+                IpAddr::V6(Ipv6Addr::from(prefix))
+            }
+        },
+    }
+}
+
+fn verify_token(
+    issuer: MysAddress,
+    recipient: MysAddress,
+    token: &str,
+    signature: &str,
+    now_ms: u64,
+) -> Result<(), FaucetError> {
+    let message = Base64::decode(token)
+        .map_err(|_| FaucetError::InvalidChallenge("Malformed token".to_string()))?;
+    let decoded: FaucetToken = bcs::from_bytes(&message)
+        .map_err(|_| FaucetError::InvalidChallenge("Malformed token".to_string()))?;
+    let signature = Base64::decode(signature)
+        .ok()
+        .and_then(|bytes| Signature::from_bytes(&bytes).ok())
+        .ok_or_else(|| FaucetError::InvalidChallenge("Malformed token signature".to_string()))?;
+    signature
+        .verify_secure(
+            &IntentMessage::new(Intent::personal_message(), PersonalMessage { message }),
+            issuer,
+            signature.scheme(),
+        )
+        .map_err(|_| FaucetError::InvalidChallenge("Invalid token signature".to_string()))?;
+    if decoded.recipient != recipient {
+        return Err(FaucetError::InvalidChallenge(
+            "Token was issued for a different recipient".to_string(),
+        ));
+    }
+    if decoded.expires_at_ms <= now_ms {
+        return Err(FaucetError::InvalidChallenge(
+            "Token has expired".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+pub fn pow_digest(challenge: &[u8], recipient: MysAddress, solution: u64) -> [u8; 32] {
+    let mut hasher = Blake2b256::default();
+    hasher.update(challenge);
+    hasher.update(recipient);
+    hasher.update(solution.to_le_bytes());
+    hasher.finalize().digest
+}
+
+/// Finds a solution to a proof-of-work challenge, as a client would.
+pub fn solve_pow(challenge: &[u8], recipient: MysAddress, difficulty: u8) -> u64 {
+    (0..)
+        .find(|solution| {
+            leading_zero_bits(&pow_digest(challenge, recipient, *solution)) >= difficulty as u32
+        })
+        .expect("A solution exists for any difficulty up to 256 bits")
+}
+
+fn leading_zero_bits(digest: &[u8]) -> u32 {
+    let mut bits = 0;
+    for byte in digest {
+        bits += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+    bits
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::faucet::write_ahead_log::WriteAheadLog;
+    use mys_types::crypto::{get_key_pair, AccountKeyPair, MysKeyPair};
+
+    fn rate_limiter(dir: &std::path::Path) -> RateLimiter {
+        let config = FaucetConfig {
+            address_rate_limit: Some(2),
+            address_rate_limit_window_secs: 60,
+            ip_rate_limit: Some(3),
+            ip_rate_limit_window_secs: 10,
+            ..Default::default()
+        };
+        RateLimiter::new(WriteAheadLog::open(dir).rate_limits, &config)
+    }
+
+    #[tokio::test]
+    async fn test_rate_limits_persist_across_restarts() {
+        let tmp = tempfile::tempdir().unwrap();
+        let ip = Some(IpAddr::from([127, 0, 0, 1]));
+        let (a, b) = (
+            MysAddress::random_for_testing_only(),
+            MysAddress::random_for_testing_only(),
+        );
+
+        let limiter = rate_limiter(&tmp.path().join("wal"));
+        limiter.check_at(a, ip, 0).unwrap();
+        limiter.check_at(a, ip, 1_000).unwrap();
+        // Address limit reached, the rejected request does not count against the IP
+        assert!(matches!(
+            limiter.check_at(a, ip, 2_000),
+            Err(FaucetError::TooManyRequests(_))
+        ));
+        limiter.check_at(b, ip, 2_000).unwrap();
+        drop(limiter);
+
+        let limiter = rate_limiter(&tmp.path().join("wal"));
+        // IP limit reached
+        assert!(limiter.check_at(b, ip, 3_000).is_err());
+        // Requests without a known client IP are only limited per address
+        limiter.check_at(b, None, 3_000).unwrap();
+        // The IP window has ended, the address window has not
+        limiter
+            .check_at(MysAddress::random_for_testing_only(), ip, 11_000)
+            .unwrap();
+        assert!(limiter.check_at(a, ip, 11_000).is_err());
+        limiter.check_at(a, ip, 61_000).unwrap();
+    }
+
+    #[test]
+    fn test_pow_challenge() {
+        let verifier = ChallengeVerifier::new(&FaucetConfig {
+            pow_difficulty: Some(8),
+            ..Default::default()
+        });
+        let recipient = MysAddress::random_for_testing_only();
+        let challenge = verifier
+            .issue_pow_challenge(IpAddr::from([127, 0, 0, 1]))
+            .unwrap();
+        let bytes = Hex::decode(&challenge.challenge).unwrap();
+        let solution = solve_pow(&bytes, recipient, challenge.difficulty);
+
+        let mut headers = HeaderMap::new();
+        assert!(verifier.verify(recipient, &headers).is_err());
+        headers.insert(POW_CHALLENGE_HEADER, challenge.challenge.parse().unwrap());
+        headers.insert(POW_SOLUTION_HEADER, solution.to_string().parse().unwrap());
+        // The solution is bound to the recipient
+        assert!(verifier
+            .verify(MysAddress::random_for_testing_only(), &headers)
+            .is_err());
+        verifier.verify(recipient, &headers).unwrap();
+        // Challenges can only be used once
+        assert!(verifier.verify(recipient, &headers).is_err());
+    }
+
+    #[test]
+    fn test_pow_challenges_per_ip() {
+        let verifier = ChallengeVerifier::new(&FaucetConfig {
+            pow_difficulty: Some(1),
+            max_challenges_per_ip: 2,
+            ..Default::default()
+        });
+        let ip: IpAddr = "2001:db8::1".parse().unwrap();
+        let recipient = MysAddress::random_for_testing_only();
+        let challenge = verifier.issue_pow_challenge(ip).unwrap();
+        // Addresses of the same /64 share the limit, other clients are not affected
+        verifier
+            .issue_pow_challenge("2001:db8::2".parse().unwrap())
+            .unwrap();
+        assert!(matches!(
+            verifier.issue_pow_challenge(ip),
+            Err(FaucetError::TooManyRequests(_))
+        ));
+        verifier
+            .issue_pow_challenge("2001:db8:0:1::1".parse().unwrap())
+            .unwrap();
+
+        // Solving a challenge frees up room for another one
+        let bytes = Hex::decode(&challenge.challenge).unwrap();
+        let solution = solve_pow(&bytes, recipient, challenge.difficulty);
+        let mut headers = HeaderMap::new();
+        headers.insert(POW_CHALLENGE_HEADER, challenge.challenge.parse().unwrap());
+        headers.insert(POW_SOLUTION_HEADER, solution.to_string().parse().unwrap());
+        verifier.verify(recipient, &headers).unwrap();
+        verifier.issue_pow_challenge(ip).unwrap();
+    }
+
+    #[test]
+    fn test_client_network() {
+        let v4: IpAddr = "203.0.113.7".parse().unwrap();
+        assert_eq!(client_network(v4), v4);
+        assert_eq!(client_network("::ffff:203.0.113.7".parse().unwrap()), v4);
+        assert_eq!(
+            client_network("2001:db8:1:2:3:4:5:6".parse().unwrap()),
+            "2001:db8:1:2::".parse::<IpAddr>().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ipv6_rate_limited_per_network() {
+        let tmp = tempfile::tempdir().unwrap();
+        let limiter = rate_limiter(&tmp.path().join("wal"));
+        let recipient = || MysAddress::random_for_testing_only();
+        for host in 1..=3u16 {
+            let ip = IpAddr::from([0x2001, 0xdb8, 0, 0, 0, 0, 0, host]);
+            limiter.check_at(recipient(), Some(ip), 0).unwrap();
+        }
+        let ip = IpAddr::from([0x2001, 0xdb8, 0, 0, 0, 0, 0, 4]);
+        assert!(limiter.check_at(recipient(), Some(ip), 0).is_err());
+        let ip = IpAddr::from([0x2001, 0xdb8, 0, 1, 0, 0, 0, 4]);
+        limiter.check_at(recipient(), Some(ip), 0).unwrap();
+    }
+
+    #[test]
+    fn test_signed_token() {
+        let (issuer, keypair): (_, AccountKeyPair) = get_key_pair();
+        let keypair = MysKeyPair::Ed25519(keypair);
+        let recipient = MysAddress::random_for_testing_only();
+        let token = bcs::to_bytes(&FaucetToken {
+            recipient,
+            expires_at_ms: 10_000,
+        })
+        .unwrap();
+        let signature = Signature::new_secure(
+            &IntentMessage::new(
+                Intent::personal_message(),
+                PersonalMessage {
+                    message: token.clone(),
+                },
+            ),
+            &keypair,
+        );
+        let (token, signature) = (Base64::encode(token), Base64::encode(signature.as_ref()));
+
+        verify_token(issuer, recipient, &token, &signature, 5_000).unwrap();
+        assert!(verify_token(issuer, recipient, &token, &signature, 10_000).is_err());
+        assert!(verify_token(
+            MysAddress::random_for_testing_only(),
+            recipient,
+            &token,
+            &signature,
+            5_000
+        )
+        .is_err());
+        assert!(verify_token(
+            issuer,
+            MysAddress::random_for_testing_only(),
+            &token,
+            &signature,
+            5_000
+        )
+        .is_err());
+    }
+}
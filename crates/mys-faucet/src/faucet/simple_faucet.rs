@@ -5,12 +5,11 @@ use crate::faucet::write_ahead_log;
 use crate::metrics::FaucetMetrics;
 use async_recursion::async_recursion;
 use async_trait::async_trait;
+use futures::StreamExt;
 use mysten_metrics::spawn_monitored_task;
 use prometheus::Registry;
 use shared_crypto::intent::Intent;
-use std::collections::HashMap;
-#[cfg(test)]
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::path::Path;
 use std::sync::{Arc, Weak};
@@ -21,17 +20,20 @@ use ttl_cache::TtlCache;
 use typed_store::Map;
 
 use mys_json_rpc_types::{
-    OwnedObjectRef, MysObjectDataOptions, MysTransactionBlockEffectsAPI,
-    MysTransactionBlockResponse, MysTransactionBlockResponseOptions,
+    ObjectChange, OwnedObjectRef, MysObjectDataOptions, MysRawData,
+    MysTransactionBlockEffectsAPI, MysTransactionBlockResponse,
+    MysTransactionBlockResponseOptions,
 };
 use mys_keys::keystore::AccountKeystore;
 use mys_sdk::wallet_context::WalletContext;
 use mys_types::object::Owner;
 use mys_types::quorum_driver_types::ExecuteTransactionRequestType;
 use mys_types::{
-    base_types::{ObjectID, MysAddress, TransactionDigest},
+    base_types::{ObjectID, ObjectRef, MysAddress, TransactionDigest},
+    coin::Coin,
     gas_coin::GasCoin,
-    transaction::{Transaction, TransactionData},
+    transaction::{Transaction, TransactionData, TransactionDataAPI},
+    TypeTag,
 };
 use tokio::sync::{
     mpsc::{self, Receiver, Sender},
@@ -43,8 +45,8 @@ use uuid::Uuid;
 
 use super::write_ahead_log::WriteAheadLog;
 use crate::{
-    BatchFaucetReceipt, BatchSendStatus, BatchSendStatusType, CoinInfo, Faucet, FaucetConfig,
-    FaucetError, FaucetReceipt,
+    BatchFaucetReceipt, BatchSendStatus, BatchSendStatusType, CoinInfo, CustomCoinConfig, Faucet,
+    FaucetConfig, FaucetError, FaucetReceipt,
 };
 
 pub struct SimpleFaucet {
@@ -61,6 +63,7 @@ pub struct SimpleFaucet {
     task_id_cache: Mutex<TtlCache<Uuid, BatchSendStatus>>,
     ttl_expiration: u64,
     coin_amount: u64,
+    custom_coin_pools: Vec<CustomCoinPool>,
     /// Shuts down the batch transfer task. Used only in testing.
     #[allow(unused)]
     batch_transfer_shutdown: parking_lot::Mutex<Option<oneshot::Sender<()>>>,
@@ -77,10 +80,91 @@ impl fmt::Debug for SimpleFaucet {
             .field("batch_request_size", &self.batch_request_size)
             .field("ttl_expiration", &self.ttl_expiration)
             .field("coin_amount", &self.coin_amount)
+            .field("custom_coin_pools", &self.custom_coin_pools)
             .finish()
     }
 }
 
+/// `Coin<T>` objects owned by the faucet that custom coins are split off from. Like gas coins, a
+/// coin is taken out of the pool while a transaction uses it, and only recycled once that
+/// transaction has been committed in the WAL.
+#[derive(Debug)]
+struct CustomCoinPool {
+    coin_type: TypeTag,
+    amount: u64,
+    /// Every coin of this type the faucet owned at startup, to recognise them among the inputs of
+    /// transactions.
+    coins: HashSet<ObjectID>,
+    producer: Mutex<Sender<ObjectID>>,
+    consumer: Mutex<Receiver<ObjectID>>,
+}
+
+impl CustomCoinPool {
+    async fn new(
+        wallet: &WalletContext,
+        owner: MysAddress,
+        config: &CustomCoinConfig,
+        reserved: &HashSet<ObjectID>,
+    ) -> Result<Self, FaucetError> {
+        let client = wallet
+            .get_client()
+            .await
+            .map_err(|e| FaucetError::Wallet(e.to_string()))?;
+        let coins: Vec<_> = client
+            .coin_read_api()
+            .get_coins_stream(owner, Some(config.coin_type.to_string()))
+            .collect()
+            .await;
+
+        let (producer, consumer) = mpsc::channel(coins.len().max(1));
+        for coin in &coins {
+            let coin_id = coin.coin_object_id;
+            // Coins used by transactions in the WAL are recycled once those are retried.
+            if coin.balance >= config.amount && !reserved.contains(&coin_id) {
+                info!(?coin_id, coin_type = %config.coin_type, "Adding coin to custom coin pool");
+                producer
+                    .try_send(coin_id)
+                    .expect("unexpected - queue is large enough to hold all coins");
+            }
+        }
+
+        Ok(Self {
+            coin_type: config.coin_type.clone(),
+            amount: config.amount,
+            coins: coins.iter().map(|coin| coin.coin_object_id).collect(),
+            producer: Mutex::new(producer),
+            consumer: Mutex::new(consumer),
+        })
+    }
+
+    async fn pop(&self, uuid: Uuid) -> Option<ObjectID> {
+        let Ok(mut consumer) = tokio::time::timeout(LOCK_TIMEOUT, self.consumer.lock()).await
+        else {
+            error!(?uuid, coin_type = %self.coin_type, "Timeout when getting consumer lock");
+            return None;
+        };
+
+        let Ok(coin) = tokio::time::timeout(RECV_TIMEOUT, consumer.recv()).await else {
+            warn!(?uuid, coin_type = %self.coin_type, "Timeout when getting coin from the queue");
+            return None;
+        };
+
+        let Some(coin) = coin else {
+            unreachable!("channel is closed");
+        };
+        Some(coin)
+    }
+
+    async fn recycle(&self, coin_id: ObjectID, uuid: Uuid) {
+        self.producer
+            .lock()
+            .await
+            .try_send(coin_id)
+            .expect("unexpected - queue is large enough to hold all coins");
+        info!(?uuid, ?coin_id, coin_type = %self.coin_type, "Recycled custom coin");
+    }
+}
+
 enum GasCoinResponse {
     GasCoinWithInsufficientBalance(ObjectID),
     InvalidGasCoin(ObjectID),
@@ -173,6 +257,25 @@ impl SimpleFaucet {
                     .unwrap();
             }
         }
+
+        let mut reserved = HashSet::new();
+        for item in wal.log.safe_iter() {
+            let (_, entry) = item.map_err(FaucetError::internal)?;
+            reserved.extend(
+                entry
+                    .tx
+                    .input_objects()
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|input| input.object_id()),
+            );
+        }
+        let mut custom_coin_pools = Vec::with_capacity(config.custom_coins.len());
+        for custom_coin in &config.custom_coins {
+            custom_coin_pools
+                .push(CustomCoinPool::new(&wallet, active_address, custom_coin, &reserved).await?);
+        }
+
         let (batch_transfer_shutdown, mut rx_batch_transfer_shutdown) = oneshot::channel();
 
         let faucet = Self {
@@ -191,6 +294,7 @@ impl SimpleFaucet {
             task_id_cache: TtlCache::new(config.max_request_per_second as usize * 60 * 10).into(),
             ttl_expiration: config.ttl_expiration,
             coin_amount: config.amount,
+            custom_coin_pools,
             batch_transfer_shutdown: parking_lot::Mutex::new(Some(batch_transfer_shutdown)),
         };
 
@@ -375,6 +479,117 @@ impl SimpleFaucet {
         }))
     }
 
+    /// Takes a coin out of every custom coin pool, with enough balance to pay each of `recipients`
+    /// its amount of that coin. Coin types without such a coin available are left out of the
+    /// transaction rather than failing the request, but amounts that overflow for this many
+    /// recipients are an error.
+    async fn prepare_custom_coins(
+        &self,
+        recipients: usize,
+        uuid: Uuid,
+    ) -> Result<Vec<(ObjectRef, u64)>, FaucetError> {
+        // Checked before taking any coin out of the pools, so that none has to be put back.
+        let total_amounts = self
+            .custom_coin_pools
+            .iter()
+            .map(|pool| {
+                pool.amount.checked_mul(recipients as u64).ok_or_else(|| {
+                    FaucetError::internal(format!(
+                        "{} of {} for each of {recipients} recipients overflows",
+                        pool.amount, pool.coin_type
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut custom_coins = vec![];
+        for (pool, total_amount) in self.custom_coin_pools.iter().zip(total_amounts) {
+            loop {
+                let Some(coin_id) = pool.pop(uuid).await else {
+                    warn!(?uuid, coin_type = %pool.coin_type, "No custom coin available");
+                    break;
+                };
+                match self.get_custom_coin(coin_id, &pool.coin_type).await {
+                    Ok(Some((coin_ref, balance))) if balance >= total_amount => {
+                        custom_coins.push((coin_ref, pool.amount));
+                        break;
+                    }
+                    Ok(_) => {
+                        warn!(
+                            ?uuid,
+                            ?coin_id,
+                            "Invalid custom coin or insufficient balance, removing from pool"
+                        );
+                    }
+                    Err(e) => {
+                        error!(?uuid, ?coin_id, "Fullnode read error: {e:?}");
+                        pool.recycle(coin_id, uuid).await;
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(custom_coins)
+    }
+
+    /// Puts custom coins taken by `prepare_custom_coins` back into their pools, for a transaction
+    /// that was never sent.
+    async fn recycle_custom_coins(&self, custom_coins: &[(ObjectRef, u64)], uuid: Uuid) {
+        for ((coin_id, _, _), _) in custom_coins {
+            if let Some(pool) = self
+                .custom_coin_pools
+                .iter()
+                .find(|pool| pool.coins.contains(coin_id))
+            {
+                pool.recycle(*coin_id, uuid).await;
+            }
+        }
+    }
+
+    /// Returns the reference and balance of a custom coin, if it is still a `Coin<coin_type>`
+    /// owned by the faucet.
+    async fn get_custom_coin(
+        &self,
+        coin_id: ObjectID,
+        coin_type: &TypeTag,
+    ) -> anyhow::Result<Option<(ObjectRef, u64)>> {
+        let client = self.wallet.get_client().await?;
+        let Some(object) = client
+            .read_api()
+            .get_object_with_options(coin_id, MysObjectDataOptions::new().with_owner().with_bcs())
+            .await?
+            .data
+        else {
+            return Ok(None);
+        };
+        let Some(MysRawData::MoveObject(raw)) = &object.bcs else {
+            return Ok(None);
+        };
+        if object.owner != Some(Owner::AddressOwner(self.active_address))
+            || raw.type_ != Coin::type_(coin_type.clone())
+        {
+            return Ok(None);
+        }
+        let balance = Coin::from_bcs_bytes(&raw.bcs_bytes)?.value();
+        Ok(Some((object.object_ref(), balance)))
+    }
+
+    /// The custom coins among the inputs of `tx_data`, with the pools they belong to.
+    fn custom_coin_inputs(&self, tx_data: &TransactionData) -> Vec<(&CustomCoinPool, ObjectID)> {
+        tx_data
+            .input_objects()
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|input| {
+                let coin_id = input.object_id();
+                self.custom_coin_pools
+                    .iter()
+                    .find(|pool| pool.coins.contains(&coin_id))
+                    .map(|pool| (pool, coin_id))
+            })
+            .collect()
+    }
+
     /// Clear the WAL list in the faucet
     pub async fn retry_wal_coins(&self) -> Result<(), FaucetError> {
         let mut wal = self.wal.lock().await;
@@ -418,6 +633,7 @@ impl SimpleFaucet {
         tx_data: TransactionData,
         for_batch: bool,
     ) -> Result<MysTransactionBlockResponse, FaucetError> {
+        let custom_coins = self.custom_coin_inputs(&tx_data);
         let signature = self
             .wallet
             .config
@@ -485,6 +701,9 @@ impl SimpleFaucet {
                 } else {
                     self.recycle_gas_coin(coin_id, uuid).await;
                 }
+                for (pool, custom_coin_id) in custom_coins {
+                    pool.recycle(custom_coin_id, uuid).await;
+                }
 
                 if let Some(ref balances) = result.balance_changes {
                     let mys_used = balances
@@ -522,10 +741,30 @@ impl SimpleFaucet {
             .await;
         match gas_coin_response {
             GasCoinResponse::ValidGasCoin(coin_id) => {
-                let tx_data = self
-                    .build_pay_mys_txn(coin_id, self.active_address, recipient, amounts, gas_cost)
+                let custom_coins = match self.prepare_custom_coins(1, uuid).await {
+                    Ok(custom_coins) => custom_coins,
+                    Err(e) => {
+                        self.recycle_gas_coin(coin_id, uuid).await;
+                        return Err(e);
+                    }
+                };
+                let tx_data = match self
+                    .build_pay_mys_txn(
+                        coin_id,
+                        self.active_address,
+                        recipient,
+                        amounts,
+                        &custom_coins,
+                        gas_cost,
+                    )
                     .await
-                    .map_err(FaucetError::internal)?;
+                {
+                    Ok(tx_data) => tx_data,
+                    Err(e) => {
+                        self.recycle_custom_coins(&custom_coins, uuid).await;
+                        return Err(FaucetError::internal(e));
+                    }
+                };
 
                 {
                     // Register the intention to send this transaction before we send it, so that if
@@ -644,7 +883,8 @@ impl SimpleFaucet {
                 tx.clone(),
                 MysTransactionBlockResponseOptions::new()
                     .with_effects()
-                    .with_balance_changes(),
+                    .with_balance_changes()
+                    .with_object_changes(),
                 Some(ExecuteTransactionRequestType::WaitForLocalExecution),
             )
             .await
@@ -684,9 +924,28 @@ impl SimpleFaucet {
         signer: MysAddress,
         recipient: MysAddress,
         amounts: &[u64],
+        custom_coins: &[(ObjectRef, u64)],
         budget: u64,
     ) -> Result<TransactionData, anyhow::Error> {
         let recipients = vec![recipient; amounts.len()];
+        if !custom_coins.is_empty() {
+            let gas_payment = self.wallet.get_object_ref(coin_id).await?;
+            let gas_price = self.wallet.get_reference_gas_price().await?;
+            let pt = {
+                let mut builder = ProgrammableTransactionBuilder::new();
+                builder.pay_mys(recipients, amounts.to_vec())?;
+                pay_custom_coins(&mut builder, custom_coins, &[recipient])?;
+                builder.finish()
+            };
+            return Ok(TransactionData::new_programmable(
+                signer,
+                vec![gas_payment],
+                pt,
+                budget,
+                gas_price,
+            ));
+        }
+
         let client = self.wallet.get_client().await?;
         client
             .transaction_builder()
@@ -707,16 +966,7 @@ impl SimpleFaucet {
         number_of_coins: usize,
         recipient: MysAddress,
     ) -> Result<(TransactionDigest, Vec<ObjectID>), FaucetError> {
-        let created = res
-            .effects
-            .ok_or_else(|| {
-                FaucetError::ParseTransactionResponseError(format!(
-                    "effects field missing for txn {}",
-                    res.digest
-                ))
-            })?
-            .created()
-            .to_vec();
+        let created = created_mys_coins(&res)?;
         if created.len() != number_of_coins {
             return Err(FaucetError::CoinAmountTransferredIncorrect(format!(
                 "PayMys Transaction should create exact {:?} new coins, but got {:?}",
@@ -740,11 +990,16 @@ impl SimpleFaucet {
         &self,
         coin_id: ObjectID,
         batch_requests: Vec<(Uuid, MysAddress, Vec<u64>)>,
+        custom_coins: &[(ObjectRef, u64)],
         signer: MysAddress,
         budget: u64,
     ) -> Result<TransactionData, anyhow::Error> {
         let gas_payment = self.wallet.get_object_ref(coin_id).await?;
         let gas_price = self.wallet.get_reference_gas_price().await?;
+        let batch_recipients: Vec<_> = batch_requests
+            .iter()
+            .map(|(_, recipient, _)| *recipient)
+            .collect();
         // TODO (Jian): change to make this more efficient by changing impl to one Splitcoin, and many TransferObjects
         let pt = {
             let mut builder = ProgrammableTransactionBuilder::new();
@@ -752,6 +1007,7 @@ impl SimpleFaucet {
                 let recipients = vec![recipient; amounts.len()];
                 builder.pay_mys(recipients, amounts)?;
             }
+            pay_custom_coins(&mut builder, custom_coins, &batch_recipients)?;
             builder.finish()
         };
 
@@ -770,16 +1026,7 @@ impl SimpleFaucet {
         requests: Vec<(Uuid, MysAddress, Vec<u64>)>,
     ) -> Result<(), FaucetError> {
         // Grab the list of created coins and turn it into a map of destination MysAddress to Vec<Coins>
-        let created = res
-            .effects
-            .ok_or_else(|| {
-                FaucetError::ParseTransactionResponseError(format!(
-                    "effects field missing for txn {}",
-                    res.digest
-                ))
-            })?
-            .created()
-            .to_vec();
+        let created = created_mys_coins(&res)?;
 
         let mut address_coins_map: HashMap<MysAddress, Vec<OwnedObjectRef>> = HashMap::new();
         created.iter().for_each(|created_coin_owner_ref| {
@@ -1046,15 +1293,29 @@ pub async fn batch_transfer_gases(
 
         match gas_coin_response {
             GasCoinResponse::ValidGasCoin(coin_id) => {
-                let tx_data = faucet
+                let custom_coins = match faucet.prepare_custom_coins(total_requests, uuid).await {
+                    Ok(custom_coins) => custom_coins,
+                    Err(e) => {
+                        faucet.recycle_gas_coin_for_batch(coin_id, uuid).await;
+                        return Err(e);
+                    }
+                };
+                let tx_data = match faucet
                     .build_batch_pay_mys_txn(
                         coin_id,
                         requests.clone(),
+                        &custom_coins,
                         faucet.active_address,
                         gas_cost,
                     )
                     .await
-                    .map_err(FaucetError::internal)?;
+                {
+                    Ok(tx_data) => tx_data,
+                    Err(e) => {
+                        faucet.recycle_custom_coins(&custom_coins, uuid).await;
+                        return Err(FaucetError::internal(e));
+                    }
+                };
 
                 // Because we are batching transactions to faucet, we will just not use a real recipient for
                 // mys address, and instead just fill it with the ZERO address.
@@ -1110,6 +1371,54 @@ pub async fn batch_transfer_gases(
     }
 }
 
+/// Sends each of `recipients` the configured amount of every custom coin, split off from the
+/// coins taken out of the custom coin pools.
+fn pay_custom_coins(
+    builder: &mut ProgrammableTransactionBuilder,
+    custom_coins: &[(ObjectRef, u64)],
+    recipients: &[MysAddress],
+) -> anyhow::Result<()> {
+    for (coin, amount) in custom_coins {
+        builder.pay(
+            vec![*coin],
+            recipients.to_vec(),
+            vec![*amount; recipients.len()],
+        )?;
+    }
+    Ok(())
+}
+
+/// The MYS coins created by a faucet transaction, leaving out any custom coins sent along.
+fn created_mys_coins(
+    res: &MysTransactionBlockResponse,
+) -> Result<Vec<OwnedObjectRef>, FaucetError> {
+    let effects = res.effects.as_ref().ok_or_else(|| {
+        FaucetError::ParseTransactionResponseError(format!(
+            "effects field missing for txn {}",
+            res.digest
+        ))
+    })?;
+    let custom_coins: HashSet<ObjectID> = res
+        .object_changes
+        .iter()
+        .flatten()
+        .filter_map(|change| match change {
+            ObjectChange::Created {
+                object_id,
+                object_type,
+                ..
+            } if !GasCoin::is_gas_coin(object_type) => Some(*object_id),
+            _ => None,
+        })
+        .collect();
+    Ok(effects
+        .created()
+        .iter()
+        .filter(|created| !custom_coins.contains(&created.object_id()))
+        .cloned()
+        .collect())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1118,6 +1427,7 @@ mod tests {
     use mys_json_rpc_types::MysExecutionStatus;
     use mys_json_rpc_types::MysTransactionBlockEffects;
     use mys_sdk::wallet_context::WalletContext;
+    use mys_types::gas_coin::GAS;
     use mys_types::transaction::SenderSignedData;
     use mys_types::transaction::TransactionDataAPI;
     use test_cluster::TestClusterBuilder;
@@ -1540,7 +1850,7 @@ mod tests {
         };
 
         let tx_data = faucet
-            .build_pay_mys_txn(coin_id, faucet_address, recipient, &[100], 200_000_000)
+            .build_pay_mys_txn(coin_id, faucet_address, recipient, &[100], &[], 200_000_000)
             .await
             .map_err(FaucetError::internal)
             .unwrap();
@@ -1843,7 +2153,7 @@ mod tests {
         };
 
         let tx_data = faucet
-            .build_pay_mys_txn(coin_id, faucet_address, recipient, &[100], 200_000_000)
+            .build_pay_mys_txn(coin_id, faucet_address, recipient, &[100], &[], 200_000_000)
             .await
             .map_err(FaucetError::internal)
             .unwrap();
@@ -1978,6 +2288,169 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_custom_coin_config() {
+        let config: CustomCoinConfig = "0x42::usdc::USDC=1000".parse().unwrap();
+        assert_eq!(
+            config.coin_type,
+            mys_types::parse_mys_type_tag("0x42::usdc::USDC").unwrap()
+        );
+        assert_eq!(config.amount, 1000);
+
+        assert!("0x42::usdc::USDC".parse::<CustomCoinConfig>().is_err());
+        assert!("0x42::usdc::USDC=lots".parse::<CustomCoinConfig>().is_err());
+        // MYS is always sent from the gas coins.
+        assert!("0x2::mys::MYS=1000".parse::<CustomCoinConfig>().is_err());
+    }
+
+    /// A custom coin pool holding `coins`. Tests use MYS coins taken out of the gas queue, which
+    /// `CustomCoinConfig` would not accept, to avoid publishing a coin package.
+    fn custom_coin_pool(amount: u64, coins: &[ObjectID]) -> CustomCoinPool {
+        let (producer, consumer) = mpsc::channel(coins.len().max(1));
+        for coin in coins {
+            producer.try_send(*coin).unwrap();
+        }
+        CustomCoinPool {
+            coin_type: GAS::type_tag(),
+            amount,
+            coins: coins.iter().copied().collect(),
+            producer: Mutex::new(producer),
+            consumer: Mutex::new(consumer),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_prepare_custom_coins() {
+        let test_cluster = TestClusterBuilder::new().build().await;
+        let context = test_cluster.wallet;
+        let tmp = tempfile::tempdir().unwrap();
+        let prom_registry = Registry::new();
+        let config = FaucetConfig::default();
+        let faucet = SimpleFaucet::new(
+            context,
+            &prom_registry,
+            &tmp.path().join("faucet.wal"),
+            config,
+        )
+        .await
+        .unwrap();
+        faucet.shutdown_batch_send_task();
+        let mut faucet = Arc::try_unwrap(faucet).unwrap();
+        let uuid = Uuid::new_v4();
+
+        let coin_id = faucet.pop_gas_coin(uuid).await.unwrap();
+        faucet
+            .custom_coin_pools
+            .push(custom_coin_pool(100, &[coin_id]));
+
+        let custom_coins = faucet.prepare_custom_coins(2, uuid).await.unwrap();
+        assert_eq!(custom_coins.len(), 1);
+        assert_eq!(custom_coins[0].0 .0, coin_id);
+        assert_eq!(custom_coins[0].1, 100);
+
+        // The coin is out of the pool until it is recycled, so the coin type is left out.
+        assert!(faucet
+            .prepare_custom_coins(2, uuid)
+            .await
+            .unwrap()
+            .is_empty());
+        faucet.recycle_custom_coins(&custom_coins, uuid).await;
+
+        // An amount that overflows fails the request without taking the coin.
+        faucet.custom_coin_pools[0].amount = u64::MAX;
+        assert!(faucet.prepare_custom_coins(2, uuid).await.is_err());
+        assert_eq!(
+            faucet.custom_coin_pools[0]
+                .consumer
+                .lock()
+                .await
+                .try_recv()
+                .ok(),
+            Some(coin_id)
+        );
+        faucet.custom_coin_pools[0].recycle(coin_id, uuid).await;
+
+        // A coin without enough balance is removed from the pool.
+        faucet.custom_coin_pools[0].amount = u64::MAX / 2;
+        assert!(faucet
+            .prepare_custom_coins(2, uuid)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(faucet.custom_coin_pools[0]
+            .consumer
+            .lock()
+            .await
+            .try_recv()
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_retry_wal_recycles_custom_coins() {
+        telemetry_subscribers::init_for_testing();
+        let test_cluster = TestClusterBuilder::new().build().await;
+        let context = test_cluster.wallet;
+        let tmp = tempfile::tempdir().unwrap();
+        let prom_registry = Registry::new();
+        let config = FaucetConfig::default();
+        let faucet = SimpleFaucet::new(
+            context,
+            &prom_registry,
+            &tmp.path().join("faucet.wal"),
+            config,
+        )
+        .await
+        .unwrap();
+        faucet.shutdown_batch_send_task();
+        let mut faucet = Arc::try_unwrap(faucet).unwrap();
+
+        let recipient = MysAddress::random_for_testing_only();
+        let faucet_address = faucet.active_address;
+        let uuid = Uuid::new_v4();
+
+        let custom_coin_id = faucet.pop_gas_coin(uuid).await.unwrap();
+        faucet
+            .custom_coin_pools
+            .push(custom_coin_pool(100, &[custom_coin_id]));
+
+        let GasCoinResponse::ValidGasCoin(coin_id) =
+            faucet.prepare_gas_coin(100, uuid, false).await
+        else {
+            panic!("prepare_gas_coin did not give a valid coin.")
+        };
+        let custom_coins = faucet.prepare_custom_coins(1, uuid).await.unwrap();
+        let tx_data = faucet
+            .build_pay_mys_txn(
+                coin_id,
+                faucet_address,
+                recipient,
+                &[100],
+                &custom_coins,
+                200_000_000,
+            )
+            .await
+            .unwrap();
+
+        // Only the custom coin is recognised among the inputs, not the gas coin.
+        let inputs = faucet.custom_coin_inputs(&tx_data);
+        assert_eq!(inputs.len(), 1);
+        assert!(std::ptr::eq(inputs[0].0, &faucet.custom_coin_pools[0]));
+        assert_eq!(inputs[0].1, custom_coin_id);
+
+        let mut wal = faucet.wal.lock().await;
+        wal.reserve(uuid, coin_id, recipient, tx_data).unwrap();
+        wal.set_in_flight(coin_id, false).unwrap();
+        drop(wal);
+
+        faucet.retry_wal_coins().await.unwrap();
+        assert!(faucet.wal.lock().await.log.is_empty());
+
+        // The custom coin is back in its pool once the retried transaction went through.
+        let custom_coins = faucet.prepare_custom_coins(1, uuid).await.unwrap();
+        assert_eq!(custom_coins.len(), 1);
+        assert_eq!(custom_coins[0].0 .0, custom_coin_id);
+    }
+
     async fn test_send_interface_has_success_status(faucet: &impl Faucet) {
         let recipient = MysAddress::random_for_testing_only();
         let amounts = vec![1, 2, 3];
@@ -10,6 +10,7 @@ use typed_store::traits::{TableSummary, TypedStoreDebug};
 use typed_store::Map;
 use typed_store::{rocks::DBMap, TypedStoreError};
 
+use super::rate_limiter::{RateLimitEntry, RateLimitKey};
 use tracing::info;
 use typed_store::DBMapUtils;
 use uuid::Uuid;
@@ -21,9 +22,13 @@ use uuid::Uuid;
 ///
 /// This allows the faucet to go down and back up, and not forget which requests were in-flight that
 /// it needs to confirm succeeded or failed.
+///
+/// The same database also holds the request counters of the faucet's rate limiter, so that limits
+/// are not reset when the faucet restarts.
 #[derive(DBMapUtils, Clone)]
 pub struct WriteAheadLog {
     pub log: DBMap<ObjectID, Entry>,
+    pub rate_limits: DBMap<RateLimitKey, RateLimitEntry>,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
@@ -2,8 +2,9 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    AppState, BatchFaucetResponse, BatchStatusFaucetResponse, FaucetConfig, FaucetError,
-    FaucetRequest, FaucetResponse, FixedAmountRequest, RequestMetricsLayer,
+    client_ip, AppState, BatchFaucetResponse, BatchStatusFaucetResponse, ChallengeVerifier,
+    FaucetConfig, FaucetError, FaucetRequest, FaucetResponse, FixedAmountRequest, RateLimiter,
+    RequestMetricsLayer,
 };
 use axum::{
     error_handling::HandleErrorLayer,
@@ -25,6 +26,7 @@ use std::{
 };
 use mys_config::MYS_CLIENT_CONFIG;
 use mys_sdk::wallet_context::WalletContext;
+use mys_types::base_types::MysAddress;
 use tower::ServiceBuilder;
 use tower_governor::{
     governor::GovernorConfigBuilder, key_extractor::GlobalKeyExtractor, GovernorLayer,
@@ -201,6 +203,28 @@ impl RequestsManager {
     }
 }
 
+/// Checks a request for `recipient` against the configured challenges and rate limits. The client
+/// IP is only known for requests coming directly from clients.
+fn check_request_limits(
+    rate_limiter: &RateLimiter,
+    challenges: Option<&ChallengeVerifier>,
+    headers: &HeaderMap,
+    recipient: MysAddress,
+    ip: Option<IpAddr>,
+) -> Result<(), (StatusCode, FaucetError)> {
+    challenges
+        .map_or(Ok(()), |challenges| challenges.verify(recipient, headers))
+        .and_then(|()| rate_limiter.check(recipient, ip))
+        .map_err(|e| {
+            let status = match e {
+                FaucetError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+                FaucetError::InvalidChallenge(_) => StatusCode::FORBIDDEN,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            (status, e)
+        })
+}
+
 pub async fn start_faucet(
     app_state: Arc<AppState>,
     concurrency_limit: usize,
@@ -244,6 +268,12 @@ pub async fn start_faucet(
         turnstile_secret_key,
     ));
 
+    let rate_limiter = Arc::new(RateLimiter::new(
+        app_state.faucet.wal.lock().await.rate_limits.clone(),
+        &app_state.config,
+    ));
+    let challenges = Arc::new(ChallengeVerifier::new(&app_state.config));
+
     let governor_cfg = Arc::new(
         GovernorConfigBuilder::default()
             .const_per_millisecond(replenish_quota_interval_ms)
@@ -269,7 +299,8 @@ pub async fn start_faucet(
         .route("/", get(redirect))
         .route("/health", get(health))
         .route("/v1/faucet_discord", post(batch_faucet_discord))
-        .route("/v1/status/:task_id", get(request_status));
+        .route("/v1/status/:task_id", get(request_status))
+        .route("/v1/challenge", get(request_challenge));
 
     // Combine all routes
     let app = Router::new()
@@ -285,6 +316,8 @@ pub async fn start_faucet(
                 .concurrency_limit(concurrency_limit)
                 .layer(Extension(app_state.clone()))
                 .layer(Extension(token_manager.clone()))
+                .layer(Extension(rate_limiter.clone()))
+                .layer(Extension(challenges.clone()))
                 .layer(cors)
                 .into_inner(),
         );
@@ -303,6 +336,10 @@ pub async fn start_faucet(
         loop {
             tokio::time::sleep(Duration::from_secs(rate_limiter_cleanup_interval_secs)).await;
             token_manager.cleanup_expired_tokens();
+            challenges.cleanup();
+            if let Err(e) = rate_limiter.cleanup() {
+                warn!("Failed to clean up expired rate limits: {:?}", e);
+            }
         }
     });
 
@@ -322,6 +359,23 @@ async fn health() -> &'static str {
     "OK"
 }
 
+/// Hands out a proof-of-work challenge, if those are enabled
+async fn request_challenge(
+    headers: HeaderMap,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(challenges): Extension<Arc<ChallengeVerifier>>,
+) -> Response {
+    let ip = client_ip(&headers, addr.ip(), &state.config.trusted_proxies);
+    match challenges.issue_pow_challenge(ip) {
+        Ok(challenge) => (StatusCode::OK, Json(challenge)).into_response(),
+        Err(e @ FaucetError::TooManyRequests(_)) => {
+            (StatusCode::TOO_MANY_REQUESTS, e.to_string()).into_response()
+        }
+        Err(e) => (StatusCode::NOT_FOUND, e.to_string()).into_response(),
+    }
+}
+
 /// Redirect to faucet.mys.io/?network if it's testnet/devnet network. For local network, keep the
 /// previous behavior to return health status.
 async fn redirect(Host(host): Host) -> Response {
@@ -341,6 +395,7 @@ async fn redirect(Host(host): Host) -> Response {
 async fn batch_faucet_discord(
     headers: HeaderMap,
     Extension(state): Extension<Arc<AppState>>,
+    Extension(rate_limiter): Extension<Arc<RateLimiter>>,
     Json(payload): Json<FaucetRequest>,
 ) -> impl IntoResponse {
     if state.config.authenticated {
@@ -375,6 +430,13 @@ async fn batch_faucet_discord(
         );
     };
 
+    // Requests are relayed by the bot, so only the recipient address is limited
+    if let Err((status_code, faucet_error)) =
+        check_request_limits(&rate_limiter, None, &headers, request.recipient, None)
+    {
+        return (status_code, Json(BatchFaucetResponse::from(faucet_error)));
+    }
+
     batch_request_spawn_task(request, state).await
 }
 
@@ -384,6 +446,8 @@ async fn batch_faucet_web_gas(
     ConnectInfo(addr): ConnectInfo<SocketAddr>,
     Extension(token_manager): Extension<Arc<RequestsManager>>,
     Extension(state): Extension<Arc<AppState>>,
+    Extension(rate_limiter): Extension<Arc<RateLimiter>>,
+    Extension(challenges): Extension<Arc<ChallengeVerifier>>,
     Json(payload): Json<FaucetRequest>,
 ) -> impl IntoResponse {
     if state.config.authenticated {
@@ -415,6 +479,20 @@ async fn batch_faucet_web_gas(
         );
     };
 
+    if let Err((status_code, faucet_error)) = check_request_limits(
+        &rate_limiter,
+        Some(&challenges),
+        &headers,
+        request.recipient,
+        Some(client_ip(
+            &headers,
+            addr.ip(),
+            &state.config.trusted_proxies,
+        )),
+    ) {
+        return (status_code, Json(BatchFaucetResponse::from(faucet_error)));
+    }
+
     batch_request_spawn_task(request, state).await
 }
 
@@ -446,7 +524,11 @@ async fn batch_request_spawn_task(
 
 /// handler for batch_request_gas requests
 async fn batch_request_gas(
+    headers: HeaderMap,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
     Extension(state): Extension<Arc<AppState>>,
+    Extension(rate_limiter): Extension<Arc<RateLimiter>>,
+    Extension(challenges): Extension<Arc<ChallengeVerifier>>,
     Json(payload): Json<FaucetRequest>,
 ) -> impl IntoResponse {
     let id = Uuid::new_v4();
@@ -462,6 +544,20 @@ async fn batch_request_gas(
         );
     };
 
+    if let Err((status_code, faucet_error)) = check_request_limits(
+        &rate_limiter,
+        Some(&challenges),
+        &headers,
+        request.recipient,
+        Some(client_ip(
+            &headers,
+            addr.ip(),
+            &state.config.trusted_proxies,
+        )),
+    ) {
+        return (status_code, Json(BatchFaucetResponse::from(faucet_error)));
+    }
+
     if state.config.batch_enabled {
         batch_request_spawn_task(request, state).await
     } else {
@@ -526,7 +622,11 @@ async fn request_status(
 
 /// handler for all the request_gas requests
 async fn request_gas(
+    headers: HeaderMap,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
     Extension(state): Extension<Arc<AppState>>,
+    Extension(rate_limiter): Extension<Arc<RateLimiter>>,
+    Extension(challenges): Extension<Arc<ChallengeVerifier>>,
     Json(payload): Json<FaucetRequest>,
 ) -> impl IntoResponse {
     // ID for traceability
@@ -535,6 +635,19 @@ async fn request_gas(
 
     let result = match payload {
         FaucetRequest::FixedAmountRequest(requests) => {
+            if let Err((status_code, faucet_error)) = check_request_limits(
+                &rate_limiter,
+                Some(&challenges),
+                &headers,
+                requests.recipient,
+                Some(client_ip(
+                    &headers,
+                    addr.ip(),
+                    &state.config.trusted_proxies,
+                )),
+            ) {
+                return (status_code, Json(FaucetResponse::from(faucet_error)));
+            }
             // We spawn a tokio task for this such that connection drop will not interrupt
             // it and impact the recycling of coins
             spawn_monitored_task!(async move {
@@ -602,7 +715,7 @@ async fn handle_error(error: BoxError) -> impl IntoResponse {
 }
 
 /// Format seconds to human readable format.
-fn secs_to_human_readable(seconds: u64) -> String {
+pub(crate) fn secs_to_human_readable(seconds: u64) -> String {
     let hours = seconds / 3600;
     let minutes = (seconds % 3600) / 60;
     let seconds = seconds % 60;
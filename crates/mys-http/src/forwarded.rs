@@ -0,0 +1,77 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Identifying clients of requests relayed by reverse proxies.
+
+use http::HeaderMap;
+use ipnetwork::IpNetwork;
+use std::net::IpAddr;
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+
+/// The IP of the client which sent a request received from `remote`. Requests relayed by one of
+/// `trusted_proxies` are attributed to the rightmost `X-Forwarded-For` address which is not a
+/// trusted proxy itself, since the entries to its left are under the client's control.
+pub fn client_ip(headers: &HeaderMap, remote: IpAddr, trusted_proxies: &[IpNetwork]) -> IpAddr {
+    let is_trusted = |ip: &IpAddr| trusted_proxies.iter().any(|net| net.contains(*ip));
+    if !is_trusted(&remote) {
+        return remote;
+    }
+    let forwarded = headers
+        .get_all(X_FORWARDED_FOR)
+        .iter()
+        .filter_map(|h| h.to_str().ok())
+        .flat_map(|h| h.split(','))
+        .map(|address| address.trim().parse::<IpAddr>().ok())
+        .collect::<Vec<_>>();
+    let mut client = remote;
+    for address in forwarded.into_iter().rev() {
+        // Entries that cannot be parsed were not added by a trusted proxy
+        let Some(address) = address else {
+            break;
+        };
+        client = address;
+        if !is_trusted(&address) {
+            break;
+        }
+    }
+    client
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderValue;
+
+    fn forwarded_for(values: &[&str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for value in values {
+            headers.append(X_FORWARDED_FOR, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_client_ip() {
+        let trusted: Vec<IpNetwork> = vec!["10.0.0.0/8".parse().unwrap()];
+        let proxy: IpAddr = "10.0.0.1".parse().unwrap();
+        let client: IpAddr = "203.0.113.7".parse().unwrap();
+
+        // Clients connecting directly cannot pick their address
+        let headers = forwarded_for(&["198.51.100.1"]);
+        assert_eq!(client_ip(&headers, client, &trusted), client);
+        assert_eq!(client_ip(&headers, proxy, &[]), proxy);
+
+        // Behind trusted proxies, addresses the client added in front are ignored, including
+        // across repeated headers
+        let headers = forwarded_for(&["198.51.100.1, 203.0.113.7, 10.0.0.2"]);
+        assert_eq!(client_ip(&headers, proxy, &trusted), client);
+        let headers = forwarded_for(&["198.51.100.1", "203.0.113.7"]);
+        assert_eq!(client_ip(&headers, proxy, &trusted), client);
+
+        // Without a usable header, the proxy itself is the client
+        assert_eq!(client_ip(&HeaderMap::new(), proxy, &trusted), proxy);
+        let headers = forwarded_for(&["not-an-address"]);
+        assert_eq!(client_ip(&headers, proxy, &trusted), proxy);
+    }
+}
@@ -22,6 +22,7 @@ pub mod body;
 mod config;
 mod connection_handler;
 mod connection_info;
+pub mod forwarded;
 mod fuse;
 mod io;
 mod listener;
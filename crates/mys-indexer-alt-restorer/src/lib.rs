@@ -6,7 +6,11 @@ mod snapshot;
 
 use archives::ArchivalCheckpointInfo;
 use clap::Parser;
+use move_core_types::language_storage::StructTag;
 use mys_pg_db::DbArgs;
+use mys_snapshot::filter::LiveObjectFilter;
+use mys_types::base_types::{MysAddress, ObjectID};
+use mys_types::parse_mys_struct_tag;
 
 use crate::snapshot::SnapshotRestorer;
 
@@ -38,11 +42,41 @@ pub struct Args {
     #[clap(long, env = "CONCURRENCY", default_value_t = 50)]
     pub concurrency: usize,
 
+    /// Only restore packages with these IDs and objects whose type mentions one of them.
+    #[clap(long = "package", env = "RESTORE_PACKAGES", value_delimiter = ',')]
+    pub packages: Vec<ObjectID>,
+
+    /// Only restore objects of these struct types, e.g. `0x2::coin::Coin` for coins of any
+    /// type or `0x2::coin::Coin<0x2::mys::MYS>` for a single one.
+    #[clap(
+        long = "struct-type",
+        env = "RESTORE_STRUCT_TYPES",
+        value_delimiter = ',',
+        value_parser = parse_mys_struct_tag
+    )]
+    pub struct_types: Vec<StructTag>,
+
+    /// Only restore objects owned by these addresses.
+    #[clap(long = "owner", env = "RESTORE_OWNERS", value_delimiter = ',')]
+    pub owners: Vec<MysAddress>,
+
     /// Database connection arguments from `mys-pg-db`.
     #[clap(flatten)]
     pub db_args: DbArgs,
 }
 
+impl Args {
+    /// Objects to restore, matching any of the `--package`, `--struct-type` or `--owner`
+    /// predicates. Everything is restored if none is given.
+    pub fn live_object_filter(&self) -> LiveObjectFilter {
+        LiveObjectFilter {
+            packages: self.packages.iter().copied().collect(),
+            struct_types: self.struct_types.clone(),
+            owners: self.owners.iter().copied().collect(),
+        }
+    }
+}
+
 pub async fn restore(args: &Args) -> anyhow::Result<()> {
     let archival_checkpoint_info =
         ArchivalCheckpointInfo::read_archival_checkpoint_info(args).await?;
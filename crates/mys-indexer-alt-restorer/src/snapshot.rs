@@ -7,6 +7,7 @@ use std::path::PathBuf;
 use std::sync::Arc;
 
 use anyhow::Error;
+use diesel::{ExpressionMethods, QueryDsl};
 use diesel_async::RunQueryDsl;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use object_store::path::Path;
@@ -21,9 +22,14 @@ use mys_indexer_alt_schema::objects::StoredObjInfo;
 use mys_indexer_alt_schema::schema::obj_info;
 use mys_pg_db::Db;
 use mys_snapshot::{
-    reader::{download_bytes, LiveObjectIter, StateSnapshotReaderV1},
+    filter::LiveObjectFilter,
+    reader::{
+        download_bytes, read_manifest_chain, verify_live_objects_sha3, LiveObjectIter,
+        StateSnapshotReaderV1,
+    },
     FileMetadata,
 };
+use mys_storage::object_store::http::HttpDownloaderBuilder;
 use mys_storage::object_store::ObjectStoreGetExt;
 
 use crate::Args;
@@ -33,7 +39,9 @@ pub type DigestByBucketAndPartition = BTreeMap<u32, BTreeMap<u32, [u8; 32]>>;
 pub struct SnapshotRestorer {
     pub restore_args: Args,
     pub next_checkpoint_after_epoch: u64,
-    pub snapshot_reader: StateSnapshotReaderV1,
+    /// Readers of the snapshot chain of the start epoch, starting with the full snapshot
+    pub snapshot_readers: Vec<StateSnapshotReaderV1>,
+    pub filter: LiveObjectFilter,
     pub db: Db,
 }
 
@@ -57,8 +65,10 @@ impl SnapshotRestorer {
         };
 
         let m = MultiProgress::new();
-        let snapshot_reader = StateSnapshotReaderV1::new(
-            args.start_epoch,
+        let manifests =
+            read_manifest_chain(args.start_epoch, &remote_store_config.make_http()?).await?;
+        let snapshot_readers = StateSnapshotReaderV1::new_chain(
+            &manifests,
             &remote_store_config,
             &local_store_config,
             usize::MAX, // indirect_objects_threshold
@@ -71,7 +81,8 @@ impl SnapshotRestorer {
 
         Ok(Self {
             restore_args: args.clone(),
-            snapshot_reader,
+            snapshot_readers,
+            filter: args.live_object_filter(),
             db,
             next_checkpoint_after_epoch,
         })
@@ -80,24 +91,45 @@ impl SnapshotRestorer {
     pub async fn restore(&mut self) -> Result<(), Error> {
         info!(
             epoch = self.restore_args.start_epoch,
+            filter = ?self.filter,
             "Starting snapshot restore"
         );
-        let (sha3_digests, num_part_files) = self.snapshot_reader.compute_checksum().await?;
-        let (input_files, epoch_dir, remote_object_store, _concurrency) =
-            self.snapshot_reader.export_metadata().await?;
-        let owned_input_files: Vec<(u32, (u32, FileMetadata))> = input_files
-            .into_iter()
-            .map(|(bucket, (part_num, metadata))| (*bucket, (part_num, metadata.clone())))
-            .collect();
-        info!("Start snapshot restore.");
-        self.restore_object_infos(
-            owned_input_files,
-            epoch_dir,
-            remote_object_store,
-            sha3_digests,
-            num_part_files,
-        )
-        .await?;
+        let mut snapshot_readers = std::mem::take(&mut self.snapshot_readers);
+        for snapshot_reader in snapshot_readers.iter_mut() {
+            let is_delta = snapshot_reader.base_epoch().is_some();
+            if is_delta {
+                let deleted_object_ids = snapshot_reader
+                    .deleted_object_refs()
+                    .await?
+                    .into_iter()
+                    .map(|(object_id, _, _)| object_id.to_vec())
+                    .collect();
+                delete_object_infos(&self.db, deleted_object_ids).await?;
+            }
+            let (sha3_digests, num_part_files) = snapshot_reader.compute_checksum().await?;
+            let (input_files, epoch_dir, remote_object_store, _concurrency) =
+                snapshot_reader.export_metadata().await?;
+            let owned_input_files: Vec<(u32, (u32, FileMetadata))> = input_files
+                .into_iter()
+                .map(|(bucket, (part_num, metadata))| (*bucket, (part_num, metadata.clone())))
+                .collect();
+            info!(
+                epoch = snapshot_reader.epoch(),
+                base_epoch = ?snapshot_reader.base_epoch(),
+                "Start snapshot restore."
+            );
+            self.restore_object_infos(
+                owned_input_files,
+                epoch_dir,
+                remote_object_store,
+                sha3_digests,
+                num_part_files,
+                snapshot_reader.get_multi_progress(),
+                is_delta,
+            )
+            .await?;
+        }
+        self.snapshot_readers = snapshot_readers;
         info!(
             epoch = self.restore_args.start_epoch,
             "Finished snapshot restore"
@@ -105,6 +137,10 @@ impl SnapshotRestorer {
         Ok(())
     }
 
+    /// Inserts the object infos of the objects of a snapshot matching the filter. Object infos
+    /// restored from previous snapshots of the chain for objects in a delta snapshot are removed
+    /// first, as the objects may have changed owner or been wrapped since.
+    #[allow(clippy::too_many_arguments)]
     async fn restore_object_infos(
         &self,
         input_files: Vec<(u32, (u32, FileMetadata))>,
@@ -112,8 +148,10 @@ impl SnapshotRestorer {
         remote_object_store: Arc<dyn ObjectStoreGetExt>,
         sha3_digests: Arc<Mutex<DigestByBucketAndPartition>>,
         num_part_files: usize,
+        m: MultiProgress,
+        is_delta: bool,
     ) -> anyhow::Result<()> {
-        let move_object_progress_bar = Arc::new(self.snapshot_reader.get_multi_progress().add(
+        let move_object_progress_bar = Arc::new(m.add(
             ProgressBar::new(num_part_files as u64).with_style(
                 ProgressStyle::with_template(
                     "[{elapsed_precise}] {wide_bar} {pos} out of {len} move object files restored ({msg})",
@@ -131,6 +169,7 @@ impl SnapshotRestorer {
                     let sha3_digests = sha3_digests.clone();
                     let bar = move_object_progress_bar.clone();
                     let db = self.db.clone();
+                    let filter = self.filter.clone();
                     let next_cp = self.next_checkpoint_after_epoch;
 
                     async move {
@@ -140,7 +179,7 @@ impl SnapshotRestorer {
                             "Start downloading move object file"
                         );
                         let mut conn = db.connect().await?;
-                        let (bytes, _) = download_bytes(
+                        let (bytes, sha3_digest) = download_bytes(
                             remote_object_store,
                             &file_metadata,
                             epoch_dir,
@@ -155,10 +194,23 @@ impl SnapshotRestorer {
                             part_num = part_num,
                             "Finished downloading move object file"
                         );
-                        let object_infos = LiveObjectIter::new(&file_metadata, bytes.clone())?
+                        let live_objects: Vec<_> =
+                            LiveObjectIter::new(&file_metadata, bytes.clone())?.collect();
+                        // NOTE: verify the whole partition before filtering it
+                        verify_live_objects_sha3(&live_objects, &sha3_digest)?;
+                        if is_delta {
+                            let object_ids = live_objects
+                                .iter()
+                                .map(|object| object.object_id().to_vec())
+                                .collect();
+                            delete_object_infos(&db, object_ids).await?;
+                        }
+                        let object_infos = live_objects
+                            .iter()
+                            .filter(|object| filter.matches(object))
                             .filter_map(|object| match object {
                                 LiveObject::Normal(obj) => {
-                                    Some(StoredObjInfo::from_object(&obj, next_cp as i64))
+                                    Some(StoredObjInfo::from_object(obj, next_cp as i64))
                                 }
                                 LiveObject::Wrapped(_) => None,
                             })
@@ -195,3 +247,15 @@ impl SnapshotRestorer {
         Ok(())
     }
 }
+
+/// Removes the object infos of `object_ids`, which all share the restored checkpoint.
+async fn delete_object_infos(db: &Db, object_ids: Vec<Vec<u8>>) -> anyhow::Result<()> {
+    let mut conn = db.connect().await?;
+    // NOTE: chunk to avoid hitting the PG limit
+    for chunk in object_ids.chunks(i16::MAX as usize) {
+        diesel::delete(obj_info::table.filter(obj_info::object_id.eq_any(chunk)))
+            .execute(&mut conn)
+            .await?;
+    }
+    Ok(())
+}
@@ -15,8 +15,9 @@ use tracing::info;
 
 use mys_config::object_storage_config::{ObjectStoreConfig, ObjectStoreType};
 use mys_core::authority::authority_store_tables::LiveObject;
-use mys_snapshot::reader::{download_bytes, LiveObjectIter, StateSnapshotReaderV1};
+use mys_snapshot::reader::{download_bytes, read_manifest_chain, LiveObjectIter, StateSnapshotReaderV1};
 use mys_snapshot::FileMetadata;
+use mys_storage::object_store::http::HttpDownloaderBuilder;
 use mys_storage::object_store::util::get;
 use mys_storage::object_store::ObjectStoreGetExt;
 use mys_types::accumulator::Accumulator;
@@ -26,7 +27,7 @@ use crate::errors::IndexerError;
 use crate::handlers::TransactionObjectChangesToCommit;
 use crate::restorer::archives::{read_restore_checkpoint_info, RestoreCheckpointInfo};
 use crate::store::{indexer_store::IndexerStore, PgIndexerStore};
-use crate::types::{IndexedCheckpoint, IndexedObject};
+use crate::types::{IndexedCheckpoint, IndexedDeletedObject, IndexedObject};
 
 pub type DigestByBucketAndPartition = BTreeMap<u32, BTreeMap<u32, [u8; 32]>>;
 pub type SnapshotChecksums = (DigestByBucketAndPartition, Accumulator);
@@ -34,8 +35,10 @@ pub type Sha3DigestType = Arc<Mutex<BTreeMap<u32, BTreeMap<u32, [u8; 32]>>>>;
 
 pub struct IndexerFormalSnapshotRestorer {
     store: PgIndexerStore,
-    reader: StateSnapshotReaderV1,
+    /// Readers of the snapshot chain of the start epoch, starting with the full snapshot
+    readers: Vec<StateSnapshotReaderV1>,
     restore_config: RestoreConfig,
+    m: MultiProgress,
 }
 
 impl IndexerFormalSnapshotRestorer {
@@ -61,8 +64,14 @@ impl IndexerFormalSnapshotRestorer {
         };
 
         let m = MultiProgress::new();
-        let reader = StateSnapshotReaderV1::new(
-            restore_config.start_epoch,
+        // The snapshot of the start epoch may be a delta on top of the snapshots of earlier
+        // epochs, which all need to be restored in order
+        let remote_object_store = remote_store_config.make_http()?;
+        let manifests = read_manifest_chain(restore_config.start_epoch, &remote_object_store)
+            .await
+            .unwrap_or_else(|err| panic!("Failed to read snapshot chain: {}", err));
+        let readers = StateSnapshotReaderV1::new_chain(
+            &manifests,
             &remote_store_config,
             &local_store_config,
             usize::MAX,
@@ -73,35 +82,53 @@ impl IndexerFormalSnapshotRestorer {
         .await
         .unwrap_or_else(|err| panic!("Failed to create reader: {}", err));
         info!(
-            "Initialized formal snapshot reader at epoch {}",
-            restore_config.start_epoch
+            "Initialized formal snapshot readers at epoch {} for snapshot epochs {:?}",
+            restore_config.start_epoch,
+            readers
+                .iter()
+                .map(|reader| reader.epoch())
+                .collect::<Vec<_>>()
         );
 
         Ok(Self {
             store,
-            reader,
+            readers,
             restore_config: restore_config.clone(),
+            m,
         })
     }
 
     pub async fn restore(&mut self) -> Result<(), IndexerError> {
-        let (sha3_digests, num_part_files) = self.reader.compute_checksum().await?;
-        let (_abort_handle, abort_registration) = AbortHandle::new_pair();
-        let (input_files, epoch_dir, remote_object_store, _concurrency) =
-            self.reader.export_metadata().await?;
-        let owned_input_files: Vec<(u32, (u32, FileMetadata))> = input_files
-            .into_iter()
-            .map(|(bucket, (part_num, metadata))| (*bucket, (part_num, metadata.clone())))
-            .collect();
-        self.restore_move_objects(
-            abort_registration,
-            owned_input_files,
-            epoch_dir,
-            remote_object_store,
-            sha3_digests,
-            num_part_files,
-        )
-        .await?;
+        let mut readers = std::mem::take(&mut self.readers);
+        for reader in readers.iter_mut() {
+            let is_delta = reader.base_epoch().is_some();
+            if is_delta {
+                self.restore_deleted_objects(reader).await?;
+            }
+            let (sha3_digests, num_part_files) = reader.compute_checksum().await?;
+            let (_abort_handle, abort_registration) = AbortHandle::new_pair();
+            let (input_files, epoch_dir, remote_object_store, _concurrency) =
+                reader.export_metadata().await?;
+            let owned_input_files: Vec<(u32, (u32, FileMetadata))> = input_files
+                .into_iter()
+                .map(|(bucket, (part_num, metadata))| (*bucket, (part_num, metadata.clone())))
+                .collect();
+            self.restore_move_objects(
+                abort_registration,
+                owned_input_files,
+                epoch_dir,
+                remote_object_store,
+                sha3_digests,
+                num_part_files,
+                is_delta,
+            )
+            .await?;
+            info!(
+                "Finished restoring move objects of snapshot at epoch {}",
+                reader.epoch()
+            );
+        }
+        self.readers = readers;
         info!("Finished restoring move objects");
         self.restore_display_table().await?;
         info!("Finished restoring display table");
@@ -110,6 +137,47 @@ impl IndexerFormalSnapshotRestorer {
         Ok(())
     }
 
+    /// Removes the objects deleted or wrapped since the base epoch of a delta snapshot.
+    async fn restore_deleted_objects(
+        &self,
+        reader: &StateSnapshotReaderV1,
+    ) -> std::result::Result<(), anyhow::Error> {
+        let deleted_objects: Vec<_> = reader
+            .deleted_object_refs()
+            .await?
+            .into_iter()
+            .map(|(object_id, version, _)| IndexedDeletedObject {
+                object_id,
+                object_version: version.value(),
+                checkpoint_sequence_number: 0,
+            })
+            .collect();
+        let num_deleted = deleted_objects.len();
+        self.store
+            .persist_objects(vec![TransactionObjectChangesToCommit {
+                changed_objects: vec![],
+                deleted_objects: deleted_objects.clone(),
+            }])
+            .await?;
+        self.store
+            .persist_objects_snapshot(vec![TransactionObjectChangesToCommit {
+                changed_objects: vec![],
+                deleted_objects,
+            }])
+            .await?;
+        info!(
+            "Removed {} objects deleted since epoch {:?} from snapshot at epoch {}",
+            num_deleted,
+            reader.base_epoch(),
+            reader.epoch()
+        );
+        Ok(())
+    }
+
+    /// Persists the objects of a snapshot. Objects of a delta snapshot replace the versions
+    /// restored from previous snapshots of the chain, and objects it records as wrapped are
+    /// removed.
+    #[allow(clippy::too_many_arguments)]
     async fn restore_move_objects(
         &self,
         abort_registration: AbortRegistration,
@@ -118,8 +186,9 @@ impl IndexerFormalSnapshotRestorer {
         remote_object_store: Arc<dyn ObjectStoreGetExt>,
         sha3_digests: Arc<Mutex<DigestByBucketAndPartition>>,
         num_part_files: usize,
+        is_delta: bool,
     ) -> std::result::Result<(), anyhow::Error> {
-        let move_object_progress_bar = Arc::new(self.reader.get_multi_progress().add(
+        let move_object_progress_bar = Arc::new(self.m.add(
             ProgressBar::new(num_part_files as u64).with_style(
                 ProgressStyle::with_template(
                     "[{elapsed_precise}] {wide_bar} {pos} out of {len} move object files restored ({msg})",
@@ -162,6 +231,7 @@ impl IndexerFormalSnapshotRestorer {
                             object_file_path
                         );
                         let mut move_objects = vec![];
+                        let mut wrapped_objects = vec![];
                         let _result: Result<(), anyhow::Error> =
                             LiveObjectIter::new(&file_metadata, bytes.clone()).map(|obj_iter| {
                                 for object in obj_iter {
@@ -173,7 +243,15 @@ impl IndexerFormalSnapshotRestorer {
                                                 IndexedObject::from_object(0, obj, None);
                                             move_objects.push(indexed_object);
                                         }
-                                        LiveObject::Wrapped(_) => {}
+                                        LiveObject::Wrapped(object_key) => {
+                                            if is_delta {
+                                                wrapped_objects.push(IndexedDeletedObject {
+                                                    object_id: object_key.0,
+                                                    object_version: object_key.1.value(),
+                                                    checkpoint_sequence_number: 0,
+                                                });
+                                            }
+                                        }
                                     }
                                 }
                             });
@@ -181,7 +259,7 @@ impl IndexerFormalSnapshotRestorer {
                         let live_obj_cnt = move_objects.len();
                         let object_changes = TransactionObjectChangesToCommit {
                             changed_objects: move_objects.clone(),
-                            deleted_objects: vec![],
+                            deleted_objects: wrapped_objects.clone(),
                         };
                         info!(
                             "Start persisting {} objects to objects table from {}",
@@ -198,7 +276,7 @@ impl IndexerFormalSnapshotRestorer {
 
                         let objects_snapshot_changes = TransactionObjectChangesToCommit {
                             changed_objects: move_objects,
-                            deleted_objects: vec![],
+                            deleted_objects: wrapped_objects,
                         };
                         store_clone
                             .persist_objects_snapshot(vec![objects_snapshot_changes])
@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use axum::extract::ConnectInfo;
+use fastcrypto::encoding::{Base64, Encoding};
 use futures::FutureExt;
 use jsonrpsee::server::middleware::rpc::RpcServiceT;
 use jsonrpsee::types::{ErrorCode, ErrorObject, Id};
@@ -11,12 +12,19 @@ use std::time::SystemTime;
 use std::{net::SocketAddr, sync::Arc};
 use mys_core::traffic_controller::{parse_ip, policies::TrafficTally, TrafficController};
 use mys_json_rpc_api::TRANSACTION_EXECUTION_CLIENT_ERROR_CODE;
+use mys_types::base_types::MysAddress;
+use mys_types::transaction::{TransactionData, TransactionDataAPI};
 use mys_types::traffic_control::ClientIdSource;
 use mys_types::traffic_control::Weight;
 use tracing::error;
 
 const TOO_MANY_REQUESTS_MSG: &str = "Too many requests";
 
+/// Methods whose first parameter, `tx_bytes`, is a BCS serialized `TransactionData` that is
+/// executed on behalf of its sender. Dry runs and dev inspects are not included, as they do
+/// not require signatures and so do not prove who the sender is.
+const TRANSACTION_DATA_METHODS: &[&str] = &["mys_executeTransactionBlock"];
+
 #[derive(Clone)]
 pub struct TrafficControllerService<S> {
     inner: S,
@@ -46,11 +54,14 @@ where
         async move {
             if let Some(traffic_controller) = traffic_controller {
                 let client = req.extensions().get::<IpAddr>().cloned();
-                if let Err(response) = handle_traffic_req(&traffic_controller, &client).await {
+                let senders = transaction_senders(&req);
+                if let Err(response) =
+                    handle_traffic_req(&traffic_controller, &client, senders).await
+                {
                     response
                 } else {
                     let response = service.call(req).await;
-                    handle_traffic_resp(&traffic_controller, client, &response);
+                    handle_traffic_resp(&traffic_controller, client, senders, &response);
                     response
                 }
             } else {
@@ -61,11 +72,33 @@ where
     }
 }
 
+/// Returns the sender and gas payer of the transaction carried by the request, if any, for use
+/// by sender-keyed traffic control policies.
+fn transaction_senders(req: &jsonrpsee::types::Request<'_>) -> Option<(MysAddress, MysAddress)> {
+    if !TRANSACTION_DATA_METHODS.contains(&req.method_name()) {
+        return None;
+    }
+    let params: serde_json::Value = req.params().parse().ok()?;
+    let tx_bytes = match &params {
+        serde_json::Value::Array(params) => params.first(),
+        serde_json::Value::Object(params) => params.get("tx_bytes"),
+        _ => None,
+    }?
+    .as_str()?;
+    let tx_bytes = Base64::decode(tx_bytes).ok()?;
+    let data: TransactionData = bcs::from_bytes(&tx_bytes).ok()?;
+    Some((data.sender(), data.gas_owner()))
+}
+
 async fn handle_traffic_req(
     traffic_controller: &TrafficController,
     client: &Option<IpAddr>,
+    senders: Option<(MysAddress, MysAddress)>,
 ) -> Result<(), MethodResponse> {
-    if !traffic_controller.check(client, &None).await {
+    let (sender, gas_payer) = senders.unzip();
+    if !traffic_controller.check(client, &None).await
+        || !traffic_controller.check_sender(&sender, &gas_payer).await
+    {
         // Entity in blocklist
         let err_obj =
             ErrorObject::borrowed(ErrorCode::ServerIsBusy.code(), TOO_MANY_REQUESTS_MSG, None);
@@ -78,12 +111,18 @@ async fn handle_traffic_req(
 fn handle_traffic_resp(
     traffic_controller: &TrafficController,
     client: Option<IpAddr>,
+    senders: Option<(MysAddress, MysAddress)>,
     response: &MethodResponse,
 ) {
     let error = response.as_error_code().map(ErrorCode::from);
+    // Signatures are only known to be valid once the transaction has been executed, so
+    // don't tally senders for failed requests, which could be forged on their behalf.
+    let (sender, gas_payer) = senders.filter(|_| response.is_success()).unzip();
     traffic_controller.tally(TrafficTally {
         direct: client,
         through_fullnode: None,
+        sender,
+        gas_payer,
         error_info: error.map(|e| {
             let error_type = e.to_string();
             let error_weight = normalize(e);
@@ -0,0 +1,31 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Reference external signer serving the keys of a keystore file, for testing external signer
+//! keystores. A real signer keeps its keys in an HSM, a remote KMS or a hardware wallet.
+//!
+//! Usage: `mys-test-signer <KEYSTORE> [--socket <PATH>]`. Without `--socket`, a single session is
+//! served over stdin and stdout.
+
+use anyhow::bail;
+use std::path::PathBuf;
+use mys_keys::external_signer::serve;
+use mys_keys::keystore::Keystore;
+
+const USAGE: &str = "Usage: mys-test-signer <KEYSTORE> [--socket <PATH>]";
+
+fn main() -> Result<(), anyhow::Error> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some(keystore_path) = args.first() else {
+        bail!(USAGE);
+    };
+    let keystore = Keystore::from_path(&PathBuf::from(keystore_path))?;
+    match &args[1..] {
+        [] => serve(&keystore, std::io::stdin().lock(), std::io::stdout().lock()),
+        #[cfg(unix)]
+        [flag, path] if flag == "--socket" => {
+            mys_keys::external_signer::serve_unix_socket(&keystore, path.as_ref())
+        }
+        _ => bail!(USAGE),
+    }
+}
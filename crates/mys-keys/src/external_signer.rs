@@ -0,0 +1,493 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Keystore delegating signing to an external signer, such as an HSM, a remote KMS or a hardware
+//! wallet bridge, so that the key material never enters the process.
+//!
+//! The signer is either a program spawned for each request, talking over its stdin and stdout,
+//! or a process listening on a unix socket, in the spirit of ssh-agent. Requests and responses are
+//! JSON objects, one per line:
+//!
+//! ```text
+//! -> {"method":"list_keys"}
+//! <- {"keys":[{"public_key":"<Base64 flag || pk>","alias":"my-key"}]}
+//! -> {"method":"sign","public_key":"<Base64 flag || pk>","intent":"<Base64 intent>","message":"<Base64 bytes>"}
+//! <- {"signature":"<Base64 flag || sig || pk>"}
+//! <- {"error":"<reason>"}
+//! ```
+//!
+//! The message is the BCS encoding of the value to sign, e.g. the `TransactionData`, so that the
+//! signer can inspect what it signs. The signer hashes `intent || message` as
+//! `Signature::new_secure` does and signs the digest.
+//!
+//! The keystore only contacts the signer once a key is first needed, so a configuration naming
+//! a signer which is not running can still be loaded.
+
+use crate::keystore::{validate_alias, AccountKeystore, Alias};
+use crate::random_names::random_name;
+use anyhow::{anyhow, bail, Context};
+use fastcrypto::encoding::{Base64, Encoding};
+use fastcrypto::hash::HashFunction;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use shared_crypto::intent::{Intent, IntentMessage};
+use std::collections::{BTreeMap, HashSet};
+use once_cell::sync::OnceCell;
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use tracing::warn;
+use mys_types::base_types::MysAddress;
+use mys_types::crypto::{
+    DefaultHash, EncodeDecodeBase64, MysKeyPair, MysSignature, PublicKey, Signature,
+};
+
+/// How to reach an external signer.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExternalSignerConfig {
+    /// Program spawned for every request, which reads the request from its stdin and writes the
+    /// response to its stdout
+    Command {
+        program: PathBuf,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+    /// Signer listening on a unix socket
+    Socket { path: PathBuf },
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum SignerRequest {
+    ListKeys,
+    Sign {
+        /// Base64 encoded `flag || pk` of the key to sign with
+        public_key: String,
+        /// Base64 encoded intent of the message
+        intent: String,
+        /// Base64 encoded BCS bytes of the message to sign
+        message: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum SignerResponse {
+    Keys(Vec<SignerKey>),
+    Signature(Signature),
+    Error(String),
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SignerKey {
+    /// Base64 encoded `flag || pk`
+    pub public_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+}
+
+impl ExternalSignerConfig {
+    /// Sends a single request to the signer and waits for its response.
+    pub fn call(&self, request: &SignerRequest) -> Result<SignerResponse, anyhow::Error> {
+        let mut request = serde_json::to_string(request)?;
+        request.push('\n');
+
+        let mut response = String::new();
+        match self {
+            ExternalSignerConfig::Command { program, args } => {
+                let mut child = Command::new(program)
+                    .args(args)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::inherit())
+                    .spawn()
+                    .with_context(|| {
+                        format!("Cannot start external signer {}", program.display())
+                    })?;
+                let mut stdin = child.stdin.take().expect("stdin is piped");
+                stdin.write_all(request.as_bytes())?;
+                // Closing stdin ends the session
+                drop(stdin);
+                let stdout = child.stdout.take().expect("stdout is piped");
+                BufReader::new(stdout).read_line(&mut response)?;
+                child.wait()?;
+            }
+            #[cfg(unix)]
+            ExternalSignerConfig::Socket { path } => {
+                let mut stream =
+                    std::os::unix::net::UnixStream::connect(path).with_context(|| {
+                        format!("Cannot connect to external signer at {}", path.display())
+                    })?;
+                stream.write_all(request.as_bytes())?;
+                stream.shutdown(std::net::Shutdown::Write)?;
+                BufReader::new(stream).read_line(&mut response)?;
+            }
+            #[cfg(not(unix))]
+            ExternalSignerConfig::Socket { .. } => {
+                bail!("Socket based external signers are only supported on unix")
+            }
+        }
+
+        if response.trim().is_empty() {
+            bail!("External signer closed the connection without responding");
+        }
+        match serde_json::from_str(&response)
+            .with_context(|| format!("Invalid response from external signer: {response}"))?
+        {
+            SignerResponse::Error(e) => bail!("External signer error: {e}"),
+            response => Ok(response),
+        }
+    }
+}
+
+/// Keystore whose keys are held by an external signer. Only public keys and aliases are known
+/// to the keystore, so keys can neither be added nor exported.
+///
+/// The keys are fetched from the signer the first time they are needed. Methods which cannot
+/// fail report no keys while the signer is unreachable, the others return the error.
+pub struct ExternalKeystore {
+    signer: ExternalSignerConfig,
+    keys: OnceCell<ExternalKeys>,
+}
+
+/// The keys held by the signer, with their aliases.
+#[derive(Default)]
+struct ExternalKeys {
+    keys: BTreeMap<MysAddress, PublicKey>,
+    aliases: BTreeMap<MysAddress, Alias>,
+}
+
+impl ExternalKeys {
+    fn fetch(signer: &ExternalSignerConfig) -> Result<Self, anyhow::Error> {
+        let SignerResponse::Keys(signer_keys) = signer.call(&SignerRequest::ListKeys)? else {
+            bail!("Unexpected response from external signer to list_keys");
+        };
+
+        let mut keys = Self::default();
+        let mut alias_names = HashSet::new();
+        for key in signer_keys {
+            let public_key = PublicKey::decode_base64(&key.public_key).map_err(|e| {
+                anyhow!(
+                    "Invalid public key {} from external signer: {e}",
+                    key.public_key
+                )
+            })?;
+            let address = MysAddress::from(&public_key);
+            // Aliases the signer does not provide, or which clash, are replaced by random ones
+            let alias = match key.alias.map(|a| validate_alias(&a)) {
+                Some(Ok(alias)) if !alias_names.contains(&alias) => alias,
+                _ => random_name(&alias_names),
+            };
+            alias_names.insert(alias.clone());
+            keys.aliases.insert(
+                address,
+                Alias {
+                    alias,
+                    public_key_base64: key.public_key,
+                },
+            );
+            keys.keys.insert(address, public_key);
+        }
+        Ok(keys)
+    }
+}
+
+impl Serialize for ExternalKeystore {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.signer.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ExternalKeystore {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(ExternalKeystore::new(ExternalSignerConfig::deserialize(
+            deserializer,
+        )?))
+    }
+}
+
+impl ExternalKeystore {
+    /// Creates a keystore for the keys of `signer`, which is only contacted once they are needed.
+    pub fn new(signer: ExternalSignerConfig) -> Self {
+        Self {
+            signer,
+            keys: OnceCell::new(),
+        }
+    }
+
+    pub fn signer(&self) -> &ExternalSignerConfig {
+        &self.signer
+    }
+
+    /// The keys of the signer, fetched on first use.
+    fn loaded(&self) -> Result<&ExternalKeys, anyhow::Error> {
+        self.keys
+            .get_or_try_init(|| ExternalKeys::fetch(&self.signer))
+    }
+
+    fn loaded_mut(&mut self) -> Result<&mut ExternalKeys, anyhow::Error> {
+        self.loaded()?;
+        Ok(self.keys.get_mut().expect("keys were just loaded"))
+    }
+
+    fn request_signature(
+        &self,
+        address: &MysAddress,
+        intent: &Intent,
+        message: &[u8],
+    ) -> Result<Signature, anyhow::Error> {
+        let public_key = self
+            .loaded()?
+            .keys
+            .get(address)
+            .ok_or_else(|| anyhow!("Cannot find key for address: [{address}]"))?;
+        let response = self.signer.call(&SignerRequest::Sign {
+            public_key: public_key.encode_base64(),
+            intent: Base64::encode(intent.to_bytes()),
+            message: Base64::encode(message),
+        })?;
+        let SignerResponse::Signature(signature) = response else {
+            bail!("Unexpected response from external signer to sign");
+        };
+        if signature.scheme() != public_key.scheme()
+            || signature.public_key_bytes() != public_key.as_ref()
+        {
+            bail!("External signer signed with a different key than requested for [{address}]");
+        }
+        Ok(signature)
+    }
+}
+
+impl AccountKeystore for ExternalKeystore {
+    /// External signers are sent the message rather than its digest, so that they can check what
+    /// they sign. Use `sign_secure` instead.
+    fn sign_hashed(
+        &self,
+        _address: &MysAddress,
+        _msg: &[u8],
+    ) -> Result<Signature, signature::Error> {
+        Err(signature::Error::from_source(
+            "External signers only sign intent messages, not digests",
+        ))
+    }
+    fn sign_secure<T>(
+        &self,
+        address: &MysAddress,
+        msg: &T,
+        intent: Intent,
+    ) -> Result<Signature, signature::Error>
+    where
+        T: Serialize,
+    {
+        let message =
+            bcs::to_bytes(msg).map_err(|e| signature::Error::from_source(e.to_string()))?;
+        let signature = self
+            .request_signature(address, &intent, &message)
+            .map_err(|e| signature::Error::from_source(e.to_string()))?;
+        let intent_msg = IntentMessage::new(intent, msg);
+        let scheme = signature.scheme();
+        signature
+            .verify_secure(&intent_msg, *address, scheme)
+            .map_err(|e| signature::Error::from_source(e.to_string()))?;
+        Ok(signature)
+    }
+
+    fn add_key(
+        &mut self,
+        _alias: Option<String>,
+        _keypair: MysKeyPair,
+    ) -> Result<(), anyhow::Error> {
+        bail!("Keys cannot be added to an external signer keystore, add them to the signer instead")
+    }
+
+    /// Return an array of `Alias`, consisting of every alias and its corresponding public key.
+    fn aliases(&self) -> Vec<&Alias> {
+        self.loaded()
+            .map(|keys| keys.aliases.values().collect())
+            .unwrap_or_default()
+    }
+
+    fn addresses_with_alias(&self) -> Vec<(&MysAddress, &Alias)> {
+        self.loaded()
+            .map(|keys| keys.aliases.iter().collect())
+            .unwrap_or_default()
+    }
+
+    /// Return an array of `Alias`, consisting of every alias and its corresponding public key.
+    fn aliases_mut(&mut self) -> Vec<&mut Alias> {
+        self.loaded_mut()
+            .map(|keys| keys.aliases.values_mut().collect())
+            .unwrap_or_default()
+    }
+
+    fn keys(&self) -> Vec<PublicKey> {
+        self.loaded()
+            .map(|keys| keys.keys.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// This function returns an error if the provided alias already exists. If the alias
+    /// has not already been used, then it returns the alias.
+    /// If no alias has been passed, it will generate a new alias.
+    fn create_alias(&self, alias: Option<String>) -> Result<String, anyhow::Error> {
+        match alias {
+            Some(a) if self.alias_exists(&a) => {
+                bail!("Alias {a} already exists. Please choose another alias.")
+            }
+            Some(a) => validate_alias(&a),
+            None => Ok(random_name(
+                &self
+                    .alias_names()
+                    .into_iter()
+                    .map(|x| x.to_string())
+                    .collect::<HashSet<_>>(),
+            )),
+        }
+    }
+
+    /// Get the address by its alias
+    fn get_address_by_alias(&self, alias: String) -> Result<&MysAddress, anyhow::Error> {
+        self.loaded()?
+            .aliases
+            .iter()
+            .find(|x| x.1.alias == alias)
+            .ok_or_else(|| anyhow!("Cannot resolve alias {alias} to an address"))
+            .map(|x| x.0)
+    }
+
+    /// Get the alias if it exists, or return an error if it does not exist.
+    fn get_alias_by_address(&self, address: &MysAddress) -> Result<String, anyhow::Error> {
+        match self.loaded()?.aliases.get(address) {
+            Some(alias) => Ok(alias.alias.clone()),
+            None => bail!("Cannot find alias for address {address}"),
+        }
+    }
+
+    fn get_key(&self, address: &MysAddress) -> Result<&MysKeyPair, anyhow::Error> {
+        match self.loaded()?.keys.get(address) {
+            Some(_) => Err(anyhow!(
+                "The key for address [{address}] is held by an external signer and cannot be exported"
+            )),
+            None => Err(anyhow!("Cannot find key for address: [{address}]")),
+        }
+    }
+
+    /// Updates an old alias to the new alias. Aliases are owned by the signer, so the change
+    /// only lasts for the lifetime of the keystore.
+    fn update_alias(
+        &mut self,
+        old_alias: &str,
+        new_alias: Option<&str>,
+    ) -> Result<String, anyhow::Error> {
+        self.loaded()?;
+        self.update_alias_value(old_alias, new_alias)
+    }
+}
+
+/// Serves requests for the keys of `keystore` over a single connection until it is closed. This
+/// is the signer side of the protocol, as implemented by the reference `mys-test-signer`.
+pub fn serve<K: AccountKeystore>(
+    keystore: &K,
+    reader: impl BufRead,
+    mut writer: impl Write,
+) -> Result<(), anyhow::Error> {
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str(&line) {
+            Ok(request) => handle_request(keystore, request),
+            Err(e) => SignerResponse::Error(format!("Invalid request: {e}")),
+        };
+        serde_json::to_writer(&mut writer, &response)?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+/// Serves connections on a unix socket at `path`, one at a time. The socket is only accessible
+/// to the current user. A socket left behind by a signer which is no longer running is replaced.
+#[cfg(unix)]
+pub fn serve_unix_socket<K: AccountKeystore>(
+    keystore: &K,
+    path: &std::path::Path,
+) -> Result<(), anyhow::Error> {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
+        if !metadata.file_type().is_socket() {
+            bail!("{} exists and is not a socket", path.display());
+        }
+        if UnixStream::connect(path).is_ok() {
+            bail!("Another signer is already listening on {}", path.display());
+        }
+        std::fs::remove_file(path)
+            .with_context(|| format!("Cannot remove stale socket {}", path.display()))?;
+    }
+    let listener =
+        UnixListener::bind(path).with_context(|| format!("Cannot listen on {}", path.display()))?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+        .with_context(|| format!("Cannot restrict access to {}", path.display()))?;
+
+    for stream in listener.incoming() {
+        // A misbehaving client must not take the signer down for the others
+        let result = stream
+            .map_err(anyhow::Error::from)
+            .and_then(|stream| serve(keystore, BufReader::new(stream.try_clone()?), stream));
+        if let Err(e) = result {
+            warn!("Error serving external signer client: {e:#}");
+        }
+    }
+    Ok(())
+}
+
+fn handle_request<K: AccountKeystore>(keystore: &K, request: SignerRequest) -> SignerResponse {
+    match request {
+        SignerRequest::ListKeys => SignerResponse::Keys(
+            keystore
+                .keys()
+                .iter()
+                .map(|public_key| SignerKey {
+                    public_key: public_key.encode_base64(),
+                    alias: keystore.get_alias_by_address(&public_key.into()).ok(),
+                })
+                .collect(),
+        ),
+        SignerRequest::Sign {
+            public_key,
+            intent,
+            message,
+        } => {
+            let signature = PublicKey::decode_base64(&public_key)
+                .map_err(|e| format!("Invalid public key: {e}"))
+                .and_then(|public_key| {
+                    let intent = Base64::decode(&intent)
+                        .map_err(|e| e.to_string())
+                        .and_then(|intent| Intent::from_bytes(&intent).map_err(|e| e.to_string()))
+                        .map_err(|e| format!("Invalid intent: {e}"))?;
+                    let message =
+                        Base64::decode(&message).map_err(|e| format!("Invalid message: {e}"))?;
+                    // The BCS encoding of the intent message is the intent followed by the message
+                    let mut hasher = DefaultHash::default();
+                    hasher.update(intent.to_bytes());
+                    hasher.update(&message);
+                    keystore
+                        .sign_hashed(&(&public_key).into(), &hasher.finalize().digest)
+                        .map_err(|e| e.to_string())
+                });
+            match signature {
+                Ok(signature) => SignerResponse::Signature(signature),
+                Err(e) => SignerResponse::Error(e),
+            }
+        }
+    }
+}
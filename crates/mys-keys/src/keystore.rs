@@ -1,12 +1,16 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::external_signer::{ExternalKeystore, ExternalSignerConfig};
 use crate::key_derive::{derive_key_pair_from_path, generate_new_key};
 use crate::random_names::{random_name, random_names};
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
 use anyhow::{anyhow, bail, ensure, Context};
 use bip32::DerivationPath;
 use bip39::{Language, Mnemonic, Seed};
-use rand::{rngs::StdRng, SeedableRng};
+use fastcrypto::encoding::{Base64, Encoding};
+use rand::{rngs::StdRng, RngCore, SeedableRng};
 use regex::Regex;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use shared_crypto::intent::{Intent, IntentMessage};
@@ -17,17 +21,26 @@ use std::fs;
 use std::fs::File;
 use std::io::BufReader;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use mys_types::base_types::MysAddress;
 use mys_types::crypto::get_key_pair_from_rng;
 use mys_types::crypto::{
     enum_dispatch, EncodeDecodeBase64, PublicKey, Signature, SignatureScheme, MysKeyPair,
 };
+use zeroize::Zeroizing;
 
-#[derive(Serialize, Deserialize)]
+/// Environment variable holding the passphrase of an encrypted keystore. If it is not set, the
+/// passphrase is prompted for.
+pub const MYS_KEYSTORE_PASSPHRASE_ENV: &str = "MYS_KEYSTORE_PASSPHRASE";
+
+#[derive(Serialize)]
 #[enum_dispatch(AccountKeystore)]
 pub enum Keystore {
     File(FileBasedKeystore),
     InMem(InMemKeystore),
+    Encrypted(EncryptedKeystore),
+    External(ExternalKeystore),
 }
 #[enum_dispatch]
 pub trait AccountKeystore: Send + Sync {
@@ -128,6 +141,54 @@ pub trait AccountKeystore: Send + Sync {
     }
 }
 
+impl Keystore {
+    /// Opens the keystore file at `path`, which may hold either plaintext or encrypted keys.
+    pub fn from_path(path: &PathBuf) -> Result<Self, anyhow::Error> {
+        if EncryptedKeystore::is_encrypted_keystore(path) {
+            Ok(Keystore::Encrypted(EncryptedKeystore::new(path)?))
+        } else {
+            Ok(Keystore::File(FileBasedKeystore::new(path)?))
+        }
+    }
+
+    /// Returns a copy of the key of `address`, decrypting it if the keystore is encrypted.
+    pub fn export_key(&self, address: &MysAddress) -> Result<MysKeyPair, anyhow::Error> {
+        match self {
+            Keystore::Encrypted(encrypted) => encrypted.export_key(address),
+            _ => Ok(self.get_key(address)?.copy()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Keystore {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use serde::de::Error;
+
+        #[derive(Deserialize)]
+        enum KeystoreConfig {
+            File(PathBuf),
+            InMem(InMemKeystore),
+            Encrypted(PathBuf),
+            External(ExternalSignerConfig),
+        }
+
+        match KeystoreConfig::deserialize(deserializer)? {
+            // A file keystore which was encrypted in place keeps working with existing configs
+            KeystoreConfig::File(path) => Keystore::from_path(&path).map_err(D::Error::custom),
+            KeystoreConfig::InMem(keystore) => Ok(Keystore::InMem(keystore)),
+            KeystoreConfig::Encrypted(path) => EncryptedKeystore::new(&path)
+                .map(Keystore::Encrypted)
+                .map_err(D::Error::custom),
+            KeystoreConfig::External(signer) => {
+                Ok(Keystore::External(ExternalKeystore::new(signer)))
+            }
+        }
+    }
+}
+
 impl Display for Keystore {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let mut writer = String::new();
@@ -141,6 +202,16 @@ impl Display for Keystore {
                 writeln!(writer, "Keystore Type : InMem")?;
                 write!(f, "{}", writer)
             }
+            Keystore::Encrypted(encrypted) => {
+                writeln!(writer, "Keystore Type : Encrypted")?;
+                write!(writer, "Keystore Path : {:?}", encrypted.path)?;
+                write!(f, "{}", writer)
+            }
+            Keystore::External(external) => {
+                writeln!(writer, "Keystore Type : External")?;
+                write!(writer, "Keystore Signer : {:?}", external.signer())?;
+                write!(f, "{}", writer)
+            }
         }
     }
 }
@@ -593,7 +664,619 @@ impl InMemKeystore {
     }
 }
 
-fn validate_alias(alias: &str) -> Result<String, anyhow::Error> {
+const ENCRYPTED_KEYSTORE_VERSION: u8 = 1;
+const CIPHER_KEY_LENGTH: usize = 32;
+const NONCE_LENGTH: usize = 12;
+const SALT_LENGTH: usize = 32;
+/// Sealed alongside the keys so that a wrong passphrase is detected even if the keystore is empty
+const PASSPHRASE_CHECK: &[u8] = b"mys encrypted keystore";
+/// Largest scrypt parameters accepted from a keystore file, so that a tampered file cannot make
+/// unlocking it use an unbounded amount of memory or time. The defaults use 128 MiB.
+const MAX_SCRYPT_LOG_N: u8 = 20;
+const MAX_SCRYPT_R: u32 = 16;
+const MAX_SCRYPT_P: u32 = 16;
+/// How long the cipher key is kept after the passphrase was entered
+const UNLOCK_DURATION: Duration = Duration::from_secs(300);
+
+/// Parameters of the scrypt function deriving the cipher key from the passphrase.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScryptParams {
+    pub log_n: u8,
+    pub r: u32,
+    pub p: u32,
+}
+
+impl Default for ScryptParams {
+    fn default() -> Self {
+        Self {
+            log_n: 17,
+            r: 8,
+            p: 1,
+        }
+    }
+}
+
+impl ScryptParams {
+    fn validate(&self) -> Result<(), anyhow::Error> {
+        ensure!(
+            self.log_n <= MAX_SCRYPT_LOG_N && self.r <= MAX_SCRYPT_R && self.p <= MAX_SCRYPT_P,
+            "The scrypt parameters of the keystore exceed log_n {MAX_SCRYPT_LOG_N}, r {MAX_SCRYPT_R} or p {MAX_SCRYPT_P}: {self:?}"
+        );
+        Ok(())
+    }
+
+    fn derive_key(
+        &self,
+        passphrase: &str,
+        salt: &[u8],
+    ) -> Result<Zeroizing<[u8; CIPHER_KEY_LENGTH]>, anyhow::Error> {
+        let params = scrypt::Params::new(self.log_n, self.r, self.p)
+            .map_err(|e| anyhow!("Invalid scrypt parameters in keystore: {e}"))?;
+        let mut key = Zeroizing::new([0u8; CIPHER_KEY_LENGTH]);
+        scrypt::scrypt(passphrase.as_bytes(), salt, &params, &mut key[..])
+            .map_err(|e| anyhow!("Cannot derive the keystore key: {e}"))?;
+        Ok(key)
+    }
+}
+
+/// Data encrypted with AES-256-GCM, both fields encoded as Base64.
+#[derive(Serialize, Deserialize, Clone)]
+struct SealedBox {
+    nonce: String,
+    ciphertext: String,
+}
+
+impl SealedBox {
+    fn seal(key: &[u8; CIPHER_KEY_LENGTH], msg: &[u8], aad: &[u8]) -> Result<Self, anyhow::Error> {
+        let mut nonce = [0u8; NONCE_LENGTH];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let ciphertext = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key))
+            .encrypt(Nonce::from_slice(&nonce), Payload { msg, aad })
+            .map_err(|_| anyhow!("Cannot encrypt keystore entry"))?;
+        Ok(Self {
+            nonce: Base64::encode(nonce),
+            ciphertext: Base64::encode(ciphertext),
+        })
+    }
+
+    fn open(
+        &self,
+        key: &[u8; CIPHER_KEY_LENGTH],
+        aad: &[u8],
+    ) -> Result<Zeroizing<Vec<u8>>, anyhow::Error> {
+        let nonce = Base64::decode(&self.nonce)
+            .map_err(|e| anyhow!("Invalid nonce in encrypted keystore: {e}"))?;
+        ensure!(
+            nonce.len() == NONCE_LENGTH,
+            "Invalid nonce length in encrypted keystore"
+        );
+        let ciphertext = Base64::decode(&self.ciphertext)
+            .map_err(|e| anyhow!("Invalid ciphertext in encrypted keystore: {e}"))?;
+        Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key))
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad,
+                },
+            )
+            .map(Zeroizing::new)
+            .map_err(|_| anyhow!("Cannot decrypt keystore entry, the passphrase is incorrect"))
+    }
+}
+
+/// A private key sealed under the keystore key. The public key is authenticated along with it,
+/// so that a sealed key cannot be swapped for another one.
+#[derive(Serialize, Deserialize, Clone)]
+struct SealedKey {
+    public_key_base64: String,
+    #[serde(flatten)]
+    sealed: SealedBox,
+}
+
+impl SealedKey {
+    fn seal(key: &[u8; CIPHER_KEY_LENGTH], keypair: &MysKeyPair) -> Result<Self, anyhow::Error> {
+        let public_key_base64 = keypair.public().encode_base64();
+        let sealed = SealedBox::seal(
+            key,
+            &Zeroizing::new(keypair.to_bytes()),
+            public_key_base64.as_bytes(),
+        )?;
+        Ok(Self {
+            public_key_base64,
+            sealed,
+        })
+    }
+
+    fn open(&self, key: &[u8; CIPHER_KEY_LENGTH]) -> Result<MysKeyPair, anyhow::Error> {
+        let bytes = self.sealed.open(key, self.public_key_base64.as_bytes())?;
+        let keypair = MysKeyPair::from_bytes(&bytes)
+            .map_err(|e| anyhow!("Invalid key in encrypted keystore: {e}"))?;
+        ensure!(
+            keypair.public().encode_base64() == self.public_key_base64,
+            "Key in encrypted keystore does not match its public key"
+        );
+        Ok(keypair)
+    }
+}
+
+/// On disk format of an encrypted keystore.
+#[derive(Serialize, Deserialize)]
+struct EncryptedKeystoreFile {
+    version: u8,
+    scrypt: ScryptParams,
+    salt: String,
+    check: SealedBox,
+    keys: Vec<SealedKey>,
+}
+
+/// Keystore file whose private keys are encrypted with AES-256-GCM under a key derived from a
+/// passphrase with scrypt. Public keys and aliases are stored in the clear, so listing keys does
+/// not need the passphrase. Private keys are only decrypted for the duration of a signature, and
+/// `get_key` cannot hand them out; `Keystore::export_key` decrypts a copy instead.
+pub struct EncryptedKeystore {
+    keys: BTreeMap<MysAddress, (PublicKey, SealedKey)>,
+    aliases: BTreeMap<MysAddress, Alias>,
+    scrypt: ScryptParams,
+    salt: Vec<u8>,
+    /// Not set until a passphrase was chosen for a new keystore
+    check: Option<SealedBox>,
+    path: Option<PathBuf>,
+    /// Cipher key derived from the passphrase and when it was derived. It is kept for
+    /// `UNLOCK_DURATION`, so that signing several transactions in a row asks for the passphrase
+    /// once.
+    cipher_key: Mutex<Option<(Zeroizing<[u8; CIPHER_KEY_LENGTH]>, Instant)>>,
+}
+
+impl Serialize for EncryptedKeystore {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(
+            self.path
+                .as_ref()
+                .unwrap_or(&PathBuf::default())
+                .to_str()
+                .unwrap_or(""),
+        )
+    }
+}
+
+impl<'de> Deserialize<'de> for EncryptedKeystore {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use serde::de::Error;
+        EncryptedKeystore::new(&PathBuf::from(String::deserialize(deserializer)?))
+            .map_err(D::Error::custom)
+    }
+}
+
+impl AccountKeystore for EncryptedKeystore {
+    fn sign_hashed(&self, address: &MysAddress, msg: &[u8]) -> Result<Signature, signature::Error> {
+        self.with_key(address, |keypair| Signature::new_hashed(msg, keypair))
+            .map_err(|e| signature::Error::from_source(e.to_string()))
+    }
+    fn sign_secure<T>(
+        &self,
+        address: &MysAddress,
+        msg: &T,
+        intent: Intent,
+    ) -> Result<Signature, signature::Error>
+    where
+        T: Serialize,
+    {
+        self.with_key(address, |keypair| {
+            Signature::new_secure(&IntentMessage::new(intent, msg), keypair)
+        })
+        .map_err(|e| signature::Error::from_source(e.to_string()))
+    }
+
+    fn add_key(&mut self, alias: Option<String>, keypair: MysKeyPair) -> Result<(), anyhow::Error> {
+        let address: MysAddress = (&keypair.public()).into();
+        let alias = self.create_alias(alias)?;
+        if self.check.is_none() {
+            let passphrase = read_passphrase(true)?;
+            self.set_passphrase(&passphrase)?;
+        }
+        let sealed = SealedKey::seal(&self.unlock()?, &keypair)?;
+        self.aliases.insert(
+            address,
+            Alias {
+                alias,
+                public_key_base64: sealed.public_key_base64.clone(),
+            },
+        );
+        self.keys.insert(address, (keypair.public(), sealed));
+        self.save()?;
+        Ok(())
+    }
+
+    /// Return an array of `Alias`, consisting of every alias and its corresponding public key.
+    fn aliases(&self) -> Vec<&Alias> {
+        self.aliases.values().collect()
+    }
+
+    fn addresses_with_alias(&self) -> Vec<(&MysAddress, &Alias)> {
+        self.aliases.iter().collect::<Vec<_>>()
+    }
+
+    /// Return an array of `Alias`, consisting of every alias and its corresponding public key.
+    fn aliases_mut(&mut self) -> Vec<&mut Alias> {
+        self.aliases.values_mut().collect()
+    }
+
+    fn keys(&self) -> Vec<PublicKey> {
+        self.keys.values().map(|(pk, _)| pk.clone()).collect()
+    }
+
+    /// This function returns an error if the provided alias already exists. If the alias
+    /// has not already been used, then it returns the alias.
+    /// If no alias has been passed, it will generate a new alias.
+    fn create_alias(&self, alias: Option<String>) -> Result<String, anyhow::Error> {
+        match alias {
+            Some(a) if self.alias_exists(&a) => {
+                bail!("Alias {a} already exists. Please choose another alias.")
+            }
+            Some(a) => validate_alias(&a),
+            None => Ok(random_name(
+                &self
+                    .alias_names()
+                    .into_iter()
+                    .map(|x| x.to_string())
+                    .collect::<HashSet<_>>(),
+            )),
+        }
+    }
+
+    /// Get the address by its alias
+    fn get_address_by_alias(&self, alias: String) -> Result<&MysAddress, anyhow::Error> {
+        self.addresses_with_alias()
+            .iter()
+            .find(|x| x.1.alias == alias)
+            .ok_or_else(|| anyhow!("Cannot resolve alias {alias} to an address"))
+            .map(|x| x.0)
+    }
+
+    /// Get the alias if it exists, or return an error if it does not exist.
+    fn get_alias_by_address(&self, address: &MysAddress) -> Result<String, anyhow::Error> {
+        match self.aliases.get(address) {
+            Some(alias) => Ok(alias.alias.clone()),
+            None => bail!("Cannot find alias for address {address}"),
+        }
+    }
+
+    /// Decrypted keys are not kept in memory, so they cannot be borrowed from the keystore. Use
+    /// `Keystore::export_key` to decrypt a copy.
+    fn get_key(&self, address: &MysAddress) -> Result<&MysKeyPair, anyhow::Error> {
+        match self.keys.get(address) {
+            Some(_) => Err(anyhow!(
+                "The key for address [{address}] is encrypted and has to be exported to be used outside of the keystore"
+            )),
+            None => Err(anyhow!("Cannot find key for address: [{address}]")),
+        }
+    }
+
+    /// Updates an old alias to the new alias and saves it to the alias file.
+    /// If the new_alias is None, it will generate a new random alias.
+    fn update_alias(
+        &mut self,
+        old_alias: &str,
+        new_alias: Option<&str>,
+    ) -> Result<String, anyhow::Error> {
+        let new_alias_name = self.update_alias_value(old_alias, new_alias)?;
+        self.save_aliases()?;
+        Ok(new_alias_name)
+    }
+}
+
+impl EncryptedKeystore {
+    /// Opens the encrypted keystore at `path`. If the file does not exist, a passphrase is chosen
+    /// when the first key is added.
+    pub fn new(path: &PathBuf) -> Result<Self, anyhow::Error> {
+        let mut keystore = Self::empty(path, ScryptParams::default());
+        if !path.exists() {
+            return Ok(keystore);
+        }
+
+        let reader = BufReader::new(
+            File::open(path)
+                .with_context(|| format!("Cannot open the keystore file: {}", path.display()))?,
+        );
+        let file: EncryptedKeystoreFile = serde_json::from_reader(reader).with_context(|| {
+            format!(
+                "Cannot deserialize the encrypted keystore file: {}",
+                path.display()
+            )
+        })?;
+        ensure!(
+            file.version == ENCRYPTED_KEYSTORE_VERSION,
+            "Unsupported encrypted keystore version {} in {}",
+            file.version,
+            path.display()
+        );
+        file.scrypt
+            .validate()
+            .with_context(|| format!("Invalid keystore file: {}", path.display()))?;
+        keystore.scrypt = file.scrypt;
+        keystore.salt = Base64::decode(&file.salt)
+            .map_err(|e| anyhow!("Invalid salt in keystore file: {}. {}", path.display(), e))?;
+        keystore.check = Some(file.check);
+        for sealed in file.keys {
+            let public_key = PublicKey::decode_base64(&sealed.public_key_base64)
+                .map_err(|e| anyhow!("Invalid keystore file: {}. {}", path.display(), e))?;
+            let address = MysAddress::from(&public_key);
+            keystore.keys.insert(address, (public_key, sealed));
+        }
+
+        // Aliases only hold public keys and are kept in the clear, like for `FileBasedKeystore`
+        let mut aliases_path = path.clone();
+        aliases_path.set_extension("aliases");
+        if aliases_path.exists() {
+            let reader = BufReader::new(File::open(&aliases_path).with_context(|| {
+                format!(
+                    "Cannot open aliases file in keystore: {}",
+                    aliases_path.display()
+                )
+            })?);
+            let aliases: Vec<Alias> = serde_json::from_reader(reader).with_context(|| {
+                format!(
+                    "Cannot deserialize aliases file in keystore: {}",
+                    aliases_path.display(),
+                )
+            })?;
+            keystore.aliases = aliases
+                .into_iter()
+                .map(|alias| {
+                    let key = PublicKey::decode_base64(&alias.public_key_base64);
+                    key.map(|k| (Into::<MysAddress>::into(&k), alias))
+                })
+                .collect::<Result<BTreeMap<_, _>, _>>()
+                .map_err(|e| {
+                    anyhow!(
+                        "Invalid aliases file in keystore: {}. {}",
+                        aliases_path.display(),
+                        e
+                    )
+                })?;
+        } else if !keystore.keys.is_empty() {
+            let names = random_names(HashSet::new(), keystore.keys.len());
+            keystore.aliases = keystore
+                .keys
+                .iter()
+                .zip(names)
+                .map(|((address, (_, sealed)), alias)| {
+                    let public_key_base64 = sealed.public_key_base64.clone();
+                    (
+                        *address,
+                        Alias {
+                            alias,
+                            public_key_base64,
+                        },
+                    )
+                })
+                .collect();
+            keystore.save_aliases()?;
+        }
+        Ok(keystore)
+    }
+
+    /// Creates an empty encrypted keystore at `path`, protected by `passphrase`. The keystore
+    /// starts out unlocked.
+    pub fn create(
+        path: &Path,
+        passphrase: &str,
+        scrypt: ScryptParams,
+    ) -> Result<Self, anyhow::Error> {
+        scrypt.validate()?;
+        let mut keystore = Self::empty(path, scrypt);
+        keystore.set_passphrase(passphrase)?;
+        keystore.save()?;
+        Ok(keystore)
+    }
+
+    /// Encrypts all keys of a plaintext keystore, keeping their aliases. The encrypted keystore
+    /// replaces the plaintext one on disk.
+    pub fn from_file_keystore(
+        keystore: &FileBasedKeystore,
+        passphrase: &str,
+        scrypt: ScryptParams,
+    ) -> Result<Self, anyhow::Error> {
+        let path = keystore
+            .path
+            .as_ref()
+            .ok_or_else(|| anyhow!("The keystore to encrypt has no path"))?;
+        scrypt.validate()?;
+        let mut encrypted = Self::empty(path, scrypt);
+        encrypted.set_passphrase(passphrase)?;
+        let cipher_key = encrypted.unlock()?;
+        for (address, keypair) in &keystore.keys {
+            encrypted.keys.insert(
+                *address,
+                (keypair.public(), SealedKey::seal(&cipher_key, keypair)?),
+            );
+        }
+        encrypted.aliases = keystore.aliases.clone();
+        encrypted.save()?;
+        Ok(encrypted)
+    }
+
+    fn empty(path: &Path, scrypt: ScryptParams) -> Self {
+        let mut salt = vec![0u8; SALT_LENGTH];
+        rand::thread_rng().fill_bytes(&mut salt);
+        Self {
+            keys: BTreeMap::new(),
+            aliases: BTreeMap::new(),
+            scrypt,
+            salt,
+            check: None,
+            path: Some(path.to_path_buf()),
+            cipher_key: Mutex::new(None),
+        }
+    }
+
+    /// Whether the file at `path` is an encrypted keystore, as opposed to a plaintext one.
+    pub fn is_encrypted_keystore(path: &Path) -> bool {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+            .is_some_and(|value| value.is_object())
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Unlocks the keystore with the given passphrase for `UNLOCK_DURATION`, instead of reading it
+    /// from the environment or prompting for it on first use.
+    pub fn unlock_with_passphrase(&self, passphrase: &str) -> Result<(), anyhow::Error> {
+        let check = self
+            .check
+            .as_ref()
+            .ok_or_else(|| anyhow!("No passphrase has been set for the keystore yet"))?;
+        let cipher_key = self.scrypt.derive_key(passphrase, &self.salt)?;
+        check
+            .open(&cipher_key, &[])
+            .map_err(|_| anyhow!("Incorrect keystore passphrase"))?;
+        *self.cipher_key.lock().unwrap() = Some((cipher_key, Instant::now()));
+        Ok(())
+    }
+
+    /// Forgets the cipher key, so that the passphrase is needed again to use the keys.
+    pub fn lock(&self) {
+        *self.cipher_key.lock().unwrap() = None;
+    }
+
+    fn set_passphrase(&mut self, passphrase: &str) -> Result<(), anyhow::Error> {
+        ensure!(
+            !passphrase.is_empty(),
+            "The keystore passphrase cannot be empty"
+        );
+        let cipher_key = self.scrypt.derive_key(passphrase, &self.salt)?;
+        self.check = Some(SealedBox::seal(&cipher_key, PASSPHRASE_CHECK, &[])?);
+        *self.cipher_key.lock().unwrap() = Some((cipher_key, Instant::now()));
+        Ok(())
+    }
+
+    /// Returns the cipher key, reading the passphrase if the keystore is locked or was unlocked
+    /// more than `UNLOCK_DURATION` ago.
+    fn unlock(&self) -> Result<Zeroizing<[u8; CIPHER_KEY_LENGTH]>, anyhow::Error> {
+        {
+            let mut cipher_key = self.cipher_key.lock().unwrap();
+            match cipher_key.as_ref() {
+                Some((key, unlocked_at)) if unlocked_at.elapsed() < UNLOCK_DURATION => {
+                    return Ok(key.clone());
+                }
+                Some(_) => *cipher_key = None,
+                None => (),
+            }
+        }
+        let passphrase = read_passphrase(false)?;
+        self.unlock_with_passphrase(&passphrase)?;
+        self.unlock()
+    }
+
+    /// Decrypts the key of `address` for the duration of `f`. The decrypted key is dropped, which
+    /// zeroizes it, as soon as `f` returns.
+    fn with_key<R>(
+        &self,
+        address: &MysAddress,
+        f: impl FnOnce(&MysKeyPair) -> R,
+    ) -> Result<R, anyhow::Error> {
+        let keypair = match self.keys.get(address) {
+            Some((_, sealed)) => sealed.open(&self.unlock()?)?,
+            None => bail!("Cannot find key for address: [{address}]"),
+        };
+        Ok(f(&keypair))
+    }
+
+    /// Decrypts a copy of the key of `address`, which the caller is responsible for.
+    pub fn export_key(&self, address: &MysAddress) -> Result<MysKeyPair, anyhow::Error> {
+        self.with_key(address, MysKeyPair::copy)
+    }
+
+    pub fn save_aliases(&self) -> Result<(), anyhow::Error> {
+        if let Some(path) = &self.path {
+            let aliases_store =
+                serde_json::to_string_pretty(&self.aliases.values().collect::<Vec<_>>())
+                    .with_context(|| {
+                        format!(
+                            "Cannot serialize aliases to file in keystore: {}",
+                            path.display()
+                        )
+                    })?;
+
+            let mut aliases_path = path.clone();
+            aliases_path.set_extension("aliases");
+            write_atomically(&aliases_path, aliases_store)?;
+        }
+        Ok(())
+    }
+
+    /// The keystore is written to a temporary file first, so that it is never left half written,
+    /// in particular when it replaces a plaintext keystore.
+    pub fn save_keystore(&self) -> Result<(), anyhow::Error> {
+        let (Some(path), Some(check)) = (&self.path, &self.check) else {
+            return Ok(());
+        };
+        let file = EncryptedKeystoreFile {
+            version: ENCRYPTED_KEYSTORE_VERSION,
+            scrypt: self.scrypt,
+            salt: Base64::encode(&self.salt),
+            check: check.clone(),
+            keys: self
+                .keys
+                .values()
+                .map(|(_, sealed)| sealed.clone())
+                .collect(),
+        };
+        let store = serde_json::to_string_pretty(&file)
+            .with_context(|| format!("Cannot serialize keystore to file: {}", path.display()))?;
+        write_atomically(path, store)
+    }
+
+    pub fn save(&self) -> Result<(), anyhow::Error> {
+        self.save_aliases()?;
+        self.save_keystore()?;
+        Ok(())
+    }
+}
+
+/// Writes `contents` to a temporary file next to `path` and renames it over `path`, so that
+/// readers and crashes never see a partially written file.
+fn write_atomically(path: &Path, contents: String) -> Result<(), anyhow::Error> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+    fs::write(&tmp_path, contents)
+        .with_context(|| format!("Cannot write file: {}", tmp_path.display()))?;
+    fs::rename(&tmp_path, path)
+        .with_context(|| format!("Cannot replace file: {}", path.display()))?;
+    Ok(())
+}
+
+/// Reads the passphrase of an encrypted keystore from `MYS_KEYSTORE_PASSPHRASE`, or prompts for
+/// it if the variable is not set. A new passphrase has to be entered twice.
+pub fn read_passphrase(new: bool) -> Result<Zeroizing<String>, anyhow::Error> {
+    if let Ok(passphrase) = std::env::var(MYS_KEYSTORE_PASSPHRASE_ENV) {
+        return Ok(Zeroizing::new(passphrase));
+    }
+    let prompt = if new {
+        inquire::Password::new("Enter a passphrase for the keystore:")
+            .with_custom_confirmation_message("Confirm the passphrase:")
+    } else {
+        inquire::Password::new("Enter the keystore passphrase:").without_confirmation()
+    };
+    prompt.prompt().map(Zeroizing::new).map_err(|e| {
+        anyhow!(
+            "Cannot read the keystore passphrase, set {MYS_KEYSTORE_PASSPHRASE_ENV} when not running interactively: {e}"
+        )
+    })
+}
+
+pub(crate) fn validate_alias(alias: &str) -> Result<String, anyhow::Error> {
     let re = Regex::new(r"^[A-Za-z][A-Za-z0-9-_\.]*$")
         .map_err(|_| anyhow!("Cannot build the regex needed to validate the alias naming"))?;
     let alias = alias.trim();
@@ -9,8 +9,12 @@ use fastcrypto::traits::EncodeDecodeBase64;
 use mys_keys::key_derive::generate_new_key;
 use tempfile::TempDir;
 
-use mys_keys::keystore::{AccountKeystore, FileBasedKeystore, InMemKeystore, Keystore};
+use mys_keys::external_signer::{ExternalKeystore, ExternalSignerConfig};
+use mys_keys::keystore::{
+    AccountKeystore, EncryptedKeystore, FileBasedKeystore, InMemKeystore, Keystore, ScryptParams,
+};
 use mys_types::crypto::{DefaultHash, SignatureScheme, MysSignatureInner};
+use shared_crypto::intent::Intent;
 use mys_types::{
     base_types::{MysAddress, MYS_ADDRESS_LENGTH},
     crypto::Ed25519MysSignature,
@@ -279,3 +283,203 @@ fn get_alias_by_address_test() {
     let address = generate_new_key(SignatureScheme::ED25519, None, None).unwrap();
     assert!(keystore.get_alias_by_address(&address.0).is_err())
 }
+
+#[test]
+fn encrypted_keystore_test() {
+    let temp_dir = TempDir::new().unwrap();
+    let keystore_path = temp_dir.path().join("mys.keystore");
+    let mut file_keystore = FileBasedKeystore::new(&keystore_path).unwrap();
+    let (address, _, _) = file_keystore
+        .generate_and_add_new_key(
+            SignatureScheme::ED25519,
+            Some("main".to_string()),
+            None,
+            None,
+        )
+        .unwrap();
+    let private_key = file_keystore.get_key(&address).unwrap().encode_base64();
+
+    // Weak parameters to keep the test fast
+    let scrypt = ScryptParams {
+        log_n: 10,
+        r: 8,
+        p: 1,
+    };
+    EncryptedKeystore::from_file_keystore(&file_keystore, "passphrase", scrypt).unwrap();
+    let content = fs::read_to_string(&keystore_path).unwrap();
+    assert!(!content.contains(&private_key));
+
+    // The plaintext keystore path now opens as an encrypted keystore, with keys and aliases
+    // readable without the passphrase
+    let Keystore::Encrypted(keystore) = Keystore::from_path(&keystore_path).unwrap() else {
+        panic!("Expected an encrypted keystore");
+    };
+    assert_eq!(keystore.addresses(), vec![address]);
+    assert_eq!(keystore.get_alias_by_address(&address).unwrap(), "main");
+
+    assert!(keystore.unlock_with_passphrase("wrong passphrase").is_err());
+    keystore.unlock_with_passphrase("passphrase").unwrap();
+    let signature = keystore
+        .sign_secure(&address, &"hello", Intent::mys_transaction())
+        .unwrap();
+    let expected = file_keystore
+        .sign_secure(&address, &"hello", Intent::mys_transaction())
+        .unwrap();
+    assert_eq!(signature, expected);
+
+    // Decrypted keys are not kept, so they can only be exported as copies
+    assert!(keystore.get_key(&address).is_err());
+    assert_eq!(
+        keystore.export_key(&address).unwrap().encode_base64(),
+        private_key
+    );
+
+    // Once locked, the keystore needs the passphrase again
+    keystore.lock();
+    assert!(keystore.unlock_with_passphrase("wrong passphrase").is_err());
+    keystore.unlock_with_passphrase("passphrase").unwrap();
+    assert!(keystore
+        .sign_secure(&address, &"hello", Intent::mys_transaction())
+        .is_ok());
+
+    // Aliases are replaced atomically, leaving no temporary files behind
+    let mut keystore = keystore;
+    keystore.update_alias("main", Some("renamed")).unwrap();
+    let mut files: Vec<_> = fs::read_dir(temp_dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    files.sort();
+    assert_eq!(files, vec!["mys.aliases", "mys.keystore"]);
+}
+
+#[test]
+fn encrypted_keystore_scrypt_limits_test() {
+    let temp_dir = TempDir::new().unwrap();
+    let keystore_path = temp_dir.path().join("mys.keystore");
+    let scrypt = ScryptParams {
+        log_n: 10,
+        r: 8,
+        p: 1,
+    };
+    EncryptedKeystore::create(&keystore_path, "passphrase", scrypt).unwrap();
+    assert!(EncryptedKeystore::new(&keystore_path).is_ok());
+
+    // A keystore file asking for an excessive amount of work is rejected when opened
+    let content = fs::read_to_string(&keystore_path).unwrap();
+    let mut file: serde_json::Value = serde_json::from_str(&content).unwrap();
+    file["scrypt"]["log_n"] = 40.into();
+    fs::write(&keystore_path, file.to_string()).unwrap();
+    assert!(EncryptedKeystore::new(&keystore_path).is_err());
+
+    let scrypt = ScryptParams {
+        log_n: 30,
+        r: 8,
+        p: 1,
+    };
+    let other_path = temp_dir.path().join("other.keystore");
+    assert!(EncryptedKeystore::create(&other_path, "passphrase", scrypt).is_err());
+}
+
+#[test]
+fn external_signer_keystore_test() {
+    let temp_dir = TempDir::new().unwrap();
+    let keystore_path = temp_dir.path().join("signer.keystore");
+    let mut signer_keystore = FileBasedKeystore::new(&keystore_path).unwrap();
+    let (address, _, _) = signer_keystore
+        .generate_and_add_new_key(
+            SignatureScheme::ED25519,
+            Some("signer-key".to_string()),
+            None,
+            None,
+        )
+        .unwrap();
+
+    let mut keystore = Keystore::External(ExternalKeystore::new(ExternalSignerConfig::Command {
+        program: env!("CARGO_BIN_EXE_mys-test-signer").into(),
+        args: vec![keystore_path.to_str().unwrap().to_string()],
+    }));
+    assert_eq!(keystore.addresses(), vec![address]);
+    assert_eq!(
+        keystore.get_alias_by_address(&address).unwrap(),
+        "signer-key"
+    );
+
+    // Signatures are produced by the signer process and verify against the intent message
+    let signature = keystore
+        .sign_secure(&address, &"hello", Intent::mys_transaction())
+        .unwrap();
+    assert_eq!(
+        signature,
+        signer_keystore
+            .sign_secure(&address, &"hello", Intent::mys_transaction())
+            .unwrap()
+    );
+
+    // Digests alone are not signed, the signer is sent the message itself
+    assert!(keystore.sign_hashed(&address, &[0; 32]).is_err());
+
+    // Key material never leaves the signer
+    assert!(keystore.get_key(&address).is_err());
+    let (_, keypair, _, _) = generate_new_key(SignatureScheme::ED25519, None, None).unwrap();
+    assert!(keystore.add_key(None, keypair).is_err());
+    let unknown = MysAddress::random_for_testing_only();
+    assert!(keystore
+        .sign_secure(&unknown, &"hello", Intent::mys_transaction())
+        .is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn external_signer_socket_test() {
+    use std::io::Write;
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = TempDir::new().unwrap();
+    let socket_path = temp_dir.path().join("signer.sock");
+    let signer_keystore = Keystore::InMem(InMemKeystore::new_insecure_for_tests(2));
+    let addresses = signer_keystore.addresses();
+
+    // The signer is only contacted once keys are needed
+    let keystore = ExternalKeystore::new(ExternalSignerConfig::Socket {
+        path: socket_path.clone(),
+    });
+    assert!(keystore.addresses().is_empty());
+    assert!(keystore
+        .sign_secure(&addresses[0], &"hello", Intent::mys_transaction())
+        .is_err());
+
+    // A socket left behind by a previous signer is replaced
+    drop(std::os::unix::net::UnixListener::bind(&socket_path).unwrap());
+    let listener_path = socket_path.clone();
+    std::thread::spawn(move || {
+        mys_keys::external_signer::serve_unix_socket(&signer_keystore, &listener_path).unwrap()
+    });
+    wait_for_signer(&socket_path);
+    let mode = std::fs::metadata(&socket_path)
+        .unwrap()
+        .permissions()
+        .mode();
+    assert_eq!(mode & 0o777, 0o600);
+
+    // A client sending garbage does not stop the signer
+    let mut stream = std::os::unix::net::UnixStream::connect(&socket_path).unwrap();
+    stream.write_all(&[0xff, 0xfe, b'\n']).unwrap();
+    drop(stream);
+
+    let keystore = ExternalKeystore::new(ExternalSignerConfig::Socket { path: socket_path });
+    assert_eq!(keystore.addresses(), addresses);
+    for address in &addresses {
+        keystore
+            .sign_secure(address, &"hello", Intent::mys_transaction())
+            .unwrap();
+    }
+}
+
+/// Waits until a signer accepts connections on `path`.
+#[cfg(unix)]
+fn wait_for_signer(path: &std::path::Path) {
+    while std::os::unix::net::UnixStream::connect(path).is_err() {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+}
@@ -2,6 +2,4 @@
 // SPDX-License-Identifier: Apache-2.0
 
 pub(crate) mod client;
-pub(crate) mod progress_store;
 mod proto;
-pub(crate) mod worker;
@@ -1,11 +1,16 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 mod bigtable;
+mod progress_store;
+mod rocksdb;
+mod worker;
 use anyhow::Result;
 use async_trait::async_trait;
 pub use bigtable::client::BigTableClient;
-pub use bigtable::progress_store::BigTableProgressStore;
-pub use bigtable::worker::KvWorker;
+pub use progress_store::{BigTableProgressStore, KvProgressStore};
+pub use rocksdb::client::RocksDbClient;
+use serde::{Deserialize, Serialize};
+pub use worker::KvWorker;
 use mys_types::base_types::ObjectID;
 use mys_types::crypto::AuthorityStrongQuorumSignInfo;
 use mys_types::digests::{CheckpointDigest, TransactionDigest};
@@ -45,14 +50,14 @@ pub trait KeyValueStoreWriter {
     async fn save_watermark(&mut self, watermark: CheckpointSequenceNumber) -> Result<()>;
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Checkpoint {
     pub summary: CheckpointSummary,
     pub contents: CheckpointContents,
     pub signatures: AuthorityStrongQuorumSignInfo,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TransactionData {
     pub transaction: Transaction,
     pub effects: TransactionEffects,
@@ -3,7 +3,11 @@
 use anyhow::Result;
 use prometheus::Registry;
 use mys_data_ingestion_core::{DataIngestionMetrics, IndexerExecutor, ReaderOptions, WorkerPool};
-use mys_kvstore::{BigTableClient, BigTableProgressStore, KvWorker};
+use mys_kvstore::{
+    BigTableClient, KeyValueStoreReader, KeyValueStoreWriter, KvProgressStore, KvWorker,
+    RocksDbClient,
+};
+use std::path::Path;
 use telemetry_subscribers::TelemetryConfig;
 use tokio::sync::oneshot;
 
@@ -11,25 +15,36 @@ use tokio::sync::oneshot;
 async fn main() -> Result<()> {
     let _guard = TelemetryConfig::new().with_env().init();
     let args: Vec<String> = std::env::args().collect();
-    if args.len() < 3 {
-        eprintln!("Please provide BigTable instance id and network name");
+    if args.len() < 3 || (args[1] == "--rocksdb" && args.len() < 4) {
+        eprintln!("Please provide BigTable instance id (or --rocksdb <path>) and network name");
         std::process::exit(1);
     }
-    let instance_id = args[1].to_string();
-    let network = args[2].to_string();
+    let network = args[args.len() - 1].to_string();
     assert!(
         network == "mainnet" || network == "testnet",
         "Invalid network name"
     );
-    let client = BigTableClient::new_local(instance_id).await?;
+    if args[1] == "--rocksdb" {
+        let client = RocksDbClient::new(Path::new(&args[2]));
+        run(client, "rocksdb", network).await
+    } else {
+        let instance_id = args[1].to_string();
+        let client = BigTableClient::new_local(instance_id).await?;
+        run(client, "bigtable", network).await
+    }
+}
 
+async fn run<C>(client: C, task_name: &str, network: String) -> Result<()>
+where
+    C: KeyValueStoreReader + KeyValueStoreWriter + Clone + Send + Sync + 'static,
+{
     let (_exit_sender, exit_receiver) = oneshot::channel();
     let mut executor = IndexerExecutor::new(
-        BigTableProgressStore::new(client.clone()),
+        KvProgressStore::new(client.clone()),
         1,
         DataIngestionMetrics::new(&Registry::new()),
     );
-    let worker_pool = WorkerPool::new(KvWorker { client }, "bigtable".to_string(), 50);
+    let worker_pool = WorkerPool::new(KvWorker { client }, task_name.to_string(), 50);
     executor.register(worker_pool).await?;
     executor
         .run(
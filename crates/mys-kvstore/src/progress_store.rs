@@ -7,18 +7,24 @@ use async_trait::async_trait;
 use mys_data_ingestion_core::ProgressStore;
 use mys_types::messages_checkpoint::CheckpointSequenceNumber;
 
-pub struct BigTableProgressStore {
-    client: BigTableClient,
+/// Keeps the ingestion watermark in the key value store it is ingesting into
+pub struct KvProgressStore<C> {
+    client: C,
 }
 
-impl BigTableProgressStore {
-    pub fn new(client: BigTableClient) -> Self {
+pub type BigTableProgressStore = KvProgressStore<BigTableClient>;
+
+impl<C> KvProgressStore<C> {
+    pub fn new(client: C) -> Self {
         Self { client }
     }
 }
 
 #[async_trait]
-impl ProgressStore for BigTableProgressStore {
+impl<C> ProgressStore for KvProgressStore<C>
+where
+    C: KeyValueStoreReader + KeyValueStoreWriter + Send + Sync,
+{
     async fn load(&mut self, _: String) -> Result<CheckpointSequenceNumber> {
         self.client.get_latest_checkpoint().await
     }
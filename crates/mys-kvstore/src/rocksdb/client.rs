@@ -0,0 +1,308 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{Checkpoint, KeyValueStoreReader, KeyValueStoreWriter, TransactionData};
+use anyhow::Result;
+use async_trait::async_trait;
+use std::path::Path;
+use std::sync::Arc;
+use mys_types::base_types::{ObjectID, TransactionDigest};
+use mys_types::digests::CheckpointDigest;
+use mys_types::full_checkpoint_content::CheckpointData;
+use mys_types::messages_checkpoint::CheckpointSequenceNumber;
+use mys_types::object::Object;
+use mys_types::storage::ObjectKey;
+use typed_store::rocks::{DBMap, MetricConf};
+use typed_store::traits::TableSummary;
+use typed_store::traits::TypedStoreDebug;
+use typed_store::DBMapUtils;
+use typed_store::Map;
+
+const WATERMARK_KEY: u8 = 0;
+
+#[derive(DBMapUtils)]
+struct KvTables {
+    objects: DBMap<ObjectKey, Object>,
+    transactions: DBMap<TransactionDigest, TransactionData>,
+    checkpoints: DBMap<CheckpointSequenceNumber, Checkpoint>,
+    checkpoints_by_digest: DBMap<CheckpointDigest, CheckpointSequenceNumber>,
+    watermark: DBMap<u8, CheckpointSequenceNumber>,
+}
+
+/// Key value store backed by a local RocksDB instance. Mirrors the layout of the BigTable
+/// backend, so the same ingestion worker and progress store can be used against either of them
+#[derive(Clone)]
+pub struct RocksDbClient {
+    tables: Arc<KvTables>,
+}
+
+impl RocksDbClient {
+    pub fn new(path: &Path) -> Self {
+        let tables = KvTables::open_tables_read_write(
+            path.to_path_buf(),
+            MetricConf::new("kvstore"),
+            None,
+            None,
+        );
+        Self {
+            tables: Arc::new(tables),
+        }
+    }
+}
+
+#[async_trait]
+impl KeyValueStoreWriter for RocksDbClient {
+    async fn save_objects(&mut self, objects: &[&Object]) -> Result<()> {
+        let mut batch = self.tables.objects.batch();
+        batch.insert_batch(
+            &self.tables.objects,
+            objects
+                .iter()
+                .map(|object| (ObjectKey(object.id(), object.version()), *object)),
+        )?;
+        batch.write()?;
+        Ok(())
+    }
+
+    async fn save_transactions(&mut self, transactions: &[TransactionData]) -> Result<()> {
+        let mut batch = self.tables.transactions.batch();
+        batch.insert_batch(
+            &self.tables.transactions,
+            transactions
+                .iter()
+                .map(|transaction| (*transaction.transaction.digest(), transaction)),
+        )?;
+        batch.write()?;
+        Ok(())
+    }
+
+    async fn save_checkpoint(&mut self, checkpoint: &CheckpointData) -> Result<()> {
+        let summary = checkpoint.checkpoint_summary.data();
+        let sequence_number = summary.sequence_number;
+        let value = Checkpoint {
+            summary: summary.clone(),
+            contents: checkpoint.checkpoint_contents.clone(),
+            signatures: checkpoint.checkpoint_summary.auth_sig().clone(),
+        };
+        let mut batch = self.tables.checkpoints.batch();
+        batch.insert_batch(
+            &self.tables.checkpoints,
+            std::iter::once((sequence_number, value)),
+        )?;
+        batch.insert_batch(
+            &self.tables.checkpoints_by_digest,
+            std::iter::once((*checkpoint.checkpoint_summary.digest(), sequence_number)),
+        )?;
+        batch.write()?;
+        Ok(())
+    }
+
+    async fn save_watermark(&mut self, watermark: CheckpointSequenceNumber) -> Result<()> {
+        self.tables.watermark.insert(&WATERMARK_KEY, &watermark)?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl KeyValueStoreReader for RocksDbClient {
+    async fn get_objects(&mut self, object_keys: &[ObjectKey]) -> Result<Vec<Object>> {
+        Ok(self
+            .tables
+            .objects
+            .multi_get(object_keys)?
+            .into_iter()
+            .flatten()
+            .collect())
+    }
+
+    async fn get_transactions(
+        &mut self,
+        transactions: &[TransactionDigest],
+    ) -> Result<Vec<TransactionData>> {
+        Ok(self
+            .tables
+            .transactions
+            .multi_get(transactions)?
+            .into_iter()
+            .flatten()
+            .collect())
+    }
+
+    async fn get_checkpoints(
+        &mut self,
+        sequence_numbers: &[CheckpointSequenceNumber],
+    ) -> Result<Vec<Checkpoint>> {
+        Ok(self
+            .tables
+            .checkpoints
+            .multi_get(sequence_numbers)?
+            .into_iter()
+            .flatten()
+            .collect())
+    }
+
+    async fn get_checkpoint_by_digest(
+        &mut self,
+        digest: CheckpointDigest,
+    ) -> Result<Option<Checkpoint>> {
+        match self.tables.checkpoints_by_digest.get(&digest)? {
+            Some(sequence_number) => Ok(self.tables.checkpoints.get(&sequence_number)?),
+            None => Ok(None),
+        }
+    }
+
+    async fn get_latest_checkpoint(&mut self) -> Result<CheckpointSequenceNumber> {
+        Ok(self
+            .tables
+            .watermark
+            .get(&WATERMARK_KEY)?
+            .unwrap_or_default())
+    }
+
+    async fn get_latest_object(&mut self, object_id: &ObjectID) -> Result<Option<Object>> {
+        let latest = self
+            .tables
+            .objects
+            .unbounded_iter()
+            .skip_prior_to(&ObjectKey::max_for_id(object_id))?
+            .reverse()
+            .next();
+        Ok(latest
+            .filter(|(key, _)| key.0 == *object_id)
+            .map(|(_, object)| object))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mys_types::base_types::{MysAddress, SequenceNumber};
+    use mys_types::object::Owner;
+    use mys_types::test_checkpoint_data_builder::TestCheckpointDataBuilder;
+
+    #[tokio::test]
+    async fn test_objects_and_watermark() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut client = RocksDbClient::new(dir.path());
+        assert_eq!(client.get_latest_checkpoint().await?, 0);
+
+        let id = ObjectID::random();
+        let owner = Owner::AddressOwner(MysAddress::ZERO);
+        let objects: Vec<_> = (1..=3)
+            .map(|version| {
+                Object::with_id_owner_version_for_testing(
+                    id,
+                    SequenceNumber::from_u64(version),
+                    owner.clone(),
+                )
+            })
+            .collect();
+        client
+            .save_objects(&objects.iter().collect::<Vec<_>>())
+            .await?;
+        client.save_watermark(42).await?;
+
+        let found = client
+            .get_objects(&[
+                ObjectKey(id, SequenceNumber::from_u64(2)),
+                ObjectKey(id, SequenceNumber::from_u64(7)),
+            ])
+            .await?;
+        assert_eq!(found, vec![objects[1].clone()]);
+        assert_eq!(
+            client.get_latest_object(&id).await?,
+            Some(objects[2].clone())
+        );
+        assert_eq!(client.get_latest_object(&ObjectID::random()).await?, None);
+        assert_eq!(client.get_latest_checkpoint().await?, 42);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_transactions() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut client = RocksDbClient::new(dir.path());
+
+        let checkpoint = TestCheckpointDataBuilder::new(1)
+            .start_transaction(0)
+            .create_owned_object(1)
+            .finish_transaction()
+            .start_transaction(1)
+            .create_owned_object(2)
+            .finish_transaction()
+            .build_checkpoint();
+        let transactions: Vec<_> = checkpoint
+            .transactions
+            .iter()
+            .map(|transaction| TransactionData {
+                transaction: transaction.transaction.clone(),
+                effects: transaction.effects.clone(),
+                events: transaction.events.clone(),
+                checkpoint_number: checkpoint.checkpoint_summary.sequence_number,
+                timestamp: checkpoint.checkpoint_summary.timestamp_ms,
+            })
+            .collect();
+        client.save_transactions(&transactions).await?;
+
+        let digests: Vec<_> = transactions
+            .iter()
+            .map(|transaction| *transaction.transaction.digest())
+            .collect();
+        let found = client
+            .get_transactions(&[digests[1], TransactionDigest::random(), digests[0]])
+            .await?;
+        assert_eq!(
+            found
+                .iter()
+                .map(|transaction| *transaction.transaction.digest())
+                .collect::<Vec<_>>(),
+            vec![digests[1], digests[0]]
+        );
+        assert_eq!(found[0].effects, transactions[1].effects);
+        assert_eq!(found[1].checkpoint_number, 1);
+        assert!(client
+            .get_transactions(&[TransactionDigest::random()])
+            .await?
+            .is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_checkpoints() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut client = RocksDbClient::new(dir.path());
+
+        let mut builder = TestCheckpointDataBuilder::new(1)
+            .start_transaction(0)
+            .finish_transaction();
+        let first = builder.build_checkpoint();
+        let mut builder = builder.start_transaction(0).finish_transaction();
+        let second = builder.build_checkpoint();
+        client.save_checkpoint(&first).await?;
+        client.save_checkpoint(&second).await?;
+
+        let found = client.get_checkpoints(&[2, 7, 1]).await?;
+        assert_eq!(
+            found
+                .iter()
+                .map(|checkpoint| checkpoint.summary.sequence_number)
+                .collect::<Vec<_>>(),
+            vec![2, 1]
+        );
+        assert_eq!(found[0].summary, *second.checkpoint_summary.data());
+        assert_eq!(found[0].contents, second.checkpoint_contents);
+        assert_eq!(found[1].summary, *first.checkpoint_summary.data());
+
+        let by_digest = client
+            .get_checkpoint_by_digest(*first.checkpoint_summary.digest())
+            .await?
+            .expect("checkpoint should be found by its digest");
+        assert_eq!(by_digest.summary, *first.checkpoint_summary.data());
+        assert_eq!(by_digest.contents, first.checkpoint_contents);
+        assert!(client
+            .get_checkpoint_by_digest(CheckpointDigest::random())
+            .await?
+            .is_none());
+        Ok(())
+    }
+}
@@ -1,17 +1,21 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::{BigTableClient, KeyValueStoreWriter, TransactionData};
+use crate::{KeyValueStoreWriter, TransactionData};
 use async_trait::async_trait;
 use mys_data_ingestion_core::Worker;
 use mys_types::full_checkpoint_content::CheckpointData;
 
-pub struct KvWorker {
-    pub client: BigTableClient,
+/// Ingests checkpoints into any key value store backend
+pub struct KvWorker<C> {
+    pub client: C,
 }
 
 #[async_trait]
-impl Worker for KvWorker {
+impl<C> Worker for KvWorker<C>
+where
+    C: KeyValueStoreWriter + Clone + Send + Sync + 'static,
+{
     type Result = ();
 
     async fn process_checkpoint(&self, checkpoint: &CheckpointData) -> anyhow::Result<()> {
@@ -16,6 +16,8 @@ use std::{
     net::{IpAddr, Ipv4Addr, SocketAddr},
     str::FromStr,
 };
+use mys_config::transaction_deny_config::DynamicTransactionDenyConfig;
+use mys_core::transaction_signing_filter::DenyListKind;
 use mys_types::{
     base_types::AuthorityName,
     crypto::{RandomnessPartialSignature, RandomnessRound, RandomnessSignature},
@@ -68,6 +70,26 @@ use tracing::info;
 // Inject a full signature from another node, bypassing validity checks.
 //
 //  $ curl 'http://127.0.0.1:1337/randomness-inject-full-sig?round=123&sigs=base64encodedsig'
+//
+// View the dynamic transaction deny config:
+//
+//  $ curl 'http://127.0.0.1:1337/transaction-deny'
+//
+// Deny a package (or `object`/`address`) for one hour, recording why:
+//
+//  $ curl -X POST 'http://127.0.0.1:1337/transaction-deny/add?list=package&id=0x123&ttl=1h&reason=incident-42'
+//
+// Remove a previously denied package:
+//
+//  $ curl -X POST 'http://127.0.0.1:1337/transaction-deny/remove?list=package&id=0x123'
+//
+// Disable package publishing for 30 minutes:
+//
+//  $ curl -X POST 'http://127.0.0.1:1337/transaction-deny/switch?name=package-publish-disabled&disabled=true&ttl=30m'
+//
+// Replace the whole dynamic transaction deny config with a YAML or JSON document:
+//
+//  $ curl -X POST 'http://127.0.0.1:1337/transaction-deny' --data-binary @deny.yaml
 
 const LOGGING_ROUTE: &str = "/logging";
 const TRACING_ROUTE: &str = "/enable-tracing";
@@ -80,6 +102,13 @@ const NODE_CONFIG: &str = "/node-config";
 const RANDOMNESS_PARTIAL_SIGS_ROUTE: &str = "/randomness-partial-sigs";
 const RANDOMNESS_INJECT_PARTIAL_SIGS_ROUTE: &str = "/randomness-inject-partial-sigs";
 const RANDOMNESS_INJECT_FULL_SIG_ROUTE: &str = "/randomness-inject-full-sig";
+const TRANSACTION_DENY_ROUTE: &str = "/transaction-deny";
+const TRANSACTION_DENY_ADD_ROUTE: &str = "/transaction-deny/add";
+const TRANSACTION_DENY_REMOVE_ROUTE: &str = "/transaction-deny/remove";
+const TRANSACTION_DENY_SWITCH_ROUTE: &str = "/transaction-deny/switch";
+
+/// Source recorded in the transaction deny audit log for changes made through this server.
+const ADMIN_SOURCE: &str = "admin";
 
 struct AppState {
     node: Arc<MysNode>,
@@ -119,6 +148,11 @@ pub async fn run_admin_server(node: Arc<MysNode>, port: u16, tracing_handle: Tra
             RANDOMNESS_INJECT_FULL_SIG_ROUTE,
             post(randomness_inject_full_sig),
         )
+        .route(TRANSACTION_DENY_ROUTE, get(get_transaction_deny))
+        .route(TRANSACTION_DENY_ROUTE, post(replace_transaction_deny))
+        .route(TRANSACTION_DENY_ADD_ROUTE, post(add_transaction_deny))
+        .route(TRANSACTION_DENY_REMOVE_ROUTE, post(remove_transaction_deny))
+        .route(TRANSACTION_DENY_SWITCH_ROUTE, post(switch_transaction_deny))
         .with_state(Arc::new(app_state));
 
     let socket_address = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port);
@@ -443,3 +477,141 @@ async fn randomness_inject_full_sig(
         Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
     }
 }
+
+async fn get_transaction_deny(State(state): State<Arc<AppState>>) -> (StatusCode, String) {
+    let overrides = state.node.state().transaction_deny_manager().overrides();
+    match serde_yaml::to_string(&overrides) {
+        Ok(output) => (StatusCode::OK, output),
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()),
+    }
+}
+
+async fn replace_transaction_deny(
+    State(state): State<Arc<AppState>>,
+    body: String,
+) -> (StatusCode, String) {
+    let overrides: DynamicTransactionDenyConfig = match serde_yaml::from_str(&body) {
+        Ok(overrides) => overrides,
+        Err(err) => return (StatusCode::BAD_REQUEST, err.to_string()),
+    };
+
+    match state
+        .node
+        .state()
+        .transaction_deny_manager()
+        .replace(ADMIN_SOURCE, overrides)
+    {
+        Ok(()) => (
+            StatusCode::OK,
+            "transaction deny config replaced\n".to_string(),
+        ),
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()),
+    }
+}
+
+#[derive(Deserialize)]
+struct AddTransactionDeny {
+    list: String,
+    id: String,
+    ttl: Option<String>,
+    reason: Option<String>,
+}
+
+async fn add_transaction_deny(
+    State(state): State<Arc<AppState>>,
+    args: Query<AddTransactionDeny>,
+) -> (StatusCode, String) {
+    let Query(AddTransactionDeny {
+        list,
+        id,
+        ttl,
+        reason,
+    }) = args;
+
+    let kind = match DenyListKind::from_str(&list) {
+        Ok(kind) => kind,
+        Err(err) => return (StatusCode::BAD_REQUEST, err.to_string()),
+    };
+    let ttl = match ttl.map(|ttl| parse_duration(&ttl)).transpose() {
+        Ok(ttl) => ttl,
+        Err(err) => return (StatusCode::BAD_REQUEST, err.to_string()),
+    };
+
+    match state.node.state().transaction_deny_manager().add_entry(
+        ADMIN_SOURCE,
+        kind,
+        &id,
+        ttl,
+        reason,
+    ) {
+        Ok(()) => (StatusCode::OK, format!("{id} added to {list} deny list\n")),
+        Err(err) => (StatusCode::BAD_REQUEST, err.to_string()),
+    }
+}
+
+#[derive(Deserialize)]
+struct RemoveTransactionDeny {
+    list: String,
+    id: String,
+}
+
+async fn remove_transaction_deny(
+    State(state): State<Arc<AppState>>,
+    args: Query<RemoveTransactionDeny>,
+) -> (StatusCode, String) {
+    let Query(RemoveTransactionDeny { list, id }) = args;
+
+    let kind = match DenyListKind::from_str(&list) {
+        Ok(kind) => kind,
+        Err(err) => return (StatusCode::BAD_REQUEST, err.to_string()),
+    };
+
+    match state
+        .node
+        .state()
+        .transaction_deny_manager()
+        .remove_entry(ADMIN_SOURCE, kind, &id)
+    {
+        Ok(()) => (
+            StatusCode::OK,
+            format!("{id} removed from {list} deny list\n"),
+        ),
+        Err(err) => (StatusCode::BAD_REQUEST, err.to_string()),
+    }
+}
+
+#[derive(Deserialize)]
+struct SwitchTransactionDeny {
+    name: String,
+    disabled: bool,
+    ttl: Option<String>,
+    reason: Option<String>,
+}
+
+async fn switch_transaction_deny(
+    State(state): State<Arc<AppState>>,
+    args: Query<SwitchTransactionDeny>,
+) -> (StatusCode, String) {
+    let Query(SwitchTransactionDeny {
+        name,
+        disabled,
+        ttl,
+        reason,
+    }) = args;
+
+    let ttl = match ttl.map(|ttl| parse_duration(&ttl)).transpose() {
+        Ok(ttl) => ttl,
+        Err(err) => return (StatusCode::BAD_REQUEST, err.to_string()),
+    };
+
+    match state.node.state().transaction_deny_manager().set_switch(
+        ADMIN_SOURCE,
+        &name,
+        disabled,
+        ttl,
+        reason,
+    ) {
+        Ok(()) => (StatusCode::OK, format!("{name} set to {disabled}\n")),
+        Err(err) => (StatusCode::BAD_REQUEST, err.to_string()),
+    }
+}
@@ -1009,6 +1009,7 @@ impl MysNode {
                 prometheus_registry,
                 checkpoint_store,
                 chain_identifier,
+                config.state_snapshot_write_config.max_delta_chain_length,
             )?;
             Ok(Some(snapshot_uploader.start()))
         } else {
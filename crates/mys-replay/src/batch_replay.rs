@@ -1,12 +1,13 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::data_fetcher::DataFetcher;
 use crate::replay::{ExecutionSandboxState, LocalExec};
 use crate::types::ReplayEngineError;
 use futures::future::join_all;
 use futures::FutureExt;
 use parking_lot::Mutex;
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
 use std::path::PathBuf;
 use std::sync::atomic::AtomicUsize;
 use std::sync::Arc;
@@ -60,6 +61,80 @@ pub async fn batch_replay(
     }
 }
 
+/// Replay the given transactions offline from a local checkpoint archive, optionally seeded from
+/// a state snapshot. Transactions read the objects written by earlier ones, so every checkpoint is
+/// replayed in order until all the given transactions have been seen, but only those are reported.
+/// Replay stops at the first transaction that fails, since the state every later transaction
+/// runs against has diverged from the archive.
+pub async fn batch_replay_from_archive(
+    tx_digests: impl Iterator<Item = TransactionDigest>,
+    archive_path: PathBuf,
+    snapshot_path: Option<PathBuf>,
+    expensive_safety_check_config: ExpensiveSafetyCheckConfig,
+    use_authority: bool,
+    persist_path: Option<PathBuf>,
+) -> Result<(), ReplayEngineError> {
+    let mut remaining: HashSet<_> = tx_digests.collect();
+    let total_count = remaining.len();
+    let cur_time = Instant::now();
+    let mut executor = LocalExec::new_for_archive(archive_path, snapshot_path).await?;
+    let latest_checkpoint = executor
+        .fetcher
+        .get_latest_checkpoint_sequence_number()
+        .await?;
+    let mut checkpoint_id = executor.fetcher.as_archive().first_replayable_checkpoint();
+    while !remaining.is_empty() && checkpoint_id <= latest_checkpoint {
+        for digest in executor.get_checkpoint_txs(checkpoint_id).await? {
+            let sandbox_state = executor
+                .execute_transaction(
+                    &digest,
+                    expensive_safety_check_config.clone(),
+                    use_authority,
+                    None,
+                    None,
+                    None,
+                    None,
+                )
+                .await
+                .and_then(|sandbox_state| {
+                    sandbox_state.check_effects()?;
+                    Ok(sandbox_state)
+                })
+                .inspect_err(|err| {
+                    error!(
+                        "Replaying transaction {:?} in checkpoint {} failed: {:?}",
+                        digest, checkpoint_id, err
+                    )
+                })?;
+            if !remaining.remove(&digest) {
+                continue;
+            }
+            info!(
+                "[{}/{}] Replayed transaction {:?}",
+                total_count - remaining.len(),
+                total_count,
+                digest
+            );
+            if let Some(path) = persist_path.as_ref() {
+                let out = serde_json::to_string(&sandbox_state).unwrap();
+                std::fs::write(path.join(format!("{}.json", digest)), out).unwrap();
+            }
+        }
+        checkpoint_id += 1;
+    }
+    info!(
+        "Finished replaying {} transactions, took {:?}",
+        total_count - remaining.len(),
+        cur_time.elapsed()
+    );
+    if !remaining.is_empty() {
+        error!("Some transactions were not replayed: {:?}", remaining);
+    } else {
+        info!("All replayed transactions passed");
+    }
+    Ok(())
+}
+
 struct TransactionDigestProvider {
     digests: Mutex<VecDeque<TransactionDigest>>,
     total_count: usize,
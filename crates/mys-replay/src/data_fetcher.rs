@@ -5,14 +5,22 @@ use crate::types::ReplayEngineError;
 use crate::types::EPOCH_CHANGE_STRUCT_TAG;
 use async_trait::async_trait;
 use futures::future::join_all;
+use indicatif::MultiProgress;
 use lru::LruCache;
 use move_core_types::language_storage::StructTag;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use rand::Rng;
 use std::collections::BTreeMap;
 use std::num::NonZeroUsize;
+use std::path::PathBuf;
 use std::str::FromStr;
+use std::sync::Arc;
+use mys_archival::reader::{ArchiveReader, ArchiveReaderMetrics};
+use mys_config::node::ArchiveReaderConfig;
+use mys_config::object_storage_config::{ObjectStoreConfig, ObjectStoreType};
+use mys_core::authority::authority_store_tables::LiveObject;
 use mys_core::authority::NodeStateDump;
+use mys_framework::BuiltInFramework;
 use mys_json_rpc_api::QUERY_MAX_RESULT_LIMIT;
 use mys_json_rpc_types::EventFilter;
 use mys_json_rpc_types::MysEvent;
@@ -24,12 +32,26 @@ use mys_json_rpc_types::MysPastObjectResponse;
 use mys_json_rpc_types::MysTransactionBlockResponse;
 use mys_json_rpc_types::MysTransactionBlockResponseOptions;
 use mys_sdk::MysClient;
-use mys_types::base_types::{ObjectID, SequenceNumber, VersionNumber};
-use mys_types::digests::TransactionDigest;
+use mys_snapshot::reader::{
+    download_bytes, verify_live_objects_sha3, LiveObjectIter, StateSnapshotReaderV1,
+};
+use mys_types::base_types::{ExecutionData, ObjectID, SequenceNumber, VersionNumber};
+use mys_types::digests::{ChainIdentifier, TransactionDigest};
+use mys_types::effects::TransactionEffectsAPI;
+use mys_types::messages_checkpoint::{CheckpointSequenceNumber, FullCheckpointContents};
+use mys_types::mys_system_state::{get_mys_system_state, MysSystemState, MysSystemStateTrait};
 use mys_types::object::Object;
+use mys_types::storage::ObjectStore;
 use mys_types::transaction::SenderSignedData;
 use mys_types::transaction::TransactionDataAPI;
-use mys_types::transaction::{EndOfEpochTransactionKind, TransactionKind};
+use mys_types::transaction::{EndOfEpochTransactionKind, GenesisObject, TransactionKind};
+use tempfile::TempDir;
+use tracing::info;
+use typed_store::rocks::{DBMap, MetricConf};
+use typed_store::traits::TableSummary;
+use typed_store::traits::TypedStoreDebug;
+use typed_store::DBMapUtils;
+use typed_store::Map;
 
 /// This trait defines the interfaces for fetching data from some local or remote store
 #[async_trait]
@@ -92,17 +114,43 @@ pub(crate) trait DataFetcher {
     ) -> Result<Object, ReplayEngineError>;
 }
 
+/// Picks a random transaction in checkpoints `start..=end`. Starting at a random checkpoint, empty
+/// checkpoints are skipped by moving on to the next one, wrapping around at the end of the range.
+async fn random_transaction_in_checkpoints<F: DataFetcher + Sync + ?Sized>(
+    fetcher: &F,
+    start: u64,
+    end: u64,
+) -> Result<TransactionDigest, ReplayEngineError> {
+    if start > end {
+        return Err(ReplayEngineError::NoTransactionsInCheckpoints { start, end });
+    }
+    let mut checkpoint_id = rand::thread_rng().gen_range(start..=end);
+    for _ in start..=end {
+        let txs = fetcher.get_checkpoint_txs(checkpoint_id).await?;
+        if !txs.is_empty() {
+            return Ok(txs[rand::thread_rng().gen_range(0..txs.len())]);
+        }
+        checkpoint_id = if checkpoint_id == end {
+            start
+        } else {
+            checkpoint_id + 1
+        };
+    }
+    Err(ReplayEngineError::NoTransactionsInCheckpoints { start, end })
+}
+
 #[derive(Clone)]
 pub enum Fetchers {
     Remote(RemoteFetcher),
     NodeStateDump(NodeStateDumpFetcher),
+    Archive(ArchiveFetcher),
 }
 
 impl Fetchers {
     pub fn as_remote(&self) -> &RemoteFetcher {
         match self {
             Fetchers::Remote(q) => q,
-            Fetchers::NodeStateDump(_) => panic!("not a remote fetcher"),
+            Fetchers::NodeStateDump(_) | Fetchers::Archive(_) => panic!("not a remote fetcher"),
         }
     }
 
@@ -114,16 +162,25 @@ impl Fetchers {
                 q.clear_cache_for_new_task();
                 q
             }
-            Fetchers::NodeStateDump(_) => panic!("not a remote fetcher"),
+            Fetchers::NodeStateDump(_) | Fetchers::Archive(_) => panic!("not a remote fetcher"),
         }
     }
 
     pub fn as_node_state_dump(&self) -> &NodeStateDumpFetcher {
         match self {
-            Fetchers::Remote(_) => panic!("not a node state dump fetcher"),
+            Fetchers::Remote(_) | Fetchers::Archive(_) => {
+                panic!("not a node state dump fetcher")
+            }
             Fetchers::NodeStateDump(q) => q,
         }
     }
+
+    pub fn as_archive(&self) -> &ArchiveFetcher {
+        match self {
+            Fetchers::Remote(_) | Fetchers::NodeStateDump(_) => panic!("not an archive fetcher"),
+            Fetchers::Archive(q) => q,
+        }
+    }
 }
 
 #[async_trait]
@@ -136,6 +193,7 @@ impl DataFetcher for Fetchers {
         match self {
             Fetchers::Remote(q) => q.multi_get_versioned(objects).await,
             Fetchers::NodeStateDump(q) => q.multi_get_versioned(objects).await,
+            Fetchers::Archive(q) => q.multi_get_versioned(objects).await,
         }
     }
 
@@ -146,6 +204,7 @@ impl DataFetcher for Fetchers {
         match self {
             Fetchers::Remote(q) => q.multi_get_latest(objects).await,
             Fetchers::NodeStateDump(q) => q.multi_get_latest(objects).await,
+            Fetchers::Archive(q) => q.multi_get_latest(objects).await,
         }
     }
 
@@ -156,6 +215,7 @@ impl DataFetcher for Fetchers {
         match self {
             Fetchers::Remote(q) => q.get_checkpoint_txs(id).await,
             Fetchers::NodeStateDump(q) => q.get_checkpoint_txs(id).await,
+            Fetchers::Archive(q) => q.get_checkpoint_txs(id).await,
         }
     }
 
@@ -166,6 +226,7 @@ impl DataFetcher for Fetchers {
         match self {
             Fetchers::Remote(q) => q.get_transaction(tx_digest).await,
             Fetchers::NodeStateDump(q) => q.get_transaction(tx_digest).await,
+            Fetchers::Archive(q) => q.get_transaction(tx_digest).await,
         }
     }
 
@@ -176,6 +237,7 @@ impl DataFetcher for Fetchers {
         match self {
             Fetchers::Remote(q) => q.get_loaded_child_objects(tx_digest).await,
             Fetchers::NodeStateDump(q) => q.get_loaded_child_objects(tx_digest).await,
+            Fetchers::Archive(q) => q.get_loaded_child_objects(tx_digest).await,
         }
     }
 
@@ -183,6 +245,7 @@ impl DataFetcher for Fetchers {
         match self {
             Fetchers::Remote(q) => q.get_latest_checkpoint_sequence_number().await,
             Fetchers::NodeStateDump(q) => q.get_latest_checkpoint_sequence_number().await,
+            Fetchers::Archive(q) => q.get_latest_checkpoint_sequence_number().await,
         }
     }
 
@@ -200,6 +263,10 @@ impl DataFetcher for Fetchers {
                 q.fetch_random_transaction(checkpoint_id_start, checkpoint_id_end)
                     .await
             }
+            Fetchers::Archive(q) => {
+                q.fetch_random_transaction(checkpoint_id_start, checkpoint_id_end)
+                    .await
+            }
         }
     }
 
@@ -210,6 +277,7 @@ impl DataFetcher for Fetchers {
         match self {
             Fetchers::Remote(q) => q.get_epoch_start_timestamp_and_rgp(epoch_id).await,
             Fetchers::NodeStateDump(q) => q.get_epoch_start_timestamp_and_rgp(epoch_id).await,
+            Fetchers::Archive(q) => q.get_epoch_start_timestamp_and_rgp(epoch_id).await,
         }
     }
 
@@ -220,12 +288,14 @@ impl DataFetcher for Fetchers {
         match self {
             Fetchers::Remote(q) => q.get_epoch_change_events(reverse).await,
             Fetchers::NodeStateDump(q) => q.get_epoch_change_events(reverse).await,
+            Fetchers::Archive(q) => q.get_epoch_change_events(reverse).await,
         }
     }
     async fn get_chain_id(&self) -> Result<String, ReplayEngineError> {
         match self {
             Fetchers::Remote(q) => q.get_chain_id().await,
             Fetchers::NodeStateDump(q) => q.get_chain_id().await,
+            Fetchers::Archive(q) => q.get_chain_id().await,
         }
     }
     async fn get_child_object(
@@ -236,6 +306,7 @@ impl DataFetcher for Fetchers {
         match self {
             Fetchers::Remote(q) => q.get_child_object(object_id, version_upper_bound).await,
             Fetchers::NodeStateDump(q) => q.get_child_object(object_id, version_upper_bound).await,
+            Fetchers::Archive(q) => q.get_child_object(object_id, version_upper_bound).await,
         }
     }
 }
@@ -492,12 +563,7 @@ impl DataFetcher for RemoteFetcher {
         let checkpoint_id_end = checkpoint_id_end_inclusive
             .unwrap_or(self.get_latest_checkpoint_sequence_number().await?);
         let checkpoint_id_start = checkpoint_id_start_inclusive.unwrap_or(1);
-        let checkpoint_id = rand::thread_rng().gen_range(checkpoint_id_start..=checkpoint_id_end);
-
-        let txs = self.get_checkpoint_txs(checkpoint_id).await?;
-        let tx_idx = rand::thread_rng().gen_range(0..txs.len());
-
-        Ok(txs[tx_idx])
+        random_transaction_in_checkpoints(self, checkpoint_id_start, checkpoint_id_end).await
     }
 
     async fn get_epoch_start_timestamp_and_rgp(
@@ -824,3 +890,702 @@ impl DataFetcher for NodeStateDumpFetcher {
         unimplemented!("get child object is not implemented for state dump");
     }
 }
+
+/// Number of checkpoints whose contents an `ArchiveFetcher` keeps in memory. Transactions are
+/// replayed checkpoint by checkpoint, so only the most recent ones are looked up again.
+const ARCHIVE_CHECKPOINT_CACHE_SIZE: usize = 16;
+
+#[derive(DBMapUtils)]
+struct ArchiveTables {
+    /// Most recent live version of each object. Objects are removed once deleted or wrapped
+    objects: DBMap<ObjectID, Object>,
+    /// Checkpoint of every transaction read from the archive so far
+    transaction_checkpoints: DBMap<TransactionDigest, CheckpointSequenceNumber>,
+}
+
+/// Objects known to an `ArchiveFetcher`. The pool is seeded from a state snapshot or the genesis
+/// transaction and then follows the outputs of every transaction replayed on top of it.
+/// Only the latest live version of each object is kept, in a temporary on-disk store, since
+/// transactions replayed in order never read older ones.
+struct ArchiveObjectPool {
+    tables: ArchiveTables,
+    /// Holds the store, which is removed along with the pool
+    _dir: TempDir,
+}
+
+impl ArchiveObjectPool {
+    fn new() -> Result<Self, ReplayEngineError> {
+        let dir = tempfile::tempdir()
+            .map_err(|e| ReplayEngineError::GeneralError { err: e.to_string() })?;
+        let tables = ArchiveTables::open_tables_read_write(
+            dir.path().to_path_buf(),
+            MetricConf::new("replay_archive"),
+            None,
+            None,
+        );
+        Ok(Self { tables, _dir: dir })
+    }
+
+    fn insert(&self, objects: impl IntoIterator<Item = Object>) -> Result<(), ReplayEngineError> {
+        self.update(objects, [])
+    }
+
+    /// Writes `written` and drops `removed` in a single batch
+    fn update(
+        &self,
+        written: impl IntoIterator<Item = Object>,
+        removed: impl IntoIterator<Item = ObjectID>,
+    ) -> Result<(), ReplayEngineError> {
+        let mut batch = self.tables.objects.batch();
+        batch.insert_batch(
+            &self.tables.objects,
+            written.into_iter().map(|object| (object.id(), object)),
+        )?;
+        batch.delete_batch(&self.tables.objects, removed)?;
+        batch.write()?;
+        Ok(())
+    }
+
+    fn get(&self, id: &ObjectID) -> Result<Option<Object>, ReplayEngineError> {
+        Ok(self.tables.objects.get(id)?)
+    }
+
+    fn get_versioned(
+        &self,
+        id: &ObjectID,
+        version: SequenceNumber,
+    ) -> Result<Option<Object>, ReplayEngineError> {
+        Ok(self.get(id)?.filter(|object| object.version() == version))
+    }
+
+    /// Latest version of a child object, as long as it is not newer than `version_upper_bound`
+    fn get_child_object(
+        &self,
+        id: &ObjectID,
+        version_upper_bound: VersionNumber,
+    ) -> Result<Option<Object>, ReplayEngineError> {
+        Ok(self
+            .get(id)?
+            .filter(|object| object.version() <= version_upper_bound))
+    }
+}
+
+impl ObjectStore for ArchiveObjectPool {
+    fn get_object(&self, object_id: &ObjectID) -> Option<Object> {
+        self.get(object_id)
+            .expect("Failed to read replay object store")
+    }
+
+    fn get_object_by_key(&self, object_id: &ObjectID, version: VersionNumber) -> Option<Object> {
+        self.get_versioned(object_id, version)
+            .expect("Failed to read replay object store")
+    }
+}
+
+/// Fetches everything needed for replay from a local checkpoint archive and an optional state
+/// snapshot, without talking to a full node.
+/// Archives only hold transactions and effects, so object contents come from the snapshot (or the
+/// genesis transaction when no snapshot is given) and from the outputs of transactions replayed
+/// since. Checkpoints must therefore be replayed in order starting at
+/// `first_replayable_checkpoint`.
+#[derive(Clone)]
+pub struct ArchiveFetcher {
+    archive_reader: ArchiveReader,
+    /// Derived from the digest of the genesis checkpoint in the archive
+    chain_id: String,
+    /// First checkpoint executed on top of the seeded object pool
+    first_replayable_checkpoint: u64,
+    object_pool: Arc<ArchiveObjectPool>,
+    /// Contents of the checkpoints read from the archive most recently
+    checkpoint_cache: Arc<Mutex<LruCache<CheckpointSequenceNumber, Arc<FullCheckpointContents>>>>,
+}
+
+impl ArchiveFetcher {
+    pub async fn new(
+        archive_path: PathBuf,
+        snapshot_path: Option<PathBuf>,
+    ) -> Result<Self, ReplayEngineError> {
+        let config = ArchiveReaderConfig {
+            remote_store_config: ObjectStoreConfig {
+                object_store: Some(ObjectStoreType::File),
+                directory: Some(archive_path),
+                ..Default::default()
+            },
+            download_concurrency: NonZeroUsize::new(1).unwrap(),
+            use_for_pruning_watermark: false,
+        };
+        let metrics = ArchiveReaderMetrics::new(&prometheus::Registry::new());
+        let archive_reader = ArchiveReader::new(config, &metrics)?;
+        archive_reader.sync_manifest_once().await?;
+
+        let (genesis_summary, genesis_contents) = archive_reader.get_checkpoint(0).await?;
+        let mut fetcher = Self::from_reader(
+            archive_reader,
+            ChainIdentifier::from(*genesis_summary.digest()).to_string(),
+        )?;
+        match snapshot_path {
+            Some(snapshot_path) => {
+                let epoch = fetcher.load_snapshot(snapshot_path).await?;
+                fetcher.first_replayable_checkpoint =
+                    fetcher.first_checkpoint_of_epoch(epoch + 1).await?;
+            }
+            None => fetcher.load_genesis(&genesis_contents)?,
+        }
+        info!(
+            "Replaying from archive starting at checkpoint {}",
+            fetcher.first_replayable_checkpoint
+        );
+        Ok(fetcher)
+    }
+
+    /// Fetcher with an empty object pool, replaying from checkpoint 0
+    fn from_reader(
+        archive_reader: ArchiveReader,
+        chain_id: String,
+    ) -> Result<Self, ReplayEngineError> {
+        Ok(Self {
+            archive_reader,
+            chain_id,
+            first_replayable_checkpoint: 0,
+            object_pool: Arc::new(ArchiveObjectPool::new()?),
+            checkpoint_cache: Arc::new(Mutex::new(LruCache::new(
+                NonZeroUsize::new(ARCHIVE_CHECKPOINT_CACHE_SIZE).unwrap(),
+            ))),
+        })
+    }
+
+    pub fn first_replayable_checkpoint(&self) -> u64 {
+        self.first_replayable_checkpoint
+    }
+
+    /// Contents of a checkpoint, read from the archive unless it was read recently
+    async fn get_checkpoint_contents(
+        &self,
+        checkpoint: CheckpointSequenceNumber,
+    ) -> Result<Arc<FullCheckpointContents>, ReplayEngineError> {
+        if let Some(contents) = self.checkpoint_cache.lock().get(&checkpoint) {
+            return Ok(contents.clone());
+        }
+        let (_, contents) = self.archive_reader.get_checkpoint(checkpoint).await?;
+        let contents = Arc::new(contents);
+        self.checkpoint_cache
+            .lock()
+            .put(checkpoint, contents.clone());
+        Ok(contents)
+    }
+
+    /// Transaction and on-chain effects of a transaction in a checkpoint already read from the
+    /// archive
+    pub async fn get_execution_data(
+        &self,
+        tx_digest: &TransactionDigest,
+    ) -> Result<ExecutionData, ReplayEngineError> {
+        let not_found = || ReplayEngineError::TransactionNotSupported {
+            digest: *tx_digest,
+            reason: "Transaction is not in any checkpoint read from the archive".to_string(),
+        };
+        let checkpoint = self
+            .object_pool
+            .tables
+            .transaction_checkpoints
+            .get(tx_digest)?
+            .ok_or_else(not_found)?;
+        self.get_checkpoint_contents(checkpoint)
+            .await?
+            .iter()
+            .find(|data| data.transaction.digest() == tx_digest)
+            .cloned()
+            .ok_or_else(not_found)
+    }
+
+    /// Add the objects written by a replayed transaction to the pool, and drop the ones its
+    /// on-chain effects deleted or wrapped
+    pub async fn record_transaction_outputs(
+        &self,
+        tx_digest: &TransactionDigest,
+        written: impl IntoIterator<Item = Object>,
+    ) -> Result<(), ReplayEngineError> {
+        let effects = self.get_execution_data(tx_digest).await?.effects;
+        self.object_pool.update(
+            written,
+            effects
+                .deleted()
+                .into_iter()
+                .chain(effects.wrapped())
+                .chain(effects.unwrapped_then_deleted())
+                .map(|(id, _, _)| id),
+        )
+    }
+
+    /// Latest versions of the system packages in the pool
+    pub fn system_package_versions(
+        &self,
+    ) -> Result<Vec<(ObjectID, SequenceNumber)>, ReplayEngineError> {
+        let ids = BuiltInFramework::all_package_ids();
+        let packages = self.object_pool.tables.objects.multi_get(&ids)?;
+        Ok(ids
+            .into_iter()
+            .zip(packages)
+            .filter_map(|(id, package)| Some((id, package?.version())))
+            .collect())
+    }
+
+    pub fn get_protocol_version(&self, epoch_id: u64) -> Result<u64, ReplayEngineError> {
+        Ok(self.get_system_state(epoch_id)?.protocol_version())
+    }
+
+    /// System state of the epoch the pool is currently at, which must be `epoch_id`
+    fn get_system_state(&self, epoch_id: u64) -> Result<MysSystemState, ReplayEngineError> {
+        let system_state = get_mys_system_state(&*self.object_pool)?;
+        if system_state.epoch() != epoch_id {
+            return Err(ReplayEngineError::ReplayedEpochMismatch {
+                epoch: epoch_id,
+                current: system_state.epoch(),
+            });
+        }
+        Ok(system_state)
+    }
+
+    /// Seed the pool with the objects created by the genesis transaction. The transaction itself
+    /// is never executed, so its objects get the versions and owners recorded in its effects.
+    fn load_genesis(&self, contents: &FullCheckpointContents) -> Result<(), ReplayEngineError> {
+        let ExecutionData {
+            transaction,
+            effects,
+        } = contents
+            .iter()
+            .find(|data| data.transaction.data().transaction_data().is_genesis_tx())
+            .ok_or_else(|| ReplayEngineError::GeneralError {
+                err: "Checkpoint 0 in the archive has no genesis transaction".to_string(),
+            })?;
+        let TransactionKind::Genesis(genesis) = transaction.data().transaction_data().kind() else {
+            unreachable!("Filtered on genesis transactions above");
+        };
+        let created: BTreeMap<_, _> = effects
+            .created()
+            .into_iter()
+            .map(|(obj_ref, owner)| (obj_ref.0, (obj_ref, owner)))
+            .collect();
+
+        let mut objects = Vec::with_capacity(genesis.objects.len());
+        for object in genesis.objects.iter().cloned() {
+            let GenesisObject::RawObject { mut data, .. } = object;
+            let (obj_ref, owner) = created.get(&data.id()).cloned().ok_or_else(|| {
+                ReplayEngineError::GeneralError {
+                    err: format!("Genesis object {} missing from genesis effects", data.id()),
+                }
+            })?;
+            if let Some(move_object) = data.try_as_move_mut() {
+                move_object.increment_version_to(obj_ref.1);
+            }
+            let object = Object::new_from_genesis(data, owner, *transaction.digest());
+            if object.compute_object_reference() != obj_ref {
+                return Err(ReplayEngineError::GeneralError {
+                    err: format!(
+                        "Genesis object {:?} does not match genesis effects {:?}",
+                        object.compute_object_reference(),
+                        obj_ref
+                    ),
+                });
+            }
+            objects.push(object);
+        }
+        self.object_pool.insert(objects)
+    }
+
+    /// Seed the pool with the live objects of the latest full snapshot under `snapshot_path`.
+    /// Returns the epoch of the snapshot.
+    async fn load_snapshot(&self, snapshot_path: PathBuf) -> Result<u64, ReplayEngineError> {
+        let epoch = std::fs::read_dir(&snapshot_path)
+            .map_err(|e| ReplayEngineError::GeneralError { err: e.to_string() })?
+            .filter_map(|entry| {
+                entry
+                    .ok()?
+                    .file_name()
+                    .to_str()?
+                    .strip_prefix("epoch_")?
+                    .parse::<u64>()
+                    .ok()
+            })
+            .max()
+            .ok_or_else(|| ReplayEngineError::GeneralError {
+                err: format!("No snapshot found in {}", snapshot_path.display()),
+            })?;
+        info!("Loading objects from snapshot of epoch {}", epoch);
+
+        // The reader stages manifest and reference files in a local directory before use
+        let staging_dir = tempfile::tempdir()
+            .map_err(|e| ReplayEngineError::GeneralError { err: e.to_string() })?;
+        let mut reader = StateSnapshotReaderV1::new(
+            epoch,
+            &ObjectStoreConfig {
+                object_store: Some(ObjectStoreType::File),
+                directory: Some(snapshot_path),
+                ..Default::default()
+            },
+            &ObjectStoreConfig {
+                object_store: Some(ObjectStoreType::File),
+                directory: Some(staging_dir.path().to_path_buf()),
+                ..Default::default()
+            },
+            usize::MAX,
+            NonZeroUsize::new(1).unwrap(),
+            MultiProgress::new(),
+            false,
+        )
+        .await?;
+        if reader.base_epoch().is_some() {
+            return Err(ReplayEngineError::GeneralError {
+                err: format!("Snapshot of epoch {epoch} is a delta, replay needs a full snapshot"),
+            });
+        }
+
+        let (sha3_digests, _) = reader.compute_checksum().await?;
+        let (object_files, epoch_dir, remote_object_store, _) = reader.export_metadata().await?;
+        for (bucket, (part_num, file_metadata)) in object_files {
+            let (bytes, sha3_digest) = download_bytes(
+                remote_object_store.clone(),
+                &file_metadata,
+                epoch_dir.clone(),
+                sha3_digests.clone(),
+                &bucket,
+                &part_num,
+                None,
+            )
+            .await;
+            let live_objects: Vec<_> = LiveObjectIter::new(&file_metadata, bytes)?.collect();
+            verify_live_objects_sha3(&live_objects, &sha3_digest)?;
+            self.object_pool
+                .insert(
+                    live_objects
+                        .into_iter()
+                        .filter_map(|live_object| match live_object {
+                            LiveObject::Normal(object) => Some(object),
+                            LiveObject::Wrapped(_) => None,
+                        }),
+                )?;
+        }
+        Ok(epoch)
+    }
+
+    /// Binary search the archive for the first checkpoint of `epoch_id`
+    async fn first_checkpoint_of_epoch(&self, epoch_id: u64) -> Result<u64, ReplayEngineError> {
+        let mut low = 0;
+        let mut high = self.archive_reader.latest_available_checkpoint().await?;
+        if self.archive_reader.get_checkpoint(high).await?.0.epoch < epoch_id {
+            return Err(ReplayEngineError::UnableToDetermineCheckpoint { epoch: epoch_id });
+        }
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if self.archive_reader.get_checkpoint(mid).await?.0.epoch < epoch_id {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        Ok(low)
+    }
+}
+
+#[async_trait]
+impl DataFetcher for ArchiveFetcher {
+    async fn multi_get_versioned(
+        &self,
+        objects: &[(ObjectID, SequenceNumber)],
+    ) -> Result<Vec<Object>, ReplayEngineError> {
+        objects
+            .iter()
+            .map(|(id, version)| {
+                self.object_pool.get_versioned(id, *version)?.ok_or(
+                    ReplayEngineError::ObjectVersionNotFound {
+                        id: *id,
+                        version: *version,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    async fn multi_get_latest(
+        &self,
+        objects: &[ObjectID],
+    ) -> Result<Vec<Object>, ReplayEngineError> {
+        objects
+            .iter()
+            .map(|id| {
+                self.object_pool
+                    .get(id)?
+                    .ok_or(ReplayEngineError::ObjectNotExist { id: *id })
+            })
+            .collect()
+    }
+
+    /// Reads the checkpoint from the archive and remembers where its transactions are for later
+    /// lookups. The genesis transaction is left out since its objects are seeded rather than
+    /// executed.
+    async fn get_checkpoint_txs(
+        &self,
+        id: u64,
+    ) -> Result<Vec<TransactionDigest>, ReplayEngineError> {
+        if id < self.first_replayable_checkpoint {
+            return Err(ReplayEngineError::CheckpointNotReplayable {
+                checkpoint: id,
+                first: self.first_replayable_checkpoint,
+            });
+        }
+        let contents = self.get_checkpoint_contents(id).await?;
+        let digests: Vec<_> = contents
+            .iter()
+            .filter(|data| !data.transaction.data().transaction_data().is_genesis_tx())
+            .map(|data| *data.transaction.digest())
+            .collect();
+        let transaction_checkpoints = &self.object_pool.tables.transaction_checkpoints;
+        let mut batch = transaction_checkpoints.batch();
+        batch.insert_batch(
+            transaction_checkpoints,
+            digests.iter().map(|digest| (*digest, id)),
+        )?;
+        batch.write()?;
+        Ok(digests)
+    }
+
+    async fn get_transaction(
+        &self,
+        _tx_digest: &TransactionDigest,
+    ) -> Result<MysTransactionBlockResponse, ReplayEngineError> {
+        Err(ReplayEngineError::UnsupportedByArchive {
+            operation: "Fetching JSON-RPC transaction responses".to_string(),
+        })
+    }
+
+    async fn get_loaded_child_objects(
+        &self,
+        _tx_digest: &TransactionDigest,
+    ) -> Result<Vec<(ObjectID, SequenceNumber)>, ReplayEngineError> {
+        // Child objects are read lazily through `get_child_object`
+        Ok(vec![])
+    }
+
+    async fn get_latest_checkpoint_sequence_number(&self) -> Result<u64, ReplayEngineError> {
+        Ok(self.archive_reader.latest_available_checkpoint().await?)
+    }
+
+    async fn fetch_random_transaction(
+        &self,
+        checkpoint_id_start_inclusive: Option<u64>,
+        checkpoint_id_end_inclusive: Option<u64>,
+    ) -> Result<TransactionDigest, ReplayEngineError> {
+        let checkpoint_id_end = checkpoint_id_end_inclusive
+            .unwrap_or(self.get_latest_checkpoint_sequence_number().await?);
+        let checkpoint_id_start = checkpoint_id_start_inclusive
+            .unwrap_or(self.first_replayable_checkpoint)
+            .max(self.first_replayable_checkpoint);
+        random_transaction_in_checkpoints(self, checkpoint_id_start, checkpoint_id_end).await
+    }
+
+    async fn get_epoch_start_timestamp_and_rgp(
+        &self,
+        epoch_id: u64,
+    ) -> Result<(u64, u64), ReplayEngineError> {
+        let system_state = self.get_system_state(epoch_id)?;
+        Ok((
+            system_state.epoch_start_timestamp_ms(),
+            system_state.reference_gas_price(),
+        ))
+    }
+
+    async fn get_epoch_change_events(
+        &self,
+        _reverse: bool,
+    ) -> Result<Vec<MysEvent>, ReplayEngineError> {
+        Err(ReplayEngineError::UnsupportedByArchive {
+            operation: "Querying epoch change events".to_string(),
+        })
+    }
+
+    async fn get_chain_id(&self) -> Result<String, ReplayEngineError> {
+        Ok(self.chain_id.clone())
+    }
+
+    async fn get_child_object(
+        &self,
+        object_id: &ObjectID,
+        version_upper_bound: VersionNumber,
+    ) -> Result<Object, ReplayEngineError> {
+        self.object_pool
+            .get_child_object(object_id, version_upper_bound)?
+            .ok_or(ReplayEngineError::ObjectNotExist { id: *object_id })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mys_types::full_checkpoint_content::CheckpointData;
+    use mys_types::object::Owner;
+    use mys_types::test_checkpoint_data_builder::TestCheckpointDataBuilder;
+
+    /// Fetcher whose checkpoints are served from its cache rather than from an archive
+    fn test_fetcher(archive_dir: &tempfile::TempDir) -> ArchiveFetcher {
+        let config = ArchiveReaderConfig {
+            remote_store_config: ObjectStoreConfig {
+                object_store: Some(ObjectStoreType::File),
+                directory: Some(archive_dir.path().to_path_buf()),
+                ..Default::default()
+            },
+            download_concurrency: NonZeroUsize::new(1).unwrap(),
+            use_for_pruning_watermark: false,
+        };
+        let metrics = ArchiveReaderMetrics::new(&prometheus::Registry::new());
+        let archive_reader = ArchiveReader::new(config, &metrics).unwrap();
+        ArchiveFetcher::from_reader(archive_reader, "test".to_string()).unwrap()
+    }
+
+    fn add_checkpoint(fetcher: &ArchiveFetcher, checkpoint: &CheckpointData) {
+        let contents = FullCheckpointContents::new_with_causally_ordered_transactions(
+            checkpoint
+                .transactions
+                .iter()
+                .map(|tx| ExecutionData::new(tx.transaction.clone(), tx.effects.clone())),
+        );
+        fetcher.checkpoint_cache.lock().put(
+            checkpoint.checkpoint_summary.sequence_number,
+            Arc::new(contents),
+        );
+    }
+
+    /// Replays the checkpoint by recording the outputs every transaction had on chain
+    async fn replay_checkpoint(fetcher: &ArchiveFetcher, checkpoint: &CheckpointData) {
+        add_checkpoint(fetcher, checkpoint);
+        let digests = fetcher
+            .get_checkpoint_txs(checkpoint.checkpoint_summary.sequence_number)
+            .await
+            .unwrap();
+        for (digest, tx) in digests.iter().zip(&checkpoint.transactions) {
+            fetcher
+                .record_transaction_outputs(digest, tx.output_objects.clone())
+                .await
+                .unwrap();
+        }
+    }
+
+    fn object_id(idx: u64) -> ObjectID {
+        TestCheckpointDataBuilder::derive_object_id(idx)
+    }
+
+    #[tokio::test]
+    async fn test_archive_fetcher_follows_transaction_outputs() {
+        let archive_dir = tempfile::tempdir().unwrap();
+        let fetcher = test_fetcher(&archive_dir);
+        let mut builder = TestCheckpointDataBuilder::new(0)
+            .start_transaction(0)
+            .create_owned_object(1)
+            .create_owned_object(2)
+            .create_owned_object(3)
+            .finish_transaction();
+        let checkpoint = builder.build_checkpoint();
+        replay_checkpoint(&fetcher, &checkpoint).await;
+        let created = fetcher.object_pool.get(&object_id(1)).unwrap().unwrap();
+
+        let mut builder = builder
+            .start_transaction(0)
+            .transfer_object(1, 1)
+            .wrap_object(2)
+            .delete_object(3)
+            .finish_transaction();
+        let checkpoint = builder.build_checkpoint();
+        replay_checkpoint(&fetcher, &checkpoint).await;
+
+        let latest = fetcher.multi_get_latest(&[object_id(1)]).await.unwrap();
+        assert_eq!(
+            latest[0].owner,
+            Owner::AddressOwner(TestCheckpointDataBuilder::derive_address(1))
+        );
+        assert!(latest[0].version() > created.version());
+        // Only the latest version is kept
+        assert!(fetcher
+            .multi_get_versioned(&[(object_id(1), created.version())])
+            .await
+            .is_err());
+        assert_eq!(
+            fetcher
+                .multi_get_versioned(&[(object_id(1), latest[0].version())])
+                .await
+                .unwrap(),
+            latest
+        );
+        for removed in [object_id(2), object_id(3)] {
+            assert!(fetcher.multi_get_latest(&[removed]).await.is_err());
+        }
+
+        // Child objects are looked up by their latest version not above the bound
+        assert!(fetcher
+            .get_child_object(&object_id(1), created.version())
+            .await
+            .is_err());
+        assert_eq!(
+            fetcher
+                .get_child_object(&object_id(1), SequenceNumber::MAX)
+                .await
+                .unwrap(),
+            latest[0]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_archive_fetcher_execution_data() {
+        let archive_dir = tempfile::tempdir().unwrap();
+        let fetcher = test_fetcher(&archive_dir);
+        let checkpoint = TestCheckpointDataBuilder::new(0)
+            .start_transaction(0)
+            .create_owned_object(1)
+            .finish_transaction()
+            .build_checkpoint();
+        let tx = &checkpoint.transactions[0];
+        let digest = *tx.transaction.digest();
+
+        // Transactions are only known once their checkpoint has been read
+        add_checkpoint(&fetcher, &checkpoint);
+        assert!(fetcher.get_execution_data(&digest).await.is_err());
+        assert!(fetcher
+            .record_transaction_outputs(&digest, tx.output_objects.clone())
+            .await
+            .is_err());
+
+        assert_eq!(fetcher.get_checkpoint_txs(0).await.unwrap(), vec![digest]);
+        let data = fetcher.get_execution_data(&digest).await.unwrap();
+        assert_eq!(data.effects, tx.effects);
+    }
+
+    #[tokio::test]
+    async fn test_archive_fetcher_random_transaction_skips_empty_checkpoints() {
+        let archive_dir = tempfile::tempdir().unwrap();
+        let fetcher = test_fetcher(&archive_dir);
+        let mut builder = TestCheckpointDataBuilder::new(0);
+        for _ in 0..3 {
+            let checkpoint = builder.build_checkpoint();
+            add_checkpoint(&fetcher, &checkpoint);
+        }
+        let checkpoint = builder
+            .start_transaction(0)
+            .create_owned_object(1)
+            .finish_transaction()
+            .build_checkpoint();
+        add_checkpoint(&fetcher, &checkpoint);
+        let digest = *checkpoint.transactions[0].transaction.digest();
+
+        for _ in 0..10 {
+            assert_eq!(
+                fetcher
+                    .fetch_random_transaction(Some(0), Some(3))
+                    .await
+                    .unwrap(),
+                digest
+            );
+        }
+        assert!(matches!(
+            fetcher.fetch_random_transaction(Some(0), Some(2)).await,
+            Err(ReplayEngineError::NoTransactionsInCheckpoints { start: 0, end: 2 })
+        ));
+    }
+}
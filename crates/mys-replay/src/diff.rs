@@ -0,0 +1,311 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Structured comparison of two local executions of the same transaction, used by the
+//! differential replay mode to find where an execution version or protocol config override
+//! changes behaviour.
+
+use crate::replay::ExecutionSandboxState;
+use crate::types::ReplayEngineError;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use mys_json_rpc_types::{
+    MysExecutionStatus, MysObjectRef, MysTransactionBlockEffects, MysTransactionBlockEffectsAPI,
+    OwnedObjectRef,
+};
+use mys_types::base_types::ObjectID;
+use mys_types::digests::{TransactionDigest, TransactionEventsDigest};
+use mys_types::event::Event;
+use mys_types::gas::GasCostSummary;
+use mys_types::object::Object;
+
+/// A value that differs between the baseline and the candidate execution
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValueDivergence<T> {
+    pub baseline: T,
+    pub candidate: T,
+}
+
+impl<T: PartialEq + Clone> ValueDivergence<T> {
+    fn compare(baseline: &T, candidate: &T) -> Option<Self> {
+        (baseline != candidate).then(|| Self {
+            baseline: baseline.clone(),
+            candidate: candidate.clone(),
+        })
+    }
+}
+
+/// Entries of an effects list that only one of the executions produced
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SetDivergence<T> {
+    pub only_in_baseline: Vec<T>,
+    pub only_in_candidate: Vec<T>,
+}
+
+impl<T: PartialEq + Clone> SetDivergence<T> {
+    fn compare(baseline: &[T], candidate: &[T]) -> Option<Self> {
+        let only_in_baseline: Vec<_> = baseline
+            .iter()
+            .filter(|x| !candidate.contains(x))
+            .cloned()
+            .collect();
+        let only_in_candidate: Vec<_> = candidate
+            .iter()
+            .filter(|x| !baseline.contains(x))
+            .cloned()
+            .collect();
+        (!only_in_baseline.is_empty() || !only_in_candidate.is_empty()).then_some(Self {
+            only_in_baseline,
+            only_in_candidate,
+        })
+    }
+}
+
+/// Differences between the effects of two executions, gas excluded. Fields that match are omitted.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EffectsDivergence {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<ValueDivergence<MysExecutionStatus>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<SetDivergence<OwnedObjectRef>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mutated: Option<SetDivergence<OwnedObjectRef>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unwrapped: Option<SetDivergence<OwnedObjectRef>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted: Option<SetDivergence<MysObjectRef>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unwrapped_then_deleted: Option<SetDivergence<MysObjectRef>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wrapped: Option<SetDivergence<MysObjectRef>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shared_objects: Option<SetDivergence<MysObjectRef>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gas_object: Option<ValueDivergence<OwnedObjectRef>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dependencies: Option<SetDivergence<TransactionDigest>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub events_digest: Option<ValueDivergence<Option<TransactionEventsDigest>>>,
+}
+
+impl EffectsDivergence {
+    pub fn compare(
+        baseline: &MysTransactionBlockEffects,
+        candidate: &MysTransactionBlockEffects,
+    ) -> Self {
+        Self {
+            status: ValueDivergence::compare(baseline.status(), candidate.status()),
+            created: SetDivergence::compare(baseline.created(), candidate.created()),
+            mutated: SetDivergence::compare(baseline.mutated(), candidate.mutated()),
+            unwrapped: SetDivergence::compare(baseline.unwrapped(), candidate.unwrapped()),
+            deleted: SetDivergence::compare(baseline.deleted(), candidate.deleted()),
+            unwrapped_then_deleted: SetDivergence::compare(
+                baseline.unwrapped_then_deleted(),
+                candidate.unwrapped_then_deleted(),
+            ),
+            wrapped: SetDivergence::compare(baseline.wrapped(), candidate.wrapped()),
+            shared_objects: SetDivergence::compare(
+                baseline.shared_objects(),
+                candidate.shared_objects(),
+            ),
+            gas_object: ValueDivergence::compare(baseline.gas_object(), candidate.gas_object()),
+            dependencies: SetDivergence::compare(baseline.dependencies(), candidate.dependencies()),
+            events_digest: ValueDivergence::compare(
+                &baseline.events_digest().copied(),
+                &candidate.events_digest().copied(),
+            ),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// An event emitted at `index` that differs between the executions. A missing side means that
+/// execution emitted fewer events.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventDivergence {
+    pub index: usize,
+    pub baseline: Option<Event>,
+    pub candidate: Option<Event>,
+}
+
+pub fn diff_events(baseline: &[Event], candidate: &[Event]) -> Vec<EventDivergence> {
+    (0..baseline.len().max(candidate.len()))
+        .filter_map(|index| {
+            let (b, c) = (baseline.get(index), candidate.get(index));
+            (b != c).then(|| EventDivergence {
+                index,
+                baseline: b.cloned(),
+                candidate: c.cloned(),
+            })
+        })
+        .collect()
+}
+
+/// An object written with different contents by the executions. A missing side means that
+/// execution did not write the object.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ObjectDivergence {
+    pub id: ObjectID,
+    pub baseline: Option<Object>,
+    pub candidate: Option<Object>,
+}
+
+pub fn diff_written_objects(
+    baseline: &BTreeMap<ObjectID, Object>,
+    candidate: &BTreeMap<ObjectID, Object>,
+) -> Vec<ObjectDivergence> {
+    let ids: BTreeSet<_> = baseline.keys().chain(candidate.keys()).collect();
+    ids.into_iter()
+        .filter_map(|id| {
+            let (b, c) = (baseline.get(id), candidate.get(id));
+            (b != c).then(|| ObjectDivergence {
+                id: *id,
+                baseline: b.cloned(),
+                candidate: c.cloned(),
+            })
+        })
+        .collect()
+}
+
+/// Everything that differs between the baseline execution of a transaction, which uses the
+/// versions the transaction originally ran with, and the candidate execution under the overrides
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionDivergence {
+    pub tx_digest: TransactionDigest,
+    pub checkpoint: u64,
+    /// Whether the baseline reproduced the on-chain effects. If not, the divergences below may
+    /// not be caused by the overrides alone.
+    pub baseline_matches_on_chain: bool,
+    /// Set when the candidate execution could not be run at all
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub candidate_error: Option<String>,
+    #[serde(default, skip_serializing_if = "EffectsDivergence::is_empty")]
+    pub effects: EffectsDivergence,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gas: Option<ValueDivergence<GasCostSummary>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<EventDivergence>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub objects: Vec<ObjectDivergence>,
+}
+
+impl TransactionDivergence {
+    pub fn new(
+        checkpoint: u64,
+        baseline: &ExecutionSandboxState,
+        candidate: Result<ExecutionSandboxState, ReplayEngineError>,
+    ) -> Self {
+        let mut divergence = Self {
+            tx_digest: baseline.transaction_info.tx_digest,
+            checkpoint,
+            baseline_matches_on_chain: baseline.transaction_info.effects
+                == baseline.local_exec_effects,
+            candidate_error: None,
+            effects: EffectsDivergence::default(),
+            gas: None,
+            events: vec![],
+            objects: vec![],
+        };
+        let candidate = match candidate {
+            Ok(candidate) => candidate,
+            Err(e) => {
+                divergence.candidate_error = Some(e.to_string());
+                return divergence;
+            }
+        };
+
+        divergence.effects =
+            EffectsDivergence::compare(&baseline.local_exec_effects, &candidate.local_exec_effects);
+        divergence.gas = ValueDivergence::compare(
+            baseline.local_exec_effects.gas_cost_summary(),
+            candidate.local_exec_effects.gas_cost_summary(),
+        );
+        if let (Some(b), Some(c)) = (
+            &baseline.local_exec_temporary_store,
+            &candidate.local_exec_temporary_store,
+        ) {
+            divergence.events = diff_events(&b.events.data, &c.events.data);
+            divergence.objects = diff_written_objects(&b.written, &c.written);
+        }
+        divergence
+    }
+
+    /// True if both executions produced the same results
+    pub fn is_empty(&self) -> bool {
+        self.candidate_error.is_none()
+            && self.effects.is_empty()
+            && self.gas.is_none()
+            && self.events.is_empty()
+            && self.objects.is_empty()
+    }
+}
+
+/// Outcome of a differential replay over a range of checkpoints
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DifferentialReport {
+    /// Transactions executed under both the baseline and the overrides
+    pub executed: u64,
+    /// Transactions whose baseline execution failed, and which were therefore not compared
+    pub failed: u64,
+    pub divergences: Vec<TransactionDivergence>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use move_core_types::identifier::Identifier;
+    use move_core_types::language_storage::StructTag;
+    use std::str::FromStr;
+    use mys_types::base_types::{MysAddress, SequenceNumber};
+    use mys_types::object::Owner;
+
+    fn event(contents: Vec<u8>) -> Event {
+        Event {
+            package_id: ObjectID::ZERO,
+            transaction_module: Identifier::new("m").unwrap(),
+            sender: MysAddress::ZERO,
+            type_: StructTag::from_str("0x2::m::E").unwrap(),
+            contents,
+        }
+    }
+
+    #[test]
+    fn test_diff_events() {
+        let baseline = vec![event(vec![1]), event(vec![2])];
+        let candidate = vec![event(vec![1]), event(vec![3]), event(vec![4])];
+
+        let diff = diff_events(&baseline, &candidate);
+        assert_eq!(diff.len(), 2);
+        assert_eq!(diff[0].index, 1);
+        assert_eq!(diff[0].baseline, Some(event(vec![2])));
+        assert_eq!(diff[0].candidate, Some(event(vec![3])));
+        assert_eq!(diff[1].index, 2);
+        assert_eq!(diff[1].baseline, None);
+
+        assert!(diff_events(&baseline, &baseline).is_empty());
+    }
+
+    #[test]
+    fn test_diff_written_objects() {
+        let (a, b, c) = (ObjectID::random(), ObjectID::random(), ObjectID::random());
+        let object = |id, version| {
+            Object::with_id_owner_version_for_testing(
+                id,
+                SequenceNumber::from_u64(version),
+                Owner::AddressOwner(MysAddress::ZERO),
+            )
+        };
+        let baseline = BTreeMap::from([(a, object(a, 2)), (b, object(b, 2))]);
+        let candidate = BTreeMap::from([(a, object(a, 2)), (b, object(b, 3)), (c, object(c, 2))]);
+
+        let diff = diff_written_objects(&baseline, &candidate);
+        let ids: BTreeSet<_> = diff.iter().map(|d| d.id).collect();
+        assert_eq!(ids, BTreeSet::from([b, c]));
+        let only_candidate = diff.iter().find(|d| d.id == c).unwrap();
+        assert!(only_candidate.baseline.is_none());
+        assert!(only_candidate.candidate.is_some());
+    }
+}
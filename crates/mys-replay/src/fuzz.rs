@@ -1,15 +1,18 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+use futures::FutureExt;
+use std::panic::AssertUnwindSafe;
 use mys_config::node::ExpensiveSafetyCheckConfig;
 use mys_types::{
     digests::TransactionDigest, execution_status::ExecutionFailureStatus,
     transaction::TransactionKind,
 };
 use thiserror::Error;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 use crate::{
+    fuzz_corpus::{FuzzCorpus, FuzzOutcome},
     replay::{ExecutionSandboxState, LocalExec},
     transaction_provider::{TransactionProvider, TransactionSource},
     types::ReplayEngineError,
@@ -41,6 +44,12 @@ pub trait TransactionKindMutator {
     fn mutate(&mut self, transaction_kind: &TransactionKind) -> Option<TransactionKind>;
 
     fn reset(&mut self, mutations_per_base: u64);
+
+    /// Whether the last mutation is expected to make the transaction fail, so that its success
+    /// points at a bug
+    fn expects_failure(&self) -> bool {
+        false
+    }
 }
 
 impl ReplayFuzzer {
@@ -134,6 +143,33 @@ impl ReplayFuzzer {
         Ok(sandbox_state)
     }
 
+    /// Executes a mutation and classifies how it behaved, including panics during execution
+    pub async fn execute_tx_and_classify(
+        &mut self,
+        transaction_kind: &TransactionKind,
+        expects_failure: bool,
+    ) -> Result<FuzzOutcome, ReplayFuzzError> {
+        match AssertUnwindSafe(self.execute_tx(transaction_kind))
+            .catch_unwind()
+            .await
+        {
+            Ok(sandbox_state) => Ok(FuzzOutcome::from_sandbox_state(
+                &sandbox_state?,
+                expects_failure,
+            )),
+            Err(payload) => {
+                self.local_exec.storage.clear_poison();
+                self.local_exec.exec_store_events.clear_poison();
+                let message = payload
+                    .downcast_ref::<&str>()
+                    .map(|s| s.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "non-string panic payload".to_string());
+                Ok(FuzzOutcome::Panic { message })
+            }
+        }
+    }
+
     // Simple command and arg shuffle mutation
     // TODO: do more complicated mutations
     pub fn next_mutation(&mut self, transaction_kind: &TransactionKind) -> Option<TransactionKind> {
@@ -181,6 +217,76 @@ impl ReplayFuzzer {
 
         Ok(())
     }
+
+    /// Like `run`, but also mutates the interesting mutations found so far for each base
+    /// transaction, and records every outcome in `corpus`, which writes a deduplicated artifact for
+    /// each new invariant violation, panic or success of a mutation expected to fail
+    pub async fn run_with_corpus(
+        mut self,
+        mut num_base_tx: u64,
+        corpus: &mut FuzzCorpus,
+    ) -> Result<(), ReplayFuzzError> {
+        while num_base_tx > 0 {
+            let base_tx_digest = self.sandbox_state.transaction_info.tx_digest;
+            let base_kind = self.sandbox_state.transaction_info.kind.clone();
+
+            info!(
+                "Starting fuzz with new base TX {}, with at most {} mutations",
+                base_tx_digest, self.config.num_mutations_per_base
+            );
+            loop {
+                let seed = corpus.next_seed(&base_tx_digest, &base_kind);
+                let Some(mutation) = self.next_mutation(&seed) else {
+                    break;
+                };
+                if mutation == base_kind {
+                    continue;
+                }
+                let expects_failure = self.config.mutator.expects_failure();
+                let outcome = match self
+                    .execute_tx_and_classify(&mutation, expects_failure)
+                    .await
+                {
+                    Ok(outcome) => outcome,
+                    Err(e) => {
+                        error!(
+                            "Error executing transaction: base tx: {}, mutation: {:?} with error{:?}",
+                            base_tx_digest, mutation, e
+                        );
+                        if self.config.fail_over_on_err {
+                            return Err(e);
+                        }
+                        continue;
+                    }
+                };
+                match corpus.record(base_tx_digest, &mutation, &outcome) {
+                    Ok(Some(path)) => warn!(
+                        "New {} for base tx {}: {:?}. Replay with `tx --tx-digest {} --fuzz-artifact {}`",
+                        outcome.class(),
+                        base_tx_digest,
+                        outcome,
+                        base_tx_digest,
+                        path.display()
+                    ),
+                    Ok(None) => (),
+                    Err(e) => error!("Failed to record fuzz outcome: {:?}", e),
+                }
+            }
+            info!(
+                "Ended fuzz with for base TX {}: {}\n",
+                base_tx_digest,
+                corpus.summary()
+            );
+            self = self
+                .re_init()
+                .await
+                .map_err(ReplayEngineError::from)
+                .map_err(ReplayFuzzError::from)?;
+            num_base_tx -= 1;
+        }
+
+        Ok(())
+    }
 }
 
 #[allow(clippy::large_enum_variant)]
@@ -0,0 +1,487 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Corpus and crash artifact bookkeeping for long-running fuzz sessions.
+//!
+//! Every executed mutation is classified into a `FuzzOutcome`. Mutations whose outcome has not
+//! been seen before are kept in the corpus and mutated further, and outcomes which point at a bug
+//! are written to disk once per distinct outcome so that they can be replayed with `tx
+//! --fuzz-artifact`.
+
+use fastcrypto::encoding::{Encoding, Hex};
+use fastcrypto::hash::{Blake2b256, HashFunction};
+use rand::{seq::SliceRandom, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use mys_json_rpc_types::MysTransactionBlockEffectsAPI;
+use mys_types::digests::TransactionDigest;
+use mys_types::execution_status::{CommandIndex, ExecutionFailureStatus, MoveLocationOpt};
+use mys_types::transaction::TransactionKind;
+
+use crate::replay::ExecutionSandboxState;
+
+const CORPUS_DIR: &str = "corpus";
+const CRASHES_DIR: &str = "crashes";
+
+/// How a mutated transaction behaved when executed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FuzzOutcome {
+    /// Execution failed with an ordinary, user facing error
+    Failure {
+        status: ExecutionFailureStatus,
+        command: Option<CommandIndex>,
+    },
+    /// Execution succeeded
+    Success {
+        created: usize,
+        mutated: usize,
+        deleted: usize,
+        events: usize,
+    },
+    /// Execution succeeded although the mutation was expected to make it fail
+    UnexpectedSuccess {
+        created: usize,
+        mutated: usize,
+        deleted: usize,
+        events: usize,
+    },
+    /// Execution hit an invariant violation in the adapter or the VM
+    InvariantViolation {
+        status: ExecutionFailureStatus,
+        command: Option<CommandIndex>,
+    },
+    /// Execution panicked
+    Panic { message: String },
+}
+
+impl FuzzOutcome {
+    /// Classifies an execution. `expects_failure` tells whether the executed mutation was
+    /// expected to fail, which makes a success a crash.
+    pub fn from_sandbox_state(
+        sandbox_state: &ExecutionSandboxState,
+        expects_failure: bool,
+    ) -> Self {
+        match &sandbox_state.local_exec_status {
+            Some(Err(e)) => {
+                let (status, command) = e.to_execution_status();
+                match status {
+                    ExecutionFailureStatus::InvariantViolation
+                    | ExecutionFailureStatus::VMInvariantViolation => {
+                        Self::InvariantViolation { status, command }
+                    }
+                    _ => Self::Failure { status, command },
+                }
+            }
+            _ => {
+                let effects = &sandbox_state.local_exec_effects;
+                let (created, mutated, deleted) = (
+                    effects.created().len(),
+                    effects.mutated().len(),
+                    effects.deleted().len(),
+                );
+                let events = sandbox_state
+                    .local_exec_temporary_store
+                    .as_ref()
+                    .map_or(0, |store| store.events.data.len());
+                if expects_failure {
+                    Self::UnexpectedSuccess {
+                        created,
+                        mutated,
+                        deleted,
+                        events,
+                    }
+                } else {
+                    Self::Success {
+                        created,
+                        mutated,
+                        deleted,
+                        events,
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn class(&self) -> &'static str {
+        match self {
+            Self::Failure { .. } => "failure",
+            Self::Success { .. } => "success",
+            Self::UnexpectedSuccess { .. } => "unexpected-success",
+            Self::InvariantViolation { .. } => "invariant-violation",
+            Self::Panic { .. } => "panic",
+        }
+    }
+
+    /// Whether the outcome deserves a crash artifact
+    pub fn is_crash(&self) -> bool {
+        !matches!(self, Self::Failure { .. } | Self::Success { .. })
+    }
+
+    /// Identifies the outcome of a mutation of `base_tx_digest` for deduplication. Failures are
+    /// identified by the kind of error, the Move location it was raised at and the command,
+    /// regardless of the values in the error or of which mutation caused them. Successes only
+    /// compare equal for the same base transaction.
+    fn fingerprint(&self, base_tx_digest: &TransactionDigest) -> String {
+        let key = match self {
+            Self::Failure { status, command } | Self::InvariantViolation { status, command } => {
+                failure_key(status, command)
+            }
+            Self::Success {
+                created,
+                mutated,
+                deleted,
+                events,
+            }
+            | Self::UnexpectedSuccess {
+                created,
+                mutated,
+                deleted,
+                events,
+            } => format!("{base_tx_digest} {created} {mutated} {deleted} {events}"),
+            Self::Panic { message } => normalize_message(message),
+        };
+        let digest = Blake2b256::digest(key.as_bytes());
+        format!("{}-{}", self.class(), &Hex::encode(digest.digest)[..16])
+    }
+}
+
+/// The name of the variant of `status`, the Move location it was raised at if any and the command
+fn failure_key(status: &ExecutionFailureStatus, command: &Option<CommandIndex>) -> String {
+    let debug = format!("{status:?}");
+    let variant = debug
+        .split(|c: char| !c.is_alphanumeric())
+        .next()
+        .unwrap_or_default();
+    let location = match status {
+        ExecutionFailureStatus::MoveAbort(location, _)
+        | ExecutionFailureStatus::MovePrimitiveRuntimeError(MoveLocationOpt(Some(location))) => {
+            format!(
+                "{}::{}@{}",
+                location.module, location.function, location.instruction
+            )
+        }
+        _ => String::new(),
+    };
+    format!("{variant} {location} {command:?}")
+}
+
+/// Replaces the numbers, addresses and other words with digits in a panic message, which mostly
+/// depend on the input rather than on where the panic happened
+fn normalize_message(message: &str) -> String {
+    let mut normalized = String::with_capacity(message.len());
+    let mut word = String::new();
+    for c in message.chars().chain(std::iter::once(' ')) {
+        if c.is_alphanumeric() || c == '_' {
+            word.push(c);
+            continue;
+        }
+        if word.chars().any(|c| c.is_ascii_digit()) {
+            normalized.push('#');
+        } else {
+            normalized.push_str(&word);
+        }
+        word.clear();
+        normalized.push(c);
+    }
+    normalized.pop();
+    normalized
+}
+
+/// A mutated transaction together with the base transaction it must be executed against
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FuzzArtifact {
+    pub base_tx_digest: TransactionDigest,
+    pub kind: TransactionKind,
+    pub outcome: FuzzOutcome,
+}
+
+impl FuzzArtifact {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    fn write(&self, path: &Path) -> anyhow::Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Interesting mutations and crash artifacts of a fuzz session, persisted under a directory so
+/// that later sessions continue where earlier ones stopped
+pub struct FuzzCorpus {
+    dir: PathBuf,
+    max_entries_per_base: usize,
+    rng: rand::rngs::StdRng,
+    /// Fingerprints of all outcomes observed so far
+    seen: HashSet<String>,
+    /// Fingerprints of the outcomes a crash artifact was written for
+    crashes: HashSet<String>,
+    entries: BTreeMap<TransactionDigest, Vec<TransactionKind>>,
+    outcome_counts: BTreeMap<&'static str, u64>,
+}
+
+impl FuzzCorpus {
+    pub fn open(dir: PathBuf, max_entries_per_base: usize) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(dir.join(CORPUS_DIR))?;
+        std::fs::create_dir_all(dir.join(CRASHES_DIR))?;
+
+        let mut corpus = Self {
+            dir,
+            max_entries_per_base,
+            rng: rand::rngs::StdRng::from_seed([0u8; 32]),
+            seen: HashSet::new(),
+            crashes: HashSet::new(),
+            entries: BTreeMap::new(),
+            outcome_counts: BTreeMap::new(),
+        };
+        for entry in std::fs::read_dir(corpus.dir.join(CORPUS_DIR))? {
+            let path = entry?.path();
+            let artifact = FuzzArtifact::load(&path)?;
+            corpus
+                .seen
+                .insert(artifact.outcome.fingerprint(&artifact.base_tx_digest));
+            corpus
+                .entries
+                .entry(artifact.base_tx_digest)
+                .or_default()
+                .push(artifact.kind);
+        }
+        for entry in std::fs::read_dir(corpus.dir.join(CRASHES_DIR))? {
+            if let Some(stem) = entry?.path().file_stem().and_then(|s| s.to_str()) {
+                corpus.crashes.insert(stem.to_owned());
+                corpus.seen.insert(stem.to_owned());
+            }
+        }
+        Ok(corpus)
+    }
+
+    /// Picks the transaction to mutate next: either the base transaction itself or one of the
+    /// interesting mutations of it found so far
+    pub fn next_seed(
+        &mut self,
+        base_tx_digest: &TransactionDigest,
+        base_kind: &TransactionKind,
+    ) -> TransactionKind {
+        let mut candidates = vec![base_kind];
+        if let Some(entries) = self.entries.get(base_tx_digest) {
+            candidates.extend(entries);
+        }
+        (*candidates.choose(&mut self.rng).unwrap()).clone()
+    }
+
+    /// Records the outcome of executing a mutation. Returns the path of the crash artifact if the
+    /// outcome is a crash that has not been seen before.
+    pub fn record(
+        &mut self,
+        base_tx_digest: TransactionDigest,
+        kind: &TransactionKind,
+        outcome: &FuzzOutcome,
+    ) -> anyhow::Result<Option<PathBuf>> {
+        *self.outcome_counts.entry(outcome.class()).or_default() += 1;
+        let fingerprint = outcome.fingerprint(&base_tx_digest);
+        if !self.seen.insert(fingerprint.clone()) {
+            return Ok(None);
+        }
+
+        let artifact = FuzzArtifact {
+            base_tx_digest,
+            kind: kind.clone(),
+            outcome: outcome.clone(),
+        };
+        let entries = self.entries.entry(base_tx_digest).or_default();
+        if entries.len() < self.max_entries_per_base {
+            artifact.write(
+                &self
+                    .dir
+                    .join(CORPUS_DIR)
+                    .join(format!("{fingerprint}.json")),
+            )?;
+            entries.push(kind.clone());
+        }
+
+        if !outcome.is_crash() {
+            return Ok(None);
+        }
+        let path = self
+            .dir
+            .join(CRASHES_DIR)
+            .join(format!("{fingerprint}.json"));
+        artifact.write(&path)?;
+        self.crashes.insert(fingerprint);
+        Ok(Some(path))
+    }
+
+    pub fn num_crashes(&self) -> usize {
+        self.crashes.len()
+    }
+
+    pub fn summary(&self) -> String {
+        let counts: Vec<_> = self
+            .outcome_counts
+            .iter()
+            .map(|(class, count)| format!("{class}: {count}"))
+            .collect();
+        format!(
+            "{} distinct outcomes, {} crash artifacts ({})",
+            self.seen.len(),
+            self.crashes.len(),
+            counts.join(", ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use move_core_types::account_address::AccountAddress;
+    use move_core_types::identifier::Identifier;
+    use move_core_types::language_storage::ModuleId;
+    use mys_types::execution_status::MoveLocation;
+    use mys_types::transaction::ProgrammableTransaction;
+
+    fn move_abort(instruction: u16, code: u64, command: CommandIndex) -> FuzzOutcome {
+        let location = MoveLocation {
+            module: ModuleId::new(AccountAddress::ONE, Identifier::new("m").unwrap()),
+            function: 0,
+            instruction,
+            function_name: Some("f".to_string()),
+        };
+        FuzzOutcome::Failure {
+            status: ExecutionFailureStatus::MoveAbort(location, code),
+            command: Some(command),
+        }
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let base = TransactionDigest::random();
+
+        // Failures are identified by their kind, location and command, not by the values in them
+        assert_eq!(
+            move_abort(1, 0, 0).fingerprint(&base),
+            move_abort(1, 7, 0).fingerprint(&base)
+        );
+        assert_ne!(
+            move_abort(1, 0, 0).fingerprint(&base),
+            move_abort(2, 0, 0).fingerprint(&base)
+        );
+        assert_ne!(
+            move_abort(1, 0, 0).fingerprint(&base),
+            move_abort(1, 0, 1).fingerprint(&base)
+        );
+        let too_big = |object_size| FuzzOutcome::Failure {
+            status: ExecutionFailureStatus::MoveObjectTooBig {
+                object_size,
+                max_object_size: 100,
+            },
+            command: None,
+        };
+        assert_eq!(
+            too_big(200).fingerprint(&base),
+            too_big(300).fingerprint(&base)
+        );
+
+        let panic = |message: &str| FuzzOutcome::Panic {
+            message: message.to_string(),
+        };
+        assert_eq!(
+            panic("index out of bounds: the len is 3 but the index is 5").fingerprint(&base),
+            panic("index out of bounds: the len is 0 but the index is 12").fingerprint(&base)
+        );
+        assert_ne!(
+            panic("index out of bounds").fingerprint(&base),
+            panic("attempt to add with overflow").fingerprint(&base)
+        );
+
+        // Successes are only compared within a base transaction
+        let success = FuzzOutcome::UnexpectedSuccess {
+            created: 1,
+            mutated: 2,
+            deleted: 0,
+            events: 0,
+        };
+        assert_eq!(success.fingerprint(&base), success.fingerprint(&base));
+        assert_ne!(
+            success.fingerprint(&base),
+            success.fingerprint(&TransactionDigest::random())
+        );
+    }
+
+    #[test]
+    fn test_normalize_message() {
+        assert_eq!(
+            normalize_message("object 0x2a at version 17 not found"),
+            "object # at version # not found"
+        );
+        assert_eq!(normalize_message("no digits here"), "no digits here");
+    }
+
+    #[test]
+    fn test_unexpected_successes_are_crashes() {
+        let dir = tempfile::tempdir().unwrap();
+        let kind = TransactionKind::ProgrammableTransaction(ProgrammableTransaction {
+            inputs: vec![],
+            commands: vec![],
+        });
+        let success = FuzzOutcome::Success {
+            created: 1,
+            mutated: 1,
+            deleted: 0,
+            events: 0,
+        };
+        let unexpected_success = FuzzOutcome::UnexpectedSuccess {
+            created: 1,
+            mutated: 1,
+            deleted: 0,
+            events: 0,
+        };
+
+        let mut corpus = FuzzCorpus::open(dir.path().to_path_buf(), 8).unwrap();
+        let base = TransactionDigest::random();
+        assert!(corpus.record(base, &kind, &success).unwrap().is_none());
+        assert!(corpus
+            .record(base, &kind, &unexpected_success)
+            .unwrap()
+            .is_some());
+        // The same effects on another base transaction are a different outcome
+        assert!(corpus
+            .record(TransactionDigest::random(), &kind, &unexpected_success)
+            .unwrap()
+            .is_some());
+        assert_eq!(corpus.num_crashes(), 2);
+    }
+
+    #[test]
+    fn test_crash_artifacts_are_deduplicated() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = TransactionDigest::random();
+        let kind = TransactionKind::ProgrammableTransaction(ProgrammableTransaction {
+            inputs: vec![],
+            commands: vec![],
+        });
+        let panic = FuzzOutcome::Panic {
+            message: "boom".to_string(),
+        };
+        let failure = FuzzOutcome::Failure {
+            status: ExecutionFailureStatus::InsufficientGas,
+            command: None,
+        };
+
+        let mut corpus = FuzzCorpus::open(dir.path().to_path_buf(), 8).unwrap();
+        assert!(corpus.record(base, &kind, &failure).unwrap().is_none());
+        let path = corpus.record(base, &kind, &panic).unwrap().unwrap();
+        assert!(corpus.record(base, &kind, &panic).unwrap().is_none());
+        assert_eq!(corpus.num_crashes(), 1);
+
+        let artifact = FuzzArtifact::load(&path).unwrap();
+        assert_eq!(artifact.base_tx_digest, base);
+        assert_eq!(artifact.outcome, panic);
+
+        // A new session picks up the crashes and corpus written by the previous one
+        let mut corpus = FuzzCorpus::open(dir.path().to_path_buf(), 8).unwrap();
+        assert!(corpus.record(base, &kind, &panic).unwrap().is_none());
+        assert_eq!(corpus.num_crashes(), 1);
+        assert_eq!(corpus.entries[&base].len(), 2);
+    }
+}
@@ -1,7 +1,7 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use rand::{seq::SliceRandom, SeedableRng};
+use rand::{Rng, SeedableRng};
 use mys_types::transaction::TransactionKind;
 
 use crate::fuzz::TransactionKindMutator;
@@ -25,10 +25,14 @@ pub struct RandomMutator {
     pub rng: rand::rngs::StdRng,
     pub mutators: Vec<Box<dyn TransactionKindMutator + Send + Sync>>,
     pub num_tries: u64,
+    /// The mutator that made the last mutation
+    pub last_mutator: Option<usize>,
 }
 
 pub struct ChainedMutator {
     pub mutators: Vec<Box<dyn TransactionKindMutator>>,
+    /// Whether any of the mutators applied in the last mutation expects it to fail
+    pub expects_failure: bool,
 }
 
 impl RandomMutator {
@@ -37,6 +41,7 @@ impl RandomMutator {
             rng: rand::rngs::StdRng::from_seed([0u8; 32]),
             mutators: vec![],
             num_tries: NUM_TRIES,
+            last_mutator: None,
         }
     }
 
@@ -45,7 +50,12 @@ impl RandomMutator {
     }
 
     pub fn select_mutator(&mut self) -> Option<&mut Box<dyn TransactionKindMutator + Send + Sync>> {
-        self.mutators.choose_mut(&mut self.rng)
+        if self.mutators.is_empty() {
+            return None;
+        }
+        let index = self.rng.gen_range(0..self.mutators.len());
+        self.last_mutator = Some(index);
+        self.mutators.get_mut(index)
     }
 }
 
@@ -70,11 +80,19 @@ impl TransactionKindMutator for RandomMutator {
             mutator.reset(mutations_per_base);
         }
     }
+
+    fn expects_failure(&self) -> bool {
+        self.last_mutator
+            .is_some_and(|index| self.mutators[index].expects_failure())
+    }
 }
 
 impl ChainedMutator {
     pub fn new() -> Self {
-        Self { mutators: vec![] }
+        Self {
+            mutators: vec![],
+            expects_failure: false,
+        }
     }
 
     pub fn add_mutator(&mut self, mutator: Box<dyn TransactionKindMutator>) {
@@ -92,11 +110,13 @@ impl TransactionKindMutator for ChainedMutator {
     fn mutate(&mut self, transaction_kind: &TransactionKind) -> Option<TransactionKind> {
         let mut mutated = transaction_kind.clone();
         let mut num_mutations = 0;
+        self.expects_failure = false;
 
         for mutator in self.mutators.iter_mut() {
             if let Some(new_mutated) = mutator.mutate(&mutated) {
                 num_mutations += 1;
                 mutated = new_mutated;
+                self.expects_failure |= mutator.expects_failure();
             }
         }
 
@@ -112,6 +132,10 @@ impl TransactionKindMutator for ChainedMutator {
             mutator.reset(mutations_per_base);
         }
     }
+
+    fn expects_failure(&self) -> bool {
+        self.expects_failure
+    }
 }
 
 pub fn base_fuzzers(num_mutations: u64) -> RandomMutator {
@@ -123,6 +147,7 @@ pub fn base_fuzzers(num_mutations: u64) -> RandomMutator {
     mutator.add_mutator(Box::new(shuffle_types::ShuffleTypes {
         rng: rand::rngs::StdRng::from_seed([0u8; 32]),
         num_mutations_per_base_left: num_mutations,
+        shuffled_types: false,
     }));
     mutator.add_mutator(Box::new(shuffle_command_inputs::ShuffleCommandInputs {
         rng: rand::rngs::StdRng::from_seed([0u8; 32]),
@@ -9,12 +9,16 @@ use tracing::info;
 pub struct ShuffleTypes {
     pub rng: rand::rngs::StdRng,
     pub num_mutations_per_base_left: u64,
+    /// Whether the last mutation swapped distinct type arguments of a call
+    pub shuffled_types: bool,
 }
 
 impl ShuffleTypes {
     fn shuffle_command(&mut self, command: &mut Command) {
         if let Command::MoveCall(ref mut pt) = command {
-            pt.type_arguments.shuffle(&mut self.rng)
+            let type_arguments = pt.type_arguments.clone();
+            pt.type_arguments.shuffle(&mut self.rng);
+            self.shuffled_types |= pt.type_arguments != type_arguments;
         }
     }
 }
@@ -27,6 +31,7 @@ impl TransactionKindMutator for ShuffleTypes {
         }
 
         self.num_mutations_per_base_left -= 1;
+        self.shuffled_types = false;
         if let TransactionKind::ProgrammableTransaction(mut p) = transaction_kind.clone() {
             for command in &mut p.commands {
                 self.shuffle_command(command);
@@ -42,4 +47,9 @@ impl TransactionKindMutator for ShuffleTypes {
     fn reset(&mut self, mutations_per_base: u64) {
         self.num_mutations_per_base_left = mutations_per_base;
     }
+
+    // Calls with swapped type arguments are expected to fail to type check
+    fn expects_failure(&self) -> bool {
+        self.shuffled_types
+    }
 }
@@ -0,0 +1,480 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Machine readable breakdown of where the gas of a transaction goes, built from a local
+//! execution and the speedscope profile the gas profiler writes for it.
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use mys_json_rpc_types::MysTransactionBlockEffectsAPI;
+use mys_protocol_config::ProtocolConfig;
+use mys_types::base_types::{ObjectID, SequenceNumber};
+use mys_types::digests::TransactionDigest;
+use mys_types::gas::GasCostSummary;
+
+use crate::replay::ExecutionSandboxState;
+
+const DYNAMIC_FIELD_MODULE: &str = "::dynamic_field::";
+const DYNAMIC_FIELD_NATIVES: &[&str] = &[
+    "hash_type_and_key",
+    "add_child_object",
+    "borrow_child_object",
+    "borrow_child_object_mut",
+    "remove_child_object",
+    "has_child_object",
+    "has_child_object_with_ty",
+];
+const BASIS_POINTS: u128 = 10000;
+
+/// Computation units charged while executing a Move function
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FunctionGas {
+    pub function: String,
+    pub calls: u64,
+    /// Units charged in the function and everything it called. Recursive calls are only
+    /// counted once.
+    pub inclusive: u64,
+    /// Units charged in the function itself
+    pub exclusive: u64,
+}
+
+/// Storage charges attributed to a single object
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ObjectStorageGas {
+    pub id: ObjectID,
+    /// Charged for the bytes of the object as written by the transaction
+    pub storage_cost: u64,
+    /// Refunded for the previous version of the object. Rounded per object, so the sum may be off
+    /// from the transaction summary by a few units.
+    pub storage_rebate: u64,
+    pub non_refundable_storage_fee: u64,
+}
+
+/// A child object loaded through a dynamic field during execution
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LoadedChildObject {
+    pub id: ObjectID,
+    pub version: SequenceNumber,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DynamicFieldGas {
+    /// Calls to the dynamic field natives and the units they were charged
+    pub accesses: Vec<FunctionGas>,
+    pub loaded_objects: Vec<LoadedChildObject>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GasReport {
+    pub tx_digest: TransactionDigest,
+    pub summary: GasCostSummary,
+    /// Sorted by exclusive units, most expensive first. Empty if no profile was available.
+    pub functions: Vec<FunctionGas>,
+    pub objects: Vec<ObjectStorageGas>,
+    pub dynamic_fields: DynamicFieldGas,
+}
+
+impl GasReport {
+    /// Builds the report for a local execution. `profile` is the speedscope file the gas profiler
+    /// wrote for it, if any.
+    pub fn new(
+        sandbox_state: &ExecutionSandboxState,
+        profile: Option<&Path>,
+    ) -> anyhow::Result<Self> {
+        let tx_info = &sandbox_state.transaction_info;
+        let effects = &sandbox_state.local_exec_effects;
+
+        let functions = match profile {
+            Some(path) => {
+                let contents = std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read gas profile {}", path.display()))?;
+                function_gas_from_speedscope(&serde_json::from_str(&contents)?)
+            }
+            None => vec![],
+        };
+        let accesses = functions
+            .iter()
+            .filter(|f| is_dynamic_field_access(&f.function))
+            .cloned()
+            .collect();
+
+        let mut objects = vec![];
+        let mut loaded_objects = vec![];
+        if let Some(store) = &sandbox_state.local_exec_temporary_store {
+            if !tx_info.kind.is_system_tx() {
+                let rebate_rate =
+                    ProtocolConfig::get_for_version(tx_info.protocol_version, tx_info.chain)
+                        .storage_rebate_rate();
+                let removed = effects
+                    .deleted()
+                    .iter()
+                    .chain(effects.wrapped())
+                    .chain(effects.unwrapped_then_deleted())
+                    .map(|o| o.object_id);
+                let touched: BTreeSet<_> = store.written.keys().copied().chain(removed).collect();
+                for id in touched {
+                    let storage_cost = store.written.get(&id).map_or(0, |o| o.storage_rebate);
+                    let previous_rebate = store
+                        .input_objects
+                        .get(&id)
+                        .map(|o| o.storage_rebate)
+                        .or_else(|| {
+                            store
+                                .loaded_runtime_objects
+                                .get(&id)
+                                .map(|o| o.storage_rebate)
+                        })
+                        .unwrap_or(0);
+                    let storage_rebate = (((previous_rebate as u128 * rebate_rate as u128)
+                        + BASIS_POINTS / 2)
+                        / BASIS_POINTS) as u64;
+                    objects.push(ObjectStorageGas {
+                        id,
+                        storage_cost,
+                        storage_rebate,
+                        non_refundable_storage_fee: previous_rebate - storage_rebate,
+                    });
+                }
+            }
+            loaded_objects = store
+                .loaded_runtime_objects
+                .iter()
+                .map(|(id, metadata)| LoadedChildObject {
+                    id: *id,
+                    version: metadata.version,
+                })
+                .collect();
+        }
+
+        Ok(Self {
+            tx_digest: tx_info.tx_digest,
+            summary: effects.gas_cost_summary().clone(),
+            functions,
+            objects,
+            dynamic_fields: DynamicFieldGas {
+                accesses,
+                loaded_objects,
+            },
+        })
+    }
+}
+
+fn is_dynamic_field_access(function: &str) -> bool {
+    function
+        .split_once(DYNAMIC_FIELD_MODULE)
+        .is_some_and(|(_, name)| DYNAMIC_FIELD_NATIVES.contains(&name))
+}
+
+#[derive(Deserialize)]
+struct SpeedscopeFile {
+    shared: SpeedscopeShared,
+    profiles: Vec<SpeedscopeProfile>,
+}
+
+#[derive(Deserialize)]
+struct SpeedscopeShared {
+    frames: Vec<SpeedscopeFrame>,
+}
+
+#[derive(Deserialize)]
+struct SpeedscopeFrame {
+    /// The profiler stores the fully qualified function name here
+    file: String,
+}
+
+#[derive(Deserialize)]
+struct SpeedscopeProfile {
+    events: Vec<SpeedscopeEvent>,
+}
+
+#[derive(Deserialize)]
+struct SpeedscopeEvent {
+    #[serde(rename = "type")]
+    ty: String,
+    frame: usize,
+    at: u64,
+}
+
+/// Aggregates the open and close frame events of a gas profile into per function totals
+fn function_gas_from_speedscope(file: &SpeedscopeFile) -> Vec<FunctionGas> {
+    let mut totals: BTreeMap<usize, FunctionGas> = BTreeMap::new();
+    for profile in &file.profiles {
+        // (frame, units used when it was opened, inclusive units of its callees)
+        let mut stack: Vec<(usize, u64, u64)> = vec![];
+        for event in &profile.events {
+            match event.ty.as_str() {
+                "O" => stack.push((event.frame, event.at, 0)),
+                "C" => {
+                    let Some((frame, opened_at, callees)) = stack.pop() else {
+                        continue;
+                    };
+                    let inclusive = event.at.saturating_sub(opened_at);
+                    let total = totals.entry(frame).or_default();
+                    total.calls += 1;
+                    total.exclusive += inclusive.saturating_sub(callees);
+                    if !stack.iter().any(|(f, _, _)| *f == frame) {
+                        total.inclusive += inclusive;
+                    }
+                    if let Some((_, _, parent_callees)) = stack.last_mut() {
+                        *parent_callees += inclusive;
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    // The profiler wraps the whole transaction in a frame of its own
+    let mut functions: Vec<_> = totals
+        .into_iter()
+        .filter_map(|(frame, total)| {
+            let function = file.shared.frames.get(frame)?.file.clone();
+            (function != "root").then_some(FunctionGas { function, ..total })
+        })
+        .collect();
+    functions.sort_by(|a, b| {
+        b.exclusive
+            .cmp(&a.exclusive)
+            .then_with(|| a.function.cmp(&b.function))
+    });
+    functions
+}
+
+/// A value in the baseline and candidate report, and how much it changed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Delta {
+    pub baseline: u64,
+    pub candidate: u64,
+    pub change: i128,
+}
+
+impl Delta {
+    fn new(baseline: u64, candidate: u64) -> Self {
+        Self {
+            baseline,
+            candidate,
+            change: candidate as i128 - baseline as i128,
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.change == 0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SummaryDiff {
+    pub computation_cost: Delta,
+    pub storage_cost: Delta,
+    pub storage_rebate: Delta,
+    pub non_refundable_storage_fee: Delta,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FunctionGasDiff {
+    pub function: String,
+    pub calls: Delta,
+    pub inclusive: Delta,
+    pub exclusive: Delta,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ObjectStorageGasDiff {
+    pub id: ObjectID,
+    pub storage_cost: Delta,
+    pub storage_rebate: Delta,
+    pub non_refundable_storage_fee: Delta,
+}
+
+/// Differences between two gas reports, usually of the same transaction executed under different
+/// code or versions. Only entries that changed are listed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GasReportDiff {
+    pub baseline_tx_digest: TransactionDigest,
+    pub candidate_tx_digest: TransactionDigest,
+    pub summary: SummaryDiff,
+    /// Sorted by the change in exclusive units, largest first
+    pub functions: Vec<FunctionGasDiff>,
+    pub objects: Vec<ObjectStorageGasDiff>,
+    pub dynamic_field_accesses: Vec<FunctionGasDiff>,
+}
+
+impl GasReportDiff {
+    pub fn new(baseline: &GasReport, candidate: &GasReport) -> Self {
+        let (b, c) = (&baseline.summary, &candidate.summary);
+        Self {
+            baseline_tx_digest: baseline.tx_digest,
+            candidate_tx_digest: candidate.tx_digest,
+            summary: SummaryDiff {
+                computation_cost: Delta::new(b.computation_cost, c.computation_cost),
+                storage_cost: Delta::new(b.storage_cost, c.storage_cost),
+                storage_rebate: Delta::new(b.storage_rebate, c.storage_rebate),
+                non_refundable_storage_fee: Delta::new(
+                    b.non_refundable_storage_fee,
+                    c.non_refundable_storage_fee,
+                ),
+            },
+            functions: diff_functions(&baseline.functions, &candidate.functions),
+            objects: diff_objects(&baseline.objects, &candidate.objects),
+            dynamic_field_accesses: diff_functions(
+                &baseline.dynamic_fields.accesses,
+                &candidate.dynamic_fields.accesses,
+            ),
+        }
+    }
+}
+
+fn diff_functions(baseline: &[FunctionGas], candidate: &[FunctionGas]) -> Vec<FunctionGasDiff> {
+    let by_name = |functions: &[FunctionGas]| -> BTreeMap<String, FunctionGas> {
+        functions
+            .iter()
+            .map(|f| (f.function.clone(), f.clone()))
+            .collect()
+    };
+    let (baseline, candidate) = (by_name(baseline), by_name(candidate));
+    let names: BTreeSet<_> = baseline.keys().chain(candidate.keys()).collect();
+
+    let mut diffs: Vec<_> = names
+        .into_iter()
+        .filter_map(|name| {
+            let b = baseline.get(name).cloned().unwrap_or_default();
+            let c = candidate.get(name).cloned().unwrap_or_default();
+            let diff = FunctionGasDiff {
+                function: name.clone(),
+                calls: Delta::new(b.calls, c.calls),
+                inclusive: Delta::new(b.inclusive, c.inclusive),
+                exclusive: Delta::new(b.exclusive, c.exclusive),
+            };
+            (!diff.calls.is_zero() || !diff.inclusive.is_zero() || !diff.exclusive.is_zero())
+                .then_some(diff)
+        })
+        .collect();
+    diffs.sort_by(|a, b| {
+        b.exclusive
+            .change
+            .abs()
+            .cmp(&a.exclusive.change.abs())
+            .then_with(|| a.function.cmp(&b.function))
+    });
+    diffs
+}
+
+fn diff_objects(
+    baseline: &[ObjectStorageGas],
+    candidate: &[ObjectStorageGas],
+) -> Vec<ObjectStorageGasDiff> {
+    let by_id = |objects: &[ObjectStorageGas]| -> BTreeMap<ObjectID, (u64, u64, u64)> {
+        objects
+            .iter()
+            .map(|o| {
+                (
+                    o.id,
+                    (
+                        o.storage_cost,
+                        o.storage_rebate,
+                        o.non_refundable_storage_fee,
+                    ),
+                )
+            })
+            .collect()
+    };
+    let (baseline, candidate) = (by_id(baseline), by_id(candidate));
+    let ids: BTreeSet<_> = baseline.keys().chain(candidate.keys()).collect();
+
+    ids.into_iter()
+        .filter_map(|id| {
+            let b = baseline.get(id).copied().unwrap_or_default();
+            let c = candidate.get(id).copied().unwrap_or_default();
+            (b != c).then(|| ObjectStorageGasDiff {
+                id: *id,
+                storage_cost: Delta::new(b.0, c.0),
+                storage_rebate: Delta::new(b.1, c.1),
+                non_refundable_storage_fee: Delta::new(b.2, c.2),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(frames: &[&str], events: &[(&str, usize, u64)]) -> SpeedscopeFile {
+        SpeedscopeFile {
+            shared: SpeedscopeShared {
+                frames: frames
+                    .iter()
+                    .map(|f| SpeedscopeFrame {
+                        file: f.to_string(),
+                    })
+                    .collect(),
+            },
+            profiles: vec![SpeedscopeProfile {
+                events: events
+                    .iter()
+                    .map(|(ty, frame, at)| SpeedscopeEvent {
+                        ty: ty.to_string(),
+                        frame: *frame,
+                        at: *at,
+                    })
+                    .collect(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_function_gas_from_speedscope() {
+        let borrow = "0x0000000000000000000000000000000000000000000000000000000000000002::dynamic_field::borrow_child_object";
+        let file = profile(
+            &["root", "0x1::m::f", "0x1::m::g", borrow],
+            &[
+                ("O", 0, 0),
+                ("O", 1, 10),
+                ("O", 2, 20),
+                ("O", 2, 25),
+                ("C", 2, 35),
+                ("C", 2, 40),
+                ("O", 3, 50),
+                ("C", 3, 80),
+                ("C", 1, 100),
+                ("C", 0, 110),
+            ],
+        );
+        let functions = function_gas_from_speedscope(&file);
+        let by_name: BTreeMap<_, _> = functions.iter().map(|f| (f.function.as_str(), f)).collect();
+
+        assert!(!by_name.contains_key("root"));
+        assert_eq!(by_name["0x1::m::f"].inclusive, 90);
+        assert_eq!(by_name["0x1::m::f"].exclusive, 40);
+        // The recursive call is counted in `calls` but not twice in `inclusive`
+        assert_eq!(by_name["0x1::m::g"].calls, 2);
+        assert_eq!(by_name["0x1::m::g"].inclusive, 20);
+        assert_eq!(by_name["0x1::m::g"].exclusive, 20);
+        assert_eq!(by_name[borrow].exclusive, 30);
+        assert_eq!(functions[0].function, "0x1::m::f");
+
+        assert!(is_dynamic_field_access(borrow));
+        assert!(!is_dynamic_field_access("0x1::m::borrow_child_object"));
+    }
+
+    #[test]
+    fn test_diff_functions_only_lists_changes() {
+        let f = |function: &str, exclusive| FunctionGas {
+            function: function.to_string(),
+            calls: 1,
+            inclusive: exclusive,
+            exclusive,
+        };
+        let baseline = vec![f("a", 10), f("b", 5)];
+        let candidate = vec![f("a", 10), f("b", 8), f("c", 1)];
+
+        let diff = diff_functions(&baseline, &candidate);
+        assert_eq!(diff.len(), 2);
+        assert_eq!(diff[0].function, "b");
+        assert_eq!(diff[0].exclusive.change, 3);
+        assert_eq!(diff[1].function, "c");
+        assert_eq!(diff[1].calls, Delta::new(0, 1));
+    }
+}
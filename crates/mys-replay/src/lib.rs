@@ -6,7 +6,9 @@ use clap::Parser;
 use config::ReplayableNetworkConfigSet;
 use fuzz::ReplayFuzzer;
 use fuzz::ReplayFuzzerConfig;
+use fuzz_corpus::{FuzzArtifact, FuzzCorpus, FuzzOutcome};
 use fuzz_mutations::base_fuzzers;
+use gas_report::{GasReport, GasReportDiff};
 use std::cmp::max;
 use mys_types::base_types::ObjectID;
 use mys_types::base_types::SequenceNumber;
@@ -22,7 +24,7 @@ use crate::replay::LocalExec;
 use crate::replay::ProtocolVersionSummary;
 use move_vm_config::runtime::get_default_output_filepath;
 use std::env;
-use std::io::BufRead;
+use std::io::{BufRead, Write};
 use std::path::PathBuf;
 use std::str::FromStr;
 use mys_config::node::ExpensiveSafetyCheckConfig;
@@ -33,9 +35,12 @@ use tracing::{error, info};
 pub mod batch_replay;
 pub mod config;
 mod data_fetcher;
+pub mod diff;
 mod displays;
 pub mod fuzz;
+pub mod fuzz_corpus;
 pub mod fuzz_mutations;
+pub mod gas_report;
 mod replay;
 #[cfg(test)]
 mod tests;
@@ -88,6 +93,20 @@ pub enum ReplayToolCommand {
         /// denied.
         #[arg(long, num_args = 2..)]
         config_objects: Option<Vec<String>>,
+        /// Optional filepath to write a JSON gas report to, breaking down computation per Move
+        /// function, storage cost and rebate per object, and dynamic field accesses
+        #[arg(long)]
+        gas_report: Option<PathBuf>,
+    },
+
+    /// Compare two gas reports written by `rp --gas-report`
+    #[command(name = "gas-diff")]
+    GasReportDiff {
+        baseline: PathBuf,
+        candidate: PathBuf,
+        /// File to write the JSON diff to. Defaults to stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
     },
 
     /// Replay transaction
@@ -108,6 +127,10 @@ pub enum ReplayToolCommand {
         /// denied.
         #[arg(long, num_args = 2..)]
         config_objects: Option<Vec<String>>,
+        /// Replay the mutation of the transaction recorded in this fuzz artifact instead of the
+        /// transaction itself
+        #[arg(long)]
+        fuzz_artifact: Option<PathBuf>,
     },
 
     /// Replay transactions listed in a file
@@ -130,6 +153,13 @@ pub enum ReplayToolCommand {
             This will allow faster replay next time."
         )]
         persist_path: Option<PathBuf>,
+        /// Replay offline from the checkpoint archive in this directory instead of over RPC
+        #[arg(long)]
+        archive_path: Option<PathBuf>,
+        /// Directory of state snapshots to seed the archive replay from. The latest full snapshot
+        /// is used; without one the replay starts at genesis
+        #[arg(long, requires = "archive_path")]
+        snapshot_path: Option<PathBuf>,
     },
 
     /// Replay a transaction from a node state dump
@@ -169,6 +199,42 @@ pub enum ReplayToolCommand {
         terminate_early: bool,
         #[arg(long, short, default_value = "16")]
         max_tasks: u64,
+        /// Replay offline from the checkpoint archive in this directory instead of over RPC.
+        /// Checkpoints are then replayed sequentially
+        #[arg(long)]
+        archive_path: Option<PathBuf>,
+        /// Directory of state snapshots to seed the archive replay from. The latest full snapshot
+        /// is used; without one the replay starts at genesis
+        #[arg(long, requires = "archive_path")]
+        snapshot_path: Option<PathBuf>,
+    },
+
+    /// Re-execute all transactions in a range of checkpoints under an execution version or
+    /// protocol version override and report every divergence from the original execution
+    #[command(name = "diff")]
+    ReplayDiff {
+        #[arg(long, short)]
+        start: u64,
+        #[arg(long, short)]
+        end: u64,
+        /// Version of the executor to compare against the one originally used for each
+        /// transaction. -1 selects the latest version
+        #[arg(long, allow_hyphen_values = true)]
+        executor_version: Option<i64>,
+        /// Protocol version to compare against the one originally used for each transaction.
+        /// -1 selects the max supported version
+        #[arg(long, allow_hyphen_values = true)]
+        protocol_version: Option<i64>,
+        /// File to write the divergences to, one JSON object per line. Defaults to stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+        /// Replay offline from the checkpoint archive in this directory instead of over RPC
+        #[arg(long)]
+        archive_path: Option<PathBuf>,
+        /// Directory of state snapshots to seed the archive replay from. The latest full snapshot
+        /// is used; without one the replay starts at genesis
+        #[arg(long, requires = "archive_path")]
+        snapshot_path: Option<PathBuf>,
     },
 
     /// Replay all transactions in an epoch
@@ -193,6 +259,24 @@ pub enum ReplayToolCommand {
         num_base_transactions: u64,
     },
 
+    /// Run the replay based fuzzer continuously, keeping a corpus of interesting mutations and
+    /// writing deduplicated crash artifacts to disk
+    #[command(name = "fzc")]
+    FuzzCorpus {
+        #[arg(long, short)]
+        start: Option<FuzzStartPoint>,
+        #[arg(long, short)]
+        num_mutations_per_base: u64,
+        #[arg(long, short = 'b', default_value = "18446744073709551614")]
+        num_base_transactions: u64,
+        /// Directory holding the corpus and crash artifacts. Reusing it resumes an earlier session
+        #[arg(long, short, default_value = "fuzz")]
+        output_dir: PathBuf,
+        /// Maximum number of interesting mutations kept per base transaction
+        #[arg(long, default_value = "64")]
+        max_corpus_entries: usize,
+    },
+
     #[command(name = "report")]
     Report,
 }
@@ -270,6 +354,28 @@ pub async fn execute_replay_command(
             fuzzer.run(num_base_transactions).await.unwrap();
             None
         }
+        ReplayToolCommand::FuzzCorpus {
+            start,
+            num_mutations_per_base,
+            num_base_transactions,
+            output_dir,
+            max_corpus_entries,
+        } => {
+            let mut corpus = FuzzCorpus::open(output_dir, max_corpus_entries)?;
+            let config = ReplayFuzzerConfig {
+                num_mutations_per_base,
+                mutator: Box::new(base_fuzzers(num_mutations_per_base)),
+                tx_source: TransactionSource::TailLatest { start },
+                fail_over_on_err: false,
+                expensive_safety_check_config: Default::default(),
+            };
+            let fuzzer = ReplayFuzzer::new(get_rpc_url(rpc_url, cfg_path, chain)?, config).await?;
+            fuzzer
+                .run_with_corpus(num_base_transactions, &mut corpus)
+                .await?;
+            info!("Fuzzing finished: {}", corpus.summary());
+            None
+        }
         ReplayToolCommand::ReplayDump { path, show_effects } => {
             let mut lx = LocalExec::new_for_state_dump(&path, rpc_url).await?;
             let (sandbox_state, node_dump_state) = lx.execute_state_dump(safety).await?;
@@ -296,6 +402,8 @@ pub async fn execute_replay_command(
             terminate_early,
             num_tasks,
             persist_path,
+            archive_path,
+            snapshot_path,
         } => {
             let file = std::fs::File::open(path).unwrap();
             let buf_reader = std::io::BufReader::new(file);
@@ -305,16 +413,31 @@ pub async fn execute_replay_command(
                     panic!("Error parsing tx digest {:?}: {:?}", line, err);
                 })
             });
-            batch_replay::batch_replay(
-                digests,
-                num_tasks,
-                get_rpc_url(rpc_url, cfg_path, chain)?,
-                safety,
-                use_authority,
-                terminate_early,
-                persist_path,
-            )
-            .await;
+            match archive_path {
+                Some(archive_path) => {
+                    batch_replay::batch_replay_from_archive(
+                        digests,
+                        archive_path,
+                        snapshot_path,
+                        safety,
+                        use_authority,
+                        persist_path,
+                    )
+                    .await?
+                }
+                None => {
+                    batch_replay::batch_replay(
+                        digests,
+                        num_tasks,
+                        get_rpc_url(rpc_url, cfg_path, chain)?,
+                        safety,
+                        use_authority,
+                        terminate_early,
+                        persist_path,
+                    )
+                    .await
+                }
+            }
 
             // TODO: clean this up
             Some((0u64, 0u64))
@@ -356,33 +479,125 @@ pub async fn execute_replay_command(
             protocol_version,
             profile_output,
             config_objects,
+            gas_report,
         } => {
-            let output_path = profile_output.or(Some(get_default_output_filepath()));
+            let output_path = profile_output.unwrap_or_else(get_default_output_filepath);
+            // The profiler appends the digest and a timestamp to the file name, so have it write
+            // into an empty directory where the report can find it
+            let profile_dir = gas_report
+                .as_ref()
+                .map(|_| tempfile::tempdir())
+                .transpose()?;
+            let profiler_path = match &profile_dir {
+                Some(dir) => dir.path().join(output_path.file_name().unwrap_or_default()),
+                None => output_path.clone(),
+            };
 
             let tx_digest = TransactionDigest::from_str(&tx_digest)?;
             info!("Executing tx: {}", tx_digest);
-            let _sandbox_state = LocalExec::replay_with_network_config(
+            let sandbox_state = LocalExec::replay_with_network_config(
                 get_rpc_url(rpc_url, cfg_path, chain)?,
                 tx_digest,
                 safety,
                 use_authority,
                 executor_version,
                 protocol_version,
-                output_path,
+                Some(profiler_path),
                 parse_configs_versions(config_objects),
             )
             .await?;
 
+            if let (Some(report_path), Some(profile_dir)) = (gas_report, profile_dir) {
+                let profile = std::fs::read_dir(profile_dir.path())?
+                    .next()
+                    .transpose()?
+                    .map(|entry| entry.path());
+                if profile.is_none() {
+                    warn!("No gas profile was written, the report will not break down computation per function. Profiling requires the `tracing` feature");
+                }
+                let report = GasReport::new(&sandbox_state, profile.as_deref())?;
+                std::fs::write(&report_path, serde_json::to_string_pretty(&report)?)?;
+                info!("Gas report written to file: {}", report_path.display());
+
+                // Keep the flamegraph profile where it would have been written otherwise
+                if let Some(profile) = profile {
+                    let dir = output_path
+                        .parent()
+                        .filter(|p| !p.as_os_str().is_empty())
+                        .unwrap_or(std::path::Path::new("."));
+                    std::fs::copy(&profile, dir.join(profile.file_name().unwrap_or_default()))?;
+                }
+            }
+
             println!("Execution finished successfully.");
             Some((1u64, 1u64))
         }
 
+        ReplayToolCommand::GasReportDiff {
+            baseline,
+            candidate,
+            output,
+        } => {
+            let read = |path: &PathBuf| -> anyhow::Result<GasReport> {
+                Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+            };
+            let diff = GasReportDiff::new(&read(&baseline)?, &read(&candidate)?);
+            let json = serde_json::to_string_pretty(&diff)?;
+            match output {
+                Some(path) => std::fs::write(path, json)?,
+                None => println!("{}", json),
+            }
+            None
+        }
+
+        ReplayToolCommand::ReplayTransaction {
+            tx_digest,
+            show_effects,
+            executor_version,
+            protocol_version,
+            config_objects: _,
+            fuzz_artifact: Some(fuzz_artifact),
+        } => {
+            let tx_digest = TransactionDigest::from_str(&tx_digest)?;
+            let artifact = FuzzArtifact::load(&fuzz_artifact)?;
+            if artifact.base_tx_digest != tx_digest {
+                anyhow::bail!(
+                    "Fuzz artifact {} was recorded for base transaction {}",
+                    fuzz_artifact.display(),
+                    artifact.base_tx_digest
+                );
+            }
+            info!("Executing mutation of tx: {}", tx_digest);
+            let sandbox_state = LocalExec::replay_fuzz_artifact(
+                get_rpc_url(rpc_url, cfg_path, chain)?,
+                &artifact,
+                safety,
+                executor_version,
+                protocol_version,
+            )
+            .await?;
+
+            if show_effects {
+                println!("{}", sandbox_state.local_exec_effects);
+            }
+
+            println!("Recorded outcome: {:?}", artifact.outcome);
+            println!(
+                "Replayed outcome: {:?}",
+                FuzzOutcome::from_sandbox_state(
+                    &sandbox_state,
+                    matches!(artifact.outcome, FuzzOutcome::UnexpectedSuccess { .. })
+                )
+            );
+            Some((1u64, 1u64))
+        }
         ReplayToolCommand::ReplayTransaction {
             tx_digest,
             show_effects,
             executor_version,
             protocol_version,
             config_objects,
+            fuzz_artifact: None,
         } => {
             let tx_digest = TransactionDigest::from_str(&tx_digest)?;
             info!("Executing tx: {}", tx_digest);
@@ -452,11 +667,36 @@ pub async fn execute_replay_command(
             None
         }
 
+        ReplayToolCommand::ReplayCheckpoints {
+            start,
+            end,
+            archive_path: Some(archive_path),
+            snapshot_path,
+            ..
+        } => {
+            assert!(start <= end, "Start checkpoint must be <= end checkpoint");
+            info!("Executing checkpoints {} to {} from archive", start, end);
+            let time = std::time::Instant::now();
+            let (succeeded, total) = LocalExec::new_for_archive(archive_path, snapshot_path)
+                .await?
+                .execute_archive_checkpoints(start, end, &safety, use_authority)
+                .await?;
+            info!(
+                "Executed {} checkpoints @ {}/{} total TXs succeeded in {} ms",
+                end - start + 1,
+                succeeded,
+                total,
+                time.elapsed().as_millis()
+            );
+            Some((succeeded, total))
+        }
         ReplayToolCommand::ReplayCheckpoints {
             start,
             end,
             terminate_early,
             max_tasks,
+            archive_path: None,
+            snapshot_path: _,
         } => {
             assert!(start <= end, "Start checkpoint must be <= end checkpoint");
             assert!(max_tasks > 0, "Max tasks must be > 0");
@@ -519,6 +759,70 @@ pub async fn execute_replay_command(
             );
             Some((total_succeeded, total_tx))
         }
+        ReplayToolCommand::ReplayDiff {
+            start,
+            end,
+            executor_version,
+            protocol_version,
+            output,
+            archive_path,
+            snapshot_path,
+        } => {
+            assert!(start <= end, "Start checkpoint must be <= end checkpoint");
+            if executor_version.is_none() && protocol_version.is_none() {
+                anyhow::bail!(
+                    "At least one of --executor-version and --protocol-version is required"
+                );
+            }
+            info!(
+                "Comparing checkpoints {} to {} against executor version {:?} and protocol version {:?}",
+                start, end, executor_version, protocol_version
+            );
+            let mut lx = match archive_path {
+                Some(archive_path) => {
+                    LocalExec::new_for_archive(archive_path, snapshot_path).await?
+                }
+                None => {
+                    LocalExec::new_from_fn_url(&get_rpc_url(rpc_url, cfg_path, chain)?)
+                        .await?
+                        .init_for_execution()
+                        .await?
+                }
+            };
+            let time = std::time::Instant::now();
+            let report = lx
+                .execute_differential_checkpoints(
+                    start,
+                    end,
+                    &safety,
+                    executor_version,
+                    protocol_version,
+                    use_authority,
+                )
+                .await?;
+
+            let mut writer: Box<dyn Write> = match output {
+                Some(path) => Box::new(std::io::BufWriter::new(std::fs::File::create(path)?)),
+                None => Box::new(std::io::stdout()),
+            };
+            for divergence in &report.divergences {
+                writeln!(writer, "{}", serde_json::to_string(divergence)?)?;
+            }
+            writer.flush()?;
+
+            info!(
+                "Compared {} TXs in {} checkpoints in {} ms: {} diverged, {} could not be executed",
+                report.executed,
+                end - start + 1,
+                time.elapsed().as_millis(),
+                report.divergences.len(),
+                report.failed
+            );
+            Some((
+                report.executed - report.divergences.len() as u64,
+                report.executed + report.failed,
+            ))
+        }
         ReplayToolCommand::ReplayEpoch {
             epoch,
             terminate_early,
@@ -544,6 +848,8 @@ pub async fn execute_replay_command(
                     end,
                     terminate_early,
                     max_tasks,
+                    archive_path: None,
+                    snapshot_path: None,
                 },
             )
             .await;
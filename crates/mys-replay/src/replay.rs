@@ -4,12 +4,15 @@
 use crate::chain_from_chain_id;
 use crate::{
     data_fetcher::{
-        extract_epoch_and_version, DataFetcher, Fetchers, NodeStateDumpFetcher, RemoteFetcher,
+        extract_epoch_and_version, ArchiveFetcher, DataFetcher, Fetchers, NodeStateDumpFetcher,
+        RemoteFetcher,
     },
+    diff::{DifferentialReport, TransactionDivergence},
     displays::{
         transaction_displays::{transform_command_results_to_annotated, FullPTB},
         Pretty,
     },
+    fuzz_corpus::FuzzArtifact,
     types::*,
 };
 use futures::executor::block_on;
@@ -44,9 +47,10 @@ use mys_types::storage::{get_module, PackageObject};
 use mys_types::transaction::TransactionKind::ProgrammableTransaction;
 use mys_types::MYS_DENY_LIST_OBJECT_ID;
 use mys_types::{
-    base_types::{ObjectID, ObjectRef, SequenceNumber, VersionNumber},
+    base_types::{ExecutionData, ObjectID, ObjectRef, SequenceNumber, VersionNumber},
     committee::EpochId,
     digests::{ObjectDigest, TransactionDigest},
+    effects::TransactionEffectsAPI,
     error::{ExecutionError, MysError, MysResult},
     executable_transaction::VerifiedExecutableTransaction,
     gas::MysGasStatus,
@@ -151,6 +155,16 @@ pub struct Storage {
     pub object_version_cache: Arc<Mutex<BTreeMap<(ObjectID, SequenceNumber), Object>>>,
 }
 
+impl Storage {
+    /// Makes the caches usable again after a panic during execution. Their contents remain valid
+    /// since objects are only ever inserted whole.
+    pub fn clear_poison(&self) {
+        self.live_objects_store.clear_poison();
+        self.package_cache.clear_poison();
+        self.object_version_cache.clear_poison();
+    }
+}
+
 impl std::fmt::Display for Storage {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "Live object store")?;
@@ -345,6 +359,31 @@ impl LocalExec {
             .await
     }
 
+    /// Executes the mutated transaction recorded in a fuzz artifact against the state its base
+    /// transaction originally ran with
+    pub async fn replay_fuzz_artifact(
+        rpc_url: String,
+        artifact: &FuzzArtifact,
+        expensive_safety_check_config: ExpensiveSafetyCheckConfig,
+        executor_version: Option<i64>,
+        protocol_version: Option<i64>,
+    ) -> Result<ExecutionSandboxState, ReplayEngineError> {
+        info!("Using RPC URL: {}", rpc_url);
+        let mut lx = LocalExec::new_from_fn_url(&rpc_url)
+            .await?
+            .init_for_execution()
+            .await?;
+        lx.executor_version = executor_version;
+        lx.protocol_version = protocol_version;
+        let tx_info = lx.resolve_tx_info(&artifact.base_tx_digest).await?;
+        lx.execution_engine_execute_with_tx_info_impl(
+            &tx_info,
+            Some(artifact.kind.clone()),
+            expensive_safety_check_config,
+        )
+        .await
+    }
+
     /// This captures the state of the network at a given point in time and populates
     /// prptocol version tables including which system packages to fetch
     /// If this function is called across epoch boundaries, the info might be stale.
@@ -437,6 +476,39 @@ impl LocalExec {
         })
     }
 
+    /// Replay fully offline from a local checkpoint archive, seeding objects from the latest full
+    /// state snapshot under `snapshot_path` if given, otherwise from genesis.
+    /// Transactions must be executed in checkpoint order starting at
+    /// `ArchiveFetcher::first_replayable_checkpoint`.
+    pub async fn new_for_archive(
+        archive_path: PathBuf,
+        snapshot_path: Option<PathBuf>,
+    ) -> Result<Self, ReplayEngineError> {
+        // Use a throwaway metrics registry for local execution.
+        let registry = prometheus::Registry::new();
+        let metrics = Arc::new(LimitsMetrics::new(&registry));
+
+        let fetcher = ArchiveFetcher::new(archive_path, snapshot_path).await?;
+
+        Ok(Self {
+            client: None,
+            protocol_version_epoch_table: BTreeMap::new(),
+            protocol_version_system_package_table: BTreeMap::new(),
+            current_protocol_version: 0,
+            exec_store_events: Arc::new(Mutex::new(Vec::new())),
+            metrics,
+            storage: Storage::default(),
+            fetcher: Fetchers::Archive(fetcher),
+            // TODO: make these configurable
+            num_retries_for_timeout: RPC_TIMEOUT_ERR_NUM_RETRIES,
+            sleep_period_for_timeout: RPC_TIMEOUT_ERR_SLEEP_RETRY_PERIOD,
+            executor_version: None,
+            protocol_version: None,
+            enable_profiler: None,
+            config_and_versions: None,
+        })
+    }
+
     pub async fn multi_download_and_store(
         &mut self,
         objs: &[(ObjectID, SequenceNumber)],
@@ -705,6 +777,150 @@ impl LocalExec {
         Ok((succeeded, num as u64))
     }
 
+    /// Replays checkpoints `start..=end` from an archive. Checkpoints between the first replayable
+    /// one and `start` are executed as well so that their outputs are available, but are not
+    /// counted. Replay stops at the first failed transaction, since later ones would run against
+    /// a state that diverged from the archive.
+    pub async fn execute_archive_checkpoints(
+        &mut self,
+        start: u64,
+        end: u64,
+        expensive_safety_check_config: &ExpensiveSafetyCheckConfig,
+        use_authority: bool,
+    ) -> Result<(u64, u64), ReplayEngineError> {
+        self.replay_archive_until(start, expensive_safety_check_config, use_authority)
+            .await?;
+        let checkpoint_ids: Vec<_> = (start..=end).collect();
+        self.execute_all_in_checkpoints(
+            &checkpoint_ids,
+            expensive_safety_check_config,
+            true,
+            use_authority,
+        )
+        .await
+    }
+
+    /// Executes the archive checkpoints preceding `start` so that the object pool holds the
+    /// state as of the beginning of checkpoint `start`
+    async fn replay_archive_until(
+        &mut self,
+        start: u64,
+        expensive_safety_check_config: &ExpensiveSafetyCheckConfig,
+        use_authority: bool,
+    ) -> Result<(), ReplayEngineError> {
+        let first = self.fetcher.as_archive().first_replayable_checkpoint();
+        if start < first {
+            return Err(ReplayEngineError::CheckpointNotReplayable {
+                checkpoint: start,
+                first,
+            });
+        }
+        if first < start {
+            info!(
+                "Replaying checkpoints {} to {} to build up the state at checkpoint {}",
+                first,
+                start - 1,
+                start
+            );
+        }
+        // Any failure leaves the state diverged from the archive, so there is no point going on
+        for checkpoint_id in first..start {
+            self.execute_all_in_checkpoints(
+                &[checkpoint_id],
+                expensive_safety_check_config,
+                true,
+                use_authority,
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Re-executes every transaction in checkpoints `start..=end` twice: once with the versions
+    /// it originally ran with, and once under the given executor and protocol version overrides.
+    /// Transactions for which the two executions differ are reported.
+    pub async fn execute_differential_checkpoints(
+        &mut self,
+        start: u64,
+        end: u64,
+        expensive_safety_check_config: &ExpensiveSafetyCheckConfig,
+        executor_version: Option<i64>,
+        protocol_version: Option<i64>,
+        use_authority: bool,
+    ) -> Result<DifferentialReport, ReplayEngineError> {
+        if self.is_archive_replay() {
+            self.replay_archive_until(start, expensive_safety_check_config, use_authority)
+                .await?;
+        }
+        let mut report = DifferentialReport::default();
+        for checkpoint_id in start..=end {
+            for tx_digest in self.get_checkpoint_txs(checkpoint_id).await? {
+                match self
+                    .execute_differential(
+                        &tx_digest,
+                        checkpoint_id,
+                        expensive_safety_check_config.clone(),
+                        executor_version,
+                        protocol_version,
+                    )
+                    .await
+                {
+                    Ok(divergence) => {
+                        report.executed += 1;
+                        if !divergence.is_empty() {
+                            warn!("Execution of {} diverged under the overrides", tx_digest);
+                            report.divergences.push(divergence);
+                        }
+                    }
+                    // Later transactions would run against a diverged archive state
+                    Err(e) if self.is_archive_replay() => return Err(e),
+                    Err(e) => {
+                        report.failed += 1;
+                        error!("Error executing tx: {},  {:#?}", tx_digest, e);
+                    }
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Executes a transaction under the given overrides and then with the versions it originally
+    /// ran with, and compares the two executions
+    pub async fn execute_differential(
+        &mut self,
+        tx_digest: &TransactionDigest,
+        checkpoint: u64,
+        expensive_safety_check_config: ExpensiveSafetyCheckConfig,
+        executor_version: Option<i64>,
+        protocol_version: Option<i64>,
+    ) -> Result<TransactionDivergence, ReplayEngineError> {
+        // System packages are cached per object ID, so each run must load its own
+        self.storage = Storage::default();
+        self.executor_version = executor_version;
+        self.protocol_version = protocol_version;
+        let candidate = match self.resolve_tx_info(tx_digest).await {
+            Ok(tx_info) => {
+                self.execution_engine_execute_with_tx_info_impl(
+                    &tx_info,
+                    None,
+                    expensive_safety_check_config.clone(),
+                )
+                .await
+            }
+            Err(e) => Err(e),
+        };
+
+        // The baseline runs last since in archive replays it records its outputs for later
+        // transactions, which would otherwise be visible to the candidate run
+        self.storage = Storage::default();
+        self.executor_version = None;
+        self.protocol_version = None;
+        let baseline = self
+            .execution_engine_execute_impl(tx_digest, expensive_safety_check_config)
+            .await?;
+        Ok(TransactionDivergence::new(checkpoint, &baseline, candidate))
+    }
+
     pub async fn execution_engine_execute_with_tx_info_impl(
         &mut self,
         tx_info: &OnChainTransactionInfo,
@@ -875,17 +1091,35 @@ impl LocalExec {
         );
         }
 
-        let tx_info = if self.is_remote_replay() {
-            self.resolve_tx_components(tx_digest).await?
-        } else {
-            self.resolve_tx_components_from_dump(tx_digest).await?
-        };
-        self.execution_engine_execute_with_tx_info_impl(
-            &tx_info,
-            None,
-            expensive_safety_check_config,
-        )
-        .await
+        if self.is_archive_replay() {
+            // Objects cached for earlier transactions may be stale by now
+            self.storage = Storage::default();
+        }
+
+        let tx_info = self.resolve_tx_info(tx_digest).await?;
+        let sandbox_state = self
+            .execution_engine_execute_with_tx_info_impl(
+                &tx_info,
+                None,
+                expensive_safety_check_config,
+            )
+            .await?;
+
+        // Later transactions in the archive read the objects written by this one, so the replayed
+        // state cannot go on once a transaction diverges from the archive
+        if let Fetchers::Archive(archive) = &self.fetcher {
+            sandbox_state.check_effects()?;
+            let inner_store = sandbox_state
+                .local_exec_temporary_store
+                .as_ref()
+                .ok_or_else(|| ReplayEngineError::GeneralError {
+                    err: format!("Replaying {tx_digest} produced no outputs to record"),
+                })?;
+            archive
+                .record_transaction_outputs(tx_digest, inner_store.written.values().cloned())
+                .await?;
+        }
+        Ok(sandbox_state)
     }
 
     /// Executes a transaction with the state specified in `pre_run_sandbox`
@@ -1093,6 +1327,10 @@ impl LocalExec {
         matches!(self.fetcher, Fetchers::Remote(_))
     }
 
+    pub fn is_archive_replay(&self) -> bool {
+        matches!(self.fetcher, Fetchers::Archive(_))
+    }
+
     /// Must be called after `populate_protocol_version_tables`
     pub fn system_package_versions_for_protocol_version(
         &self,
@@ -1116,6 +1354,9 @@ impl LocalExec {
                 .map(|w| (w.id, w.version, w.digest))
                 .map(|q| (q.0, q.1))
                 .collect()),
+
+            // The pool only holds the state right before the transaction being replayed
+            Fetchers::Archive(a) => a.system_package_versions(),
         }
     }
 
@@ -1351,6 +1592,13 @@ impl LocalExec {
         match self.protocol_version {
             Some(x) if x < 0 => Ok(ProtocolConfig::get_for_max_version_UNSAFE()),
             Some(v) => Ok(ProtocolConfig::get_for_version((v as u64).into(), chain)),
+            None if self.is_archive_replay() => Ok(ProtocolConfig::get_for_version(
+                self.fetcher
+                    .as_archive()
+                    .get_protocol_version(epoch_id)?
+                    .into(),
+                chain,
+            )),
             None => self
                 .protocol_version_epoch_table
                 .iter()
@@ -1492,14 +1740,16 @@ impl LocalExec {
             .iter()
             .map(|so_ref| {
                 if so_ref.digest == ObjectDigest::OBJECT_DIGEST_DELETED {
-                    unimplemented!(
-                        "Replay of deleted shared object transactions is not supported yet"
-                    );
+                    Err(ReplayEngineError::TransactionNotSupported {
+                        digest: *tx_digest,
+                        reason: "Replay of deleted shared object transactions is not supported yet"
+                            .to_string(),
+                    })
                 } else {
-                    so_ref.to_object_ref()
+                    Ok(so_ref.to_object_ref())
                 }
             })
-            .collect();
+            .collect::<Result<_, _>>()?;
         let gas_data = match tx_info.clone().transaction.unwrap().data {
             mys_json_rpc_types::MysTransactionBlockData::V1(tx) => tx.gas_data,
         };
@@ -1548,6 +1798,101 @@ impl LocalExec {
         })
     }
 
+    async fn resolve_tx_info(
+        &self,
+        tx_digest: &TransactionDigest,
+    ) -> Result<OnChainTransactionInfo, ReplayEngineError> {
+        match &self.fetcher {
+            Fetchers::Remote(_) => self.resolve_tx_components(tx_digest).await,
+            Fetchers::NodeStateDump(_) => self.resolve_tx_components_from_dump(tx_digest).await,
+            Fetchers::Archive(_) => self.resolve_tx_components_from_archive(tx_digest).await,
+        }
+    }
+
+    async fn resolve_tx_components_from_archive(
+        &self,
+        tx_digest: &TransactionDigest,
+    ) -> Result<OnChainTransactionInfo, ReplayEngineError> {
+        assert!(self.is_archive_replay());
+
+        let ExecutionData {
+            transaction,
+            effects,
+        } = self
+            .fetcher
+            .as_archive()
+            .get_execution_data(tx_digest)
+            .await?;
+        let orig_tx = transaction.into_data();
+        let sender = orig_tx.transaction_data().sender();
+        let epoch_id = effects.executed_epoch();
+        let effects = MysTransactionBlockEffects::try_from(effects)?;
+        let config_objects = self.add_config_objects_if_needed(effects.status());
+
+        let input_objs = orig_tx
+            .transaction_data()
+            .input_objects()
+            .map_err(|e| ReplayEngineError::UserInputError { err: e })?;
+        let tx_kind_orig = orig_tx.transaction_data().kind();
+
+        // Download the objects at the version right before the execution of this TX
+        let modified_at_versions: Vec<(ObjectID, SequenceNumber)> = effects.modified_at_versions();
+
+        let shared_object_refs: Vec<ObjectRef> = effects
+            .shared_objects()
+            .iter()
+            .map(|so_ref| {
+                if so_ref.digest == ObjectDigest::OBJECT_DIGEST_DELETED {
+                    Err(ReplayEngineError::TransactionNotSupported {
+                        digest: *tx_digest,
+                        reason: "Replay of deleted shared object transactions is not supported yet"
+                            .to_string(),
+                    })
+                } else {
+                    Ok(so_ref.to_object_ref())
+                }
+            })
+            .collect::<Result<_, _>>()?;
+        let gas_data = orig_tx.transaction_data().gas_data();
+        let gas_object_refs: Vec<_> = gas_data.clone().payment.into_iter().collect();
+        let receiving_objs = orig_tx
+            .transaction_data()
+            .receiving_objects()
+            .into_iter()
+            .map(|(obj_id, version, _)| (obj_id, version))
+            .collect();
+
+        let chain = chain_from_chain_id(self.fetcher.get_chain_id().await?.as_str());
+
+        // Epoch 0 is replayable here since the genesis state is seeded from the archive
+        let (epoch_start_timestamp, reference_gas_price) = self
+            .fetcher
+            .get_epoch_start_timestamp_and_rgp(epoch_id)
+            .await?;
+
+        Ok(OnChainTransactionInfo {
+            kind: tx_kind_orig.clone(),
+            sender,
+            modified_at_versions,
+            input_objects: input_objs,
+            shared_object_refs,
+            gas: gas_object_refs,
+            gas_budget: gas_data.budget,
+            gas_price: gas_data.price,
+            executed_epoch: epoch_id,
+            dependencies: effects.dependencies().to_vec(),
+            effects,
+            receiving_objs,
+            config_objects,
+            protocol_version: self.get_protocol_config(epoch_id, chain).await?.version,
+            tx_digest: *tx_digest,
+            epoch_start_timestamp,
+            sender_signed_data: orig_tx.clone(),
+            reference_gas_price,
+            chain,
+        })
+    }
+
     async fn resolve_tx_components_from_dump(
         &self,
         tx_digest: &TransactionDigest,
@@ -1584,14 +1929,16 @@ impl LocalExec {
             .iter()
             .map(|so_ref| {
                 if so_ref.digest == ObjectDigest::OBJECT_DIGEST_DELETED {
-                    unimplemented!(
-                        "Replay of deleted shared object transactions is not supported yet"
-                    );
+                    Err(ReplayEngineError::TransactionNotSupported {
+                        digest: *tx_digest,
+                        reason: "Replay of deleted shared object transactions is not supported yet"
+                            .to_string(),
+                    })
                 } else {
-                    so_ref.to_object_ref()
+                    Ok(so_ref.to_object_ref())
                 }
             })
-            .collect();
+            .collect::<Result<_, _>>()?;
         let gas_data = orig_tx.transaction_data().gas_data();
         let gas_object_refs: Vec<_> = gas_data.clone().payment.into_iter().collect();
         let receiving_objs = orig_tx
@@ -1650,7 +1997,7 @@ impl LocalExec {
         // correct transaction dependency for a deleted shared object.
         if !deleted_shared_objects.is_empty() {
             for tx_digest in tx_info.dependencies.iter() {
-                let tx_info = self.resolve_tx_components(tx_digest).await?;
+                let tx_info = self.resolve_tx_info(tx_digest).await?;
                 for (obj_id, version, _) in tx_info.shared_object_refs.iter() {
                     deleted_shared_info_map.insert(*obj_id, (tx_info.tx_digest, *version));
                 }
@@ -20,6 +20,7 @@ use mys_types::transaction::{InputObjectKind, SenderSignedData, TransactionKind}
 use thiserror::Error;
 use tokio::time::Duration;
 use tracing::{error, warn};
+use typed_store::TypedStoreError;
 
 use crate::config::ReplayableNetworkConfigSet;
 
@@ -199,6 +200,18 @@ pub enum ReplayEngineError {
 
     #[error("Unable to get chain id: {}", err)]
     UnableToGetChainId { err: String },
+
+    #[error("Replayed state is at epoch {current}, cannot serve epoch {epoch}")]
+    ReplayedEpochMismatch { epoch: u64, current: u64 },
+
+    #[error("Checkpoint {checkpoint} precedes the state replay starts from (checkpoint {first})")]
+    CheckpointNotReplayable { checkpoint: u64, first: u64 },
+
+    #[error("{operation} is not supported when replaying from an archive")]
+    UnsupportedByArchive { operation: String },
+
+    #[error("No transactions found in checkpoints {start} to {end}")]
+    NoTransactionsInCheckpoints { start: u64, end: u64 },
 }
 
 impl From<MysObjectResponseError> for ReplayEngineError {
@@ -251,6 +264,14 @@ impl From<UserInputError> for ReplayEngineError {
     }
 }
 
+impl From<TypedStoreError> for ReplayEngineError {
+    fn from(err: TypedStoreError) -> Self {
+        ReplayEngineError::GeneralError {
+            err: err.to_string(),
+        }
+    }
+}
+
 impl From<anyhow::Error> for ReplayEngineError {
     fn from(err: anyhow::Error) -> Self {
         ReplayEngineError::GeneralError {
@@ -17,26 +17,32 @@ use mys_json_rpc_types::{
 };
 use mys_sdk::rpc_types::MysExecutionStatus;
 use mys_types::base_types::{ObjectRef, MysAddress};
-use mys_types::crypto::{DefaultHash, SignatureScheme, ToFromBytes};
+use mys_types::crypto::{CompressedSignature, DefaultHash, SignatureScheme, ToFromBytes};
 use mys_types::error::MysError;
+use mys_types::multisig::MultiSig;
 use mys_types::signature::{GenericSignature, VerifyParams};
 use mys_types::signature_verification::{
     verify_sender_signed_data_message_signatures, VerifiedDigestCache,
 };
+use mys_types::parse_mys_type_tag;
 use mys_types::transaction::{Transaction, TransactionData, TransactionDataAPI};
 
 use crate::errors::Error;
 use crate::types::{
-    Amount, ConstructionCombineRequest, ConstructionCombineResponse, ConstructionDeriveRequest,
-    ConstructionDeriveResponse, ConstructionHashRequest, ConstructionMetadata,
-    ConstructionMetadataRequest, ConstructionMetadataResponse, ConstructionParseRequest,
-    ConstructionParseResponse, ConstructionPayloadsRequest, ConstructionPayloadsResponse,
-    ConstructionPreprocessRequest, ConstructionPreprocessResponse, ConstructionSubmitRequest,
-    InternalOperation, MetadataOptions, SignatureType, SigningPayload, TransactionIdentifier,
+    AccountIdentifier, AccountIdentifierMetadata, Amount, ConstructionCombineRequest,
+    ConstructionCombineResponse, ConstructionDeriveRequest, ConstructionDeriveResponse,
+    ConstructionHashRequest, ConstructionMetadata, ConstructionMetadataRequest,
+    ConstructionMetadataResponse, ConstructionParseRequest, ConstructionParseResponse,
+    ConstructionPayloadsRequest, ConstructionPayloadsResponse, ConstructionPreprocessRequest,
+    ConstructionPreprocessResponse, ConstructionSubmitRequest, CurveType, InternalOperation,
+    MetadataOptions, Signature, SignatureType, SigningPayload, TransactionIdentifier,
     TransactionIdentifierResponse,
 };
 use crate::{OnlineServerContext, MysEnv};
 
+/// Maximum number of coins merged by a single `MergeCoins` operation.
+const MAX_MERGE_COINS: usize = 500;
+
 /// This module implements the [Rosetta Construction API](https://www.rosetta-api.org/docs/ConstructionApi.html)
 
 /// Derive returns the AccountIdentifier associated with a public key.
@@ -47,7 +53,21 @@ pub async fn derive(
     WithRejection(Json(request), _): WithRejection<Json<ConstructionDeriveRequest>, Error>,
 ) -> Result<ConstructionDeriveResponse, Error> {
     env.check_network_identifier(&request.network_identifier)?;
-    let address: MysAddress = request.public_key.try_into()?;
+    let address: MysAddress = match request.metadata.and_then(|m| m.multisig) {
+        Some(multisig) => {
+            if !multisig
+                .public_keys
+                .iter()
+                .any(|m| m.public_key == request.public_key)
+            {
+                return Err(Error::InvalidInput(
+                    "Public key is not a member of the multisig".into(),
+                ));
+            }
+            multisig.address()?
+        }
+        None => request.public_key.try_into()?,
+    };
     Ok(ConstructionDeriveResponse {
         account_identifier: address.into(),
     })
@@ -65,6 +85,7 @@ pub async fn payloads(
     env.check_network_identifier(&request.network_identifier)?;
     let metadata = request.metadata.ok_or(Error::MissingMetadata)?;
     let address = metadata.sender;
+    let multisig = metadata.multisig.clone();
 
     let data = request
         .operations
@@ -77,13 +98,43 @@ pub async fn payloads(
     hasher.update(bcs::to_bytes(&intent_msg).expect("Message serialization should not fail"));
     let digest = hasher.finalize().digest;
 
-    Ok(ConstructionPayloadsResponse {
-        unsigned_transaction: Hex::from_bytes(&intent_msg_bytes),
-        payloads: vec![SigningPayload {
+    let payloads = match multisig {
+        // Every member signs the same digest, the payloads carry the multisig composition so
+        // that /construction/combine can assemble the partial signatures.
+        Some(multisig) => {
+            if multisig.address()? != address {
+                return Err(Error::InvalidInput(
+                    "Multisig does not match the sender address".into(),
+                ));
+            }
+            multisig
+                .public_keys
+                .iter()
+                .map(|member| {
+                    Ok(SigningPayload {
+                        account_identifier: AccountIdentifier {
+                            address: member.public_key.clone().try_into()?,
+                            sub_account: None,
+                            metadata: Some(AccountIdentifierMetadata {
+                                multisig: Some(multisig.clone()),
+                            }),
+                        },
+                        hex_bytes: Hex::encode(digest),
+                        signature_type: Some(member.public_key.curve_type.into()),
+                    })
+                })
+                .collect::<Result<Vec<_>, Error>>()?
+        }
+        None => vec![SigningPayload {
             account_identifier: address.into(),
             hex_bytes: Hex::encode(digest),
             signature_type: Some(SignatureType::Ed25519),
         }],
+    };
+
+    Ok(ConstructionPayloadsResponse {
+        unsigned_transaction: Hex::from_bytes(&intent_msg_bytes),
+        payloads,
     })
 }
 
@@ -98,33 +149,65 @@ pub async fn combine(
     env.check_network_identifier(&request.network_identifier)?;
     let unsigned_tx = request.unsigned_transaction.to_vec()?;
     let intent_msg: IntentMessage<TransactionData> = bcs::from_bytes(&unsigned_tx)?;
-    let sig = request
+    let first_sig = request
         .signatures
         .first()
         .ok_or_else(|| Error::MissingInput("Signature".to_string()))?;
-    let sig_bytes = sig.hex_bytes.to_vec()?;
-    let pub_key = sig.public_key.hex_bytes.to_vec()?;
-    let flag = vec![match sig.signature_type {
-        SignatureType::Ed25519 => SignatureScheme::ED25519,
-        SignatureType::Ecdsa => SignatureScheme::Secp256k1,
+    let multisig = first_sig
+        .signing_payload
+        .account_identifier
+        .metadata
+        .as_ref()
+        .and_then(|m| m.multisig.clone());
+
+    let signature = match multisig {
+        Some(multisig) => {
+            let multisig_pk = multisig.to_multisig_public_key()?;
+            if MysAddress::from(&multisig_pk) != intent_msg.value.sender() {
+                return Err(Error::InvalidInput(
+                    "Multisig does not match the transaction sender".into(),
+                ));
+            }
+            let mut sigs = request
+                .signatures
+                .iter()
+                .map(|sig| {
+                    let sig = to_generic_signature(sig)?;
+                    let index = multisig_pk
+                        .get_index(&sig.to_public_key()?)
+                        .ok_or_else(|| {
+                            Error::InvalidInput("Signer is not a member of the multisig".into())
+                        })?;
+                    Ok((index, sig))
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+            // The signatures must be in the same order as the public keys of the multisig.
+            sigs.sort_by_key(|(index, _)| *index);
+            let sigs = sigs.into_iter().map(|(_, sig)| sig).collect();
+            GenericSignature::MultiSig(MultiSig::combine(sigs, multisig_pk)?)
+        }
+        None => to_generic_signature(first_sig)?,
+    };
+    let is_zklogin = match &signature {
+        GenericSignature::MultiSig(multisig) => multisig
+            .get_sigs()
+            .iter()
+            .any(|sig| matches!(sig, CompressedSignature::ZkLogin(_))),
+        signature => signature.is_zklogin(),
+    };
+
+    let signed_tx = Transaction::from_generic_sig_data(intent_msg.value, vec![signature]);
+    // zkLogin signatures cannot be verified offline, they need the current epoch and the JWKs
+    // of the providers. They are verified by the network on submission.
+    if !is_zklogin {
+        let place_holder_epoch = 0;
+        verify_sender_signed_data_message_signatures(
+            &signed_tx,
+            place_holder_epoch,
+            &VerifyParams::default(),
+            Arc::new(VerifiedDigestCache::new_empty()), // no need to use cache in rosetta
+        )?;
     }
-    .flag()];
-
-    let signed_tx = Transaction::from_generic_sig_data(
-        intent_msg.value,
-        vec![GenericSignature::from_bytes(
-            &[&*flag, &*sig_bytes, &*pub_key].concat(),
-        )?],
-    );
-    // TODO: this will likely fail with zklogin authenticator, since we do not know the current epoch.
-    // As long as coinbase doesn't need to use zklogin for custodial wallets this is okay.
-    let place_holder_epoch = 0;
-    verify_sender_signed_data_message_signatures(
-        &signed_tx,
-        place_holder_epoch,
-        &VerifyParams::default(),
-        Arc::new(VerifiedDigestCache::new_empty()), // no need to use cache in rosetta
-    )?;
     let signed_tx_bytes = bcs::to_bytes(&signed_tx)?;
 
     Ok(ConstructionCombineResponse {
@@ -132,6 +215,24 @@ pub async fn combine(
     })
 }
 
+/// Builds the signature of a single signer, `flag || signature || public key` for key pairs,
+/// or the serialized authenticator for zkLogin and passkey signers.
+fn to_generic_signature(sig: &Signature) -> Result<GenericSignature, Error> {
+    let sig_bytes = sig.hex_bytes.to_vec()?;
+    let scheme = match (sig.signature_type, sig.public_key.curve_type) {
+        (SignatureType::ZkLogin | SignatureType::Passkey, _) => {
+            return Ok(GenericSignature::from_bytes(&sig_bytes)?);
+        }
+        (SignatureType::Ed25519, _) => SignatureScheme::ED25519,
+        (SignatureType::Ecdsa, CurveType::Secp256r1) => SignatureScheme::Secp256r1,
+        (SignatureType::Ecdsa, _) => SignatureScheme::Secp256k1,
+    };
+    let pub_key = sig.public_key.hex_bytes.to_vec()?;
+    Ok(GenericSignature::from_bytes(
+        &[&[scheme.flag()], &*sig_bytes, &*pub_key].concat(),
+    )?)
+}
+
 /// Submit a pre-signed transaction to the node.
 ///
 /// [Rosetta API Spec](https://www.rosetta-api.org/docs/ConstructionApi.html#constructionsubmit)
@@ -197,13 +298,35 @@ pub async fn preprocess(
 
     let internal_operation = request.operations.into_internal()?;
     let sender = internal_operation.sender();
-    let budget = request.metadata.and_then(|m| m.budget);
+    let (budget, multisig) = request
+        .metadata
+        .map(|m| (m.budget, m.multisig))
+        .unwrap_or_default();
+    let required_public_keys = match &multisig {
+        Some(multisig) => {
+            if multisig.address()? != sender {
+                return Err(Error::InvalidInput(
+                    "Multisig does not match the sender address".into(),
+                ));
+            }
+            multisig
+                .public_keys
+                .iter()
+                .map(|member| {
+                    let address: MysAddress = member.public_key.clone().try_into()?;
+                    Ok(address.into())
+                })
+                .collect::<Result<Vec<_>, Error>>()?
+        }
+        None => vec![sender.into()],
+    };
     Ok(ConstructionPreprocessResponse {
         options: Some(MetadataOptions {
             internal_operation,
             budget,
+            multisig,
         }),
-        required_public_keys: vec![sender.into()],
+        required_public_keys,
     })
 }
 
@@ -238,11 +361,24 @@ pub async fn metadata(
     let option = request.options.ok_or(Error::MissingMetadata)?;
     let budget = option.budget;
     let sender = option.internal_operation.sender();
-    let currency = match &option.internal_operation {
-        InternalOperation::PayCoin { currency, .. } => Some(currency.clone()),
-        _ => None,
-    };
-    let coin_type = currency.as_ref().map(|c| c.metadata.coin_type.clone());
+    let currency = option.internal_operation.currency().cloned();
+    if let Some(currency) = &currency {
+        // Reject currencies whose symbol or decimals do not match the on-chain `CoinMetadata`,
+        // amounts would otherwise be misinterpreted by the caller.
+        let type_tag = parse_mys_type_tag(&currency.metadata.coin_type)
+            .map_err(|e| Error::InvalidInput(format!("Invalid coin type: {e}")))?;
+        let expected = context.coin_metadata_cache.get_currency(&type_tag).await?;
+        if expected.symbol != currency.symbol || expected.decimals != currency.decimals {
+            return Err(Error::InvalidInput(format!(
+                "Currency {} does not match the coin metadata, expected symbol {} and decimals {}",
+                currency.metadata.coin_type, expected.symbol, expected.decimals
+            )));
+        }
+    }
+    let coin_type = currency
+        .as_ref()
+        .filter(|c| !c.is_mys())
+        .map(|c| c.metadata.coin_type.clone());
 
     let mut gas_price = context
         .client
@@ -264,14 +400,46 @@ pub async fn metadata(
                 .client
                 .coin_read_api()
                 .select_coins(sender, coin_type, amount.into(), vec![])
-                .await
-                .ok()
-                .unwrap_or_default()
+                .await?
                 .iter()
                 .map(|coin| coin.object_ref())
                 .collect();
             (Some(0), coin_objs) // amount is 0 for gas coin
         }
+        InternalOperation::SplitCoins { amounts, .. } => {
+            let amount = amounts.iter().sum::<u64>();
+            if coin_type.is_none() {
+                (Some(amount), vec![])
+            } else {
+                let coin_objs: Vec<ObjectRef> = context
+                    .client
+                    .coin_read_api()
+                    .select_coins(sender, coin_type, amount.into(), vec![])
+                    .await?
+                    .iter()
+                    .map(|coin| coin.object_ref())
+                    .collect();
+                (Some(0), coin_objs)
+            }
+        }
+        // All MYS coins are selected as gas coins below, which merges them.
+        InternalOperation::MergeCoins { .. } if coin_type.is_none() => (None, vec![]),
+        InternalOperation::MergeCoins { .. } => {
+            let coin_objs: Vec<ObjectRef> = context
+                .client
+                .coin_read_api()
+                .get_coins_stream(sender, coin_type)
+                .take(MAX_MERGE_COINS)
+                .map(|coin| coin.object_ref())
+                .collect()
+                .await;
+            if coin_objs.len() < 2 {
+                return Err(Error::InvalidInput(
+                    "At least two coins are needed to merge".into(),
+                ));
+            }
+            (Some(0), coin_objs)
+        }
         InternalOperation::Stake { amount, .. } => (*amount, vec![]),
         InternalOperation::WithdrawStake { sender, stake_ids } => {
             let stake_ids = if stake_ids.is_empty() {
@@ -333,6 +501,7 @@ pub async fn metadata(
                     // MAX BUDGET
                     budget: 50_000_000_000,
                     currency: currency.clone(),
+                    multisig: None,
                 })?;
 
             let dry_run = context
@@ -390,6 +559,7 @@ pub async fn metadata(
             gas_price,
             budget,
             currency,
+            multisig: option.multisig,
         },
         suggested_fee: vec![Amount::new(budget as i128, None)],
     })
@@ -1,10 +1,12 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::num::NonZeroUsize;
 use std::string::ToString;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use axum::routing::post;
 use axum::{Extension, Router};
@@ -14,7 +16,13 @@ use once_cell::sync::Lazy;
 use tokio::sync::Mutex;
 use tracing::info;
 
+use mys_json_rpc_types::{
+    MysObjectDataOptions, MysRawData, MysTransactionBlockResponseOptions,
+    MysTransactionBlockResponseQuery, ObjectChange, TransactionFilter,
+};
 use mys_sdk::{MysClient, MYS_COIN_TYPE};
+use mys_types::base_types::ObjectID;
+use mys_types::coin::CoinMetadata;
 
 use crate::errors::Error;
 use crate::errors::Error::MissingMetadata;
@@ -43,6 +51,13 @@ pub static MYS: Lazy<Currency> = Lazy::new(|| Currency {
     },
 });
 
+/// Pages of transactions calling the package of a coin type searched for its `CoinMetadata`.
+const METADATA_LOOKUP_MAX_PAGES: usize = 5;
+
+/// How long a coin type whose metadata could not be found is answered from the cache, so that
+/// requests for it do not repeat the lookup.
+const MISSING_METADATA_TTL: Duration = Duration::from_secs(60);
+
 pub struct RosettaOnlineServer {
     env: MysEnv,
     context: OnlineServerContext,
@@ -50,7 +65,18 @@ pub struct RosettaOnlineServer {
 
 impl RosettaOnlineServer {
     pub fn new(env: MysEnv, client: MysClient) -> Self {
-        let coin_cache = CoinMetadataCache::new(client.clone(), NonZeroUsize::new(1000).unwrap());
+        Self::new_with_coin_metadata_objects(env, client, HashMap::new())
+    }
+
+    /// Same as `new`, with the `CoinMetadata` object of coin types whose metadata cannot be
+    /// looked up from their package, see `CoinMetadataCache::with_metadata_objects`.
+    pub fn new_with_coin_metadata_objects(
+        env: MysEnv,
+        client: MysClient,
+        coin_metadata_objects: HashMap<TypeTag, ObjectID>,
+    ) -> Self {
+        let coin_cache = CoinMetadataCache::new(client.clone(), NonZeroUsize::new(1000).unwrap())
+            .with_metadata_objects(coin_metadata_objects);
         let blocks = Arc::new(CheckpointBlockProvider::new(
             client.clone(),
             coin_cache.clone(),
@@ -121,6 +147,9 @@ impl RosettaOfflineServer {
 pub struct CoinMetadataCache {
     client: MysClient,
     metadata: Arc<Mutex<LruCache<TypeTag, Currency>>>,
+    // Coin types without metadata, with the time they were looked up.
+    missing_metadata: Arc<Mutex<LruCache<TypeTag, Instant>>>,
+    metadata_objects: Arc<HashMap<TypeTag, ObjectID>>,
 }
 
 impl CoinMetadataCache {
@@ -128,22 +157,45 @@ impl CoinMetadataCache {
         Self {
             client,
             metadata: Arc::new(Mutex::new(LruCache::new(size))),
+            missing_metadata: Arc::new(Mutex::new(LruCache::new(size))),
+            metadata_objects: Default::default(),
         }
     }
 
+    /// The fullnode only finds the `CoinMetadata` of a coin type among the objects created
+    /// when its package was published. Currencies created later, e.g. creator tokens from
+    /// `user_token::create_user_token` or platform tokens from
+    /// `platform::approve_token_creation`, are looked up among the objects created by the
+    /// first transactions calling the package of the coin type. The `CoinMetadata` objects
+    /// configured here are used for coin types that lookup does not find.
+    pub fn with_metadata_objects(mut self, metadata_objects: HashMap<TypeTag, ObjectID>) -> Self {
+        self.metadata_objects = Arc::new(metadata_objects);
+        self
+    }
+
     pub async fn get_currency(&self, type_tag: &TypeTag) -> Result<Currency, Error> {
         let mut cache = self.metadata.lock().await;
         if !cache.contains(type_tag) {
-            let metadata = self
-                .client
-                .coin_read_api()
-                .get_coin_metadata(type_tag.to_string())
-                .await?
-                .ok_or(MissingMetadata)?;
+            let mut missing_metadata = self.missing_metadata.lock().await;
+            if missing_metadata
+                .get(type_tag)
+                .is_some_and(|looked_up| looked_up.elapsed() < MISSING_METADATA_TTL)
+            {
+                return Err(MissingMetadata);
+            }
+            let metadata = match self.lookup_metadata(type_tag).await {
+                Ok(metadata) => metadata,
+                Err(MissingMetadata) => {
+                    missing_metadata.push(type_tag.clone(), Instant::now());
+                    return Err(MissingMetadata);
+                }
+                Err(e) => return Err(e),
+            };
+            missing_metadata.pop(type_tag);
 
             let ccy = Currency {
-                symbol: metadata.symbol,
-                decimals: metadata.decimals as u64,
+                symbol: metadata.0,
+                decimals: metadata.1 as u64,
                 metadata: CurrencyMetadata {
                     coin_type: type_tag.to_string(),
                 },
@@ -152,4 +204,97 @@ impl CoinMetadataCache {
         }
         cache.get(type_tag).cloned().ok_or(MissingMetadata)
     }
+
+    /// Reads the symbol and decimals of `type_tag` from the fullnode, the first transactions
+    /// calling its package or the configured `CoinMetadata` objects, in that order.
+    async fn lookup_metadata(&self, type_tag: &TypeTag) -> Result<(String, u8), Error> {
+        if let Some(metadata) = self
+            .client
+            .coin_read_api()
+            .get_coin_metadata(type_tag.to_string())
+            .await?
+        {
+            return Ok((metadata.symbol, metadata.decimals));
+        }
+        let object_id = match self.find_metadata_object(type_tag).await? {
+            Some(object_id) => object_id,
+            None => *self.metadata_objects.get(type_tag).ok_or(MissingMetadata)?,
+        };
+        self.read_metadata_object(object_id, type_tag).await
+    }
+
+    /// Searches the objects created by the first transactions calling the package of
+    /// `type_tag` for its `CoinMetadata`.
+    async fn find_metadata_object(&self, type_tag: &TypeTag) -> Result<Option<ObjectID>, Error> {
+        let TypeTag::Struct(coin_type) = type_tag else {
+            return Ok(None);
+        };
+        let query = MysTransactionBlockResponseQuery::new(
+            Some(TransactionFilter::MoveFunction {
+                package: ObjectID::from(coin_type.address),
+                module: None,
+                function: None,
+            }),
+            Some(MysTransactionBlockResponseOptions::new().with_object_changes()),
+        );
+        let mut cursor = None;
+        for _ in 0..METADATA_LOOKUP_MAX_PAGES {
+            let page = self
+                .client
+                .read_api()
+                .query_transaction_blocks(query.clone(), cursor, None, false)
+                .await?;
+            for change in page
+                .data
+                .iter()
+                .flat_map(|tx| tx.object_changes.iter().flatten())
+            {
+                if let ObjectChange::Created {
+                    object_type,
+                    object_id,
+                    ..
+                } = change
+                {
+                    if CoinMetadata::is_coin_metadata_with_coin_type(object_type)
+                        == Some(coin_type.as_ref())
+                    {
+                        return Ok(Some(*object_id));
+                    }
+                }
+            }
+            if !page.has_next_page {
+                break;
+            }
+            cursor = page.next_cursor;
+        }
+        Ok(None)
+    }
+
+    /// Reads the symbol and decimals from the `CoinMetadata` object `object_id` of `type_tag`.
+    async fn read_metadata_object(
+        &self,
+        object_id: ObjectID,
+        type_tag: &TypeTag,
+    ) -> Result<(String, u8), Error> {
+        let object = self
+            .client
+            .read_api()
+            .get_object_with_options(object_id, MysObjectDataOptions::bcs_lossless())
+            .await?
+            .into_object()
+            .map_err(|e| Error::DataError(e.to_string()))?;
+        let Some(MysRawData::MoveObject(object)) = object.bcs else {
+            return Err(MissingMetadata);
+        };
+        match CoinMetadata::is_coin_metadata_with_coin_type(&object.type_) {
+            Some(coin_type) if TypeTag::Struct(Box::new(coin_type.clone())) == *type_tag => {}
+            _ => {
+                return Err(Error::DataError(format!(
+                    "Object {object_id} is not the CoinMetadata of {type_tag}"
+                )))
+            }
+        }
+        let metadata = CoinMetadata::from_bcs_bytes(&object.bcs_bytes)?;
+        Ok((metadata.symbol, metadata.decimals))
+    }
 }
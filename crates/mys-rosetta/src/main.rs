@@ -19,8 +19,10 @@ use mys_node::MysNode;
 use mys_rosetta::types::{CurveType, PrefundedAccount, MysEnv};
 use mys_rosetta::{RosettaOfflineServer, RosettaOnlineServer, MYS};
 use mys_sdk::{MysClient, MysClientBuilder};
-use mys_types::base_types::MysAddress;
+use mys_types::base_types::{MysAddress, ObjectID};
 use mys_types::crypto::{KeypairTraits, MysKeyPair, ToFromBytes};
+use mys_types::parse_mys_type_tag;
+use mys_types::TypeTag;
 use tracing::info;
 use tracing::log::warn;
 
@@ -46,6 +48,10 @@ pub enum RosettaServerCommand {
         full_node_url: String,
         #[clap(long, default_value = "/data")]
         data_path: PathBuf,
+        /// `CoinMetadata` object of a coin type whose metadata cannot be found on chain, as
+        /// `<COIN_TYPE>=<OBJECT_ID>`. Can be repeated.
+        #[clap(long = "coin-metadata", value_parser = parse_coin_metadata)]
+        coin_metadata: Vec<(TypeTag, ObjectID)>,
     },
     StartOnlineServer {
         #[clap(long, default_value = "localnet")]
@@ -56,6 +62,10 @@ pub enum RosettaServerCommand {
         node_config: Option<PathBuf>,
         #[clap(long, default_value = "/data")]
         data_path: PathBuf,
+        /// `CoinMetadata` object of a coin type whose metadata cannot be found on chain, as
+        /// `<COIN_TYPE>=<OBJECT_ID>`. Can be repeated.
+        #[clap(long = "coin-metadata", value_parser = parse_coin_metadata)]
+        coin_metadata: Vec<(TypeTag, ObjectID)>,
     },
     StartOfflineServer {
         #[clap(long, default_value = "localnet")]
@@ -136,6 +146,7 @@ impl RosettaServerCommand {
                 addr,
                 full_node_url,
                 data_path,
+                coin_metadata,
             } => {
                 info!(
                     "Starting Rosetta Online Server with remove Mys full node [{full_node_url}]."
@@ -143,7 +154,11 @@ impl RosettaServerCommand {
                 let mys_client = wait_for_mys_client(full_node_url).await;
                 let rosetta_path = data_path.join("rosetta_db");
                 info!("Rosetta db path : {rosetta_path:?}");
-                let rosetta = RosettaOnlineServer::new(env, mys_client);
+                let rosetta = RosettaOnlineServer::new_with_coin_metadata_objects(
+                    env,
+                    mys_client,
+                    coin_metadata.into_iter().collect(),
+                );
                 rosetta.serve(addr).await;
             }
 
@@ -152,6 +167,7 @@ impl RosettaServerCommand {
                 addr,
                 node_config,
                 data_path,
+                coin_metadata,
             } => {
                 info!("Starting Rosetta Online Server with embedded Mys full node.");
                 info!("Data directory path: {data_path:?}");
@@ -176,7 +192,11 @@ impl RosettaServerCommand {
 
                 let rosetta_path = data_path.join("rosetta_db");
                 info!("Rosetta db path : {rosetta_path:?}");
-                let rosetta = RosettaOnlineServer::new(env, mys_client);
+                let rosetta = RosettaOnlineServer::new_with_coin_metadata_objects(
+                    env,
+                    mys_client,
+                    coin_metadata.into_iter().collect(),
+                );
                 rosetta.serve(addr).await;
             }
         };
@@ -196,6 +216,16 @@ async fn wait_for_mys_client(rpc_address: String) -> MysClient {
     }
 }
 
+fn parse_coin_metadata(s: &str) -> Result<(TypeTag, ObjectID), anyhow::Error> {
+    let (coin_type, object_id) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow!("Expected <COIN_TYPE>=<OBJECT_ID>, got [{s}]"))?;
+    Ok((
+        parse_mys_type_tag(coin_type)?,
+        ObjectID::from_hex_literal(object_id)?,
+    ))
+}
+
 /// This method reads the keypairs from the Mys keystore to create the PrefundedAccount objects,
 /// PrefundedAccount will be written to the rosetta-cli config file for testing.
 ///
@@ -30,8 +30,8 @@ use mys_types::transaction::TransactionData;
 use mys_types::{MYS_SYSTEM_ADDRESS, MYS_SYSTEM_PACKAGE_ID};
 
 use crate::types::{
-    AccountIdentifier, Amount, CoinAction, CoinChange, CoinID, CoinIdentifier, Currency,
-    InternalOperation, OperationIdentifier, OperationStatus, OperationType,
+    AccountIdentifier, Amount, CoinAction, CoinChange, CoinID, CoinIdentifier, CoinOperationMarker,
+    Currency, InternalOperation, OperationIdentifier, OperationStatus, OperationType,
 };
 use crate::{CoinMetadataCache, Error, MYS};
 
@@ -102,6 +102,8 @@ impl Operations {
         match type_ {
             OperationType::PayMys => self.pay_mys_ops_to_internal(),
             OperationType::PayCoin => self.pay_coin_ops_to_internal(),
+            OperationType::MergeCoins => self.merge_coins_ops_to_internal(),
+            OperationType::SplitCoins => self.split_coins_ops_to_internal(),
             OperationType::Stake => self.stake_ops_to_internal(),
             OperationType::WithdrawStake => self.withdraw_stake_ops_to_internal(),
             op => Err(Error::UnsupportedOperation(op)),
@@ -168,6 +170,72 @@ impl Operations {
         })
     }
 
+    fn merge_coins_ops_to_internal(self) -> Result<InternalOperation, Error> {
+        let mut ops = self
+            .0
+            .into_iter()
+            .filter(|op| op.type_ == OperationType::MergeCoins)
+            .collect::<Vec<_>>();
+        if ops.len() != 1 {
+            return Err(Error::MalformedOperationError(
+                "Merge coins should only have one operation.".into(),
+            ));
+        }
+        // Checked above, safe to unwrap.
+        let op = ops.pop().unwrap();
+        let sender = op
+            .account
+            .ok_or_else(|| Error::MissingInput("Sender address".to_string()))?
+            .address;
+        let amount = op
+            .amount
+            .ok_or_else(|| Error::MissingInput("Currency".to_string()))?;
+        if amount.value != 0 {
+            return Err(Error::MalformedOperationError(
+                "Merge coins amount should be zero.".into(),
+            ));
+        }
+        Ok(InternalOperation::MergeCoins {
+            sender,
+            currency: amount.currency,
+        })
+    }
+
+    fn split_coins_ops_to_internal(self) -> Result<InternalOperation, Error> {
+        let mut amounts = vec![];
+        let mut sender = None;
+        let mut currency = None;
+        for op in self {
+            if let (Some(amount), Some(account)) = (op.amount, op.account) {
+                if currency.get_or_insert_with(|| amount.currency.clone()) != &amount.currency {
+                    return Err(Error::MalformedOperationError(
+                        "Split coins operations should have the same currency.".into(),
+                    ));
+                }
+                if *sender.get_or_insert(account.address) != account.address {
+                    return Err(Error::MalformedOperationError(
+                        "Split coins operations should have the same account.".into(),
+                    ));
+                }
+                if amount.value.is_positive() {
+                    if amount.value > u64::MAX as i128 {
+                        return Err(Error::InvalidInput(
+                            "Input amount exceed u64::MAX".to_string(),
+                        ));
+                    }
+                    amounts.push(amount.value as u64)
+                }
+            }
+        }
+        let sender = sender.ok_or_else(|| Error::MissingInput("Sender address".to_string()))?;
+        let currency = currency.ok_or_else(|| Error::MissingInput("Currency".to_string()))?;
+        Ok(InternalOperation::SplitCoins {
+            sender,
+            amounts,
+            currency,
+        })
+    }
+
     fn stake_ops_to_internal(self) -> Result<InternalOperation, Error> {
         let mut ops = self
             .0
@@ -401,12 +469,17 @@ impl Operations {
         let mut needs_generic = false;
         let mut operations = vec![];
         let mut stake_ids = vec![];
-        let mut currency: Option<Currency> = None;
+        let mut split_amounts = vec![];
+        let marker = Self::parse_coin_operation_marker(inputs);
         for command in commands {
             let result = match command {
                 MysCommand::SplitCoins(coin, amounts) => {
-                    split_coins(inputs, &known_results, *coin, amounts)
+                    split_coins(inputs, &known_results, *coin, amounts).inspect(|values| {
+                        split_amounts.extend(values.iter().map(|KnownValue::GasCoin(v)| *v))
+                    })
                 }
+                // Merging does not move any balance between accounts.
+                MysCommand::MergeCoins(_, _) => Some(vec![]),
                 MysCommand::TransferObjects(objs, addr) => transfer_object(
                     &mut aggregated_recipients,
                     inputs,
@@ -444,38 +517,56 @@ impl Operations {
             }
         }
 
+        if let (false, Some(marker)) = (needs_generic, &marker) {
+            let currency = Some(marker.currency.clone());
+            match marker.operation {
+                OperationType::MergeCoins => {
+                    return Ok(vec![Operation::coin_op(
+                        OperationType::MergeCoins,
+                        status,
+                        sender,
+                        0,
+                        currency,
+                    )]);
+                }
+                OperationType::SplitCoins => {
+                    let total: u64 = split_amounts.iter().sum();
+                    let mut operations = split_amounts
+                        .into_iter()
+                        .map(|amount| {
+                            Operation::coin_op(
+                                OperationType::SplitCoins,
+                                status,
+                                sender,
+                                amount.into(),
+                                currency.clone(),
+                            )
+                        })
+                        .collect::<Vec<_>>();
+                    operations.push(Operation::coin_op(
+                        OperationType::SplitCoins,
+                        status,
+                        sender,
+                        -(total as i128),
+                        currency,
+                    ));
+                    return Ok(operations);
+                }
+                _ => {}
+            }
+        }
+
         if !needs_generic && !aggregated_recipients.is_empty() {
             let total_paid: u64 = aggregated_recipients.values().copied().sum();
+            let currency = marker.map(|marker| marker.currency);
             operations.extend(
                 aggregated_recipients
                     .into_iter()
-                    .map(|(recipient, amount)| {
-                        currency = inputs.iter().last().and_then(|arg| {
-                            if let MysCallArg::Pure(value) = arg {
-                                let bytes = value
-                                    .value()
-                                    .to_json_value()
-                                    .as_array()?
-                                    .clone()
-                                    .into_iter()
-                                    .map(|v| v.as_u64().map(|n| n as u8))
-                                    .collect::<Option<Vec<u8>>>()?;
-                                bcs::from_bytes::<String>(&bytes)
-                                    .ok()
-                                    .and_then(|bcs_str| serde_json::from_str(&bcs_str).ok())
-                            } else {
-                                None
-                            }
-                        });
-                        match currency {
-                            Some(_) => Operation::pay_coin(
-                                status,
-                                recipient,
-                                amount.into(),
-                                currency.clone(),
-                            ),
-                            None => Operation::pay_mys(status, recipient, amount.into()),
+                    .map(|(recipient, amount)| match currency {
+                        Some(_) => {
+                            Operation::pay_coin(status, recipient, amount.into(), currency.clone())
                         }
+                        None => Operation::pay_mys(status, recipient, amount.into()),
                     }),
             );
             match currency {
@@ -512,6 +603,33 @@ impl Operations {
         Ok(operations)
     }
 
+    /// Reads the currency, and for coin management operations the operation type, that the
+    /// construction API stores in the last pure input of coin transactions.
+    fn parse_coin_operation_marker(inputs: &[MysCallArg]) -> Option<CoinOperationMarker> {
+        let MysCallArg::Pure(value) = inputs.last()? else {
+            return None;
+        };
+        let bytes = value
+            .value()
+            .to_json_value()
+            .as_array()?
+            .iter()
+            .map(|v| v.as_u64().map(|n| n as u8))
+            .collect::<Option<Vec<u8>>>()?;
+        let marker = bcs::from_bytes::<String>(&bytes).ok()?;
+        serde_json::from_str::<CoinOperationMarker>(&marker)
+            .ok()
+            .or_else(|| {
+                // `PayCoin` transactions only carry the currency.
+                serde_json::from_str(&marker)
+                    .ok()
+                    .map(|currency| CoinOperationMarker {
+                        operation: OperationType::PayCoin,
+                        currency,
+                    })
+            })
+    }
+
     fn is_stake_call(tx: &MysProgrammableMoveCall) -> bool {
         tx.package == MYS_SYSTEM_PACKAGE_ID
             && tx.module == MYS_SYSTEM_MODULE_NAME.as_str()
@@ -847,6 +965,24 @@ impl Operation {
         }
     }
 
+    fn coin_op(
+        type_: OperationType,
+        status: Option<OperationStatus>,
+        address: MysAddress,
+        amount: i128,
+        currency: Option<Currency>,
+    ) -> Self {
+        Operation {
+            operation_identifier: Default::default(),
+            type_,
+            status,
+            account: Some(address.into()),
+            amount: Some(Amount::new(amount, currency)),
+            coin_change: None,
+            metadata: None,
+        }
+    }
+
     fn balance_change(
         status: Option<OperationStatus>,
         addr: MysAddress,
@@ -17,13 +17,15 @@ use strum_macros::EnumString;
 use mys_sdk::rpc_types::{MysExecutionStatus, MysTransactionBlockKind};
 use mys_types::base_types::{ObjectID, ObjectRef, SequenceNumber, MysAddress, TransactionDigest};
 use mys_types::crypto::PublicKey as MysPublicKey;
-use mys_types::crypto::SignatureScheme;
+use mys_types::crypto::{SignatureScheme, ZkLoginPublicIdentifier};
+use mys_types::gas_coin::GAS;
 use mys_types::governance::{ADD_STAKE_FUN_NAME, WITHDRAW_STAKE_FUN_NAME};
 use mys_types::messages_checkpoint::CheckpointDigest;
+use mys_types::multisig::{MultiSigPublicKey, ThresholdUnit, WeightUnit};
 use mys_types::programmable_transaction_builder::ProgrammableTransactionBuilder;
 use mys_types::mys_system_state::MYS_SYSTEM_MODULE_NAME;
 use mys_types::transaction::{Argument, CallArg, Command, ObjectArg, TransactionData};
-use mys_types::MYS_SYSTEM_PACKAGE_ID;
+use mys_types::{parse_mys_type_tag, MYS_SYSTEM_PACKAGE_ID};
 
 use crate::errors::{Error, ErrorType};
 use crate::operations::Operations;
@@ -75,6 +77,44 @@ pub struct AccountIdentifier {
     pub address: MysAddress,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub sub_account: Option<SubAccount>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<AccountIdentifierMetadata>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct AccountIdentifierMetadata {
+    /// Set on the signing payloads of multisig senders, so that /construction/combine can
+    /// assemble the partial signatures.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub multisig: Option<MultiSigAccount>,
+}
+
+/// The composition of a multisig address, see `mys_types::multisig::MultiSigPublicKey`.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct MultiSigAccount {
+    pub public_keys: Vec<MultiSigMember>,
+    pub threshold: ThresholdUnit,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct MultiSigMember {
+    pub public_key: PublicKey,
+    pub weight: WeightUnit,
+}
+
+impl MultiSigAccount {
+    pub fn to_multisig_public_key(&self) -> Result<MultiSigPublicKey, Error> {
+        let (pks, weights) = self
+            .public_keys
+            .iter()
+            .map(|member| Ok((MysPublicKey::try_from(&member.public_key)?, member.weight)))
+            .collect::<Result<(Vec<_>, Vec<_>), Error>>()?;
+        Ok(MultiSigPublicKey::new(pks, weights, self.threshold)?)
+    }
+
+    pub fn address(&self) -> Result<MysAddress, Error> {
+        Ok((&self.to_multisig_public_key()?).into())
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
@@ -95,6 +135,7 @@ impl From<MysAddress> for AccountIdentifier {
         AccountIdentifier {
             address,
             sub_account: None,
+            metadata: None,
         }
     }
 }
@@ -124,6 +165,20 @@ impl Default for Currency {
     }
 }
 
+impl Currency {
+    pub fn is_mys(&self) -> bool {
+        parse_mys_type_tag(&self.metadata.coin_type).is_ok_and(|tag| GAS::is_gas_type(&tag))
+    }
+}
+
+/// Bearer of the currency of a coin operation across the construction flow, stored as the last
+/// pure input of the transaction. See `InternalOperation::try_into_data`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CoinOperationMarker {
+    pub operation: OperationType,
+    pub currency: Currency,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 #[serde(transparent)]
 pub struct Currencies(pub Vec<Currency>);
@@ -362,14 +417,27 @@ impl IntoResponse for NetworkListResponse {
 pub struct ConstructionDeriveRequest {
     pub network_identifier: NetworkIdentifier,
     pub public_key: PublicKey,
+    /// Derives the multisig address instead, `public_key` must be one of its members.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<AccountIdentifierMetadata>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct PublicKey {
     pub hex_bytes: Hex,
     pub curve_type: CurveType,
 }
 
+// Compares the decoded keys, the same key can be hex encoded with or without prefix.
+impl PartialEq for PublicKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.curve_type == other.curve_type
+            && self.hex_bytes.to_vec().ok() == other.hex_bytes.to_vec().ok()
+    }
+}
+
+impl Eq for PublicKey {}
+
 impl From<MysPublicKey> for PublicKey {
     fn from(pk: MysPublicKey) -> Self {
         match pk {
@@ -397,17 +465,28 @@ impl From<MysPublicKey> for PublicKey {
     }
 }
 
+impl TryFrom<&PublicKey> for MysPublicKey {
+    type Error = Error;
+
+    fn try_from(pk: &PublicKey) -> Result<Self, Self::Error> {
+        let key_bytes = pk.hex_bytes.to_vec()?;
+        Ok(match pk.curve_type {
+            CurveType::ZkLogin => MysPublicKey::ZkLogin(ZkLoginPublicIdentifier(key_bytes)),
+            curve_type => MysPublicKey::try_from_bytes(curve_type.into(), &key_bytes)?,
+        })
+    }
+}
+
 impl TryInto<MysAddress> for PublicKey {
     type Error = Error;
 
     fn try_into(self) -> Result<MysAddress, Self::Error> {
-        let key_bytes = self.hex_bytes.to_vec()?;
-        let pub_key = MysPublicKey::try_from_bytes(self.curve_type.into(), &key_bytes)?;
+        let pub_key = MysPublicKey::try_from(&self)?;
         Ok((&pub_key).into())
     }
 }
 
-#[derive(Deserialize, Serialize, Copy, Clone, Debug)]
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, Eq, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum CurveType {
     Secp256k1,
@@ -458,6 +537,8 @@ pub enum OperationType {
     // mys-rosetta supported operation type
     PayMys,
     PayCoin,
+    MergeCoins,
+    SplitCoins,
     Stake,
     WithdrawStake,
     // All other Mys transaction types, readonly
@@ -546,11 +627,25 @@ pub struct SigningPayload {
     pub signature_type: Option<SignatureType>,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, Eq, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum SignatureType {
     Ed25519,
     Ecdsa,
+    /// The signature bytes are a serialized `ZkLoginAuthenticator`, including the flag.
+    ZkLogin,
+    /// The signature bytes are a serialized `PasskeyAuthenticator`, including the flag.
+    Passkey,
+}
+
+impl From<CurveType> for SignatureType {
+    fn from(curve_type: CurveType) -> Self {
+        match curve_type {
+            CurveType::Edwards25519 => SignatureType::Ed25519,
+            CurveType::Secp256k1 | CurveType::Secp256r1 => SignatureType::Ecdsa,
+            CurveType::ZkLogin => SignatureType::ZkLogin,
+        }
+    }
 }
 
 #[derive(Deserialize, Serialize)]
@@ -614,6 +709,9 @@ pub struct ConstructionPreprocessRequest {
 pub struct PreprocessMetadata {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub budget: Option<u64>,
+    /// Composition of the sender address, required when the sender is a multisig address.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub multisig: Option<MultiSigAccount>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -629,6 +727,8 @@ pub struct MetadataOptions {
     pub internal_operation: InternalOperation,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub budget: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub multisig: Option<MultiSigAccount>,
 }
 
 impl IntoResponse for ConstructionPreprocessResponse {
@@ -668,6 +768,8 @@ pub struct ConstructionMetadata {
     pub gas_price: u64,
     pub budget: u64,
     pub currency: Option<Currency>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub multisig: Option<MultiSigAccount>,
 }
 
 impl IntoResponse for ConstructionMetadataResponse {
@@ -924,6 +1026,17 @@ pub enum InternalOperation {
         amounts: Vec<u64>,
         currency: Currency,
     },
+    /// Merge all coins of `currency` owned by the sender into one.
+    MergeCoins {
+        sender: MysAddress,
+        currency: Currency,
+    },
+    /// Split coins of `currency` owned by the sender into new coins of the given amounts.
+    SplitCoins {
+        sender: MysAddress,
+        amounts: Vec<u64>,
+        currency: Currency,
+    },
     Stake {
         sender: MysAddress,
         validator: MysAddress,
@@ -936,15 +1049,47 @@ pub enum InternalOperation {
     },
 }
 
+/// Appends the operation type and currency as the last pure input so that /construction/parse
+/// can recover the operation from the transaction data. Like the currency of `PayCoin`, the
+/// value is not used by any command.
+fn add_coin_operation_marker(
+    builder: &mut ProgrammableTransactionBuilder,
+    operation: OperationType,
+    currency: Currency,
+) -> Result<(), Error> {
+    let marker = serde_json::to_string(&CoinOperationMarker {
+        operation,
+        currency,
+    })
+    .map_err(|e| Error::InternalError(e.into()))?;
+    builder.pure(marker)?;
+    Ok(())
+}
+
 impl InternalOperation {
     pub fn sender(&self) -> MysAddress {
         match self {
             InternalOperation::PayMys { sender, .. }
             | InternalOperation::PayCoin { sender, .. }
+            | InternalOperation::MergeCoins { sender, .. }
+            | InternalOperation::SplitCoins { sender, .. }
             | InternalOperation::Stake { sender, .. }
             | InternalOperation::WithdrawStake { sender, .. } => *sender,
         }
     }
+
+    /// The currency of coin operations, `None` for operations that only involve MYS.
+    pub fn currency(&self) -> Option<&Currency> {
+        match self {
+            InternalOperation::PayCoin { currency, .. }
+            | InternalOperation::MergeCoins { currency, .. }
+            | InternalOperation::SplitCoins { currency, .. } => Some(currency),
+            InternalOperation::PayMys { .. }
+            | InternalOperation::Stake { .. }
+            | InternalOperation::WithdrawStake { .. } => None,
+        }
+    }
+
     /// Combine with ConstructionMetadata to form the TransactionData
     pub fn try_into_data(self, metadata: ConstructionMetadata) -> Result<TransactionData, Error> {
         let pt = match self {
@@ -975,6 +1120,43 @@ impl InternalOperation {
                 builder.pure(currency_str)?;
                 builder.finish()
             }
+            Self::MergeCoins { currency, .. } => {
+                let mut builder = ProgrammableTransactionBuilder::new();
+                // MYS coins are all used as gas coins, which merges them into the first one.
+                if !currency.is_mys() {
+                    let mut coins = metadata.objects.into_iter();
+                    let primary = coins
+                        .next()
+                        .ok_or_else(|| Error::InvalidInput("No coins to merge".to_string()))?;
+                    let primary = builder.obj(ObjectArg::ImmOrOwnedObject(primary))?;
+                    let coins = coins
+                        .map(|coin| builder.obj(ObjectArg::ImmOrOwnedObject(coin)))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    if coins.is_empty() {
+                        return Err(Error::InvalidInput(
+                            "At least two coins are needed to merge".to_string(),
+                        ));
+                    }
+                    builder.command(Command::MergeCoins(primary, coins));
+                }
+                add_coin_operation_marker(&mut builder, OperationType::MergeCoins, currency)?;
+                builder.finish()
+            }
+            Self::SplitCoins {
+                sender,
+                amounts,
+                currency,
+            } => {
+                let mut builder = ProgrammableTransactionBuilder::new();
+                let recipients = vec![sender; amounts.len()];
+                if currency.is_mys() {
+                    builder.pay_mys(recipients, amounts)?;
+                } else {
+                    builder.pay(metadata.objects, recipients, amounts)?;
+                }
+                add_coin_operation_marker(&mut builder, OperationType::SplitCoins, currency)?;
+                builder.finish()
+            }
             InternalOperation::Stake {
                 validator, amount, ..
             } => {
@@ -49,6 +49,7 @@ async fn test_operation_data_parsing_pay_mys() -> Result<(), anyhow::Error> {
         gas_price,
         budget: TEST_ONLY_GAS_UNIT_FOR_TRANSFER * gas_price,
         currency: None,
+        multisig: None,
     };
     let parsed_data = ops.into_internal()?.try_into_data(metadata)?;
     assert_eq!(data, parsed_data);
@@ -105,6 +106,7 @@ async fn test_operation_data_parsing_pay_coin() -> Result<(), anyhow::Error> {
         gas_price,
         budget: TEST_ONLY_GAS_UNIT_FOR_TRANSFER * gas_price,
         currency: Some(MYS.clone()),
+        multisig: None,
     };
     let parsed_data = ops.into_internal()?.try_into_data(metadata)?;
     assert_eq!(data, parsed_data);
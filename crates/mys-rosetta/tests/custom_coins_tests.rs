@@ -7,6 +7,7 @@ mod rosetta_client;
 mod test_coin_utils;
 
 use serde_json::json;
+use std::collections::HashMap;
 use std::num::NonZeroUsize;
 use std::path::Path;
 use mys_json_rpc_types::{
@@ -20,6 +21,7 @@ use mys_rosetta::types::{
 use mys_rosetta::types::{Currencies, OperationType};
 use mys_rosetta::CoinMetadataCache;
 use mys_rosetta::MYS;
+use mys_types::{Identifier, TypeTag};
 use test_cluster::TestClusterBuilder;
 use test_coin_utils::{init_package, mint};
 
@@ -77,6 +79,7 @@ async fn test_custom_coin_balance() {
         account_identifier: AccountIdentifier {
             address: address1,
             sub_account: None,
+            metadata: None,
         },
         block_identifier: Default::default(),
         currencies: Currencies(vec![mys_currency, test_coin_currency]),
@@ -301,3 +304,167 @@ async fn test_custom_coin_without_symbol() {
         }
     }
 }
+
+#[tokio::test]
+async fn test_custom_coin_merge_and_split() {
+    const COIN1_BALANCE: u64 = 100_000_000;
+    const COIN2_BALANCE: u64 = 200_000_000;
+    let test_cluster = TestClusterBuilder::new().build().await;
+    let sender = test_cluster.get_address_0();
+    let client = test_cluster.wallet.get_client().await.unwrap();
+    let keystore = &test_cluster.wallet.config.keystore;
+
+    // TEST_COIN setup and mint two coins to the sender
+    let init_ret = init_package(
+        &client,
+        keystore,
+        sender,
+        Path::new("tests/custom_coins/test_coin"),
+    )
+    .await
+    .unwrap();
+    let balances_to = vec![(COIN1_BALANCE, sender), (COIN2_BALANCE, sender)];
+    let coin_type = init_ret.coin_tag.to_canonical_string(true);
+    let _mint_res = mint(&client, keystore, init_ret, balances_to)
+        .await
+        .unwrap();
+
+    let (rosetta_client, _handle) = start_rosetta_test_server(client.clone()).await;
+    let currency = json!({
+        "symbol": "TEST_COIN",
+        "decimals": 6,
+        "metadata": {
+            "coin_type": coin_type.clone(),
+        }
+    });
+
+    let merge_ops: Operations = serde_json::from_value(json!(
+        [{
+            "operation_identifier":{"index":0},
+            "type":"MergeCoins",
+            "account": { "address" : sender.to_string() },
+            "amount" : { "value": "0", "currency": currency.clone() },
+        }]
+    ))
+    .unwrap();
+    let split_ops: Operations = serde_json::from_value(json!(
+        [{
+            "operation_identifier":{"index":0},
+            "type":"SplitCoins",
+            "account": { "address" : sender.to_string() },
+            "amount" : { "value": "10000000", "currency": currency.clone() },
+        },
+        {
+            "operation_identifier":{"index":1},
+            "type":"SplitCoins",
+            "account": { "address" : sender.to_string() },
+            "amount" : { "value": "20000000", "currency": currency.clone() },
+        },
+        {
+            "operation_identifier":{"index":2},
+            "type":"SplitCoins",
+            "account": { "address" : sender.to_string() },
+            "amount" : { "value": "-30000000", "currency": currency },
+        }]
+    ))
+    .unwrap();
+
+    let coin_cache = CoinMetadataCache::new(client.clone(), NonZeroUsize::new(2).unwrap());
+    for ops in [merge_ops, split_ops] {
+        let response = rosetta_client.rosetta_flow(&ops, keystore).await;
+
+        let tx = client
+            .read_api()
+            .get_transaction_with_options(
+                response.transaction_identifier.hash,
+                MysTransactionBlockResponseOptions::new()
+                    .with_input()
+                    .with_effects()
+                    .with_balance_changes()
+                    .with_events(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            &MysExecutionStatus::Success,
+            tx.effects.as_ref().unwrap().status()
+        );
+        let ops2 = Operations::try_from_response(tx, &coin_cache)
+            .await
+            .unwrap();
+        assert!(
+            ops2.contains(&ops),
+            "Operation mismatch. expecting:{}, got:{}",
+            serde_json::to_string(&ops).unwrap(),
+            serde_json::to_string(&ops2).unwrap()
+        );
+    }
+
+    let coins = client
+        .coin_read_api()
+        .get_coins(sender, Some(coin_type), None, None)
+        .await
+        .unwrap();
+    assert_eq!(coins.data.len(), 3);
+}
+
+#[tokio::test]
+async fn test_custom_coin_configured_metadata() {
+    let test_cluster = TestClusterBuilder::new().build().await;
+    let sender = test_cluster.get_address_0();
+    let client = test_cluster.wallet.get_client().await.unwrap();
+    let keystore = &test_cluster.wallet.config.keystore;
+
+    let init_ret = init_package(
+        &client,
+        keystore,
+        sender,
+        Path::new("tests/custom_coins/test_coin"),
+    )
+    .await
+    .unwrap();
+    let coin_tag = init_ret.coin_tag.clone();
+    let metadata_id = client
+        .coin_read_api()
+        .get_coin_metadata(coin_tag.to_canonical_string(true))
+        .await
+        .unwrap()
+        .and_then(|metadata| metadata.id)
+        .unwrap();
+
+    // A coin type of the same module without metadata on chain.
+    let TypeTag::Struct(mut other_type) = coin_tag.clone() else {
+        panic!("Coin type is not a struct");
+    };
+    other_type.name = Identifier::new("OTHER_COIN").unwrap();
+    let other_tag = TypeTag::Struct(other_type);
+
+    let coin_cache = CoinMetadataCache::new(client.clone(), NonZeroUsize::new(2).unwrap())
+        .with_metadata_objects(HashMap::from([
+            (coin_tag.clone(), metadata_id),
+            (other_tag.clone(), metadata_id),
+        ]));
+    let currency = coin_cache.get_currency(&coin_tag).await.unwrap();
+    assert_eq!(currency.symbol, "TEST_COIN");
+    assert_eq!(currency.decimals, 6);
+
+    // The configured object must be the `CoinMetadata` of the coin type.
+    let err = coin_cache.get_currency(&other_tag).await.unwrap_err();
+    assert!(
+        err.to_string()
+            .contains(&format!("Object {metadata_id} is not the CoinMetadata of")),
+        "unexpected error: {err}"
+    );
+
+    let coin_cache = CoinMetadataCache::new(client.clone(), NonZeroUsize::new(2).unwrap())
+        .with_metadata_objects(HashMap::from([(
+            other_tag.clone(),
+            init_ret.treasury_cap.0,
+        )]));
+    assert!(coin_cache.get_currency(&other_tag).await.is_err());
+
+    // Without a configured object the coin type has no metadata.
+    let coin_cache = CoinMetadataCache::new(client, NonZeroUsize::new(2).unwrap());
+    assert!(coin_cache.get_currency(&other_tag).await.is_err());
+}
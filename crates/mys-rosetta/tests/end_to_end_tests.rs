@@ -11,8 +11,8 @@ use mys_keys::keystore::AccountKeystore;
 use mys_rosetta::operations::Operations;
 use mys_rosetta::types::Currencies;
 use mys_rosetta::types::{
-    AccountBalanceRequest, AccountBalanceResponse, AccountIdentifier, Currency, NetworkIdentifier,
-    SubAccount, SubAccountType, MysEnv,
+    AccountBalanceRequest, AccountBalanceResponse, AccountIdentifier, Currency, MultiSigAccount,
+    MultiSigMember, NetworkIdentifier, SubAccount, SubAccountType, MysEnv,
 };
 use mys_rosetta::CoinMetadataCache;
 use mys_sdk::rpc_types::{MysExecutionStatus, MysTransactionBlockEffectsAPI};
@@ -46,6 +46,7 @@ async fn test_get_staked_mys() {
         account_identifier: AccountIdentifier {
             address,
             sub_account: None,
+            metadata: None,
         },
         block_identifier: Default::default(),
         currencies: Currencies(vec![Currency::default()]),
@@ -67,6 +68,7 @@ async fn test_get_staked_mys() {
             sub_account: Some(SubAccount {
                 account_type: SubAccountType::PendingStake,
             }),
+            metadata: None,
         },
         block_identifier: Default::default(),
         currencies: Currencies(vec![Currency::default()]),
@@ -440,6 +442,96 @@ async fn test_pay_mys() {
     );
 }
 
+#[tokio::test]
+async fn test_multisig_pay_mys() {
+    let test_cluster = TestClusterBuilder::new().build().await;
+    let funder = test_cluster.get_address_0();
+    let recipient = test_cluster.get_address_1();
+    let client = test_cluster.wallet.get_client().await.unwrap();
+    let keystore = &test_cluster.wallet.config.keystore;
+
+    let (rosetta_client, _handle) = start_rosetta_test_server(client.clone()).await;
+
+    // 2-of-3 multisig of the keystore addresses
+    let multisig = MultiSigAccount {
+        public_keys: test_cluster
+            .get_addresses()
+            .into_iter()
+            .take(3)
+            .map(|address| MultiSigMember {
+                public_key: keystore.get_key(&address).unwrap().public().into(),
+                weight: 1,
+            })
+            .collect(),
+        threshold: 2,
+    };
+    let sender = multisig.address().unwrap();
+
+    // Fund the multisig address
+    let ops = serde_json::from_value(json!(
+        [{
+            "operation_identifier":{"index":0},
+            "type":"PayMys",
+            "account": { "address" : sender.to_string() },
+            "amount" : { "value": "100000000000" }
+        },{
+            "operation_identifier":{"index":1},
+            "type":"PayMys",
+            "account": { "address" : funder.to_string() },
+            "amount" : { "value": "-100000000000" }
+        }]
+    ))
+    .unwrap();
+    rosetta_client.rosetta_flow(&ops, keystore).await;
+
+    let ops = serde_json::from_value(json!(
+        [{
+            "operation_identifier":{"index":0},
+            "type":"PayMys",
+            "account": { "address" : recipient.to_string() },
+            "amount" : { "value": "1000000000" }
+        },{
+            "operation_identifier":{"index":1},
+            "type":"PayMys",
+            "account": { "address" : sender.to_string() },
+            "amount" : { "value": "-1000000000" }
+        }]
+    ))
+    .unwrap();
+
+    let response = rosetta_client
+        .rosetta_multisig_flow(&ops, keystore, Some(multisig))
+        .await;
+
+    let tx = client
+        .read_api()
+        .get_transaction_with_options(
+            response.transaction_identifier.hash,
+            MysTransactionBlockResponseOptions::new()
+                .with_input()
+                .with_effects()
+                .with_balance_changes()
+                .with_events(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        &MysExecutionStatus::Success,
+        tx.effects.as_ref().unwrap().status()
+    );
+    let coin_cache = CoinMetadataCache::new(client, NonZeroUsize::new(2).unwrap());
+    let ops2 = Operations::try_from_response(tx, &coin_cache)
+        .await
+        .unwrap();
+    assert!(
+        ops2.contains(&ops),
+        "Operation mismatch. expecting:{}, got:{}",
+        serde_json::to_string(&ops).unwrap(),
+        serde_json::to_string(&ops2).unwrap()
+    );
+}
+
 #[tokio::test]
 async fn test_pay_mys_multiple_times() {
     let test_cluster = TestClusterBuilder::new()
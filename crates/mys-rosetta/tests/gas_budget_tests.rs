@@ -86,6 +86,7 @@ async fn pay_with_gas_budget(budget: u64) -> TransactionIdentifierResponseResult
 
     let metadata = Some(PreprocessMetadata {
         budget: Some(budget),
+        multisig: None,
     });
 
     let preprocess: ConstructionPreprocessResponse = rosetta_client
@@ -20,8 +20,9 @@ use mys_rosetta::types::{
     AccountBalanceRequest, AccountBalanceResponse, AccountIdentifier, ConstructionCombineRequest,
     ConstructionCombineResponse, ConstructionMetadataRequest, ConstructionMetadataResponse,
     ConstructionPayloadsRequest, ConstructionPayloadsResponse, ConstructionPreprocessRequest,
-    ConstructionPreprocessResponse, ConstructionSubmitRequest, Currencies, NetworkIdentifier,
-    Signature, SignatureType, SubAccount, SubAccountType, MysEnv, TransactionIdentifierResponse,
+    ConstructionPreprocessResponse, ConstructionSubmitRequest, Currencies, MultiSigAccount,
+    NetworkIdentifier, PreprocessMetadata, Signature, SignatureType, SubAccount, SubAccountType,
+    MysEnv, TransactionIdentifierResponse,
 };
 use mys_rosetta::{RosettaOfflineServer, RosettaOnlineServer};
 use mys_sdk::MysClient;
@@ -107,6 +108,17 @@ impl RosettaClient {
         &self,
         operations: &Operations,
         keystore: &Keystore,
+    ) -> TransactionIdentifierResponse {
+        self.rosetta_multisig_flow(operations, keystore, None).await
+    }
+
+    /// Same as `rosetta_flow`, for a multisig sender the members in the keystore sign until the
+    /// threshold is reached.
+    pub async fn rosetta_multisig_flow(
+        &self,
+        operations: &Operations,
+        keystore: &Keystore,
+        multisig: Option<MultiSigAccount>,
     ) -> TransactionIdentifierResponse {
         let network_identifier = NetworkIdentifier {
             blockchain: "mys".to_string(),
@@ -119,7 +131,10 @@ impl RosettaClient {
                 &ConstructionPreprocessRequest {
                     network_identifier: network_identifier.clone(),
                     operations: operations.clone(),
-                    metadata: None,
+                    metadata: multisig.clone().map(|multisig| PreprocessMetadata {
+                        budget: None,
+                        multisig: Some(multisig),
+                    }),
                 },
             )
             .await;
@@ -150,23 +165,43 @@ impl RosettaClient {
             .await;
         println!("Payload : {payloads:?}");
         // Combine
-        let signing_payload = payloads.payloads.first().unwrap();
-        let bytes = Hex::decode(&signing_payload.hex_bytes).unwrap();
-        let signer = signing_payload.account_identifier.address;
-        let signature = keystore.sign_hashed(&signer, &bytes).unwrap();
-        let public_key = keystore.get_key(&signer).unwrap().public();
+        let threshold = multisig.map_or(1, |multisig| multisig.threshold);
+        let mut weight = 0;
+        let mut signatures = vec![];
+        for signing_payload in &payloads.payloads {
+            if weight >= threshold {
+                break;
+            }
+            let bytes = Hex::decode(&signing_payload.hex_bytes).unwrap();
+            let signer = signing_payload.account_identifier.address;
+            let signature = keystore.sign_hashed(&signer, &bytes).unwrap();
+            let public_key = keystore.get_key(&signer).unwrap().public();
+            signatures.push(Signature {
+                signing_payload: signing_payload.clone(),
+                public_key: public_key.into(),
+                signature_type: SignatureType::Ed25519,
+                hex_bytes: Hex::from_bytes(MysSignature::signature_bytes(&signature)),
+            });
+            weight += signing_payload
+                .account_identifier
+                .metadata
+                .as_ref()
+                .and_then(|m| m.multisig.as_ref())
+                .and_then(|multisig| {
+                    multisig.public_keys.iter().find(|member| {
+                        let address: MysAddress = member.public_key.clone().try_into().unwrap();
+                        address == signer
+                    })
+                })
+                .map_or(1, |member| member.weight as u16);
+        }
         let combine: ConstructionCombineResponse = self
             .call(
                 RosettaEndpoint::Combine,
                 &ConstructionCombineRequest {
                     network_identifier: network_identifier.clone(),
                     unsigned_transaction: payloads.unsigned_transaction,
-                    signatures: vec![Signature {
-                        signing_payload: signing_payload.clone(),
-                        public_key: public_key.into(),
-                        signature_type: SignatureType::Ed25519,
-                        hex_bytes: Hex::from_bytes(MysSignature::signature_bytes(&signature)),
-                    }],
+                    signatures,
                 },
             )
             .await;
@@ -197,6 +232,7 @@ impl RosettaClient {
             account_identifier: AccountIdentifier {
                 address,
                 sub_account,
+                metadata: None,
             },
             block_identifier: Default::default(),
             currencies: Currencies(vec![]),
@@ -9,9 +9,10 @@ use tonic::{
     body::{boxed, BoxBody},
     server::NamedService,
 };
+use tokio_stream::StreamExt;
 use tower::{Service, ServiceExt};
 
-use crate::subscription::SubscriptionServiceHandle;
+use crate::subscription::SubscriptionFilter;
 
 pub type BoxError = Box<dyn std::error::Error + Send + Sync + 'static>;
 
@@ -307,10 +308,12 @@ impl crate::proto::node::v2::node_service_server::NodeService for crate::RpcServ
 }
 
 use crate::proto::node::v2alpha::SubscribeCheckpointsResponse;
+use crate::proto::node::v2alpha::SubscribeEventsResponse;
+use crate::proto::node::v2alpha::SubscribeTransactionsResponse;
 
 #[tonic::async_trait]
 impl crate::proto::node::v2alpha::subscription_service_server::SubscriptionService
-    for SubscriptionServiceHandle
+    for crate::RpcService
 {
     /// Server streaming response type for the SubscribeCheckpoints method.
     type SubscribeCheckpointsStream = Pin<
@@ -320,32 +323,148 @@ impl crate::proto::node::v2alpha::subscription_service_server::SubscriptionServi
         >,
     >;
 
+    /// Server streaming response type for the SubscribeTransactions method.
+    type SubscribeTransactionsStream = Pin<
+        Box<
+            dyn tokio_stream::Stream<Item = Result<SubscribeTransactionsResponse, tonic::Status>>
+                + Send,
+        >,
+    >;
+
+    /// Server streaming response type for the SubscribeEvents method.
+    type SubscribeEventsStream = Pin<
+        Box<dyn tokio_stream::Stream<Item = Result<SubscribeEventsResponse, tonic::Status>> + Send>,
+    >;
+
     async fn subscribe_checkpoints(
         &self,
         request: tonic::Request<crate::proto::node::v2alpha::SubscribeCheckpointsRequest>,
     ) -> Result<tonic::Response<Self::SubscribeCheckpointsStream>, tonic::Status> {
-        let read_mask = request.into_inner().read_mask.unwrap_or_default();
+        let request = request.into_inner();
+        let read_mask = request.read_mask.unwrap_or_default();
+        let filter = SubscriptionFilter::try_from(request.filters.as_slice())?;
 
-        let Some(mut receiver) = self.register_subscription().await else {
-            return Err(tonic::Status::unavailable(
-                "too many existing subscriptions",
-            ));
-        };
+        let mut checkpoints = self.checkpoint_subscription(request.start_cursor).await?;
+
+        let response = Box::pin(async_stream::stream! {
+            while let Some(checkpoint) = checkpoints.next().await {
+                let checkpoint = match checkpoint {
+                    Ok(checkpoint) => checkpoint,
+                    Err(e) => {
+                        yield Err(e.into());
+                        break;
+                    }
+                };
+
+                let matches = checkpoint
+                    .data
+                    .transactions
+                    .iter()
+                    .map(|transaction| filter.matches(transaction))
+                    .collect::<Vec<_>>();
+                if !filter.is_empty() && !matches.contains(&true) {
+                    continue;
+                }
+
+                let mut response = apply_checkpoint_read_mask(&read_mask, &checkpoint.response);
+                if !filter.is_empty() {
+                    let mut matches = matches.into_iter();
+                    response
+                        .transactions
+                        .retain(|_| matches.next().unwrap_or_default());
+                }
+
+                yield Ok(SubscribeCheckpointsResponse {
+                    cursor: Some(checkpoint.sequence_number()),
+                    checkpoint: Some(response),
+                });
+            }
+        });
+
+        Ok(tonic::Response::new(response))
+    }
+
+    async fn subscribe_transactions(
+        &self,
+        request: tonic::Request<crate::proto::node::v2alpha::SubscribeTransactionsRequest>,
+    ) -> Result<tonic::Response<Self::SubscribeTransactionsStream>, tonic::Status> {
+        let request = request.into_inner();
+        let read_mask = request.read_mask.unwrap_or_default();
+        let filter = SubscriptionFilter::try_from(request.filters.as_slice())?;
+
+        let mut checkpoints = self.checkpoint_subscription(request.start_cursor).await?;
 
         let response = Box::pin(async_stream::stream! {
-            while let Some(checkpoint) = receiver.recv().await {
-                let Some(cursor) = checkpoint.sequence_number else {
-                    yield Err(tonic::Status::internal("unable to determine cursor"));
-                    break;
+            while let Some(checkpoint) = checkpoints.next().await {
+                let checkpoint = match checkpoint {
+                    Ok(checkpoint) => checkpoint,
+                    Err(e) => {
+                        yield Err(e.into());
+                        break;
+                    }
                 };
 
-                let checkpoint = apply_checkpoint_read_mask(&read_mask, &checkpoint);
-                let response = SubscribeCheckpointsResponse {
-                    cursor: Some(cursor),
-                    checkpoint: Some(checkpoint),
+                for (index, (transaction, response)) in checkpoint
+                    .data
+                    .transactions
+                    .iter()
+                    .zip(&checkpoint.response.transactions)
+                    .enumerate()
+                {
+                    if !filter.matches(transaction) {
+                        continue;
+                    }
+
+                    yield Ok(SubscribeTransactionsResponse {
+                        cursor: Some(checkpoint.sequence_number()),
+                        transaction_index: Some(index as u64),
+                        transaction: Some(apply_transaction_read_mask(&read_mask, response)),
+                    });
+                }
+            }
+        });
+
+        Ok(tonic::Response::new(response))
+    }
+
+    async fn subscribe_events(
+        &self,
+        request: tonic::Request<crate::proto::node::v2alpha::SubscribeEventsRequest>,
+    ) -> Result<tonic::Response<Self::SubscribeEventsStream>, tonic::Status> {
+        let request = request.into_inner();
+        let filter = SubscriptionFilter::try_from(request.filters.as_slice())?;
+
+        let mut checkpoints = self.checkpoint_subscription(request.start_cursor).await?;
+
+        let response = Box::pin(async_stream::stream! {
+            while let Some(checkpoint) = checkpoints.next().await {
+                let checkpoint = match checkpoint {
+                    Ok(checkpoint) => checkpoint,
+                    Err(e) => {
+                        yield Err(e.into());
+                        break;
+                    }
                 };
 
-                yield Ok(response);
+                for (transaction, response) in checkpoint
+                    .data
+                    .transactions
+                    .iter()
+                    .zip(&checkpoint.response.transactions)
+                {
+                    let Some(events) = &response.events else {
+                        continue;
+                    };
+
+                    for index in filter.matching_events(transaction) {
+                        yield Ok(SubscribeEventsResponse {
+                            cursor: Some(checkpoint.sequence_number()),
+                            transaction_digest: response.digest.clone(),
+                            event_index: Some(index as u64),
+                            event: events.events.get(index).cloned(),
+                        });
+                    }
+                }
             }
         });
 
@@ -391,59 +510,7 @@ fn apply_checkpoint_read_mask(
                     .iter()
                     .zip(response.transactions.iter_mut())
                 {
-                    match component {
-                        "digest" => dst.digest = src.digest.clone(),
-                        "transaction" => dst.transaction = src.transaction.clone(),
-                        "transaction_bcs" => dst.transaction_bcs = src.transaction_bcs.clone(),
-                        "effects" => dst.effects = src.effects.clone(),
-                        "effects_bcs" => dst.effects_bcs = src.effects_bcs.clone(),
-                        "events" => dst.events = src.events.clone(),
-                        "events_bcs" => dst.events_bcs = src.events_bcs.clone(),
-                        "input_objects" => {
-                            let Some(component) = components.clone().next() else {
-                                dst.input_objects = src.input_objects.clone();
-                                continue;
-                            };
-                            if dst.input_objects.len() != src.input_objects.len() {
-                                dst.input_objects =
-                                    vec![Default::default(); src.input_objects.len()];
-                            }
-
-                            for (src, dst) in
-                                src.input_objects.iter().zip(dst.input_objects.iter_mut())
-                            {
-                                match component {
-                                    "object" => dst.object = src.object.clone(),
-                                    "object_bcs" => dst.object_bcs = src.object_bcs.clone(),
-                                    // Ignore unknown field
-                                    _ => {}
-                                }
-                            }
-                        }
-                        "output_objects" => {
-                            let Some(component) = components.clone().next() else {
-                                dst.output_objects = src.output_objects.clone();
-                                continue;
-                            };
-                            if dst.output_objects.len() != src.output_objects.len() {
-                                dst.output_objects =
-                                    vec![Default::default(); src.output_objects.len()];
-                            }
-
-                            for (src, dst) in
-                                src.output_objects.iter().zip(dst.output_objects.iter_mut())
-                            {
-                                match component {
-                                    "object" => dst.object = src.object.clone(),
-                                    "object_bcs" => dst.object_bcs = src.object_bcs.clone(),
-                                    // Ignore unknown field
-                                    _ => {}
-                                }
-                            }
-                        }
-                        // Ignore unknown field
-                        _ => {}
-                    }
+                    apply_transaction_read_mask_path(component, components.clone(), src, dst);
                 }
             }
             // Ignore unknown field
@@ -454,6 +521,80 @@ fn apply_checkpoint_read_mask(
     response
 }
 
+// Same as `apply_checkpoint_read_mask` but for a single transaction.
+fn apply_transaction_read_mask(
+    read_mask: &prost_types::FieldMask,
+    transaction: &crate::proto::node::v2::FullCheckpointTransaction,
+) -> crate::proto::node::v2::FullCheckpointTransaction {
+    let mut response = crate::proto::node::v2::FullCheckpointTransaction::default();
+
+    for path in &read_mask.paths {
+        let mut components = path.split('.');
+        let Some(component) = components.next() else {
+            continue;
+        };
+
+        apply_transaction_read_mask_path(component, components, transaction, &mut response);
+    }
+
+    response
+}
+
+fn apply_transaction_read_mask_path(
+    component: &str,
+    mut components: std::str::Split<'_, char>,
+    src: &crate::proto::node::v2::FullCheckpointTransaction,
+    dst: &mut crate::proto::node::v2::FullCheckpointTransaction,
+) {
+    match component {
+        "digest" => dst.digest = src.digest.clone(),
+        "transaction" => dst.transaction = src.transaction.clone(),
+        "transaction_bcs" => dst.transaction_bcs = src.transaction_bcs.clone(),
+        "effects" => dst.effects = src.effects.clone(),
+        "effects_bcs" => dst.effects_bcs = src.effects_bcs.clone(),
+        "events" => dst.events = src.events.clone(),
+        "events_bcs" => dst.events_bcs = src.events_bcs.clone(),
+        "input_objects" => {
+            let Some(component) = components.next() else {
+                dst.input_objects = src.input_objects.clone();
+                return;
+            };
+            if dst.input_objects.len() != src.input_objects.len() {
+                dst.input_objects = vec![Default::default(); src.input_objects.len()];
+            }
+
+            for (src, dst) in src.input_objects.iter().zip(dst.input_objects.iter_mut()) {
+                match component {
+                    "object" => dst.object = src.object.clone(),
+                    "object_bcs" => dst.object_bcs = src.object_bcs.clone(),
+                    // Ignore unknown field
+                    _ => {}
+                }
+            }
+        }
+        "output_objects" => {
+            let Some(component) = components.next() else {
+                dst.output_objects = src.output_objects.clone();
+                return;
+            };
+            if dst.output_objects.len() != src.output_objects.len() {
+                dst.output_objects = vec![Default::default(); src.output_objects.len()];
+            }
+
+            for (src, dst) in src.output_objects.iter().zip(dst.output_objects.iter_mut()) {
+                match component {
+                    "object" => dst.object = src.object.clone(),
+                    "object_bcs" => dst.object_bcs = src.object_bcs.clone(),
+                    // Ignore unknown field
+                    _ => {}
+                }
+            }
+        }
+        // Ignore unknown field
+        _ => {}
+    }
+}
+
 #[tonic::async_trait]
 impl crate::proto::node::v2alpha::node_service_server::NodeService for crate::RpcService {
     async fn get_coin_info(
@@ -490,6 +631,34 @@ impl crate::proto::node::v2alpha::node_service_server::NodeService for crate::Rp
             .map_err(Into::into)
     }
 
+    async fn get_object_at_checkpoint(
+        &self,
+        request: tonic::Request<crate::proto::node::v2alpha::GetObjectAtCheckpointRequest>,
+    ) -> std::result::Result<
+        tonic::Response<crate::proto::node::v2::GetObjectResponse>,
+        tonic::Status,
+    > {
+        let request = request.into_inner();
+        let object_id = request
+            .object_id
+            .as_ref()
+            .ok_or_else(|| tonic::Status::new(tonic::Code::InvalidArgument, "missing object_id"))?
+            .try_into()
+            .map_err(|_| tonic::Status::new(tonic::Code::InvalidArgument, "invalid object_id"))?;
+        let checkpoint = request.checkpoint.ok_or_else(|| {
+            tonic::Status::new(tonic::Code::InvalidArgument, "missing checkpoint")
+        })?;
+        let options = request
+            .read_mask
+            .map(crate::types::GetObjectOptions::from_read_mask)
+            .unwrap_or_default();
+
+        self.get_object_at_checkpoint(object_id, checkpoint, options)
+            .map(Into::into)
+            .map(tonic::Response::new)
+            .map_err(Into::into)
+    }
+
     async fn get_protocol_config(
         &self,
         request: tonic::Request<crate::proto::node::v2alpha::GetProtocolConfigRequest>,
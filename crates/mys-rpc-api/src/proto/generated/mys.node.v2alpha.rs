@@ -200,6 +200,11 @@ pub struct ListAccountObjectsRequest {
     /// match the call that provided the page token.
     #[prost(string, optional, tag = "3")]
     pub page_token: ::core::option::Option<::prost::alloc::string::String>,
+    /// Optional. List the objects owned by the account as of this checkpoint
+    /// instead of the latest ones. Only checkpoints which are still covered by
+    /// the node's object history index can be queried.
+    #[prost(uint64, optional, tag = "4")]
+    pub checkpoint: ::core::option::Option<u64>,
 }
 /// Response message for `NodeService.ListAccountObjects`
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -223,6 +228,22 @@ pub struct AccountObject {
     #[prost(message, optional, tag = "4")]
     pub object_type: ::core::option::Option<super::super::types::StructTag>,
 }
+/// Request message for `NodeService.GetObjectAtCheckpoint`
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetObjectAtCheckpointRequest {
+    /// Required. The `ObjectId` of the requested object.
+    #[prost(message, optional, tag = "1")]
+    pub object_id: ::core::option::Option<super::super::types::ObjectId>,
+    /// Required. The checkpoint as of which the object should be looked up. The
+    /// returned object is the version which was live at the end of this
+    /// checkpoint.
+    #[prost(uint64, optional, tag = "2")]
+    pub checkpoint: ::core::option::Option<u64>,
+    /// Optional. Mask for specifying which parts of the `GetObjectResponse`
+    /// should be returned.
+    #[prost(message, optional, tag = "3")]
+    pub read_mask: ::core::option::Option<::prost_types::FieldMask>,
+}
 /// Generated client implementations.
 pub mod node_service_client {
     #![allow(
@@ -393,6 +414,36 @@ pub mod node_service_client {
                 );
             self.inner.unary(req, path, codec).await
         }
+        /// Request the version of an object which was live as of a particular checkpoint.
+        pub async fn get_object_at_checkpoint(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetObjectAtCheckpointRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::super::v2::GetObjectResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/mys.node.v2alpha.NodeService/GetObjectAtCheckpoint",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new(
+                        "mys.node.v2alpha.NodeService",
+                        "GetObjectAtCheckpoint",
+                    ),
+                );
+            self.inner.unary(req, path, codec).await
+        }
         pub async fn get_protocol_config(
             &mut self,
             request: impl tonic::IntoRequest<super::GetProtocolConfigRequest>,
@@ -536,6 +587,14 @@ pub mod node_service_server {
             tonic::Response<super::ListAccountObjectsResponse>,
             tonic::Status,
         >;
+        /// Request the version of an object which was live as of a particular checkpoint.
+        async fn get_object_at_checkpoint(
+            &self,
+            request: tonic::Request<super::GetObjectAtCheckpointRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::super::v2::GetObjectResponse>,
+            tonic::Status,
+        >;
         async fn get_protocol_config(
             &self,
             request: tonic::Request<super::GetProtocolConfigRequest>,
@@ -779,6 +838,55 @@ pub mod node_service_server {
                     };
                     Box::pin(fut)
                 }
+                "/mys.node.v2alpha.NodeService/GetObjectAtCheckpoint" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetObjectAtCheckpointSvc<T: NodeService>(pub Arc<T>);
+                    impl<
+                        T: NodeService,
+                    > tonic::server::UnaryService<super::GetObjectAtCheckpointRequest>
+                    for GetObjectAtCheckpointSvc<T> {
+                        type Response = super::super::v2::GetObjectResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetObjectAtCheckpointRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as NodeService>::get_object_at_checkpoint(
+                                        &inner,
+                                        request,
+                                    )
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = GetObjectAtCheckpointSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 "/mys.node.v2alpha.NodeService/GetProtocolConfig" => {
                     #[allow(non_camel_case_types)]
                     struct GetProtocolConfigSvc<T: NodeService>(pub Arc<T>);
@@ -1003,6 +1111,17 @@ pub mod node_service_server {
 /// Request message for SubscriptionService.SubscribeCheckpoints
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct SubscribeCheckpointsRequest {
+    /// Optional. The checkpoint to start the subscription from. When provided
+    /// the server will first replay all checkpoints from `start_cursor` up to
+    /// the latest executed checkpoint before continuing with the live stream.
+    /// Returns `OUT_OF_RANGE` if `start_cursor` has already been pruned.
+    #[prost(uint64, optional, tag = "1")]
+    pub start_cursor: ::core::option::Option<u64>,
+    /// Optional. When provided, only the transactions matching at least one of
+    /// the filters are included in each checkpoint and checkpoints without any
+    /// matching transactions are skipped.
+    #[prost(message, repeated, tag = "2")]
+    pub filters: ::prost::alloc::vec::Vec<TransactionFilter>,
     /// Optional. Mask for specifiying which parts of the
     /// SubscribeCheckpointsResponse should be returned.
     #[prost(message, optional, tag = "3")]
@@ -1019,6 +1138,106 @@ pub struct SubscribeCheckpointsResponse {
     #[prost(message, optional, tag = "2")]
     pub checkpoint: ::core::option::Option<super::v2::GetFullCheckpointResponse>,
 }
+/// Request message for SubscriptionService.SubscribeTransactions
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeTransactionsRequest {
+    /// Optional. The checkpoint to start the subscription from.
+    ///
+    /// See `SubscribeCheckpointsRequest.start_cursor`.
+    #[prost(uint64, optional, tag = "1")]
+    pub start_cursor: ::core::option::Option<u64>,
+    /// Optional. When provided, only the transactions matching at least one of
+    /// the filters are emitted.
+    #[prost(message, repeated, tag = "2")]
+    pub filters: ::prost::alloc::vec::Vec<TransactionFilter>,
+    /// Optional. Mask for specifiying which parts of the
+    /// FullCheckpointTransaction should be returned.
+    #[prost(message, optional, tag = "3")]
+    pub read_mask: ::core::option::Option<::prost_types::FieldMask>,
+}
+/// Response message for SubscriptionService.SubscribeTransactions
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeTransactionsResponse {
+    /// Required. The sequence number of the checkpoint which includes this
+    /// transaction.
+    #[prost(uint64, optional, tag = "1")]
+    pub cursor: ::core::option::Option<u64>,
+    /// Required. The position of this transaction in its checkpoint.
+    #[prost(uint64, optional, tag = "2")]
+    pub transaction_index: ::core::option::Option<u64>,
+    /// The requested data for this transaction
+    #[prost(message, optional, tag = "3")]
+    pub transaction: ::core::option::Option<super::v2::FullCheckpointTransaction>,
+}
+/// Request message for SubscriptionService.SubscribeEvents
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeEventsRequest {
+    /// Optional. The checkpoint to start the subscription from.
+    ///
+    /// See `SubscribeCheckpointsRequest.start_cursor`.
+    #[prost(uint64, optional, tag = "1")]
+    pub start_cursor: ::core::option::Option<u64>,
+    /// Optional. When provided, only the events emitted by transactions matching
+    /// at least one of the filters are emitted. If the matching filter specifies
+    /// an `event_type` then only the events of that type are emitted.
+    #[prost(message, repeated, tag = "2")]
+    pub filters: ::prost::alloc::vec::Vec<TransactionFilter>,
+}
+/// Response message for SubscriptionService.SubscribeEvents
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeEventsResponse {
+    /// Required. The sequence number of the checkpoint which includes the
+    /// transaction that emitted this event.
+    #[prost(uint64, optional, tag = "1")]
+    pub cursor: ::core::option::Option<u64>,
+    /// Required. The digest of the transaction that emitted this event.
+    #[prost(message, optional, tag = "2")]
+    pub transaction_digest: ::core::option::Option<super::super::types::Digest>,
+    /// Required. The position of this event in the transaction's events.
+    #[prost(uint64, optional, tag = "3")]
+    pub event_index: ::core::option::Option<u64>,
+    /// Required. The event.
+    #[prost(message, optional, tag = "4")]
+    pub event: ::core::option::Option<super::super::types::Event>,
+}
+/// Filter selecting the transactions a subscription is interested in.
+///
+/// A transaction matches a filter only if it matches every criteria which is
+/// set on the filter.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TransactionFilter {
+    /// Optional. Only match transactions sent by this address.
+    #[prost(message, optional, tag = "1")]
+    pub sender: ::core::option::Option<super::super::types::Address>,
+    /// Optional. Only match transactions which include a matching Move call.
+    #[prost(message, optional, tag = "2")]
+    pub move_call: ::core::option::Option<MoveCallFilter>,
+    /// Optional. Only match transactions which emitted an event of this type,
+    /// e.g. `0x2::coin::CurrencyCreated`. If no type parameters are provided then
+    /// events with any type parameters will be matched.
+    #[prost(string, optional, tag = "3")]
+    pub event_type: ::core::option::Option<::prost::alloc::string::String>,
+    /// Optional. Only match transactions which read or wrote this object.
+    #[prost(message, optional, tag = "4")]
+    pub object: ::core::option::Option<super::super::types::ObjectId>,
+    /// Optional. Only match transactions which read or wrote an object owned by
+    /// this address.
+    #[prost(message, optional, tag = "5")]
+    pub owner: ::core::option::Option<super::super::types::Address>,
+}
+/// Filter selecting Move calls by their target.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MoveCallFilter {
+    /// Required. The package of the called function.
+    #[prost(message, optional, tag = "1")]
+    pub package: ::core::option::Option<super::super::types::ObjectId>,
+    /// Optional. The module of the called function.
+    #[prost(string, optional, tag = "2")]
+    pub module: ::core::option::Option<::prost::alloc::string::String>,
+    /// Optional. The name of the called function. Requires `module` to be set.
+    #[prost(string, optional, tag = "3")]
+    pub function: ::core::option::Option<::prost::alloc::string::String>,
+}
 /// Generated client implementations.
 pub mod subscription_service_client {
     #![allow(
@@ -1123,6 +1342,11 @@ pub mod subscription_service_client {
         /// and then leverage other APIs (e.g.
         /// mys.node.v2.NodeService.GetFullCheckpoint) in order to request data for
         /// the checkpoints they missed.
+        ///
+        /// Alternatively clients can provide a `start_cursor` in order to have the
+        /// server catch them up from its history before continuing with the live
+        /// stream. A set of `filters` can be provided in order to only receive the
+        /// transactions which are of interest.
         pub async fn subscribe_checkpoints(
             &mut self,
             request: impl tonic::IntoRequest<super::SubscribeCheckpointsRequest>,
@@ -1154,6 +1378,78 @@ pub mod subscription_service_client {
                 );
             self.inner.server_streaming(req, path, codec).await
         }
+        /// Subscribe to the stream of transactions.
+        ///
+        /// Behaves like `SubscribeCheckpoints` except that individual transactions
+        /// are returned and only the transactions matching the provided `filters`
+        /// are emitted.
+        pub async fn subscribe_transactions(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SubscribeTransactionsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<
+                tonic::codec::Streaming<super::SubscribeTransactionsResponse>,
+            >,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/mys.node.v2alpha.SubscriptionService/SubscribeTransactions",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new(
+                        "mys.node.v2alpha.SubscriptionService",
+                        "SubscribeTransactions",
+                    ),
+                );
+            self.inner.server_streaming(req, path, codec).await
+        }
+        /// Subscribe to the stream of events.
+        ///
+        /// Behaves like `SubscribeCheckpoints` except that individual events are
+        /// returned and only the events emitted by transactions matching the
+        /// provided `filters` are emitted.
+        pub async fn subscribe_events(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SubscribeEventsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<
+                tonic::codec::Streaming<super::SubscribeEventsResponse>,
+            >,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/mys.node.v2alpha.SubscriptionService/SubscribeEvents",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new(
+                        "mys.node.v2alpha.SubscriptionService",
+                        "SubscribeEvents",
+                    ),
+                );
+            self.inner.server_streaming(req, path, codec).await
+        }
     }
 }
 /// Generated server implementations.
@@ -1190,6 +1486,11 @@ pub mod subscription_service_server {
         /// and then leverage other APIs (e.g.
         /// mys.node.v2.NodeService.GetFullCheckpoint) in order to request data for
         /// the checkpoints they missed.
+        ///
+        /// Alternatively clients can provide a `start_cursor` in order to have the
+        /// server catch them up from its history before continuing with the live
+        /// stream. A set of `filters` can be provided in order to only receive the
+        /// transactions which are of interest.
         async fn subscribe_checkpoints(
             &self,
             request: tonic::Request<super::SubscribeCheckpointsRequest>,
@@ -1197,6 +1498,48 @@ pub mod subscription_service_server {
             tonic::Response<Self::SubscribeCheckpointsStream>,
             tonic::Status,
         >;
+        /// Server streaming response type for the SubscribeTransactions method.
+        type SubscribeTransactionsStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<
+                    super::SubscribeTransactionsResponse,
+                    tonic::Status,
+                >,
+            >
+            + std::marker::Send
+            + 'static;
+        /// Subscribe to the stream of transactions.
+        ///
+        /// Behaves like `SubscribeCheckpoints` except that individual transactions
+        /// are returned and only the transactions matching the provided `filters`
+        /// are emitted.
+        async fn subscribe_transactions(
+            &self,
+            request: tonic::Request<super::SubscribeTransactionsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<Self::SubscribeTransactionsStream>,
+            tonic::Status,
+        >;
+        /// Server streaming response type for the SubscribeEvents method.
+        type SubscribeEventsStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<
+                    super::SubscribeEventsResponse,
+                    tonic::Status,
+                >,
+            >
+            + std::marker::Send
+            + 'static;
+        /// Subscribe to the stream of events.
+        ///
+        /// Behaves like `SubscribeCheckpoints` except that individual events are
+        /// returned and only the events emitted by transactions matching the
+        /// provided `filters` are emitted.
+        async fn subscribe_events(
+            &self,
+            request: tonic::Request<super::SubscribeEventsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<Self::SubscribeEventsStream>,
+            tonic::Status,
+        >;
     }
     /// Service for subscribing to data from a Mys Fullnode
     #[derive(Debug)]
@@ -1326,6 +1669,108 @@ pub mod subscription_service_server {
                     };
                     Box::pin(fut)
                 }
+                "/mys.node.v2alpha.SubscriptionService/SubscribeTransactions" => {
+                    #[allow(non_camel_case_types)]
+                    struct SubscribeTransactionsSvc<T: SubscriptionService>(pub Arc<T>);
+                    impl<
+                        T: SubscriptionService,
+                    > tonic::server::ServerStreamingService<
+                        super::SubscribeTransactionsRequest,
+                    > for SubscribeTransactionsSvc<T> {
+                        type Response = super::SubscribeTransactionsResponse;
+                        type ResponseStream = T::SubscribeTransactionsStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SubscribeTransactionsRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SubscriptionService>::subscribe_transactions(
+                                        &inner,
+                                        request,
+                                    )
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = SubscribeTransactionsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/mys.node.v2alpha.SubscriptionService/SubscribeEvents" => {
+                    #[allow(non_camel_case_types)]
+                    struct SubscribeEventsSvc<T: SubscriptionService>(pub Arc<T>);
+                    impl<
+                        T: SubscriptionService,
+                    > tonic::server::ServerStreamingService<
+                        super::SubscribeEventsRequest,
+                    > for SubscribeEventsSvc<T> {
+                        type Response = super::SubscribeEventsResponse;
+                        type ResponseStream = T::SubscribeEventsStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SubscribeEventsRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SubscriptionService>::subscribe_events(
+                                        &inner,
+                                        request,
+                                    )
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = SubscribeEventsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 _ => {
                     Box::pin(async move {
                         let mut response = http::Response::new(empty_body());
@@ -42,8 +42,18 @@ impl RpcService {
             .map(|token| decode_page_token(&token))
             .transpose()?;
 
-        let mut object_info = indexes
-            .account_owned_objects_info_iter(owner.into(), page_token.map(Into::into))?
+        let iter = if let Some(checkpoint) = request.checkpoint {
+            self.check_object_history_available(indexes, checkpoint, 0)?;
+            indexes.account_owned_objects_info_at_checkpoint_iter(
+                owner.into(),
+                checkpoint,
+                page_token.map(Into::into),
+            )?
+        } else {
+            indexes.account_owned_objects_info_iter(owner.into(), page_token.map(Into::into))?
+        };
+
+        let mut object_info = iter
             .take(page_size + 1)
             .map(|info| {
                 AccountOwnedObjectInfo {
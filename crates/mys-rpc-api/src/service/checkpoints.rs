@@ -128,7 +128,7 @@ impl RpcService {
             .inner()
             .get_checkpoint_data(verified_summary, checkpoint_contents)?;
 
-        checkpoint_data_to_full_checkpoint_response(checkpoint, options)
+        checkpoint_data_to_full_checkpoint_response(&checkpoint, options)
     }
 }
 
@@ -137,24 +137,25 @@ pub(crate) fn checkpoint_data_to_full_checkpoint_response(
         checkpoint_summary,
         checkpoint_contents,
         transactions,
-    }: mys_types::full_checkpoint_content::CheckpointData,
+    }: &mys_types::full_checkpoint_content::CheckpointData,
     options: &GetFullCheckpointOptions,
 ) -> Result<FullCheckpointResponse> {
     let sequence_number = checkpoint_summary.sequence_number;
     let digest = checkpoint_summary.digest().to_owned().into();
-    let (summary, signature) = checkpoint_summary.into_data_and_sig();
+    let summary = checkpoint_summary.data();
+    let signature = checkpoint_summary.auth_sig();
 
     let summary_bcs = options
         .include_summary_bcs()
-        .then(|| bcs::to_bytes(&summary))
+        .then(|| bcs::to_bytes(summary))
         .transpose()?;
     let contents_bcs = options
         .include_contents_bcs()
-        .then(|| bcs::to_bytes(&checkpoint_contents))
+        .then(|| bcs::to_bytes(checkpoint_contents))
         .transpose()?;
 
     let transactions = transactions
-        .into_iter()
+        .iter()
         .map(|transaction| transaction_to_checkpoint_transaction(transaction, options))
         .collect::<Result<_>>()?;
 
@@ -163,13 +164,15 @@ pub(crate) fn checkpoint_data_to_full_checkpoint_response(
         digest,
         summary: options
             .include_summary()
-            .then(|| summary.try_into())
+            .then(|| summary.clone().try_into())
             .transpose()?,
         summary_bcs,
-        signature: options.include_signature().then(|| signature.into()),
+        signature: options
+            .include_signature()
+            .then(|| signature.clone().into()),
         contents: options
             .include_contents()
-            .then(|| checkpoint_contents.try_into())
+            .then(|| checkpoint_contents.clone().try_into())
             .transpose()?,
         contents_bcs,
         transactions,
@@ -184,26 +187,26 @@ fn transaction_to_checkpoint_transaction(
         events,
         input_objects,
         output_objects,
-    }: mys_types::full_checkpoint_content::CheckpointTransaction,
+    }: &mys_types::full_checkpoint_content::CheckpointTransaction,
     options: &GetFullCheckpointOptions,
 ) -> Result<FullCheckpointTransaction> {
     let digest = transaction.digest().to_owned().into();
-    let transaction = transaction.into_data().into_inner().intent_message.value;
+    let transaction = transaction.transaction_data();
     let transaction_bcs = options
         .include_transaction_bcs()
-        .then(|| bcs::to_bytes(&transaction))
+        .then(|| bcs::to_bytes(transaction))
         .transpose()?;
     let transaction = options
         .include_transaction()
-        .then(|| transaction.try_into())
+        .then(|| transaction.clone().try_into())
         .transpose()?;
     let effects_bcs = options
         .include_effects_bcs()
-        .then(|| bcs::to_bytes(&effects))
+        .then(|| bcs::to_bytes(effects))
         .transpose()?;
     let effects = options
         .include_effects()
-        .then(|| effects.try_into())
+        .then(|| effects.clone().try_into())
         .transpose()?;
     let events_bcs = options
         .include_events_bcs()
@@ -212,7 +215,7 @@ fn transaction_to_checkpoint_transaction(
         .transpose()?;
     let events = options
         .include_events()
-        .then(|| events.map(TryInto::try_into))
+        .then(|| events.clone().map(TryInto::try_into))
         .flatten()
         .transpose()?;
 
@@ -220,7 +223,7 @@ fn transaction_to_checkpoint_transaction(
         .include_input_objects()
         .then(|| {
             input_objects
-                .into_iter()
+                .iter()
                 .map(|object| object_to_object_response(object, options))
                 .collect::<Result<_>>()
         })
@@ -229,7 +232,7 @@ fn transaction_to_checkpoint_transaction(
         .include_output_objects()
         .then(|| {
             output_objects
-                .into_iter()
+                .iter()
                 .map(|object| object_to_object_response(object, options))
                 .collect::<Result<_>>()
         })
@@ -250,7 +253,7 @@ fn transaction_to_checkpoint_transaction(
 }
 
 fn object_to_object_response(
-    object: mys_types::object::Object,
+    object: &mys_types::object::Object,
     options: &GetFullCheckpointOptions,
 ) -> Result<FullCheckpointObject> {
     let object_id = object.id().into();
@@ -259,11 +262,11 @@ fn object_to_object_response(
 
     let object_bcs = options
         .include_object_bcs()
-        .then(|| bcs::to_bytes(&object))
+        .then(|| bcs::to_bytes(object))
         .transpose()?;
     let object = options
         .include_object()
-        .then(|| object.try_into())
+        .then(|| object.clone().try_into())
         .transpose()?;
 
     FullCheckpointObject {
@@ -10,4 +10,5 @@ pub(crate) mod health;
 mod info;
 pub(crate) mod objects;
 mod protocol_config;
+mod subscription;
 pub(crate) mod transactions;
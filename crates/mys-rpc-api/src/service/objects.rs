@@ -9,12 +9,13 @@ use crate::types::ObjectResponse;
 use crate::Result;
 use crate::RpcError;
 use crate::RpcService;
+use mys_sdk_types::CheckpointSequenceNumber;
 use mys_sdk_types::ObjectId;
 use mys_sdk_types::TypeTag;
 use mys_sdk_types::Version;
 use mys_types::mys_sdk_types_conversions::type_tag_core_to_sdk;
 use mys_types::{
-    storage::{DynamicFieldIndexInfo, DynamicFieldKey},
+    storage::{DynamicFieldIndexInfo, DynamicFieldKey, RpcIndexes},
     mys_sdk_types_conversions::SdkTypeConversionError,
 };
 use tap::Pipe;
@@ -50,12 +51,100 @@ impl RpcService {
         }
         .pipe(Ok)
     }
+
+    pub fn get_object_at_checkpoint(
+        &self,
+        object_id: ObjectId,
+        checkpoint: CheckpointSequenceNumber,
+        options: GetObjectOptions,
+    ) -> Result<ObjectResponse> {
+        let indexes = self
+            .reader
+            .inner()
+            .indexes()
+            .ok_or_else(RpcError::not_found)?;
+
+        // Object contents older than the lowest available checkpoint for objects may already have
+        // been pruned, even if the index still knows which version was live.
+        let lowest_available_checkpoint = self
+            .reader
+            .inner()
+            .get_lowest_available_checkpoint_objects()?;
+        self.check_object_history_available(indexes, checkpoint, lowest_available_checkpoint)?;
+
+        let version = indexes
+            .get_object_version_at_checkpoint(object_id.into(), checkpoint)?
+            .ok_or_else(|| ObjectNotFoundError::new_at_checkpoint(object_id, checkpoint))?;
+
+        self.get_object(object_id, Some(version.into()), options)
+    }
+
+    /// Checks that `checkpoint` falls within the range of checkpoints which the object history
+    /// index is able to answer queries for.
+    pub(crate) fn check_object_history_available(
+        &self,
+        indexes: &dyn RpcIndexes,
+        checkpoint: CheckpointSequenceNumber,
+        lowest_available_checkpoint: CheckpointSequenceNumber,
+    ) -> Result<()> {
+        let lowest_available_checkpoint = indexes
+            .get_lowest_available_checkpoint_object_history()?
+            .max(lowest_available_checkpoint);
+        let latest_checkpoint = self.reader.inner().get_latest_checkpoint()?.sequence_number;
+
+        if checkpoint < lowest_available_checkpoint {
+            return Err(ObjectHistoryPrunedError {
+                checkpoint,
+                lowest_available_checkpoint,
+            }
+            .into());
+        }
+
+        if checkpoint > latest_checkpoint {
+            return Err(RpcError::new(
+                tonic::Code::OutOfRange,
+                format!(
+                    "checkpoint {checkpoint} is newer than the latest checkpoint \
+                    {latest_checkpoint}"
+                ),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Returned when querying object history for a checkpoint which has already been pruned by the
+/// node.
+#[derive(Debug)]
+pub struct ObjectHistoryPrunedError {
+    checkpoint: CheckpointSequenceNumber,
+    lowest_available_checkpoint: CheckpointSequenceNumber,
+}
+
+impl std::fmt::Display for ObjectHistoryPrunedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Object history for checkpoint {} has been pruned, the lowest available checkpoint is {}",
+            self.checkpoint, self.lowest_available_checkpoint
+        )
+    }
+}
+
+impl std::error::Error for ObjectHistoryPrunedError {}
+
+impl From<ObjectHistoryPrunedError> for crate::RpcError {
+    fn from(value: ObjectHistoryPrunedError) -> Self {
+        Self::new(tonic::Code::NotFound, value.to_string())
+    }
 }
 
 #[derive(Debug)]
 pub struct ObjectNotFoundError {
     object_id: ObjectId,
     version: Option<Version>,
+    checkpoint: Option<CheckpointSequenceNumber>,
 }
 
 impl ObjectNotFoundError {
@@ -63,6 +152,7 @@ impl ObjectNotFoundError {
         Self {
             object_id,
             version: None,
+            checkpoint: None,
         }
     }
 
@@ -70,6 +160,15 @@ impl ObjectNotFoundError {
         Self {
             object_id,
             version: Some(version),
+            checkpoint: None,
+        }
+    }
+
+    pub fn new_at_checkpoint(object_id: ObjectId, checkpoint: CheckpointSequenceNumber) -> Self {
+        Self {
+            object_id,
+            version: None,
+            checkpoint: Some(checkpoint),
         }
     }
 }
@@ -82,6 +181,10 @@ impl std::fmt::Display for ObjectNotFoundError {
             write!(f, " with version {version}")?;
         }
 
+        if let Some(checkpoint) = self.checkpoint {
+            write!(f, " as of checkpoint {checkpoint}")?;
+        }
+
         write!(f, " not found")
     }
 }
@@ -210,3 +313,399 @@ impl DynamicFieldInfo {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::node::v2alpha::node_service_server::NodeService;
+    use crate::proto::node::v2alpha::GetObjectAtCheckpointRequest;
+    use move_core_types::language_storage::StructTag;
+    use mys_types::base_types::{EpochId, MysAddress, ObjectID, SequenceNumber};
+    use mys_types::committee::Committee;
+    use mys_types::digests::{
+        ChainIdentifier, CheckpointContentsDigest, CheckpointDigest, TransactionDigest,
+        TransactionEventsDigest,
+    };
+    use mys_types::effects::{TransactionEffects, TransactionEvents};
+    use mys_types::full_checkpoint_content::CheckpointData;
+    use mys_types::messages_checkpoint::{
+        CheckpointContents, FullCheckpointContents, VerifiedCheckpoint,
+    };
+    use mys_types::object::Object;
+    use mys_types::storage::error::Result as StorageResult;
+    use mys_types::storage::{
+        AccountOwnedObjectInfo, CoinInfo, ObjectStore, ReadStore, RpcStateReader,
+    };
+    use mys_types::test_checkpoint_data_builder::TestCheckpointDataBuilder;
+    use mys_types::transaction::VerifiedTransaction;
+    use std::collections::{BTreeMap, HashMap};
+    use std::sync::Arc;
+
+    /// Object history index built from the effects of a set of checkpoints.
+    struct TestIndexes {
+        watermark: CheckpointSequenceNumber,
+        versions: BTreeMap<(ObjectID, CheckpointSequenceNumber), Option<SequenceNumber>>,
+    }
+
+    impl RpcIndexes for TestIndexes {
+        fn get_transaction_checkpoint(
+            &self,
+            _digest: &TransactionDigest,
+        ) -> StorageResult<Option<CheckpointSequenceNumber>> {
+            unimplemented!()
+        }
+
+        fn account_owned_objects_info_iter(
+            &self,
+            _owner: MysAddress,
+            _cursor: Option<ObjectID>,
+        ) -> StorageResult<Box<dyn Iterator<Item = AccountOwnedObjectInfo> + '_>> {
+            unimplemented!()
+        }
+
+        fn dynamic_field_iter(
+            &self,
+            _parent: ObjectID,
+            _cursor: Option<ObjectID>,
+        ) -> StorageResult<Box<dyn Iterator<Item = (DynamicFieldKey, DynamicFieldIndexInfo)> + '_>>
+        {
+            unimplemented!()
+        }
+
+        fn get_coin_info(&self, _coin_type: &StructTag) -> StorageResult<Option<CoinInfo>> {
+            unimplemented!()
+        }
+
+        fn get_lowest_available_checkpoint_object_history(
+            &self,
+        ) -> StorageResult<CheckpointSequenceNumber> {
+            Ok(self.watermark)
+        }
+
+        fn get_object_version_at_checkpoint(
+            &self,
+            object_id: ObjectID,
+            checkpoint: CheckpointSequenceNumber,
+        ) -> StorageResult<Option<SequenceNumber>> {
+            Ok(self
+                .versions
+                .range((object_id, 0)..=(object_id, checkpoint))
+                .next_back()
+                .and_then(|(_, version)| *version))
+        }
+
+        fn account_owned_objects_info_at_checkpoint_iter(
+            &self,
+            _owner: MysAddress,
+            _checkpoint: CheckpointSequenceNumber,
+            _cursor: Option<ObjectID>,
+        ) -> StorageResult<Box<dyn Iterator<Item = AccountOwnedObjectInfo> + '_>> {
+            unimplemented!()
+        }
+    }
+
+    struct TestReader {
+        objects: HashMap<(ObjectID, SequenceNumber), Object>,
+        latest_checkpoint: VerifiedCheckpoint,
+        lowest_available_checkpoint_objects: CheckpointSequenceNumber,
+        indexes: TestIndexes,
+    }
+
+    impl TestReader {
+        fn new(checkpoints: &[CheckpointData]) -> Self {
+            let mut objects = HashMap::new();
+            let mut versions = BTreeMap::new();
+            for checkpoint in checkpoints {
+                let sequence_number = checkpoint.checkpoint_summary.sequence_number;
+                for tx in &checkpoint.transactions {
+                    for object in tx.removed_objects_pre_version() {
+                        versions.insert((object.id(), sequence_number), None);
+                    }
+                    for object in &tx.output_objects {
+                        objects.insert((object.id(), object.version()), object.clone());
+                        versions.insert((object.id(), sequence_number), Some(object.version()));
+                    }
+                }
+            }
+            let latest_checkpoint = VerifiedCheckpoint::new_unchecked(
+                checkpoints.last().unwrap().checkpoint_summary.clone(),
+            );
+
+            Self {
+                objects,
+                latest_checkpoint,
+                lowest_available_checkpoint_objects: 0,
+                indexes: TestIndexes {
+                    watermark: 0,
+                    versions,
+                },
+            }
+        }
+    }
+
+    impl ObjectStore for TestReader {
+        fn get_object(&self, _object_id: &ObjectID) -> Option<Object> {
+            unimplemented!()
+        }
+
+        fn get_object_by_key(
+            &self,
+            object_id: &ObjectID,
+            version: SequenceNumber,
+        ) -> Option<Object> {
+            self.objects.get(&(*object_id, version)).cloned()
+        }
+    }
+
+    impl ReadStore for TestReader {
+        fn get_committee(&self, _epoch: EpochId) -> Option<Arc<Committee>> {
+            unimplemented!()
+        }
+
+        fn get_latest_checkpoint(&self) -> StorageResult<VerifiedCheckpoint> {
+            Ok(self.latest_checkpoint.clone())
+        }
+
+        fn get_highest_verified_checkpoint(&self) -> StorageResult<VerifiedCheckpoint> {
+            unimplemented!()
+        }
+
+        fn get_highest_synced_checkpoint(&self) -> StorageResult<VerifiedCheckpoint> {
+            unimplemented!()
+        }
+
+        fn get_lowest_available_checkpoint(&self) -> StorageResult<CheckpointSequenceNumber> {
+            unimplemented!()
+        }
+
+        fn get_checkpoint_by_digest(
+            &self,
+            _digest: &CheckpointDigest,
+        ) -> Option<VerifiedCheckpoint> {
+            unimplemented!()
+        }
+
+        fn get_checkpoint_by_sequence_number(
+            &self,
+            _sequence_number: CheckpointSequenceNumber,
+        ) -> Option<VerifiedCheckpoint> {
+            unimplemented!()
+        }
+
+        fn get_checkpoint_contents_by_digest(
+            &self,
+            _digest: &CheckpointContentsDigest,
+        ) -> Option<CheckpointContents> {
+            unimplemented!()
+        }
+
+        fn get_checkpoint_contents_by_sequence_number(
+            &self,
+            _sequence_number: CheckpointSequenceNumber,
+        ) -> Option<CheckpointContents> {
+            unimplemented!()
+        }
+
+        fn get_transaction(
+            &self,
+            _tx_digest: &TransactionDigest,
+        ) -> Option<Arc<VerifiedTransaction>> {
+            unimplemented!()
+        }
+
+        fn get_transaction_effects(
+            &self,
+            _tx_digest: &TransactionDigest,
+        ) -> Option<TransactionEffects> {
+            unimplemented!()
+        }
+
+        fn get_events(&self, _event_digest: &TransactionEventsDigest) -> Option<TransactionEvents> {
+            unimplemented!()
+        }
+
+        fn get_full_checkpoint_contents_by_sequence_number(
+            &self,
+            _sequence_number: CheckpointSequenceNumber,
+        ) -> Option<FullCheckpointContents> {
+            unimplemented!()
+        }
+
+        fn get_full_checkpoint_contents(
+            &self,
+            _digest: &CheckpointContentsDigest,
+        ) -> Option<FullCheckpointContents> {
+            unimplemented!()
+        }
+    }
+
+    impl RpcStateReader for TestReader {
+        fn get_lowest_available_checkpoint_objects(
+            &self,
+        ) -> StorageResult<CheckpointSequenceNumber> {
+            Ok(self.lowest_available_checkpoint_objects)
+        }
+
+        fn get_chain_identifier(&self) -> StorageResult<ChainIdentifier> {
+            Ok(CheckpointDigest::default().into())
+        }
+
+        fn indexes(&self) -> Option<&dyn RpcIndexes> {
+            Some(&self.indexes)
+        }
+    }
+
+    /// Object 1 is created in checkpoint 1, transferred twice in checkpoint 2 and deleted in
+    /// checkpoint 3, while object 2 is created in checkpoint 1 and wrapped in checkpoint 3.
+    fn build_checkpoints() -> Vec<CheckpointData> {
+        let mut builder = TestCheckpointDataBuilder::new(1)
+            .start_transaction(0)
+            .create_owned_object(1)
+            .create_owned_object(2)
+            .finish_transaction();
+        let checkpoint_1 = builder.build_checkpoint();
+
+        let mut builder = builder
+            .start_transaction(0)
+            .transfer_object(1, 1)
+            .finish_transaction()
+            .start_transaction(1)
+            .transfer_object(1, 2)
+            .finish_transaction();
+        let checkpoint_2 = builder.build_checkpoint();
+
+        let mut builder = builder
+            .start_transaction(2)
+            .delete_object(1)
+            .finish_transaction()
+            .start_transaction(0)
+            .wrap_object(2)
+            .finish_transaction();
+        let checkpoint_3 = builder.build_checkpoint();
+
+        vec![checkpoint_1, checkpoint_2, checkpoint_3]
+    }
+
+    fn object_id(object_idx: u64) -> ObjectId {
+        TestCheckpointDataBuilder::derive_object_id(object_idx).into()
+    }
+
+    /// Version of the object written last by the transactions of `checkpoint`.
+    fn output_version(checkpoint: &CheckpointData, object_idx: u64) -> Version {
+        checkpoint
+            .transactions
+            .iter()
+            .flat_map(|tx| &tx.output_objects)
+            .filter(|object| ObjectId::from(object.id()) == object_id(object_idx))
+            .last()
+            .unwrap()
+            .version()
+            .into()
+    }
+
+    fn new_service(reader: TestReader) -> RpcService {
+        RpcService::new_without_version(Arc::new(reader))
+    }
+
+    #[test]
+    fn get_object_at_checkpoint() {
+        let checkpoints = build_checkpoints();
+        let service = new_service(TestReader::new(&checkpoints));
+        let version_at = |object_idx, checkpoint| {
+            service
+                .get_object_at_checkpoint(
+                    object_id(object_idx),
+                    checkpoint,
+                    GetObjectOptions::default(),
+                )
+                .map(|response| response.version)
+        };
+
+        assert_eq!(
+            version_at(1, 1).unwrap(),
+            output_version(&checkpoints[0], 1)
+        );
+        // Only the last of several transfers within a checkpoint is visible.
+        assert_eq!(
+            version_at(1, 2).unwrap(),
+            output_version(&checkpoints[1], 1)
+        );
+        assert_eq!(
+            version_at(2, 2).unwrap(),
+            output_version(&checkpoints[0], 2)
+        );
+
+        // Deleted and wrapped objects are reported as absent.
+        for object_idx in [1, 2] {
+            let error = tonic::Status::from(version_at(object_idx, 3).unwrap_err());
+            assert_eq!(error.code(), tonic::Code::NotFound);
+            assert!(error.message().contains("as of checkpoint 3"));
+        }
+    }
+
+    #[test]
+    fn get_object_at_pruned_checkpoint() {
+        let checkpoints = build_checkpoints();
+        let request = |service: &RpcService, checkpoint| {
+            service
+                .get_object_at_checkpoint(object_id(1), checkpoint, GetObjectOptions::default())
+                .map(|response| response.version)
+        };
+
+        // Pruned by the object history index.
+        let mut reader = TestReader::new(&checkpoints);
+        reader.indexes.watermark = 2;
+        let service = new_service(reader);
+        let error = tonic::Status::from(request(&service, 1).unwrap_err());
+        assert_eq!(error.code(), tonic::Code::NotFound);
+        assert!(error.message().contains("has been pruned"));
+        assert_eq!(
+            request(&service, 2).unwrap(),
+            output_version(&checkpoints[1], 1)
+        );
+
+        // Pruned object contents, even though the index still covers the checkpoint.
+        let mut reader = TestReader::new(&checkpoints);
+        reader.lowest_available_checkpoint_objects = 3;
+        let service = new_service(reader);
+        let error = tonic::Status::from(request(&service, 2).unwrap_err());
+        assert_eq!(error.code(), tonic::Code::NotFound);
+        assert!(error.message().contains("has been pruned"));
+
+        // Checkpoints which have not been executed yet.
+        let error = tonic::Status::from(request(&service, 4).unwrap_err());
+        assert_eq!(error.code(), tonic::Code::OutOfRange);
+    }
+
+    #[tokio::test]
+    async fn get_object_at_checkpoint_grpc() {
+        let checkpoints = build_checkpoints();
+        let service = new_service(TestReader::new(&checkpoints));
+
+        let response = NodeService::get_object_at_checkpoint(
+            &service,
+            tonic::Request::new(GetObjectAtCheckpointRequest {
+                object_id: Some(object_id(1).into()),
+                checkpoint: Some(2),
+                read_mask: None,
+            }),
+        )
+        .await
+        .unwrap()
+        .into_inner();
+        assert_eq!(response.object_id, Some(object_id(1).into()));
+        assert_eq!(response.version, Some(output_version(&checkpoints[1], 1)));
+
+        let error = NodeService::get_object_at_checkpoint(
+            &service,
+            tonic::Request::new(GetObjectAtCheckpointRequest {
+                object_id: Some(object_id(1).into()),
+                checkpoint: None,
+                read_mask: None,
+            }),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error.code(), tonic::Code::InvalidArgument);
+    }
+}
@@ -0,0 +1,116 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use super::checkpoints::CheckpointId;
+use super::checkpoints::CheckpointNotFoundError;
+use crate::subscription::SubscriptionCheckpoint;
+use crate::Result;
+use crate::RpcError;
+use crate::RpcService;
+use mys_sdk_types::CheckpointSequenceNumber;
+use std::pin::Pin;
+use std::sync::Arc;
+
+pub(crate) type CheckpointSubscription =
+    Pin<Box<dyn tokio_stream::Stream<Item = Result<Arc<SubscriptionCheckpoint>>> + Send>>;
+
+impl RpcService {
+    /// Subscribe to the stream of executed checkpoints.
+    ///
+    /// If a `start_cursor` is provided then all checkpoints from `start_cursor` up to the latest
+    /// executed checkpoint are first read from the store before following the live stream,
+    /// otherwise the stream begins with the next checkpoint to be executed. In both cases
+    /// checkpoints are returned in-order and without gaps.
+    pub(crate) async fn checkpoint_subscription(
+        &self,
+        start_cursor: Option<CheckpointSequenceNumber>,
+    ) -> Result<CheckpointSubscription> {
+        let handle = self.subscription_service_handle.clone().ok_or_else(|| {
+            RpcError::new(
+                tonic::Code::Unimplemented,
+                "subscription service is not enabled",
+            )
+        })?;
+
+        if let Some(start_cursor) = start_cursor {
+            let lowest_available_checkpoint = self
+                .reader
+                .inner()
+                .get_lowest_available_checkpoint_objects()?;
+            if start_cursor < lowest_available_checkpoint {
+                return Err(RpcError::new(
+                    tonic::Code::OutOfRange,
+                    format!(
+                        "start_cursor {start_cursor} has been pruned, lowest available checkpoint is {lowest_available_checkpoint}"
+                    ),
+                ));
+            }
+        }
+
+        let service = self.clone();
+        let stream = async_stream::try_stream! {
+            let mut next = start_cursor;
+
+            // Catch up from the store before registering with the subscription service so that
+            // the live channel doesn't fill up, and get us dropped, while we replay history
+            if let Some(cursor) = next.as_mut() {
+                let mut latest = service.reader.inner().get_latest_checkpoint_sequence_number()?;
+                while *cursor <= latest {
+                    yield Arc::new(service.load_subscription_checkpoint(*cursor)?);
+                    *cursor += 1;
+
+                    if *cursor > latest {
+                        latest = service.reader.inner().get_latest_checkpoint_sequence_number()?;
+                    }
+                }
+            }
+
+            let mut receiver = handle.register_subscription().await.ok_or_else(|| {
+                RpcError::new(tonic::Code::Unavailable, "too many existing subscriptions")
+            })?;
+
+            while let Some(checkpoint) = receiver.recv().await {
+                let sequence_number = checkpoint.sequence_number();
+
+                if let Some(cursor) = next.as_mut() {
+                    // Already returned while catching up
+                    if sequence_number < *cursor {
+                        continue;
+                    }
+
+                    // Fill in anything executed between catching up and registering
+                    while *cursor < sequence_number {
+                        yield Arc::new(service.load_subscription_checkpoint(*cursor)?);
+                        *cursor += 1;
+                    }
+                }
+
+                next = Some(sequence_number + 1);
+                yield checkpoint;
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+
+    fn load_subscription_checkpoint(
+        &self,
+        sequence_number: CheckpointSequenceNumber,
+    ) -> Result<SubscriptionCheckpoint> {
+        let not_found = || CheckpointNotFoundError(CheckpointId::SequenceNumber(sequence_number));
+
+        let summary = self
+            .reader
+            .inner()
+            .get_checkpoint_by_sequence_number(sequence_number)
+            .ok_or_else(not_found)?;
+        let contents = self
+            .reader
+            .inner()
+            .get_checkpoint_contents_by_sequence_number(sequence_number)
+            .ok_or_else(not_found)?;
+        let data = self.reader.inner().get_checkpoint_data(summary, contents)?;
+
+        SubscriptionCheckpoint::new(data)
+    }
+}
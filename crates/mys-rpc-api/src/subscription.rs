@@ -4,8 +4,15 @@
 use crate::metrics::SubscriptionMetrics;
 use crate::proto::node::v2::GetFullCheckpointOptions;
 use crate::proto::node::v2::GetFullCheckpointResponse;
+use crate::RpcError;
+use move_core_types::language_storage::StructTag;
+use mys_sdk_types::Address;
+use mys_sdk_types::ObjectId;
+use mys_types::base_types::{MysAddress, ObjectID};
+use mys_types::event::Event;
+use mys_types::full_checkpoint_content::{CheckpointData, CheckpointTransaction};
+use mys_types::transaction::TransactionDataAPI;
 use std::sync::Arc;
-use mys_types::full_checkpoint_content::CheckpointData;
 use tap::Pipe;
 use tokio::sync::mpsc;
 use tokio::sync::oneshot;
@@ -19,7 +26,33 @@ const SUBSCRIPTION_CHANNEL_SIZE: usize = 256;
 const MAX_SUBSCRIBERS: usize = 1024;
 
 struct SubscriptionRequest {
-    sender: oneshot::Sender<mpsc::Receiver<Arc<GetFullCheckpointResponse>>>,
+    sender: oneshot::Sender<mpsc::Receiver<Arc<SubscriptionCheckpoint>>>,
+}
+
+/// A checkpoint as it is broadcast to subscribers.
+///
+/// Carries both the native checkpoint, which subscriptions use to evaluate their filters, and its
+/// fully populated proto representation, which is converted only once for all subscribers. The
+/// checkpoint is handed to subscribers behind an `Arc` so neither is copied per subscriber.
+pub struct SubscriptionCheckpoint {
+    pub data: CheckpointData,
+    pub response: GetFullCheckpointResponse,
+}
+
+impl SubscriptionCheckpoint {
+    pub fn new(data: CheckpointData) -> crate::Result<Self> {
+        let response = crate::service::checkpoints::checkpoint_data_to_full_checkpoint_response(
+            &data,
+            &GetFullCheckpointOptions::all().into(),
+        )?
+        .into();
+
+        Ok(Self { data, response })
+    }
+
+    pub fn sequence_number(&self) -> u64 {
+        *self.data.checkpoint_summary.sequence_number()
+    }
 }
 
 #[derive(Clone)]
@@ -30,7 +63,7 @@ pub struct SubscriptionServiceHandle {
 impl SubscriptionServiceHandle {
     pub async fn register_subscription(
         &self,
-    ) -> Option<mpsc::Receiver<Arc<GetFullCheckpointResponse>>> {
+    ) -> Option<mpsc::Receiver<Arc<SubscriptionCheckpoint>>> {
         let (sender, reciever) = oneshot::channel();
         let request = SubscriptionRequest { sender };
         self.sender.send(request).await.ok()?;
@@ -45,7 +78,7 @@ pub struct SubscriptionService {
     // Expectation is that checkpoints are recieved in-order
     checkpoint_mailbox: mpsc::Receiver<CheckpointData>,
     mailbox: mpsc::Receiver<SubscriptionRequest>,
-    subscribers: Vec<mpsc::Sender<Arc<GetFullCheckpointResponse>>>,
+    subscribers: Vec<mpsc::Sender<Arc<SubscriptionCheckpoint>>>,
 
     metrics: SubscriptionMetrics,
 }
@@ -122,17 +155,13 @@ impl SubscriptionService {
             self.metrics.last_recieved_checkpoint.set(sequence_number);
         }
 
-        let checkpoint =
-            match crate::service::checkpoints::checkpoint_data_to_full_checkpoint_response(
-                checkpoint,
-                &GetFullCheckpointOptions::all().into(),
-            ) {
-                Ok(checkpoint) => GetFullCheckpointResponse::from(checkpoint).pipe(Arc::new),
-                Err(e) => {
-                    error!("unable to convert checkpoint to proto: {e:?}");
-                    return;
-                }
-            };
+        let checkpoint = match SubscriptionCheckpoint::new(checkpoint) {
+            Ok(checkpoint) => Arc::new(checkpoint),
+            Err(e) => {
+                error!("unable to convert checkpoint to proto: {e:?}");
+                return;
+            }
+        };
 
         // Try to send the latest checkpoint to all subscribers. If a subscriber's channel is full
         // then they are likely too slow so we drop them.
@@ -175,3 +204,414 @@ impl SubscriptionService {
         }
     }
 }
+
+/// The set of filters provided by a subscription.
+///
+/// A transaction is of interest if it matches any one of the filters. An empty set of filters
+/// matches every transaction.
+#[derive(Clone, Debug, Default)]
+pub struct SubscriptionFilter(Vec<TransactionFilter>);
+
+impl SubscriptionFilter {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn matches(&self, transaction: &CheckpointTransaction) -> bool {
+        self.is_empty() || self.0.iter().any(|filter| filter.matches(transaction))
+    }
+
+    /// Returns the indexes of the events emitted by `transaction` which are of interest.
+    pub fn matching_events(&self, transaction: &CheckpointTransaction) -> Vec<usize> {
+        let Some(events) = &transaction.events else {
+            return Vec::new();
+        };
+
+        let filters = self
+            .0
+            .iter()
+            .filter(|filter| filter.matches(transaction))
+            .collect::<Vec<_>>();
+        if !self.is_empty() && filters.is_empty() {
+            return Vec::new();
+        }
+
+        events
+            .data
+            .iter()
+            .enumerate()
+            .filter(|(_, event)| {
+                filters.is_empty() || filters.iter().any(|filter| filter.matches_event(event))
+            })
+            .map(|(index, _)| index)
+            .collect()
+    }
+}
+
+impl TryFrom<&[crate::proto::node::v2alpha::TransactionFilter]> for SubscriptionFilter {
+    type Error = RpcError;
+
+    fn try_from(
+        filters: &[crate::proto::node::v2alpha::TransactionFilter],
+    ) -> Result<Self, Self::Error> {
+        filters
+            .iter()
+            .map(TryFrom::try_from)
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+/// A single subscription filter. A transaction matches the filter only if it satisfies every
+/// criteria which is set.
+#[derive(Clone, Debug, Default)]
+pub struct TransactionFilter {
+    pub sender: Option<MysAddress>,
+    pub move_call: Option<MoveCallFilter>,
+    pub event_type: Option<StructTag>,
+    pub object: Option<ObjectID>,
+    pub owner: Option<MysAddress>,
+}
+
+impl TransactionFilter {
+    pub fn matches(&self, transaction: &CheckpointTransaction) -> bool {
+        let data = transaction.transaction.transaction_data();
+
+        if self.sender.is_some_and(|sender| data.sender() != sender) {
+            return false;
+        }
+
+        if let Some(move_call) = &self.move_call {
+            if !data
+                .move_calls()
+                .into_iter()
+                .any(|(package, module, function)| move_call.matches(package, module, function))
+            {
+                return false;
+            }
+        }
+
+        if self.event_type.is_some()
+            && !transaction
+                .events
+                .iter()
+                .flat_map(|events| &events.data)
+                .any(|event| self.matches_event(event))
+        {
+            return false;
+        }
+
+        let mut objects = transaction
+            .input_objects
+            .iter()
+            .chain(&transaction.output_objects);
+
+        if let Some(object_id) = self.object {
+            if !objects.clone().any(|object| object.id() == object_id) {
+                return false;
+            }
+        }
+
+        if let Some(owner) = self.owner {
+            if !objects.any(|object| object.owner().get_owner_address().ok() == Some(owner)) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    pub fn matches_event(&self, event: &Event) -> bool {
+        let Some(event_type) = &self.event_type else {
+            return true;
+        };
+
+        // Type parameters are only compared when the filter specifies them
+        event_type.address == event.type_.address
+            && event_type.module == event.type_.module
+            && event_type.name == event.type_.name
+            && (event_type.type_params.is_empty()
+                || event_type.type_params == event.type_.type_params)
+    }
+}
+
+impl TryFrom<&crate::proto::node::v2alpha::TransactionFilter> for TransactionFilter {
+    type Error = RpcError;
+
+    fn try_from(
+        filter: &crate::proto::node::v2alpha::TransactionFilter,
+    ) -> Result<Self, Self::Error> {
+        let sender = filter
+            .sender
+            .as_ref()
+            .map(|sender| Address::try_from(sender).map(Into::into))
+            .transpose()
+            .map_err(|e| {
+                RpcError::new(tonic::Code::InvalidArgument, format!("invalid sender: {e}"))
+            })?;
+        let move_call = filter
+            .move_call
+            .as_ref()
+            .map(MoveCallFilter::try_from)
+            .transpose()?;
+        let event_type = filter
+            .event_type
+            .as_deref()
+            .map(mys_types::parse_mys_struct_tag)
+            .transpose()
+            .map_err(|e| {
+                RpcError::new(
+                    tonic::Code::InvalidArgument,
+                    format!("invalid event_type: {e}"),
+                )
+            })?;
+        let object = filter
+            .object
+            .as_ref()
+            .map(|object| ObjectId::try_from(object).map(Into::into))
+            .transpose()
+            .map_err(|e| {
+                RpcError::new(tonic::Code::InvalidArgument, format!("invalid object: {e}"))
+            })?;
+        let owner = filter
+            .owner
+            .as_ref()
+            .map(|owner| Address::try_from(owner).map(Into::into))
+            .transpose()
+            .map_err(|e| {
+                RpcError::new(tonic::Code::InvalidArgument, format!("invalid owner: {e}"))
+            })?;
+
+        Ok(Self {
+            sender,
+            move_call,
+            event_type,
+            object,
+            owner,
+        })
+    }
+}
+
+/// Filter on the target of a Move call. `function` is only set if `module` is.
+#[derive(Clone, Debug)]
+pub struct MoveCallFilter {
+    pub package: ObjectID,
+    pub module: Option<String>,
+    pub function: Option<String>,
+}
+
+impl MoveCallFilter {
+    fn matches(&self, package: &ObjectID, module: &str, function: &str) -> bool {
+        self.package == *package
+            && self.module.as_ref().map_or(true, |m| m == module)
+            && self.function.as_ref().map_or(true, |f| f == function)
+    }
+}
+
+impl TryFrom<&crate::proto::node::v2alpha::MoveCallFilter> for MoveCallFilter {
+    type Error = RpcError;
+
+    fn try_from(filter: &crate::proto::node::v2alpha::MoveCallFilter) -> Result<Self, Self::Error> {
+        let package = filter
+            .package
+            .as_ref()
+            .ok_or_else(|| {
+                RpcError::new(tonic::Code::InvalidArgument, "missing move_call.package")
+            })?
+            .pipe(ObjectId::try_from)
+            .map_err(|e| {
+                RpcError::new(
+                    tonic::Code::InvalidArgument,
+                    format!("invalid move_call.package: {e}"),
+                )
+            })?
+            .into();
+
+        if filter.function.is_some() && filter.module.is_none() {
+            return Err(RpcError::new(
+                tonic::Code::InvalidArgument,
+                "move_call.function requires move_call.module to be set",
+            ));
+        }
+
+        Ok(Self {
+            package,
+            module: filter.module.clone(),
+            function: filter.function.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mys_types::test_checkpoint_data_builder::TestCheckpointDataBuilder;
+
+    fn package() -> ObjectID {
+        ObjectID::from_single_byte(0x42)
+    }
+
+    fn event(type_: &str) -> Event {
+        let mut event = Event::random_for_testing();
+        event.type_ = mys_types::parse_mys_struct_tag(type_).unwrap();
+        event
+    }
+
+    fn event_type_filter(type_: &str) -> TransactionFilter {
+        TransactionFilter {
+            event_type: Some(mys_types::parse_mys_struct_tag(type_).unwrap()),
+            ..Default::default()
+        }
+    }
+
+    fn move_call_filter(module: Option<&str>, function: Option<&str>) -> TransactionFilter {
+        TransactionFilter {
+            move_call: Some(MoveCallFilter {
+                package: package(),
+                module: module.map(ToOwned::to_owned),
+                function: function.map(ToOwned::to_owned),
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn move_call_filter_matches_package_module_and_function() {
+        let checkpoint = TestCheckpointDataBuilder::new(1)
+            .start_transaction(0)
+            .add_move_call(package(), "pool", "swap")
+            .finish_transaction()
+            .build_checkpoint();
+        let transaction = &checkpoint.transactions[0];
+
+        assert!(move_call_filter(None, None).matches(transaction));
+        assert!(move_call_filter(Some("pool"), None).matches(transaction));
+        assert!(move_call_filter(Some("pool"), Some("swap")).matches(transaction));
+        assert!(!move_call_filter(Some("market"), None).matches(transaction));
+        assert!(!move_call_filter(Some("pool"), Some("deposit")).matches(transaction));
+
+        let other_package = TransactionFilter {
+            move_call: Some(MoveCallFilter {
+                package: ObjectID::from_single_byte(0x43),
+                module: None,
+                function: None,
+            }),
+            ..Default::default()
+        };
+        assert!(!other_package.matches(transaction));
+    }
+
+    #[test]
+    fn event_type_filter_matches_type_parameters_only_when_set() {
+        let checkpoint = TestCheckpointDataBuilder::new(1)
+            .start_transaction(0)
+            .with_events(vec![event("0x42::market::Trade<0x2::mys::MYS>")])
+            .finish_transaction()
+            .build_checkpoint();
+        let transaction = &checkpoint.transactions[0];
+
+        assert!(event_type_filter("0x42::market::Trade").matches(transaction));
+        assert!(event_type_filter("0x42::market::Trade<0x2::mys::MYS>").matches(transaction));
+        assert!(!event_type_filter("0x42::market::Trade<0x42::usd::USD>").matches(transaction));
+        assert!(!event_type_filter("0x42::market::Listed").matches(transaction));
+        assert!(!event_type_filter("0x43::market::Trade").matches(transaction));
+    }
+
+    #[test]
+    fn object_filter_matches_input_and_output_objects() {
+        let checkpoint = TestCheckpointDataBuilder::new(1)
+            .start_transaction(0)
+            .create_owned_object(1)
+            .finish_transaction()
+            .start_transaction(0)
+            .mutate_object(1)
+            .finish_transaction()
+            .start_transaction(0)
+            .create_owned_object(2)
+            .finish_transaction()
+            .build_checkpoint();
+
+        let filter = TransactionFilter {
+            object: Some(TestCheckpointDataBuilder::derive_object_id(1)),
+            ..Default::default()
+        };
+        let matches = checkpoint
+            .transactions
+            .iter()
+            .map(|transaction| filter.matches(transaction))
+            .collect::<Vec<_>>();
+        assert_eq!(matches, vec![true, true, false]);
+    }
+
+    #[test]
+    fn owner_filter_matches_objects_owned_by_address() {
+        let checkpoint = TestCheckpointDataBuilder::new(1)
+            .start_transaction(0)
+            .create_owned_object(1)
+            .finish_transaction()
+            .start_transaction(0)
+            .transfer_object(1, 1)
+            .finish_transaction()
+            .build_checkpoint();
+
+        let filter = TransactionFilter {
+            owner: Some(TestCheckpointDataBuilder::derive_address(1)),
+            ..Default::default()
+        };
+        assert!(!filter.matches(&checkpoint.transactions[0]));
+        assert!(filter.matches(&checkpoint.transactions[1]));
+
+        let filter = TransactionFilter {
+            owner: Some(TestCheckpointDataBuilder::derive_address(0)),
+            ..Default::default()
+        };
+        assert!(filter.matches(&checkpoint.transactions[0]));
+        assert!(filter.matches(&checkpoint.transactions[1]));
+    }
+
+    #[test]
+    fn matching_events() {
+        let checkpoint = TestCheckpointDataBuilder::new(1)
+            .start_transaction(0)
+            .with_events(vec![
+                event("0x42::market::Trade<0x2::mys::MYS>"),
+                event("0x42::market::Listed"),
+                event("0x42::market::Trade<0x42::usd::USD>"),
+            ])
+            .finish_transaction()
+            .start_transaction(0)
+            .finish_transaction()
+            .build_checkpoint();
+        let transaction = &checkpoint.transactions[0];
+
+        // Without filters every event is of interest
+        let filter = SubscriptionFilter::default();
+        assert_eq!(filter.matching_events(transaction), vec![0, 1, 2]);
+        assert!(filter
+            .matching_events(&checkpoint.transactions[1])
+            .is_empty());
+
+        let filter = SubscriptionFilter(vec![event_type_filter("0x42::market::Trade")]);
+        assert_eq!(filter.matching_events(transaction), vec![0, 2]);
+
+        let filter = SubscriptionFilter(vec![
+            event_type_filter("0x42::market::Listed"),
+            event_type_filter("0x42::market::Trade<0x42::usd::USD>"),
+        ]);
+        assert_eq!(filter.matching_events(transaction), vec![1, 2]);
+
+        // A filter without an event type selects every event of the transactions it matches
+        let filter = SubscriptionFilter(vec![TransactionFilter {
+            sender: Some(TestCheckpointDataBuilder::derive_address(0)),
+            ..Default::default()
+        }]);
+        assert_eq!(filter.matching_events(transaction), vec![0, 1, 2]);
+
+        // Event types are only selected from transactions which satisfy the rest of the filter
+        let filter = SubscriptionFilter(vec![TransactionFilter {
+            sender: Some(TestCheckpointDataBuilder::derive_address(1)),
+            ..event_type_filter("0x42::market::Trade")
+        }]);
+        assert!(filter.matching_events(transaction).is_empty());
+    }
+}
@@ -11,8 +11,8 @@ use payload::AddressQueryType;
 use std::error::Error;
 use std::path::PathBuf;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
-use mys_keys::keystore::{AccountKeystore, FileBasedKeystore, Keystore};
-use mys_types::crypto::{EncodeDecodeBase64, MysKeyPair};
+use mys_keys::keystore::{AccountKeystore, Keystore};
+use mys_types::crypto::EncodeDecodeBase64;
 use tracing::info;
 
 use crate::load_test::{LoadTest, LoadTestConfig};
@@ -139,9 +139,9 @@ fn get_keypair() -> Result<SignerInfo> {
     // TODO(chris) allow pass in custom path for keystore
     // Load keystore from ~/.mys/mys_config/mys.keystore
     let keystore_path = get_mys_config_directory().join("mys.keystore");
-    let keystore = Keystore::from(FileBasedKeystore::new(&keystore_path)?);
+    let keystore = Keystore::from_path(&keystore_path)?;
     let active_address = keystore.addresses().pop().unwrap();
-    let keypair: &MysKeyPair = keystore.get_key(&active_address)?;
+    let keypair = keystore.export_key(&active_address)?;
     println!("using address {active_address} for signing");
     Ok(SignerInfo::new(keypair.encode_base64()))
 }
@@ -0,0 +1,85 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use move_core_types::language_storage::{StructTag, TypeTag};
+use mys_core::authority::authority_store_tables::LiveObject;
+use mys_types::base_types::{MysAddress, ObjectID};
+use mys_types::object::Object;
+use std::collections::BTreeSet;
+
+/// Selects the live objects a restore materializes. An object is restored if it matches any of
+/// the predicates, and an empty filter restores every object. Wrapped objects carry no type or
+/// owner, so they never match a non-empty filter.
+///
+/// Filtering only affects what is written out: checksums and accumulators are still computed
+/// over the full live object set of the snapshot.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LiveObjectFilter {
+    /// Packages with these IDs, and objects whose type (or any of its type parameters) is
+    /// defined in one of them
+    pub packages: BTreeSet<ObjectID>,
+    /// Objects of these types. A type given without type parameters matches all of its
+    /// instantiations, e.g. `0x2::coin::Coin` matches `0x2::coin::Coin<0x2::mys::MYS>`
+    pub struct_types: Vec<StructTag>,
+    /// Objects owned by these addresses or objects
+    pub owners: BTreeSet<MysAddress>,
+}
+
+impl LiveObjectFilter {
+    pub fn is_empty(&self) -> bool {
+        self.packages.is_empty() && self.struct_types.is_empty() && self.owners.is_empty()
+    }
+
+    pub fn matches(&self, object: &LiveObject) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+        match object {
+            LiveObject::Normal(object) => self.matches_object(object),
+            LiveObject::Wrapped(_) => false,
+        }
+    }
+
+    pub fn matches_object(&self, object: &Object) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+        if let Some(owner) = object.get_single_owner() {
+            if self.owners.contains(&owner) {
+                return true;
+            }
+        }
+        let Some(tag) = object.struct_tag() else {
+            // Move package
+            return self.packages.contains(&object.id());
+        };
+        self.struct_types
+            .iter()
+            .any(|struct_type| struct_type_matches(struct_type, &tag))
+            || self.mentions_package(&tag)
+    }
+
+    fn mentions_package(&self, tag: &StructTag) -> bool {
+        !self.packages.is_empty()
+            && (self.packages.contains(&ObjectID::from(tag.address))
+                || tag
+                    .type_params
+                    .iter()
+                    .any(|type_param| self.type_mentions_package(type_param)))
+    }
+
+    fn type_mentions_package(&self, type_tag: &TypeTag) -> bool {
+        match type_tag {
+            TypeTag::Struct(tag) => self.mentions_package(tag),
+            TypeTag::Vector(inner) => self.type_mentions_package(inner),
+            _ => false,
+        }
+    }
+}
+
+fn struct_type_matches(pattern: &StructTag, tag: &StructTag) -> bool {
+    pattern.address == tag.address
+        && pattern.module == tag.module
+        && pattern.name == tag.name
+        && (pattern.type_params.is_empty() || pattern.type_params == tag.type_params)
+}
@@ -5,6 +5,7 @@
 #[cfg(test)]
 mod tests;
 
+pub mod filter;
 pub mod reader;
 pub mod uploader;
 mod writer;
@@ -73,6 +74,14 @@ use tokio::time::Instant;
 ///       - 1_1.obj
 ///       - ...
 ///
+/// A delta snapshot has the same layout as a full one, but its *.obj and *.ref files only hold
+/// objects created or mutated since a base epoch. Objects deleted since the base epoch are listed
+/// by the object reference they had at the base epoch in <bucket_number>_<partition_number>.del
+/// files which follow the REFERENCE file disk format with a different magic. The MANIFEST of a
+/// delta snapshot is a `Manifest::V2` pointing to its base epoch, which is either a full snapshot
+/// or another delta. Restoring a delta means restoring the full snapshot at the root of its chain
+/// and applying every delta on top of it in order.
+///
 /// Object File Disk Format
 ///┌──────────────────────────────┐
 ///│  magic(0x00B7EC75) <4 byte>  │
@@ -118,6 +127,7 @@ use tokio::time::Instant;
 const OBJECT_FILE_MAGIC: u32 = 0x00B7EC75;
 const REFERENCE_FILE_MAGIC: u32 = 0xDEADBEEF;
 const MANIFEST_FILE_MAGIC: u32 = 0x00C0FFEE;
+const DELETED_FILE_MAGIC: u32 = 0xDE1E7ED0;
 const MAGIC_BYTES: usize = 4;
 const SNAPSHOT_VERSION_BYTES: usize = 1;
 const ADDRESS_LENGTH_BYTES: usize = 8;
@@ -143,6 +153,7 @@ const FILE_METADATA_BYTES: usize =
 pub enum FileType {
     Object = 0,
     Reference,
+    Deleted,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
@@ -163,6 +174,9 @@ impl FileMetadata {
             FileType::Reference => {
                 dir_path.child(&*format!("{}_{}.ref", self.bucket_num, self.part_num))
             }
+            FileType::Deleted => {
+                dir_path.child(&*format!("{}_{}.del", self.bucket_num, self.part_num))
+            }
         }
     }
     pub fn local_file_path(&self, root_path: &std::path::Path, dir_path: &Path) -> Result<PathBuf> {
@@ -170,7 +184,7 @@ impl FileMetadata {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
 pub struct ManifestV1 {
     pub snapshot_version: u8,
     pub address_length: u64,
@@ -178,32 +192,67 @@ pub struct ManifestV1 {
     pub epoch: u64,
 }
 
-#[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+/// Manifest of a delta snapshot, which only holds the changes to the live object set since
+/// `base_epoch`.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct ManifestV2 {
+    pub snapshot_version: u8,
+    pub address_length: u64,
+    pub file_metadata: Vec<FileMetadata>,
+    pub epoch: u64,
+    /// Epoch of the snapshot (full or delta) this delta applies on top of
+    pub base_epoch: u64,
+    pub num_created: u64,
+    pub num_mutated: u64,
+    pub num_deleted: u64,
+    /// Size of the live object set at `epoch` once the whole chain is applied
+    pub num_live_objects: u64,
+    pub include_wrapped_tombstone: bool,
+    /// Root state hash of the live object set at `epoch`
+    pub root_state_hash: ECMHLiveObjectSetDigest,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
 pub enum Manifest {
     V1(ManifestV1),
+    V2(ManifestV2),
 }
 
 impl Manifest {
     pub fn snapshot_version(&self) -> u8 {
         match self {
             Self::V1(manifest) => manifest.snapshot_version,
+            Self::V2(manifest) => manifest.snapshot_version,
         }
     }
     pub fn address_length(&self) -> u64 {
         match self {
             Self::V1(manifest) => manifest.address_length,
+            Self::V2(manifest) => manifest.address_length,
         }
     }
     pub fn file_metadata(&self) -> &Vec<FileMetadata> {
         match self {
             Self::V1(manifest) => &manifest.file_metadata,
+            Self::V2(manifest) => &manifest.file_metadata,
         }
     }
     pub fn epoch(&self) -> u64 {
         match self {
             Self::V1(manifest) => manifest.epoch,
+            Self::V2(manifest) => manifest.epoch,
         }
     }
+    /// Epoch of the snapshot this one is a delta of, or `None` for a full snapshot
+    pub fn base_epoch(&self) -> Option<u64> {
+        match self {
+            Self::V1(_) => None,
+            Self::V2(manifest) => Some(manifest.base_epoch),
+        }
+    }
+    pub fn is_delta(&self) -> bool {
+        self.base_epoch().is_some()
+    }
 }
 
 pub fn create_file_metadata(
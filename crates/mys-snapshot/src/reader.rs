@@ -1,16 +1,18 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::filter::LiveObjectFilter;
 use crate::{
-    FileMetadata, FileType, Manifest, MAGIC_BYTES, MANIFEST_FILE_MAGIC, OBJECT_FILE_MAGIC,
-    OBJECT_ID_BYTES, OBJECT_REF_BYTES, REFERENCE_FILE_MAGIC, SEQUENCE_NUM_BYTES, SHA3_BYTES,
+    accumulate_live_object_iter, FileMetadata, FileType, Manifest, DELETED_FILE_MAGIC, MAGIC_BYTES,
+    MANIFEST_FILE_MAGIC, OBJECT_FILE_MAGIC, OBJECT_ID_BYTES, OBJECT_REF_BYTES,
+    REFERENCE_FILE_MAGIC, SEQUENCE_NUM_BYTES, SHA3_BYTES,
 };
 use anyhow::{anyhow, Context, Result};
 use byteorder::{BigEndian, ReadBytesExt};
 use bytes::{Buf, Bytes};
 use fastcrypto::hash::MultisetHash;
 use fastcrypto::hash::{HashFunction, Sha3_256};
-use futures::future::{AbortRegistration, Abortable};
+use futures::future::{AbortHandle, AbortRegistration, Abortable};
 use futures::{StreamExt, TryStreamExt};
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use integer_encoding::VarIntReader;
@@ -19,6 +21,7 @@ use std::collections::BTreeMap;
 use std::fs;
 use std::fs::File;
 use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::iter::Peekable;
 use std::num::NonZeroUsize;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
@@ -26,13 +29,16 @@ use std::sync::Arc;
 use mys_config::object_storage_config::ObjectStoreConfig;
 use mys_core::authority::authority_store_tables::{AuthorityPerpetualTables, LiveObject};
 use mys_core::authority::AuthorityStore;
+use mys_core::state_accumulator::WrappedObject;
 use mys_indexer_alt_framework::task::TrySpawnStreamExt;
 use mys_storage::blob::{Blob, BlobEncoding};
 use mys_storage::object_store::http::HttpDownloaderBuilder;
-use mys_storage::object_store::util::{copy_file, copy_files, path_to_filesystem};
+use mys_storage::compute_sha3_checksum_for_bytes;
+use mys_storage::object_store::util::{copy_file, copy_files, get, path_to_filesystem};
 use mys_storage::object_store::{ObjectStoreGetExt, ObjectStoreListExt, ObjectStorePutExt};
 use mys_types::accumulator::Accumulator;
 use mys_types::base_types::{ObjectDigest, ObjectID, ObjectRef, SequenceNumber};
+use mys_types::messages_checkpoint::ECMHLiveObjectSetDigest;
 use tokio::sync::Mutex;
 use tokio::task::JoinHandle;
 use tokio::time::Duration;
@@ -50,6 +56,9 @@ pub struct StateSnapshotReaderV1 {
     local_object_store: Arc<dyn ObjectStorePutExt>,
     ref_files: BTreeMap<u32, BTreeMap<u32, FileMetadata>>,
     object_files: BTreeMap<u32, BTreeMap<u32, FileMetadata>>,
+    deleted_files: BTreeMap<u32, BTreeMap<u32, FileMetadata>>,
+    base_epoch: Option<u64>,
+    filter: LiveObjectFilter,
     indirect_objects_threshold: usize,
     m: MultiProgress,
     concurrency: usize,
@@ -101,7 +110,7 @@ impl StateSnapshotReaderV1 {
             &manifest_file_path,
         )?)?;
         let snapshot_version = manifest.snapshot_version();
-        if snapshot_version != 1u8 {
+        if snapshot_version != 1u8 && snapshot_version != 2u8 {
             return Err(anyhow!("Unexpected snapshot version: {}", snapshot_version));
         }
         if manifest.address_length() as usize > ObjectID::LENGTH {
@@ -115,6 +124,7 @@ impl StateSnapshotReaderV1 {
         }
         let mut object_files = BTreeMap::new();
         let mut ref_files = BTreeMap::new();
+        let mut deleted_files = BTreeMap::new();
         for file_metadata in manifest.file_metadata() {
             match file_metadata.file_type {
                 FileType::Object => {
@@ -129,6 +139,12 @@ impl StateSnapshotReaderV1 {
                         .or_insert_with(BTreeMap::new);
                     entry.insert(file_metadata.part_num, file_metadata.clone());
                 }
+                FileType::Deleted => {
+                    let entry = deleted_files
+                        .entry(file_metadata.bucket_num)
+                        .or_insert_with(BTreeMap::new);
+                    entry.insert(file_metadata.part_num, file_metadata.clone());
+                }
             }
         }
         let epoch_dir_path = Path::from(epoch_dir);
@@ -186,18 +202,95 @@ impl StateSnapshotReaderV1 {
             local_object_store,
             ref_files,
             object_files,
+            deleted_files,
+            base_epoch: manifest.base_epoch(),
+            filter: LiveObjectFilter::default(),
             indirect_objects_threshold,
             m,
             concurrency: download_concurrency.get(),
         })
     }
 
+    /// Creates a reader for every snapshot of the chain described by `manifests` (as returned by
+    /// `read_manifest_chain`), starting with the full snapshot. Callers restoring the objects
+    /// into their own store must apply the deleted objects and the objects of every delta in
+    /// order on top of what the previous readers restored.
+    pub async fn new_chain(
+        manifests: &[Manifest],
+        remote_store_config: &ObjectStoreConfig,
+        local_store_config: &ObjectStoreConfig,
+        indirect_objects_threshold: usize,
+        download_concurrency: NonZeroUsize,
+        m: MultiProgress,
+        skip_reset_local_store: bool,
+    ) -> Result<Vec<Self>> {
+        let mut readers = Vec::with_capacity(manifests.len());
+        for manifest in manifests {
+            readers.push(
+                Self::new(
+                    manifest.epoch(),
+                    remote_store_config,
+                    local_store_config,
+                    indirect_objects_threshold,
+                    download_concurrency,
+                    m.clone(),
+                    skip_reset_local_store,
+                )
+                .await?,
+            );
+        }
+        Ok(readers)
+    }
+
+    /// Only restore objects matching `filter`. Checksums and accumulators still cover every
+    /// object in the snapshot.
+    pub fn with_filter(mut self, filter: LiveObjectFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Epoch of the snapshot this one is a delta of, or `None` for a full snapshot
+    pub fn base_epoch(&self) -> Option<u64> {
+        self.base_epoch
+    }
+
     pub async fn read(
         &mut self,
         perpetual_db: &AuthorityPerpetualTables,
         abort_registration: AbortRegistration,
         sender: Option<tokio::sync::mpsc::Sender<(Accumulator, u64)>>,
     ) -> Result<()> {
+        if self.base_epoch.is_some() {
+            // A delta only restores correctly on top of the live object set of its base epoch
+            return Err(anyhow!(
+                "Snapshot for epoch {} is a delta and must be restored with StateSnapshotChainReader",
+                self.epoch
+            ));
+        }
+        self.read_internal(perpetual_db, abort_registration, sender)
+            .await
+    }
+
+    async fn read_internal(
+        &mut self,
+        perpetual_db: &AuthorityPerpetualTables,
+        abort_registration: AbortRegistration,
+        sender: Option<tokio::sync::mpsc::Sender<(Accumulator, u64)>>,
+    ) -> Result<()> {
+        if self.base_epoch.is_some() && sender.is_some() {
+            // Partial accumulators of a delta only cover the objects it changed
+            return Err(anyhow!(
+                "Cannot accumulate live objects from delta snapshot for epoch: {}",
+                self.epoch
+            ));
+        }
+        // Objects deleted since the base epoch are removed before anything is inserted so
+        // that an object recreated at a later version (e.g. unwrapped) ends up live
+        self.apply_deleted_objects(perpetual_db).await?;
         // This computes and stores the sha3 digest of object references in REFERENCE file for each
         // bucket partition. When downloading objects, we will match sha3 digest of object references
         // per *.obj file against this. We do this so during restore we can pre fetch object
@@ -377,6 +470,38 @@ impl StateSnapshotReaderV1 {
         })
     }
 
+    async fn apply_deleted_objects(&self, perpetual_db: &AuthorityPerpetualTables) -> Result<()> {
+        for file_metadata in self.deleted_files.values().flat_map(|parts| parts.values()) {
+            let deleted_objects = self.deleted_objects_iter(file_metadata).await?;
+            AuthorityStore::bulk_delete_live_objects(perpetual_db, deleted_objects)?;
+        }
+        Ok(())
+    }
+
+    /// Tombstone references of the objects deleted or wrapped since the base epoch of this
+    /// snapshot, empty for a full snapshot.
+    pub async fn deleted_object_refs(&self) -> Result<Vec<ObjectRef>> {
+        let mut deleted_object_refs = vec![];
+        for file_metadata in self.deleted_files.values().flat_map(|parts| parts.values()) {
+            deleted_object_refs.extend(self.deleted_objects_iter(file_metadata).await?);
+        }
+        Ok(deleted_object_refs)
+    }
+
+    async fn deleted_objects_iter(&self, file_metadata: &FileMetadata) -> Result<ObjectRefIter> {
+        let file_path = file_metadata.file_path(&self.epoch_dir());
+        let bytes = get(&self.remote_object_store, &file_path).await?;
+        let sha3_digest = compute_sha3_checksum_for_bytes(bytes.clone())?;
+        if sha3_digest != file_metadata.sha3_digest {
+            return Err(anyhow!(
+                "Checksum mismatch for deleted objects file, bucket: {}, part: {}",
+                file_metadata.bucket_num,
+                file_metadata.part_num
+            ));
+        }
+        ObjectRefIter::new_from_bytes(file_metadata, bytes)
+    }
+
     async fn sync_live_objects(
         &self,
         perpetual_db: &AuthorityPerpetualTables,
@@ -386,6 +511,8 @@ impl StateSnapshotReaderV1 {
         let epoch_dir = self.epoch_dir();
         let concurrency = self.concurrency;
         let threshold = self.indirect_objects_threshold;
+        let is_delta = self.base_epoch.is_some();
+        let filter = &self.filter;
         let remote_object_store = self.remote_object_store.clone();
         let input_files: Vec<_> = self
             .object_files
@@ -442,12 +569,23 @@ impl StateSnapshotReaderV1 {
                         let bytes_len = bytes.len();
                         let result: Result<(), anyhow::Error> =
                             LiveObjectIter::new(&file_metadata, bytes).map(|obj_iter| {
-                                AuthorityStore::bulk_insert_live_objects(
-                                    perpetual_db,
-                                    obj_iter,
-                                    threshold,
-                                    &sha3_digest,
-                                )
+                                if is_delta {
+                                    AuthorityStore::bulk_insert_delta_live_objects(
+                                        perpetual_db,
+                                        obj_iter,
+                                        |object| filter.matches(object),
+                                        threshold,
+                                        &sha3_digest,
+                                    )
+                                } else {
+                                    AuthorityStore::bulk_insert_filtered_live_objects(
+                                        perpetual_db,
+                                        obj_iter,
+                                        |object| filter.matches(object),
+                                        threshold,
+                                        &sha3_digest,
+                                    )
+                                }
                                 .expect("Failed to insert live objects");
                             });
                         downloaded_bytes.fetch_add(bytes_len, Ordering::Relaxed);
@@ -553,11 +691,501 @@ impl StateSnapshotReaderV1 {
         Ok(manifest)
     }
 
+    /// Number of live objects (including wrapped tombstones) in the REFERENCE files of this
+    /// snapshot
+    fn num_refs(&self) -> Result<u64> {
+        let mut num_refs = 0u64;
+        for (bucket, part_files) in self.ref_files.iter() {
+            for part in part_files.keys() {
+                num_refs += self.ref_iter(*bucket, *part)?.count() as u64;
+            }
+        }
+        Ok(num_refs)
+    }
+
     pub fn get_multi_progress(&self) -> MultiProgress {
         self.m.clone()
     }
 }
 
+/// StateSnapshotChainReader restores the live object set at an epoch that may have been
+/// snapshotted as a delta. It restores the full snapshot at the root of the delta chain and then
+/// applies every delta on top of it in order.
+pub struct StateSnapshotChainReader {
+    /// Full snapshot first, followed by deltas in increasing epoch order
+    readers: Vec<StateSnapshotReaderV1>,
+    /// Manifest of the requested epoch
+    manifest: Manifest,
+    /// Manifests of the whole chain, in the same order as `readers`
+    manifests: Vec<Manifest>,
+    filter: LiveObjectFilter,
+    remote_object_store: Arc<dyn ObjectStoreGetExt>,
+    local_staging_dir_root: PathBuf,
+    m: MultiProgress,
+}
+
+impl StateSnapshotChainReader {
+    pub async fn new(
+        epoch: u64,
+        remote_store_config: &ObjectStoreConfig,
+        local_store_config: &ObjectStoreConfig,
+        indirect_objects_threshold: usize,
+        download_concurrency: NonZeroUsize,
+        m: MultiProgress,
+    ) -> Result<Self> {
+        let remote_object_store: Arc<dyn ObjectStoreGetExt> = if remote_store_config.no_sign_request
+        {
+            remote_store_config.make_http()?
+        } else {
+            remote_store_config.make().map(Arc::new)?
+        };
+        let local_staging_dir_root = local_store_config
+            .directory
+            .as_ref()
+            .context("No directory specified")?
+            .clone();
+        let manifests = read_manifest_chain(epoch, &remote_object_store).await?;
+        let readers = StateSnapshotReaderV1::new_chain(
+            &manifests,
+            remote_store_config,
+            local_store_config,
+            indirect_objects_threshold,
+            download_concurrency,
+            m.clone(),
+            false, // skip_reset_local_store
+        )
+        .await?;
+        let manifest = manifests
+            .last()
+            .context(format!("No snapshot found for epoch: {epoch}"))?
+            .clone();
+        Ok(StateSnapshotChainReader {
+            readers,
+            manifest,
+            manifests,
+            filter: LiveObjectFilter::default(),
+            remote_object_store,
+            local_staging_dir_root,
+            m,
+        })
+    }
+
+    /// Only restore objects matching `filter` from every snapshot of the chain.
+    pub fn with_filter(mut self, filter: LiveObjectFilter) -> Self {
+        self.readers = self
+            .readers
+            .into_iter()
+            .map(|reader| reader.with_filter(filter.clone()))
+            .collect();
+        self.filter = filter;
+        self
+    }
+
+    /// Epochs of the snapshots in the chain, starting with the full snapshot
+    pub fn epochs(&self) -> Vec<u64> {
+        self.readers.iter().map(|reader| reader.epoch()).collect()
+    }
+
+    /// Restores the live object set into `perpetual_db` and returns its accumulator, which the
+    /// caller is expected to check against the root state hash committed on chain. When the
+    /// requested epoch is a delta, the accumulator is also checked against the root state hash
+    /// recorded in its manifest. With a filter, the accumulator of a delta is built from the
+    /// object references of the whole chain, since `perpetual_db` only holds a subset of the
+    /// live objects.
+    pub async fn read(
+        &mut self,
+        perpetual_db: &AuthorityPerpetualTables,
+        abort_registration: AbortRegistration,
+    ) -> Result<Accumulator> {
+        let Manifest::V2(manifest) = &self.manifest else {
+            return self.read_full(perpetual_db, abort_registration).await;
+        };
+        let readers = &mut self.readers;
+        Abortable::new(
+            async move {
+                for reader in readers.iter_mut() {
+                    info!(
+                        epoch = reader.epoch(),
+                        base_epoch = ?reader.base_epoch(),
+                        "Restoring state snapshot"
+                    );
+                    // Aborting is handled for the whole chain
+                    let (_abort_handle, abort_registration) = AbortHandle::new_pair();
+                    reader
+                        .read_internal(perpetual_db, abort_registration, None)
+                        .await?;
+                }
+                Ok::<(), anyhow::Error>(())
+            },
+            abort_registration,
+        )
+        .await??;
+
+        let accumulator = if self.filter.is_empty() {
+            let iter = perpetual_db.iter_live_object_set(manifest.include_wrapped_tombstone);
+            accumulate_live_object_iter(Box::new(iter), self.m.clone(), manifest.num_live_objects)
+                .await
+        } else {
+            let ref_dir = tempfile::tempdir_in(&self.local_staging_dir_root)?;
+            download_live_object_refs(&self.manifests, &self.remote_object_store, ref_dir.path())
+                .await?;
+            let manifests = self.manifests.clone();
+            let ref_dir_path = ref_dir.path().to_path_buf();
+            tokio::task::spawn_blocking(move || {
+                let mut accumulator = Accumulator::default();
+                for object_ref in LiveObjectRefIter::new(&manifests, ref_dir_path)? {
+                    let (object_id, version, digest) = object_ref?;
+                    if digest == ObjectDigest::OBJECT_DIGEST_WRAPPED {
+                        accumulator.insert(bcs::to_bytes(&WrappedObject::new(object_id, version))?);
+                    } else {
+                        accumulator.insert(digest);
+                    }
+                }
+                Ok::<Accumulator, anyhow::Error>(accumulator)
+            })
+            .await??
+        };
+        let local_digest = ECMHLiveObjectSetDigest::from(accumulator.digest());
+        if local_digest != manifest.root_state_hash {
+            return Err(anyhow!(
+                "Root state digest {} of delta snapshot for epoch {} does not match \
+                    local root state hash {} after restoring db",
+                manifest.root_state_hash.digest,
+                manifest.epoch,
+                local_digest.digest,
+            ));
+        }
+        Ok(accumulator)
+    }
+
+    /// Restores a chain made of a single full snapshot, accumulating the object references of
+    /// its REFERENCE files while the objects are being restored.
+    async fn read_full(
+        &mut self,
+        perpetual_db: &AuthorityPerpetualTables,
+        abort_registration: AbortRegistration,
+    ) -> Result<Accumulator> {
+        let reader = self
+            .readers
+            .last_mut()
+            .context("Empty state snapshot chain")?;
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(1000);
+        let accum_handle = tokio::spawn(async move {
+            let mut accumulator = Accumulator::default();
+            while let Some((partial_acc, _num_objects)) = receiver.recv().await {
+                accumulator.union(&partial_acc);
+            }
+            accumulator
+        });
+        reader
+            .read_internal(perpetual_db, abort_registration, Some(sender))
+            .await?;
+        Ok(accum_handle.await?)
+    }
+
+    /// Number of live objects at the requested epoch
+    pub fn num_live_objects(&self) -> Result<u64> {
+        match &self.manifest {
+            Manifest::V1(_) => self
+                .readers
+                .last()
+                .context("Empty state snapshot chain")?
+                .num_refs(),
+            Manifest::V2(manifest) => Ok(manifest.num_live_objects),
+        }
+    }
+}
+
+/// Reads the MANIFEST of the snapshot at `epoch` from the remote store.
+pub async fn read_remote_manifest<S: ObjectStoreGetExt>(
+    epoch: u64,
+    remote_object_store: &S,
+) -> Result<Manifest> {
+    let manifest_file_path = Path::from(format!("epoch_{}", epoch)).child("MANIFEST");
+    let bytes = get(remote_object_store, &manifest_file_path).await?;
+    parse_manifest(&bytes)
+}
+
+/// Returns the manifests of the chain of snapshots needed to restore `epoch`, starting with the
+/// full snapshot at the root of the chain and ending with the one for `epoch`.
+pub async fn read_manifest_chain<S: ObjectStoreGetExt>(
+    epoch: u64,
+    remote_object_store: &S,
+) -> Result<Vec<Manifest>> {
+    let mut manifests = vec![];
+    let mut next_epoch = Some(epoch);
+    while let Some(epoch) = next_epoch {
+        let manifest = read_remote_manifest(epoch, remote_object_store).await?;
+        if manifest.epoch() != epoch {
+            return Err(anyhow!("Download manifest is not for epoch: {}", epoch));
+        }
+        next_epoch = manifest.base_epoch();
+        if let Some(base_epoch) = next_epoch {
+            if base_epoch >= epoch {
+                return Err(anyhow!(
+                    "Delta snapshot for epoch {} has invalid base epoch {}",
+                    epoch,
+                    base_epoch
+                ));
+            }
+        }
+        manifests.push(manifest);
+    }
+    manifests.reverse();
+    Ok(manifests)
+}
+
+/// Downloads the REFERENCE and deleted object files of the snapshot chain described by
+/// `manifests` (as returned by `read_manifest_chain`) under `local_staging_dir_root`, so that
+/// `LiveObjectRefIter` can stream the live object references from local files.
+pub async fn download_live_object_refs<S: ObjectStoreGetExt>(
+    manifests: &[Manifest],
+    remote_object_store: &S,
+    local_staging_dir_root: &std::path::Path,
+) -> Result<()> {
+    for manifest in manifests {
+        let epoch_dir = Path::from(format!("epoch_{}", manifest.epoch()));
+        fs::create_dir_all(path_to_filesystem(
+            local_staging_dir_root.to_path_buf(),
+            &epoch_dir,
+        )?)?;
+        for file_metadata in manifest
+            .file_metadata()
+            .iter()
+            .filter(|file_metadata| file_metadata.file_type != FileType::Object)
+        {
+            let file_path = file_metadata.file_path(&epoch_dir);
+            let bytes = get(remote_object_store, &file_path).await?;
+            if compute_sha3_checksum_for_bytes(bytes.clone())? != file_metadata.sha3_digest {
+                return Err(anyhow!("Checksum mismatch for file: {}", file_path));
+            }
+            fs::write(
+                file_metadata.local_file_path(local_staging_dir_root, &epoch_dir)?,
+                bytes,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+type ObjectRefResultIter = Box<dyn Iterator<Item = Result<ObjectRef>>>;
+
+/// Streams the references of all live objects at the last epoch of a snapshot chain in
+/// increasing `ObjectID` order, without holding the live object set in memory. The REFERENCE
+/// files of the full snapshot are merged with the changes of every delta in order, using the
+/// files previously fetched with `download_live_object_refs`.
+pub struct LiveObjectRefIter {
+    inner: ObjectRefResultIter,
+}
+
+impl LiveObjectRefIter {
+    pub fn new(manifests: &[Manifest], local_staging_dir_root: PathBuf) -> Result<Self> {
+        let mut live_refs: Option<ObjectRefResultIter> = None;
+        for manifest in manifests {
+            let refs = Self::sorted_refs(manifest, FileType::Reference, &local_staging_dir_root)?;
+            live_refs = Some(match (live_refs, manifest.base_epoch()) {
+                (None, None) => refs,
+                (Some(base), Some(_)) => Box::new(MergedObjectRefIter::new(
+                    base,
+                    Self::sorted_refs(manifest, FileType::Deleted, &local_staging_dir_root)?,
+                    refs,
+                )),
+                _ => {
+                    return Err(anyhow!(
+                        "Snapshot chain must start with a full snapshot followed by deltas"
+                    ))
+                }
+            });
+        }
+        Ok(LiveObjectRefIter {
+            inner: live_refs.context("Empty state snapshot chain")?,
+        })
+    }
+
+    /// References in the files of `file_type` of the snapshot, in increasing `ObjectID` order.
+    /// Parts of a bucket are already ordered, buckets are merged together.
+    fn sorted_refs(
+        manifest: &Manifest,
+        file_type: FileType,
+        local_staging_dir_root: &std::path::Path,
+    ) -> Result<ObjectRefResultIter> {
+        let epoch_dir = Path::from(format!("epoch_{}", manifest.epoch()));
+        let mut buckets: BTreeMap<u32, BTreeMap<u32, &FileMetadata>> = BTreeMap::new();
+        for file_metadata in manifest.file_metadata() {
+            if file_metadata.file_type == file_type {
+                buckets
+                    .entry(file_metadata.bucket_num)
+                    .or_default()
+                    .insert(file_metadata.part_num, file_metadata);
+            }
+        }
+        let mut sorted_refs: ObjectRefResultIter = Box::new(std::iter::empty());
+        for parts in buckets.into_values() {
+            let mut bucket_refs: Vec<ObjectRefIter> = vec![];
+            for file_metadata in parts.into_values() {
+                bucket_refs.push(ObjectRefIter::new(
+                    file_metadata,
+                    local_staging_dir_root.to_path_buf(),
+                    epoch_dir.clone(),
+                )?);
+            }
+            let bucket_refs = SortedObjectRefIter {
+                inner: bucket_refs.into_iter().flatten(),
+                last: None,
+            };
+            sorted_refs = Box::new(MergedObjectRefIter::new(
+                sorted_refs,
+                Box::new(std::iter::empty()),
+                Box::new(bucket_refs),
+            ));
+        }
+        Ok(sorted_refs)
+    }
+}
+
+impl Iterator for LiveObjectRefIter {
+    type Item = Result<ObjectRef>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+}
+
+/// Checks that object references are in strictly increasing `ObjectID` order.
+struct SortedObjectRefIter<I> {
+    inner: I,
+    last: Option<ObjectID>,
+}
+
+impl<I: Iterator<Item = ObjectRef>> Iterator for SortedObjectRefIter<I> {
+    type Item = Result<ObjectRef>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let object_ref = self.inner.next()?;
+        if self.last.is_some_and(|last| last >= object_ref.0) {
+            return Some(Err(anyhow!(
+                "Object references are not sorted by object id at: {}",
+                object_ref.0
+            )));
+        }
+        self.last = Some(object_ref.0);
+        Some(Ok(object_ref))
+    }
+}
+
+/// Applies a sorted stream of deleted and of created or mutated object references on top of a
+/// sorted stream of base object references, keeping the result sorted.
+struct MergedObjectRefIter {
+    base: Peekable<ObjectRefResultIter>,
+    deleted: Peekable<ObjectRefResultIter>,
+    changed: Peekable<ObjectRefResultIter>,
+}
+
+impl MergedObjectRefIter {
+    fn new(
+        base: ObjectRefResultIter,
+        deleted: ObjectRefResultIter,
+        changed: ObjectRefResultIter,
+    ) -> Self {
+        MergedObjectRefIter {
+            base: base.peekable(),
+            deleted: deleted.peekable(),
+            changed: changed.peekable(),
+        }
+    }
+
+    fn peek_id(iter: &mut Peekable<ObjectRefResultIter>) -> Result<Option<ObjectID>> {
+        match iter.peek() {
+            Some(Ok(object_ref)) => Ok(Some(object_ref.0)),
+            Some(Err(_)) => iter.next().transpose().map(|_| None),
+            None => Ok(None),
+        }
+    }
+
+    fn next_ref(&mut self) -> Result<Option<ObjectRef>> {
+        loop {
+            let base_id = Self::peek_id(&mut self.base)?;
+            let changed_id = Self::peek_id(&mut self.changed)?;
+            let deleted_id = Self::peek_id(&mut self.deleted)?;
+            match (base_id, deleted_id) {
+                (Some(base_id), Some(deleted_id)) if deleted_id == base_id => {
+                    self.base.next();
+                    self.deleted.next();
+                    continue;
+                }
+                (Some(base_id), Some(deleted_id)) if deleted_id < base_id => {
+                    return Err(anyhow!("Deleted object {} is not live", deleted_id));
+                }
+                (None, Some(deleted_id)) => {
+                    return Err(anyhow!("Deleted object {} is not live", deleted_id));
+                }
+                _ => {}
+            }
+            return match (base_id, changed_id) {
+                (Some(base_id), Some(changed_id)) if changed_id <= base_id => {
+                    if changed_id == base_id {
+                        self.base.next();
+                    }
+                    self.changed.next().transpose()
+                }
+                (Some(_), _) => self.base.next().transpose(),
+                (None, _) => self.changed.next().transpose(),
+            };
+        }
+    }
+}
+
+impl Iterator for MergedObjectRefIter {
+    type Item = Result<ObjectRef>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_ref().transpose()
+    }
+}
+
+/// Checks that the references of `live_objects`, in order, hash to `expected_sha3_digest`: the
+/// digest of the REFERENCE file partition matching the object file they were read from.
+pub fn verify_live_objects_sha3(
+    live_objects: &[LiveObject],
+    expected_sha3_digest: &[u8; 32],
+) -> Result<()> {
+    let mut hasher = Sha3_256::default();
+    for object in live_objects {
+        hasher.update(object.object_reference().2.inner());
+    }
+    let sha3_digest = hasher.finalize().digest;
+    if sha3_digest != *expected_sha3_digest {
+        return Err(anyhow!(
+            "Sha does not match! expected: {:?}, actual: {:?}",
+            expected_sha3_digest,
+            sha3_digest
+        ));
+    }
+    Ok(())
+}
+
+fn parse_manifest(bytes: &[u8]) -> Result<Manifest> {
+    if bytes.len() < MAGIC_BYTES + SHA3_BYTES {
+        return Err(anyhow!("Manifest too short: {} bytes", bytes.len()));
+    }
+    let magic = (&bytes[..MAGIC_BYTES]).read_u32::<BigEndian>()?;
+    if magic != MANIFEST_FILE_MAGIC {
+        return Err(anyhow!("Unexpected magic byte: {}", magic));
+    }
+    let (content_buf, sha3_digest) = bytes.split_at(bytes.len() - SHA3_BYTES);
+    let mut hasher = Sha3_256::default();
+    hasher.update(content_buf);
+    let computed_digest = hasher.finalize().digest;
+    if computed_digest != sha3_digest {
+        return Err(anyhow!(
+            "Checksum: {:?} don't match: {:?}",
+            computed_digest,
+            sha3_digest
+        ));
+    }
+    Ok(bcs::from_bytes(&content_buf[MAGIC_BYTES..])?)
+}
+
 pub async fn download_bytes(
     remote_object_store: Arc<dyn ObjectStoreGetExt>,
     file_metadata: &FileMetadata,
@@ -607,7 +1235,7 @@ pub async fn download_bytes(
     (bytes, sha3_digest)
 }
 
-/// An iterator over all object refs in a .ref file.
+/// An iterator over all object refs in a .ref or .del file.
 pub struct ObjectRefIter {
     reader: Box<dyn Read>,
 }
@@ -615,11 +1243,28 @@ pub struct ObjectRefIter {
 impl ObjectRefIter {
     pub fn new(file_metadata: &FileMetadata, root_path: PathBuf, dir_path: Path) -> Result<Self> {
         let file_path = file_metadata.local_file_path(&root_path, &dir_path)?;
-        let mut reader = file_metadata.file_compression.decompress(&file_path)?;
+        let reader = file_metadata.file_compression.decompress(&file_path)?;
+        Self::from_reader(file_metadata, reader)
+    }
+
+    pub fn new_from_bytes(file_metadata: &FileMetadata, bytes: Bytes) -> Result<Self> {
+        let reader = file_metadata.file_compression.bytes_decompress(bytes)?;
+        Self::from_reader(file_metadata, reader)
+    }
+
+    fn from_reader(file_metadata: &FileMetadata, mut reader: Box<dyn Read>) -> Result<Self> {
+        let expected_magic = match file_metadata.file_type {
+            FileType::Reference => REFERENCE_FILE_MAGIC,
+            FileType::Deleted => DELETED_FILE_MAGIC,
+            FileType::Object => {
+                return Err(anyhow!("Cannot read object refs from an object file"));
+            }
+        };
         let magic = reader.read_u32::<BigEndian>()?;
-        if magic != REFERENCE_FILE_MAGIC {
+        if magic != expected_magic {
             Err(anyhow!(
-                "Unexpected magic string in REFERENCE file: {:?}",
+                "Unexpected magic string in {:?} file: {:?}",
+                file_metadata.file_type,
                 magic
             ))
         } else {
@@ -1,23 +1,29 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::reader::StateSnapshotReaderV1;
+use crate::filter::LiveObjectFilter;
+use crate::reader::{
+    download_live_object_refs, read_manifest_chain, LiveObjectRefIter, StateSnapshotChainReader,
+    StateSnapshotReaderV1,
+};
 use crate::writer::StateSnapshotWriterV1;
 use crate::FileCompression;
 use fastcrypto::hash::MultisetHash;
 use futures::future::AbortHandle;
 use indicatif::MultiProgress;
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
 use std::num::NonZeroUsize;
 use std::sync::Arc;
 use mys_config::object_storage_config::{ObjectStoreConfig, ObjectStoreType};
-use mys_core::authority::authority_store_tables::AuthorityPerpetualTables;
+use mys_core::authority::authority_store_tables::{AuthorityPerpetualTables, LiveObject};
+use mys_core::authority::AuthorityStore;
 use mys_core::state_accumulator::StateAccumulator;
 use mys_protocol_config::ProtocolConfig;
 use mys_types::accumulator::Accumulator;
-use mys_types::base_types::ObjectID;
+use mys_types::base_types::{MysAddress, ObjectDigest, ObjectID, SequenceNumber};
 use mys_types::messages_checkpoint::ECMHLiveObjectSetDigest;
-use mys_types::object::Object;
+use mys_types::object::{Object, Owner};
+use mys_types::parse_mys_struct_tag;
 use tempfile::tempdir;
 
 fn temp_dir() -> std::path::PathBuf {
@@ -183,3 +189,284 @@ async fn test_snapshot_empty_db() -> Result<(), anyhow::Error> {
     )?;
     Ok(())
 }
+
+#[tokio::test]
+async fn test_snapshot_delta_chain() -> Result<(), anyhow::Error> {
+    let db_path = temp_dir();
+    let restored_db_path = temp_dir();
+    let local = temp_dir().join("local_dir");
+    let remote = temp_dir().join("remote_dir");
+    let restored_local = temp_dir().join("local_dir_restore");
+    let local_store_config = ObjectStoreConfig {
+        object_store: Some(ObjectStoreType::File),
+        directory: Some(local),
+        ..Default::default()
+    };
+    let remote_store_config = ObjectStoreConfig {
+        object_store: Some(ObjectStoreType::File),
+        directory: Some(remote),
+        ..Default::default()
+    };
+    let new_writer = || {
+        StateSnapshotWriterV1::new(
+            &local_store_config,
+            &remote_store_config,
+            FileCompression::Zstd,
+            NonZeroUsize::new(1).unwrap(),
+        )
+    };
+    let perpetual_db = Arc::new(AuthorityPerpetualTables::open(&db_path, None));
+    insert_keys(&perpetual_db, 1000)?;
+    let root_accumulator =
+        ECMHLiveObjectSetDigest::from(accumulate_live_object_set(&perpetual_db, true).digest());
+    new_writer()
+        .await?
+        .write_internal(0, true, perpetual_db.clone(), root_accumulator)
+        .await?;
+
+    // Epoch 1: delete the first 100 objects and mutate the next 100
+    let ids = ObjectID::in_range(ObjectID::ZERO, 200)?;
+    let deleted: Vec<_> = ids[..100]
+        .iter()
+        .map(|id| {
+            (
+                *id,
+                SequenceNumber::from_u64(2),
+                ObjectDigest::OBJECT_DIGEST_DELETED,
+            )
+        })
+        .collect();
+    AuthorityStore::bulk_delete_live_objects(&perpetual_db, deleted.iter().copied())?;
+    for id in &ids[100..] {
+        perpetual_db.insert_object_test_only(Object::with_id_owner_version_for_testing(
+            *id,
+            SequenceNumber::from_u64(5),
+            Owner::Immutable,
+        ))?;
+    }
+    let root_accumulator =
+        ECMHLiveObjectSetDigest::from(accumulate_live_object_set(&perpetual_db, true).digest());
+    new_writer()
+        .await?
+        .write_delta_internal(1, 0, true, perpetual_db.clone(), root_accumulator)
+        .await?;
+
+    // Epoch 2: create 100 new objects
+    for id in &ObjectID::in_range(ObjectID::ZERO, 1100)?[1000..] {
+        perpetual_db.insert_object_test_only(Object::immutable_with_id_for_testing(*id))?;
+    }
+    let root_accumulator =
+        ECMHLiveObjectSetDigest::from(accumulate_live_object_set(&perpetual_db, true).digest());
+    new_writer()
+        .await?
+        .write_delta_internal(2, 1, true, perpetual_db.clone(), root_accumulator.clone())
+        .await?;
+
+    // The deleted objects are recorded with the version they were deleted at
+    let remote_object_store = remote_store_config.make()?;
+    let mut delta_reader = StateSnapshotReaderV1::new(
+        1,
+        &remote_store_config,
+        &local_store_config,
+        usize::MAX,
+        NonZeroUsize::new(1).unwrap(),
+        MultiProgress::new(),
+        false, // skip_reset_local_store
+    )
+    .await?;
+    let mut deleted_refs = delta_reader.deleted_object_refs().await?;
+    deleted_refs.sort();
+    assert_eq!(deleted_refs, deleted);
+
+    // A delta cannot be restored on its own
+    let (_abort_handle, abort_registration) = AbortHandle::new_pair();
+    assert!(delta_reader
+        .read(
+            &AuthorityPerpetualTables::open(&temp_dir(), None),
+            abort_registration,
+            None
+        )
+        .await
+        .is_err());
+
+    let manifests = read_manifest_chain(2, &remote_object_store).await?;
+    let refs_dir = temp_dir();
+    download_live_object_refs(&manifests, &remote_object_store, &refs_dir).await?;
+    let live_refs = LiveObjectRefIter::new(&manifests, refs_dir)?.collect::<Result<Vec<_>, _>>()?;
+    let expected_refs: Vec<_> = perpetual_db
+        .iter_live_object_set(true)
+        .map(|object| object.object_reference())
+        .collect();
+    assert_eq!(live_refs.len(), 1000);
+    assert_eq!(live_refs, expected_refs);
+
+    let local_store_restore_config = ObjectStoreConfig {
+        object_store: Some(ObjectStoreType::File),
+        directory: Some(restored_local),
+        ..Default::default()
+    };
+    let mut chain_reader = StateSnapshotChainReader::new(
+        2,
+        &remote_store_config,
+        &local_store_restore_config,
+        usize::MAX,
+        NonZeroUsize::new(1).unwrap(),
+        MultiProgress::new(),
+    )
+    .await?;
+    assert_eq!(chain_reader.epochs(), vec![0, 1, 2]);
+    assert_eq!(chain_reader.num_live_objects()?, 1000);
+    let restored_perpetual_db = AuthorityPerpetualTables::open(&restored_db_path, None);
+    let (_abort_handle, abort_registration) = AbortHandle::new_pair();
+    let restored_accumulator = chain_reader
+        .read(&restored_perpetual_db, abort_registration)
+        .await?;
+    assert_eq!(
+        ECMHLiveObjectSetDigest::from(restored_accumulator.digest()),
+        root_accumulator
+    );
+    compare_live_objects(&perpetual_db, &restored_perpetual_db, true)?;
+    for (object_id, version, _) in deleted {
+        assert!(restored_perpetual_db
+            .get_latest_object_ref_or_tombstone(object_id)?
+            .is_some_and(|object_ref| object_ref.1 == version && !object_ref.2.is_alive()));
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_snapshot_filtered_restore() -> Result<(), anyhow::Error> {
+    let db_path = temp_dir();
+    let restored_db_path = temp_dir();
+    let local = temp_dir().join("local_dir");
+    let remote = temp_dir().join("remote_dir");
+    let restored_local = temp_dir().join("local_dir_restore");
+    let local_store_config = ObjectStoreConfig {
+        object_store: Some(ObjectStoreType::File),
+        directory: Some(local),
+        ..Default::default()
+    };
+    let remote_store_config = ObjectStoreConfig {
+        object_store: Some(ObjectStoreType::File),
+        directory: Some(remote),
+        ..Default::default()
+    };
+
+    let snapshot_writer = StateSnapshotWriterV1::new(
+        &local_store_config,
+        &remote_store_config,
+        FileCompression::Zstd,
+        NonZeroUsize::new(1).unwrap(),
+    )
+    .await?;
+    let perpetual_db = Arc::new(AuthorityPerpetualTables::open(&db_path, None));
+    insert_keys(&perpetual_db, 1000)?;
+    let owner = MysAddress::random_for_testing_only();
+    let mut owned_objects = HashSet::new();
+    for _ in 0..10 {
+        let LiveObject::Normal(object) = owned_gas_coin(owner) else {
+            unreachable!()
+        };
+        owned_objects.insert(object.compute_object_reference());
+        perpetual_db.insert_object_test_only(object)?;
+    }
+    let root_accumulator =
+        ECMHLiveObjectSetDigest::from(accumulate_live_object_set(&perpetual_db, true).digest());
+    snapshot_writer
+        .write_internal(0, true, perpetual_db.clone(), root_accumulator.clone())
+        .await?;
+    let local_store_restore_config = ObjectStoreConfig {
+        object_store: Some(ObjectStoreType::File),
+        directory: Some(restored_local),
+        ..Default::default()
+    };
+    let filter = LiveObjectFilter {
+        owners: BTreeSet::from([owner]),
+        ..Default::default()
+    };
+    let mut snapshot_reader = StateSnapshotReaderV1::new(
+        0,
+        &remote_store_config,
+        &local_store_restore_config,
+        usize::MAX,
+        NonZeroUsize::new(1).unwrap(),
+        MultiProgress::new(),
+        false, // skip_reset_local_store
+    )
+    .await?
+    .with_filter(filter);
+    let restored_perpetual_db = AuthorityPerpetualTables::open(&restored_db_path, None);
+    let (_abort_handle, abort_registration) = AbortHandle::new_pair();
+    let (sender, mut receiver) = tokio::sync::mpsc::channel(100);
+    snapshot_reader
+        .read(&restored_perpetual_db, abort_registration, Some(sender))
+        .await?;
+
+    // The accumulator still covers the full live object set
+    let mut snapshot_accumulator = Accumulator::default();
+    let mut num_live_objects = 0;
+    while let Some((partial_acc, num_objects)) = receiver.recv().await {
+        num_live_objects += num_objects;
+        snapshot_accumulator.union(&partial_acc);
+    }
+    assert_eq!(num_live_objects, 1010);
+    assert_eq!(
+        ECMHLiveObjectSetDigest::from(snapshot_accumulator.digest()),
+        root_accumulator
+    );
+    let restored_objects: HashSet<_> = restored_perpetual_db
+        .iter_live_object_set(true)
+        .map(|object| object.object_reference())
+        .collect();
+    assert_eq!(restored_objects, owned_objects);
+    Ok(())
+}
+
+fn owned_gas_coin(owner: MysAddress) -> LiveObject {
+    LiveObject::Normal(Object::with_id_owner_version_for_testing(
+        ObjectID::random(),
+        SequenceNumber::from_u64(1),
+        Owner::AddressOwner(owner),
+    ))
+}
+
+#[test]
+fn test_empty_filter_matches_everything() {
+    let filter = LiveObjectFilter::default();
+    assert!(filter.matches(&owned_gas_coin(MysAddress::random_for_testing_only())));
+}
+
+#[test]
+fn test_struct_type_filter() {
+    let object = owned_gas_coin(MysAddress::random_for_testing_only());
+    for (pattern, expected) in [
+        ("0x2::coin::Coin", true),
+        ("0x2::coin::Coin<0x2::mys::MYS>", true),
+        ("0x2::coin::Coin<0x2::mys::MYSX>", false),
+        ("0x2::balance::Balance", false),
+    ] {
+        let filter = LiveObjectFilter {
+            struct_types: vec![parse_mys_struct_tag(pattern).unwrap()],
+            ..Default::default()
+        };
+        assert_eq!(filter.matches(&object), expected, "{pattern}");
+    }
+}
+
+#[test]
+fn test_package_and_owner_filter() {
+    let owner = MysAddress::random_for_testing_only();
+    let object = owned_gas_coin(owner);
+    let filter = LiveObjectFilter {
+        packages: BTreeSet::from([ObjectID::from_single_byte(2)]),
+        ..Default::default()
+    };
+    assert!(filter.matches(&object));
+    let filter = LiveObjectFilter {
+        packages: BTreeSet::from([ObjectID::random()]),
+        owners: BTreeSet::from([owner]),
+        ..Default::default()
+    };
+    assert!(filter.matches(&object));
+    assert!(!filter.matches(&owned_gas_coin(MysAddress::random_for_testing_only())));
+}
@@ -1,9 +1,11 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::reader::read_manifest_chain;
 use crate::writer::StateSnapshotWriterV1;
 use anyhow::Result;
 use bytes::Bytes;
+use object_store::path::Path;
 use object_store::DynObjectStore;
 use prometheus::{
     register_int_counter_with_registry, register_int_gauge_with_registry, IntCounter, IntGauge,
@@ -21,10 +23,11 @@ use mys_storage::object_store::util::{
     find_all_dirs_with_epoch_prefix, find_missing_epochs_dirs, path_to_filesystem, put,
     run_manifest_update_loop,
 };
+use mys_storage::object_store::ObjectStoreGetExt;
 use mys_storage::FileCompression;
 use mys_types::digests::ChainIdentifier;
 use mys_types::messages_checkpoint::CheckpointCommitment::ECMHLiveObjectSetDigest;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
 pub struct StateSnapshotUploaderMetrics {
     pub first_missing_state_snapshot_epoch: IntGauge,
@@ -70,9 +73,13 @@ pub struct StateSnapshotUploader {
     /// The chain identifier is derived from the genesis checkpoint and used to identify the
     /// network.
     chain_identifier: ChainIdentifier,
+    /// Maximum number of delta snapshots on top of a full snapshot, zero to only write full
+    /// snapshots
+    max_delta_chain_length: usize,
 }
 
 impl StateSnapshotUploader {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         db_checkpoint_path: &std::path::Path,
         staging_path: &std::path::Path,
@@ -81,6 +88,7 @@ impl StateSnapshotUploader {
         registry: &Registry,
         checkpoint_store: Arc<CheckpointStore>,
         chain_identifier: ChainIdentifier,
+        max_delta_chain_length: usize,
     ) -> Result<Arc<Self>> {
         let db_checkpoint_store_config = ObjectStoreConfig {
             object_store: Some(ObjectStoreType::File),
@@ -102,6 +110,7 @@ impl StateSnapshotUploader {
             interval: Duration::from_secs(interval_s),
             metrics: StateSnapshotUploaderMetrics::new(registry),
             chain_identifier,
+            max_delta_chain_length,
         }))
     }
 
@@ -124,14 +133,6 @@ impl StateSnapshotUploader {
         for (epoch, db_path) in dirs {
             if missing_epochs.contains(epoch) || *epoch >= last_missing_epoch {
                 info!("Starting state snapshot creation for epoch: {}", *epoch);
-                let state_snapshot_writer = StateSnapshotWriterV1::new_from_store(
-                    &self.staging_path,
-                    &self.staging_store,
-                    &self.snapshot_store,
-                    FileCompression::Zstd,
-                    NonZeroUsize::new(20).unwrap(),
-                )
-                .await?;
                 let db = Arc::new(AuthorityPerpetualTables::open(
                     &path_to_filesystem(self.db_checkpoint_path.clone(), &db_path.child("store"))?,
                     None,
@@ -145,9 +146,38 @@ impl StateSnapshotUploader {
                     .last()
                     .expect("Expected at least one commitment")
                     .clone();
-                state_snapshot_writer
-                    .write(*epoch, db, state_hash_commitment, self.chain_identifier)
-                    .await?;
+                let mut full_snapshot = true;
+                if let Some(base_epoch) = self.delta_base_epoch(*epoch).await {
+                    info!(
+                        "Writing delta state snapshot for epoch {} on top of epoch {}",
+                        *epoch, base_epoch
+                    );
+                    match self
+                        .new_writer()
+                        .await?
+                        .write_delta(
+                            *epoch,
+                            base_epoch,
+                            db.clone(),
+                            state_hash_commitment.clone(),
+                            self.chain_identifier,
+                        )
+                        .await
+                    {
+                        Ok(()) => full_snapshot = false,
+                        Err(err) => warn!(
+                            "Failed to write delta state snapshot for epoch {}, writing a full \
+                            snapshot instead: {:?}",
+                            *epoch, err
+                        ),
+                    }
+                }
+                if full_snapshot {
+                    self.new_writer()
+                        .await?
+                        .write(*epoch, db, state_hash_commitment, self.chain_identifier)
+                        .await?;
+                }
                 info!("State snapshot creation successful for epoch: {}", *epoch);
                 // Drop marker in the output directory that upload completed successfully
                 let bytes = Bytes::from_static(b"success");
@@ -211,6 +241,47 @@ impl StateSnapshotUploader {
         Ok(())
     }
 
+    async fn new_writer(&self) -> Result<StateSnapshotWriterV1> {
+        StateSnapshotWriterV1::new_from_store(
+            &self.staging_path,
+            &self.staging_store,
+            &self.snapshot_store,
+            FileCompression::Zstd,
+            NonZeroUsize::new(20).unwrap(),
+        )
+        .await
+    }
+
+    /// Epoch to write the snapshot of `epoch` as a delta of: the previous epoch, if its snapshot
+    /// was uploaded successfully and the delta chain would not exceed `max_delta_chain_length`.
+    async fn delta_base_epoch(&self, epoch: u64) -> Option<u64> {
+        if self.max_delta_chain_length == 0 {
+            return None;
+        }
+        let base_epoch = epoch.checked_sub(1)?;
+        let success_marker = Path::from(format!("epoch_{}", base_epoch)).child(SUCCESS_MARKER);
+        if self
+            .snapshot_store
+            .get_bytes(&success_marker)
+            .await
+            .is_err()
+        {
+            return None;
+        }
+        match read_manifest_chain(base_epoch, &self.snapshot_store).await {
+            // The chain of the base epoch starts with a full snapshot followed by its deltas
+            Ok(manifests) if manifests.len() <= self.max_delta_chain_length => Some(base_epoch),
+            Ok(_) => None,
+            Err(err) => {
+                warn!(
+                    "Failed to read snapshot chain of epoch {}, writing a full snapshot: {:?}",
+                    base_epoch, err
+                );
+                None
+            }
+        }
+    }
+
     async fn get_missing_epochs(&self) -> Result<Vec<u64>> {
         let missing_epochs = find_missing_epochs_dirs(&self.snapshot_store, SUCCESS_MARKER).await?;
         Ok(missing_epochs.to_vec())
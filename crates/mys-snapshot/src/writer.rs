@@ -2,12 +2,13 @@
 // SPDX-License-Identifier: Apache-2.0
 #![allow(dead_code)]
 
+use crate::reader::{download_live_object_refs, read_manifest_chain, LiveObjectRefIter};
 use crate::{
     compute_sha3_checksum, create_file_metadata, FileCompression, FileMetadata, FileType, Manifest,
-    ManifestV1, FILE_MAX_BYTES, MAGIC_BYTES, MANIFEST_FILE_MAGIC, OBJECT_FILE_MAGIC,
-    OBJECT_REF_BYTES, REFERENCE_FILE_MAGIC, SEQUENCE_NUM_BYTES,
+    ManifestV1, ManifestV2, DELETED_FILE_MAGIC, FILE_MAX_BYTES, MAGIC_BYTES, MANIFEST_FILE_MAGIC,
+    OBJECT_FILE_MAGIC, OBJECT_REF_BYTES, REFERENCE_FILE_MAGIC, SEQUENCE_NUM_BYTES,
 };
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use byteorder::{BigEndian, ByteOrder};
 use fastcrypto::hash::MultisetHash;
 use futures::StreamExt;
@@ -193,15 +194,110 @@ impl LiveObjectSetWriterV1 {
         Ok(())
     }
     fn write_object_ref(&mut self, object_ref: &ObjectRef) -> Result<()> {
-        let mut buf = [0u8; OBJECT_REF_BYTES];
-        buf[0..ObjectID::LENGTH].copy_from_slice(object_ref.0.as_ref());
-        BigEndian::write_u64(
-            &mut buf[ObjectID::LENGTH..OBJECT_REF_BYTES],
-            object_ref.1.value(),
-        );
-        buf[ObjectID::LENGTH + SEQUENCE_NUM_BYTES..OBJECT_REF_BYTES]
-            .copy_from_slice(object_ref.2.as_ref());
-        self.ref_wbuf.write_all(&buf)?;
+        self.ref_wbuf.write_all(&serialize_object_ref(object_ref))?;
+        Ok(())
+    }
+}
+
+fn serialize_object_ref(object_ref: &ObjectRef) -> [u8; OBJECT_REF_BYTES] {
+    let mut buf = [0u8; OBJECT_REF_BYTES];
+    buf[0..ObjectID::LENGTH].copy_from_slice(object_ref.0.as_ref());
+    BigEndian::write_u64(
+        &mut buf[ObjectID::LENGTH..OBJECT_REF_BYTES],
+        object_ref.1.value(),
+    );
+    buf[ObjectID::LENGTH + SEQUENCE_NUM_BYTES..OBJECT_REF_BYTES]
+        .copy_from_slice(object_ref.2.as_ref());
+    buf
+}
+
+/// DeletedObjectsWriterV1 writes the references of objects deleted since the base epoch of a
+/// delta snapshot to *.del files. Deleted objects are not bucketed and all go to bucket 1.
+struct DeletedObjectsWriterV1 {
+    dir_path: PathBuf,
+    bucket_num: u32,
+    current_part_num: u32,
+    wbuf: BufWriter<File>,
+    n: usize,
+    files: Vec<FileMetadata>,
+    sender: Option<Sender<FileMetadata>>,
+    file_compression: FileCompression,
+}
+
+impl DeletedObjectsWriterV1 {
+    fn new(
+        dir_path: PathBuf,
+        file_compression: FileCompression,
+        sender: Sender<FileMetadata>,
+    ) -> Result<Self> {
+        let bucket_num = 1;
+        let part_num = 1;
+        let (n, f) = Self::deleted_file(dir_path.clone(), bucket_num, part_num)?;
+        Ok(DeletedObjectsWriterV1 {
+            dir_path,
+            bucket_num,
+            current_part_num: part_num,
+            wbuf: BufWriter::new(f),
+            n,
+            files: vec![],
+            sender: Some(sender),
+            file_compression,
+        })
+    }
+    pub fn write(&mut self, object_ref: &ObjectRef) -> Result<()> {
+        if self.n + OBJECT_REF_BYTES > FILE_MAX_BYTES {
+            self.finalize()?;
+            self.current_part_num += 1;
+            let (n, f) = Self::deleted_file(
+                self.dir_path.clone(),
+                self.bucket_num,
+                self.current_part_num,
+            )?;
+            self.n = n;
+            self.wbuf = BufWriter::new(f);
+        }
+        self.wbuf.write_all(&serialize_object_ref(object_ref))?;
+        self.n += OBJECT_REF_BYTES;
+        Ok(())
+    }
+    pub fn done(mut self) -> Result<Vec<FileMetadata>> {
+        self.finalize()?;
+        self.sender = None;
+        Ok(self.files.clone())
+    }
+    fn deleted_file(dir_path: PathBuf, bucket_num: u32, part_num: u32) -> Result<(usize, File)> {
+        let del_path = dir_path.join(format!("{bucket_num}_{part_num}.del"));
+        let del_tmp_path = dir_path.join(format!("{bucket_num}_{part_num}.del.tmp"));
+        let mut f = File::create(del_tmp_path.clone())?;
+        f.rewind()?;
+        let mut metab = [0u8; MAGIC_BYTES];
+        BigEndian::write_u32(&mut metab, DELETED_FILE_MAGIC);
+        let n = f.write(&metab)?;
+        drop(f);
+        fs::rename(del_tmp_path, del_path.clone())?;
+        let mut f = OpenOptions::new().append(true).open(del_path)?;
+        f.seek(SeekFrom::Start(n as u64))?;
+        Ok((n, f))
+    }
+    fn finalize(&mut self) -> Result<()> {
+        self.wbuf.flush()?;
+        self.wbuf.get_ref().sync_data()?;
+        let off = self.wbuf.get_ref().stream_position()?;
+        self.wbuf.get_ref().set_len(off)?;
+        let file_path = self
+            .dir_path
+            .join(format!("{}_{}.del", self.bucket_num, self.current_part_num));
+        let file_metadata = create_file_metadata(
+            &file_path,
+            self.file_compression,
+            FileType::Deleted,
+            self.bucket_num,
+            self.current_part_num,
+        )?;
+        self.files.push(file_metadata.clone());
+        if let Some(sender) = &self.sender {
+            sender.blocking_send(file_metadata)?;
+        }
         Ok(())
     }
 }
@@ -279,6 +375,108 @@ impl StateSnapshotWriterV1 {
         .await
     }
 
+    /// Writes a delta snapshot for `epoch` holding only the changes to the live object set since
+    /// the snapshot (full or delta) at `base_epoch`, which must already exist in the remote store.
+    pub async fn write_delta(
+        self,
+        epoch: u64,
+        base_epoch: u64,
+        perpetual_db: Arc<AuthorityPerpetualTables>,
+        root_state_hash: ECMHLiveObjectSetDigest,
+        chain_identifier: ChainIdentifier,
+    ) -> Result<()> {
+        let system_state_object = get_mys_system_state(&perpetual_db)?;
+
+        let protocol_version = system_state_object.protocol_version();
+        let protocol_config = ProtocolConfig::get_for_version(
+            ProtocolVersion::new(protocol_version),
+            chain_identifier.chain(),
+        );
+        let include_wrapped_tombstone = !protocol_config.simplified_unwrap_then_delete();
+        self.write_delta_internal(
+            epoch,
+            base_epoch,
+            include_wrapped_tombstone,
+            perpetual_db,
+            root_state_hash,
+        )
+        .await
+    }
+
+    pub(crate) async fn write_delta_internal(
+        mut self,
+        epoch: u64,
+        base_epoch: u64,
+        include_wrapped_tombstone: bool,
+        perpetual_db: Arc<AuthorityPerpetualTables>,
+        root_state_hash: ECMHLiveObjectSetDigest,
+    ) -> Result<()> {
+        if base_epoch >= epoch {
+            return Err(anyhow!(
+                "Base epoch {} of delta snapshot must be before epoch {}",
+                base_epoch,
+                epoch
+            ));
+        }
+        // Object references of the base epoch are streamed from local copies of the REFERENCE
+        // and deleted object files of its snapshot chain
+        fs::create_dir_all(&self.local_staging_dir)?;
+        let base_refs_dir = tempfile::tempdir_in(&self.local_staging_dir)?;
+        let base_manifests = read_manifest_chain(base_epoch, &self.remote_object_store)
+            .await
+            .context(format!(
+                "Failed to read base snapshot for epoch: {}",
+                &base_epoch
+            ))?;
+        download_live_object_refs(
+            &base_manifests,
+            &self.remote_object_store,
+            base_refs_dir.path(),
+        )
+        .await?;
+        self.setup_epoch_dir(epoch).await?;
+
+        let manifest_file_path = self.epoch_dir(epoch).child("MANIFEST");
+        let local_staging_dir = self.local_staging_dir.clone();
+        let local_object_store = self.local_staging_store.clone();
+        let remote_object_store = self.remote_object_store.clone();
+
+        let (sender, receiver) = mpsc::channel::<FileMetadata>(1000);
+        let upload_handle = self.start_upload(epoch, receiver)?;
+        let write_handler = tokio::task::spawn_blocking(move || {
+            let base_refs =
+                LiveObjectRefIter::new(&base_manifests, base_refs_dir.path().to_path_buf())?;
+            self.write_delta_object_set(
+                epoch,
+                base_epoch,
+                base_refs,
+                perpetual_db,
+                sender,
+                Self::bucket_func,
+                include_wrapped_tombstone,
+                root_state_hash,
+            )
+        });
+        write_handler.await?.context(format!(
+            "Failed to write delta state snapshot for epoch: {}",
+            &epoch
+        ))?;
+
+        upload_handle.await?.context(format!(
+            "Failed to upload delta state snapshot for epoch: {}",
+            &epoch
+        ))?;
+
+        Self::sync_file_to_remote(
+            local_staging_dir,
+            manifest_file_path,
+            local_object_store,
+            remote_object_store,
+        )
+        .await?;
+        Ok(())
+    }
+
     pub(crate) async fn write_internal(
         mut self,
         epoch: u64,
@@ -405,19 +603,152 @@ impl StateSnapshotWriterV1 {
         for (_, writer) in object_writers.into_iter() {
             files.extend(writer.done()?);
         }
-        self.write_manifest(epoch, files)?;
+        let manifest = Manifest::V1(ManifestV1 {
+            snapshot_version: 1,
+            address_length: ObjectID::LENGTH as u64,
+            file_metadata: files,
+            epoch,
+        });
+        self.write_manifest(epoch, manifest)?;
         Ok(())
     }
 
-    fn write_manifest(&mut self, epoch: u64, file_metadata: Vec<FileMetadata>) -> Result<()> {
-        let (f, manifest_file_path) = self.manifest_file(epoch)?;
-        let mut wbuf = BufWriter::new(f);
-        let manifest: Manifest = Manifest::V1(ManifestV1 {
-            snapshot_version: 1,
+    #[allow(clippy::too_many_arguments)]
+    fn write_delta_object_set<F>(
+        &mut self,
+        epoch: u64,
+        base_epoch: u64,
+        base_refs: LiveObjectRefIter,
+        perpetual_db: Arc<AuthorityPerpetualTables>,
+        sender: Sender<FileMetadata>,
+        bucket_func: F,
+        include_wrapped_tombstone: bool,
+        root_state_hash: ECMHLiveObjectSetDigest,
+    ) -> Result<()>
+    where
+        F: Fn(&LiveObject) -> u32,
+    {
+        let mut object_writers: HashMap<u32, LiveObjectSetWriterV1> = HashMap::new();
+        let local_staging_dir_path =
+            path_to_filesystem(self.local_staging_dir.clone(), &self.epoch_dir(epoch))?;
+        let mut deleted_writer = DeletedObjectsWriterV1::new(
+            local_staging_dir_path.clone(),
+            self.file_compression,
+            sender.clone(),
+        )?;
+        // Both the base object references and the live object set are sorted by object id
+        let mut base_refs = base_refs.peekable();
+        let mut acc = Accumulator::default();
+        let mut num_live_objects = 0u64;
+        let mut num_created = 0u64;
+        let mut num_mutated = 0u64;
+        let mut num_deleted = 0u64;
+        for object in perpetual_db.iter_live_object_set(include_wrapped_tombstone) {
+            StateAccumulator::accumulate_live_object(&mut acc, &object);
+            num_live_objects += 1;
+            let object_ref = object.object_reference();
+            let base_ref = loop {
+                match base_refs.peek() {
+                    Some(Ok(base_ref)) if base_ref.0 < object_ref.0 => {
+                        Self::write_deleted_object(
+                            &perpetual_db,
+                            &mut deleted_writer,
+                            base_ref,
+                            base_epoch,
+                        )?;
+                        num_deleted += 1;
+                        base_refs.next();
+                    }
+                    Some(Ok(base_ref)) if base_ref.0 == object_ref.0 => {
+                        break base_refs.next().transpose()?;
+                    }
+                    Some(Err(_)) => {
+                        base_refs.next().transpose()?;
+                    }
+                    _ => break None,
+                }
+            };
+            match base_ref {
+                Some(base_ref) if base_ref == object_ref => continue,
+                Some(_) => num_mutated += 1,
+                None => num_created += 1,
+            }
+            let bucket_num = bucket_func(&object);
+            if let Vacant(entry) = object_writers.entry(bucket_num) {
+                entry.insert(LiveObjectSetWriterV1::new(
+                    local_staging_dir_path.clone(),
+                    bucket_num,
+                    self.file_compression,
+                    sender.clone(),
+                )?);
+            }
+            let writer = object_writers
+                .get_mut(&bucket_num)
+                .context("Unexpected missing bucket writer")?;
+            writer.write(&object)?;
+        }
+        assert_eq!(
+            ECMHLiveObjectSetDigest::from(acc.digest()),
+            root_state_hash,
+            "Root state hash mismatch!"
+        );
+        // Whatever is left of the base live object set is no longer live
+        for base_ref in base_refs {
+            Self::write_deleted_object(&perpetual_db, &mut deleted_writer, &base_ref?, base_epoch)?;
+            num_deleted += 1;
+        }
+        let mut files = deleted_writer.done()?;
+        for (_, writer) in object_writers.into_iter() {
+            files.extend(writer.done()?);
+        }
+        debug!(
+            epoch,
+            base_epoch,
+            num_created,
+            num_mutated,
+            num_deleted,
+            "Finished writing delta state snapshot"
+        );
+        let manifest = Manifest::V2(ManifestV2 {
+            snapshot_version: 2,
             address_length: ObjectID::LENGTH as u64,
-            file_metadata,
+            file_metadata: files,
             epoch,
+            base_epoch,
+            num_created,
+            num_mutated,
+            num_deleted,
+            num_live_objects,
+            include_wrapped_tombstone,
+            root_state_hash,
         });
+        self.write_manifest(epoch, manifest)?;
+        Ok(())
+    }
+
+    /// Records an object of the base live object set that is no longer live with its tombstone
+    /// reference, so that restoring the delta deletes it at the version it was actually deleted
+    /// or wrapped at.
+    fn write_deleted_object(
+        perpetual_db: &AuthorityPerpetualTables,
+        deleted_writer: &mut DeletedObjectsWriterV1,
+        base_ref: &ObjectRef,
+        base_epoch: u64,
+    ) -> Result<()> {
+        let tombstone = perpetual_db
+            .get_latest_object_ref_or_tombstone(base_ref.0)?
+            .filter(|(_, version, digest)| !digest.is_alive() && *version > base_ref.1)
+            .context(format!(
+                "No tombstone found for object {} deleted since epoch {}, tombstones must be \
+                    retained to write delta state snapshots",
+                base_ref.0, base_epoch
+            ))?;
+        deleted_writer.write(&tombstone)
+    }
+
+    fn write_manifest(&mut self, epoch: u64, manifest: Manifest) -> Result<()> {
+        let (f, manifest_file_path) = self.manifest_file(epoch)?;
+        let mut wbuf = BufWriter::new(f);
         let serialized_manifest = bcs::to_bytes(&manifest)?;
         wbuf.write_all(&serialized_manifest)?;
         wbuf.flush()?;
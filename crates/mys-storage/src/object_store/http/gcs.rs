@@ -1,7 +1,7 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::object_store::http::{get, DEFAULT_USER_AGENT};
+use crate::object_store::http::{get, get_range, DEFAULT_USER_AGENT};
 use crate::object_store::ObjectStoreGetExt;
 use anyhow::Result;
 use async_trait::async_trait;
@@ -12,6 +12,7 @@ use percent_encoding::{percent_encode, utf8_percent_encode, NON_ALPHANUMERIC};
 use reqwest::Client;
 use reqwest::ClientBuilder;
 use std::fmt;
+use std::ops::Range;
 use std::sync::Arc;
 
 #[derive(Debug)]
@@ -38,6 +39,11 @@ impl GoogleCloudStorageClient {
         get(&url, "gcs", path, &self.client).await
     }
 
+    async fn get_range(&self, path: &Path, range: Range<usize>) -> Result<Bytes> {
+        let url = self.object_url(path);
+        get_range(&url, path, &self.client, range).await
+    }
+
     fn object_url(&self, path: &Path) -> String {
         let encoded = utf8_percent_encode(path.as_ref(), NON_ALPHANUMERIC);
         format!(
@@ -75,4 +81,7 @@ impl ObjectStoreGetExt for GoogleCloudStorage {
         let bytes = result.bytes().await?;
         Ok(bytes)
     }
+    async fn get_bytes_range(&self, location: &Path, range: Range<usize>) -> Result<Bytes> {
+        self.client.get_range(location, range).await
+    }
 }
@@ -8,7 +8,8 @@ use async_trait::async_trait;
 use bytes::Bytes;
 use object_store::path::Path;
 use std::fs::File;
-use std::io::Read;
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::Range;
 use std::path::PathBuf;
 use std::{fmt, fs};
 
@@ -47,4 +48,17 @@ impl ObjectStoreGetExt for LocalStorage {
         });
         handle.await?
     }
+    async fn get_bytes_range(&self, location: &Path, range: Range<usize>) -> Result<Bytes> {
+        let path_to_filesystem = path_to_filesystem(self.root.clone(), location)?;
+        let handle = tokio::task::spawn_blocking(move || {
+            let mut f = File::open(path_to_filesystem)
+                .map_err(|e| anyhow!("Failed to open file with error: {}", e.to_string()))?;
+            f.seek(SeekFrom::Start(range.start as u64))?;
+            let mut buf = vec![0u8; range.len()];
+            f.read_exact(&mut buf)
+                .context(anyhow!("Failed to read range {:?} of file", range))?;
+            Ok(buf.into())
+        });
+        handle.await?
+    }
 }
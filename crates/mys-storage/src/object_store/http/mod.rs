@@ -5,6 +5,7 @@ mod gcs;
 mod local;
 mod s3;
 
+use std::ops::Range;
 use std::sync::Arc;
 
 use crate::object_store::http::gcs::GoogleCloudStorage;
@@ -14,12 +15,13 @@ use mys_config::object_storage_config::{ObjectStoreConfig, ObjectStoreType};
 
 use crate::object_store::ObjectStoreGetExt;
 use anyhow::{anyhow, Context, Result};
+use bytes::Bytes;
 use chrono::{DateTime, Utc};
 use futures::{StreamExt, TryStreamExt};
 use object_store::path::Path;
 use object_store::{Error, GetResult, GetResultPayload, ObjectMeta};
-use reqwest::header::{HeaderMap, CONTENT_LENGTH, ETAG, LAST_MODIFIED};
-use reqwest::{Client, Method};
+use reqwest::header::{HeaderMap, CONTENT_LENGTH, ETAG, LAST_MODIFIED, RANGE};
+use reqwest::{Client, Method, StatusCode};
 
 // http://docs.aws.amazon.com/general/latest/gr/sigv4-create-canonical-request.html
 //
@@ -94,6 +96,40 @@ async fn get(
     })
 }
 
+async fn get_range(
+    url: &str,
+    location: &Path,
+    client: &Client,
+    range: Range<usize>,
+) -> Result<Bytes> {
+    if range.is_empty() {
+        return Ok(Bytes::new());
+    }
+    let request = client
+        .request(Method::GET, url)
+        .header(RANGE, format!("bytes={}-{}", range.start, range.end - 1));
+    let response = request
+        .send()
+        .await
+        .context("failed to get range")?
+        .error_for_status()
+        .context(format!("failed to get range {:?} of {}", range, location))?;
+    let status = response.status();
+    let bytes = response.bytes().await.context("failed to read range")?;
+    match status {
+        StatusCode::PARTIAL_CONTENT if bytes.len() == range.len() => Ok(bytes),
+        // Server ignored the range header and returned the whole object
+        StatusCode::OK if bytes.len() >= range.end => Ok(bytes.slice(range)),
+        _ => Err(anyhow!(
+            "Unexpected response of {} bytes with status {} for range {:?} of {}",
+            bytes.len(),
+            status,
+            range,
+            location
+        )),
+    }
+}
+
 fn header_meta(location: &Path, headers: &HeaderMap) -> Result<ObjectMeta> {
     let last_modified = headers
         .get(LAST_MODIFIED)
@@ -155,4 +191,28 @@ mod tests {
         assert_eq!(downloaded.to_vec(), b"Lorem ipsum");
         Ok(())
     }
+
+    #[tokio::test]
+    pub async fn test_local_download_range() -> anyhow::Result<()> {
+        let input = TempDir::new()?;
+        let input_path = input.path();
+        fs::write(input_path.join("file1"), b"Lorem ipsum")?;
+
+        let input_store = ObjectStoreConfig {
+            object_store: Some(ObjectStoreType::File),
+            directory: Some(input_path.to_path_buf()),
+            ..Default::default()
+        }
+        .make_http()?;
+
+        let downloaded = input_store
+            .get_bytes_range(&Path::from("file1"), 6..11)
+            .await?;
+        assert_eq!(downloaded.to_vec(), b"ipsum");
+        assert!(input_store
+            .get_bytes_range(&Path::from("file1"), 6..12)
+            .await
+            .is_err());
+        Ok(())
+    }
 }
@@ -1,7 +1,7 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::object_store::http::{get, DEFAULT_USER_AGENT, STRICT_PATH_ENCODE_SET};
+use crate::object_store::http::{get, get_range, DEFAULT_USER_AGENT, STRICT_PATH_ENCODE_SET};
 use crate::object_store::ObjectStoreGetExt;
 use anyhow::Result;
 use async_trait::async_trait;
@@ -12,6 +12,7 @@ use percent_encoding::{utf8_percent_encode, PercentEncode};
 use reqwest::Client;
 use reqwest::ClientBuilder;
 use std::fmt;
+use std::ops::Range;
 use std::sync::Arc;
 
 #[derive(Debug)]
@@ -37,6 +38,10 @@ impl S3Client {
         let url = self.path_url(location);
         get(&url, "s3", location, &self.client).await
     }
+    async fn get_range(&self, location: &Path, range: Range<usize>) -> Result<Bytes> {
+        let url = self.path_url(location);
+        get_range(&url, location, &self.client, range).await
+    }
     fn path_url(&self, path: &Path) -> String {
         format!("{}/{}", self.endpoint, Self::encode_path(path))
     }
@@ -73,4 +78,7 @@ impl ObjectStoreGetExt for AmazonS3 {
         let bytes = result.bytes().await?;
         Ok(bytes)
     }
+    async fn get_bytes_range(&self, location: &Path, range: Range<usize>) -> Result<Bytes> {
+        self.client.get_range(location, range).await
+    }
 }
@@ -7,6 +7,7 @@ use bytes::Bytes;
 use futures::stream::BoxStream;
 use object_store::path::Path;
 use object_store::{DynObjectStore, ObjectMeta};
+use std::ops::Range;
 use std::sync::Arc;
 
 pub mod http;
@@ -16,6 +17,21 @@ pub mod util;
 pub trait ObjectStoreGetExt: std::fmt::Display + Send + Sync + 'static {
     /// Return the bytes at given path in object store
     async fn get_bytes(&self, src: &Path) -> Result<Bytes>;
+
+    /// Return the bytes in the given byte range of the object at given path in object store.
+    /// Stores which can't serve partial reads fall back to downloading the whole object
+    async fn get_bytes_range(&self, src: &Path, range: Range<usize>) -> Result<Bytes> {
+        let bytes = self.get_bytes(src).await?;
+        if range.start > range.end || range.end > bytes.len() {
+            return Err(anyhow!(
+                "Range {:?} is out of bounds for file {} of size {}",
+                range,
+                src,
+                bytes.len()
+            ));
+        }
+        Ok(bytes.slice(range))
+    }
 }
 
 macro_rules! as_ref_get_ext_impl {
@@ -25,6 +41,9 @@ macro_rules! as_ref_get_ext_impl {
             async fn get_bytes(&self, src: &Path) -> Result<Bytes> {
                 self.as_ref().get_bytes(src).await
             }
+            async fn get_bytes_range(&self, src: &Path, range: Range<usize>) -> Result<Bytes> {
+                self.as_ref().get_bytes_range(src, range).await
+            }
         }
     };
 }
@@ -48,6 +67,16 @@ impl ObjectStoreGetExt for Arc<DynObjectStore> {
                 )
             })
     }
+    async fn get_bytes_range(&self, src: &Path, range: Range<usize>) -> Result<Bytes> {
+        self.get_range(src, range.clone()).await.map_err(|e| {
+            anyhow!(
+                "Failed to get range {:?} of file {} with error: {:?}",
+                range,
+                src,
+                e
+            )
+        })
+    }
 }
 
 #[async_trait]
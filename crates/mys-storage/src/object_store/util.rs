@@ -88,6 +88,27 @@ pub async fn get<S: ObjectStoreGetExt>(store: &S, src: &Path) -> Result<Bytes> {
     Ok(bytes)
 }
 
+pub async fn get_range<S: ObjectStoreGetExt>(
+    store: &S,
+    src: &Path,
+    range: Range<usize>,
+) -> Result<Bytes> {
+    let bytes = retry(backoff::ExponentialBackoff::default(), || async {
+        store
+            .get_bytes_range(src, range.clone())
+            .await
+            .map_err(|e| {
+                error!(
+                    "Failed to read file range from object store with error: {:?}",
+                    &e
+                );
+                backoff::Error::transient(e)
+            })
+    })
+    .await?;
+    Ok(bytes)
+}
+
 pub async fn exists<S: ObjectStoreGetExt>(store: &S, src: &Path) -> bool {
     store.get_bytes(src).await.is_ok()
 }
@@ -26,7 +26,7 @@ use mys_types::{
 
 use clap::*;
 use fastcrypto::encoding::Encoding;
-use mys_archival::{read_manifest_as_json, write_manifest_from_json};
+use mys_archival::{convert_archive_to_indexed, read_manifest_as_json, write_manifest_from_json};
 use mys_config::object_storage_config::{ObjectStoreConfig, ObjectStoreType};
 use mys_config::Config;
 use mys_core::authority_aggregator::AuthorityAggregatorBuilder;
@@ -164,6 +164,16 @@ pub enum ToolCommand {
         download_concurrency: usize,
     },
 
+    /// Tool to rewrite an archive in the indexed format which supports reading single
+    /// checkpoints. Archive writers must be stopped while converting.
+    #[command(name = "convert-archive-to-indexed")]
+    ConvertArchiveToIndexed {
+        #[command(flatten)]
+        object_store_config: ObjectStoreConfig,
+        #[arg(default_value_t = 5)]
+        concurrency: usize,
+    },
+
     /// Tool to print archive contents in checkpoint range
     #[command(name = "dump-archive")]
     DumpArchiveByChecksum {
@@ -1067,6 +1077,12 @@ impl ToolCommand {
             } => {
                 verify_archive_by_checksum(object_store_config, download_concurrency).await?;
             }
+            ToolCommand::ConvertArchiveToIndexed {
+                object_store_config,
+                concurrency,
+            } => {
+                convert_archive_to_indexed(object_store_config, concurrency).await?;
+            }
             ToolCommand::DumpArchiveByChecksum {
                 object_store_config,
                 start,
@@ -0,0 +1,98 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Offline inspection of the consensus DAG stored by a validator: exports rounds of the DAG for
+//! visualization, explains how leaders were committed or skipped, and reports block propagation
+//! per authority.
+
+use anyhow::{anyhow, bail};
+use clap::ValueEnum;
+use consensus_core::{BlockAPI as _, DagExportFormat, DagInspector};
+use std::path::Path;
+use mys_core::authority::authority_store_tables::AuthorityPerpetualTables;
+use mys_core::authority::epoch_start_configuration::EpochStartConfigTrait;
+use mys_protocol_config::ProtocolConfig;
+use mys_types::mys_system_state::epoch_start_mys_system_state::EpochStartSystemStateTrait;
+use typed_store::traits::Map;
+
+use super::{ConsensusDagCommand, ConsensusDagOptions};
+
+/// Number of rounds inspected when no start round is given.
+const DEFAULT_ROUNDS: u32 = 50;
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum DagFormat {
+    /// Graphviz DOT graph, to render with e.g. `dot -Tsvg`
+    Dot,
+    Json,
+}
+
+pub fn inspect_consensus_dag(db_path: &Path, opt: ConsensusDagOptions) -> anyhow::Result<()> {
+    // The committee and protocol config of the current epoch are read from the authority store.
+    let perpetual_db = AuthorityPerpetualTables::open_readonly(&db_path.join("store"));
+    let epoch_start_configuration = perpetual_db
+        .epoch_start_configuration
+        .get(&())?
+        .ok_or(anyhow!("Epoch start configuration not found"))?;
+    let epoch_start_state = epoch_start_configuration.epoch_start_state();
+    let committee = epoch_start_state.get_consensus_committee();
+    let protocol_config =
+        ProtocolConfig::get_for_version(epoch_start_state.protocol_version(), opt.network);
+    drop(perpetual_db);
+
+    let inspector = DagInspector::open(&opt.consensus_db_path, committee, protocol_config);
+    let last_committed_leader = inspector.last_committed_leader();
+    if let Some(leader) = &last_committed_leader {
+        if leader.epoch() != epoch_start_state.epoch() {
+            bail!(
+                "Consensus store {:?} is of epoch {}, but the authority store is at epoch {}",
+                opt.consensus_db_path,
+                leader.epoch(),
+                epoch_start_state.epoch()
+            );
+        }
+    }
+
+    let last_committed_round = last_committed_leader.map_or(0, |leader| leader.round());
+    let end_round = opt.end_round.unwrap_or(last_committed_round);
+    let start_round = opt
+        .start_round
+        .unwrap_or(end_round.saturating_sub(DEFAULT_ROUNDS - 1));
+    if start_round > end_round {
+        bail!("Start round {start_round} is after end round {end_round}");
+    }
+    let rounds = start_round..=end_round;
+
+    match opt.command {
+        ConsensusDagCommand::Export { format, output } => {
+            let format = match format {
+                DagFormat::Dot => DagExportFormat::Dot,
+                DagFormat::Json => DagExportFormat::Json,
+            };
+            let exported = inspector.export(rounds, format);
+            match output {
+                Some(output) => std::fs::write(output, exported)?,
+                None => println!("{exported}"),
+            }
+        }
+        ConsensusDagCommand::Explain => {
+            for report in inspector.explain_leaders(rounds) {
+                println!("{report}");
+            }
+        }
+        ConsensusDagCommand::Authorities => {
+            for report in inspector.authority_reports(rounds) {
+                println!("{report}");
+            }
+            if let Some(scores) = inspector.reputation_scores().last() {
+                println!(
+                    "Last reputation scores, over commits {} to {}: {:?}",
+                    scores.commit_range.start(),
+                    scores.commit_range.end(),
+                    scores.scores
+                );
+            }
+        }
+    }
+    Ok(())
+}
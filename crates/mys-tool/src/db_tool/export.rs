@@ -0,0 +1,504 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Exports typed-store tables, or key ranges of them, into Parquet files with decoded columns,
+//! so that they can be analyzed with tools like DuckDB or Polars.
+
+use anyhow::anyhow;
+use clap::ValueEnum;
+use fastcrypto::encoding::{Base58, Base64, Encoding};
+use serde::Serialize;
+use std::fmt::Display;
+use std::ops::Bound;
+use std::path::Path;
+use std::str::FromStr;
+use strum_macros::EnumString;
+use mys_analytics_indexer::{ParquetFileWriter, ParquetSchema, ParquetValue};
+use mys_analytics_indexer_derive::SerializeParquet;
+use mys_core::authority::authority_store_tables::AuthorityPerpetualTables;
+use mys_core::jsonrpc_index::IndexStoreTables;
+use mys_types::base_types::{MysAddress, ObjectID, TxSequenceNumber};
+use mys_types::digests::{TransactionDigest, TransactionEffectsDigest, TransactionEventsDigest};
+use mys_types::effects::TransactionEffectsAPI;
+use mys_types::execution_status::ExecutionStatus;
+use mys_types::storage::ObjectKey;
+use mys_types::transaction::{TransactionDataAPI, TransactionExpiration};
+use typed_store::rocks::MetricConf;
+use typed_store::traits::Map;
+
+use super::ExportOptions;
+
+#[derive(EnumString, Clone, Copy, Debug, ValueEnum)]
+pub enum ExportTable {
+    /// All stored object versions, keyed by object id
+    Objects,
+    /// Transactions, keyed by transaction digest
+    Transactions,
+    /// Transaction effects, keyed by effects digest
+    Effects,
+    /// Events, keyed by events digest
+    Events,
+    /// Index of transactions by sender, keyed by address
+    TransactionsFromAddr,
+    /// Index of transactions by recipient, keyed by address
+    TransactionsToAddr,
+    /// Index of objects by owner, keyed by address
+    OwnerIndex,
+}
+
+#[derive(Serialize, Clone, SerializeParquet)]
+struct ObjectRow {
+    object_id: String,
+    version: u64,
+    // tombstones of deleted or wrapped objects have no contents
+    is_tombstone: bool,
+    digest: Option<String>,
+    type_: Option<String>,
+    owner: Option<String>,
+    previous_transaction: Option<String>,
+    storage_rebate: Option<u64>,
+    object_size: Option<u64>,
+    coin_balance: Option<u64>,
+    bcs: Option<String>,
+}
+
+#[derive(Serialize, Clone, SerializeParquet)]
+struct TransactionRow {
+    transaction_digest: String,
+    sender: String,
+    transaction_kind: String,
+    is_system_txn: bool,
+    command_count: u64,
+    gas_owner: String,
+    gas_budget: u64,
+    gas_price: u64,
+    expiration_epoch: Option<u64>,
+    signature_count: u64,
+    bcs: String,
+}
+
+#[derive(Serialize, Clone, SerializeParquet)]
+struct EffectsRow {
+    effects_digest: String,
+    transaction_digest: String,
+    executed_epoch: u64,
+    execution_success: bool,
+    failure: Option<String>,
+    computation_cost: u64,
+    storage_cost: u64,
+    storage_rebate: u64,
+    non_refundable_storage_fee: u64,
+    created: u64,
+    mutated: u64,
+    unwrapped: u64,
+    deleted: u64,
+    wrapped: u64,
+    shared_objects: u64,
+    dependencies: u64,
+    gas_object_id: String,
+    events_digest: Option<String>,
+}
+
+#[derive(Serialize, Clone, SerializeParquet)]
+struct EventRow {
+    events_digest: String,
+    event_index: u64,
+    package_id: String,
+    module: String,
+    sender: String,
+    event_type: String,
+    bcs: String,
+}
+
+#[derive(Serialize, Clone, SerializeParquet)]
+struct AddressTransactionRow {
+    address: String,
+    tx_sequence_number: u64,
+    transaction_digest: String,
+}
+
+#[derive(Serialize, Clone, SerializeParquet)]
+struct OwnerIndexRow {
+    owner: String,
+    object_id: String,
+    version: u64,
+    digest: String,
+    type_: String,
+    owner_kind: String,
+    previous_transaction: String,
+}
+
+/// Range of keys whose leading component lies between `start` and `end`, both inclusive.
+/// `min` and `max` map a leading component to the smallest and largest full key it prefixes.
+fn key_range<T, K>(
+    start: Option<&str>,
+    end: Option<&str>,
+    min: impl Fn(T) -> K,
+    max: impl Fn(T) -> K,
+) -> anyhow::Result<(Bound<K>, Bound<K>)>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let parse = |s: &str| T::from_str(s).map_err(|e| anyhow!("Invalid key {s}: {e}"));
+    let lower = match start {
+        Some(start) => Bound::Included(min(parse(start)?)),
+        None => Bound::Unbounded,
+    };
+    let upper = match end {
+        Some(end) => Bound::Included(max(parse(end)?)),
+        None => Bound::Unbounded,
+    };
+    Ok((lower, upper))
+}
+
+/// Writes rows in batches, so that memory use is bounded by the batch size regardless of the
+/// size of the table
+fn write_rows<S: ParquetSchema>(
+    output: &Path,
+    rows: impl Iterator<Item = anyhow::Result<S>>,
+    options: &ExportOptions,
+) -> anyhow::Result<usize> {
+    let mut writer = ParquetFileWriter::new(output);
+    let mut batch = Vec::with_capacity(options.batch_size);
+    for row in rows.take(options.limit.unwrap_or(usize::MAX)) {
+        batch.push(row?);
+        if batch.len() >= options.batch_size {
+            writer.write(&batch)?;
+            batch.clear();
+        }
+    }
+    writer.write(&batch)?;
+    writer.close()
+}
+
+pub fn export_table(db_path: &Path, options: ExportOptions) -> anyhow::Result<()> {
+    let start = options.start.as_deref();
+    let end = options.end.as_deref();
+    let output = options.output.as_path();
+    let num_rows = match options.table {
+        ExportTable::Objects => {
+            let perpetual_db = AuthorityPerpetualTables::open_readonly(&db_path.join("store"));
+            let range = key_range(
+                start,
+                end,
+                |id: ObjectID| ObjectKey::min_for_id(&id),
+                |id: ObjectID| ObjectKey::max_for_id(&id),
+            )?;
+            let rows = perpetual_db.objects.safe_range_iter(range).map(
+                |entry| -> anyhow::Result<ObjectRow> {
+                    let (key, store_object) = entry?;
+                    let Some(object) = perpetual_db.object(&key, store_object)? else {
+                        return Ok(ObjectRow {
+                            object_id: key.0.to_string(),
+                            version: key.1.value(),
+                            is_tombstone: true,
+                            digest: None,
+                            type_: None,
+                            owner: None,
+                            previous_transaction: None,
+                            storage_rebate: None,
+                            object_size: None,
+                            coin_balance: None,
+                            bcs: None,
+                        });
+                    };
+                    Ok(ObjectRow {
+                        object_id: key.0.to_string(),
+                        version: key.1.value(),
+                        is_tombstone: false,
+                        digest: Some(object.digest().base58_encode()),
+                        type_: object.type_().map(|t| t.to_string()),
+                        owner: Some(object.owner.to_string()),
+                        previous_transaction: Some(object.previous_transaction.base58_encode()),
+                        storage_rebate: Some(object.storage_rebate),
+                        object_size: Some(object.object_size_for_gas_metering() as u64),
+                        coin_balance: object
+                            .coin_type_maybe()
+                            .map(|_| object.get_coin_value_unsafe()),
+                        bcs: Some(Base64::encode(bcs::to_bytes(&object)?)),
+                    })
+                },
+            );
+            write_rows(output, rows, &options)?
+        }
+        ExportTable::Transactions => {
+            let perpetual_db = AuthorityPerpetualTables::open_readonly(&db_path.join("store"));
+            let range = key_range(start, end, |d: TransactionDigest| d, |d| d)?;
+            let rows = perpetual_db.transactions.safe_range_iter(range).map(
+                |entry| -> anyhow::Result<TransactionRow> {
+                    let (digest, transaction) = entry?;
+                    let transaction = transaction.into_inner();
+                    let data = transaction.data().transaction_data();
+                    Ok(TransactionRow {
+                        transaction_digest: digest.base58_encode(),
+                        sender: data.sender().to_string(),
+                        transaction_kind: data.kind().name().to_string(),
+                        is_system_txn: data.kind().is_system_tx(),
+                        command_count: data.kind().num_commands() as u64,
+                        gas_owner: data.gas_owner().to_string(),
+                        gas_budget: data.gas_budget(),
+                        gas_price: data.gas_price(),
+                        expiration_epoch: match data.expiration() {
+                            TransactionExpiration::None => None,
+                            TransactionExpiration::Epoch(epoch) => Some(*epoch),
+                        },
+                        signature_count: transaction.data().tx_signatures().len() as u64,
+                        bcs: Base64::encode(bcs::to_bytes(transaction.data())?),
+                    })
+                },
+            );
+            write_rows(output, rows, &options)?
+        }
+        ExportTable::Effects => {
+            let perpetual_db = AuthorityPerpetualTables::open_readonly(&db_path.join("store"));
+            // Effects digests are written like transaction digests
+            let range = key_range(
+                start,
+                end,
+                |d: TransactionDigest| TransactionEffectsDigest::new(d.into_inner()),
+                |d| TransactionEffectsDigest::new(d.into_inner()),
+            )?;
+            let rows = perpetual_db.effects.safe_range_iter(range).map(
+                |entry| -> anyhow::Result<EffectsRow> {
+                    let (digest, effects) = entry?;
+                    let gas = effects.gas_cost_summary();
+                    let failure = match effects.status() {
+                        ExecutionStatus::Success => None,
+                        ExecutionStatus::Failure { error, command } => Some(match command {
+                            Some(command) => format!("{error:?} in command {command}"),
+                            None => format!("{error:?}"),
+                        }),
+                    };
+                    Ok(EffectsRow {
+                        effects_digest: digest.base58_encode(),
+                        transaction_digest: effects.transaction_digest().base58_encode(),
+                        executed_epoch: effects.executed_epoch(),
+                        execution_success: failure.is_none(),
+                        failure,
+                        computation_cost: gas.computation_cost,
+                        storage_cost: gas.storage_cost,
+                        storage_rebate: gas.storage_rebate,
+                        non_refundable_storage_fee: gas.non_refundable_storage_fee,
+                        created: effects.created().len() as u64,
+                        mutated: effects.mutated().len() as u64,
+                        unwrapped: effects.unwrapped().len() as u64,
+                        deleted: effects.deleted().len() as u64,
+                        wrapped: effects.wrapped().len() as u64,
+                        shared_objects: effects.input_shared_objects().len() as u64,
+                        dependencies: effects.dependencies().len() as u64,
+                        gas_object_id: effects.gas_object().0 .0.to_string(),
+                        events_digest: effects
+                            .events_digest()
+                            .map(|d| Base58::encode(d.into_inner())),
+                    })
+                },
+            );
+            write_rows(output, rows, &options)?
+        }
+        ExportTable::Events => {
+            let perpetual_db = AuthorityPerpetualTables::open_readonly(&db_path.join("store"));
+            let range = key_range(
+                start,
+                end,
+                |d: TransactionEventsDigest| (d, 0),
+                |d| (d, usize::MAX),
+            )?;
+            let rows = perpetual_db.events.safe_range_iter(range).map(
+                |entry| -> anyhow::Result<EventRow> {
+                    let ((digest, index), event) = entry?;
+                    Ok(EventRow {
+                        events_digest: Base58::encode(digest.into_inner()),
+                        event_index: index as u64,
+                        package_id: event.package_id.to_string(),
+                        module: event.transaction_module.to_string(),
+                        sender: event.sender.to_string(),
+                        event_type: event.type_.to_canonical_string(true),
+                        bcs: Base64::encode(&event.contents),
+                    })
+                },
+            );
+            write_rows(output, rows, &options)?
+        }
+        ExportTable::TransactionsFromAddr | ExportTable::TransactionsToAddr => {
+            let index_db = IndexStoreTables::get_read_only_handle(
+                db_path.join("indexes"),
+                None,
+                None,
+                MetricConf::default(),
+            );
+            let table = if matches!(options.table, ExportTable::TransactionsFromAddr) {
+                &index_db.transactions_from_addr
+            } else {
+                &index_db.transactions_to_addr
+            };
+            let range = key_range(
+                start,
+                end,
+                |a: MysAddress| (a, TxSequenceNumber::MIN),
+                |a| (a, TxSequenceNumber::MAX),
+            )?;
+            let rows = table.safe_range_iter(range).map(
+                |entry| -> anyhow::Result<AddressTransactionRow> {
+                    let ((address, seq), digest) = entry?;
+                    Ok(AddressTransactionRow {
+                        address: address.to_string(),
+                        tx_sequence_number: seq,
+                        transaction_digest: digest.base58_encode(),
+                    })
+                },
+            );
+            write_rows(output, rows, &options)?
+        }
+        ExportTable::OwnerIndex => {
+            let index_db = IndexStoreTables::get_read_only_handle(
+                db_path.join("indexes"),
+                None,
+                None,
+                MetricConf::default(),
+            );
+            let range = key_range(
+                start,
+                end,
+                |a: MysAddress| (a, ObjectID::ZERO),
+                |a| (a, ObjectID::MAX),
+            )?;
+            let rows = index_db.owner_index.safe_range_iter(range).map(
+                |entry| -> anyhow::Result<OwnerIndexRow> {
+                    let ((owner, object_id), info) = entry?;
+                    Ok(OwnerIndexRow {
+                        owner: owner.to_string(),
+                        object_id: object_id.to_string(),
+                        version: info.version.value(),
+                        digest: info.digest.base58_encode(),
+                        type_: info.type_.to_string(),
+                        owner_kind: info.owner.to_string(),
+                        previous_transaction: info.previous_transaction.base58_encode(),
+                    })
+                },
+            );
+            write_rows(output, rows, &options)?
+        }
+    };
+    if num_rows == 0 {
+        // The writer only creates the file once it is handed rows
+        println!(
+            "No rows of {:?} matched, {} was not written",
+            options.table,
+            options.output.display()
+        );
+    } else {
+        println!(
+            "Exported {num_rows} rows of {:?} to {}",
+            options.table,
+            options.output.display()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::path::Path;
+
+    use clap::Parser;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use mys_core::authority::authority_store_tables::AuthorityPerpetualTables;
+    use mys_types::base_types::{MysAddress, ObjectID};
+    use mys_types::object::Object;
+
+    use super::export_table;
+    use crate::db_tool::ExportOptions;
+
+    /// Returns the number of rows of each row group of a Parquet file
+    fn row_groups(path: &Path) -> Vec<i64> {
+        let reader = SerializedFileReader::new(File::open(path).unwrap()).unwrap();
+        reader
+            .metadata()
+            .row_groups()
+            .iter()
+            .map(|group| group.num_rows())
+            .collect()
+    }
+
+    fn export_options(output: &Path, args: &[&str]) -> ExportOptions {
+        let output = output.to_str().unwrap();
+        ExportOptions::try_parse_from(
+            ["export", "--table", "objects", "--output", output]
+                .iter()
+                .chain(args),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_export_objects() -> Result<(), anyhow::Error> {
+        let db_path = tempfile::tempdir()?;
+        let perpetual_db = AuthorityPerpetualTables::open(&db_path.path().join("store"), None);
+        let mut ids: Vec<_> = (0..5).map(|_| ObjectID::random()).collect();
+        ids.sort();
+        for id in &ids {
+            perpetual_db.insert_object_test_only(Object::with_id_owner_for_testing(
+                *id,
+                MysAddress::ZERO,
+            ))?;
+        }
+        drop(perpetual_db);
+        let output_dir = tempfile::tempdir()?;
+
+        // Rows are written in row groups of the batch size
+        let output = output_dir.path().join("all.parquet");
+        export_table(
+            db_path.path(),
+            export_options(&output, &["--batch-size", "2"]),
+        )?;
+        assert_eq!(row_groups(&output), vec![2, 2, 1]);
+
+        let output = output_dir.path().join("limit.parquet");
+        export_table(
+            db_path.path(),
+            export_options(&output, &["--batch-size", "2", "--limit", "3"]),
+        )?;
+        assert_eq!(row_groups(&output), vec![2, 1]);
+
+        // Both ends of the key range are inclusive
+        let output = output_dir.path().join("range.parquet");
+        let (start, end) = (ids[1].to_string(), ids[3].to_string());
+        export_table(
+            db_path.path(),
+            export_options(&output, &["--start", &start, "--end", &end]),
+        )?;
+        assert_eq!(row_groups(&output), vec![3]);
+
+        // No file is written when no rows are exported
+        let output = output_dir.path().join("empty.parquet");
+        let id = ObjectID::ZERO.to_string();
+        export_table(
+            db_path.path(),
+            export_options(&output, &["--start", &id, "--end", &id]),
+        )?;
+        assert!(!output.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_invalid_options() {
+        let output = Path::new("objects.parquet");
+        assert!(ExportOptions::try_parse_from([
+            "export",
+            "--table",
+            "objects",
+            "--output",
+            "objects.parquet",
+            "--batch-size",
+            "0"
+        ])
+        .is_err());
+
+        let db_path = tempfile::tempdir().unwrap();
+        let _ = AuthorityPerpetualTables::open(&db_path.path().join("store"), None);
+        let options = export_options(output, &["--start", "not an object id"]);
+        assert!(export_table(db_path.path(), options).is_err());
+    }
+}
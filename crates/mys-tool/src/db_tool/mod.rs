@@ -1,22 +1,29 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+use self::consensus_dag::{inspect_consensus_dag, DagFormat};
 use self::db_dump::{dump_table, duplicate_objects_summary, list_tables, table_summary, StoreName};
 use self::index_search::{search_index, SearchRange};
+use self::verify::verify_db;
 use crate::db_tool::db_dump::{compact, print_table_metadata, prune_checkpoints, prune_objects};
+use crate::db_tool::export::{export_table, ExportTable};
 use anyhow::{anyhow, bail};
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use std::path::{Path, PathBuf};
 use mys_core::authority::authority_per_epoch_store::AuthorityEpochTables;
 use mys_core::authority::authority_store_tables::AuthorityPerpetualTables;
 use mys_core::checkpoints::CheckpointStore;
+use mys_protocol_config::Chain;
 use mys_types::base_types::{EpochId, ObjectID};
 use mys_types::digests::{CheckpointContentsDigest, TransactionDigest};
 use mys_types::effects::TransactionEffectsAPI;
 use mys_types::messages_checkpoint::{CheckpointDigest, CheckpointSequenceNumber};
 use typed_store::rocks::MetricConf;
+mod consensus_dag;
 pub mod db_dump;
+mod export;
 mod index_search;
+mod verify;
 
 #[derive(Parser)]
 #[command(rename_all = "kebab-case")]
@@ -40,6 +47,9 @@ pub enum DbToolCommand {
     PruneObjects,
     PruneCheckpoints,
     SetCheckpointWatermark(SetCheckpointWatermarkOptions),
+    Verify(VerifyOptions),
+    Export(ExportOptions),
+    ConsensusDag(ConsensusDagOptions),
 }
 
 #[derive(Parser)]
@@ -176,6 +186,102 @@ pub struct SetCheckpointWatermarkOptions {
     highest_synced: Option<CheckpointSequenceNumber>,
 }
 
+#[derive(Parser)]
+#[command(rename_all = "kebab-case")]
+pub struct VerifyOptions {
+    /// The checkpoint to start verifying from. Defaults to genesis, or to the first checkpoint
+    /// after the pruning watermark if the database has been pruned.
+    #[arg(long)]
+    start: Option<CheckpointSequenceNumber>,
+
+    /// The last checkpoint to verify. Defaults to the highest executed checkpoint.
+    #[arg(long)]
+    end: Option<CheckpointSequenceNumber>,
+
+    /// Skip checking that objects written by each transaction exist at the written version.
+    /// Required for databases with object pruning enabled.
+    #[arg(long)]
+    skip_objects: bool,
+
+    /// Stop at the first inconsistency instead of reporting all of them
+    #[arg(long)]
+    fail_fast: bool,
+}
+
+#[derive(Parser)]
+#[command(rename_all = "kebab-case")]
+pub struct ExportOptions {
+    /// The table to export
+    #[arg(long = "table", short = 't', value_enum)]
+    table: ExportTable,
+
+    /// The Parquet file to write
+    #[arg(long = "output", short = 'o')]
+    output: PathBuf,
+
+    /// First key to export, inclusive. Keys are given by their leading component, e.g. an object
+    /// id for objects or an address for the address indexes.
+    #[arg(long)]
+    start: Option<String>,
+
+    /// Last key to export, inclusive
+    #[arg(long)]
+    end: Option<String>,
+
+    /// Maximum number of rows to export
+    #[arg(long)]
+    limit: Option<usize>,
+
+    /// Number of rows per Parquet row group
+    #[arg(
+        long,
+        default_value_t = 10_000,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    batch_size: usize,
+}
+
+#[derive(Parser)]
+#[command(rename_all = "kebab-case")]
+pub struct ConsensusDagOptions {
+    /// The consensus store of the current epoch, i.e. the consensus db path of the validator
+    /// followed by the epoch number
+    #[arg(long)]
+    consensus_db_path: PathBuf,
+
+    /// The network the validator belongs to, to load its protocol config
+    #[arg(long, default_value = "mainnet")]
+    network: Chain,
+
+    /// First round to inspect. Defaults to 50 rounds before the end round.
+    #[arg(long)]
+    start_round: Option<u32>,
+
+    /// Last round to inspect. Defaults to the round of the last committed leader.
+    #[arg(long)]
+    end_round: Option<u32>,
+
+    #[command(subcommand)]
+    command: ConsensusDagCommand,
+}
+
+#[derive(Subcommand)]
+#[command(rename_all = "kebab-case")]
+pub enum ConsensusDagCommand {
+    /// Export the blocks of the rounds and the commits of their leaders
+    Export {
+        #[arg(long, value_enum, default_value = "dot")]
+        format: DagFormat,
+        /// The file to write. Defaults to stdout.
+        #[arg(long, short = 'o')]
+        output: Option<PathBuf>,
+    },
+    /// Explain why each leader of the rounds was committed or skipped
+    Explain,
+    /// Report block propagation, leader outcomes and reputation scores per authority
+    Authorities,
+}
+
 pub async fn execute_db_tool_command(db_path: PathBuf, cmd: DbToolCommand) -> anyhow::Result<()> {
     match cmd {
         DbToolCommand::ListTables => print_db_all_tables(db_path),
@@ -232,6 +338,9 @@ pub async fn execute_db_tool_command(db_path: PathBuf, cmd: DbToolCommand) -> an
             Ok(())
         }
         DbToolCommand::SetCheckpointWatermark(d) => set_checkpoint_watermark(&db_path, d),
+        DbToolCommand::Verify(d) => verify_db(&db_path, d),
+        DbToolCommand::Export(d) => export_table(&db_path, d),
+        DbToolCommand::ConsensusDag(d) => inspect_consensus_dag(&db_path, d),
     }
 }
 
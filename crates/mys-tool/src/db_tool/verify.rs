@@ -0,0 +1,537 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Offline integrity check of a node database. Walks the executed checkpoints, checks that they
+//! form a chain and that everything they reference is present in the perpetual tables, and
+//! recomputes the root state accumulator of every epoch that is fully covered by the walk. The
+//! stored root of the epoch before the walk, which the recomputation starts from, is checked
+//! against the commitment in the last checkpoint of that epoch.
+
+use anyhow::{anyhow, bail};
+use fastcrypto::hash::MultisetHash;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+use mys_core::authority::authority_store_tables::AuthorityPerpetualTables;
+use mys_core::checkpoints::CheckpointStore;
+use mys_core::state_accumulator::accumulate_effects;
+use mys_protocol_config::{Chain, ProtocolConfig, ProtocolVersion};
+use mys_types::accumulator::Accumulator;
+use mys_types::base_types::EpochId;
+use mys_types::digests::ChainIdentifier;
+use mys_types::effects::{TransactionEffects, TransactionEffectsAPI};
+use mys_types::message_envelope::Message;
+use mys_types::messages_checkpoint::{
+    CheckpointCommitment, CheckpointSequenceNumber, CheckpointSummary, ECMHLiveObjectSetDigest,
+    VerifiedCheckpoint,
+};
+use mys_types::mys_system_state::{get_mys_system_state, MysSystemStateTrait};
+
+use super::VerifyOptions;
+
+const PROGRESS_INTERVAL: u64 = 10_000;
+
+/// Running root accumulator of the epoch currently being walked
+struct EpochAccumulator {
+    epoch: EpochId,
+    protocol_config: ProtocolConfig,
+    running_root: Accumulator,
+}
+
+struct Verifier {
+    perpetual_db: AuthorityPerpetualTables,
+    checkpoint_store: Arc<CheckpointStore>,
+    chain: Chain,
+    options: VerifyOptions,
+    errors: u64,
+}
+
+impl Verifier {
+    fn report(&mut self, message: String) -> anyhow::Result<()> {
+        self.errors += 1;
+        println!("ERROR: {message}");
+        if self.options.fail_fast {
+            bail!("Verification failed: {message}");
+        }
+        Ok(())
+    }
+
+    fn get_checkpoint(&self, seq: CheckpointSequenceNumber) -> anyhow::Result<VerifiedCheckpoint> {
+        self.checkpoint_store
+            .get_checkpoint_by_sequence_number(seq)?
+            .ok_or(anyhow!("Checkpoint {seq} not found in checkpoint store"))
+    }
+
+    /// The protocol version of the epoch that starts at `checkpoint`, which must be either the
+    /// genesis checkpoint or the first checkpoint of an epoch
+    fn protocol_version_at_epoch_start(
+        &self,
+        checkpoint: &CheckpointSummary,
+    ) -> anyhow::Result<ProtocolVersion> {
+        if checkpoint.sequence_number > 0 {
+            let previous = self.get_checkpoint(checkpoint.sequence_number - 1)?;
+            return previous
+                .end_of_epoch_data
+                .as_ref()
+                .map(|data| data.next_epoch_protocol_version)
+                .ok_or(anyhow!(
+                    "Checkpoint {} is not the first checkpoint of epoch {}",
+                    checkpoint.sequence_number,
+                    checkpoint.epoch
+                ));
+        }
+
+        // The genesis protocol version is only recorded in the system state created by genesis
+        let contents = self
+            .checkpoint_store
+            .get_checkpoint_contents(&checkpoint.content_digest)?
+            .ok_or(anyhow!("Genesis checkpoint contents not found"))?;
+        let mut genesis_objects = BTreeMap::new();
+        for digests in contents.iter() {
+            let effects = self
+                .perpetual_db
+                .get_effects(&digests.transaction)?
+                .ok_or(anyhow!("Genesis effects not found"))?;
+            for ((id, version, _), _, _) in effects.all_changed_objects() {
+                if let Some(object) = self.perpetual_db.get_object_by_key_fallible(&id, version)? {
+                    genesis_objects.insert(id, object);
+                }
+            }
+        }
+        let system_state = get_mys_system_state(&genesis_objects)?;
+        Ok(ProtocolVersion::new(system_state.protocol_version()))
+    }
+
+    /// The root state hash of the epoch before the one starting at `checkpoint`. The root
+    /// recomputed by the walk is used if there is one, otherwise the stored root is checked
+    /// against the commitment in the last checkpoint of the epoch.
+    fn previous_epoch_root(
+        &mut self,
+        checkpoint: &CheckpointSummary,
+        recomputed: Option<(EpochId, Accumulator)>,
+    ) -> anyhow::Result<Accumulator> {
+        let epoch = checkpoint.epoch - 1;
+        if let Some((recomputed_epoch, root)) = recomputed {
+            if recomputed_epoch == epoch {
+                return Ok(root);
+            }
+        }
+
+        let (last_checkpoint, root) = self
+            .perpetual_db
+            .get_root_state_hash(epoch)?
+            .ok_or(anyhow!("Root state hash of epoch {epoch} not found"))?;
+        if last_checkpoint + 1 != checkpoint.sequence_number {
+            self.report(format!(
+                "Root state hash of epoch {epoch} was computed up to checkpoint {last_checkpoint}, but the epoch ends at checkpoint {}",
+                checkpoint.sequence_number - 1
+            ))?;
+        }
+        let stored: ECMHLiveObjectSetDigest = root.digest().into();
+        let previous = self.get_checkpoint(checkpoint.sequence_number - 1)?;
+        let commitments = previous
+            .end_of_epoch_data
+            .as_ref()
+            .map(|data| data.epoch_commitments.clone())
+            .unwrap_or_default();
+        if commitments.is_empty() {
+            println!(
+                "WARNING: No commitment to the root state hash of epoch {epoch}, the accumulators of epoch {} start from an unverified root",
+                checkpoint.epoch
+            );
+        }
+        for commitment in commitments {
+            let CheckpointCommitment::ECMHLiveObjectSetDigest(committed) = commitment;
+            if committed != stored {
+                self.report(format!(
+                    "Root state hash of epoch {epoch} is {stored:?}, but checkpoint {} commits to {committed:?}",
+                    previous.sequence_number
+                ))?;
+            }
+        }
+        Ok(root)
+    }
+
+    fn start_epoch(
+        &mut self,
+        checkpoint: &CheckpointSummary,
+        recomputed: Option<(EpochId, Accumulator)>,
+    ) -> anyhow::Result<EpochAccumulator> {
+        let running_root = if checkpoint.epoch == 0 {
+            Accumulator::default()
+        } else {
+            self.previous_epoch_root(checkpoint, recomputed)?
+        };
+        let protocol_version = self.protocol_version_at_epoch_start(checkpoint)?;
+        Ok(EpochAccumulator {
+            epoch: checkpoint.epoch,
+            protocol_config: ProtocolConfig::get_for_version(protocol_version, self.chain),
+            running_root,
+        })
+    }
+
+    /// Checks that `checkpoint` links to `previous` and returns the effects of its transactions
+    fn verify_checkpoint(
+        &mut self,
+        checkpoint: &CheckpointSummary,
+        previous: Option<&CheckpointSummary>,
+    ) -> anyhow::Result<Vec<TransactionEffects>> {
+        let seq = checkpoint.sequence_number;
+        if let Some(previous) = previous {
+            if checkpoint.previous_digest != Some(previous.digest()) {
+                self.report(format!(
+                    "Checkpoint {seq} links to {:?}, but the digest of checkpoint {} is {:?}",
+                    checkpoint.previous_digest,
+                    previous.sequence_number,
+                    previous.digest()
+                ))?;
+            }
+            let expected_epoch = if previous.end_of_epoch_data.is_some() {
+                previous.epoch + 1
+            } else {
+                previous.epoch
+            };
+            if checkpoint.epoch != expected_epoch {
+                self.report(format!(
+                    "Checkpoint {seq} is in epoch {}, expected epoch {expected_epoch}",
+                    checkpoint.epoch
+                ))?;
+            }
+        } else if seq == 0 && checkpoint.previous_digest.is_some() {
+            self.report("Genesis checkpoint links to a previous checkpoint".to_string())?;
+        }
+
+        let Some(contents) = self
+            .checkpoint_store
+            .get_checkpoint_contents(&checkpoint.content_digest)?
+        else {
+            self.report(format!(
+                "Contents {:?} of checkpoint {seq} not found",
+                checkpoint.content_digest
+            ))?;
+            return Ok(vec![]);
+        };
+        if *contents.digest() != checkpoint.content_digest {
+            self.report(format!(
+                "Contents of checkpoint {seq} hash to {:?}, expected {:?}",
+                contents.digest(),
+                checkpoint.content_digest
+            ))?;
+        }
+        let previous_total = previous.map_or(0, |p| p.network_total_transactions);
+        if previous.is_some() || seq == 0 {
+            let expected_total = previous_total + contents.size() as u64;
+            if checkpoint.network_total_transactions != expected_total {
+                self.report(format!(
+                    "Checkpoint {seq} claims {} network transactions, expected {expected_total}",
+                    checkpoint.network_total_transactions
+                ))?;
+            }
+        }
+
+        let mut checkpoint_effects = Vec::with_capacity(contents.size());
+        for digests in contents.iter() {
+            let tx_digest = digests.transaction;
+            match self.perpetual_db.get_transaction(&tx_digest)? {
+                Some(transaction) if *transaction.inner().digest() != tx_digest => {
+                    self.report(format!(
+                        "Transaction {tx_digest:?} in checkpoint {seq} hashes to {:?}",
+                        transaction.inner().digest()
+                    ))?;
+                }
+                Some(_) => (),
+                None => {
+                    self.report(format!(
+                        "Transaction {tx_digest:?} in checkpoint {seq} not found"
+                    ))?;
+                }
+            }
+
+            let Some(effects) = self.perpetual_db.get_effects(&tx_digest)? else {
+                self.report(format!(
+                    "Effects of transaction {tx_digest:?} in checkpoint {seq} not found"
+                ))?;
+                continue;
+            };
+            if effects.digest() != digests.effects {
+                self.report(format!(
+                    "Effects of transaction {tx_digest:?} in checkpoint {seq} hash to {:?}, expected {:?}",
+                    effects.digest(),
+                    digests.effects
+                ))?;
+            }
+            if !self.options.skip_objects {
+                self.verify_written_objects(seq, &effects)?;
+            }
+            checkpoint_effects.push(effects);
+        }
+        Ok(checkpoint_effects)
+    }
+
+    fn verify_written_objects(
+        &mut self,
+        seq: CheckpointSequenceNumber,
+        effects: &TransactionEffects,
+    ) -> anyhow::Result<()> {
+        for (object_ref, _, _) in effects.all_changed_objects() {
+            let (id, version, digest) = object_ref;
+            let object = self.perpetual_db.get_object_by_key_fallible(&id, version)?;
+            match object {
+                Some(object) if object.compute_object_reference() != object_ref => {
+                    self.report(format!(
+                        "Object {id:?} at version {version:?} written by {:?} in checkpoint {seq} has digest {:?}, expected {digest:?}",
+                        effects.transaction_digest(),
+                        object.digest()
+                    ))?;
+                }
+                Some(_) => (),
+                None => {
+                    self.report(format!(
+                        "Object {id:?} at version {version:?} written by {:?} in checkpoint {seq} not found",
+                        effects.transaction_digest()
+                    ))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Compares the recomputed root state hash of an epoch against the stored one and against
+    /// the commitment in its last checkpoint
+    fn verify_epoch_root(
+        &mut self,
+        checkpoint: &CheckpointSummary,
+        epoch_accumulator: &EpochAccumulator,
+    ) -> anyhow::Result<()> {
+        let epoch = epoch_accumulator.epoch;
+        let computed: ECMHLiveObjectSetDigest = epoch_accumulator.running_root.digest().into();
+        match self.perpetual_db.get_root_state_hash(epoch)? {
+            Some((last_checkpoint, stored)) => {
+                if last_checkpoint != checkpoint.sequence_number {
+                    self.report(format!(
+                        "Root state hash of epoch {epoch} was computed up to checkpoint {last_checkpoint}, but the epoch ends at checkpoint {}",
+                        checkpoint.sequence_number
+                    ))?;
+                }
+                let stored: ECMHLiveObjectSetDigest = stored.digest().into();
+                if stored != computed {
+                    self.report(format!(
+                        "Root state hash of epoch {epoch} is {stored:?}, recomputed {computed:?}"
+                    ))?;
+                }
+            }
+            None => self.report(format!("Root state hash of epoch {epoch} not found"))?,
+        }
+
+        let commitments = checkpoint
+            .end_of_epoch_data
+            .as_ref()
+            .map(|data| data.epoch_commitments.as_slice())
+            .unwrap_or_default();
+        for commitment in commitments {
+            let CheckpointCommitment::ECMHLiveObjectSetDigest(committed) = commitment;
+            if *committed != computed {
+                self.report(format!(
+                    "Checkpoint {} commits to root state hash {committed:?} for epoch {epoch}, recomputed {computed:?}",
+                    checkpoint.sequence_number
+                ))?;
+            }
+        }
+        println!("Verified root state hash of epoch {epoch}: {computed:?}");
+        Ok(())
+    }
+}
+
+pub fn verify_db(path: &Path, options: VerifyOptions) -> anyhow::Result<()> {
+    let perpetual_db = AuthorityPerpetualTables::open(&path.join("store"), None);
+    let checkpoint_store = CheckpointStore::new(&path.join("checkpoints"));
+    verify_stores(perpetual_db, checkpoint_store, options)
+}
+
+fn verify_stores(
+    perpetual_db: AuthorityPerpetualTables,
+    checkpoint_store: Arc<CheckpointStore>,
+    options: VerifyOptions,
+) -> anyhow::Result<()> {
+    let genesis = checkpoint_store
+        .get_checkpoint_by_sequence_number(0)?
+        .ok_or(anyhow!("Genesis checkpoint not found in checkpoint store"))?;
+    let chain = ChainIdentifier::from(*genesis.digest()).chain();
+
+    let highest_pruned = perpetual_db
+        .get_highest_pruned_checkpoint()?
+        .max(checkpoint_store.get_highest_pruned_checkpoint_seq_number()?);
+    let first_unpruned = if highest_pruned > 0 {
+        highest_pruned + 1
+    } else {
+        0
+    };
+    let start = options.start.unwrap_or(first_unpruned);
+    let end = match options.end {
+        Some(end) => end,
+        None => checkpoint_store
+            .get_highest_executed_checkpoint_seq_number()?
+            .ok_or(anyhow!("No executed checkpoints found"))?,
+    };
+    if start > end {
+        bail!("Nothing to verify, start checkpoint {start} is after end checkpoint {end}");
+    }
+    println!("Verifying checkpoints {start} to {end}");
+
+    let mut verifier = Verifier {
+        perpetual_db,
+        checkpoint_store,
+        chain,
+        options,
+        errors: 0,
+    };
+
+    let mut previous = if start > 0 {
+        verifier
+            .checkpoint_store
+            .get_checkpoint_by_sequence_number(start - 1)?
+            .map(|c| c.into_summary_and_sequence().1)
+    } else {
+        None
+    };
+    let mut epoch_accumulator = None;
+    // The root of the last epoch recomputed by the walk, which the next epoch starts from
+    let mut recomputed_root = None;
+    let mut verified_epochs = 0;
+    for seq in start..=end {
+        let checkpoint = verifier.get_checkpoint(seq)?.into_summary_and_sequence().1;
+        if checkpoint.sequence_number != seq {
+            verifier.report(format!(
+                "Checkpoint stored at {seq} has sequence number {}",
+                checkpoint.sequence_number
+            ))?;
+        }
+
+        // The accumulator can only be recomputed for epochs whose first checkpoint is covered
+        let starts_epoch = seq == 0
+            || previous
+                .as_ref()
+                .is_some_and(|p| p.end_of_epoch_data.is_some());
+        if starts_epoch {
+            epoch_accumulator = Some(verifier.start_epoch(&checkpoint, recomputed_root.take())?);
+        }
+
+        let effects = verifier.verify_checkpoint(&checkpoint, previous.as_ref())?;
+        if let Some(epoch_accumulator) = epoch_accumulator.as_mut() {
+            let acc = accumulate_effects(
+                &verifier.perpetual_db,
+                effects,
+                &epoch_accumulator.protocol_config,
+            );
+            epoch_accumulator.running_root.union(&acc);
+        }
+        if checkpoint.end_of_epoch_data.is_some() {
+            if let Some(epoch_accumulator) = epoch_accumulator.take() {
+                verifier.verify_epoch_root(&checkpoint, &epoch_accumulator)?;
+                recomputed_root = Some((epoch_accumulator.epoch, epoch_accumulator.running_root));
+                verified_epochs += 1;
+            }
+        }
+
+        if seq % PROGRESS_INTERVAL == 0 {
+            println!("Verified up to checkpoint {seq}");
+        }
+        previous = Some(checkpoint);
+    }
+
+    println!(
+        "Verified {} checkpoints and {verified_epochs} epoch root state hashes, found {} errors",
+        end - start + 1,
+        verifier.errors
+    );
+    if verifier.errors > 0 {
+        bail!("Database verification found {} errors", verifier.errors);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use fastcrypto::hash::MultisetHash;
+    use mys_core::authority::authority_store_tables::AuthorityPerpetualTables;
+    use mys_core::checkpoints::CheckpointStore;
+    use mys_protocol_config::ProtocolVersion;
+    use mys_swarm_config::test_utils::{empty_contents, CommitteeFixture};
+    use mys_types::accumulator::Accumulator;
+    use mys_types::messages_checkpoint::{ECMHLiveObjectSetDigest, EndOfEpochData};
+
+    use super::verify_stores;
+    use crate::db_tool::VerifyOptions;
+
+    /// Stores a chain of empty checkpoints where epoch 0 ends at checkpoint 3, committing to an
+    /// empty root state, and verifies the checkpoints of epoch 1, which starts from the root of
+    /// epoch 0 stored by `store_root`
+    fn verify_epoch_1(
+        store_root: impl FnOnce(&AuthorityPerpetualTables),
+    ) -> Result<(), anyhow::Error> {
+        let path = tempfile::tempdir()?;
+        let perpetual_db = AuthorityPerpetualTables::open(&path.path().join("store"), None);
+        let checkpoint_store = CheckpointStore::new(&path.path().join("checkpoints"));
+
+        let epoch_0 = CommitteeFixture::generate(rand::rngs::OsRng, 0, 4);
+        let epoch_1 = CommitteeFixture::generate(rand::rngs::OsRng, 1, 4);
+        let (mut checkpoints, _, _, _) = epoch_0.make_empty_checkpoints(3, None);
+        let root: ECMHLiveObjectSetDigest = Accumulator::default().digest().into();
+        let (_, _, last_checkpoint) = epoch_0.make_end_of_epoch_checkpoint(
+            checkpoints.last().unwrap().clone(),
+            Some(EndOfEpochData {
+                next_epoch_committee: epoch_1.committee().voting_rights.clone(),
+                next_epoch_protocol_version: ProtocolVersion::MAX,
+                epoch_commitments: vec![root.into()],
+            }),
+        );
+        let (epoch_1_checkpoints, _, _, _) =
+            epoch_1.make_empty_checkpoints(2, Some(last_checkpoint.clone()));
+        checkpoints.push(last_checkpoint);
+        checkpoints.extend(epoch_1_checkpoints);
+
+        for checkpoint in &checkpoints {
+            checkpoint_store.insert_verified_checkpoint(checkpoint)?;
+        }
+        checkpoint_store
+            .insert_checkpoint_contents(empty_contents().into_inner().into_checkpoint_contents())?;
+        store_root(&perpetual_db);
+
+        let options = VerifyOptions {
+            start: Some(4),
+            end: Some(5),
+            skip_objects: false,
+            fail_fast: false,
+        };
+        verify_stores(perpetual_db, checkpoint_store, options)
+    }
+
+    #[test]
+    fn test_verify_previous_epoch_root() {
+        verify_epoch_1(|perpetual_db| {
+            perpetual_db
+                .insert_root_state_hash(0, 3, Accumulator::default())
+                .unwrap();
+        })
+        .unwrap();
+
+        // A stored root which does not match the commitment of the last checkpoint of the epoch
+        verify_epoch_1(|perpetual_db| {
+            let mut root = Accumulator::default();
+            root.insert(b"corrupted");
+            perpetual_db.insert_root_state_hash(0, 3, root).unwrap();
+        })
+        .unwrap_err();
+
+        // A stored root which was computed up to the wrong checkpoint
+        verify_epoch_1(|perpetual_db| {
+            perpetual_db
+                .insert_root_state_hash(0, 2, Accumulator::default())
+                .unwrap();
+        })
+        .unwrap_err();
+
+        // A missing root
+        verify_epoch_1(|_| ()).unwrap_err();
+    }
+}
@@ -33,7 +33,6 @@ use mys_types::crypto::AuthorityPublicKeyBytes;
 use mys_types::messages_grpc::LayoutGenerationOption;
 use mys_types::multiaddr::Multiaddr;
 use mys_types::{base_types::*, object::Owner};
-use tokio::sync::mpsc;
 use tokio::task::JoinHandle;
 use tokio::time::Instant;
 
@@ -54,7 +53,7 @@ use mys_core::authority::AuthorityStore;
 use mys_core::checkpoints::CheckpointStore;
 use mys_core::epoch::committee_store::CommitteeStore;
 use mys_core::storage::RocksDbStore;
-use mys_snapshot::reader::StateSnapshotReaderV1;
+use mys_snapshot::reader::StateSnapshotChainReader;
 use mys_snapshot::setup_db_state;
 use mys_storage::object_store::util::{copy_file, exists, get_path};
 use mys_storage::object_store::ObjectStoreGetExt;
@@ -872,40 +871,37 @@ pub async fn download_formal_snapshot(
     }
     let snapshot_dir_clone = snapshot_dir.clone();
 
-    // TODO if verify is false, we should skip generating these and
-    // not pass in a channel to the reader
-    let (sender, mut receiver) = mpsc::channel(num_parallel_downloads);
     let m_clone = m.clone();
 
+    // The snapshot of the epoch may be a delta, in which case the whole chain of snapshots it
+    // is based on gets restored
     let snapshot_handle = tokio::spawn(async move {
         let local_store_config = ObjectStoreConfig {
             object_store: Some(ObjectStoreType::File),
             directory: Some(snapshot_dir_clone.to_path_buf()),
             ..Default::default()
         };
-        let mut reader = StateSnapshotReaderV1::new(
+        let mut reader = StateSnapshotChainReader::new(
             epoch,
             &snapshot_store_config,
             &local_store_config,
             usize::MAX,
             NonZeroUsize::new(num_parallel_downloads).unwrap(),
             m_clone,
-            false, // skip_reset_local_store
         )
         .await
         .unwrap_or_else(|err| panic!("Failed to create reader: {}", err));
-        reader
-            .read(&perpetual_db_clone, abort_registration, Some(sender))
+        let root_accumulator = reader
+            .read(&perpetual_db_clone, abort_registration)
             .await
             .unwrap_or_else(|err| panic!("Failed during read: {}", err));
-        Ok::<(), anyhow::Error>(())
+        let num_live_objects = reader.num_live_objects()?;
+        Ok::<(Accumulator, u64), anyhow::Error>((root_accumulator, num_live_objects))
     });
-    let mut root_accumulator = Accumulator::default();
-    let mut num_live_objects = 0;
-    while let Some((partial_acc, num_objects)) = receiver.recv().await {
-        num_live_objects += num_objects;
-        root_accumulator.union(&partial_acc);
-    }
+    let (root_accumulator, num_live_objects) = snapshot_handle
+        .await
+        .expect("Task join failed")
+        .expect("Snapshot restore task failed");
     summaries_handle
         .await
         .expect("Task join failed")
@@ -965,11 +961,6 @@ pub async fn download_formal_snapshot(
         )?;
     }
 
-    snapshot_handle
-        .await
-        .expect("Task join failed")
-        .expect("Snapshot restore task failed");
-
     // TODO we should ensure this map is being updated for all end of epoch
     // checkpoints during summary sync. This happens in `insert_{verified|certified}_checkpoint`
     // in checkpoint store, but not in the corresponding functions in ObjectStore trait
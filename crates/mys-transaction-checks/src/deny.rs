@@ -11,17 +11,68 @@ use mys_types::{
     transaction::{Command, InputObjectKind, TransactionData, TransactionDataAPI},
 };
 macro_rules! deny_if_true {
-    ($cond:expr, $msg:expr) => {
+    ($rule:expr, $cond:expr, $msg:expr) => {
         if ($cond) {
-            return Err(MysError::UserInputError {
-                error: UserInputError::TransactionDenied {
-                    error: $msg.to_string(),
+            return Err(TransactionDenied {
+                rule: Some($rule),
+                error: MysError::UserInputError {
+                    error: UserInputError::TransactionDenied {
+                        error: $msg.to_string(),
+                    },
                 },
             });
         }
     };
 }
 
+/// The deny config rule that rejected a transaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DenyRule {
+    UserTransactionDisabled,
+    ZkLoginSigDisabled,
+    ZkLoginProviderDisabled,
+    PackagePublishDisabled,
+    PackageUpgradeDisabled,
+    SharedObjectDisabled,
+    ReceivingObjectsDisabled,
+    AddressDenied,
+    ObjectDenied,
+    PackageDenied,
+}
+
+impl DenyRule {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DenyRule::UserTransactionDisabled => "user_transaction_disabled",
+            DenyRule::ZkLoginSigDisabled => "zklogin_sig_disabled",
+            DenyRule::ZkLoginProviderDisabled => "zklogin_provider_disabled",
+            DenyRule::PackagePublishDisabled => "package_publish_disabled",
+            DenyRule::PackageUpgradeDisabled => "package_upgrade_disabled",
+            DenyRule::SharedObjectDisabled => "shared_object_disabled",
+            DenyRule::ReceivingObjectsDisabled => "receiving_objects_disabled",
+            DenyRule::AddressDenied => "address_denied",
+            DenyRule::ObjectDenied => "object_denied",
+            DenyRule::PackageDenied => "package_denied",
+        }
+    }
+}
+
+/// A failed deny check. `rule` is `None` when the check itself failed, e.g. because a
+/// package could not be loaded, rather than the transaction being denied.
+#[derive(Debug)]
+pub struct TransactionDenied {
+    pub rule: Option<DenyRule>,
+    pub error: MysError,
+}
+
+impl From<MysError> for TransactionDenied {
+    fn from(error: MysError) -> Self {
+        Self { rule: None, error }
+    }
+}
+
+type DenyResult = Result<(), TransactionDenied>;
+
 /// Check that the provided transaction is allowed to be signed according to the
 /// deny config.
 pub fn check_transaction_for_signing(
@@ -32,6 +83,27 @@ pub fn check_transaction_for_signing(
     filter_config: &TransactionDenyConfig,
     package_store: &dyn BackingPackageStore,
 ) -> MysResult {
+    check_transaction_for_signing_with_rule(
+        tx_data,
+        tx_signatures,
+        input_object_kinds,
+        receiving_objects,
+        filter_config,
+        package_store,
+    )
+    .map_err(|denied| denied.error)
+}
+
+/// Same as `check_transaction_for_signing`, but also reports which rule denied the
+/// transaction.
+pub fn check_transaction_for_signing_with_rule(
+    tx_data: &TransactionData,
+    tx_signatures: &[GenericSignature],
+    input_object_kinds: &[InputObjectKind],
+    receiving_objects: &[ObjectRef],
+    filter_config: &TransactionDenyConfig,
+    package_store: &dyn BackingPackageStore,
+) -> DenyResult {
     check_disabled_features(filter_config, tx_data, tx_signatures)?;
 
     check_signers(filter_config, tx_data)?;
@@ -48,13 +120,15 @@ pub fn check_transaction_for_signing(
 fn check_receiving_objects(
     filter_config: &TransactionDenyConfig,
     receiving_objects: &[ObjectRef],
-) -> MysResult {
+) -> DenyResult {
     deny_if_true!(
+        DenyRule::ReceivingObjectsDisabled,
         filter_config.receiving_objects_disabled() && !receiving_objects.is_empty(),
         "Receiving objects is temporarily disabled".to_string()
     );
     for (id, _, _) in receiving_objects {
         deny_if_true!(
+            DenyRule::ObjectDenied,
             filter_config.get_object_deny_set().contains(id),
             format!("Access to object {:?} is temporarily disabled", id)
         );
@@ -66,8 +140,9 @@ fn check_disabled_features(
     filter_config: &TransactionDenyConfig,
     tx_data: &TransactionData,
     tx_signatures: &[GenericSignature],
-) -> MysResult {
+) -> DenyResult {
     deny_if_true!(
+        DenyRule::UserTransactionDisabled,
         filter_config.user_transaction_disabled(),
         "Transaction signing is temporarily disabled"
     );
@@ -75,10 +150,12 @@ fn check_disabled_features(
     tx_signatures.iter().try_for_each(|s| {
         if let GenericSignature::ZkLoginAuthenticator(z) = s {
             deny_if_true!(
+                DenyRule::ZkLoginSigDisabled,
                 filter_config.zklogin_sig_disabled(),
                 "zkLogin authenticator is temporarily disabled"
             );
             deny_if_true!(
+                DenyRule::ZkLoginProviderDisabled,
                 filter_config.zklogin_disabled_providers().contains(
                     &OIDCProvider::from_iss(z.get_iss())
                         .map_err(|_| MysError::UnexpectedMessage(z.get_iss().to_string()))?
@@ -96,10 +173,12 @@ fn check_disabled_features(
 
     for command in tx_data.kind().iter_commands() {
         deny_if_true!(
+            DenyRule::PackagePublishDisabled,
             filter_config.package_publish_disabled() && matches!(command, Command::Publish(..)),
             "Package publish is temporarily disabled"
         );
         deny_if_true!(
+            DenyRule::PackageUpgradeDisabled,
             filter_config.package_upgrade_disabled() && matches!(command, Command::Upgrade(..)),
             "Package upgrade is temporarily disabled"
         );
@@ -107,13 +186,14 @@ fn check_disabled_features(
     Ok(())
 }
 
-fn check_signers(filter_config: &TransactionDenyConfig, tx_data: &TransactionData) -> MysResult {
+fn check_signers(filter_config: &TransactionDenyConfig, tx_data: &TransactionData) -> DenyResult {
     let deny_map = filter_config.get_address_deny_set();
     if deny_map.is_empty() {
         return Ok(());
     }
     for signer in tx_data.signers() {
         deny_if_true!(
+            DenyRule::AddressDenied,
             deny_map.contains(&signer),
             format!(
                 "Access to account address {:?} is temporarily disabled",
@@ -127,7 +207,7 @@ fn check_signers(filter_config: &TransactionDenyConfig, tx_data: &TransactionDat
 fn check_input_objects(
     filter_config: &TransactionDenyConfig,
     input_object_kinds: &[InputObjectKind],
-) -> MysResult {
+) -> DenyResult {
     let deny_map = filter_config.get_object_deny_set();
     let shared_object_disabled = filter_config.shared_object_disabled();
     if deny_map.is_empty() && !shared_object_disabled {
@@ -137,10 +217,12 @@ fn check_input_objects(
     for input_object_kind in input_object_kinds {
         let id = input_object_kind.object_id();
         deny_if_true!(
+            DenyRule::ObjectDenied,
             deny_map.contains(&id),
             format!("Access to input object {:?} is temporarily disabled", id)
         );
         deny_if_true!(
+            DenyRule::SharedObjectDisabled,
             shared_object_disabled && input_object_kind.is_shared_object(),
             "Usage of shared object in transactions is temporarily disabled"
         );
@@ -152,7 +234,7 @@ fn check_package_dependencies(
     filter_config: &TransactionDenyConfig,
     tx_data: &TransactionData,
     package_store: &dyn BackingPackageStore,
-) -> MysResult {
+) -> DenyResult {
     let deny_map = filter_config.get_package_deny_set();
     if deny_map.is_empty() {
         return Ok(());
@@ -203,6 +285,7 @@ fn check_package_dependencies(
     }
     for dep in dependencies {
         deny_if_true!(
+            DenyRule::PackageDenied,
             deny_map.contains(&dep),
             format!("Access to package {:?} is temporarily disabled", dep)
         );
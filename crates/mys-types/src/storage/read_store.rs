@@ -630,6 +630,25 @@ pub trait RpcIndexes: Send + Sync {
     ) -> Result<Box<dyn Iterator<Item = (DynamicFieldKey, DynamicFieldIndexInfo)> + '_>>;
 
     fn get_coin_info(&self, coin_type: &StructTag) -> Result<Option<CoinInfo>>;
+
+    /// Lowest checkpoint for which the historical object queries below can be answered.
+    fn get_lowest_available_checkpoint_object_history(&self) -> Result<CheckpointSequenceNumber>;
+
+    /// Returns the version of the object which was live as of the end of `checkpoint`, or `None`
+    /// if the object did not exist (or was wrapped) at that point.
+    fn get_object_version_at_checkpoint(
+        &self,
+        object_id: ObjectID,
+        checkpoint: CheckpointSequenceNumber,
+    ) -> Result<Option<SequenceNumber>>;
+
+    /// Iterate over the objects owned by `owner` as of the end of `checkpoint`.
+    fn account_owned_objects_info_at_checkpoint_iter(
+        &self,
+        owner: MysAddress,
+        checkpoint: CheckpointSequenceNumber,
+        cursor: Option<ObjectID>,
+    ) -> Result<Box<dyn Iterator<Item = AccountOwnedObjectInfo> + '_>>;
 }
 
 pub struct AccountOwnedObjectInfo {
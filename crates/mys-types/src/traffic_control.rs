@@ -202,6 +202,126 @@ fn default_sketch_tolerance() -> f64 {
     DEFAULT_SKETCH_TOLERANCE
 }
 
+#[serde_as]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TokenBucketConfig {
+    /// Maximum number of tokens a connection IP can accumulate, i.e. the
+    /// burst that is allowed on top of `client_refill_rate`
+    #[serde(default = "default_client_capacity")]
+    pub client_capacity: u64,
+    /// Number of tokens per second added back to a connection IP's bucket,
+    /// i.e. the sustained rate that is allowed
+    #[serde(default = "default_client_refill_rate")]
+    pub client_refill_rate: f64,
+    #[serde(default = "default_proxied_client_capacity")]
+    pub proxied_client_capacity: u64,
+    #[serde(default = "default_proxied_client_refill_rate")]
+    pub proxied_client_refill_rate: f64,
+    /// Maximum number of buckets to keep track of. Once reached, buckets
+    /// which have refilled completely are evicted
+    #[serde(default = "default_max_tracked_clients")]
+    pub max_tracked_clients: usize,
+}
+
+impl Default for TokenBucketConfig {
+    fn default() -> Self {
+        Self {
+            client_capacity: default_client_capacity(),
+            client_refill_rate: default_client_refill_rate(),
+            proxied_client_capacity: default_proxied_client_capacity(),
+            proxied_client_refill_rate: default_proxied_client_refill_rate(),
+            max_tracked_clients: default_max_tracked_clients(),
+        }
+    }
+}
+
+fn default_client_capacity() -> u64 {
+    // as with `default_client_threshold`, a connection IP may be a fullnode
+    // proxying traffic for many clients, so only limit unreasonable rates
+    // by default
+    1_000_000
+}
+
+fn default_client_refill_rate() -> f64 {
+    100_000.0
+}
+
+fn default_proxied_client_capacity() -> u64 {
+    100
+}
+
+fn default_proxied_client_refill_rate() -> f64 {
+    10.0
+}
+
+fn default_max_tracked_clients() -> usize {
+    100_000
+}
+
+/// The address of a transaction which is used to identify the client
+/// by sender-keyed policies.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SenderKey {
+    #[default]
+    Sender,
+    GasPayer,
+}
+
+#[serde_as]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SenderRateLimitConfig {
+    #[serde(default)]
+    pub key: SenderKey,
+    /// Maximum number of tokens an address can accumulate, i.e. the burst
+    /// that is allowed on top of `refill_rate`
+    #[serde(default = "default_sender_capacity")]
+    pub capacity: u64,
+    /// Number of tokens per second added back to an address's bucket
+    #[serde(default = "default_sender_refill_rate")]
+    pub refill_rate: f64,
+    #[serde(default = "default_max_tracked_clients")]
+    pub max_tracked_senders: usize,
+}
+
+impl Default for SenderRateLimitConfig {
+    fn default() -> Self {
+        Self {
+            key: SenderKey::default(),
+            capacity: default_sender_capacity(),
+            refill_rate: default_sender_refill_rate(),
+            max_tracked_senders: default_max_tracked_clients(),
+        }
+    }
+}
+
+fn default_sender_capacity() -> u64 {
+    100
+}
+
+fn default_sender_refill_rate() -> f64 {
+    10.0
+}
+
+/// How the decisions of the policies in a composite policy are combined.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CompositeOperator {
+    /// Block a client only if all policies would block it
+    And,
+    /// Block a client if any policy would block it
+    Or,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CompositePolicyConfig {
+    pub operator: CompositeOperator,
+    pub policies: Vec<PolicyType>,
+}
+
 // Serializable representation of policy types, used in config
 // in order to easily change in tests or to killswitch
 #[derive(Clone, Serialize, Deserialize, Debug, Default)]
@@ -215,6 +335,21 @@ pub enum PolicyType {
     /// with granularity of `update_interval_secs`
     FreqThreshold(FreqThresholdConfig),
 
+    /// Blocks connection_ip or proxy_ip once its token bucket is empty. Each
+    /// tally consumes a token and buckets are refilled at a constant rate,
+    /// allowing bursts of up to the bucket capacity
+    TokenBucket(TokenBucketConfig),
+
+    /// Same as `TokenBucket`, but keyed on the sender or gas payer of the
+    /// transaction rather than on IP, so that a single spammy client cannot
+    /// get everyone behind the same IP blocked. Tallies without a transaction
+    /// are ignored
+    SenderRateLimit(SenderRateLimitConfig),
+
+    /// Combines the decisions of several policies, each of which is handed
+    /// every tally
+    Composite(CompositePolicyConfig),
+
     /* Below this point are test policies, and thus should not be used in production */
     ///
     /// Simple policy that adds connection_ip to blocklist when the same connection_ip
@@ -236,6 +371,8 @@ pub struct PolicyConfig {
     pub connection_blocklist_ttl_sec: u64,
     #[serde(default)]
     pub proxy_blocklist_ttl_sec: u64,
+    #[serde(default = "default_sender_blocklist_ttl_sec")]
+    pub sender_blocklist_ttl_sec: u64,
     #[serde(default)]
     pub spam_policy_type: PolicyType,
     #[serde(default)]
@@ -264,6 +401,7 @@ impl Default for PolicyConfig {
             client_id_source: default_client_id_source(),
             connection_blocklist_ttl_sec: 0,
             proxy_blocklist_ttl_sec: 0,
+            sender_blocklist_ttl_sec: default_sender_blocklist_ttl_sec(),
             spam_policy_type: PolicyType::NoOp,
             error_policy_type: PolicyType::NoOp,
             channel_capacity: 100,
@@ -281,6 +419,11 @@ pub fn default_client_id_source() -> ClientIdSource {
 pub fn default_connection_blocklist_ttl_sec() -> u64 {
     60
 }
+
+pub fn default_sender_blocklist_ttl_sec() -> u64 {
+    60
+}
+
 pub fn default_channel_capacity() -> usize {
     100
 }
@@ -544,6 +544,10 @@ pub enum MysClientCommands {
         /// If an output filepath is not specified, it will output a file `gas_profile_{tx_digest}_{unix_timestamp}.json` to the working directory
         #[arg(long, short)]
         profile_output: Option<PathBuf>,
+
+        /// If specified, also writes a JSON gas report to this filepath, breaking down computation per Move function, storage cost and rebate per object, and dynamic field accesses
+        #[arg(long)]
+        gas_report: Option<PathBuf>,
     },
 
     /// Replay a given transaction to view transaction effects. Set environment variable MOVE_VM_STEP=1 to debug.
@@ -695,6 +699,7 @@ impl MysClientCommands {
             MysClientCommands::ProfileTransaction {
                 tx_digest,
                 profile_output,
+                gas_report,
             } => {
                 move_vm_profiler::tracing_feature_disabled! {
                     bail!(
@@ -709,6 +714,7 @@ impl MysClientCommands {
                     protocol_version: None,
                     profile_output,
                     config_objects: None,
+                    gas_report,
                 };
                 let rpc = context.config.get_active_env()?.rpc.clone();
                 let _command_result =
@@ -730,6 +736,7 @@ impl MysClientCommands {
                     executor_version,
                     protocol_version,
                     config_objects: None,
+                    fuzz_artifact: None,
                 };
 
                 let rpc = context.config.get_active_env()?.rpc.clone();
@@ -748,6 +755,8 @@ impl MysClientCommands {
                     terminate_early,
                     num_tasks: 16,
                     persist_path: None,
+                    archive_path: None,
+                    snapshot_path: None,
                 };
                 let rpc = context.config.get_active_env()?.rpc.clone();
                 let _command_result =
@@ -766,6 +775,8 @@ impl MysClientCommands {
                     end,
                     terminate_early,
                     max_tasks: 16,
+                    archive_path: None,
+                    snapshot_path: None,
                 };
                 let rpc = context.config.get_active_env()?.rpc.clone();
                 let _command_result =
@@ -38,7 +38,9 @@ use mys_keys::keypair_file::{
     read_authority_keypair_from_file, read_keypair_from_file, write_authority_keypair_to_file,
     write_keypair_to_file,
 };
-use mys_keys::keystore::{AccountKeystore, Keystore};
+use mys_keys::keystore::{
+    read_passphrase, AccountKeystore, EncryptedKeystore, Keystore, ScryptParams,
+};
 use mys_types::base_types::MysAddress;
 use mys_types::committee::EpochId;
 use mys_types::crypto::{
@@ -99,6 +101,11 @@ pub enum KeyToolCommand {
         #[clap(long, default_value = "0")]
         cur_epoch: u64,
     },
+    /// Encrypt the private keys in Mys CLI Keystore with a passphrase. The keystore file is
+    /// replaced in place, aliases are kept. The passphrase is read from the
+    /// MYS_KEYSTORE_PASSPHRASE environment variable, or prompted for if it is not set. The same
+    /// passphrase is needed to sign with the keys afterwards.
+    EncryptKeystore,
     /// Generate a new keypair with key scheme flag {ed25519 | secp256k1 | secp256r1}
     /// with optional derivation path, default to m/44'/784'/0'/0'/0' for ed25519 or
     /// m/54'/784'/0'/0/0 for secp256k1 or m/74'/784'/0'/0/0 for secp256r1. Word
@@ -339,6 +346,13 @@ pub struct Key {
     peer_id: Option<String>,
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptedKeystoreOutput {
+    keystore_path: Option<PathBuf>,
+    num_keys: usize,
+}
+
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ExportedKey {
@@ -458,6 +472,7 @@ pub enum CommandOutput {
     Convert(ConvertOutput),
     DecodeMultiSig(DecodedMultiSigOutput),
     DecodeOrVerifyTx(DecodeOrVerifyTxOutput),
+    EncryptKeystore(EncryptedKeystoreOutput),
     Error(String),
     Generate(Key),
     Import(Key),
@@ -584,6 +599,23 @@ impl KeyToolCommand {
                     }
                 }
             }
+            KeyToolCommand::EncryptKeystore => {
+                let Keystore::File(file_keystore) = keystore else {
+                    return Err(anyhow!("Only a plaintext file keystore can be encrypted"));
+                };
+                let passphrase = read_passphrase(true)?;
+                let encrypted = EncryptedKeystore::from_file_keystore(
+                    file_keystore,
+                    &passphrase,
+                    ScryptParams::default(),
+                )?;
+                let output = EncryptedKeystoreOutput {
+                    keystore_path: encrypted.path().map(Path::to_path_buf),
+                    num_keys: encrypted.keys().len(),
+                };
+                *keystore = Keystore::Encrypted(encrypted);
+                CommandOutput::EncryptKeystore(output)
+            }
             KeyToolCommand::Generate {
                 key_scheme,
                 derivation_path,
@@ -651,8 +683,8 @@ impl KeyToolCommand {
                             derivation_path,
                             alias.clone(),
                         )?;
-                        let skp = keystore.get_key(&mys_address)?;
-                        let mut key = Key::from(skp);
+                        let skp = keystore.export_key(&mys_address)?;
+                        let mut key = Key::from(&skp);
 
                         let alias = match alias {
                             Some(x) => x,
@@ -666,12 +698,12 @@ impl KeyToolCommand {
             }
             KeyToolCommand::Export { key_identity } => {
                 let address = get_identity_address_from_keystore(key_identity, keystore)?;
-                let skp = keystore.get_key(&address)?;
+                let skp = keystore.export_key(&address)?;
                 let key = ExportedKey {
                     exported_private_key: skp
                         .encode()
                         .map_err(|_| anyhow!("Cannot decode keypair"))?,
-                    key: Key::from(skp),
+                    key: Key::from(&skp),
                 };
                 CommandOutput::Export(key)
             }
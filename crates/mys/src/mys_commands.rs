@@ -433,7 +433,7 @@ impl MysCommand {
             } => {
                 let keystore_path =
                     keystore_path.unwrap_or(mys_config_dir()?.join(MYS_KEYSTORE_FILENAME));
-                let mut keystore = Keystore::from(FileBasedKeystore::new(&keystore_path)?);
+                let mut keystore = Keystore::from_path(&keystore_path)?;
                 cmd.execute(&mut keystore).await?.print(!json);
                 Ok(())
             }
@@ -991,7 +991,7 @@ async fn genesis(
     if write_config.is_none() && !files.is_empty() {
         if force {
             // check old keystore and client.yaml is compatible
-            let is_compatible = FileBasedKeystore::new(&keystore_path).is_ok()
+            let is_compatible = Keystore::from_path(&keystore_path).is_ok()
                 && PersistedConfig::<MysClientConfig>::read(&client_path).is_ok();
             // Keep keystore and client.yaml if they are compatible
             if is_compatible {
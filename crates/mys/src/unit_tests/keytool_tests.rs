@@ -20,7 +20,9 @@ use rand::rngs::StdRng;
 use rand::SeedableRng;
 use shared_crypto::intent::Intent;
 use shared_crypto::intent::IntentScope;
-use mys_keys::keystore::{AccountKeystore, FileBasedKeystore, InMemKeystore, Keystore};
+use mys_keys::keystore::{
+    AccountKeystore, FileBasedKeystore, InMemKeystore, Keystore, MYS_KEYSTORE_PASSPHRASE_ENV,
+};
 use mys_types::base_types::ObjectDigest;
 use mys_types::base_types::ObjectID;
 use mys_types::base_types::SequenceNumber;
@@ -565,3 +567,66 @@ async fn test_sign_command() -> Result<(), anyhow::Error> {
     .await?;
     Ok(())
 }
+
+/// Serializes the tests reading the keystore passphrase from the process-wide environment.
+static PASSPHRASE_ENV_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// Sets `MYS_KEYSTORE_PASSPHRASE` until dropped, then restores its previous value.
+struct PassphraseEnv {
+    previous: Option<std::ffi::OsString>,
+    _lock: tokio::sync::MutexGuard<'static, ()>,
+}
+
+impl PassphraseEnv {
+    async fn set(passphrase: &str) -> Self {
+        let lock = PASSPHRASE_ENV_LOCK.lock().await;
+        let previous = std::env::var_os(MYS_KEYSTORE_PASSPHRASE_ENV);
+        std::env::set_var(MYS_KEYSTORE_PASSPHRASE_ENV, passphrase);
+        Self {
+            previous,
+            _lock: lock,
+        }
+    }
+}
+
+impl Drop for PassphraseEnv {
+    fn drop(&mut self) {
+        match self.previous.take() {
+            Some(previous) => std::env::set_var(MYS_KEYSTORE_PASSPHRASE_ENV, previous),
+            None => std::env::remove_var(MYS_KEYSTORE_PASSPHRASE_ENV),
+        }
+    }
+}
+
+#[test]
+async fn test_encrypt_keystore_command() -> Result<(), anyhow::Error> {
+    let temp_dir = TempDir::new().unwrap();
+    let keystore_path = temp_dir.path().join("mys.keystore");
+    let mut keystore = Keystore::from(FileBasedKeystore::new(&keystore_path)?);
+    keystore.add_key(
+        Some("main".to_string()),
+        MysKeyPair::Ed25519(get_key_pair().1),
+    )?;
+    let address = keystore.addresses()[0];
+    let private_key = keystore.get_key(&address)?.encode_base64();
+
+    let _passphrase = PassphraseEnv::set("passphrase").await;
+    let output = KeyToolCommand::EncryptKeystore
+        .execute(&mut keystore)
+        .await?;
+    assert!(matches!(output, CommandOutput::EncryptKeystore(_)));
+    assert!(matches!(keystore, Keystore::Encrypted(_)));
+    assert!(!std::fs::read_to_string(&keystore_path)?.contains(&private_key));
+
+    // Encrypting twice is rejected
+    assert!(KeyToolCommand::EncryptKeystore
+        .execute(&mut keystore)
+        .await
+        .is_err());
+
+    // The migrated keystore is picked up from the same path and unlocked from the environment
+    let keystore = Keystore::from_path(&keystore_path)?;
+    assert_eq!(keystore.get_alias_by_address(&address)?, "main");
+    keystore.sign_secure(&address, &"hello", Intent::mys_transaction())?;
+    Ok(())
+}
@@ -51,6 +51,7 @@ async fn test_profiler() {
         protocol_version: None,
         profile_output: Some(profile_output),
         config_objects: None,
+        gas_report: None,
     };
 
     let command_result =
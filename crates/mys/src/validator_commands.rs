@@ -285,8 +285,8 @@ impl MysValidatorCommand {
             } => {
                 let dir = std::env::current_dir()?;
                 let protocol_key_file_name = dir.join("protocol.key");
-                let account_key = match context.config.keystore.get_key(&mys_address)? {
-                    MysKeyPair::Ed25519(account_key) => MysKeyPair::Ed25519(account_key.copy()),
+                let account_key = match context.config.keystore.export_key(&mys_address)? {
+                    MysKeyPair::Ed25519(account_key) => MysKeyPair::Ed25519(account_key),
                     _ => panic!(
                         "Other account key types supported yet, please use Ed25519 keys for now."
                     ),